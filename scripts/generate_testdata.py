@@ -18,6 +18,7 @@ Usage:
     ./scripts/generate_testdata.py                      # Generate all testdata
     ./scripts/generate_testdata.py --only logs          # Only logs
     ./scripts/generate_testdata.py --only metrics-gauge # Specific metric type
+    ./scripts/generate_testdata.py --only metrics-edge-cases  # Edge-case fixtures
     ./scripts/generate_testdata.py --verbose            # Verbose output
     ./scripts/generate_testdata.py --size-mb 50         # Generate ~50MB files (uses _large suffix)
     ./scripts/generate_testdata.py --size-mb 100 --only logs  # 100MB logs only
@@ -1085,6 +1086,16 @@ def generate_all_metrics(config: GenerationConfig):
     export_to_protobuf(mixed_data, TESTDATA_DIR / "metrics_mixed.pb")
     print("  ✓ metrics_mixed.{json,jsonl,pb}")
 
+    generate_metric_edge_cases(config)
+
+
+def generate_metric_edge_cases(config: GenerationConfig):
+    """Generate the metric edge-case fixtures (see metric_edge_cases.py)."""
+    import metric_edge_cases
+
+    for path in metric_edge_cases.write_fixtures():
+        print(f"  ✓ metrics_edge_cases/{path.name}")
+
 
 def generate_all_traces(config: GenerationConfig):
     """Generate all trace files."""
@@ -1120,7 +1131,8 @@ def main():
         choices=[
             "logs", "traces", "metrics",
             "metrics-gauge", "metrics-sum", "metrics-histogram",
-            "metrics-exponential-histogram", "metrics-summary", "metrics-mixed"
+            "metrics-exponential-histogram", "metrics-summary", "metrics-mixed",
+            "metrics-edge-cases"
         ],
         help="Generate only specific signal type or metric type"
     )
@@ -1162,6 +1174,9 @@ def main():
             generate_all_traces(config)
         elif config.only == "metrics":
             generate_all_metrics(config)
+        elif config.only == "metrics-edge-cases":
+            print("Generating metric edge cases...")
+            generate_metric_edge_cases(config)
         elif config.only.startswith("metrics-"):
             # Specific metric type
             metric_type = config.only.replace("metrics-", "")
@@ -0,0 +1,333 @@
+#!/usr/bin/env -S uv run --quiet --script
+# /// script
+# dependencies = [
+#   "opentelemetry-proto>=1.20.0",
+#   "protobuf>=4.0.0",
+# ]
+# requires-python = ">=3.11"
+# ///
+
+"""
+Metric Edge-Case Fixtures
+
+Builds OTLP metrics payloads for the cases the converter has to get right
+beyond the happy path: empty buckets, NaN/Infinity values, negative
+exponential scales, exemplar-heavy points and payloads mixing several
+resources and services. Each fixture is a function returning MetricsData
+(deterministic, no RNG), so other generators can import them; run as a
+script to write them to testdata/metrics_edge_cases/ as protobuf (OTLP/JSON
+cannot carry NaN or Infinity).
+
+The expected conversion of every fixture is kept by hand in
+testdata/metrics_edge_cases/expected.json and checked by
+tests/metric_edge_cases.rs.
+
+Usage:
+    ./scripts/metric_edge_cases.py                        # Write all fixtures
+    ./scripts/metric_edge_cases.py --only negative_scales # One fixture
+    ./scripts/metric_edge_cases.py --list                 # List fixture names
+"""
+
+import argparse
+import math
+import sys
+from pathlib import Path
+from typing import Callable, Dict
+
+from opentelemetry.proto.common.v1.common_pb2 import AnyValue, InstrumentationScope, KeyValue
+from opentelemetry.proto.metrics.v1.metrics_pb2 import (
+    AggregationTemporality,
+    Exemplar,
+    ExponentialHistogram,
+    ExponentialHistogramDataPoint,
+    Gauge,
+    Histogram,
+    HistogramDataPoint,
+    Metric,
+    MetricsData,
+    NumberDataPoint,
+    ResourceMetrics,
+    ScopeMetrics,
+    Sum,
+    Summary,
+    SummaryDataPoint,
+)
+from opentelemetry.proto.resource.v1.resource_pb2 import Resource
+
+OUTPUT_DIR = Path(__file__).parent.parent / "testdata" / "metrics_edge_cases"
+BASE_TIME_NS = 1736938800000000000  # 2025-01-15T11:00:00Z
+
+CUMULATIVE = AggregationTemporality.AGGREGATION_TEMPORALITY_CUMULATIVE
+DELTA = AggregationTemporality.AGGREGATION_TEMPORALITY_DELTA
+SCOPE = InstrumentationScope(name="edge-case-fixtures", version="1.0.0")
+
+
+def attribute(key: str, value: str) -> KeyValue:
+    """A string attribute."""
+    return KeyValue(key=key, value=AnyValue(string_value=value))
+
+
+def resource_metrics(service: str | None, *metrics: Metric) -> ResourceMetrics:
+    """One resource (without `service.name` for None) with one scope."""
+    attrs = [attribute("host.name", "fixture-host")]
+    if service is not None:
+        attrs.insert(0, attribute("service.name", service))
+    return ResourceMetrics(
+        resource=Resource(attributes=attrs),
+        scope_metrics=[ScopeMetrics(scope=SCOPE, metrics=list(metrics))],
+    )
+
+
+def at(offset_secs: int) -> int:
+    return BASE_TIME_NS + offset_secs * 1_000_000_000
+
+
+def empty_buckets() -> MetricsData:
+    """Histograms with all-zero buckets, no buckets at all, and an empty
+    exponential histogram."""
+    histogram = Metric(
+        name="http.server.duration",
+        unit="ms",
+        histogram=Histogram(
+            aggregation_temporality=CUMULATIVE,
+            data_points=[
+                HistogramDataPoint(
+                    time_unix_nano=at(0),
+                    count=0,
+                    sum=0.0,
+                    bucket_counts=[0, 0, 0, 0],
+                    explicit_bounds=[10.0, 50.0, 100.0],
+                ),
+                HistogramDataPoint(time_unix_nano=at(1), count=0),
+            ],
+        ),
+    )
+    exp_histogram = Metric(
+        name="rpc.latency",
+        unit="ms",
+        exponential_histogram=ExponentialHistogram(
+            aggregation_temporality=DELTA,
+            data_points=[
+                ExponentialHistogramDataPoint(
+                    time_unix_nano=at(0),
+                    count=0,
+                    scale=0,
+                    zero_count=0,
+                    positive=ExponentialHistogramDataPoint.Buckets(offset=0, bucket_counts=[]),
+                ),
+            ],
+        ),
+    )
+    return MetricsData(resource_metrics=[resource_metrics("checkout", histogram, exp_histogram)])
+
+
+def non_finite_values() -> MetricsData:
+    """NaN, +/-Infinity and missing values in gauges and sums, and a
+    histogram whose sum and min are not finite."""
+    gauge = Metric(
+        name="queue.depth",
+        gauge=Gauge(
+            data_points=[
+                NumberDataPoint(time_unix_nano=at(0), as_double=3.5),
+                NumberDataPoint(time_unix_nano=at(1), as_double=math.nan),
+                NumberDataPoint(time_unix_nano=at(2), as_double=math.inf),
+                NumberDataPoint(time_unix_nano=at(3), as_double=-math.inf),
+                NumberDataPoint(time_unix_nano=at(4)),
+                NumberDataPoint(time_unix_nano=at(5), as_int=7),
+            ],
+        ),
+    )
+    total = Metric(
+        name="items.processed",
+        sum=Sum(
+            aggregation_temporality=CUMULATIVE,
+            is_monotonic=True,
+            data_points=[
+                NumberDataPoint(time_unix_nano=at(0), as_double=10.0),
+                NumberDataPoint(time_unix_nano=at(1), as_double=math.nan),
+            ],
+        ),
+    )
+    histogram = Metric(
+        name="batch.size",
+        histogram=Histogram(
+            aggregation_temporality=DELTA,
+            data_points=[
+                HistogramDataPoint(
+                    time_unix_nano=at(0),
+                    count=2,
+                    sum=math.nan,
+                    min=-math.inf,
+                    max=4.0,
+                    bucket_counts=[1, 1],
+                    explicit_bounds=[1.0],
+                ),
+            ],
+        ),
+    )
+    return MetricsData(resource_metrics=[resource_metrics("inventory", gauge, total, histogram)])
+
+
+def negative_scales() -> MetricsData:
+    """Exponential histograms at negative scales down to the minimum (-10),
+    with negative, zero and positive bucket offsets."""
+    points = [
+        (-1, -3, [1, 2], [2]),
+        (-4, 0, [5], []),
+        (-10, 2, [0, 3, 1], []),
+    ]
+    data_points = []
+    for i, (scale, offset, positive, negative) in enumerate(points):
+        point = ExponentialHistogramDataPoint(
+            time_unix_nano=at(i),
+            count=sum(positive) + sum(negative),
+            sum=float(10 * (i + 1)),
+            scale=scale,
+            zero_count=0,
+            positive=ExponentialHistogramDataPoint.Buckets(offset=offset, bucket_counts=positive),
+        )
+        if negative:
+            point.negative.CopyFrom(
+                ExponentialHistogramDataPoint.Buckets(offset=-1, bucket_counts=negative)
+            )
+        data_points.append(point)
+    metric = Metric(
+        name="payment.amount",
+        unit="USD",
+        exponential_histogram=ExponentialHistogram(
+            aggregation_temporality=DELTA,
+            data_points=data_points,
+        ),
+    )
+    return MetricsData(resource_metrics=[resource_metrics("payments", metric)])
+
+
+def exemplar_heavy() -> MetricsData:
+    """A gauge point with 64 exemplars and a histogram point with 32."""
+
+    def exemplars(count: int) -> list:
+        return [
+            Exemplar(
+                filtered_attributes=[attribute("user.tier", "pro" if i % 2 else "free")],
+                time_unix_nano=at(0) - i * 1_000_000,
+                as_double=i * 1.5,
+                span_id=(i + 1).to_bytes(8, "big"),
+                trace_id=(0xE0 << 120 | i + 1).to_bytes(16, "big"),
+            )
+            for i in range(count)
+        ]
+
+    gauge = Metric(
+        name="recommendations.served",
+        gauge=Gauge(
+            data_points=[
+                NumberDataPoint(time_unix_nano=at(0), as_int=42, exemplars=exemplars(64)),
+            ],
+        ),
+    )
+    histogram = Metric(
+        name="recommendation.latency",
+        unit="ms",
+        histogram=Histogram(
+            aggregation_temporality=DELTA,
+            data_points=[
+                HistogramDataPoint(
+                    time_unix_nano=at(0),
+                    count=32,
+                    sum=4800.0,
+                    bucket_counts=[8, 16, 8],
+                    explicit_bounds=[100.0, 200.0],
+                    exemplars=exemplars(32),
+                ),
+            ],
+        ),
+    )
+    return MetricsData(resource_metrics=[resource_metrics("recommendation", gauge, histogram)])
+
+
+def mixed_services() -> MetricsData:
+    """Several resources in one payload: a service split across two
+    resources, a resource without `service.name` and a summary to skip."""
+
+    def gauge(name: str, values: list) -> Metric:
+        return Metric(
+            name=name,
+            gauge=Gauge(
+                data_points=[
+                    NumberDataPoint(time_unix_nano=at(i), as_double=v)
+                    for i, v in enumerate(values)
+                ],
+            ),
+        )
+
+    requests = Metric(
+        name="requests",
+        sum=Sum(
+            aggregation_temporality=DELTA,
+            is_monotonic=True,
+            data_points=[NumberDataPoint(time_unix_nano=at(0), as_int=12)],
+        ),
+    )
+    summary = Metric(
+        name="legacy.latency",
+        summary=Summary(
+            data_points=[
+                SummaryDataPoint(time_unix_nano=at(0), count=3, sum=9.0),
+                SummaryDataPoint(time_unix_nano=at(1), count=1, sum=2.0),
+            ],
+        ),
+    )
+    return MetricsData(
+        resource_metrics=[
+            resource_metrics("cart", gauge("cart.items", [1.0, 2.0])),
+            resource_metrics("payment-service", gauge("queue.depth", [4.0]), requests, summary),
+            resource_metrics("cart", gauge("cart.value", [19.99])),
+            resource_metrics(None, gauge("orphan.value", [1.0])),
+        ]
+    )
+
+
+FIXTURES: Dict[str, Callable[[], MetricsData]] = {
+    "empty_buckets": empty_buckets,
+    "non_finite_values": non_finite_values,
+    "negative_scales": negative_scales,
+    "exemplar_heavy": exemplar_heavy,
+    "mixed_services": mixed_services,
+}
+
+
+def build(name: str) -> MetricsData:
+    """The fixture `name`."""
+    return FIXTURES[name]()
+
+
+def write_fixtures(output_dir: Path = OUTPUT_DIR, only: str | None = None) -> list:
+    """Write `<name>.pb` for every fixture (or just `only`); returns the paths."""
+    output_dir.mkdir(parents=True, exist_ok=True)
+    written = []
+    for name in [only] if only else FIXTURES:
+        path = output_dir / f"{name}.pb"
+        path.write_bytes(build(name).SerializeToString())
+        written.append(path)
+    return written
+
+
+def main() -> int:
+    parser = argparse.ArgumentParser(description="Generate metric edge-case fixtures")
+    parser.add_argument("--only", choices=list(FIXTURES), help="Generate a single fixture")
+    parser.add_argument("--output-dir", type=Path, default=OUTPUT_DIR, help="Output directory")
+    parser.add_argument("--list", action="store_true", help="List fixture names and exit")
+    args = parser.parse_args()
+
+    if args.list:
+        for name, func in FIXTURES.items():
+            print(f"{name}: {func.__doc__.splitlines()[0]}")
+        return 0
+
+    for path in write_fixtures(args.output_dir, args.only):
+        print(f"  ✓ {path.name}")
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,47 @@
+"""
+Spark reader for the reader compatibility test (tests/reader_compat.rs)
+
+Usage: spark-submit spark_read.py ROOT TABLE...
+
+Reads every Parquet file under ROOT/TABLE and prints one line with the
+marker the test looks for, followed by JSON mapping each table to its
+columns, row count and non-null count per column. Partition discovery is
+off so the year=/month= directories do not turn into columns.
+"""
+
+import json
+import sys
+
+from pyspark.sql import SparkSession
+from pyspark.sql import functions as F
+
+
+def main() -> None:
+    root, tables = sys.argv[1], sys.argv[2:]
+    spark = SparkSession.builder.appName("otlp2parquet-reader-compat").getOrCreate()
+    spark.sparkContext.setLogLevel("ERROR")
+
+    views = {}
+    for table in tables:
+        df = (
+            spark.read.option("recursiveFileLookup", "true")
+            .option("pathGlobFilter", "*.parquet")
+            .parquet(f"{root}/{table}")
+        )
+        counts = df.agg(
+            F.count(F.lit(1)).alias("__rows"),
+            *[F.count(F.col(f"`{c}`")).alias(c) for c in df.columns],
+        ).first()
+        counts = counts.asDict()
+        views[table] = {
+            "columns": df.columns,
+            "rows": counts.pop("__rows"),
+            "non_null": counts,
+        }
+
+    print("READER_COMPAT " + json.dumps(views), flush=True)
+    spark.stop()
+
+
+if __name__ == "__main__":
+    main()
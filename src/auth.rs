@@ -0,0 +1,42 @@
+// Bearer token checks
+//
+// The admin and cluster endpoints accept `Authorization: Bearer <token>`.
+// Tokens are compared in constant time so response timing does not reveal how
+// much of a guess matched.
+
+use axum::http::{header, HeaderMap};
+
+/// Whether `headers` carry the bearer `token`; always true without a token.
+pub(crate) fn authorized(headers: &HeaderMap, token: Option<&str>) -> bool {
+    let Some(expected) = token else {
+        return true;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|v| constant_time_eq(v.trim().as_bytes(), expected.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bearer_token_must_match() {
+        let mut headers = HeaderMap::new();
+        assert!(authorized(&headers, None));
+        assert!(!authorized(&headers, Some("secret")));
+
+        headers.insert(header::AUTHORIZATION, "Bearer secreT".parse().unwrap());
+        assert!(!authorized(&headers, Some("secret")));
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        assert!(authorized(&headers, Some("secret")));
+        headers.insert(header::AUTHORIZATION, "Basic secret".parse().unwrap());
+        assert!(!authorized(&headers, Some("secret")));
+    }
+}
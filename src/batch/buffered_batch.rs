@@ -19,6 +19,8 @@ pub(crate) struct BufferedBatch<M: BatchMetadata> {
     first_timestamp: i64,
     service_name: Arc<str>,
     created_at: Instant,
+    /// Ids of the requests that contributed rows, in arrival order
+    request_ids: Vec<String>,
     _marker: PhantomData<M>,
 }
 
@@ -35,11 +37,25 @@ impl<M: BatchMetadata> BufferedBatch<M> {
             },
             service_name: Arc::clone(metadata.service_name()),
             created_at: Instant::now(),
+            request_ids: Vec::new(),
             _marker: PhantomData,
         }
     }
 
-    pub fn add_batches(&mut self, batches: Vec<RecordBatch>, metadata: &M, approx_bytes: usize) {
+    pub fn add_batches(
+        &mut self,
+        batches: Vec<RecordBatch>,
+        metadata: &M,
+        approx_bytes: usize,
+        request_id: Option<&str>,
+    ) {
+        if let Some(id) = request_id {
+            if self.request_ids.len() < crate::request_id::MAX_PER_FILE
+                && !self.request_ids.iter().any(|seen| seen == id)
+            {
+                self.request_ids.push(id.to_string());
+            }
+        }
         if metadata.first_timestamp_micros() > 0 {
             self.first_timestamp = self.first_timestamp.min(metadata.first_timestamp_micros());
         }
@@ -48,10 +64,22 @@ impl<M: BatchMetadata> BufferedBatch<M> {
         self.batches.extend(batches);
     }
 
+    pub fn batches(&self) -> &[RecordBatch] {
+        &self.batches
+    }
+
+    pub fn total_rows(&self) -> usize {
+        self.total_rows
+    }
+
     pub fn total_bytes(&self) -> usize {
         self.total_bytes
     }
 
+    pub fn created_at(&self) -> Instant {
+        self.created_at
+    }
+
     pub fn should_flush(&self, cfg: &BatchConfig) -> bool {
         self.total_rows >= cfg.max_rows
             || self.total_bytes >= cfg.max_bytes
@@ -74,8 +102,10 @@ impl<M: BatchMetadata> BufferedBatch<M> {
         );
 
         Ok(CompletedBatch {
-            batches: self.batches,
+            batches: super::schema::merge_batches(self.batches),
             metadata,
+            buffered_since: self.created_at,
+            request_ids: self.request_ids,
         })
     }
 }
@@ -7,19 +7,23 @@
 //! in the server config. Currently the handlers write directly per-request, but this
 //! infrastructure is available for future use.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::marker::PhantomData;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Result};
 use arrow::array::RecordBatch;
 use otlp2records::PartitionedBatch;
 use parking_lot::Mutex;
+use serde::Serialize;
 
 mod buffered_batch;
+mod schema;
+mod table;
 
 use buffered_batch::BufferedBatch;
+pub(crate) use table::TableBatcher;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct BatchKey {
@@ -137,6 +141,33 @@ impl SignalProcessor for LogSignalProcessor {
 pub struct CompletedBatch<M: BatchMetadata = LogMetadata> {
     pub batches: Vec<RecordBatch>,
     pub metadata: M,
+    /// When the first request of this batch was buffered (flush lag is measured from here)
+    pub buffered_since: Instant,
+    /// Ids of the requests whose rows are in this batch
+    pub request_ids: Vec<String>,
+}
+
+/// What a batcher holds in memory right now
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BufferStats {
+    pub batches: usize,
+    pub rows: usize,
+    /// Approximate (request bytes, as counted against `batch.max_bytes`)
+    pub bytes: usize,
+    /// Age of the oldest buffered batch; 0 when empty
+    pub oldest_age_ms: u64,
+}
+
+impl BufferStats {
+    /// Sum of two batchers' stats, keeping the older age.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            batches: self.batches + other.batches,
+            rows: self.rows + other.rows,
+            bytes: self.bytes + other.bytes,
+            oldest_age_ms: self.oldest_age_ms.max(other.oldest_age_ms),
+        }
+    }
 }
 
 /// Thread-safe batch orchestrator shared across handlers.
@@ -168,6 +199,7 @@ impl<P: SignalProcessor> BatchManager<P> {
         &self,
         request: &P::Request,
         approx_bytes: usize,
+        request_id: Option<&str>,
     ) -> BatchIngestResult<P::Metadata> {
         let capacity_hint = P::estimate_row_count(request);
         let (batches, metadata) = P::convert_request(request, capacity_hint)?;
@@ -199,7 +231,7 @@ impl<P: SignalProcessor> BatchManager<P> {
                 .batches
                 .entry(key.clone())
                 .or_insert_with(|| BufferedBatch::new(&metadata));
-            buffered.add_batches(batches, &metadata, approx_bytes);
+            buffered.add_batches(batches, &metadata, approx_bytes, request_id);
             buffered.should_flush(&self.config)
         };
 
@@ -220,17 +252,21 @@ impl<P: SignalProcessor> BatchManager<P> {
         Ok((completed, metadata))
     }
 
+    /// Remove and finalize every batch past its row, byte or age threshold.
+    ///
+    /// Batches come back in [`fair_order`], so a backlog in one service cannot
+    /// push other services' flushes to the end of the pass.
     pub fn drain_expired(&self) -> Result<Vec<CompletedBatch<P::Metadata>>> {
         let mut guard = self.inner.lock();
-        let mut completed = Vec::new();
-        let keys: Vec<BatchKey> = guard
+        let expired: Vec<(BatchKey, Instant)> = guard
             .batches
             .iter()
             .filter(|(_, batch)| batch.should_flush(&self.config))
-            .map(|(key, _)| key.clone())
+            .map(|(key, batch)| (key.clone(), batch.created_at()))
             .collect();
 
-        for key in keys {
+        let mut completed = Vec::new();
+        for key in fair_order(expired) {
             if let Some(batch) = guard.batches.remove(&key) {
                 guard.total_bytes = guard.total_bytes.saturating_sub(batch.total_bytes());
                 completed.push(batch.finalize()?);
@@ -240,19 +276,85 @@ impl<P: SignalProcessor> BatchManager<P> {
         Ok(completed)
     }
 
+    /// Copies of every buffered batch, for querying rows not yet written.
+    pub fn pending(&self) -> Vec<RecordBatch> {
+        let guard = self.inner.lock();
+        guard
+            .batches
+            .values()
+            .flat_map(|buffered| buffered.batches().iter().cloned())
+            .collect()
+    }
+
+    /// Current depth and age of the buffer.
+    pub fn stats(&self) -> BufferStats {
+        let guard = self.inner.lock();
+        BufferStats {
+            batches: guard.batches.len(),
+            rows: guard.batches.values().map(BufferedBatch::total_rows).sum(),
+            bytes: guard.total_bytes,
+            oldest_age_ms: guard
+                .batches
+                .values()
+                .map(|batch| batch.created_at().elapsed().as_millis() as u64)
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
     pub fn drain_all(&self) -> Result<Vec<CompletedBatch<P::Metadata>>> {
         let mut guard = self.inner.lock();
-        let drained: Vec<_> = guard.batches.drain().collect();
+        let mut drained: HashMap<_, _> = guard.batches.drain().collect();
         guard.total_bytes = 0;
         drop(guard);
 
-        drained
+        let keys = drained
+            .iter()
+            .map(|(key, batch)| (key.clone(), batch.created_at()))
+            .collect();
+        fair_order(keys)
             .into_iter()
-            .map(|(_, batch)| batch.finalize())
+            .filter_map(|key| drained.remove(&key))
+            .map(|batch| batch.finalize())
             .collect()
     }
 }
 
+/// Order batches for flushing: round-robin across services, oldest first.
+///
+/// Services take turns in order of their oldest pending batch and each turn
+/// flushes that service's oldest remaining batch, so a service with many
+/// pending minute buckets gets one slot per round rather than all of them
+/// up front.
+fn fair_order(mut keys: Vec<(BatchKey, Instant)>) -> Vec<BatchKey> {
+    keys.sort_by(|(a_key, a_created), (b_key, b_created)| {
+        a_created
+            .cmp(b_created)
+            .then_with(|| a_key.minute_bucket.cmp(&b_key.minute_bucket))
+    });
+
+    // Per-service queues, in order of each service's oldest batch
+    let mut queues: Vec<VecDeque<BatchKey>> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (key, _) in keys {
+        let slot = *index.entry(key.service.clone()).or_insert_with(|| {
+            queues.push(VecDeque::new());
+            queues.len() - 1
+        });
+        queues[slot].push_back(key);
+    }
+
+    let mut ordered = Vec::new();
+    while queues.iter().any(|queue| !queue.is_empty()) {
+        for queue in &mut queues {
+            if let Some(key) = queue.pop_front() {
+                ordered.push(key);
+            }
+        }
+    }
+    ordered
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -260,7 +362,7 @@ mod tests {
     use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
     use std::sync::Arc as StdArc;
 
-    fn create_test_batch(service_name: &str, record_count: usize) -> PartitionedBatch {
+    pub(super) fn create_test_batch(service_name: &str, record_count: usize) -> PartitionedBatch {
         let schema = StdArc::new(Schema::new(vec![
             Field::new(
                 "timestamp",
@@ -307,14 +409,16 @@ mod tests {
         // First request - should not flush
         let request1 = create_test_batch("test-service", 10);
         let approx1 = 320; // Approximate bytes
-        let (completed1, _meta1) = manager.ingest(&request1, approx1).unwrap();
+        let (completed1, _meta1) = manager.ingest(&request1, approx1, Some("req-1")).unwrap();
         assert_eq!(completed1.len(), 0); // Not flushed yet
 
         // Second request - should not flush (total 20 rows)
         let request2 = create_test_batch("test-service", 10);
         let approx2 = 320;
-        let (completed2, _meta2) = manager.ingest(&request2, approx2).unwrap();
+        let (completed2, _meta2) = manager.ingest(&request2, approx2, Some("req-2")).unwrap();
         assert_eq!(completed2.len(), 0); // Still not flushed
+        let stats = manager.stats();
+        assert_eq!((stats.batches, stats.rows, stats.bytes), (1, 20, 640));
 
         // Third test with smaller limit - should flush when hitting threshold
         let config_small = BatchConfig {
@@ -326,16 +430,89 @@ mod tests {
 
         let req1 = create_test_batch("test-service", 10);
         let approx_small_1 = 320;
-        let (c1, _) = manager_small.ingest(&req1, approx_small_1).unwrap();
+        let (c1, _) = manager_small
+            .ingest(&req1, approx_small_1, Some("req-1"))
+            .unwrap();
         assert_eq!(c1.len(), 0); // 10 rows < 20, no flush
 
         let req2 = create_test_batch("test-service", 10);
         let approx_small_2 = 320;
-        let (c2, _) = manager_small.ingest(&req2, approx_small_2).unwrap();
+        let (c2, _) = manager_small
+            .ingest(&req2, approx_small_2, Some("req-2"))
+            .unwrap();
         assert_eq!(c2.len(), 1); // 10 + 10 = 20 rows, should flush!
         assert_eq!(
             c2[0].batches.iter().map(|b| b.num_rows()).sum::<usize>(),
             20
         );
+        assert_eq!(c2[0].request_ids, vec!["req-1", "req-2"]);
+    }
+
+    #[test]
+    fn test_pending_copies_buffered_rows() {
+        let config = BatchConfig {
+            max_rows: 100,
+            max_bytes: 1024 * 1024,
+            max_age: Duration::from_secs(10),
+        };
+        let manager = BatchManager::<LogSignalProcessor>::new(config);
+        let pending_rows = |manager: &BatchManager<LogSignalProcessor>| {
+            manager
+                .pending()
+                .iter()
+                .map(|b| b.num_rows())
+                .sum::<usize>()
+        };
+        assert_eq!(pending_rows(&manager), 0);
+
+        manager
+            .ingest(&create_test_batch("cart", 10), 320, None)
+            .unwrap();
+        manager
+            .ingest(&create_test_batch("checkout", 5), 160, None)
+            .unwrap();
+        assert_eq!(pending_rows(&manager), 15);
+        // Copies: the rows stay buffered
+        assert_eq!(pending_rows(&manager), 15);
+
+        manager.drain_all().unwrap();
+        assert_eq!(pending_rows(&manager), 0);
+    }
+
+    #[test]
+    fn test_drain_interleaves_services_oldest_first() {
+        let config = BatchConfig {
+            max_rows: 1_000,
+            max_bytes: 1024 * 1024,
+            max_age: Duration::from_millis(50),
+        };
+        let manager = BatchManager::<LogSignalProcessor>::new(config);
+
+        // A noisy service with several minute buckets, then two quiet ones
+        for minute in 0..3 {
+            let mut request = create_test_batch("noisy", 5);
+            request.min_timestamp_micros += minute * 60_000_000;
+            manager.ingest(&request, 160, None).unwrap();
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        manager
+            .ingest(&create_test_batch("quiet-a", 1), 32, None)
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        manager
+            .ingest(&create_test_batch("quiet-b", 1), 32, None)
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(60));
+
+        let order: Vec<String> = manager
+            .drain_expired()
+            .unwrap()
+            .iter()
+            .map(|c| c.metadata.service_name.to_string())
+            .collect();
+        assert_eq!(
+            order,
+            ["noisy", "quiet-a", "quiet-b", "noisy", "noisy"].map(String::from)
+        );
     }
 }
@@ -0,0 +1,333 @@
+// Schema unification for merging buffered batches
+//
+// Requests for the same service are decoded independently, so their batches can
+// disagree slightly: a column missing from one payload, a column that was
+// all-null (`Null` typed) in one request and typed in another, or a numeric
+// column of a different width. Before concatenating, batches are brought to a
+// common schema (missing columns become nullable, compatible types are cast to
+// the wider one). Batches that still conflict (including UInt64 against signed
+// integers, which no integer type holds both of) are kept apart and written as
+// separate files instead of failing the whole flush.
+
+use arrow::array::{new_null_array, ArrayRef, RecordBatch};
+use arrow::compute::{can_cast_types, cast_with_options, concat_batches, CastOptions};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::error::ArrowError;
+use metrics::counter;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Batches sharing one unified schema
+struct Group {
+    fields: Vec<Field>,
+    batches: Vec<RecordBatch>,
+    /// Adjustments made to reach `fields`, reported once when merging
+    adjustments: Vec<String>,
+}
+
+/// Two batches typed a column incompatibly
+struct Conflict {
+    column: String,
+    existing: DataType,
+    incoming: DataType,
+}
+
+/// Merge buffered batches into as few batches as their schemas allow
+/// (normally one).
+pub(crate) fn merge_batches(batches: Vec<RecordBatch>) -> Vec<RecordBatch> {
+    if batches.len() <= 1 {
+        return batches;
+    }
+
+    let mut groups: Vec<Group> = Vec::new();
+    'batches: for batch in batches {
+        let mut conflict = None;
+        for group in &mut groups {
+            match unify(&group.fields, batch.schema_ref()) {
+                Ok((fields, adjustments)) => {
+                    group.fields = fields;
+                    group.adjustments.extend(adjustments);
+                    group.batches.push(batch);
+                    continue 'batches;
+                }
+                Err(c) => conflict = Some(c),
+            }
+        }
+        if let Some(conflict) = conflict {
+            counter!("otlp.batch.schema_conflicts").increment(1);
+            warn!(
+                column = %conflict.column,
+                existing = %conflict.existing,
+                incoming = %conflict.incoming,
+                "Incompatible column types in buffered batches, writing them to separate files"
+            );
+        }
+        groups.push(Group {
+            fields: batch
+                .schema()
+                .fields()
+                .iter()
+                .map(|f| f.as_ref().clone())
+                .collect(),
+            batches: vec![batch],
+            adjustments: Vec::new(),
+        });
+    }
+
+    groups.into_iter().flat_map(Group::merge).collect()
+}
+
+impl Group {
+    fn merge(self) -> Vec<RecordBatch> {
+        if self.batches.len() == 1 {
+            return self.batches;
+        }
+        if !self.adjustments.is_empty() {
+            counter!("otlp.batch.schema_unified").increment(1);
+            warn!(
+                adjustments = %self.adjustments.join("; "),
+                "Unified differing schemas while merging buffered batches"
+            );
+        }
+
+        let schema = Arc::new(Schema::new_with_metadata(
+            self.fields,
+            self.batches[0].schema().metadata().clone(),
+        ));
+        let merged = self
+            .batches
+            .iter()
+            .map(|batch| conform(batch, &schema))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|conformed| concat_batches(&schema, &conformed));
+        match merged {
+            Ok(batch) => vec![batch],
+            Err(e) => {
+                // Writing the batches unmerged is always possible
+                warn!(error = %e, "Failed to merge buffered batches, writing them separately");
+                self.batches
+            }
+        }
+    }
+}
+
+/// Extend `fields` with `incoming`, returning the unified fields and a note for
+/// every change made along the way.
+fn unify(fields: &[Field], incoming: &Schema) -> Result<(Vec<Field>, Vec<String>), Conflict> {
+    let mut merged = fields.to_vec();
+    let mut adjustments = Vec::new();
+
+    for field in merged.iter_mut() {
+        if incoming.field_with_name(field.name()).is_err() && !field.is_nullable() {
+            adjustments.push(format!("{}: missing in some batches", field.name()));
+            *field = field.clone().with_nullable(true);
+        }
+    }
+
+    for incoming_field in incoming.fields() {
+        match merged
+            .iter_mut()
+            .find(|f| f.name() == incoming_field.name())
+        {
+            Some(field) => {
+                let data_type = common_type(field.data_type(), incoming_field.data_type())
+                    .ok_or_else(|| Conflict {
+                        column: field.name().clone(),
+                        existing: field.data_type().clone(),
+                        incoming: incoming_field.data_type().clone(),
+                    })?;
+                let has_null_type = field.data_type() == &DataType::Null
+                    || incoming_field.data_type() == &DataType::Null;
+                if field.data_type() != incoming_field.data_type() && !has_null_type {
+                    adjustments.push(format!(
+                        "{}: {} and {} cast to {}",
+                        field.name(),
+                        field.data_type(),
+                        incoming_field.data_type(),
+                        data_type
+                    ));
+                }
+                let nullable = field.is_nullable() || incoming_field.is_nullable() || has_null_type;
+                *field = field
+                    .clone()
+                    .with_data_type(data_type)
+                    .with_nullable(nullable);
+            }
+            None => {
+                if !incoming_field.is_nullable() {
+                    adjustments.push(format!(
+                        "{}: missing in some batches",
+                        incoming_field.name()
+                    ));
+                }
+                merged.push(incoming_field.as_ref().clone().with_nullable(true));
+            }
+        }
+    }
+
+    Ok((merged, adjustments))
+}
+
+/// Smallest type both `a` and `b` can be cast to without losing meaning
+fn common_type(a: &DataType, b: &DataType) -> Option<DataType> {
+    use DataType::*;
+
+    if a == b {
+        return Some(a.clone());
+    }
+    let widened = match (a, b) {
+        (Null, other) | (other, Null) => other.clone(),
+        (Utf8, LargeUtf8) | (LargeUtf8, Utf8) => LargeUtf8,
+        (Binary, LargeBinary) | (LargeBinary, Binary) => LargeBinary,
+        (Timestamp(unit_a, tz_a), Timestamp(unit_b, tz_b)) if tz_a == tz_b => {
+            Timestamp((*unit_a).max(*unit_b), tz_a.clone())
+        }
+        (a, b) if a.is_unsigned_integer() && b.is_unsigned_integer() => UInt64,
+        // UInt64 values above i64::MAX have no signed equivalent
+        (UInt64, _) | (_, UInt64) if a.is_integer() && b.is_integer() => return None,
+        (a, b) if a.is_integer() && b.is_integer() => Int64,
+        (a, b) if a.is_numeric() && b.is_numeric() => Float64,
+        _ => return None,
+    };
+    (can_cast_types(a, &widened) && can_cast_types(b, &widened)).then_some(widened)
+}
+
+/// Cast and null-fill `batch` to `schema`
+fn conform(batch: &RecordBatch, schema: &Arc<Schema>) -> Result<RecordBatch, ArrowError> {
+    if batch.schema_ref() == schema {
+        return Ok(batch.clone());
+    }
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| match batch.column_by_name(field.name()) {
+            Some(column) if column.data_type() == field.data_type() => Ok(Arc::clone(column)),
+            // An unsafe cast fails on values that do not fit instead of
+            // nulling them, and the batches are then written unmerged
+            Some(column) => cast_with_options(
+                column,
+                field.data_type(),
+                &CastOptions {
+                    safe: false,
+                    ..CastOptions::default()
+                },
+            ),
+            None => Ok(new_null_array(field.data_type(), batch.num_rows())),
+        })
+        .collect::<Result<Vec<ArrayRef>, _>>()?;
+    RecordBatch::try_new(Arc::clone(schema), columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, AsArray, Int32Array, Int64Array, NullArray, StringArray};
+    use arrow::datatypes::Int64Type;
+
+    fn batch(fields: Vec<Field>, columns: Vec<ArrayRef>) -> RecordBatch {
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap()
+    }
+
+    #[test]
+    fn test_unifies_null_missing_and_widened_columns() {
+        let first = batch(
+            vec![
+                Field::new("service_name", DataType::Utf8, false),
+                Field::new("count", DataType::Int32, false),
+                Field::new("extra", DataType::Null, true),
+            ],
+            vec![
+                Arc::new(StringArray::from(vec!["a"])),
+                Arc::new(Int32Array::from(vec![1])),
+                Arc::new(NullArray::new(1)),
+            ],
+        );
+        let second = batch(
+            vec![
+                Field::new("service_name", DataType::Utf8, false),
+                Field::new("count", DataType::Int64, false),
+                Field::new("extra", DataType::Utf8, true),
+                Field::new("late", DataType::Utf8, false),
+            ],
+            vec![
+                Arc::new(StringArray::from(vec!["a", "a"])),
+                Arc::new(Int64Array::from(vec![2, 3])),
+                Arc::new(StringArray::from(vec!["x", "y"])),
+                Arc::new(StringArray::from(vec!["l", "m"])),
+            ],
+        );
+
+        let merged = merge_batches(vec![first, second]);
+        assert_eq!(merged.len(), 1);
+        let merged = &merged[0];
+        assert_eq!(merged.num_rows(), 3);
+
+        let schema = merged.schema();
+        assert_eq!(
+            schema.field_with_name("count").unwrap().data_type(),
+            &DataType::Int64
+        );
+        assert_eq!(
+            schema.field_with_name("extra").unwrap().data_type(),
+            &DataType::Utf8
+        );
+        assert!(schema.field_with_name("late").unwrap().is_nullable());
+        assert!(!schema
+            .field_with_name("service_name")
+            .unwrap()
+            .is_nullable());
+
+        let counts = merged
+            .column_by_name("count")
+            .unwrap()
+            .as_primitive::<Int64Type>();
+        assert_eq!(counts.values().to_vec(), vec![1, 2, 3]);
+        assert!(merged.column_by_name("late").unwrap().is_null(0));
+    }
+
+    #[test]
+    fn test_conflicting_batches_stay_separate() {
+        let text = batch(
+            vec![Field::new("value", DataType::Utf8, true)],
+            vec![Arc::new(StringArray::from(vec!["1"]))],
+        );
+        let number = batch(
+            vec![Field::new("value", DataType::Int64, true)],
+            vec![Arc::new(Int64Array::from(vec![1]))],
+        );
+
+        let merged = merge_batches(vec![text.clone(), number, text]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].num_rows(), 2);
+        assert_eq!(merged[0].schema().field(0).data_type(), &DataType::Utf8);
+        assert_eq!(merged[1].schema().field(0).data_type(), &DataType::Int64);
+    }
+
+    #[test]
+    fn test_uint64_and_signed_columns_conflict() {
+        use arrow::array::UInt64Array;
+        use arrow::datatypes::UInt64Type;
+
+        let unsigned = batch(
+            vec![Field::new("value", DataType::UInt64, true)],
+            vec![Arc::new(UInt64Array::from(vec![u64::MAX]))],
+        );
+        let signed = batch(
+            vec![Field::new("value", DataType::Int64, true)],
+            vec![Arc::new(Int64Array::from(vec![-1]))],
+        );
+
+        let merged = merge_batches(vec![unsigned, signed]);
+        assert_eq!(merged.len(), 2);
+        let value = merged[0].column(0).as_primitive::<UInt64Type>().value(0);
+        assert_eq!(value, u64::MAX);
+        assert_eq!(
+            common_type(&DataType::UInt32, &DataType::UInt64),
+            Some(DataType::UInt64)
+        );
+        assert_eq!(
+            common_type(&DataType::UInt32, &DataType::Int8),
+            Some(DataType::Int64)
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Batching for tables written beside the signal tables (events, `_unattributed/*`).
+//!
+//! Their records are split out of a request before the signal rows are
+//! written, but only handed here once those rows are accepted: a failed
+//! request is retried by the client, which must not duplicate side-table
+//! rows. Past that point a failure can no longer be reported to the client,
+//! so it is logged and counted in `otlp.tables.dropped_rows` instead.
+
+use metrics::counter;
+use otlp2records::PartitionedBatch;
+use tracing::{debug, info, warn};
+
+use super::{BatchConfig, BatchManager, BufferStats, CompletedBatch};
+
+pub(crate) struct TableBatcher {
+    /// Table directory below the storage prefix
+    table: &'static str,
+    /// Partition files by service below the table directory
+    per_service: bool,
+    /// `None` writes every request's records straight through
+    batcher: Option<BatchManager>,
+}
+
+impl TableBatcher {
+    /// `config` is `None` when batching is disabled.
+    pub(crate) fn new(table: &'static str, per_service: bool, config: Option<BatchConfig>) -> Self {
+        Self {
+            table,
+            per_service,
+            batcher: config.map(BatchManager::new),
+        }
+    }
+
+    pub(crate) fn table(&self) -> &'static str {
+        self.table
+    }
+
+    /// Buffer the records of an accepted request, writing whatever crosses a
+    /// batch threshold (or everything, without batching).
+    pub(crate) async fn accept(&self, batches: Vec<PartitionedBatch>, request_id: Option<&str>) {
+        let request_ids: Vec<String> = request_id.map(str::to_string).into_iter().collect();
+        for pb in batches {
+            if pb.batch.num_rows() == 0 {
+                continue;
+            }
+            let Some(ref batcher) = self.batcher else {
+                self.write(
+                    &pb.service_name,
+                    pb.min_timestamp_micros,
+                    &pb.batch,
+                    &request_ids,
+                )
+                .await;
+                continue;
+            };
+            match batcher.ingest(&pb, pb.batch.get_array_memory_size(), request_id) {
+                Ok((completed, _metadata)) => {
+                    for completed in completed {
+                        self.persist(&completed).await;
+                    }
+                }
+                Err(e) => {
+                    counter!("otlp.tables.dropped_rows", "table" => self.table)
+                        .increment(pb.record_count as u64);
+                    warn!(
+                        error = %e,
+                        table = self.table,
+                        rows = pb.record_count,
+                        "Failed to buffer records"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Write batches past their row, byte or age threshold.
+    pub(crate) async fn flush_expired(&self) {
+        let Some(ref batcher) = self.batcher else {
+            return;
+        };
+        match batcher.drain_expired() {
+            Ok(expired) => {
+                for completed in expired {
+                    self.persist(&completed).await;
+                }
+            }
+            Err(e) => warn!(error = %e, table = self.table, "Failed to drain expired batches"),
+        }
+    }
+
+    /// Write everything buffered, during shutdown.
+    pub(crate) async fn flush_all(&self) {
+        let Some(ref batcher) = self.batcher else {
+            return;
+        };
+        match batcher.drain_all() {
+            Ok(pending) => {
+                if !pending.is_empty() {
+                    info!(
+                        batch_count = pending.len(),
+                        table = self.table,
+                        "Flushing buffered batches before shutdown"
+                    );
+                }
+                for completed in pending {
+                    self.persist(&completed).await;
+                }
+            }
+            Err(e) => warn!(
+                error = %e,
+                table = self.table,
+                "Failed to drain pending batches during shutdown"
+            ),
+        }
+    }
+
+    /// Buffer depth and age; `None` without batching.
+    pub(crate) fn stats(&self) -> Option<BufferStats> {
+        self.batcher.as_ref().map(BatchManager::stats)
+    }
+
+    async fn persist(&self, completed: &CompletedBatch) {
+        for batch in &completed.batches {
+            self.write(
+                &completed.metadata.service_name,
+                completed.metadata.first_timestamp_micros,
+                batch,
+                &completed.request_ids,
+            )
+            .await;
+        }
+    }
+
+    async fn write(
+        &self,
+        service_name: &str,
+        timestamp_micros: i64,
+        batch: &arrow::array::RecordBatch,
+        request_ids: &[String],
+    ) {
+        let service = self.per_service.then_some(service_name);
+        match crate::writer::write_table_batch(
+            self.table,
+            service,
+            timestamp_micros,
+            batch,
+            request_ids,
+        )
+        .await
+        {
+            Ok(path) => debug!(
+                path = %path,
+                table = self.table,
+                rows = batch.num_rows(),
+                "Wrote table batch"
+            ),
+            Err(e) => {
+                counter!("otlp.tables.dropped_rows", "table" => self.table)
+                    .increment(batch.num_rows() as u64);
+                warn!(
+                    error = %e,
+                    table = self.table,
+                    rows = batch.num_rows(),
+                    "Failed to write table batch"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::create_test_batch;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_accepted_records_are_buffered() {
+        let table = TableBatcher::new(
+            "events",
+            true,
+            Some(BatchConfig {
+                max_rows: 100,
+                max_bytes: 1024 * 1024,
+                max_age: Duration::from_secs(60),
+            }),
+        );
+        table
+            .accept(vec![create_test_batch("web", 10)], Some("req-1"))
+            .await;
+        table
+            .accept(vec![create_test_batch("web", 10)], Some("req-2"))
+            .await;
+
+        let stats = table.stats().unwrap();
+        assert_eq!(stats.batches, 1);
+        assert_eq!(stats.rows, 20);
+        assert!(TableBatcher::new("events", true, None).stats().is_none());
+    }
+}
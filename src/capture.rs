@@ -0,0 +1,280 @@
+// Failed request capture
+//
+// With `capture.enabled`, the bodies of the last `capture.max_requests`
+// ingestion requests that failed (4xx/5xx) are kept gzip-compressed, in memory
+// or as files in `capture.dir`, so "my collector gets 400s" reports can be
+// reproduced byte for byte. They are listed at `GET /admin/captures` and
+// downloaded from `GET /admin/captures/{id}`, both requiring
+// `Authorization: Bearer <capture.admin_token>`.
+
+use crate::auth::authorized;
+use crate::config::CaptureConfig;
+use crate::{AppState, SignalType};
+use anyhow::Context;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use metrics::counter;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+pub(crate) struct Captures {
+    max_requests: usize,
+    max_body_bytes: usize,
+    dir: Option<PathBuf>,
+    admin_token: String,
+    entries: Mutex<VecDeque<Capture>>,
+    next_id: AtomicU64,
+}
+
+struct Capture {
+    info: CaptureInfo,
+    body: CapturedBody,
+}
+
+/// What the listing shows about one captured request
+#[derive(Debug, Clone, Serialize)]
+struct CaptureInfo {
+    id: u64,
+    signal: &'static str,
+    status: u16,
+    error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    /// Body size as received (after transport decompression)
+    bytes: usize,
+    /// Stored (gzip) size
+    stored_bytes: usize,
+    captured_at_micros: i64,
+}
+
+enum CapturedBody {
+    Memory(Vec<u8>),
+    File(PathBuf),
+}
+
+impl Captures {
+    /// `None` when capture is disabled.
+    pub(crate) fn new(config: &CaptureConfig) -> anyhow::Result<Option<Self>> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let dir = config.dir.as_ref().map(PathBuf::from);
+        if let Some(ref dir) = dir {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create capture dir {}", dir.display()))?;
+        }
+        Ok(Some(Self {
+            max_requests: config.max_requests,
+            max_body_bytes: config.max_body_bytes,
+            dir,
+            admin_token: config.admin_token.clone().unwrap_or_default(),
+            entries: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(1),
+        }))
+    }
+
+    /// Keep the body of a failed request, evicting the oldest capture when full.
+    pub(crate) fn record(
+        &self,
+        signal: SignalType,
+        content_type: Option<&str>,
+        body: &[u8],
+        status: StatusCode,
+        error: &str,
+    ) {
+        if body.len() > self.max_body_bytes {
+            counter!("otlp.capture.skipped").increment(1);
+            return;
+        }
+        let compressed = match gzip(body) {
+            Ok(compressed) => compressed,
+            Err(e) => {
+                warn!(error = %e, "Failed to compress captured request");
+                return;
+            }
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let info = CaptureInfo {
+            id,
+            signal: signal.as_str(),
+            status: status.as_u16(),
+            error: error.to_string(),
+            content_type: content_type.map(str::to_string),
+            bytes: body.len(),
+            stored_bytes: compressed.len(),
+            captured_at_micros: crate::clock::now_micros(),
+        };
+        let body = match self.dir {
+            Some(ref dir) => {
+                let path = dir.join(format!("{}-{}.gz", id, info.signal));
+                if let Err(e) = std::fs::write(&path, &compressed) {
+                    warn!(error = %e, path = %path.display(), "Failed to write captured request");
+                    return;
+                }
+                CapturedBody::File(path)
+            }
+            None => CapturedBody::Memory(compressed),
+        };
+
+        let evicted = {
+            let mut entries = self.entries.lock();
+            entries.push_back(Capture { info, body });
+            let excess = entries.len().saturating_sub(self.max_requests);
+            entries.drain(..excess).collect::<Vec<_>>()
+        };
+        for capture in evicted {
+            if let CapturedBody::File(path) = capture.body {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+        counter!("otlp.capture.requests", "signal" => signal.as_str()).increment(1);
+    }
+
+    fn list(&self) -> Vec<CaptureInfo> {
+        self.entries
+            .lock()
+            .iter()
+            .rev()
+            .map(|c| c.info.clone())
+            .collect()
+    }
+
+    /// Info and gzip body of capture `id`
+    fn get(&self, id: u64) -> Option<(CaptureInfo, Vec<u8>)> {
+        let (info, path) = {
+            let entries = self.entries.lock();
+            let capture = entries.iter().find(|c| c.info.id == id)?;
+            match capture.body {
+                CapturedBody::Memory(ref body) => {
+                    return Some((capture.info.clone(), body.clone()))
+                }
+                CapturedBody::File(ref path) => (capture.info.clone(), path.clone()),
+            }
+        };
+        std::fs::read(path).ok().map(|body| (info, body))
+    }
+}
+
+/// GET /admin/captures - newest first
+pub(crate) async fn list_captures(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let Some(ref captures) = state.captures else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !authorized(&headers, Some(&captures.admin_token)) {
+        return unauthorized();
+    }
+    Json(json!({ "captures": captures.list() })).into_response()
+}
+
+/// GET /admin/captures/{id} - the request body, gzip-compressed
+pub(crate) async fn download_capture(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(ref captures) = state.captures else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !authorized(&headers, Some(&captures.admin_token)) {
+        return unauthorized();
+    }
+    let Some((info, body)) = captures.get(id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("No capture {}", id) })),
+        )
+            .into_response();
+    };
+    let extension = match info.content_type.as_deref() {
+        Some(ct) if ct.contains("json") => "json",
+        _ => "bin",
+    };
+    (
+        [
+            (header::CONTENT_TYPE, "application/gzip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"capture-{}-{}.{}.gz\"",
+                    info.id, info.signal, extension
+                ),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+fn unauthorized() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        Json(json!({ "error": "admin token required" })),
+    )
+        .into_response()
+}
+
+fn gzip(body: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(body.len() / 4), Compression::fast());
+    encoder.write_all(body)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_ring_buffer_keeps_newest_and_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let captures = Captures::new(&CaptureConfig {
+            enabled: true,
+            max_requests: 2,
+            max_body_bytes: 64,
+            dir: Some(dir.path().to_string_lossy().into_owned()),
+            admin_token: Some("0123456789abcdef".to_string()),
+        })
+        .unwrap()
+        .unwrap();
+
+        for body in [&b"first"[..], b"second", b"third", &[b'x'; 65]] {
+            captures.record(
+                SignalType::Logs,
+                Some("application/json"),
+                body,
+                StatusCode::BAD_REQUEST,
+                "Failed to parse OTLP logs request",
+            );
+        }
+
+        let ids: Vec<u64> = captures.list().iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![3, 2]);
+        assert!(captures.get(1).is_none());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        let (_, gz) = captures.get(3).unwrap();
+        let mut body = Vec::new();
+        GzDecoder::new(&gz[..]).read_to_end(&mut body).unwrap();
+        assert_eq!(body, b"third");
+
+        let mut headers = HeaderMap::new();
+        assert!(!authorized(&headers, Some(&captures.admin_token)));
+        headers.insert(
+            header::AUTHORIZATION,
+            "Bearer 0123456789abcdef".parse().unwrap(),
+        );
+        assert!(authorized(&headers, Some(&captures.admin_token)));
+    }
+}
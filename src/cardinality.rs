@@ -0,0 +1,287 @@
+// Metric cardinality guard
+//
+// Caps the number of distinct series each metric name may introduce per flush
+// window. A series is one combination of service, resource attributes and data
+// point attributes. Label explosions (request IDs, user IDs, timestamps as
+// attributes) otherwise blow up Parquet dictionaries and every query engine
+// that later groups by those columns.
+
+use crate::codec::{PartitionedMetrics, ServiceGroupedBatches};
+use crate::config::SeriesOverflow;
+use arrow::array::{Array, BooleanArray, StringArray};
+use arrow::record_batch::RecordBatch;
+use metrics::counter;
+use parking_lot::Mutex;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// A metric exceeded its series budget in `reject` mode.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "metric '{metric_name}' exceeds {limit} unique attribute sets in the current window; \
+     remove high-cardinality attributes or raise request.max_series_per_metric"
+)]
+pub(crate) struct CardinalityError {
+    pub metric_name: String,
+    pub limit: usize,
+}
+
+pub(crate) struct CardinalityGuard {
+    limit: usize,
+    overflow: SeriesOverflow,
+    window: Duration,
+    state: Mutex<WindowState>,
+}
+
+struct WindowState {
+    started: Instant,
+    /// Series hashes seen per metric name in the current window
+    series: HashMap<String, HashSet<u64>>,
+}
+
+impl CardinalityGuard {
+    pub(crate) fn new(limit: usize, overflow: SeriesOverflow, window: Duration) -> Self {
+        Self {
+            limit,
+            overflow,
+            window,
+            state: Mutex::new(WindowState {
+                started: Instant::now(),
+                series: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Apply the series budget to every metric type in `partitioned`.
+    ///
+    /// In `drop` mode rows for series over budget are removed and the number of
+    /// dropped rows is returned. In `reject` mode nothing is admitted if any
+    /// metric would exceed its budget, so a retried request sees the same state.
+    pub(crate) fn apply(
+        &self,
+        partitioned: &mut PartitionedMetrics,
+    ) -> Result<usize, CardinalityError> {
+        self.apply_at(partitioned, Instant::now())
+    }
+
+    fn apply_at(
+        &self,
+        partitioned: &mut PartitionedMetrics,
+        now: Instant,
+    ) -> Result<usize, CardinalityError> {
+        let mut state = self.state.lock();
+        if now.duration_since(state.started) >= self.window {
+            state.started = now;
+            state.series.clear();
+        }
+
+        let groups = [
+            &mut partitioned.gauge,
+            &mut partitioned.sum,
+            &mut partitioned.histogram,
+            &mut partitioned.exp_histogram,
+        ];
+
+        match self.overflow {
+            SeriesOverflow::Reject => {
+                let mut pending: HashMap<String, HashSet<u64>> = HashMap::new();
+                for group in &groups {
+                    for pb in &group.batches {
+                        for (name, hash) in series_keys(&pb.batch) {
+                            let known = state.series.get(&name);
+                            if known.is_some_and(|set| set.contains(&hash)) {
+                                continue;
+                            }
+                            let new = pending.entry(name.clone()).or_default();
+                            new.insert(hash);
+                            if known.map_or(0, HashSet::len) + new.len() > self.limit {
+                                counter!("otlp.metrics.cardinality_rejected").increment(1);
+                                return Err(CardinalityError {
+                                    metric_name: name,
+                                    limit: self.limit,
+                                });
+                            }
+                        }
+                    }
+                }
+                for (name, hashes) in pending {
+                    state.series.entry(name).or_default().extend(hashes);
+                }
+                Ok(0)
+            }
+            SeriesOverflow::Drop => {
+                let mut dropped = 0;
+                for group in groups {
+                    dropped += drop_over_budget(group, &mut state.series, self.limit);
+                }
+                if dropped > 0 {
+                    counter!("otlp.metrics.cardinality_dropped").increment(dropped as u64);
+                }
+                Ok(dropped)
+            }
+        }
+    }
+}
+
+fn drop_over_budget(
+    group: &mut ServiceGroupedBatches,
+    series: &mut HashMap<String, HashSet<u64>>,
+    limit: usize,
+) -> usize {
+    let mut dropped = 0;
+    for pb in &mut group.batches {
+        let mut over_budget: HashSet<String> = HashSet::new();
+        let keep: BooleanArray = series_keys(&pb.batch)
+            .map(|(name, hash)| {
+                let set = series.entry(name.clone()).or_default();
+                let admitted = set.contains(&hash) || (set.len() < limit && set.insert(hash));
+                if !admitted {
+                    over_budget.insert(name);
+                }
+                Some(admitted)
+            })
+            .collect();
+
+        if over_budget.is_empty() {
+            continue;
+        }
+
+        match arrow::compute::filter_record_batch(&pb.batch, &keep) {
+            Ok(filtered) => {
+                let removed = pb.batch.num_rows() - filtered.num_rows();
+                dropped += removed;
+                pb.record_count = pb.record_count.saturating_sub(removed);
+                group.total_records = group.total_records.saturating_sub(removed);
+                pb.batch = filtered;
+                for name in over_budget {
+                    warn!(
+                        metric_name = %name,
+                        service = %pb.service_name,
+                        limit,
+                        "Dropping data points: metric exceeds series limit for this window"
+                    );
+                }
+            }
+            // Keeping the data is the safer failure mode for a guardrail
+            Err(e) => warn!(error = %e, "Failed to filter over-budget metric rows"),
+        }
+    }
+    dropped
+}
+
+/// (metric name, series hash) for each row, or nothing if the batch does not
+/// carry a `metric_name` column.
+fn series_keys(batch: &RecordBatch) -> impl Iterator<Item = (String, u64)> + '_ {
+    let column = |name: &str| {
+        batch
+            .column_by_name(name)
+            .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+    };
+    let names = column("metric_name");
+    let service = column("service_name");
+    let resource = column("resource_attributes");
+    let attributes = column("metric_attributes");
+
+    let rows = names.map_or(0, |_| batch.num_rows());
+    (0..rows).map(move |row| {
+        let value = |array: Option<&StringArray>| {
+            array
+                .filter(|a| a.is_valid(row))
+                .map(|a| a.value(row))
+                .unwrap_or_default()
+        };
+        let mut hasher = DefaultHasher::new();
+        value(service).hash(&mut hasher);
+        value(resource).hash(&mut hasher);
+        value(attributes).hash(&mut hasher);
+        (value(names).to_string(), hasher.finish())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::PartitionedBatch;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use std::sync::Arc;
+
+    fn gauge_rows(rows: &[(&str, &str)]) -> PartitionedMetrics {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("metric_name", DataType::Utf8, false),
+            Field::new("service_name", DataType::Utf8, false),
+            Field::new("metric_attributes", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.0))),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|_| "svc"))),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| r.1))),
+            ],
+        )
+        .unwrap();
+
+        let mut partitioned = PartitionedMetrics::default();
+        partitioned.gauge.total_records = rows.len();
+        partitioned.gauge.batches.push(PartitionedBatch {
+            batch,
+            service_name: Arc::from("svc"),
+            min_timestamp_micros: 0,
+            record_count: rows.len(),
+        });
+        partitioned
+    }
+
+    #[test]
+    fn test_drop_keeps_known_series_and_trims_new_ones() {
+        let guard = CardinalityGuard::new(2, SeriesOverflow::Drop, Duration::from_secs(60));
+        let mut first = gauge_rows(&[("cpu", "{\"a\":1}"), ("cpu", "{\"a\":2}")]);
+        assert_eq!(guard.apply(&mut first).unwrap(), 0);
+
+        let mut second = gauge_rows(&[
+            ("cpu", "{\"a\":1}"),
+            ("cpu", "{\"a\":3}"),
+            ("mem", "{\"a\":3}"),
+        ]);
+        assert_eq!(guard.apply(&mut second).unwrap(), 1);
+        assert_eq!(second.gauge.total_records, 2);
+        assert_eq!(second.gauge.batches[0].record_count, 2);
+        assert_eq!(second.gauge.batches[0].batch.num_rows(), 2);
+    }
+
+    #[test]
+    fn test_reject_admits_nothing_from_failing_request() {
+        let guard = CardinalityGuard::new(2, SeriesOverflow::Reject, Duration::from_secs(60));
+        let mut over = gauge_rows(&[
+            ("cpu", "{\"a\":1}"),
+            ("cpu", "{\"a\":2}"),
+            ("cpu", "{\"a\":3}"),
+        ]);
+        let err = guard.apply(&mut over).unwrap_err();
+        assert_eq!(err.metric_name, "cpu");
+        assert!(err.to_string().contains("request.max_series_per_metric"));
+
+        // The rejected request did not consume any of the budget
+        let mut ok = gauge_rows(&[("cpu", "{\"a\":4}"), ("cpu", "{\"a\":5}")]);
+        assert!(guard.apply(&mut ok).is_ok());
+    }
+
+    #[test]
+    fn test_window_rollover_resets_budget() {
+        let window = Duration::from_secs(10);
+        let guard = CardinalityGuard::new(1, SeriesOverflow::Drop, window);
+        let start = Instant::now();
+
+        let mut first = gauge_rows(&[("cpu", "{\"a\":1}")]);
+        assert_eq!(guard.apply_at(&mut first, start).unwrap(), 0);
+
+        let mut second = gauge_rows(&[("cpu", "{\"a\":2}")]);
+        assert_eq!(guard.apply_at(&mut second, start).unwrap(), 1);
+
+        let mut third = gauge_rows(&[("cpu", "{\"a\":2}")]);
+        assert_eq!(guard.apply_at(&mut third, start + window).unwrap(), 0);
+    }
+}
@@ -0,0 +1,249 @@
+//! Cat command - prints the rows of one Parquet file
+//!
+//! Reads a file through OpenDAL with the storage credentials from the config
+//! and prints its first rows as a table or JSONL, so a written file can be
+//! checked without installing DuckDB. The location is a key on the configured
+//! storage (with or without the storage prefix), a local path, or an
+//! `s3://bucket/key` / `r2://bucket/key` URI read with the configured S3 or R2
+//! credentials.
+
+use crate::config::{R2Config, RuntimeConfig, S3Config, StorageBackend, StorageConfig};
+use anyhow::{bail, Context, Result};
+use arrow::array::RecordBatch;
+use arrow::util::pretty::pretty_format_batches;
+use clap::{Args, ValueEnum};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ProjectionMask;
+use parquet::file::reader::ChunkReader;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CatFormat {
+    Table,
+    Jsonl,
+}
+
+#[derive(Args)]
+pub struct CatArgs {
+    /// Storage key, local path, or s3://bucket/key / r2://bucket/key
+    pub location: String,
+
+    /// Rows to print
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+
+    /// Columns to print, comma separated (all by default)
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = CatFormat::Table)]
+    pub format: CatFormat,
+}
+
+/// Where a location points
+enum Source {
+    /// A file on this machine
+    Local(String),
+    /// A key on a storage backend
+    Remote(StorageConfig, String),
+}
+
+impl CatArgs {
+    pub async fn run(&self, config: &RuntimeConfig) -> Result<()> {
+        let batches = match resolve(&self.location, &config.storage)? {
+            Source::Local(path) => {
+                let file = std::fs::File::open(&path)
+                    .with_context(|| format!("Failed to open {}", path))?;
+                read_batches(file, &self.columns, self.limit)?
+            }
+            Source::Remote(storage, key) => {
+                let op = crate::writer::build_operator(&storage)?;
+                let data = op
+                    .read(&key)
+                    .await
+                    .with_context(|| format!("Failed to read {}", self.location))?;
+                read_batches(data.to_bytes(), &self.columns, self.limit)?
+            }
+        };
+
+        match self.format {
+            CatFormat::Table => println!("{}", pretty_format_batches(&batches)?),
+            CatFormat::Jsonl => {
+                let mut writer = arrow::json::LineDelimitedWriter::new(std::io::stdout().lock());
+                writer.write_batches(&batches.iter().collect::<Vec<_>>())?;
+                writer.finish()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resolve `location` against the configured storage.
+fn resolve(location: &str, storage: &StorageConfig) -> Result<Source> {
+    if let Some(path) = location.strip_prefix("file://") {
+        return Ok(Source::Local(path.to_string()));
+    }
+    if let Some(rest) = location.strip_prefix("s3://") {
+        let (bucket, key) = split_bucket(rest)?;
+        let s3 = match &storage.s3 {
+            Some(s3) => S3Config {
+                bucket,
+                prefix: None,
+                ..s3.clone()
+            },
+            None => S3Config {
+                bucket,
+                region: std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                endpoint: None,
+                prefix: None,
+                storage_class: None,
+            },
+        };
+        let storage = StorageConfig {
+            backend: StorageBackend::S3,
+            s3: Some(s3),
+            ..storage.clone()
+        };
+        return Ok(Source::Remote(storage, key));
+    }
+    if let Some(rest) = location.strip_prefix("r2://") {
+        let (bucket, key) = split_bucket(rest)?;
+        let Some(r2) = &storage.r2 else {
+            bail!("Reading {} needs the [storage.r2] credentials", location);
+        };
+        let storage = StorageConfig {
+            backend: StorageBackend::R2,
+            r2: Some(R2Config {
+                bucket,
+                prefix: None,
+                ..r2.clone()
+            }),
+            ..storage.clone()
+        };
+        return Ok(Source::Remote(storage, key));
+    }
+    if location.contains("://") {
+        bail!(
+            "Unsupported location {} (use s3://, r2:// or file://)",
+            location
+        );
+    }
+    if Path::new(location).is_file() {
+        return Ok(Source::Local(location.to_string()));
+    }
+
+    let prefix = crate::writer::storage_prefix(storage).unwrap_or_default();
+    let key = location.trim_start_matches('/');
+    let key = if key.starts_with(prefix.as_str()) {
+        key.to_string()
+    } else {
+        format!("{}{}", prefix, key)
+    };
+    Ok(Source::Remote(storage.clone(), key))
+}
+
+/// `bucket/key` into its parts
+fn split_bucket(rest: &str) -> Result<(String, String)> {
+    match rest.split_once('/') {
+        Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => {
+            Ok((bucket.to_string(), key.to_string()))
+        }
+        _ => bail!("Expected <scheme>://<bucket>/<key>, got '{}'", rest),
+    }
+}
+
+/// The first `limit` rows of a Parquet file, restricted to `columns` (in
+/// that order) when any are given.
+fn read_batches<R: ChunkReader + 'static>(
+    reader: R,
+    columns: &[String],
+    limit: usize,
+) -> Result<Vec<RecordBatch>> {
+    let builder =
+        ParquetRecordBatchReaderBuilder::try_new(reader).context("Not a readable Parquet file")?;
+    let schema = builder.schema().clone();
+    let mut indices = Vec::with_capacity(columns.len());
+    for name in columns {
+        match schema.index_of(name) {
+            Ok(index) => indices.push(index),
+            Err(_) => {
+                let available: Vec<&str> =
+                    schema.fields().iter().map(|f| f.name().as_str()).collect();
+                bail!("No column '{}' (available: {})", name, available.join(", "));
+            }
+        }
+    }
+
+    let builder = if indices.is_empty() {
+        builder
+    } else {
+        let mask = ProjectionMask::roots(builder.parquet_schema(), indices.iter().copied());
+        builder.with_projection(mask)
+    };
+    let reader = builder
+        .with_limit(limit)
+        .with_batch_size(limit.clamp(1, 8192))
+        .build()?;
+
+    // The projection keeps file order; put the columns in the requested order
+    let mut sorted = indices.clone();
+    sorted.sort_unstable();
+    let order: Vec<usize> = indices
+        .iter()
+        .filter_map(|index| sorted.iter().position(|i| i == index))
+        .collect();
+    let mut batches = Vec::new();
+    for batch in reader {
+        let batch = batch?;
+        batches.push(if order.is_empty() {
+            batch
+        } else {
+            batch.project(&order)?
+        });
+    }
+    Ok(batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int64Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_reads_limited_rows_and_columns() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("n", DataType::Int64, false),
+            Field::new("service_name", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec!["a", "b", "c"])),
+            ],
+        )
+        .unwrap();
+        let bytes = otlp2records::output::to_parquet_bytes(&batch).unwrap();
+
+        let columns = vec!["service_name".to_string(), "n".to_string()];
+        let batches = read_batches(bytes.clone(), &columns, 2).unwrap();
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(rows, 2);
+        assert_eq!(batches[0].schema().field(0).name(), "service_name");
+        assert!(read_batches(bytes, &["missing".to_string()], 2).is_err());
+
+        let storage = RuntimeConfig::from_platform_defaults(crate::Platform::Server).storage;
+        match resolve("s3://otlp/logs/a.parquet", &storage).unwrap() {
+            Source::Remote(storage, key) => {
+                assert_eq!(storage.backend, StorageBackend::S3);
+                assert_eq!(storage.s3.unwrap().bucket, "otlp");
+                assert_eq!(key, "logs/a.parquet");
+            }
+            Source::Local(_) => panic!("expected a remote source"),
+        }
+        assert!(resolve("gs://otlp/a.parquet", &storage).is_err());
+    }
+}
@@ -0,0 +1,433 @@
+//! Local SQLite catalog of written Parquet files and the `query` command.
+//!
+//! For laptop and single-node setups, every written file is recorded in a
+//! SQLite database (table, path, rows, bytes, min/max event timestamp) so
+//! readers can find files without listing partition directories or running a
+//! catalog service. `otlp2parquet query` loads the catalog into an in-memory
+//! DuckDB, exposes one view per table over the catalogued files and runs a SQL
+//! statement against them; `otlp2parquet export` copies a table (optionally as
+//! of an earlier point in time) to a Parquet, CSV or JSONL file. Enabled with
+//! the `catalog` feature.
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+
+use crate::config::{RuntimeConfig, StorageBackend};
+use crate::writer::manifest::ManifestEntry;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS files (
+    path TEXT PRIMARY KEY,
+    table_name TEXT NOT NULL,
+    rows INTEGER NOT NULL,
+    bytes INTEGER NOT NULL,
+    min_timestamp_micros INTEGER NOT NULL,
+    max_timestamp_micros INTEGER NOT NULL,
+    written_at_micros INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS files_by_table_time
+    ON files (table_name, min_timestamp_micros);
+";
+
+/// Summary printed when `query` is run without a statement
+const DEFAULT_QUERY: &str = "SELECT table_name, count(*) AS files, sum(rows) AS rows, \
+     make_timestamp(min(min_timestamp_micros)) AS oldest, \
+     make_timestamp(max(max_timestamp_micros)) AS newest \
+     FROM catalog_files GROUP BY table_name ORDER BY table_name";
+
+/// One catalogued file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogFile {
+    pub path: String,
+    pub table_name: String,
+    pub rows: i64,
+    pub bytes: i64,
+    pub min_timestamp_micros: i64,
+    pub max_timestamp_micros: i64,
+    pub written_at_micros: i64,
+}
+
+/// SQLite file catalog shared by all writers in the process.
+pub struct Catalog {
+    conn: Mutex<Connection>,
+}
+
+impl Catalog {
+    /// Open (creating if needed) the catalog at `path`.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open catalog {}", path.display()))?;
+        // WAL lets `otlp2parquet query` read while the server keeps writing
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create catalog schema")?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Open an existing catalog without write access.
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Failed to open catalog {}", path.display()))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Record a freshly written file. `path` is the full object path and
+    /// `table` the table directory it belongs to (`logs`, `metrics/gauge`, ...).
+    ///
+    /// A single indexed insert in WAL mode; cheap enough to run inline on the
+    /// write path.
+    pub fn record(&self, table: &str, path: &str, entry: &ManifestEntry) -> Result<()> {
+        self.conn
+            .lock()
+            .execute(
+                "INSERT OR REPLACE INTO files (path, table_name, rows, bytes, \
+                 min_timestamp_micros, max_timestamp_micros, written_at_micros) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    path,
+                    table,
+                    entry.rows as i64,
+                    entry.bytes as i64,
+                    entry.min_timestamp_micros,
+                    entry.max_timestamp_micros,
+                    entry.written_at_micros,
+                ],
+            )
+            .with_context(|| format!("Failed to record {} in catalog", path))?;
+        Ok(())
+    }
+
+    /// Point the entry for `path` at `location` after the file moved to
+    /// another storage target; returns whether the file was catalogued.
+    pub fn relocate(&self, path: &str, location: &str) -> Result<bool> {
+        let updated = self
+            .conn
+            .lock()
+            .execute(
+                "UPDATE OR REPLACE files SET path = ?2 WHERE path = ?1",
+                params![path, location],
+            )
+            .with_context(|| format!("Failed to relocate {} in catalog", path))?;
+        Ok(updated > 0)
+    }
+
+    /// Every catalogued file, oldest data first.
+    pub fn files(&self) -> Result<Vec<CatalogFile>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            "SELECT path, table_name, rows, bytes, min_timestamp_micros, \
+             max_timestamp_micros, written_at_micros \
+             FROM files ORDER BY table_name, min_timestamp_micros, path",
+        )?;
+        let files = stmt
+            .query_map([], |row| {
+                Ok(CatalogFile {
+                    path: row.get(0)?,
+                    table_name: row.get(1)?,
+                    rows: row.get(2)?,
+                    bytes: row.get(3)?,
+                    min_timestamp_micros: row.get(4)?,
+                    max_timestamp_micros: row.get(5)?,
+                    written_at_micros: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(files)
+    }
+}
+
+/// DuckDB view name for a table directory (`metrics/gauge` -> `metrics_gauge`)
+pub fn view_name(table: &str) -> String {
+    table
+        .trim_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[derive(Args)]
+pub struct QueryArgs {
+    /// SQL to run; views are named after tables (logs, traces, metrics_gauge, ...)
+    /// and `catalog_files` lists every file. Defaults to a per-table summary.
+    pub sql: Option<String>,
+
+    /// Catalog file (default: catalog.path, or _catalog.sqlite under the storage path)
+    #[arg(long, value_name = "FILE")]
+    pub catalog: Option<PathBuf>,
+}
+
+impl QueryArgs {
+    pub fn run(&self, config: &RuntimeConfig) -> Result<()> {
+        let (root, files) = catalogued_files(config, self.catalog.as_deref())?;
+        let conn = duckdb::Connection::open_in_memory().context("Failed to open DuckDB")?;
+        load_catalog(&conn, &root, &files)?;
+
+        let sql = self.sql.as_deref().unwrap_or(DEFAULT_QUERY);
+        let mut stmt = conn.prepare(sql).context("Failed to prepare query")?;
+        let batches: Vec<_> = stmt.query_arrow([]).context("Query failed")?.collect();
+        println!(
+            "{}",
+            duckdb::arrow::util::pretty::pretty_format_batches(&batches)?
+        );
+        Ok(())
+    }
+}
+
+/// Output format of `export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Parquet,
+    Csv,
+    Jsonl,
+}
+
+impl ExportFormat {
+    /// Format implied by the file extension, parquet if there is none
+    fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => Self::Csv,
+            Some("jsonl" | "json" | "ndjson") => Self::Jsonl,
+            _ => Self::Parquet,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Table to export (`logs`, `traces`, `metrics/gauge` or its view name `metrics_gauge`, ...)
+    #[arg(long)]
+    pub table: String,
+
+    /// Only include files written at or before this time (RFC 3339, e.g.
+    /// 2025-01-15T10:30:00Z), so repeated exports return the same rows
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub at: Option<String>,
+
+    /// SQL filter on the table's columns, e.g. "service_name = 'checkout'"
+    #[arg(long = "where", value_name = "PREDICATE")]
+    pub predicate: Option<String>,
+
+    /// Output format (default: from the file extension, else parquet)
+    #[arg(long, value_enum)]
+    pub format: Option<ExportFormat>,
+
+    /// File to write
+    #[arg(value_name = "FILE")]
+    pub destination: PathBuf,
+
+    /// Catalog file (default: catalog.path, or _catalog.sqlite under the storage path)
+    #[arg(long, value_name = "FILE")]
+    pub catalog: Option<PathBuf>,
+}
+
+impl ExportArgs {
+    pub fn run(&self, config: &RuntimeConfig) -> Result<()> {
+        let (root, mut files) = catalogued_files(config, self.catalog.as_deref())?;
+        let conn = duckdb::Connection::open_in_memory().context("Failed to open DuckDB")?;
+
+        let view = view_name(&self.table);
+        files.retain(|file| view_name(&file.table_name) == view);
+        if files.is_empty() {
+            bail!("No files catalogued for table '{}'", self.table);
+        }
+        if let Some(at) = self.at.as_deref() {
+            let at_micros: i64 = conn
+                .query_row("SELECT epoch_us(CAST(? AS TIMESTAMPTZ))", [at], |row| {
+                    row.get(0)
+                })
+                .with_context(|| format!("Invalid --at timestamp '{}'", at))?;
+            files.retain(|file| file.written_at_micros <= at_micros);
+            if files.is_empty() {
+                bail!("No files of table '{}' were written by {}", self.table, at);
+            }
+        }
+        load_catalog(&conn, &root, &files)?;
+
+        let format = self
+            .format
+            .unwrap_or_else(|| ExportFormat::for_path(&self.destination));
+        let options = match format {
+            ExportFormat::Parquet => "FORMAT parquet, COMPRESSION zstd",
+            ExportFormat::Csv => "FORMAT csv, HEADER true",
+            ExportFormat::Jsonl => "FORMAT json",
+        };
+        let filter = self
+            .predicate
+            .as_deref()
+            .map(|predicate| format!(" WHERE {}", predicate))
+            .unwrap_or_default();
+        let destination = self.destination.display().to_string().replace('\'', "''");
+        let rows = conn
+            .execute(
+                &format!(
+                    "COPY (SELECT * FROM \"{}\"{}) TO '{}' ({})",
+                    view, filter, destination, options
+                ),
+                [],
+            )
+            .context("Export failed")?;
+        println!(
+            "Exported {} rows of {} from {} files to {}",
+            rows,
+            self.table,
+            files.len(),
+            self.destination.display()
+        );
+        Ok(())
+    }
+}
+
+/// Local data root and catalogued files for the `query` and `export` commands
+fn catalogued_files(
+    config: &RuntimeConfig,
+    catalog: Option<&Path>,
+) -> Result<(PathBuf, Vec<CatalogFile>)> {
+    let root = match (config.storage.backend, &config.storage.fs) {
+        (StorageBackend::Fs, Some(fs)) => PathBuf::from(&fs.path),
+        (backend, _) => bail!(
+            "query and export read Parquet files from local disk; storage backend is {}",
+            backend
+        ),
+    };
+    let catalog_path = catalog
+        .map(Path::to_path_buf)
+        .or_else(|| config.catalog.resolved_path(&config.storage))
+        .context("No catalog path configured (set catalog.path or pass --catalog)")?;
+    if !catalog_path.exists() {
+        bail!(
+            "Catalog {} does not exist; run the server with catalog.enabled = true first",
+            catalog_path.display()
+        );
+    }
+    let files = Catalog::open_read_only(&catalog_path)?.files()?;
+    Ok((root, files))
+}
+
+/// Create `catalog_files` and one view per table over the files still on disk.
+fn load_catalog(conn: &duckdb::Connection, root: &Path, files: &[CatalogFile]) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE catalog_files (
+            path VARCHAR, table_name VARCHAR, rows BIGINT, bytes BIGINT,
+            min_timestamp_micros BIGINT, max_timestamp_micros BIGINT,
+            written_at_micros BIGINT
+        );",
+    )?;
+    {
+        let mut appender = conn.appender("catalog_files")?;
+        for file in files {
+            appender.append_row(duckdb::params![
+                file.path,
+                file.table_name,
+                file.rows,
+                file.bytes,
+                file.min_timestamp_micros,
+                file.max_timestamp_micros,
+                file.written_at_micros,
+            ])?;
+        }
+    }
+
+    let mut tables: Vec<(&str, Vec<String>)> = Vec::new();
+    for file in files {
+        // Files removed by retention or by hand stay catalogued; skip them
+        let local = root.join(&file.path);
+        if !local.exists() {
+            continue;
+        }
+        let literal = format!("'{}'", local.display().to_string().replace('\'', "''"));
+        match tables.last_mut() {
+            Some((table, paths)) if *table == file.table_name => paths.push(literal),
+            _ => tables.push((&file.table_name, vec![literal])),
+        }
+    }
+    for (table, paths) in tables {
+        conn.execute_batch(&format!(
+            "CREATE VIEW \"{}\" AS SELECT * FROM read_parquet([{}], union_by_name = true);",
+            view_name(table),
+            paths.join(", ")
+        ))
+        .with_context(|| format!("Failed to create view for table {}", table))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(rows: usize, min_ts: i64) -> ManifestEntry {
+        ManifestEntry {
+            file: String::new(),
+            rows,
+            bytes: 1024,
+            min_timestamp_micros: min_ts,
+            max_timestamp_micros: min_ts + 1_000_000,
+            blake3: String::new(),
+            written_at_micros: min_ts + 2_000_000,
+        }
+    }
+
+    #[test]
+    fn test_records_and_lists_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("_catalog.sqlite");
+        let catalog = Catalog::open(&path).unwrap();
+
+        catalog
+            .record("logs", "logs/web/year=2025/b.parquet", &entry(10, 2_000))
+            .unwrap();
+        catalog
+            .record("logs", "logs/web/year=2025/a.parquet", &entry(5, 1_000))
+            .unwrap();
+        catalog
+            .record("metrics/gauge", "metrics/gauge/web/c.parquet", &entry(1, 0))
+            .unwrap();
+        // Re-recording a path replaces its row
+        catalog
+            .record("logs", "logs/web/year=2025/a.parquet", &entry(6, 1_000))
+            .unwrap();
+
+        let files = Catalog::open_read_only(&path).unwrap().files().unwrap();
+        let summary: Vec<(&str, &str, i64)> = files
+            .iter()
+            .map(|f| (f.table_name.as_str(), f.path.as_str(), f.rows))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("logs", "logs/web/year=2025/a.parquet", 6),
+                ("logs", "logs/web/year=2025/b.parquet", 10),
+                ("metrics/gauge", "metrics/gauge/web/c.parquet", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_view_name() {
+        assert_eq!(view_name("logs"), "logs");
+        assert_eq!(
+            view_name("metrics/exponential_histogram"),
+            "metrics_exponential_histogram"
+        );
+    }
+
+    #[test]
+    fn test_export_format_from_extension() {
+        let format = |p: &str| ExportFormat::for_path(Path::new(p));
+        assert_eq!(format("incident.csv"), ExportFormat::Csv);
+        assert_eq!(format("incident.jsonl"), ExportFormat::Jsonl);
+        assert_eq!(format("incident.parquet"), ExportFormat::Parquet);
+        assert_eq!(format("incident"), ExportFormat::Parquet);
+    }
+}
@@ -0,0 +1,215 @@
+// ClickHouse dual sink
+//
+// With `clickhouse.enabled`, every signal batch written to Parquet is also
+// inserted into ClickHouse, so rows are queryable immediately while Parquet
+// stays the archive. Batches are sent as Arrow IPC streams through the HTTP
+// interface (`INSERT ... FORMAT ArrowStream`), not the native TCP protocol,
+// keeping the columnar blocks intact without a separate client.
+//
+// The copy is the batch exactly as stored (timestamp unit and optional
+// columns applied) and is queued only after the Parquet write succeeds: a
+// failed write is retried by the client, which must not duplicate rows in
+// ClickHouse. Parquet never waits on ClickHouse: inserts go to a background
+// task that retries with exponential backoff, and a full queue drops the
+// ClickHouse copy (counted in `otlp.clickhouse.dropped`).
+
+use crate::config::ClickHouseConfig;
+use crate::retry_queue::{backoff, spawn_worker, RetryQueue};
+use crate::SignalType;
+use anyhow::{bail, Context, Result};
+use arrow::array::RecordBatch;
+use metrics::counter;
+use once_cell::sync::OnceCell;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Longest pause between retries of one insert
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+static SINK: OnceCell<ClickHouseSink> = OnceCell::new();
+
+struct ClickHouseSink {
+    queue: RetryQueue<Insert>,
+    table_prefix: String,
+}
+
+struct Insert {
+    table: String,
+    batch: RecordBatch,
+}
+
+struct Inserter {
+    client: reqwest::Client,
+    config: ClickHouseConfig,
+}
+
+/// Start the background insert task if the sink is enabled.
+pub(crate) fn init(config: &ClickHouseConfig) -> Result<()> {
+    if !config.enabled || SINK.get().is_some() {
+        return Ok(());
+    }
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .build()
+        .context("Failed to build ClickHouse HTTP client")?;
+    let inserter = Arc::new(Inserter {
+        client,
+        config: config.clone(),
+    });
+    let queue = spawn_worker(config.queue_capacity, 1, move |insert: Insert| {
+        let inserter = Arc::clone(&inserter);
+        async move { inserter.insert_with_retry(&insert).await }
+    });
+    let _ = SINK.set(ClickHouseSink {
+        queue,
+        table_prefix: config.table_prefix.clone(),
+    });
+    info!(
+        "Streaming batches to ClickHouse at {} (database {}, tables {}*)",
+        config.url, config.database, config.table_prefix
+    );
+    Ok(())
+}
+
+/// Queue a copy of `batch` for ClickHouse. Never blocks; no-op when disabled.
+pub(crate) fn send(signal_type: SignalType, metric_type: Option<&str>, batch: &RecordBatch) {
+    let Some(sink) = SINK.get() else {
+        return;
+    };
+    let table = table_name(&sink.table_prefix, signal_type, metric_type);
+    let rows = batch.num_rows() as u64;
+
+    if let Err(e) = sink.queue.try_push(Insert {
+        table,
+        batch: batch.clone(),
+    }) {
+        let insert = match e {
+            mpsc::error::TrySendError::Full(insert) | mpsc::error::TrySendError::Closed(insert) => {
+                insert
+            }
+        };
+        counter!("otlp.clickhouse.dropped", "table" => insert.table.clone()).increment(rows);
+        warn!(
+            table = %insert.table,
+            rows,
+            "ClickHouse insert queue full, dropping batch (Parquet copy unaffected)"
+        );
+    }
+}
+
+/// Wait up to `timeout` for queued inserts to finish during shutdown.
+pub(crate) async fn drain(timeout: Duration) {
+    let Some(sink) = SINK.get() else {
+        return;
+    };
+    let pending = sink.queue.drain(timeout).await;
+    if pending > 0 {
+        warn!(pending, "Gave up waiting for queued ClickHouse inserts");
+    }
+}
+
+/// ClickHouse table for a signal: `otel_logs`, `otel_traces`, `otel_metrics_gauge`, ...
+fn table_name(prefix: &str, signal_type: SignalType, metric_type: Option<&str>) -> String {
+    match (signal_type, metric_type) {
+        (SignalType::Metrics, Some(metric_type)) => format!("{}metrics_{}", prefix, metric_type),
+        (signal_type, _) => format!("{}{}", prefix, signal_type.as_str()),
+    }
+}
+
+impl Inserter {
+    async fn insert_with_retry(&self, insert: &Insert) {
+        let rows = insert.batch.num_rows() as u64;
+        let body = match crate::codec::encode_arrow_ipc(&insert.batch) {
+            Ok(body) => body,
+            Err(e) => {
+                counter!("otlp.clickhouse.failed", "table" => insert.table.clone()).increment(rows);
+                warn!(error = %e, table = %insert.table, "Failed to encode batch for ClickHouse");
+                return;
+            }
+        };
+
+        let mut attempt = 0u32;
+        loop {
+            match self.insert(&insert.table, body.clone()).await {
+                Ok(()) => {
+                    counter!("otlp.clickhouse.rows", "table" => insert.table.clone())
+                        .increment(rows);
+                    debug!(table = %insert.table, rows, "Inserted batch into ClickHouse");
+                    return;
+                }
+                Err(e) if attempt < self.config.max_retries => {
+                    attempt += 1;
+                    let delay = backoff(attempt, MAX_BACKOFF);
+                    debug!(
+                        error = %e,
+                        table = %insert.table,
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        "ClickHouse insert failed, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => {
+                    counter!("otlp.clickhouse.failed", "table" => insert.table.clone())
+                        .increment(rows);
+                    warn!(
+                        error = %e,
+                        table = %insert.table,
+                        rows,
+                        attempts = attempt + 1,
+                        "Giving up on ClickHouse insert (Parquet copy unaffected)"
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn insert(&self, table: &str, body: Vec<u8>) -> Result<()> {
+        let mut url = reqwest::Url::parse(&self.config.url).context("Invalid ClickHouse URL")?;
+        url.query_pairs_mut().append_pair(
+            "query",
+            &format!(
+                "INSERT INTO {}.{} FORMAT ArrowStream",
+                quote_identifier(&self.config.database),
+                quote_identifier(table)
+            ),
+        );
+
+        let mut request = self.client.post(url).body(body);
+        if let Some(user) = &self.config.username {
+            request = request.header("X-ClickHouse-User", user);
+        }
+        if let Some(password) = &self.config.password {
+            request = request.header("X-ClickHouse-Key", password);
+        }
+        let response = request.send().await.context("ClickHouse request failed")?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            bail!("ClickHouse returned {}: {}", status, text.trim());
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_names() {
+        assert_eq!(table_name("otel_", SignalType::Logs, None), "otel_logs");
+        assert_eq!(table_name("", SignalType::Traces, None), "traces");
+        assert_eq!(
+            table_name("otel_", SignalType::Metrics, Some("exponential_histogram")),
+            "otel_metrics_exponential_histogram"
+        );
+    }
+}
@@ -0,0 +1,19 @@
+// Wall-clock timestamps
+//
+// Rows and manifests store microseconds since the Unix epoch; OTLP payloads
+// built in-process (probes, derived metrics) carry nanoseconds.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Nanoseconds since the Unix epoch (0 if the clock is before it)
+pub(crate) fn now_unix_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default()
+}
+
+/// Microseconds since the Unix epoch
+pub(crate) fn now_micros() -> i64 {
+    (now_unix_nanos() / 1_000) as i64
+}
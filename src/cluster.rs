@@ -0,0 +1,318 @@
+// Peer forwarding between replicas
+//
+// Behind a load balancer every replica receives a slice of every service, so
+// each one writes its own small files for the same service and minute. With
+// `cluster.peers` (or `cluster.dns`) set, each batch key (service, minute
+// bucket) is owned by one replica chosen by rendezvous hashing over the peer
+// list. Handlers forward decoded batches they do not own to the owner's
+// `POST /cluster/batches` as Arrow IPC streams; the owner batches and writes
+// them. A failed forward falls back to batching locally, so peer trouble costs
+// file sizes, never data. Forwarded batches are never forwarded again.
+
+use crate::auth::authorized;
+use crate::codec::{decode_arrow_ipc, encode_arrow_ipc, PartitionedBatch, ServiceGroupedBatches};
+use crate::config::ClusterConfig;
+use crate::{AppError, AppState, SignalType};
+use anyhow::{anyhow, bail, Context, Result};
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use metrics::{counter, gauge};
+use parking_lot::RwLock;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+const TABLE_HEADER: &str = "x-otlp2parquet-table";
+const SERVICE_HEADER: &str = "x-otlp2parquet-service";
+const TIMESTAMP_HEADER: &str = "x-otlp2parquet-min-timestamp";
+
+pub(crate) struct Cluster {
+    self_url: String,
+    token: Option<String>,
+    /// Normalized peer URLs, sorted; includes this replica when it is listed
+    peers: RwLock<Vec<String>>,
+    client: reqwest::Client,
+}
+
+impl Cluster {
+    /// `None` when peer forwarding is not configured.
+    pub(crate) fn new(config: &ClusterConfig) -> Result<Option<Self>> {
+        if !config.enabled() {
+            return Ok(None);
+        }
+        let self_url = config
+            .self_url
+            .as_deref()
+            .map(normalize)
+            .context("cluster.self_url is required")?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.forward_timeout_secs))
+            .build()
+            .context("Failed to build cluster HTTP client")?;
+        let cluster = Self {
+            self_url,
+            token: config.token.clone(),
+            peers: RwLock::new(Vec::new()),
+            client,
+        };
+        cluster.set_peers(config.peers.iter().map(|peer| normalize(peer)).collect());
+        Ok(Some(cluster))
+    }
+
+    /// Keep the peer list current from `cluster.dns`, if set.
+    pub(crate) fn start_discovery(self: &Arc<Self>, config: &ClusterConfig) {
+        let Some(dns) = config.dns.clone() else {
+            return;
+        };
+        let cluster = Arc::clone(self);
+        let interval = Duration::from_secs(config.refresh_secs);
+        let scheme = if cluster.self_url.starts_with("https://") {
+            "https"
+        } else {
+            "http"
+        };
+        tokio::spawn(async move {
+            loop {
+                match tokio::net::lookup_host(dns.as_str()).await {
+                    Ok(addrs) => {
+                        let peers = addrs
+                            .map(|addr| format!("{}://{}", scheme, addr))
+                            .collect::<Vec<_>>();
+                        if peers.is_empty() {
+                            warn!(dns = %dns, "Cluster DNS returned no peers; keeping previous list");
+                        } else {
+                            cluster.set_peers(peers);
+                        }
+                    }
+                    Err(e) => warn!(dns = %dns, error = %e, "Failed to resolve cluster peers"),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    fn set_peers(&self, mut peers: Vec<String>) {
+        peers.sort();
+        peers.dedup();
+        gauge!("otlp.cluster.peers").set(peers.len() as f64);
+        let mut current = self.peers.write();
+        if *current != peers {
+            info!(peers = %peers.join(","), "Cluster peers updated");
+            *current = peers;
+        }
+    }
+
+    /// Replica owning the batch key of `service` at `timestamp_micros`;
+    /// `None` when this replica owns it
+    fn owner(&self, service: &str, timestamp_micros: i64) -> Option<String> {
+        // Same minute bucket as the batcher's BatchKey
+        let minute = if timestamp_micros > 0 {
+            timestamp_micros / 60_000_000
+        } else {
+            0
+        };
+        let peers = self.peers.read();
+        let owner = peers
+            .iter()
+            .max_by_key(|peer| score(peer, service, minute))?;
+        (*owner != self.self_url).then(|| owner.clone())
+    }
+
+    /// Forward the batches other replicas own; returns the ones to batch here.
+    pub(crate) async fn forward(
+        &self,
+        signal: SignalType,
+        table: &str,
+        grouped: ServiceGroupedBatches,
+        request_id: Option<&str>,
+    ) -> ServiceGroupedBatches {
+        let mut local = ServiceGroupedBatches {
+            batches: Vec::with_capacity(grouped.batches.len()),
+            total_records: grouped.total_records,
+        };
+        for pb in grouped.batches {
+            let Some(owner) = self.owner(&pb.service_name, pb.min_timestamp_micros) else {
+                local.batches.push(pb);
+                continue;
+            };
+            match self.send(&owner, table, &pb, request_id).await {
+                Ok(()) => {
+                    counter!("otlp.cluster.forwarded", "signal" => signal.as_str())
+                        .increment(pb.record_count as u64);
+                    debug!(owner = %owner, service = %pb.service_name, rows = pb.record_count, table, "Forwarded batch");
+                    local.total_records -= pb.record_count;
+                }
+                Err(e) => {
+                    counter!("otlp.cluster.forward_failures", "signal" => signal.as_str())
+                        .increment(1);
+                    warn!(owner = %owner, error = %e, table, "Forwarding failed, batching locally");
+                    local.batches.push(pb);
+                }
+            }
+        }
+        local
+    }
+
+    async fn send(
+        &self,
+        owner: &str,
+        table: &str,
+        pb: &PartitionedBatch,
+        request_id: Option<&str>,
+    ) -> Result<()> {
+        let mut request = self
+            .client
+            .post(format!("{}/cluster/batches", owner))
+            .header(header::CONTENT_TYPE, "application/vnd.apache.arrow.stream")
+            .header(TABLE_HEADER, table)
+            .header(SERVICE_HEADER, &*pb.service_name)
+            .header(TIMESTAMP_HEADER, pb.min_timestamp_micros.to_string())
+            .body(encode_arrow_ipc(&pb.batch)?);
+        if let Some(ref token) = self.token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(id) = request_id {
+            request = request.header(crate::request_id::HEADER, id);
+        }
+        let response = request.send().await.context("request failed")?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            bail!("peer returned {}: {}", status, text.trim());
+        }
+        Ok(())
+    }
+}
+
+/// POST /cluster/batches - a batch forwarded by a peer, batched here
+pub(crate) async fn handle_forwarded(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<Response, AppError> {
+    let Some(ref cluster) = state.cluster else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    if !authorized(&headers, cluster.token.as_deref()) {
+        return Ok(StatusCode::UNAUTHORIZED.into_response());
+    }
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| AppError::bad_request(anyhow!("missing {} header", name)))
+    };
+    let table = header(TABLE_HEADER)?;
+    let service: Arc<str> = Arc::from(header(SERVICE_HEADER)?);
+    let min_timestamp_micros = header(TIMESTAMP_HEADER)?
+        .parse::<i64>()
+        .map_err(|e| AppError::bad_request(anyhow!("invalid {}: {}", TIMESTAMP_HEADER, e)))?;
+
+    let target = match (table, &state.metrics_batchers) {
+        ("logs", _) => state.batcher.as_ref().map(|b| (SignalType::Logs, None, b)),
+        ("traces", _) => state
+            .traces_batcher
+            .as_ref()
+            .map(|b| (SignalType::Traces, None, b)),
+        ("metrics/gauge", Some(mb)) => Some((SignalType::Metrics, Some("gauge"), &mb.gauge)),
+        ("metrics/sum", Some(mb)) => Some((SignalType::Metrics, Some("sum"), &mb.sum)),
+        ("metrics/histogram", Some(mb)) => {
+            Some((SignalType::Metrics, Some("histogram"), &mb.histogram))
+        }
+        ("metrics/exponential_histogram", Some(mb)) => Some((
+            SignalType::Metrics,
+            Some("exponential_histogram"),
+            &mb.exp_histogram,
+        )),
+        _ => None,
+    };
+    let Some((signal, metric_type, batcher)) = target else {
+        return Err(AppError::bad_request(anyhow!(
+            "cannot batch forwarded table '{}'",
+            table
+        )));
+    };
+
+    let batches = decode_arrow_ipc(&body).map_err(AppError::bad_request)?;
+    let approx_bytes = body.len() / batches.len().max(1);
+    let mut records = 0;
+    for batch in batches {
+        let pb = PartitionedBatch {
+            record_count: batch.num_rows(),
+            batch,
+            service_name: Arc::clone(&service),
+            min_timestamp_micros,
+        };
+        records += pb.record_count;
+        let (completed, _metadata) = batcher
+            .ingest(&pb, approx_bytes, crate::request_id::from_headers(&headers))
+            .map_err(|e| AppError::internal(anyhow!("Batch ingestion failed: {}", e)))?;
+        for batch in completed {
+            crate::handlers::persist_batch(&batch, signal, metric_type, &state.request_metrics)
+                .await
+                .map_err(|e| AppError::unavailable(anyhow!("Failed to flush batch: {}", e)))?;
+        }
+    }
+    counter!("otlp.cluster.received", "signal" => signal.as_str()).increment(records as u64);
+    Ok(Json(json!({ "status": "ok", "records": records })).into_response())
+}
+
+/// Rendezvous hashing weight of `peer` for a batch key; stable across
+/// processes and versions, unlike `DefaultHasher`
+fn score(peer: &str, service: &str, minute: i64) -> u64 {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(peer.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(service.as_bytes());
+    hasher.update(&minute.to_le_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hasher.finalize().as_bytes()[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn normalize(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cluster(self_url: &str, peers: &[&str]) -> Cluster {
+        Cluster::new(&ClusterConfig {
+            peers: peers.iter().map(|p| p.to_string()).collect(),
+            self_url: Some(self_url.to_string()),
+            ..ClusterConfig::default()
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_every_replica_agrees_on_one_owner() {
+        let peers = ["http://a:4318", "http://b:4318/", "http://c:4318"];
+        let replicas: Vec<Cluster> = peers.iter().map(|p| cluster(p, &peers)).collect();
+
+        let mut owned = [0usize; 3];
+        for (i, service) in ["checkout", "cart", "search", "auth", "email"]
+            .iter()
+            .enumerate()
+        {
+            let ts = 1_700_000_000_000_000 + i as i64 * 60_000_000;
+            let local: Vec<usize> = (0..3)
+                .filter(|r| replicas[*r].owner(service, ts).is_none())
+                .collect();
+            assert_eq!(local.len(), 1, "exactly one owner for {}", service);
+            owned[local[0]] += 1;
+            // Non-owners all forward to the same replica
+            let owner = &replicas[local[0]].self_url;
+            for r in (0..3).filter(|r| *r != local[0]) {
+                assert_eq!(replicas[r].owner(service, ts).as_ref(), Some(owner));
+            }
+        }
+        assert_eq!(owned.iter().sum::<usize>(), 5);
+    }
+}
@@ -2,9 +2,22 @@
 //!
 //! This module provides pure functions for decoding OTLP payloads.
 
+use crate::SignalType;
+use anyhow::Context;
+use arrow::array::RecordBatch;
+use arrow::ipc::reader::StreamReader;
+use arrow::ipc::writer::StreamWriter;
+use opentelemetry_proto::tonic::collector::logs::v1::ExportLogsServiceRequest;
+use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, KeyValue};
+use opentelemetry_proto::tonic::metrics::v1::{metric, Exemplar};
 use otlp2records::{
     group_batch_by_service, transform_logs, transform_metrics, transform_traces, InputFormat,
 };
+use prost::Message;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
 
 pub use otlp2records::{
     PartitionedBatch, PartitionedMetrics, ServiceGroupedBatches, SkippedMetrics,
@@ -74,6 +87,555 @@ pub fn decode_metrics_partitioned(
     })
 }
 
+/// Signal of an OTLP/JSON export request from its top-level resource key
+/// (`resourceLogs`, or `resource_logs` as some exporters write it). The key
+/// comes first in every request, so the earliest one found wins.
+pub(crate) fn detect_signal(body: &[u8]) -> Option<SignalType> {
+    [
+        (&b"\"resourceLogs\""[..], SignalType::Logs),
+        (&b"\"resource_logs\""[..], SignalType::Logs),
+        (&b"\"resourceSpans\""[..], SignalType::Traces),
+        (&b"\"resource_spans\""[..], SignalType::Traces),
+        (&b"\"resourceMetrics\""[..], SignalType::Metrics),
+        (&b"\"resource_metrics\""[..], SignalType::Metrics),
+    ]
+    .into_iter()
+    .filter_map(|(key, signal)| {
+        body.windows(key.len())
+            .position(|w| w == key)
+            .map(|pos| (pos, signal))
+    })
+    .min_by_key(|(pos, _)| *pos)
+    .map(|(_, signal)| signal)
+}
+
+// =============================================================================
+// Lenient parsing - tolerate enum names from newer OTLP versions
+// =============================================================================
+
+/// Prefix of the attributes holding enum names this version does not know
+const UNKNOWN_ENUM_ATTRIBUTE_PREFIX: &str = "otlp.unknown.";
+
+/// Rewrite a JSON/JSONL payload so enum names unknown to this OTLP version
+/// decode as UNSPECIFIED (0), recording each raw name as an
+/// `otlp.unknown.<field>` attribute on the enclosing record (for aggregation
+/// temporality: on every data point of the metric).
+///
+/// Returns the rewritten payload and the number of values replaced, or `None`
+/// when the payload is not JSON or has no unknown enum names. Protobuf needs no
+/// such pass: unknown fields are skipped and unknown enum numbers kept.
+pub fn tolerate_unknown_enums(body: &[u8], format: InputFormat) -> Option<(Vec<u8>, usize)> {
+    let text = std::str::from_utf8(body).ok()?;
+    let mut replaced = 0;
+    let mut rewrite = |document: &str| -> Option<String> {
+        let mut value: Value = serde_json::from_str(document).ok()?;
+        let unattached = tolerate_value(&mut value, &mut replaced);
+        if !unattached.is_empty() {
+            tracing::debug!(
+                fields = ?unattached,
+                "Unknown enum names outside any record, replaced without capture"
+            );
+        }
+        Some(value.to_string())
+    };
+    let rewritten = match format {
+        InputFormat::Protobuf => return None,
+        InputFormat::Json => rewrite(text)?,
+        InputFormat::Jsonl => text
+            .lines()
+            .map(|line| {
+                if line.trim().is_empty() {
+                    Some(line.to_string())
+                } else {
+                    rewrite(line)
+                }
+            })
+            .collect::<Option<Vec<_>>>()?
+            .join("\n"),
+        InputFormat::Auto => {
+            if !text.trim_start().starts_with('{') {
+                return None;
+            }
+            match rewrite(text) {
+                Some(rewritten) => rewritten,
+                None => return tolerate_unknown_enums(body, InputFormat::Jsonl),
+            }
+        }
+    };
+    (replaced > 0).then(|| (rewritten.into_bytes(), replaced))
+}
+
+/// Replace unknown enum names below `value`. Returns the (field, raw name)
+/// pairs not yet attached to a record.
+fn tolerate_value(value: &mut Value, replaced: &mut usize) -> Vec<(String, String)> {
+    match value {
+        Value::Object(map) => {
+            let mut unknown = Vec::new();
+            for (key, child) in map.iter_mut() {
+                match child {
+                    Value::String(raw) if is_unknown_enum(key, raw) => {
+                        unknown.push((enum_field(key).to_string(), std::mem::take(raw)));
+                        *child = Value::from(0);
+                        *replaced += 1;
+                    }
+                    _ => unknown.extend(tolerate_value(child, replaced)),
+                }
+            }
+            if unknown.is_empty() {
+                return unknown;
+            }
+            // Temporality lives on the sum/histogram: stamp its data points
+            if let Some(Value::Array(points)) = map
+                .get_mut("dataPoints")
+                .or_else(|| map.get_mut("data_points"))
+            {
+                for point in points.iter_mut().filter_map(Value::as_object_mut) {
+                    add_unknown_attributes(point, &unknown);
+                }
+                return Vec::new();
+            }
+            if is_record(map) {
+                add_unknown_attributes(map, &unknown);
+                return Vec::new();
+            }
+            unknown
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .flat_map(|item| tolerate_value(item, replaced))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether `raw` is a name (not a number) the enum field `key` does not define
+fn is_unknown_enum(key: &str, raw: &str) -> bool {
+    if raw.is_empty() || raw.parse::<i64>().is_ok() {
+        return false;
+    }
+    match enum_field(key) {
+        "kind" => !matches!(
+            raw,
+            "SPAN_KIND_UNSPECIFIED"
+                | "SPAN_KIND_INTERNAL"
+                | "SPAN_KIND_SERVER"
+                | "SPAN_KIND_CLIENT"
+                | "SPAN_KIND_PRODUCER"
+                | "SPAN_KIND_CONSUMER"
+        ),
+        "code" => !matches!(
+            raw,
+            "STATUS_CODE_UNSET" | "STATUS_CODE_OK" | "STATUS_CODE_ERROR"
+        ),
+        "aggregation_temporality" => !matches!(
+            raw,
+            "AGGREGATION_TEMPORALITY_UNSPECIFIED"
+                | "AGGREGATION_TEMPORALITY_DELTA"
+                | "AGGREGATION_TEMPORALITY_CUMULATIVE"
+        ),
+        "severity_number" => !is_known_severity(raw),
+        _ => false,
+    }
+}
+
+fn is_known_severity(raw: &str) -> bool {
+    let Some(name) = raw.strip_prefix("SEVERITY_NUMBER_") else {
+        return false;
+    };
+    if name == "UNSPECIFIED" {
+        return true;
+    }
+    let level = name.trim_end_matches(['2', '3', '4']);
+    name.len() - level.len() <= 1
+        && matches!(
+            level,
+            "TRACE" | "DEBUG" | "INFO" | "WARN" | "ERROR" | "FATAL"
+        )
+}
+
+/// Canonical (snake_case) name of an enum field, any other key unchanged
+fn enum_field(key: &str) -> &str {
+    match key {
+        "severityNumber" => "severity_number",
+        "aggregationTemporality" => "aggregation_temporality",
+        other => other,
+    }
+}
+
+/// Log records, spans and data points: the objects that carry attributes
+fn is_record(map: &Map<String, Value>) -> bool {
+    [
+        "attributes",
+        "spanId",
+        "span_id",
+        "timeUnixNano",
+        "time_unix_nano",
+        "observedTimeUnixNano",
+        "observed_time_unix_nano",
+    ]
+    .iter()
+    .any(|key| map.contains_key(*key))
+}
+
+fn add_unknown_attributes(record: &mut Map<String, Value>, unknown: &[(String, String)]) {
+    let attributes = record
+        .entry("attributes")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Value::Array(attributes) = attributes {
+        for (field, raw) in unknown {
+            attributes.push(json!({
+                "key": format!("{}{}", UNKNOWN_ENUM_ATTRIBUTE_PREFIX, field),
+                "value": { "stringValue": raw },
+            }));
+        }
+    }
+}
+
+// =============================================================================
+// Strict schema - report values that would be stored lossily
+// =============================================================================
+
+/// What a payload carries that conversion would drop or coerce: metric data
+/// points skipped, severity numbers outside the OTLP enum, and attribute and
+/// log body values that do not survive as their OTLP type. Each entry is a
+/// description with the number of values it covers, most frequent first.
+pub fn strict_violations(
+    signal: SignalType,
+    body: &[u8],
+    format: InputFormat,
+    skipped: Option<&SkippedMetrics>,
+) -> Vec<(String, usize)> {
+    let mut found: BTreeMap<String, usize> = BTreeMap::new();
+    if let Some(skipped) = skipped {
+        for (count, what) in [
+            (
+                skipped.summaries,
+                "summary data point(s) skipped (unsupported type)",
+            ),
+            (skipped.nan_values, "data point(s) with a NaN value skipped"),
+            (
+                skipped.infinity_values,
+                "data point(s) with an infinite value skipped",
+            ),
+            (
+                skipped.missing_values,
+                "data point(s) without a value skipped",
+            ),
+        ] {
+            if count > 0 {
+                found.insert(what.to_string(), count);
+            }
+        }
+    }
+    let is_json = match format {
+        InputFormat::Protobuf => false,
+        InputFormat::Json | InputFormat::Jsonl => true,
+        InputFormat::Auto => body.trim_ascii_start().starts_with(b"{"),
+    };
+    if is_json {
+        for document in json_documents(body, format) {
+            lossy_values(&document, &mut found);
+        }
+    } else {
+        lossy_proto_values(signal, body, &mut found);
+    }
+    let mut found: Vec<(String, usize)> = found.into_iter().collect();
+    found.sort_by(|a, b| b.1.cmp(&a.1));
+    found
+}
+
+fn note(found: &mut BTreeMap<String, usize>, label: &str, what: &str) {
+    *found.entry(format!("{}: {}", label, what)).or_default() += 1;
+}
+
+/// Whether a severity number is one of the OTLP SeverityNumber values
+fn is_severity_number(number: i64) -> bool {
+    (0..=24).contains(&number)
+}
+
+fn note_severity(found: &mut BTreeMap<String, usize>, severity: &dyn std::fmt::Display) {
+    note(
+        found,
+        "log record",
+        &format!("severityNumber {} is not an OTLP severity", severity),
+    );
+}
+
+/// Label of a value nested in a kvlist under `key`
+fn nested_label(label: &str, key: &str) -> String {
+    match label.strip_suffix('\'') {
+        Some(open) => format!("{}.{}'", open, key),
+        None => format!("{} '{}'", label, key),
+    }
+}
+
+/// The JSON documents of a JSON/JSONL payload; none for protobuf or invalid JSON
+fn json_documents(body: &[u8], format: InputFormat) -> Vec<Value> {
+    let Ok(text) = std::str::from_utf8(body) else {
+        return Vec::new();
+    };
+    let lines = || {
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    };
+    match format {
+        InputFormat::Protobuf => Vec::new(),
+        InputFormat::Json => serde_json::from_str(text).into_iter().collect(),
+        InputFormat::Jsonl => lines(),
+        InputFormat::Auto if text.trim_start().starts_with('{') => {
+            match serde_json::from_str(text) {
+                Ok(document) => vec![document],
+                Err(_) => lines(),
+            }
+        }
+        InputFormat::Auto => Vec::new(),
+    }
+}
+
+/// Count attribute (`{"key", "value"}`), log body and severity values below
+/// `value` that conversion would coerce
+fn lossy_values(value: &Value, found: &mut BTreeMap<String, usize>) {
+    match value {
+        Value::Object(map) => {
+            if let (Some(Value::String(key)), Some(any)) = (map.get("key"), map.get("value")) {
+                lossy_any_value(&format!("attribute '{}'", key), any, true, found);
+                return;
+            }
+            for (key, child) in map {
+                match key.as_str() {
+                    "body" => lossy_any_value("log body", child, true, found),
+                    "severityNumber" | "severity_number" => {
+                        let known = match child {
+                            Value::Number(n) => n.as_i64().is_some_and(is_severity_number),
+                            Value::String(s) => match s.parse::<i64>() {
+                                Ok(n) => is_severity_number(n),
+                                Err(_) => is_known_severity(s),
+                            },
+                            _ => false,
+                        };
+                        if !known {
+                            note_severity(found, child);
+                        }
+                    }
+                    _ => lossy_values(child, found),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| lossy_values(item, found)),
+        _ => {}
+    }
+}
+
+/// Check an OTLP/JSON AnyValue. Arrays and kvlists are reported where they
+/// are stored as JSON (`top`); values inside them only for their own losses.
+fn lossy_any_value(label: &str, any: &Value, top: bool, found: &mut BTreeMap<String, usize>) {
+    let field = |camel: &str, snake: &str| any.get(camel).or_else(|| any.get(snake));
+    if field("bytesValue", "bytes_value").is_some() {
+        note(found, label, "bytesValue stored as text");
+    }
+    if let Some(int) = field("intValue", "int_value") {
+        let fits = match int {
+            Value::Number(n) => n.as_i64().is_some(),
+            Value::String(s) => s.parse::<i64>().is_ok(),
+            _ => false,
+        };
+        if !fits {
+            note(
+                found,
+                label,
+                &format!("intValue {} is not an int64, stored as null", int),
+            );
+        }
+    }
+    if let Some(array) = field("arrayValue", "array_value") {
+        if top {
+            note(found, label, "arrayValue stored as JSON text");
+        }
+        if let Some(Value::Array(values)) = array.get("values") {
+            for item in values {
+                lossy_any_value(label, item, false, found);
+            }
+        }
+    }
+    if let Some(kvlist) = field("kvlistValue", "kvlist_value") {
+        if top {
+            note(found, label, "kvlistValue stored as JSON text");
+        }
+        if let Some(Value::Array(values)) = kvlist.get("values") {
+            for kv in values {
+                if let (Some(Value::String(key)), Some(child)) = (kv.get("key"), kv.get("value")) {
+                    lossy_any_value(&nested_label(label, key), child, false, found);
+                }
+            }
+        }
+    }
+}
+
+/// Count the attribute, log body and severity values of a protobuf request
+/// that conversion would coerce. Undecodable payloads are left to the decoder.
+fn lossy_proto_values(signal: SignalType, body: &[u8], found: &mut BTreeMap<String, usize>) {
+    fn attributes(attributes: &[KeyValue], found: &mut BTreeMap<String, usize>) {
+        for kv in attributes {
+            if let Some(value) = &kv.value {
+                lossy_proto_any(&format!("attribute '{}'", kv.key), value, true, found);
+            }
+        }
+    }
+    fn exemplars(exemplars: &[Exemplar], found: &mut BTreeMap<String, usize>) {
+        for exemplar in exemplars {
+            attributes(&exemplar.filtered_attributes, found);
+        }
+    }
+
+    match signal {
+        SignalType::Logs => {
+            let Ok(request) = ExportLogsServiceRequest::decode(body) else {
+                return;
+            };
+            for resource_logs in &request.resource_logs {
+                if let Some(resource) = &resource_logs.resource {
+                    attributes(&resource.attributes, found);
+                }
+                for scope_logs in &resource_logs.scope_logs {
+                    if let Some(scope) = &scope_logs.scope {
+                        attributes(&scope.attributes, found);
+                    }
+                    for record in &scope_logs.log_records {
+                        attributes(&record.attributes, found);
+                        if let Some(body) = &record.body {
+                            lossy_proto_any("log body", body, true, found);
+                        }
+                        if !is_severity_number(i64::from(record.severity_number)) {
+                            note_severity(found, &record.severity_number);
+                        }
+                    }
+                }
+            }
+        }
+        SignalType::Traces => {
+            let Ok(request) = ExportTraceServiceRequest::decode(body) else {
+                return;
+            };
+            for resource_spans in &request.resource_spans {
+                if let Some(resource) = &resource_spans.resource {
+                    attributes(&resource.attributes, found);
+                }
+                for scope_spans in &resource_spans.scope_spans {
+                    if let Some(scope) = &scope_spans.scope {
+                        attributes(&scope.attributes, found);
+                    }
+                    for span in &scope_spans.spans {
+                        attributes(&span.attributes, found);
+                        for event in &span.events {
+                            attributes(&event.attributes, found);
+                        }
+                        for link in &span.links {
+                            attributes(&link.attributes, found);
+                        }
+                    }
+                }
+            }
+        }
+        SignalType::Metrics => {
+            let Ok(request) = ExportMetricsServiceRequest::decode(body) else {
+                return;
+            };
+            for resource_metrics in &request.resource_metrics {
+                if let Some(resource) = &resource_metrics.resource {
+                    attributes(&resource.attributes, found);
+                }
+                for scope_metrics in &resource_metrics.scope_metrics {
+                    if let Some(scope) = &scope_metrics.scope {
+                        attributes(&scope.attributes, found);
+                    }
+                    for metric in &scope_metrics.metrics {
+                        match &metric.data {
+                            Some(metric::Data::Gauge(gauge)) => {
+                                for point in &gauge.data_points {
+                                    attributes(&point.attributes, found);
+                                    exemplars(&point.exemplars, found);
+                                }
+                            }
+                            Some(metric::Data::Sum(sum)) => {
+                                for point in &sum.data_points {
+                                    attributes(&point.attributes, found);
+                                    exemplars(&point.exemplars, found);
+                                }
+                            }
+                            Some(metric::Data::Histogram(histogram)) => {
+                                for point in &histogram.data_points {
+                                    attributes(&point.attributes, found);
+                                    exemplars(&point.exemplars, found);
+                                }
+                            }
+                            Some(metric::Data::ExponentialHistogram(histogram)) => {
+                                for point in &histogram.data_points {
+                                    attributes(&point.attributes, found);
+                                    exemplars(&point.exemplars, found);
+                                }
+                            }
+                            Some(metric::Data::Summary(summary)) => {
+                                for point in &summary.data_points {
+                                    attributes(&point.attributes, found);
+                                }
+                            }
+                            None => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Protobuf counterpart of `lossy_any_value`; int64 values always fit
+fn lossy_proto_any(label: &str, any: &AnyValue, top: bool, found: &mut BTreeMap<String, usize>) {
+    match &any.value {
+        Some(any_value::Value::BytesValue(_)) => note(found, label, "bytesValue stored as text"),
+        Some(any_value::Value::ArrayValue(array)) => {
+            if top {
+                note(found, label, "arrayValue stored as JSON text");
+            }
+            for item in &array.values {
+                lossy_proto_any(label, item, false, found);
+            }
+        }
+        Some(any_value::Value::KvlistValue(kvlist)) => {
+            if top {
+                note(found, label, "kvlistValue stored as JSON text");
+            }
+            for kv in &kvlist.values {
+                if let Some(value) = &kv.value {
+                    lossy_proto_any(&nested_label(label, &kv.key), value, false, found);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// =============================================================================
+// Arrow IPC - batches sent to peers and ClickHouse
+// =============================================================================
+
+/// Encode `batch` as an Arrow IPC stream.
+pub(crate) fn encode_arrow_ipc(batch: &RecordBatch) -> anyhow::Result<Vec<u8>> {
+    let mut writer = StreamWriter::try_new(Vec::new(), batch.schema_ref())?;
+    writer.write(batch)?;
+    writer.finish()?;
+    Ok(writer.into_inner()?)
+}
+
+/// Decode every batch of an Arrow IPC stream.
+pub(crate) fn decode_arrow_ipc(body: &[u8]) -> anyhow::Result<Vec<RecordBatch>> {
+    let reader = StreamReader::try_new(body, None).context("invalid Arrow IPC stream")?;
+    reader
+        .collect::<Result<Vec<_>, _>>()
+        .context("invalid Arrow IPC stream")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -95,4 +657,196 @@ mod tests {
         let result = decode_metrics_partitioned(b"", InputFormat::Jsonl);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_strict_violations_find_coerced_values() {
+        let body = json!({"resourceLogs": [{
+            "resource": {"attributes": [
+                {"key": "service.name", "value": {"stringValue": "api"}},
+            ]},
+            "scopeLogs": [{"logRecords": [
+                {
+                    "timeUnixNano": "1736938800000000000",
+                    "body": {"bytesValue": "AAEC"},
+                    "attributes": [
+                        {"key": "payload", "value": {"bytesValue": "AAEC"}},
+                        {"key": "big", "value": {"intValue": "18446744073709551615"}},
+                        {"key": "ok", "value": {"intValue": "42"}},
+                        {"key": "http", "value": {"kvlistValue": {"values": [
+                            {"key": "raw", "value": {"bytesValue": "AA=="}},
+                        ]}}},
+                    ],
+                },
+                {
+                    "timeUnixNano": "1736938800000000000",
+                    "severityNumber": 99,
+                    "attributes": [{"key": "payload", "value": {"bytesValue": "AQ=="}}],
+                },
+            ]}],
+        }]})
+        .to_string();
+        let found = strict_violations(SignalType::Logs, body.as_bytes(), InputFormat::Json, None);
+        assert_eq!(
+            found,
+            [
+                ("attribute 'payload': bytesValue stored as text".to_string(), 2),
+                (
+                    "attribute 'big': intValue \"18446744073709551615\" is not an int64, stored as null"
+                        .to_string(),
+                    1
+                ),
+                ("attribute 'http': kvlistValue stored as JSON text".to_string(), 1),
+                ("attribute 'http.raw': bytesValue stored as text".to_string(), 1),
+                ("log body: bytesValue stored as text".to_string(), 1),
+                (
+                    "log record: severityNumber 99 is not an OTLP severity".to_string(),
+                    1
+                ),
+            ]
+        );
+
+        let skipped = SkippedMetrics {
+            summaries: 3,
+            ..Default::default()
+        };
+        let found = strict_violations(
+            SignalType::Metrics,
+            b"\x0a\x00",
+            InputFormat::Protobuf,
+            Some(&skipped),
+        );
+        assert_eq!(
+            found,
+            [(
+                "summary data point(s) skipped (unsupported type)".to_string(),
+                3
+            )]
+        );
+    }
+
+    #[test]
+    fn test_strict_violations_in_protobuf() {
+        use opentelemetry_proto::tonic::common::v1::{ArrayValue, KeyValueList};
+        use opentelemetry_proto::tonic::logs::v1::{LogRecord, ResourceLogs, ScopeLogs};
+        use opentelemetry_proto::tonic::resource::v1::Resource;
+
+        let any = |value| AnyValue { value: Some(value) };
+        let kv = |key: &str, value| KeyValue {
+            key: key.to_string(),
+            value: Some(any(value)),
+        };
+        let string = |s: &str| any_value::Value::StringValue(s.to_string());
+        let record = |severity_number, body, attributes| LogRecord {
+            time_unix_nano: 1_736_938_800_000_000_000,
+            severity_number,
+            body,
+            attributes,
+            ..Default::default()
+        };
+        let request = ExportLogsServiceRequest {
+            resource_logs: vec![ResourceLogs {
+                resource: Some(Resource {
+                    attributes: vec![kv("service.name", string("api"))],
+                    ..Default::default()
+                }),
+                scope_logs: vec![ScopeLogs {
+                    log_records: vec![
+                        record(
+                            30,
+                            Some(any(any_value::Value::BytesValue(vec![0, 1]))),
+                            vec![
+                                kv(
+                                    "args",
+                                    any_value::Value::ArrayValue(ArrayValue {
+                                        values: vec![any(string("-v"))],
+                                    }),
+                                ),
+                                kv(
+                                    "http",
+                                    any_value::Value::KvlistValue(KeyValueList {
+                                        values: vec![kv(
+                                            "raw",
+                                            any_value::Value::BytesValue(vec![0]),
+                                        )],
+                                    }),
+                                ),
+                                kv("ok", any_value::Value::IntValue(42)),
+                            ],
+                        ),
+                        record(9, Some(any(string("fine"))), Vec::new()),
+                    ],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+        let found = strict_violations(
+            SignalType::Logs,
+            &request.encode_to_vec(),
+            InputFormat::Protobuf,
+            None,
+        );
+        assert_eq!(
+            found,
+            [
+                (
+                    "attribute 'args': arrayValue stored as JSON text".to_string(),
+                    1
+                ),
+                (
+                    "attribute 'http': kvlistValue stored as JSON text".to_string(),
+                    1
+                ),
+                (
+                    "attribute 'http.raw': bytesValue stored as text".to_string(),
+                    1
+                ),
+                ("log body: bytesValue stored as text".to_string(), 1),
+                (
+                    "log record: severityNumber 30 is not an OTLP severity".to_string(),
+                    1
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_signal() {
+        assert_eq!(
+            detect_signal(br#"{"resourceSpans":[{"resource":{}}]}"#),
+            Some(SignalType::Traces)
+        );
+        assert_eq!(
+            detect_signal(b"{\n  \"resource_metrics\": []}"),
+            Some(SignalType::Metrics)
+        );
+        // A later match, e.g. an attribute key, does not override the first
+        assert_eq!(
+            detect_signal(br#"{"resourceLogs":[{"attributes":[{"key":"resourceSpans"}]}]}"#),
+            Some(SignalType::Logs)
+        );
+        assert_eq!(detect_signal(b"\x0a\x02\x0a\x00"), None);
+    }
+
+    #[test]
+    fn test_arrow_ipc_round_trip() {
+        use arrow::array::{Int64Array, StringArray};
+        use arrow::datatypes::{DataType, Field, Schema};
+        use std::sync::Arc;
+
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("service_name", DataType::Utf8, false),
+                Field::new("count", DataType::Int64, false),
+            ])),
+            vec![
+                Arc::new(StringArray::from(vec!["checkout", "cart"])),
+                Arc::new(Int64Array::from(vec![1, 2])),
+            ],
+        )
+        .unwrap();
+        let decoded = decode_arrow_ipc(&encode_arrow_ipc(&batch).unwrap()).unwrap();
+        assert_eq!(decoded, vec![batch]);
+        assert!(decode_arrow_ipc(b"not arrow").is_err());
+    }
 }
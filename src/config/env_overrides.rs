@@ -1,8 +1,16 @@
-use super::{FsConfig, LogFormat, R2Config, RuntimeConfig, S3Config, ServerConfig, StorageBackend};
+use super::{
+    AzblobConfig, DiskFullPolicy, FileNaming, FsConfig, FsyncPolicy, GcsConfig, HdfsConfig,
+    LogFormat, ParquetCompression, R2Config, RuntimeConfig, S3Config, ServerConfig, SftpConfig,
+    StorageBackend, WebdavConfig,
+};
 use anyhow::{anyhow, Context, Result};
 
 pub const ENV_PREFIX: &str = "OTLP2PARQUET_";
 
+/// Separator of config keys in path overrides:
+/// `OTLP2PARQUET_STORAGE__S3__BUCKET` sets `storage.s3.bucket`
+const PATH_SEPARATOR: &str = "__";
+
 /// Abstraction over environment-variable lookups so runtimes without `std::env`
 /// can supply their own source of overrides.
 pub trait EnvSource {
@@ -11,9 +19,20 @@ pub trait EnvSource {
     /// Get an environment variable WITHOUT the OTLP2PARQUET_ prefix
     /// Used for AWS standard variables (AWS_ACCESS_KEY_ID, etc.)
     fn get_raw(&self, key: &str) -> Option<String>;
+
+    /// Names (without the OTLP2PARQUET_ prefix) of all variables that are set.
+    /// Path overrides need them; sources that cannot list variables return
+    /// none and support the named overrides only.
+    fn keys(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 /// Apply environment-variable overrides (highest priority) to the runtime config.
+///
+/// The named variables are shorthands for common settings; every key is
+/// reachable through `OTLP2PARQUET_<SECTION>__<KEY>` (see
+/// `apply_path_overrides`), so new settings do not get a named variable.
 pub fn apply_env_overrides<E: EnvSource>(config: &mut RuntimeConfig, env: &E) -> Result<()> {
     // Batch configuration
     if let Some(val) = get_env_usize(env, "BATCH_MAX_ROWS")? {
@@ -24,6 +43,9 @@ pub fn apply_env_overrides<E: EnvSource>(config: &mut RuntimeConfig, env: &E) ->
     if let Some(addr) = get_env_string(env, "LISTEN_ADDR")? {
         ensure_server(config).listen_addr = addr;
     }
+    if let Some(addr) = get_env_string(env, "GRPC_LISTEN_ADDR")? {
+        ensure_server(config).grpc_listen_addr = Some(addr);
+    }
     if let Some(level) = get_env_string(env, "LOG_LEVEL")? {
         ensure_server(config).log_level = level;
     }
@@ -52,6 +74,263 @@ pub fn apply_env_overrides<E: EnvSource>(config: &mut RuntimeConfig, env: &E) ->
     if let Some(val) = get_env_usize(env, "MAX_PAYLOAD_BYTES")? {
         config.request.max_payload_bytes = val;
     }
+    if let Some(val) = get_env_u64(env, "SHED_WRITE_P95_MS")? {
+        config.request.shed_write_p95_ms = Some(val);
+    }
+    if let Some(val) = get_env_usize(env, "LOGS_MAX_PAYLOAD_BYTES")? {
+        config.request.logs_max_payload_bytes = Some(val);
+    }
+    if let Some(val) = get_env_usize(env, "TRACES_MAX_PAYLOAD_BYTES")? {
+        config.request.traces_max_payload_bytes = Some(val);
+    }
+    if let Some(val) = get_env_usize(env, "METRICS_MAX_PAYLOAD_BYTES")? {
+        config.request.metrics_max_payload_bytes = Some(val);
+    }
+    if let Some(val) = get_env_usize(env, "MAX_SERIES_PER_METRIC")? {
+        config.request.max_series_per_metric = Some(val);
+    }
+    if let Some(val) = get_env_string(env, "SERIES_OVERFLOW")? {
+        config.request.series_overflow = val.parse()?;
+    }
+    if let Some(val) = get_env_string(env, "UNATTRIBUTED")? {
+        config.request.unattributed = val.parse()?;
+    }
+    if let Some(val) = get_env_string(env, "SERVICE_HEADER")? {
+        config.request.service_header = Some(val);
+    }
+    if let Some(val) = get_env_bool(env, "LENIENT_PARSING")? {
+        config.request.lenient_parsing = val;
+    }
+    if let Some(val) = get_env_bool(env, "STRICT_SCHEMA")? {
+        config.request.strict_schema = val;
+    }
+    if let Some(val) = get_env_usize(env, "METRICS_MAX_SERVICES")? {
+        config.request.metrics_max_services = val;
+    }
+
+    // Latency probe
+    if let Some(val) = get_env_bool(env, "PROBE_ENABLED")? {
+        config.probe.enabled = val;
+    }
+    if let Some(val) = get_env_u64(env, "PROBE_INTERVAL_SECS")? {
+        config.probe.interval_secs = val;
+    }
+    if let Some(val) = get_env_u64(env, "PROBE_TIMEOUT_SECS")? {
+        config.probe.timeout_secs = val;
+    }
+
+    // Derived metrics (rules are only configurable in the config file)
+    if let Some(val) = get_env_u64(env, "DERIVE_INTERVAL_SECS")? {
+        config.derive.interval_secs = val;
+    }
+    if let Some(val) = get_env_bool(env, "SPAN_METRICS_ENABLED")? {
+        config.derive.span_metrics.enabled = val;
+    }
+    if let Some(val) = get_env_bool(env, "SERVICE_GRAPH_ENABLED")? {
+        config.derive.service_graph.enabled = val;
+    }
+
+    // RUM/event records
+    if let Some(val) = get_env_bool(env, "EVENTS_ENABLED")? {
+        config.events.enabled = val;
+    }
+    if let Some(val) = get_env_string(env, "EVENTS_COLUMNS")? {
+        config.events.columns = val
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(String::from)
+            .collect();
+    }
+
+    // Local catalog
+    if let Some(val) = get_env_bool(env, "CATALOG_ENABLED")? {
+        config.catalog.enabled = val;
+    }
+    if let Some(path) = get_env_string(env, "CATALOG_PATH")? {
+        config.catalog.path = Some(path);
+    }
+
+    // Usage accounting
+    if let Some(val) = get_env_bool(env, "USAGE_ENABLED")? {
+        config.usage.enabled = val;
+    }
+    if let Some(key) = get_env_string(env, "USAGE_TENANT_ATTRIBUTE")? {
+        config.usage.tenant_attribute = Some(key);
+    }
+    if let Some(secs) = get_env_u64(env, "USAGE_FLUSH_INTERVAL_SECS")? {
+        config.usage.flush_interval_secs = secs;
+    }
+
+    // Failed request capture
+    if let Some(val) = get_env_bool(env, "CAPTURE_ENABLED")? {
+        config.capture.enabled = val;
+    }
+    if let Some(val) = get_env_usize(env, "CAPTURE_MAX_REQUESTS")? {
+        config.capture.max_requests = val;
+    }
+    if let Some(val) = get_env_usize(env, "CAPTURE_MAX_BODY_BYTES")? {
+        config.capture.max_body_bytes = val;
+    }
+    if let Some(val) = get_env_string(env, "CAPTURE_DIR")? {
+        config.capture.dir = Some(val);
+    }
+    if let Some(val) = get_env_string(env, "ADMIN_TOKEN")? {
+        config.capture.admin_token = Some(val);
+    }
+
+    // Peer forwarding
+    if let Some(val) = get_env_string(env, "CLUSTER_PEERS")? {
+        config.cluster.peers = val
+            .split(',')
+            .map(str::trim)
+            .filter(|peer| !peer.is_empty())
+            .map(String::from)
+            .collect();
+    }
+    if let Some(val) = get_env_string(env, "CLUSTER_DNS")? {
+        config.cluster.dns = Some(val);
+    }
+    if let Some(val) = get_env_string(env, "CLUSTER_SELF_URL")? {
+        config.cluster.self_url = Some(val);
+    }
+    if let Some(val) = get_env_string(env, "CLUSTER_TOKEN")? {
+        config.cluster.token = Some(val);
+    }
+
+    // Write notifications
+    if let Some(val) = get_env_string(env, "WEBHOOK_URL")? {
+        config.notifications.webhook_url = Some(val);
+    }
+    if let Some(val) = get_env_string(env, "WEBHOOK_TOKEN")? {
+        config.notifications.webhook_token = Some(val);
+    }
+
+    // Request mirroring
+    if let Some(val) = get_env_string(env, "MIRROR_ENDPOINT")? {
+        config.mirror.endpoint = Some(val);
+    }
+    if let Some(val) = get_env_string(env, "MIRROR_MODE")? {
+        config.mirror.mode = val.parse()?;
+    }
+    if let Some(val) = get_env_string(env, "MIRROR_SAMPLE_PERCENT")? {
+        config.mirror.sample_percent = val
+            .parse()
+            .map_err(|e| anyhow!("Failed to parse {}MIRROR_SAMPLE_PERCENT: {}", ENV_PREFIX, e))?;
+    }
+
+    // Replication
+    if let Some(val) = get_env_bool(env, "REPLICATION_ENABLED")? {
+        config.replication.enabled = val;
+    }
+    if let Some(path) = get_env_string(env, "REPLICATION_CATALOG_PATH")? {
+        config.replication.catalog_path = Some(path);
+    }
+
+    // Tiering
+    if let Some(val) = get_env_bool(env, "TIERING_ENABLED")? {
+        config.tiering.enabled = val;
+    }
+    if let Some(days) = get_env_u64(env, "TIERING_COLD_AFTER_DAYS")? {
+        config.tiering.cold_after_days = u32::try_from(days).map_err(|_| {
+            anyhow!(
+                "{}TIERING_COLD_AFTER_DAYS is too large: {}",
+                ENV_PREFIX,
+                days
+            )
+        })?;
+    }
+    if let Some(secs) = get_env_u64(env, "TIERING_INTERVAL_SECS")? {
+        config.tiering.interval_secs = secs;
+    }
+
+    // ClickHouse sink
+    if let Some(val) = get_env_bool(env, "CLICKHOUSE_ENABLED")? {
+        config.clickhouse.enabled = val;
+    }
+    if let Some(url) = get_env_string(env, "CLICKHOUSE_URL")? {
+        config.clickhouse.url = url;
+    }
+    if let Some(database) = get_env_string(env, "CLICKHOUSE_DATABASE")? {
+        config.clickhouse.database = database;
+    }
+    if let Some(user) = get_env_string(env, "CLICKHOUSE_USER")? {
+        config.clickhouse.username = Some(user);
+    }
+    if let Some(password) = get_env_string(env, "CLICKHOUSE_PASSWORD")? {
+        config.clickhouse.password = Some(password);
+    }
+    if let Some(prefix) = get_env_string(env, "CLICKHOUSE_TABLE_PREFIX")? {
+        config.clickhouse.table_prefix = prefix;
+    }
+
+    // Enrichment
+    if let Some(val) = get_env_string(env, "RESOURCE_ATTRIBUTES")? {
+        // Same format as OTEL_RESOURCE_ATTRIBUTES: key1=value1,key2=value2
+        for pair in val.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (key, value) = pair.split_once('=').with_context(|| {
+                format!(
+                    "Invalid OTLP2PARQUET_RESOURCE_ATTRIBUTES entry '{}': expected key=value",
+                    pair
+                )
+            })?;
+            config
+                .enrichment
+                .resource_attributes
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    if let Some(val) = get_env_bool(env, "K8S_ENRICHMENT_ENABLED")? {
+        config.enrichment.kubernetes.enabled = val;
+    }
+    if let Some(val) = get_env_string(env, "K8S_NODE_NAME")? {
+        config.enrichment.kubernetes.node_name = Some(val);
+    }
+    if let Some(val) = get_env_bool(env, "GEOIP_ENABLED")? {
+        config.enrichment.geoip.enabled = val;
+    }
+    if let Some(val) = get_env_string(env, "GEOIP_CITY_DATABASE")? {
+        config.enrichment.geoip.city_database = Some(val);
+    }
+    if let Some(val) = get_env_string(env, "GEOIP_ASN_DATABASE")? {
+        config.enrichment.geoip.asn_database = Some(val);
+    }
+    if let Some(val) = get_env_string(env, "BAGGAGE_KEYS")? {
+        config.enrichment.baggage_keys = val
+            .split(',')
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .collect();
+    }
+
+    // Output schema
+    if let Some(val) = get_env_string(env, "LOGS_TIMESTAMP_UNIT")? {
+        config.schema.logs_timestamp_unit = val
+            .parse()
+            .context("Invalid OTLP2PARQUET_LOGS_TIMESTAMP_UNIT value")?;
+    }
+    if let Some(val) = get_env_string(env, "TRACES_TIMESTAMP_UNIT")? {
+        config.schema.traces_timestamp_unit = val
+            .parse()
+            .context("Invalid OTLP2PARQUET_TRACES_TIMESTAMP_UNIT value")?;
+    }
+    if let Some(val) = get_env_string(env, "METRICS_TIMESTAMP_UNIT")? {
+        config.schema.metrics_timestamp_unit = val
+            .parse()
+            .context("Invalid OTLP2PARQUET_METRICS_TIMESTAMP_UNIT value")?;
+    }
+    if let Some(val) = get_env_bool(env, "SAMPLING_COLUMNS")? {
+        config.schema.sampling_columns = val;
+    }
+    if let Some(val) = get_env_bool(env, "NORMALIZE_SEVERITY")? {
+        config.schema.normalize_severity = val;
+    }
+    if let Some(val) = get_env_bool(env, "SPAN_TEXT_COLUMNS")? {
+        config.schema.span_text_columns = val;
+    }
+    if let Some(val) = get_env_bool(env, "PROVENANCE_COLUMNS")? {
+        config.schema.provenance_columns = val;
+    }
 
     // Storage backend
     if let Some(backend) = get_env_string(env, "STORAGE_BACKEND")? {
@@ -68,6 +347,80 @@ pub fn apply_env_overrides<E: EnvSource>(config: &mut RuntimeConfig, env: &E) ->
             fs.path = path;
         }
     }
+    if let Some(val) = get_env_bool(env, "STORAGE_ATOMIC_WRITES")? {
+        config
+            .storage
+            .fs
+            .get_or_insert_with(FsConfig::default)
+            .atomic_writes = val;
+    }
+    if let Some(val) = get_env_string(env, "STORAGE_FSYNC")? {
+        config
+            .storage
+            .fs
+            .get_or_insert_with(FsConfig::default)
+            .fsync = val
+            .parse::<FsyncPolicy>()
+            .context("Invalid OTLP2PARQUET_STORAGE_FSYNC value")?;
+    }
+    if let Some(val) = get_env_u64(env, "STORAGE_MAX_DISK_BYTES")? {
+        config
+            .storage
+            .fs
+            .get_or_insert_with(FsConfig::default)
+            .max_disk_bytes = Some(val);
+    }
+    if let Some(val) = get_env_string(env, "STORAGE_ON_DISK_FULL")? {
+        config
+            .storage
+            .fs
+            .get_or_insert_with(FsConfig::default)
+            .on_disk_full = val
+            .parse::<DiskFullPolicy>()
+            .context("Invalid OTLP2PARQUET_STORAGE_ON_DISK_FULL value")?;
+    }
+
+    if let Some(val) = get_env_bool(env, "STORAGE_VERIFY_AFTER_WRITE")? {
+        config.storage.verify_after_write = val;
+    }
+    if let Some(val) = get_env_bool(env, "STORAGE_WRITE_MANIFESTS")? {
+        config.storage.write_manifests = val;
+    }
+    if let Some(naming) = get_env_string(env, "FILE_NAMING")? {
+        config.storage.file_naming = naming
+            .parse::<FileNaming>()
+            .context("Invalid OTLP2PARQUET_FILE_NAMING value")?;
+    }
+    if let Some(compression) = get_env_string(env, "PARQUET_COMPRESSION")? {
+        config.storage.parquet_compression = compression
+            .parse::<ParquetCompression>()
+            .context("Invalid OTLP2PARQUET_PARQUET_COMPRESSION value")?;
+    }
+    if let Some(level) = get_env_usize(env, "PARQUET_COMPRESSION_LEVEL")? {
+        config.storage.parquet_compression_level = Some(u32::try_from(level).map_err(|_| {
+            anyhow!(
+                "{}PARQUET_COMPRESSION_LEVEL is too large: {}",
+                ENV_PREFIX,
+                level
+            )
+        })?);
+    }
+    if let Some(val) = get_env_bool(env, "PARQUET_BLOOM_FILTERS")? {
+        config.storage.parquet_bloom_filters = val;
+    }
+    if let Some(shards) = get_env_usize(env, "WRITE_SHARDS")? {
+        config.storage.write_shards = u16::try_from(shards)
+            .map_err(|_| anyhow!("{}WRITE_SHARDS is too large: {}", ENV_PREFIX, shards))?;
+    }
+    if let Some(retries) = get_env_usize(env, "STORAGE_WRITE_RETRIES")? {
+        config.storage.write_retries = u32::try_from(retries).map_err(|_| {
+            anyhow!(
+                "{}STORAGE_WRITE_RETRIES is too large: {}",
+                ENV_PREFIX,
+                retries
+            )
+        })?;
+    }
 
     // S3 storage
     if let Some(bucket) = get_env_string(env, "S3_BUCKET")? {
@@ -79,6 +432,9 @@ pub fn apply_env_overrides<E: EnvSource>(config: &mut RuntimeConfig, env: &E) ->
     if let Some(endpoint) = get_env_string(env, "S3_ENDPOINT")? {
         ensure_s3(config).endpoint = Some(endpoint);
     }
+    if let Some(class) = get_env_string(env, "S3_STORAGE_CLASS")? {
+        ensure_s3(config).storage_class = Some(class);
+    }
     if let Some(prefix) = get_env_string(env, "S3_PREFIX")? {
         ensure_s3(config).prefix = normalize_prefix(prefix);
     }
@@ -108,15 +464,234 @@ pub fn apply_env_overrides<E: EnvSource>(config: &mut RuntimeConfig, env: &E) ->
         ensure_r2(config).prefix = normalize_prefix(prefix);
     }
 
+    // SFTP storage
+    if let Some(endpoint) = get_env_string(env, "SFTP_ENDPOINT")? {
+        ensure_sftp(config).endpoint = endpoint;
+    }
+    if let Some(root) = get_env_string(env, "SFTP_ROOT")? {
+        ensure_sftp(config).root = root;
+    }
+    if let Some(user) = get_env_string(env, "SFTP_USER")? {
+        ensure_sftp(config).user = Some(user);
+    }
+    if let Some(key) = get_env_string(env, "SFTP_KEY")? {
+        ensure_sftp(config).key = Some(key);
+    }
+    if let Some(known_hosts) = get_env_string(env, "SFTP_KNOWN_HOSTS")? {
+        ensure_sftp(config).known_hosts = known_hosts.to_lowercase();
+    }
+
+    // WebDAV storage
+    if let Some(endpoint) = get_env_string(env, "WEBDAV_ENDPOINT")? {
+        ensure_webdav(config).endpoint = endpoint;
+    }
+    if let Some(root) = get_env_string(env, "WEBDAV_ROOT")? {
+        ensure_webdav(config).root = root;
+    }
+    if let Some(username) = get_env_string(env, "WEBDAV_USERNAME")? {
+        ensure_webdav(config).username = Some(username);
+    }
+    if let Some(password) = get_env_string(env, "WEBDAV_PASSWORD")? {
+        ensure_webdav(config).password = Some(password);
+    }
+    if let Some(token) = get_env_string(env, "WEBDAV_TOKEN")? {
+        ensure_webdav(config).token = Some(token);
+    }
+
+    // HDFS storage
+    if let Some(endpoint) = get_env_string(env, "HDFS_ENDPOINT")? {
+        ensure_hdfs(config).endpoint = endpoint;
+    }
+    if let Some(root) = get_env_string(env, "HDFS_ROOT")? {
+        ensure_hdfs(config).root = root;
+    }
+    if let Some(user) = get_env_string(env, "HDFS_USER")? {
+        ensure_hdfs(config).user = Some(user);
+    }
+    if let Some(token) = get_env_string(env, "HDFS_DELEGATION_TOKEN")? {
+        ensure_hdfs(config).delegation_token = Some(token);
+    }
+    if let Some(val) = get_env_bool(env, "HDFS_DISABLE_LIST_BATCH")? {
+        ensure_hdfs(config).disable_list_batch = val;
+    }
+
+    // GCS storage
+    if let Some(bucket) = get_env_string(env, "GCS_BUCKET")? {
+        ensure_gcs(config).bucket = bucket;
+    }
+    if let Some(path) = get_env_string(env, "GCS_CREDENTIAL_PATH")? {
+        ensure_gcs(config).credential_path = Some(path);
+    }
+    if let Some(credential) = get_env_string(env, "GCS_CREDENTIAL")? {
+        ensure_gcs(config).credential = Some(credential);
+    }
+    if let Some(endpoint) = get_env_string(env, "GCS_ENDPOINT")? {
+        ensure_gcs(config).endpoint = Some(endpoint);
+    }
+    if let Some(prefix) = get_env_string(env, "GCS_PREFIX")? {
+        ensure_gcs(config).prefix = normalize_prefix(prefix);
+    }
+
+    // Azure Blob storage
+    if let Some(container) = get_env_string(env, "AZBLOB_CONTAINER")? {
+        ensure_azblob(config).container = container;
+    }
+    if let Some(account_name) = get_env_string(env, "AZBLOB_ACCOUNT_NAME")? {
+        ensure_azblob(config).account_name = account_name;
+    }
+    if let Some(key) = get_env_string(env, "AZBLOB_ACCOUNT_KEY")? {
+        ensure_azblob(config).account_key = Some(key);
+    }
+    if let Some(token) = get_env_string(env, "AZBLOB_SAS_TOKEN")? {
+        ensure_azblob(config).sas_token = Some(token);
+    }
+    if let Some(endpoint) = get_env_string(env, "AZBLOB_ENDPOINT")? {
+        ensure_azblob(config).endpoint = Some(endpoint);
+    }
+    if let Some(prefix) = get_env_string(env, "AZBLOB_PREFIX")? {
+        ensure_azblob(config).prefix = normalize_prefix(prefix);
+    }
+
+    apply_path_overrides(config, env)?;
+
     Ok(())
 }
 
+/// Apply `OTLP2PARQUET_<SECTION>__<KEY>` overrides, which reach every config
+/// key, after the named variables. Values are TOML literals (`100`, `true`,
+/// `["a", "b"]`); keys holding strings take the value verbatim.
+fn apply_path_overrides<E: EnvSource>(config: &mut RuntimeConfig, env: &E) -> Result<()> {
+    let mut keys: Vec<String> = env
+        .keys()
+        .into_iter()
+        .filter(|key| key.contains(PATH_SEPARATOR))
+        .collect();
+    if keys.is_empty() {
+        return Ok(());
+    }
+    keys.sort();
+
+    let mut tree = toml::Value::try_from(&*config).context("Failed to serialize configuration")?;
+    let mut paths = Vec::with_capacity(keys.len());
+    // Unset options are not serialized, so there is no value to tell whether
+    // they hold strings; their literals are retried as strings if rejected
+    let mut untyped = Vec::new();
+    for key in keys {
+        let Some(raw) = env.get(&key) else {
+            continue;
+        };
+        let path: Vec<String> = key
+            .split(PATH_SEPARATOR)
+            .map(str::to_ascii_lowercase)
+            .collect();
+        let set = set_path(&mut tree, &path, &raw, false)
+            .with_context(|| format!("Invalid {}{}", ENV_PREFIX, key))?;
+        if set == Set::UntypedLiteral {
+            untyped.push((path.clone(), raw));
+        }
+        paths.push((key, path));
+    }
+    *config = loop {
+        let error = match tree.clone().try_into() {
+            Ok(config) => break config,
+            Err(e) => e,
+        };
+        let rejected = error_key(&error);
+        let Some(position) = untyped
+            .iter()
+            .position(|(path, _)| Some(path.join(".")) == rejected)
+        else {
+            return Err(error).context(
+                "Invalid configuration after applying OTLP2PARQUET_<SECTION>__<KEY> overrides",
+            );
+        };
+        let (path, raw) = untyped.swap_remove(position);
+        set_path(&mut tree, &path, &raw, true)?;
+    };
+
+    // Keys the config does not know are dropped when deserializing
+    let applied = toml::Value::try_from(&*config).context("Failed to serialize configuration")?;
+    for (key, path) in paths {
+        if path
+            .iter()
+            .try_fold(&applied, |node, key| node.get(key.as_str()))
+            .is_none()
+        {
+            return Err(anyhow!(
+                "{}{} does not match a configuration key ({})",
+                ENV_PREFIX,
+                key,
+                path.join(".")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// How `set_path` stored a value
+#[derive(Debug, PartialEq, Eq)]
+enum Set {
+    /// As a string, verbatim
+    String,
+    /// As the TOML literal, the key holding a value of that type
+    Literal,
+    /// As a non-string TOML literal for a key with no current value
+    UntypedLiteral,
+}
+
+/// Set `path` in `tree` to `raw`, creating missing sections. `raw` is a TOML
+/// literal unless the key holds a string or `as_string` is set.
+fn set_path(tree: &mut toml::Value, path: &[String], raw: &str, as_string: bool) -> Result<Set> {
+    let (last, sections) = path
+        .split_last()
+        .filter(|_| path.iter().all(|key| !key.is_empty()))
+        .ok_or_else(|| anyhow!("empty key in path"))?;
+    let mut node = tree;
+    for section in sections {
+        node = node
+            .as_table_mut()
+            .ok_or_else(|| anyhow!("'{}' is not inside a section", section))?
+            .entry(section.clone())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    }
+    let table = node
+        .as_table_mut()
+        .ok_or_else(|| anyhow!("'{}' is not inside a section", last))?;
+    let literal = match table.get(last) {
+        _ if as_string => None,
+        Some(toml::Value::String(_)) => None,
+        current => format!("v = {}", raw)
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|mut parsed| parsed.remove("v"))
+            .filter(|value| !value.is_str())
+            .map(|value| (value, current.is_none())),
+    };
+    let (value, set) = match literal {
+        Some((value, true)) => (value, Set::UntypedLiteral),
+        Some((value, false)) => (value, Set::Literal),
+        None => (toml::Value::String(raw.to_string()), Set::String),
+    };
+    table.insert(last.clone(), value);
+    Ok(set)
+}
+
+/// Dotted key a deserialization error points at (`storage.s3.prefix`)
+fn error_key(error: &toml::de::Error) -> Option<String> {
+    error.to_string().lines().find_map(|line| {
+        line.strip_prefix("in `")
+            .and_then(|key| key.strip_suffix('`'))
+            .map(String::from)
+    })
+}
+
 fn ensure_s3(config: &mut RuntimeConfig) -> &mut S3Config {
     config.storage.s3.get_or_insert_with(|| S3Config {
         bucket: String::new(),
         region: String::new(),
         endpoint: None,
         prefix: None,
+        storage_class: None,
     })
 }
 
@@ -131,6 +706,58 @@ fn ensure_r2(config: &mut RuntimeConfig) -> &mut R2Config {
     })
 }
 
+fn ensure_sftp(config: &mut RuntimeConfig) -> &mut SftpConfig {
+    config.storage.sftp.get_or_insert_with(|| SftpConfig {
+        endpoint: String::new(),
+        root: "/".to_string(),
+        user: None,
+        key: None,
+        known_hosts: "strict".to_string(),
+    })
+}
+
+fn ensure_webdav(config: &mut RuntimeConfig) -> &mut WebdavConfig {
+    config.storage.webdav.get_or_insert_with(|| WebdavConfig {
+        endpoint: String::new(),
+        root: "/".to_string(),
+        username: None,
+        password: None,
+        token: None,
+    })
+}
+
+fn ensure_hdfs(config: &mut RuntimeConfig) -> &mut HdfsConfig {
+    config.storage.hdfs.get_or_insert_with(|| HdfsConfig {
+        endpoint: String::new(),
+        root: "/".to_string(),
+        user: None,
+        delegation_token: None,
+        disable_list_batch: false,
+    })
+}
+
+fn ensure_gcs(config: &mut RuntimeConfig) -> &mut GcsConfig {
+    config.storage.gcs.get_or_insert_with(|| GcsConfig {
+        bucket: String::new(),
+        credential_path: None,
+        credential: None,
+        endpoint: None,
+        allow_anonymous: false,
+        prefix: None,
+    })
+}
+
+fn ensure_azblob(config: &mut RuntimeConfig) -> &mut AzblobConfig {
+    config.storage.azblob.get_or_insert_with(|| AzblobConfig {
+        container: String::new(),
+        account_name: String::new(),
+        account_key: None,
+        sas_token: None,
+        endpoint: None,
+        prefix: None,
+    })
+}
+
 fn ensure_server(config: &mut RuntimeConfig) -> &mut ServerConfig {
     config.server.get_or_insert_with(ServerConfig::default)
 }
@@ -195,3 +822,75 @@ fn normalize_prefix(prefix: String) -> Option<String> {
         Some(format!("{}/", prefix))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Platform;
+    use std::collections::HashMap;
+
+    struct MapEnv(HashMap<String, String>);
+
+    impl EnvSource for MapEnv {
+        fn get(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn get_raw(&self, _key: &str) -> Option<String> {
+            None
+        }
+
+        fn keys(&self) -> Vec<String> {
+            self.0.keys().cloned().collect()
+        }
+    }
+
+    fn env(vars: &[(&str, &str)]) -> MapEnv {
+        MapEnv(
+            vars.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_path_overrides_reach_any_key() {
+        let mut config = RuntimeConfig::from_platform_defaults(Platform::Server);
+        apply_env_overrides(
+            &mut config,
+            &env(&[
+                ("BATCH__MAX_AGE_SECS", "42"),
+                ("STORAGE__VERIFY_AFTER_WRITE", "true"),
+                ("CLUSTER__PEERS", r#"["http://a:4318", "http://b:4318"]"#),
+                ("SERVER__LOG_LEVEL", "123"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.batch.max_age_secs, 42);
+        assert!(config.storage.verify_after_write);
+        assert_eq!(config.cluster.peers.len(), 2);
+        assert_eq!(config.server.as_ref().unwrap().log_level, "123");
+
+        // Unset options take numbers as numbers, or as strings if they hold strings
+        apply_env_overrides(
+            &mut config,
+            &env(&[
+                ("CLUSTER__TOKEN", "123456"),
+                ("STORAGE__S3__BUCKET", "logs"),
+                ("STORAGE__S3__REGION", "us-east-1"),
+                ("STORAGE__S3__PREFIX", "2025"),
+                ("REQUEST__MAX_SERIES_PER_METRIC", "500"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.cluster.token.as_deref(), Some("123456"));
+        assert_eq!(
+            config.storage.s3.as_ref().unwrap().prefix.as_deref(),
+            Some("2025")
+        );
+        assert_eq!(config.request.max_series_per_metric, Some(500));
+
+        let unknown = apply_env_overrides(&mut config, &env(&[("BATCH__MAX_ROWZ", "1")]));
+        assert!(unknown.unwrap_err().to_string().contains("batch.max_rowz"));
+    }
+}
@@ -0,0 +1,126 @@
+// Config file includes and profiles
+//
+// A config file may list other files to build on with `include = [...]`
+// (paths relative to the including file) and define named overrides in
+// `[profile.<name>]` sections, selected with OTLP2PARQUET_PROFILE. Layers are
+// merged key by key, later layers winning:
+//
+//   included files (in list order) < the file itself < the selected profile
+//
+// Environment variables and CLI flags still apply on top. Arrays replace
+// rather than extend the value below them.
+
+use super::RuntimeConfig;
+use anyhow::{bail, Context, Result};
+use toml::{Table, Value};
+
+/// Variable (after the OTLP2PARQUET_ prefix) naming the profile to apply
+pub(crate) const PROFILE_ENV: &str = "PROFILE";
+
+/// Remove and return the `include` list of `table`.
+pub(crate) fn take_includes(table: &mut Table) -> Result<Vec<String>> {
+    match table.remove("include") {
+        None => Ok(Vec::new()),
+        Some(Value::Array(paths)) => paths
+            .into_iter()
+            .map(|path| match path {
+                Value::String(path) => Ok(path),
+                other => bail!("include entries must be file paths, got {}", other),
+            })
+            .collect(),
+        Some(other) => bail!(
+            "include must be a list of file paths, e.g. include = [\"base.toml\"], got {}",
+            other
+        ),
+    }
+}
+
+/// Merge `overlay` into `base`: sections merge recursively, any other value
+/// in `overlay` replaces the one in `base`.
+pub(crate) fn merge_tables(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(below)), Value::Table(above)) => merge_tables(below, above),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Apply the `profile` section named `profile` (if any) and deserialize.
+pub(crate) fn finish(mut table: Table, profile: Option<&str>) -> Result<RuntimeConfig> {
+    let profiles = match table.remove("profile") {
+        None => Table::new(),
+        Some(Value::Table(profiles)) => profiles,
+        Some(_) => bail!("profile must only contain [profile.<name>] sections"),
+    };
+    if let Some(name) = profile.filter(|name| !name.is_empty()) {
+        match profiles.get(name) {
+            Some(Value::Table(overrides)) => merge_tables(&mut table, overrides.clone()),
+            Some(_) => bail!("profile.{} must be a section", name),
+            None => {
+                let defined: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                bail!(
+                    "Unknown config profile '{}' (defined: {})",
+                    name,
+                    if defined.is_empty() {
+                        "none".to_string()
+                    } else {
+                        defined.join(", ")
+                    }
+                );
+            }
+        }
+    }
+    Value::Table(table)
+        .try_into()
+        .context("Invalid configuration")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_includes_and_profiles_layer_in_order() {
+        let mut base: Table = toml::from_str(
+            r#"
+            [storage]
+            backend = "fs"
+
+            [batch]
+            max_rows = 1000
+            max_bytes = 1048576
+            max_age_secs = 10
+
+            [profile.production.batch]
+            max_age_secs = 60
+            "#,
+        )
+        .unwrap();
+        let mut file: Table = toml::from_str(
+            r#"
+            include = ["base.toml"]
+
+            [batch]
+            max_rows = 5000
+            "#,
+        )
+        .unwrap();
+        assert!(take_includes(&mut base).unwrap().is_empty());
+        assert_eq!(take_includes(&mut file).unwrap(), vec!["base.toml"]);
+        merge_tables(&mut base, file);
+
+        let default = finish(base.clone(), None).unwrap();
+        assert_eq!(default.batch.max_rows, 5000);
+        assert_eq!(default.batch.max_age_secs, 10);
+
+        let production = finish(base.clone(), Some("production")).unwrap();
+        assert_eq!(production.batch.max_rows, 5000);
+        assert_eq!(production.batch.max_age_secs, 60);
+
+        let unknown = finish(base, Some("staging")).unwrap_err().to_string();
+        assert!(unknown.contains("defined: production"));
+    }
+}
@@ -6,75 +6,1014 @@
 // 3. Config file contents from OTLP2PARQUET_CONFIG_CONTENT env var
 // 4. Default config file locations (./config.toml, ./.otlp2parquet.toml)
 // 5. Platform-specific defaults (lowest priority)
+//
+// Config files may build on others (`include`) and carry named profiles
+// (`[profile.<name>]`, selected with OTLP2PARQUET_PROFILE); see layers.rs.
+
+use crate::types::SignalType;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+mod env_overrides;
+mod layers;
+mod platform;
+#[cfg(not(target_arch = "wasm32"))]
+mod sources;
+mod validation;
+
+pub use env_overrides::{EnvSource, ENV_PREFIX};
+pub use platform::Platform;
+
+/// Main runtime configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeConfig {
+    #[serde(default)]
+    pub batch: BatchConfig,
+
+    #[serde(default)]
+    pub request: RequestConfig,
+
+    pub storage: StorageConfig,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<ServerConfig>,
+
+    #[serde(default)]
+    pub probe: ProbeConfig,
+
+    #[serde(default)]
+    pub derive: DeriveConfig,
+
+    #[serde(default)]
+    pub events: EventsConfig,
+
+    #[serde(default)]
+    pub enrichment: EnrichmentConfig,
+
+    #[serde(default)]
+    pub schema: SchemaConfig,
+
+    #[serde(default)]
+    pub catalog: CatalogConfig,
+
+    #[serde(default)]
+    pub clickhouse: ClickHouseConfig,
+
+    /// Rules sending matching signal batches to other storage targets; the
+    /// first matching rule wins, unmatched rows go to `storage`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routes: Vec<RouteConfig>,
+
+    /// Storage targets tried in order when a write to `storage` fails
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<FallbackConfig>,
+
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+
+    #[serde(default)]
+    pub tiering: TieringConfig,
+
+    #[serde(default)]
+    pub usage: UsageConfig,
+
+    #[serde(default)]
+    pub capture: CaptureConfig,
+
+    #[serde(default)]
+    pub cluster: ClusterConfig,
+
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+
+    #[serde(default)]
+    pub mirror: MirrorConfig,
+}
+
+/// Batch configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchConfig {
+    pub max_rows: usize,
+    pub max_bytes: usize,
+    pub max_age_secs: u64,
+    #[serde(default = "default_batching_enabled")]
+    pub enabled: bool,
+}
+
+fn default_batching_enabled() -> bool {
+    true
+}
+
+impl BatchConfig {}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_rows: 200_000,
+            max_bytes: 128 * 1024 * 1024,
+            max_age_secs: 10,
+            enabled: true,
+        }
+    }
+}
+
+/// Request handling configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestConfig {
+    pub max_payload_bytes: usize,
+    /// Reject requests with 429 while the rolling p95 storage write latency
+    /// exceeds this many milliseconds. Unset disables load shedding.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shed_write_p95_ms: Option<u64>,
+    /// Per-signal overrides of `max_payload_bytes`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logs_max_payload_bytes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traces_max_payload_bytes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_max_payload_bytes: Option<usize>,
+    /// Maximum distinct attribute sets per metric name per flush window.
+    /// Unset disables the cardinality guard.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_series_per_metric: Option<usize>,
+    /// What to do with data points beyond `max_series_per_metric`
+    #[serde(default)]
+    pub series_overflow: SeriesOverflow,
+    /// What to do with records whose resource has no `service.name`
+    #[serde(default)]
+    pub unattributed: UnattributedPolicy,
+    /// Request header naming the service of unattributed records (`infer`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_header: Option<String>,
+    /// Services by `service_header` value (e.g. per API key); when empty the
+    /// header value is the service name itself
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub header_services: BTreeMap<String, String>,
+    /// Accept JSON payloads with enum names from newer OTLP versions: unknown
+    /// values become UNSPECIFIED and the raw name is kept as an
+    /// `otlp.unknown.<field>` attribute
+    #[serde(default)]
+    pub lenient_parsing: bool,
+    /// Reject payloads that would be stored lossily (skipped data points,
+    /// attribute values coerced to another type) instead of converting them
+    #[serde(default)]
+    pub strict_schema: bool,
+    /// Services that get their own label on per-service request metrics;
+    /// later services share the `_other` label
+    #[serde(default = "default_metrics_max_services")]
+    pub metrics_max_services: usize,
+}
+
+fn default_metrics_max_services() -> usize {
+    50
+}
+
+impl RequestConfig {
+    /// Effective payload limit for `signal`
+    pub fn max_payload_bytes_for(&self, signal: SignalType) -> usize {
+        let override_bytes = match signal {
+            SignalType::Logs => self.logs_max_payload_bytes,
+            SignalType::Traces => self.traces_max_payload_bytes,
+            SignalType::Metrics => self.metrics_max_payload_bytes,
+        };
+        override_bytes.unwrap_or(self.max_payload_bytes)
+    }
+}
+
+impl Default for RequestConfig {
+    fn default() -> Self {
+        Self {
+            max_payload_bytes: 8 * 1024 * 1024,
+            shed_write_p95_ms: None,
+            logs_max_payload_bytes: None,
+            traces_max_payload_bytes: None,
+            metrics_max_payload_bytes: None,
+            max_series_per_metric: None,
+            series_overflow: SeriesOverflow::default(),
+            unattributed: UnattributedPolicy::default(),
+            service_header: None,
+            header_services: BTreeMap::new(),
+            lenient_parsing: false,
+            strict_schema: false,
+            metrics_max_services: default_metrics_max_services(),
+        }
+    }
+}
+
+/// Handling of metric data points for series over the cardinality limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeriesOverflow {
+    /// Drop the offending data points and accept the rest of the request
+    #[default]
+    Drop,
+    /// Reject the whole request with 400
+    Reject,
+}
+
+impl std::str::FromStr for SeriesOverflow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "drop" => Ok(SeriesOverflow::Drop),
+            "reject" => Ok(SeriesOverflow::Reject),
+            _ => anyhow::bail!(
+                "Unsupported series overflow mode: {}. Supported: drop, reject",
+                s
+            ),
+        }
+    }
+}
+
+/// Handling of records without a `service.name` resource attribute, which
+/// the decoder otherwise files under the `unknown` service
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnattributedPolicy {
+    /// Write them under the `unknown` service
+    #[default]
+    Keep,
+    /// Reject the whole request with 400
+    Reject,
+    /// Write them to the `_unattributed` table
+    Table,
+    /// Take the service from `service_header`; records it cannot attribute go
+    /// to the `_unattributed` table
+    Infer,
+}
+
+impl std::str::FromStr for UnattributedPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "keep" => Ok(UnattributedPolicy::Keep),
+            "reject" => Ok(UnattributedPolicy::Reject),
+            "table" => Ok(UnattributedPolicy::Table),
+            "infer" => Ok(UnattributedPolicy::Infer),
+            _ => anyhow::bail!(
+                "Unsupported unattributed policy: {}. Supported: keep, reject, table, infer",
+                s
+            ),
+        }
+    }
+}
+
+/// Output schema options applied when writing the signal tables
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchemaConfig {
+    #[serde(default)]
+    pub logs_timestamp_unit: TimestampUnit,
+    #[serde(default)]
+    pub traces_timestamp_unit: TimestampUnit,
+    #[serde(default)]
+    pub metrics_timestamp_unit: TimestampUnit,
+    /// Add `sampling_probability` and `sampling_adjusted_count` columns to
+    /// logs and traces, from sampling attributes and the W3C trace state
+    #[serde(default)]
+    pub sampling_columns: bool,
+    /// Derive, clamp and backfill log `severity_number`/`severity_text` per
+    /// the OpenTelemetry log data model
+    #[serde(default)]
+    pub normalize_severity: bool,
+    /// Add `span_kind_text` and `status_code_text` columns to traces
+    #[serde(default)]
+    pub span_text_columns: bool,
+    /// Add `ingest_timestamp`, `ingest_host`, `ingest_runtime` and
+    /// `pipeline_version` columns to ingested logs, traces and metrics
+    #[serde(default)]
+    pub provenance_columns: bool,
+}
+
+impl SchemaConfig {
+    pub fn timestamp_unit_for(&self, signal: SignalType) -> TimestampUnit {
+        match signal {
+            SignalType::Logs => self.logs_timestamp_unit,
+            SignalType::Traces => self.traces_timestamp_unit,
+            SignalType::Metrics => self.metrics_timestamp_unit,
+        }
+    }
+}
+
+/// Precision of timestamp columns in written Parquet files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampUnit {
+    #[serde(alias = "ms")]
+    Millisecond,
+    #[default]
+    #[serde(alias = "us")]
+    Microsecond,
+    #[serde(alias = "ns")]
+    Nanosecond,
+}
+
+impl std::fmt::Display for TimestampUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimestampUnit::Millisecond => write!(f, "millisecond"),
+            TimestampUnit::Microsecond => write!(f, "microsecond"),
+            TimestampUnit::Nanosecond => write!(f, "nanosecond"),
+        }
+    }
+}
+
+impl std::str::FromStr for TimestampUnit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "millisecond" | "ms" => Ok(TimestampUnit::Millisecond),
+            "microsecond" | "us" => Ok(TimestampUnit::Microsecond),
+            "nanosecond" | "ns" => Ok(TimestampUnit::Nanosecond),
+            _ => anyhow::bail!(
+                "Unsupported timestamp unit: {}. Supported: millisecond, microsecond, nanosecond",
+                s
+            ),
+        }
+    }
+}
+
+/// End-to-end latency probe configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeConfig {
+    /// Periodically inject a marked log record and time until it is in storage
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_probe_interval_secs")]
+    pub interval_secs: u64,
+    /// Give up on a probe record after this long and count a timeout
+    #[serde(default = "default_probe_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_probe_interval_secs() -> u64 {
+    60
+}
+
+fn default_probe_timeout_secs() -> u64 {
+    300
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_probe_interval_secs(),
+            timeout_secs: default_probe_timeout_secs(),
+        }
+    }
+}
+
+/// Metrics derived from other signals during ingestion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeriveConfig {
+    /// Aggregation window; derived data points are emitted once per window
+    #[serde(default = "default_derive_interval_secs")]
+    pub interval_secs: u64,
+    /// Sum metrics computed from matching log records
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_metrics: Vec<LogMetricRule>,
+    /// Request/error/duration metrics aggregated from spans
+    #[serde(default)]
+    pub span_metrics: SpanMetricsConfig,
+    /// Service dependency edges written to the `service_graph` table
+    #[serde(default)]
+    pub service_graph: ServiceGraphConfig,
+}
+
+fn default_derive_interval_secs() -> u64 {
+    60
+}
+
+impl Default for DeriveConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: default_derive_interval_secs(),
+            log_metrics: Vec::new(),
+            span_metrics: SpanMetricsConfig::default(),
+            service_graph: ServiceGraphConfig::default(),
+        }
+    }
+}
+
+/// Service graph: client/server span pairs aggregated into edges per window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceGraphConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Client span attributes naming an uninstrumented peer (database, queue,
+    /// external API), tried in order
+    #[serde(default = "default_peer_attributes")]
+    pub peer_attributes: Vec<String>,
+}
+
+fn default_peer_attributes() -> Vec<String> {
+    vec![
+        "peer.service".to_string(),
+        "db.system".to_string(),
+        "messaging.system".to_string(),
+        "server.address".to_string(),
+    ]
+}
+
+impl Default for ServiceGraphConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            peer_attributes: default_peer_attributes(),
+        }
+    }
+}
+
+/// RUM/event log records (`event.name`) routed to the `events` table
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Event attributes promoted to their own string columns; dots and other
+    /// non-alphanumeric characters become underscores (`url.full` -> `url_full`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+}
+
+/// Local SQLite catalog of written files, for single-node / desktop use
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CatalogConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// SQLite file; defaults to `_catalog.sqlite` under the filesystem storage path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl CatalogConfig {
+    /// Catalog file location, if one can be determined for this storage setup
+    pub fn resolved_path(&self, storage: &StorageConfig) -> Option<std::path::PathBuf> {
+        match (&self.path, storage.backend, &storage.fs) {
+            (Some(path), _, _) => Some(path.into()),
+            (None, StorageBackend::Fs, Some(fs)) => {
+                Some(std::path::Path::new(&fs.path).join("_catalog.sqlite"))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Storage target for signal rows matching a set of predicates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteConfig {
+    /// Identifies the route in logs and metrics
+    pub name: String,
+    /// Signals the route applies to (`logs`, `traces`, `metrics`); empty means all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signals: Vec<String>,
+    /// Service name pattern; `*` matches any run of characters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// Resource attributes every routed row must carry; a value of `*` only
+    /// requires the attribute to be present
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub resource_attributes: BTreeMap<String, String>,
+    /// Backend, bucket and prefix to write to; write options (verification,
+    /// manifests, naming) still come from the top-level `[storage]`
+    pub storage: StorageConfig,
+}
+
+/// Per service/tenant accounting of stored rows, bytes and PUTs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Resource attribute naming the tenant (e.g. `tenant.id`); without it
+    /// usage is tracked per service only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_attribute: Option<String>,
+    /// How often accumulated usage is written to the `_usage` table
+    #[serde(default = "default_usage_flush_interval_secs")]
+    pub flush_interval_secs: u64,
+}
+
+fn default_usage_flush_interval_secs() -> u64 {
+    300
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tenant_attribute: None,
+            flush_interval_secs: default_usage_flush_interval_secs(),
+        }
+    }
+}
+
+/// Ring buffer of failed request bodies for reproducing ingestion errors,
+/// downloadable from `/admin/captures`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Failed requests kept; the oldest is dropped first
+    #[serde(default = "default_capture_max_requests")]
+    pub max_requests: usize,
+    /// Bodies larger than this (before compression) are not captured
+    #[serde(default = "default_capture_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Keep captures as files in this directory instead of in memory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    /// Bearer token required by the admin endpoints
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin_token: Option<String>,
+}
+
+fn default_capture_max_requests() -> usize {
+    20
+}
+
+fn default_capture_max_body_bytes() -> usize {
+    1024 * 1024
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_requests: default_capture_max_requests(),
+            max_body_bytes: default_capture_max_body_bytes(),
+            dir: None,
+            admin_token: None,
+        }
+    }
+}
+
+/// Peer forwarding between replicas: each batch key (service and minute) is
+/// owned by one replica, which does the batching and writing for it. Enabled
+/// by listing `peers` or setting `dns`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    /// Base URLs of all replicas, including this one (`http://10.0.0.1:4318`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub peers: Vec<String>,
+    /// `host:port` resolving to every replica (e.g. a Kubernetes headless
+    /// service), re-resolved every `refresh_secs`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns: Option<String>,
+    /// This replica's base URL as the other replicas reach it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_url: Option<String>,
+    /// Shared bearer token for forwarded batches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(default = "default_cluster_refresh_secs")]
+    pub refresh_secs: u64,
+    /// Give up forwarding after this long and batch locally instead
+    #[serde(default = "default_cluster_forward_timeout_secs")]
+    pub forward_timeout_secs: u64,
+}
+
+fn default_cluster_refresh_secs() -> u64 {
+    30
+}
+
+fn default_cluster_forward_timeout_secs() -> u64 {
+    5
+}
+
+impl ClusterConfig {
+    pub fn enabled(&self) -> bool {
+        !self.peers.is_empty() || self.dns.is_some()
+    }
+}
+
+/// Notifications sent after every committed file. Enabled by `webhook_url`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationsConfig {
+    /// URL each notification is POSTed to as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// Sent as `Authorization: Bearer <token>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_token: Option<String>,
+    /// Per-request timeout
+    #[serde(default = "default_notifications_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Retries per notification (exponential backoff, 1s doubling up to 30s)
+    #[serde(default = "default_notifications_max_retries")]
+    pub max_retries: u32,
+    /// Notifications waiting to be sent before new ones are dropped
+    #[serde(default = "default_notifications_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+fn default_notifications_timeout_secs() -> u64 {
+    10
+}
+
+fn default_notifications_max_retries() -> u32 {
+    5
+}
+
+fn default_notifications_queue_capacity() -> usize {
+    4096
+}
+
+/// Dual-write of accepted OTLP requests to another OTLP/HTTP endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorConfig {
+    /// Base URL of the endpoint; requests go to `{endpoint}/v1/{signal}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Headers added to every mirrored request, e.g. a vendor API key
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Per-request timeout
+    #[serde(default = "default_mirror_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Retries of a request failing with 429, 502, 503, 504 or a connection
+    /// error (exponential backoff, 1s doubling up to 30s)
+    #[serde(default = "default_mirror_max_retries")]
+    pub max_retries: u32,
+    /// Requests waiting to be sent before new ones are dropped
+    #[serde(default = "default_mirror_queue_capacity")]
+    pub queue_capacity: usize,
+    /// Requests sent at the same time
+    #[serde(default = "default_mirror_concurrency")]
+    pub concurrency: usize,
+    /// What is mirrored and whether answers are compared
+    #[serde(default)]
+    pub mode: MirrorMode,
+    /// Share of requests mirrored, in percent
+    #[serde(default = "default_mirror_sample_percent")]
+    pub sample_percent: f64,
+}
+
+/// Purpose of a request mirror
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MirrorMode {
+    /// Dual-write: send requests accepted locally
+    #[default]
+    Forward,
+    /// Topology testing: send requests whatever their local outcome and
+    /// count answers that differ from the local one
+    Shadow,
+}
+
+impl std::str::FromStr for MirrorMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "forward" => Ok(MirrorMode::Forward),
+            "shadow" => Ok(MirrorMode::Shadow),
+            _ => anyhow::bail!("Unsupported mirror mode: {}. Supported: forward, shadow", s),
+        }
+    }
+}
+
+fn default_mirror_timeout_secs() -> u64 {
+    10
+}
+
+fn default_mirror_max_retries() -> u32 {
+    3
+}
+
+fn default_mirror_queue_capacity() -> usize {
+    1024
+}
+
+fn default_mirror_concurrency() -> usize {
+    4
+}
+
+fn default_mirror_sample_percent() -> f64 {
+    100.0
+}
+
+impl Default for MirrorConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            headers: BTreeMap::new(),
+            timeout_secs: default_mirror_timeout_secs(),
+            max_retries: default_mirror_max_retries(),
+            queue_capacity: default_mirror_queue_capacity(),
+            concurrency: default_mirror_concurrency(),
+            mode: MirrorMode::default(),
+            sample_percent: default_mirror_sample_percent(),
+        }
+    }
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            webhook_token: None,
+            timeout_secs: default_notifications_timeout_secs(),
+            max_retries: default_notifications_max_retries(),
+            queue_capacity: default_notifications_queue_capacity(),
+        }
+    }
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            peers: Vec::new(),
+            dns: None,
+            self_url: None,
+            token: None,
+            refresh_secs: default_cluster_refresh_secs(),
+            forward_timeout_secs: default_cluster_forward_timeout_secs(),
+        }
+    }
+}
+
+/// Background copy of committed files to a second bucket/region
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplicationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Replica backend, bucket and prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<StorageConfig>,
+    /// Also record replicated files in this SQLite catalog (requires the
+    /// `catalog` feature)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub catalog_path: Option<String>,
+}
+
+/// Background move of older partitions from the primary (hot) storage to a
+/// cold target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TieringConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Partitions whose day is this many days before today (UTC) or older
+    /// move to the cold target
+    #[serde(default = "default_cold_after_days")]
+    pub cold_after_days: u32,
+    /// Seconds between scans of the primary storage
+    #[serde(default = "default_tiering_interval_secs")]
+    pub interval_secs: u64,
+    /// Cold backend, bucket and prefix (e.g. with an S3 `storage_class`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<StorageConfig>,
+}
+
+fn default_cold_after_days() -> u32 {
+    7
+}
+
+fn default_tiering_interval_secs() -> u64 {
+    3600
+}
+
+impl Default for TieringConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cold_after_days: default_cold_after_days(),
+            interval_secs: default_tiering_interval_secs(),
+            storage: None,
+        }
+    }
+}
+
+/// Storage target taking writes the primary storage could not accept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackConfig {
+    /// Identifies the fallback in logs, metrics and relocation entries
+    pub name: String,
+    /// Backend, bucket and prefix to write to
+    pub storage: StorageConfig,
+}
+
+/// Second sink inserting every signal batch into ClickHouse alongside Parquet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClickHouseConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// HTTP interface endpoint
+    #[serde(default = "default_clickhouse_url")]
+    pub url: String,
+    #[serde(default = "default_clickhouse_database")]
+    pub database: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// Tables are `{prefix}logs`, `{prefix}traces` and `{prefix}metrics_{type}`
+    #[serde(default = "default_clickhouse_table_prefix")]
+    pub table_prefix: String,
+    /// Per-insert request timeout
+    #[serde(default = "default_clickhouse_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Retries per batch (exponential backoff, 1s doubling up to 30s)
+    #[serde(default = "default_clickhouse_max_retries")]
+    pub max_retries: u32,
+    /// Batches waiting for ClickHouse before new ones are dropped
+    #[serde(default = "default_clickhouse_queue_capacity")]
+    pub queue_capacity: usize,
+}
 
-use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
+fn default_clickhouse_url() -> String {
+    "http://localhost:8123".to_string()
+}
 
-mod env_overrides;
-mod platform;
-#[cfg(not(target_arch = "wasm32"))]
-mod sources;
-mod validation;
+fn default_clickhouse_database() -> String {
+    "default".to_string()
+}
 
-pub use env_overrides::{EnvSource, ENV_PREFIX};
-pub use platform::Platform;
+fn default_clickhouse_table_prefix() -> String {
+    "otel_".to_string()
+}
 
-/// Main runtime configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RuntimeConfig {
+fn default_clickhouse_timeout_secs() -> u64 {
+    30
+}
+
+fn default_clickhouse_max_retries() -> u32 {
+    5
+}
+
+fn default_clickhouse_queue_capacity() -> usize {
+    1024
+}
+
+impl Default for ClickHouseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: default_clickhouse_url(),
+            database: default_clickhouse_database(),
+            username: None,
+            password: None,
+            table_prefix: default_clickhouse_table_prefix(),
+            timeout_secs: default_clickhouse_timeout_secs(),
+            max_retries: default_clickhouse_max_retries(),
+            queue_capacity: default_clickhouse_queue_capacity(),
+        }
+    }
+}
+
+/// Resource attribute enrichment applied after decoding
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EnrichmentConfig {
+    /// Added to every record's resource attributes unless already set; values
+    /// may use `${hostname}` and `${env:NAME}`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub resource_attributes: BTreeMap<String, String>,
     #[serde(default)]
-    pub batch: BatchConfig,
+    pub kubernetes: KubernetesEnrichmentConfig,
+    #[serde(default)]
+    pub geoip: GeoIpConfig,
+    /// W3C Baggage keys captured into `baggage_<key>` columns on logs and
+    /// spans, from record attributes or the request's `baggage` header
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub baggage_keys: Vec<String>,
+}
 
+/// Pod metadata lookup by `k8s.pod.ip` / `k8s.pod.name` (requires the
+/// `k8s-enrichment` feature)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KubernetesEnrichmentConfig {
     #[serde(default)]
-    pub request: RequestConfig,
+    pub enabled: bool,
+    /// Only watch pods scheduled on this node (DaemonSet deployments; set from
+    /// the downward API). Watches the whole cluster when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_name: Option<String>,
+    /// Copy pod labels as `k8s.pod.label.<key>`
+    #[serde(default = "default_pod_labels")]
+    pub pod_labels: bool,
+}
 
-    pub storage: StorageConfig,
+fn default_pod_labels() -> bool {
+    true
+}
 
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub server: Option<ServerConfig>,
+impl Default for KubernetesEnrichmentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            node_name: None,
+            pod_labels: default_pod_labels(),
+        }
+    }
 }
 
-/// Batch configuration
+/// Client address geolocation from MaxMind databases (requires the `geoip`
+/// feature)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BatchConfig {
-    pub max_rows: usize,
-    pub max_bytes: usize,
-    pub max_age_secs: u64,
-    #[serde(default = "default_batching_enabled")]
+pub struct GeoIpConfig {
+    #[serde(default)]
     pub enabled: bool,
+    /// GeoIP2/GeoLite2 City database (`.mmdb`): country, region, city, location
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city_database: Option<String>,
+    /// GeoIP2/GeoLite2 ASN database (`.mmdb`): autonomous system number and org
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asn_database: Option<String>,
+    /// Record attributes holding the client IP, tried in order
+    #[serde(default = "default_geoip_source_attributes")]
+    pub source_attributes: Vec<String>,
+    /// Signals to enrich: "logs", "traces", "metrics"
+    #[serde(default = "default_geoip_signals")]
+    pub signals: Vec<String>,
 }
 
-fn default_batching_enabled() -> bool {
-    true
+fn default_geoip_source_attributes() -> Vec<String> {
+    vec!["client.address".to_string(), "http.client_ip".to_string()]
 }
 
-impl BatchConfig {}
+fn default_geoip_signals() -> Vec<String> {
+    vec!["logs".to_string(), "traces".to_string()]
+}
 
-impl Default for BatchConfig {
+impl Default for GeoIpConfig {
     fn default() -> Self {
         Self {
-            max_rows: 200_000,
-            max_bytes: 128 * 1024 * 1024,
-            max_age_secs: 10,
-            enabled: true,
+            enabled: false,
+            city_database: None,
+            asn_database: None,
+            source_attributes: default_geoip_source_attributes(),
+            signals: default_geoip_signals(),
         }
     }
 }
 
-/// Request handling configuration
+/// spanmetrics-style RED aggregation: `span.calls` and `span.errors` sums and
+/// a `span.duration` histogram (ms) per service and route
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RequestConfig {
-    pub max_payload_bytes: usize,
+pub struct SpanMetricsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Span kinds that count as requests
+    #[serde(default = "default_span_kinds")]
+    pub span_kinds: Vec<String>,
+    /// Attributes tried in order for the route; falls back to the span name
+    #[serde(default = "default_route_attributes")]
+    pub route_attributes: Vec<String>,
+    /// Histogram bucket upper bounds in milliseconds
+    #[serde(default = "default_duration_buckets_ms")]
+    pub buckets_ms: Vec<f64>,
 }
 
-impl Default for RequestConfig {
+fn default_span_kinds() -> Vec<String> {
+    vec!["server".to_string(), "consumer".to_string()]
+}
+
+fn default_route_attributes() -> Vec<String> {
+    vec!["http.route".to_string(), "rpc.method".to_string()]
+}
+
+fn default_duration_buckets_ms() -> Vec<f64> {
+    vec![
+        5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+    ]
+}
+
+impl Default for SpanMetricsConfig {
     fn default() -> Self {
         Self {
-            max_payload_bytes: 8 * 1024 * 1024,
+            enabled: false,
+            span_kinds: default_span_kinds(),
+            route_attributes: default_route_attributes(),
+            buckets_ms: default_duration_buckets_ms(),
         }
     }
 }
 
+/// Counts (or sums an attribute of) log records matching every filter set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogMetricRule {
+    /// Name of the emitted sum metric
+    pub name: String,
+    /// Only records with at least this severity number (e.g. 17 = ERROR)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_severity_number: Option<i32>,
+    /// Only records from this service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// Only records whose body contains this substring
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_contains: Option<String>,
+    /// Add this numeric log attribute instead of counting records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_attribute: Option<String>,
+    /// Data point attributes: `severity_text`, `severity_number`, or a log
+    /// attribute key. Points are always split by service.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group_by: Vec<String>,
+}
+
 /// Storage backend configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
@@ -88,6 +1027,121 @@ pub struct StorageConfig {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub r2: Option<R2Config>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sftp: Option<SftpConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webdav: Option<WebdavConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hdfs: Option<HdfsConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gcs: Option<GcsConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azblob: Option<AzblobConfig>,
+
+    /// Re-check each object after writing (size and Parquet footer magic)
+    /// before acknowledging it. Catches silent truncation by some S3-compatible stores.
+    #[serde(default)]
+    pub verify_after_write: bool,
+
+    /// Maintain `_manifest.jsonl` per partition and `_latest.json` per table
+    /// so readers can discover files without listing the bucket.
+    #[serde(default)]
+    pub write_manifests: bool,
+
+    /// How data file names are generated within a partition
+    #[serde(default)]
+    pub file_naming: FileNaming,
+
+    /// Compression codec of data files
+    #[serde(default)]
+    pub parquet_compression: ParquetCompression,
+
+    /// Level for `gzip` (0-10) or `zstd` (1-22); the codec's default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parquet_compression_level: Option<u32>,
+
+    /// Write bloom filters on `trace_id`/`span_id` (traces) and `trace_id`
+    /// (logs) so point lookups skip row groups without the id
+    #[serde(default)]
+    pub parquet_bloom_filters: bool,
+
+    /// Spread each hour partition over this many `shard=NN` subdirectories so
+    /// high write rates land on distinct key prefixes (0 or 1 disables)
+    #[serde(default)]
+    pub write_shards: u16,
+
+    /// Retries of a failed object write (temporary errors only, with
+    /// exponential backoff) before it counts as failed
+    #[serde(default = "default_write_retries")]
+    pub write_retries: u32,
+}
+
+fn default_write_retries() -> u32 {
+    3
+}
+
+/// Data file naming scheme
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileNaming {
+    /// `{min_timestamp_micros}-{uuid v4}.parquet`: ordered by data time
+    #[default]
+    Timestamp,
+    /// `{uuid v7}.parquet`: ordered by write time, monotonic per process
+    Uuid7,
+}
+
+impl std::str::FromStr for FileNaming {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "timestamp" => Ok(FileNaming::Timestamp),
+            "uuid7" => Ok(FileNaming::Uuid7),
+            _ => anyhow::bail!(
+                "Unsupported file naming: {}. Supported: timestamp, uuid7",
+                s
+            ),
+        }
+    }
+}
+
+/// Parquet compression codec
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParquetCompression {
+    #[default]
+    Uncompressed,
+    Snappy,
+    /// Level 0-10 (default 6)
+    Gzip,
+    /// LZ4 raw blocks (the `LZ4_RAW` codec)
+    Lz4,
+    /// Level 1-22 (default 1); high levels suit cold storage
+    Zstd,
+}
+
+impl std::str::FromStr for ParquetCompression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "uncompressed" | "none" => Ok(ParquetCompression::Uncompressed),
+            "snappy" => Ok(ParquetCompression::Snappy),
+            "gzip" => Ok(ParquetCompression::Gzip),
+            "lz4" => Ok(ParquetCompression::Lz4),
+            "zstd" => Ok(ParquetCompression::Zstd),
+            _ => anyhow::bail!(
+                "Unsupported Parquet compression: {}. Supported: uncompressed, snappy, gzip, lz4, zstd",
+                s
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -96,6 +1150,18 @@ pub enum StorageBackend {
     Fs,
     S3,
     R2,
+    /// SFTP server (`sftp` feature)
+    Sftp,
+    /// WebDAV server (`webdav` feature)
+    Webdav,
+    /// HDFS or Ozone through the WebHDFS REST API (`hdfs` feature)
+    Hdfs,
+    /// Google Cloud Storage (`gcs` feature)
+    Gcs,
+    /// Azure Blob Storage (`azblob` feature)
+    Azblob,
+    /// Dry run: files are encoded and counted but never stored
+    Null,
 }
 
 impl std::fmt::Display for StorageBackend {
@@ -104,6 +1170,12 @@ impl std::fmt::Display for StorageBackend {
             StorageBackend::Fs => write!(f, "fs"),
             StorageBackend::S3 => write!(f, "s3"),
             StorageBackend::R2 => write!(f, "r2"),
+            StorageBackend::Sftp => write!(f, "sftp"),
+            StorageBackend::Webdav => write!(f, "webdav"),
+            StorageBackend::Hdfs => write!(f, "hdfs"),
+            StorageBackend::Gcs => write!(f, "gcs"),
+            StorageBackend::Azblob => write!(f, "azblob"),
+            StorageBackend::Null => write!(f, "null"),
         }
     }
 }
@@ -116,7 +1188,16 @@ impl std::str::FromStr for StorageBackend {
             "fs" | "filesystem" => Ok(StorageBackend::Fs),
             "s3" | "aws" => Ok(StorageBackend::S3),
             "r2" => Ok(StorageBackend::R2),
-            _ => anyhow::bail!("Unsupported storage backend: {}. Supported: fs, s3, r2", s),
+            "sftp" => Ok(StorageBackend::Sftp),
+            "webdav" | "dav" => Ok(StorageBackend::Webdav),
+            "hdfs" | "webhdfs" | "ozone" => Ok(StorageBackend::Hdfs),
+            "gcs" | "gs" => Ok(StorageBackend::Gcs),
+            "azblob" | "azure" | "abfs" => Ok(StorageBackend::Azblob),
+            "null" | "none" => Ok(StorageBackend::Null),
+            _ => anyhow::bail!(
+                "Unsupported storage backend: {}. Supported: fs, s3, r2, sftp, webdav, hdfs, gcs, azblob, null",
+                s
+            ),
         }
     }
 }
@@ -124,12 +1205,85 @@ impl std::str::FromStr for StorageBackend {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FsConfig {
     pub path: String,
+    /// Write each file under `{path}/.tmp` and rename it into place, so
+    /// readers never see a partially written file
+    #[serde(default = "default_atomic_writes")]
+    pub atomic_writes: bool,
+    /// What is flushed to disk before a write counts as done
+    #[serde(default)]
+    pub fsync: FsyncPolicy,
+    /// Most bytes the files under `path` may take; unlimited when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_disk_bytes: Option<u64>,
+    /// What happens once `max_disk_bytes` is reached
+    #[serde(default)]
+    pub on_disk_full: DiskFullPolicy,
+}
+
+fn default_atomic_writes() -> bool {
+    true
 }
 
 impl Default for FsConfig {
     fn default() -> Self {
         Self {
             path: "./data".to_string(),
+            atomic_writes: default_atomic_writes(),
+            fsync: FsyncPolicy::default(),
+            max_disk_bytes: None,
+            on_disk_full: DiskFullPolicy::default(),
+        }
+    }
+}
+
+/// Durability of filesystem writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FsyncPolicy {
+    /// Sync file contents before the write completes (and before the rename)
+    #[default]
+    File,
+    /// Also sync the partition directory, so the new entry survives power loss
+    Directory,
+}
+
+impl std::str::FromStr for FsyncPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "file" => Ok(FsyncPolicy::File),
+            "directory" | "dir" => Ok(FsyncPolicy::Directory),
+            _ => anyhow::bail!(
+                "Unsupported fsync policy: {}. Supported: file, directory",
+                s
+            ),
+        }
+    }
+}
+
+/// Behaviour of the filesystem backend at `max_disk_bytes`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskFullPolicy {
+    /// Delete the oldest hour partitions until usage is below the limit
+    #[default]
+    Delete,
+    /// Stop accepting data (503 with `Retry-After`) until space is freed
+    Reject,
+}
+
+impl std::str::FromStr for DiskFullPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "delete" | "delete_oldest" => Ok(DiskFullPolicy::Delete),
+            "reject" | "stop" => Ok(DiskFullPolicy::Reject),
+            _ => anyhow::bail!(
+                "Unsupported disk full policy: {}. Supported: delete, reject",
+                s
+            ),
         }
     }
 }
@@ -143,6 +1297,10 @@ pub struct S3Config {
     /// Optional path prefix for all stored files (e.g., "smoke-abc123/")
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub prefix: Option<String>,
+    /// Storage class of written objects (e.g. `STANDARD_IA`, `GLACIER_IR`);
+    /// the bucket default otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_class: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -157,12 +1315,136 @@ pub struct R2Config {
     pub prefix: Option<String>,
 }
 
+/// SFTP storage. Authenticates with an SSH key through the system `ssh`
+/// client, so it is available on Unix only.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SftpConfig {
+    /// `host`, `host:port` or `ssh://[user@]host[:port]`
+    pub endpoint: String,
+    /// Remote directory files are written below
+    #[serde(default = "default_remote_root")]
+    pub root: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Private key file; the ssh client's default keys and agent otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Host key checking: `strict` (host must be in known_hosts), `add`
+    /// (trust and remember new hosts) or `accept` (no checking)
+    #[serde(default = "default_known_hosts")]
+    pub known_hosts: String,
+}
+
+/// WebDAV storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebdavConfig {
+    /// Server URL, e.g. `https://dav.example.com/remote.php/dav`
+    pub endpoint: String,
+    /// Directory below the endpoint files are written below
+    #[serde(default = "default_remote_root")]
+    pub root: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// Bearer token, instead of username and password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// HDFS storage through WebHDFS (a NameNode, an HttpFS gateway, or Ozone's
+/// HttpFS gateway)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HdfsConfig {
+    /// NameNode or HttpFS address, e.g. `http://namenode:9870`
+    pub endpoint: String,
+    /// HDFS directory files are written below
+    #[serde(default = "default_remote_root")]
+    pub root: String,
+    /// `user.name` for clusters with simple (pseudo) authentication
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Delegation token for Kerberos-secured clusters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delegation_token: Option<String>,
+    /// List directories one entry page at a time instead of with
+    /// LISTSTATUS_BATCH, for clusters older than Hadoop 2.8 and HttpFS
+    #[serde(default)]
+    pub disable_list_batch: bool,
+}
+
+/// Google Cloud Storage bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcsConfig {
+    pub bucket: String,
+    /// Path of a service account key file (JSON). Without it or
+    /// `credential`, `GOOGLE_APPLICATION_CREDENTIALS` and then the VM
+    /// metadata server are used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_path: Option<String>,
+    /// Service account key, base64-encoded, for deployments without a file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+    /// API endpoint override, e.g. a fake-gcs-server emulator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Send unsigned requests when no credential is found instead of asking
+    /// the VM metadata server; for emulators such as fake-gcs-server
+    #[serde(default)]
+    pub allow_anonymous: bool,
+    /// Optional path prefix for all stored files (e.g., "smoke-abc123/")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+}
+
+/// Azure Blob Storage container
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AzblobConfig {
+    pub container: String,
+    /// Storage account; also names the default endpoint
+    pub account_name: String,
+    /// Shared account key. Without it or `sas_token`, Azure AD credentials
+    /// from `AZURE_*` environment variables or a managed identity are used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_key: Option<String>,
+    /// Shared access signature with write and delete permission
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sas_token: Option<String>,
+    /// Blob endpoint, `https://{account_name}.blob.core.windows.net` by
+    /// default (e.g. an Azurite emulator or a sovereign cloud)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Optional path prefix for all stored files (e.g., "smoke-abc123/")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+}
+
+impl AzblobConfig {
+    /// `endpoint`, or the account's public blob endpoint
+    pub fn endpoint_or_default(&self) -> String {
+        self.endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://{}.blob.core.windows.net", self.account_name))
+    }
+}
+
+fn default_remote_root() -> String {
+    "/".to_string()
+}
+
+fn default_known_hosts() -> String {
+    "strict".to_string()
+}
+
 /// Server-specific configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub listen_addr: String,
     pub log_level: String,
     pub log_format: LogFormat,
+    /// OTLP/gRPC listen address (`grpc` feature); no gRPC listener when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_listen_addr: Option<String>,
 }
 
 impl Default for ServerConfig {
@@ -171,6 +1453,7 @@ impl Default for ServerConfig {
             listen_addr: "0.0.0.0:4318".to_string(),
             log_level: "info".to_string(),
             log_format: LogFormat::Text,
+            grpc_listen_addr: None,
         }
     }
 }
@@ -226,6 +1509,22 @@ impl RuntimeConfig {
         self.batch = other.batch;
         self.request = other.request;
         self.storage = other.storage;
+        self.probe = other.probe;
+        self.derive = other.derive;
+        self.events = other.events;
+        self.enrichment = other.enrichment;
+        self.schema = other.schema;
+        self.catalog = other.catalog;
+        self.clickhouse = other.clickhouse;
+        self.routes = other.routes;
+        self.fallbacks = other.fallbacks;
+        self.replication = other.replication;
+        self.tiering = other.tiering;
+        self.usage = other.usage;
+        self.capture = other.capture;
+        self.cluster = other.cluster;
+        self.notifications = other.notifications;
+        self.mirror = other.mirror;
 
         if other.server.is_some() {
             self.server = other.server;
@@ -248,8 +1547,14 @@ impl RuntimeConfig {
         let mut config = RuntimeConfig::from_platform_defaults(platform);
 
         if let Some(inline) = inline_config {
-            let file_config: RuntimeConfig =
+            let mut table: toml::Table =
                 toml::from_str(inline).context("Failed to parse inline config content")?;
+            if !layers::take_includes(&mut table)?.is_empty() {
+                anyhow::bail!("include is not supported in inline config content");
+            }
+            let profile = env.get(layers::PROFILE_ENV);
+            let file_config = layers::finish(table, profile.as_deref())
+                .context("Failed to load inline config content")?;
             config.merge(file_config);
         }
 
@@ -271,6 +1576,89 @@ impl RuntimeConfig {
     pub fn validate(&self) -> Result<()> {
         validation::validate_config(self)
     }
+
+    /// Copy of this config with credentials masked, safe to print or log.
+    pub fn redacted(&self) -> RuntimeConfig {
+        let mut config = self.clone();
+        if let Some(r2) = config.storage.r2.as_mut() {
+            r2.access_key_id = redact_secret(&r2.access_key_id);
+            r2.secret_access_key = redact_secret(&r2.secret_access_key);
+        }
+        if let Some(credential) = config
+            .storage
+            .gcs
+            .as_mut()
+            .and_then(|g| g.credential.as_mut())
+        {
+            *credential = redact_secret(credential);
+        }
+        if let Some(azblob) = config.storage.azblob.as_mut() {
+            redact_azblob(azblob);
+        }
+        let extra_storage = config
+            .routes
+            .iter_mut()
+            .map(|route| &mut route.storage)
+            .chain(config.fallbacks.iter_mut().map(|f| &mut f.storage))
+            .chain(config.replication.storage.as_mut())
+            .chain(config.tiering.storage.as_mut());
+        for storage in extra_storage {
+            if let Some(r2) = storage.r2.as_mut() {
+                r2.access_key_id = redact_secret(&r2.access_key_id);
+                r2.secret_access_key = redact_secret(&r2.secret_access_key);
+            }
+            if let Some(credential) = storage.gcs.as_mut().and_then(|g| g.credential.as_mut()) {
+                *credential = redact_secret(credential);
+            }
+            if let Some(azblob) = storage.azblob.as_mut() {
+                redact_azblob(azblob);
+            }
+        }
+        if let Some(password) = config.clickhouse.password.as_mut() {
+            *password = redact_secret(password);
+        }
+        if let Some(token) = config.capture.admin_token.as_mut() {
+            *token = redact_secret(token);
+        }
+        if let Some(token) = config.cluster.token.as_mut() {
+            *token = redact_secret(token);
+        }
+        if let Some(token) = config.notifications.webhook_token.as_mut() {
+            *token = redact_secret(token);
+        }
+        // Header values are typically API keys
+        for value in config.mirror.headers.values_mut() {
+            *value = redact_secret(value);
+        }
+        config.request.header_services = std::mem::take(&mut config.request.header_services)
+            .into_iter()
+            .map(|(key, service)| (redact_secret(&key), service))
+            .collect();
+        config
+    }
+}
+
+/// Mask a secret for display, keeping only the last four characters of
+/// values long enough that doing so reveals nothing useful.
+pub fn redact_secret(value: &str) -> String {
+    if value.is_empty() {
+        return String::new();
+    }
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < 12 {
+        return "****".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("****{}", tail)
+}
+
+fn redact_azblob(azblob: &mut AzblobConfig) {
+    for secret in [azblob.account_key.as_mut(), azblob.sas_token.as_mut()]
+        .into_iter()
+        .flatten()
+    {
+        *secret = redact_secret(secret);
+    }
 }
 
 fn platform_defaults(platform: Platform) -> RuntimeConfig {
@@ -282,11 +1670,30 @@ fn platform_defaults(platform: Platform) -> RuntimeConfig {
         .unwrap_or(StorageBackend::Fs);
 
     let storage = match storage_backend {
-        StorageBackend::Fs => StorageConfig {
+        // SFTP, WebDAV, HDFS, GCS and Azure need a target; no platform defaults to them
+        StorageBackend::Fs
+        | StorageBackend::Sftp
+        | StorageBackend::Webdav
+        | StorageBackend::Hdfs
+        | StorageBackend::Gcs
+        | StorageBackend::Azblob => StorageConfig {
             backend: StorageBackend::Fs,
             fs: Some(FsConfig::default()),
             s3: None,
             r2: None,
+            sftp: None,
+            webdav: None,
+            hdfs: None,
+            gcs: None,
+            azblob: None,
+            verify_after_write: false,
+            write_manifests: false,
+            file_naming: FileNaming::Timestamp,
+            parquet_compression: ParquetCompression::Uncompressed,
+            parquet_compression_level: None,
+            parquet_bloom_filters: false,
+            write_shards: 0,
+            write_retries: default_write_retries(),
         },
         StorageBackend::S3 => StorageConfig {
             backend: StorageBackend::S3,
@@ -296,8 +1703,22 @@ fn platform_defaults(platform: Platform) -> RuntimeConfig {
                 region: "us-east-1".to_string(),
                 endpoint: None,
                 prefix: None,
+                storage_class: None,
             }),
             r2: None,
+            sftp: None,
+            webdav: None,
+            hdfs: None,
+            gcs: None,
+            azblob: None,
+            verify_after_write: false,
+            write_manifests: false,
+            file_naming: FileNaming::Timestamp,
+            parquet_compression: ParquetCompression::Uncompressed,
+            parquet_compression_level: None,
+            parquet_bloom_filters: false,
+            write_shards: 0,
+            write_retries: default_write_retries(),
         },
         StorageBackend::R2 => StorageConfig {
             backend: StorageBackend::R2,
@@ -311,6 +1732,38 @@ fn platform_defaults(platform: Platform) -> RuntimeConfig {
                 endpoint: None,
                 prefix: None,
             }),
+            sftp: None,
+            webdav: None,
+            hdfs: None,
+            gcs: None,
+            azblob: None,
+            verify_after_write: false,
+            write_manifests: false,
+            file_naming: FileNaming::Timestamp,
+            parquet_compression: ParquetCompression::Uncompressed,
+            parquet_compression_level: None,
+            parquet_bloom_filters: false,
+            write_shards: 0,
+            write_retries: default_write_retries(),
+        },
+        StorageBackend::Null => StorageConfig {
+            backend: StorageBackend::Null,
+            fs: None,
+            s3: None,
+            r2: None,
+            sftp: None,
+            webdav: None,
+            hdfs: None,
+            gcs: None,
+            azblob: None,
+            verify_after_write: false,
+            write_manifests: false,
+            file_naming: FileNaming::Timestamp,
+            parquet_compression: ParquetCompression::Uncompressed,
+            parquet_compression_level: None,
+            parquet_bloom_filters: false,
+            write_shards: 0,
+            write_retries: default_write_retries(),
         },
     };
 
@@ -323,9 +1776,26 @@ fn platform_defaults(platform: Platform) -> RuntimeConfig {
         },
         request: RequestConfig {
             max_payload_bytes: defaults.max_payload_bytes,
+            ..RequestConfig::default()
         },
         storage,
         server: Some(ServerConfig::default()),
+        probe: ProbeConfig::default(),
+        derive: DeriveConfig::default(),
+        events: EventsConfig::default(),
+        enrichment: EnrichmentConfig::default(),
+        schema: SchemaConfig::default(),
+        catalog: CatalogConfig::default(),
+        clickhouse: ClickHouseConfig::default(),
+        routes: Vec::new(),
+        fallbacks: Vec::new(),
+        replication: ReplicationConfig::default(),
+        tiering: TieringConfig::default(),
+        usage: UsageConfig::default(),
+        capture: CaptureConfig::default(),
+        cluster: ClusterConfig::default(),
+        notifications: NotificationsConfig::default(),
+        mirror: MirrorConfig::default(),
     }
 }
 
@@ -338,6 +1808,27 @@ mod tests {
         assert_eq!("fs".parse::<StorageBackend>().unwrap(), StorageBackend::Fs);
         assert_eq!("s3".parse::<StorageBackend>().unwrap(), StorageBackend::S3);
         assert_eq!("r2".parse::<StorageBackend>().unwrap(), StorageBackend::R2);
+        assert_eq!(
+            "sftp".parse::<StorageBackend>().unwrap(),
+            StorageBackend::Sftp
+        );
+        assert_eq!(
+            "WebDAV".parse::<StorageBackend>().unwrap(),
+            StorageBackend::Webdav
+        );
+        assert_eq!(
+            "ozone".parse::<StorageBackend>().unwrap(),
+            StorageBackend::Hdfs
+        );
+        assert_eq!("gs".parse::<StorageBackend>().unwrap(), StorageBackend::Gcs);
+        assert_eq!(
+            "abfs".parse::<StorageBackend>().unwrap(),
+            StorageBackend::Azblob
+        );
+        assert_eq!(
+            "null".parse::<StorageBackend>().unwrap(),
+            StorageBackend::Null
+        );
         assert_eq!(
             "filesystem".parse::<StorageBackend>().unwrap(),
             StorageBackend::Fs
@@ -345,6 +1836,50 @@ mod tests {
         assert_eq!("aws".parse::<StorageBackend>().unwrap(), StorageBackend::S3);
     }
 
+    #[test]
+    fn test_max_payload_bytes_for_signal() {
+        let request = RequestConfig {
+            metrics_max_payload_bytes: Some(1024),
+            ..RequestConfig::default()
+        };
+        assert_eq!(request.max_payload_bytes_for(SignalType::Metrics), 1024);
+        assert_eq!(
+            request.max_payload_bytes_for(SignalType::Logs),
+            request.max_payload_bytes
+        );
+        assert_eq!(
+            "REJECT".parse::<SeriesOverflow>().unwrap(),
+            SeriesOverflow::Reject
+        );
+        assert!("truncate".parse::<SeriesOverflow>().is_err());
+    }
+
+    #[test]
+    fn test_redact_secret() {
+        assert_eq!(redact_secret(""), "");
+        assert_eq!(redact_secret("short"), "****");
+        assert_eq!(redact_secret("wJalrXUtnFEMIK7MDENG"), "****DENG");
+    }
+
+    #[test]
+    fn test_redacted_masks_r2_credentials() {
+        let mut config = RuntimeConfig::from_platform_defaults(Platform::Server);
+        config.storage.r2 = Some(R2Config {
+            bucket: "bucket".to_string(),
+            account_id: "account".to_string(),
+            access_key_id: "AKIAABCDEFGHIJKL".to_string(),
+            secret_access_key: "super-secret-value-1234".to_string(),
+            endpoint: None,
+            prefix: None,
+        });
+
+        let redacted = config.redacted();
+        let r2 = redacted.storage.r2.unwrap();
+        assert_eq!(r2.access_key_id, "****IJKL");
+        assert_eq!(r2.secret_access_key, "****1234");
+        assert_eq!(r2.account_id, "account");
+    }
+
     #[test]
     fn test_default_configs() {
         let batch = BatchConfig::default();
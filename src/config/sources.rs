@@ -6,14 +6,21 @@
 // 3. Inline config content from OTLP2PARQUET_CONFIG_CONTENT
 // 4. Default config files (./config.toml, ./.otlp2parquet.toml)
 // 5. Platform defaults (based on auto-detected Platform)
+//
+// Files are read with their includes, and the profile named by
+// OTLP2PARQUET_PROFILE is applied on top (see layers.rs).
 
 use super::env_overrides::{self, EnvSource, ENV_PREFIX};
+use super::layers;
 use super::platform::Platform;
 use super::*;
 use anyhow::{Context, Result};
 use std::env;
 use std::path::Path;
 
+/// Deepest chain of `include`s followed before assuming a cycle
+const MAX_INCLUDE_DEPTH: usize = 8;
+
 /// Load configuration for the detected platform using native environment/file access.
 pub fn load_config(platform: Platform) -> Result<RuntimeConfig> {
     let mut config = RuntimeConfig::from_platform_defaults(platform);
@@ -30,42 +37,71 @@ pub fn load_config(platform: Platform) -> Result<RuntimeConfig> {
 
 fn load_from_file() -> Result<Option<RuntimeConfig>> {
     if let Ok(path) = env::var("OTLP2PARQUET_CONFIG") {
-        let content = std::fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read config file: {}", path))?;
-        let config: RuntimeConfig = toml::from_str(&content)
-            .with_context(|| format!("Failed to parse config file: {}", path))?;
-        return Ok(Some(config));
+        return read_config_file(Path::new(&path)).map(Some);
     }
 
     if let Ok(content) = env::var("OTLP2PARQUET_CONFIG_CONTENT") {
-        let config: RuntimeConfig = toml::from_str(&content)
-            .context("Failed to parse inline config from OTLP2PARQUET_CONFIG_CONTENT")?;
-        return Ok(Some(config));
+        let table = read_layers(&content, "OTLP2PARQUET_CONFIG_CONTENT", Path::new("."), 0)?;
+        return layers::finish(table, profile().as_deref())
+            .context("Failed to load inline config from OTLP2PARQUET_CONFIG_CONTENT")
+            .map(Some);
     }
 
     for path in &["./config.toml", "./.otlp2parquet.toml"] {
         if Path::new(path).exists() {
-            let content = std::fs::read_to_string(path)
-                .with_context(|| format!("Failed to read config file: {}", path))?;
-            let config: RuntimeConfig = toml::from_str(&content)
-                .with_context(|| format!("Failed to parse config file: {}", path))?;
-            return Ok(Some(config));
+            return read_config_file(Path::new(path)).map(Some);
         }
     }
 
     Ok(None)
 }
 
+/// Read `path` with its includes and the selected profile applied.
+fn read_config_file(path: &Path) -> Result<RuntimeConfig> {
+    let table = read_file_layers(path, 0)?;
+    layers::finish(table, profile().as_deref())
+        .with_context(|| format!("Failed to load config file: {}", path.display()))
+}
+
+fn read_file_layers(path: &Path, depth: usize) -> Result<toml::Table> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    read_layers(&content, &path.display().to_string(), dir, depth)
+}
+
+/// Parse `content` and merge it over its includes (relative to `dir`).
+fn read_layers(content: &str, origin: &str, dir: &Path, depth: usize) -> Result<toml::Table> {
+    if depth > MAX_INCLUDE_DEPTH {
+        anyhow::bail!(
+            "{}: includes nested more than {} deep (include cycle?)",
+            origin,
+            MAX_INCLUDE_DEPTH
+        );
+    }
+    let mut table: toml::Table = toml::from_str(content)
+        .with_context(|| format!("Failed to parse config file: {}", origin))?;
+    let mut merged = toml::Table::new();
+    for include in layers::take_includes(&mut table).with_context(|| origin.to_string())? {
+        let included = read_file_layers(&dir.join(&include), depth + 1)
+            .with_context(|| format!("{}: failed to include {}", origin, include))?;
+        layers::merge_tables(&mut merged, included);
+    }
+    layers::merge_tables(&mut merged, table);
+    Ok(merged)
+}
+
+/// Profile selected with OTLP2PARQUET_PROFILE
+fn profile() -> Option<String> {
+    env::var(format!("{}{}", ENV_PREFIX, layers::PROFILE_ENV)).ok()
+}
+
 /// Load configuration from a specific file path (for CLI --config flag).
 /// Returns error if file doesn't exist or can't be parsed.
 /// Unlike load_config(), this starts with the file content and then applies
 /// platform defaults and environment overrides.
 pub fn load_from_file_path(path: impl AsRef<Path>) -> Result<RuntimeConfig> {
-    let path = path.as_ref();
-    let content = std::fs::read_to_string(path)
-        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
-    let file_config: RuntimeConfig = toml::from_str(&content)
-        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+    let file_config = read_config_file(path.as_ref())?;
 
     // Start with platform defaults, then merge file config
     let platform = Platform::detect();
@@ -108,6 +144,13 @@ impl EnvSource for StdEnvSource {
     fn get_raw(&self, key: &str) -> Option<String> {
         env::var(key).ok()
     }
+
+    fn keys(&self) -> Vec<String> {
+        env::vars_os()
+            .filter_map(|(key, _)| key.into_string().ok())
+            .filter_map(|key| key.strip_prefix(ENV_PREFIX).map(str::to_string))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -3,24 +3,321 @@
 // Validates that required fields are present and values are sensible
 
 use super::*;
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use tracing::warn;
 
+/// Upper bound for `storage.write_shards`; beyond this shards only fragment files
+const MAX_WRITE_SHARDS: u16 = 256;
+
+/// Check every section and the constraints between them, reporting all
+/// problems found rather than only the first.
 pub fn validate_config(config: &RuntimeConfig) -> Result<()> {
-    // Validate batch config
-    validate_batch_config(&config.batch)?;
+    let checks = [
+        validate_batch_config(&config.batch),
+        validate_request_config(&config.request),
+        validate_storage_config(&config.storage),
+        validate_probe_config(&config.probe, &config.batch),
+        validate_derive_config(&config.derive),
+        validate_events_config(&config.events),
+        validate_enrichment_config(&config.enrichment),
+        validate_catalog_config(&config.catalog, &config.storage),
+        validate_clickhouse_config(&config.clickhouse),
+        validate_routes(&config.routes),
+        validate_fallbacks(&config.fallbacks),
+        validate_replication_config(&config.replication),
+        validate_tiering_config(&config.tiering),
+        validate_usage_config(&config.usage),
+        validate_capture_config(&config.capture),
+        validate_cluster_config(&config.cluster, &config.batch),
+        validate_notifications_config(&config.notifications),
+        validate_mirror_config(&config.mirror),
+        config
+            .server
+            .as_ref()
+            .map_or(Ok(()), validate_server_config),
+    ];
+    let mut problems: Vec<String> = checks
+        .into_iter()
+        .filter_map(|check| check.err())
+        .map(|e| format!("{:#}", e))
+        .collect();
+    problems.extend(cross_field_problems(config));
+
+    match problems.len() {
+        0 => Ok(()),
+        1 => bail!("{}", problems.remove(0)),
+        count => bail!(
+            "Found {} configuration problems:\n\n{}",
+            count,
+            problems
+                .iter()
+                .enumerate()
+                .map(|(i, problem)| format!("{}. {}", i + 1, problem.trim_end()))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        ),
+    }
+}
+
+/// Settings that are valid on their own but do not work together
+fn cross_field_problems(config: &RuntimeConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    // A request larger than the batch limit is flushed on its own, so
+    // batching never produces files bigger than one request
+    let request = &config.request;
+    let largest_request = [SignalType::Logs, SignalType::Traces, SignalType::Metrics]
+        .into_iter()
+        .map(|signal| request.max_payload_bytes_for(signal))
+        .max()
+        .unwrap_or(request.max_payload_bytes);
+    if config.batch.enabled
+        && config.batch.max_bytes > 0
+        && config.batch.max_bytes < largest_request
+    {
+        problems.push(format!(
+            "batch.max_bytes ({}) is smaller than the largest accepted request ({} bytes); \
+             every such request would be written as its own file\n\n\
+             How to fix:\n\
+               • Raise batch.max_bytes: export {prefix}BATCH_MAX_BYTES={}\n\
+               • Or lower request.max_payload_bytes: export {prefix}MAX_PAYLOAD_BYTES={}",
+            config.batch.max_bytes,
+            largest_request,
+            largest_request,
+            config.batch.max_bytes,
+            prefix = ENV_PREFIX
+        ));
+    }
+
+    // Replicating onto the primary target overwrites nothing but doubles every PUT
+    if let Some(replica) = config
+        .replication
+        .storage
+        .as_ref()
+        .filter(|_| config.replication.enabled)
+    {
+        if storage_target(replica) == storage_target(&config.storage) {
+            problems.push(format!(
+                "replication.storage points at the primary storage target ({})\n\n\
+                 How to fix:\n\
+                   • Point [replication.storage] at a different bucket, account or path\n\
+                   • Or disable replication: export {}REPLICATION_ENABLED=false",
+                storage_target(replica),
+                ENV_PREFIX
+            ));
+        }
+    }
+    if let Some(cold) = config
+        .tiering
+        .storage
+        .as_ref()
+        .filter(|_| config.tiering.enabled)
+    {
+        if storage_target(cold) == storage_target(&config.storage) {
+            problems.push(format!(
+                "tiering.storage points at the primary storage target ({}); partitions \
+                 would be deleted after moving onto themselves\n\n\
+                 How to fix:\n\
+                   • Point [tiering.storage] at a different bucket, prefix or path\n\
+                   • Or disable tiering: export {}TIERING_ENABLED=false",
+                storage_target(cold),
+                ENV_PREFIX
+            ));
+        }
+    }
+    for fallback in &config.fallbacks {
+        if storage_target(&fallback.storage) == storage_target(&config.storage) {
+            problems.push(format!(
+                "fallbacks '{}' points at the primary storage target ({}); it cannot help \
+                 when the primary fails\n\n\
+                 How to fix:\n\
+                   • Point [fallbacks.storage] at a different bucket, account or path",
+                fallback.name,
+                storage_target(&fallback.storage)
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Backend and location a storage config writes to, for comparing targets
+fn storage_target(config: &StorageConfig) -> String {
+    match config.backend {
+        StorageBackend::Fs => format!(
+            "fs:{}",
+            config
+                .fs
+                .as_ref()
+                .map_or("", |fs| fs.path.trim_end_matches('/'))
+        ),
+        StorageBackend::S3 => config.s3.as_ref().map_or_else(
+            || "s3:".to_string(),
+            |s3| {
+                format!(
+                    "s3:{}/{}/{}",
+                    s3.endpoint.as_deref().unwrap_or_default(),
+                    s3.bucket,
+                    s3.prefix.as_deref().unwrap_or_default().trim_matches('/')
+                )
+            },
+        ),
+        StorageBackend::R2 => config.r2.as_ref().map_or_else(
+            || "r2:".to_string(),
+            |r2| format!("r2:{}/{}", r2.account_id, r2.bucket),
+        ),
+        StorageBackend::Sftp => config.sftp.as_ref().map_or_else(
+            || "sftp:".to_string(),
+            |sftp| format!("sftp:{}/{}", sftp.endpoint, sftp.root.trim_matches('/')),
+        ),
+        StorageBackend::Webdav => config.webdav.as_ref().map_or_else(
+            || "webdav:".to_string(),
+            |webdav| {
+                format!(
+                    "webdav:{}/{}",
+                    webdav.endpoint.trim_end_matches('/'),
+                    webdav.root.trim_matches('/')
+                )
+            },
+        ),
+        StorageBackend::Hdfs => config.hdfs.as_ref().map_or_else(
+            || "hdfs:".to_string(),
+            |hdfs| {
+                format!(
+                    "hdfs:{}/{}",
+                    hdfs.endpoint.trim_end_matches('/'),
+                    hdfs.root.trim_matches('/')
+                )
+            },
+        ),
+        StorageBackend::Gcs => config.gcs.as_ref().map_or_else(
+            || "gcs:".to_string(),
+            |gcs| {
+                format!(
+                    "gcs:{}/{}/{}",
+                    gcs.endpoint.as_deref().unwrap_or_default(),
+                    gcs.bucket,
+                    gcs.prefix.as_deref().unwrap_or_default().trim_matches('/')
+                )
+            },
+        ),
+        StorageBackend::Azblob => config.azblob.as_ref().map_or_else(
+            || "azblob:".to_string(),
+            |azblob| {
+                format!(
+                    "azblob:{}/{}/{}",
+                    azblob.endpoint_or_default(),
+                    azblob.container,
+                    azblob
+                        .prefix
+                        .as_deref()
+                        .unwrap_or_default()
+                        .trim_matches('/')
+                )
+            },
+        ),
+        StorageBackend::Null => "null".to_string(),
+    }
+}
 
-    // Validate request config
-    validate_request_config(&config.request)?;
+fn validate_usage_config(config: &UsageConfig) -> Result<()> {
+    if config.enabled && config.flush_interval_secs == 0 {
+        bail!("usage.flush_interval_secs must be greater than 0");
+    }
+    if config
+        .tenant_attribute
+        .as_deref()
+        .is_some_and(|key| key.trim().is_empty())
+    {
+        bail!("usage.tenant_attribute must not be empty");
+    }
+    Ok(())
+}
 
-    // Validate storage config
-    validate_storage_config(&config.storage)?;
+fn validate_capture_config(config: &CaptureConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    if config.max_requests == 0 || config.max_body_bytes == 0 {
+        bail!("capture.max_requests and capture.max_body_bytes must be greater than 0");
+    }
+    // Captured bodies are customer telemetry; never serve them unauthenticated
+    if config
+        .admin_token
+        .as_deref()
+        .is_none_or(|token| token.trim().len() < 16)
+    {
+        bail!("capture.enabled requires capture.admin_token of at least 16 characters");
+    }
+    Ok(())
+}
+
+fn validate_cluster_config(config: &ClusterConfig, batch: &BatchConfig) -> Result<()> {
+    if !config.enabled() {
+        return Ok(());
+    }
+    if !batch.enabled {
+        bail!("cluster peer forwarding requires batch.enabled");
+    }
+    let Some(self_url) = config.self_url.as_deref() else {
+        bail!("cluster.peers / cluster.dns require cluster.self_url");
+    };
+    for url in config.peers.iter().map(String::as_str).chain([self_url]) {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            bail!("cluster URL '{}' must start with http:// or https://", url);
+        }
+    }
+    if config.dns.as_deref().is_some_and(|dns| !dns.contains(':')) {
+        bail!("cluster.dns must be host:port");
+    }
+    if config.refresh_secs == 0 || config.forward_timeout_secs == 0 {
+        bail!("cluster.refresh_secs and cluster.forward_timeout_secs must be greater than 0");
+    }
+    if !config.peers.is_empty()
+        && !config
+            .peers
+            .iter()
+            .any(|peer| peer.trim_end_matches('/') == self_url.trim_end_matches('/'))
+    {
+        warn!("cluster.self_url is not in cluster.peers; this replica will own no batches");
+    }
+    Ok(())
+}
 
-    // Validate platform-specific configs
-    if let Some(ref server) = config.server {
-        validate_server_config(server)?;
+fn validate_notifications_config(config: &NotificationsConfig) -> Result<()> {
+    let Some(url) = config.webhook_url.as_deref() else {
+        return Ok(());
+    };
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        bail!(
+            "notifications.webhook_url must be an http(s) URL (got '{}')",
+            url
+        );
+    }
+    if config.timeout_secs == 0 || config.queue_capacity == 0 {
+        bail!("notifications.timeout_secs and notifications.queue_capacity must be greater than 0");
     }
+    Ok(())
+}
 
+fn validate_mirror_config(config: &MirrorConfig) -> Result<()> {
+    let Some(url) = config.endpoint.as_deref() else {
+        return Ok(());
+    };
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        bail!("mirror.endpoint must be an http(s) URL (got '{}')", url);
+    }
+    if config.timeout_secs == 0 || config.queue_capacity == 0 || config.concurrency == 0 {
+        bail!(
+            "mirror.timeout_secs, mirror.queue_capacity and mirror.concurrency must be greater than 0"
+        );
+    }
+    if !(config.sample_percent > 0.0 && config.sample_percent <= 100.0) {
+        bail!(
+            "mirror.sample_percent must be greater than 0 and at most 100 (got {})",
+            config.sample_percent
+        );
+    }
     Ok(())
 }
 
@@ -56,11 +353,77 @@ fn validate_batch_config(config: &BatchConfig) -> Result<()> {
     Ok(())
 }
 
+/// Headers whose values are secrets, never to be written as a service name
+fn is_credential_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    matches!(
+        name.as_str(),
+        "authorization" | "proxy-authorization" | "cookie"
+    ) || name.ends_with("-key")
+        || name.ends_with("-token")
+        || name.ends_with("-secret")
+}
+
 fn validate_request_config(config: &RequestConfig) -> Result<()> {
     if config.max_payload_bytes == 0 {
         bail!("request.max_payload_bytes must be greater than 0");
     }
 
+    if config.shed_write_p95_ms == Some(0) {
+        bail!("request.shed_write_p95_ms must be greater than 0 (omit it to disable shedding)");
+    }
+
+    for (name, value) in [
+        ("logs_max_payload_bytes", config.logs_max_payload_bytes),
+        ("traces_max_payload_bytes", config.traces_max_payload_bytes),
+        (
+            "metrics_max_payload_bytes",
+            config.metrics_max_payload_bytes,
+        ),
+    ] {
+        if value == Some(0) {
+            bail!(
+                "request.{} must be greater than 0 (omit it to use max_payload_bytes)",
+                name
+            );
+        }
+    }
+
+    if config.max_series_per_metric == Some(0) {
+        bail!(
+            "request.max_series_per_metric must be greater than 0 (omit it to disable the limit)"
+        );
+    }
+
+    let header = config.service_header.as_deref();
+    if header.is_some_and(|h| axum::http::HeaderName::from_bytes(h.as_bytes()).is_err()) {
+        bail!("request.service_header must be a valid HTTP header name");
+    }
+    if config.unattributed == UnattributedPolicy::Infer && header.is_none() {
+        bail!("request.unattributed = \"infer\" requires request.service_header");
+    }
+    if let Some(header) = header {
+        if config.unattributed == UnattributedPolicy::Infer
+            && config.header_services.is_empty()
+            && is_credential_header(header)
+        {
+            bail!(
+                "request.service_header '{}' carries credentials; map its values to services \
+                 with request.header_services instead of using them as service names",
+                header
+            );
+        }
+    }
+    if config.header_services.values().any(|s| s.trim().is_empty()) {
+        bail!("request.header_services must not map to an empty service name");
+    }
+    if config.strict_schema && config.lenient_parsing {
+        bail!(
+            "request.strict_schema and request.lenient_parsing cannot both be set \
+             (lenient parsing replaces unknown enum names, which strict mode refuses)"
+        );
+    }
+
     // Warn about very large payloads
     if config.max_payload_bytes > 100 * 1024 * 1024 {
         // 100 MB
@@ -70,6 +433,342 @@ fn validate_request_config(config: &RequestConfig) -> Result<()> {
         );
     }
 
+    if config.metrics_max_services > 1000 {
+        warn!(
+            metrics_max_services = config.metrics_max_services,
+            "request.metrics_max_services is very large; per-service metrics may overload the metrics backend"
+        );
+    }
+
+    Ok(())
+}
+
+fn validate_derive_config(config: &DeriveConfig) -> Result<()> {
+    if config.interval_secs == 0 {
+        bail!("derive.interval_secs must be greater than 0");
+    }
+
+    let mut names = std::collections::HashSet::new();
+    for rule in &config.log_metrics {
+        if rule.name.trim().is_empty() {
+            bail!("derive.log_metrics entries require a non-empty name");
+        }
+        if !names.insert(rule.name.as_str()) {
+            bail!(
+                "derive.log_metrics name '{}' is used more than once",
+                rule.name
+            );
+        }
+        if rule.group_by.iter().any(|key| key.trim().is_empty()) {
+            bail!(
+                "derive.log_metrics '{}' has an empty group_by entry",
+                rule.name
+            );
+        }
+    }
+
+    let spans = &config.span_metrics;
+    if spans.enabled {
+        const KINDS: [&str; 5] = ["internal", "server", "client", "producer", "consumer"];
+        if spans.span_kinds.is_empty() {
+            bail!("derive.span_metrics.span_kinds must list at least one span kind");
+        }
+        if let Some(kind) = spans
+            .span_kinds
+            .iter()
+            .find(|kind| !KINDS.contains(&kind.to_lowercase().as_str()))
+        {
+            bail!(
+                "derive.span_metrics.span_kinds: unknown span kind '{}'. Supported: {}",
+                kind,
+                KINDS.join(", ")
+            );
+        }
+        if spans.buckets_ms.is_empty()
+            || spans.buckets_ms.windows(2).any(|w| w[0] >= w[1])
+            || spans.buckets_ms.iter().any(|b| !b.is_finite())
+        {
+            bail!("derive.span_metrics.buckets_ms must be finite and strictly increasing");
+        }
+    }
+
+    if config.service_graph.enabled
+        && config
+            .service_graph
+            .peer_attributes
+            .iter()
+            .any(|key| key.trim().is_empty())
+    {
+        bail!("derive.service_graph.peer_attributes must not contain empty entries");
+    }
+
+    Ok(())
+}
+
+fn validate_events_config(config: &EventsConfig) -> Result<()> {
+    let mut names = std::collections::HashSet::new();
+    for key in &config.columns {
+        let column = crate::events::column_name(key);
+        if column.is_empty() {
+            bail!("events.columns must not contain empty entries");
+        }
+        if crate::events::FIXED_COLUMNS.contains(&column.as_str()) {
+            bail!(
+                "events.columns '{}' collides with the built-in '{}' column",
+                key,
+                column
+            );
+        }
+        if !names.insert(column.clone()) {
+            bail!(
+                "events.columns '{}' maps to column '{}' more than once",
+                key,
+                column
+            );
+        }
+    }
+    Ok(())
+}
+
+fn validate_catalog_config(config: &CatalogConfig, storage: &StorageConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    if !cfg!(feature = "catalog") {
+        bail!(
+            "catalog.enabled requires a build with the catalog feature \
+             (cargo build --features catalog)"
+        );
+    }
+    if config.resolved_path(storage).is_none() {
+        bail!(
+            "catalog.path is required unless the filesystem storage backend is used\n\n\
+            How to fix:\n\
+              • Environment: export {}CATALOG_PATH=/var/lib/otlp2parquet/catalog.sqlite\n\
+              • TOML: [catalog]\n              path = \"/var/lib/otlp2parquet/catalog.sqlite\"\n",
+            ENV_PREFIX
+        );
+    }
+    Ok(())
+}
+
+fn validate_clickhouse_config(config: &ClickHouseConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    if !config.url.starts_with("http://") && !config.url.starts_with("https://") {
+        bail!(
+            "clickhouse.url must be an http(s) URL of the HTTP interface (got '{}')",
+            config.url
+        );
+    }
+    if config.database.trim().is_empty() {
+        bail!("clickhouse.database must not be empty");
+    }
+    if !config
+        .table_prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        bail!(
+            "clickhouse.table_prefix may only contain letters, digits and underscores (got '{}')",
+            config.table_prefix
+        );
+    }
+    if config.timeout_secs == 0 {
+        bail!("clickhouse.timeout_secs must be greater than 0");
+    }
+    if config.queue_capacity == 0 {
+        bail!("clickhouse.queue_capacity must be greater than 0");
+    }
+    Ok(())
+}
+
+fn validate_routes(routes: &[RouteConfig]) -> Result<()> {
+    let mut names = std::collections::HashSet::new();
+    for route in routes {
+        if route.name.trim().is_empty() {
+            bail!("routes entries require a non-empty name");
+        }
+        if !names.insert(route.name.as_str()) {
+            bail!("routes name '{}' is used more than once", route.name);
+        }
+        for signal in &route.signals {
+            if !matches!(signal.as_str(), "logs" | "traces" | "metrics") {
+                bail!(
+                    "routes '{}' has unknown signal '{}' (expected logs, traces or metrics)",
+                    route.name,
+                    signal
+                );
+            }
+        }
+        if route.service.as_deref().is_some_and(|s| s.is_empty()) {
+            bail!("routes '{}' has an empty service pattern", route.name);
+        }
+        if route
+            .resource_attributes
+            .keys()
+            .any(|k| k.trim().is_empty())
+        {
+            bail!(
+                "routes '{}' has an empty resource attribute key",
+                route.name
+            );
+        }
+        validate_storage_config(&route.storage)
+            .with_context(|| format!("routes '{}' storage", route.name))?;
+    }
+    Ok(())
+}
+
+fn validate_fallbacks(fallbacks: &[FallbackConfig]) -> Result<()> {
+    let mut names = std::collections::HashSet::new();
+    for fallback in fallbacks {
+        if fallback.name.trim().is_empty() {
+            bail!("fallbacks entries require a non-empty name");
+        }
+        if !names.insert(fallback.name.as_str()) {
+            bail!("fallbacks name '{}' is used more than once", fallback.name);
+        }
+        validate_storage_config(&fallback.storage)
+            .with_context(|| format!("fallbacks '{}' storage", fallback.name))?;
+    }
+    Ok(())
+}
+
+fn validate_replication_config(config: &ReplicationConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    let Some(storage) = &config.storage else {
+        bail!(
+            "replication.enabled requires a replica target\n\n\
+            How to fix:\n\
+              • TOML: [replication.storage]\n              backend = \"s3\"\n\
+              [replication.storage.s3]\n              bucket = \"otlp-dr\"\n              region = \"us-west-2\"\n"
+        );
+    };
+    validate_storage_config(storage).context("replication storage")?;
+    if config.catalog_path.is_some() && !cfg!(feature = "catalog") {
+        bail!(
+            "replication.catalog_path requires a build with the catalog feature \
+             (cargo build --features catalog)"
+        );
+    }
+    Ok(())
+}
+
+fn validate_tiering_config(config: &TieringConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    let Some(storage) = &config.storage else {
+        bail!(
+            "tiering.enabled requires a cold target\n\n\
+            How to fix:\n\
+              • TOML: [tiering.storage]\n              backend = \"s3\"\n\
+              [tiering.storage.s3]\n              bucket = \"otlp-cold\"\n              region = \"us-east-1\"\n              storage_class = \"STANDARD_IA\"\n"
+        );
+    };
+    validate_storage_config(storage).context("tiering storage")?;
+    if config.cold_after_days == 0 {
+        bail!("tiering.cold_after_days must be at least 1; the current day stays hot");
+    }
+    if config.interval_secs == 0 {
+        bail!("tiering.interval_secs must be greater than 0");
+    }
+    Ok(())
+}
+
+fn validate_enrichment_config(config: &EnrichmentConfig) -> Result<()> {
+    for (key, template) in &config.resource_attributes {
+        if key.trim().is_empty() {
+            bail!("enrichment.resource_attributes must not contain empty keys");
+        }
+        crate::enrich::check_template(template)
+            .with_context(|| format!("enrichment.resource_attributes.{}", key))?;
+    }
+
+    let k8s = &config.kubernetes;
+    if k8s.enabled && !cfg!(feature = "k8s-enrichment") {
+        bail!(
+            "enrichment.kubernetes.enabled requires a build with the k8s-enrichment feature \
+             (cargo build --features k8s-enrichment)"
+        );
+    }
+    if k8s
+        .node_name
+        .as_deref()
+        .is_some_and(|n| n.trim().is_empty())
+    {
+        bail!("enrichment.kubernetes.node_name must not be empty when set");
+    }
+
+    let geoip = &config.geoip;
+    if geoip.enabled {
+        if !cfg!(feature = "geoip") {
+            bail!(
+                "enrichment.geoip.enabled requires a build with the geoip feature \
+                 (cargo build --features geoip)"
+            );
+        }
+        if geoip.city_database.is_none() && geoip.asn_database.is_none() {
+            bail!("enrichment.geoip requires city_database and/or asn_database");
+        }
+        if geoip.source_attributes.is_empty() {
+            bail!("enrichment.geoip.source_attributes must list at least one attribute");
+        }
+        const SIGNALS: [&str; 3] = ["logs", "traces", "metrics"];
+        if let Some(signal) = geoip
+            .signals
+            .iter()
+            .find(|s| !SIGNALS.contains(&s.as_str()))
+        {
+            bail!(
+                "enrichment.geoip.signals: unknown signal '{}'. Supported: {}",
+                signal,
+                SIGNALS.join(", ")
+            );
+        }
+    }
+
+    let mut columns = std::collections::HashSet::new();
+    for key in &config.baggage_keys {
+        if key.trim().is_empty() {
+            bail!("enrichment.baggage_keys must not contain empty keys");
+        }
+        if !columns.insert(crate::events::column_name(key)) {
+            bail!(
+                "enrichment.baggage_keys: '{}' maps to the same column as another key",
+                key
+            );
+        }
+    }
+    Ok(())
+}
+
+fn validate_probe_config(config: &ProbeConfig, batch: &BatchConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+
+    if config.interval_secs == 0 {
+        bail!("probe.interval_secs must be greater than 0");
+    }
+
+    if config.timeout_secs == 0 {
+        bail!("probe.timeout_secs must be greater than 0");
+    }
+
+    // Probe records sit in the batcher until max_age, so shorter timeouts always fail
+    if batch.enabled && config.timeout_secs <= batch.max_age_secs {
+        warn!(
+            timeout_secs = config.timeout_secs,
+            max_age_secs = batch.max_age_secs,
+            "probe.timeout_secs is not longer than batch.max_age_secs; probes will time out"
+        );
+    }
+
     Ok(())
 }
 
@@ -91,6 +790,9 @@ fn validate_storage_config(config: &StorageConfig) -> Result<()> {
                     ENV_PREFIX
                 );
             }
+            if fs.max_disk_bytes == Some(0) {
+                bail!("storage.fs.max_disk_bytes must be greater than 0 (omit it for no limit)");
+            }
         }
         StorageBackend::S3 => {
             let s3 = config
@@ -169,6 +871,139 @@ fn validate_storage_config(config: &StorageConfig) -> Result<()> {
                 );
             }
         }
+        StorageBackend::Sftp => {
+            let sftp = config.sftp.as_ref().ok_or_else(|| {
+                anyhow::anyhow!("sftp storage backend requires 'sftp' configuration")
+            })?;
+
+            if sftp.endpoint.is_empty() {
+                bail!(
+                    "SFTP endpoint is required\n\n\
+                    How to fix:\n\
+                      • Environment: export {}SFTP_ENDPOINT=ssh://otlp@sftp.internal:22\n\
+                      • TOML: [storage.sftp]\n              endpoint = \"ssh://otlp@sftp.internal:22\"\n",
+                    ENV_PREFIX
+                );
+            }
+            if !matches!(sftp.known_hosts.as_str(), "strict" | "add" | "accept") {
+                bail!(
+                    "storage.sftp.known_hosts must be strict, add or accept (got '{}')",
+                    sftp.known_hosts
+                );
+            }
+        }
+        StorageBackend::Webdav => {
+            let webdav = config.webdav.as_ref().ok_or_else(|| {
+                anyhow::anyhow!("webdav storage backend requires 'webdav' configuration")
+            })?;
+
+            if !webdav.endpoint.starts_with("http://") && !webdav.endpoint.starts_with("https://") {
+                bail!(
+                    "WebDAV endpoint must be an http(s) URL (got '{}')\n\n\
+                    How to fix:\n\
+                      • Environment: export {}WEBDAV_ENDPOINT=https://dav.internal/otlp\n\
+                      • TOML: [storage.webdav]\n              endpoint = \"https://dav.internal/otlp\"\n",
+                    webdav.endpoint,
+                    ENV_PREFIX
+                );
+            }
+            if webdav.token.is_some() && webdav.username.is_some() {
+                bail!("storage.webdav: set either token or username/password, not both");
+            }
+        }
+        StorageBackend::Hdfs => {
+            let hdfs = config.hdfs.as_ref().ok_or_else(|| {
+                anyhow::anyhow!("hdfs storage backend requires 'hdfs' configuration")
+            })?;
+
+            if !hdfs.endpoint.starts_with("http://") && !hdfs.endpoint.starts_with("https://") {
+                bail!(
+                    "HDFS endpoint must be the NameNode or HttpFS http(s) address (got '{}')\n\n\
+                    How to fix:\n\
+                      • Environment: export {}HDFS_ENDPOINT=http://namenode:9870\n\
+                      • TOML: [storage.hdfs]\n              endpoint = \"http://namenode:9870\"\n",
+                    hdfs.endpoint,
+                    ENV_PREFIX
+                );
+            }
+            if hdfs.user.is_some() && hdfs.delegation_token.is_some() {
+                bail!("storage.hdfs: set either user or delegation_token, not both");
+            }
+        }
+        StorageBackend::Gcs => {
+            let gcs = config.gcs.as_ref().ok_or_else(|| {
+                anyhow::anyhow!("gcs storage backend requires 'gcs' configuration")
+            })?;
+
+            if gcs.bucket.is_empty() {
+                bail!(
+                    "GCS bucket name is required\n\n\
+                    How to fix:\n\
+                      • Environment: export {}GCS_BUCKET=my-bucket\n\
+                      • TOML: [storage.gcs]\n              bucket = \"my-bucket\"\n",
+                    ENV_PREFIX
+                );
+            }
+            if gcs.credential_path.is_some() && gcs.credential.is_some() {
+                bail!("storage.gcs: set either credential_path or credential, not both");
+            }
+        }
+        StorageBackend::Azblob => {
+            let azblob = config.azblob.as_ref().ok_or_else(|| {
+                anyhow::anyhow!("azblob storage backend requires 'azblob' configuration")
+            })?;
+
+            if azblob.container.is_empty() {
+                bail!(
+                    "Azure container name is required\n\n\
+                    How to fix:\n\
+                      • Environment: export {}AZBLOB_CONTAINER=otlp\n\
+                      • TOML: [storage.azblob]\n              container = \"otlp\"\n",
+                    ENV_PREFIX
+                );
+            }
+            if azblob.account_name.is_empty() {
+                bail!(
+                    "Azure storage account name is required\n\n\
+                    How to fix:\n\
+                      • Environment: export {}AZBLOB_ACCOUNT_NAME=mystorageaccount\n\
+                      • TOML: [storage.azblob]\n              account_name = \"mystorageaccount\"\n",
+                    ENV_PREFIX
+                );
+            }
+            if azblob.account_key.is_some() && azblob.sas_token.is_some() {
+                bail!("storage.azblob: set either account_key or sas_token, not both");
+            }
+        }
+        StorageBackend::Null => {}
+    }
+
+    if let Some(level) = config.parquet_compression_level {
+        let range = match config.parquet_compression {
+            ParquetCompression::Gzip => 0..=10,
+            ParquetCompression::Zstd => 1..=22,
+            other => bail!(
+                "storage.parquet_compression_level only applies to gzip and zstd (compression is {:?})",
+                other
+            ),
+        };
+        if !range.contains(&level) {
+            bail!(
+                "storage.parquet_compression_level for {:?} must be {}-{} (got {})",
+                config.parquet_compression,
+                range.start(),
+                range.end(),
+                level
+            );
+        }
+    }
+
+    if config.write_shards > MAX_WRITE_SHARDS {
+        bail!(
+            "storage.write_shards must be at most {} (got {})",
+            MAX_WRITE_SHARDS,
+            config.write_shards
+        );
     }
 
     Ok(())
@@ -184,6 +1019,21 @@ fn validate_server_config(config: &ServerConfig) -> Result<()> {
         bail!("server.listen_addr must be in format 'host:port'");
     }
 
+    if let Some(addr) = &config.grpc_listen_addr {
+        if !cfg!(feature = "grpc") {
+            bail!(
+                "server.grpc_listen_addr requires a build with the grpc feature \
+                 (cargo build --features grpc)"
+            );
+        }
+        if !addr.contains(':') {
+            bail!("server.grpc_listen_addr must be in format 'host:port'");
+        }
+        if *addr == config.listen_addr {
+            bail!("server.grpc_listen_addr must differ from server.listen_addr");
+        }
+    }
+
     Ok(())
 }
 
@@ -210,6 +1060,29 @@ mod tests {
         assert!(validate_batch_config(&invalid_rows).is_err());
     }
 
+    #[test]
+    fn test_validate_probe_config() {
+        let batch = BatchConfig::default();
+        let disabled = ProbeConfig {
+            enabled: false,
+            interval_secs: 0,
+            timeout_secs: 0,
+        };
+        assert!(validate_probe_config(&disabled, &batch).is_ok());
+
+        let enabled = ProbeConfig {
+            enabled: true,
+            ..ProbeConfig::default()
+        };
+        assert!(validate_probe_config(&enabled, &batch).is_ok());
+
+        let zero_interval = ProbeConfig {
+            interval_secs: 0,
+            ..enabled
+        };
+        assert!(validate_probe_config(&zero_interval, &batch).is_err());
+    }
+
     #[test]
     fn test_validate_storage_config() {
         // Valid S3 config
@@ -221,8 +1094,22 @@ mod tests {
                 region: "us-east-1".to_string(),
                 endpoint: None,
                 prefix: None,
+                storage_class: None,
             }),
             r2: None,
+            sftp: None,
+            webdav: None,
+            hdfs: None,
+            gcs: None,
+            azblob: None,
+            verify_after_write: false,
+            write_manifests: false,
+            file_naming: FileNaming::Timestamp,
+            parquet_compression: ParquetCompression::Uncompressed,
+            parquet_compression_level: None,
+            parquet_bloom_filters: false,
+            write_shards: 0,
+            write_retries: 3,
         };
         assert!(validate_storage_config(&s3_config).is_ok());
 
@@ -235,9 +1122,109 @@ mod tests {
                 region: "us-east-1".to_string(),
                 endpoint: None,
                 prefix: None,
+                storage_class: None,
             }),
             r2: None,
+            sftp: None,
+            webdav: None,
+            hdfs: None,
+            gcs: None,
+            azblob: None,
+            verify_after_write: false,
+            write_manifests: false,
+            file_naming: FileNaming::Timestamp,
+            parquet_compression: ParquetCompression::Uncompressed,
+            parquet_compression_level: None,
+            parquet_bloom_filters: false,
+            write_shards: 0,
+            write_retries: 3,
         };
         assert!(validate_storage_config(&invalid_s3).is_err());
+
+        // GCS takes one kind of credential
+        let mut gcs = StorageConfig {
+            backend: StorageBackend::Gcs,
+            s3: None,
+            gcs: Some(GcsConfig {
+                bucket: "test-bucket".to_string(),
+                credential_path: Some("/etc/key.json".to_string()),
+                credential: None,
+                endpoint: None,
+                allow_anonymous: false,
+                prefix: None,
+            }),
+            ..invalid_s3
+        };
+        assert!(validate_storage_config(&gcs).is_ok());
+        gcs.gcs.as_mut().unwrap().credential = Some("e30=".to_string());
+        assert!(validate_storage_config(&gcs).is_err());
+
+        // Azure needs the account and at most one shared credential
+        let mut azblob = StorageConfig {
+            backend: StorageBackend::Azblob,
+            gcs: None,
+            azblob: Some(AzblobConfig {
+                container: "otlp".to_string(),
+                account_name: "otlpstore".to_string(),
+                account_key: None,
+                sas_token: Some("sv=2024-01-01&sig=abc".to_string()),
+                endpoint: None,
+                prefix: None,
+            }),
+            ..gcs
+        };
+        assert!(validate_storage_config(&azblob).is_ok());
+        azblob.azblob.as_mut().unwrap().account_key = Some("a2V5".to_string());
+        assert!(validate_storage_config(&azblob).is_err());
+    }
+
+    #[test]
+    fn test_validate_request_service_header() {
+        let infer = RequestConfig {
+            unattributed: UnattributedPolicy::Infer,
+            service_header: Some("X-Service".to_string()),
+            ..RequestConfig::default()
+        };
+        assert!(validate_request_config(&infer).is_ok());
+
+        // Raw API keys must not become service names and partition paths
+        let api_key = RequestConfig {
+            service_header: Some("X-API-Key".to_string()),
+            ..infer.clone()
+        };
+        assert!(validate_request_config(&api_key).is_err());
+        let authorization = RequestConfig {
+            service_header: Some("authorization".to_string()),
+            ..infer.clone()
+        };
+        assert!(validate_request_config(&authorization).is_err());
+
+        let mapped = RequestConfig {
+            header_services: [("key-123".to_string(), "checkout".to_string())].into(),
+            ..api_key
+        };
+        assert!(validate_request_config(&mapped).is_ok());
+    }
+
+    #[test]
+    fn test_validate_config_collects_problems() {
+        let mut config = RuntimeConfig::from_platform_defaults(Platform::Server);
+        config.storage.fs = Some(FsConfig {
+            path: "/data/otlp".to_string(),
+            ..FsConfig::default()
+        });
+        assert!(validate_config(&config).is_ok());
+
+        config.batch.max_age_secs = 0;
+        config.batch.max_bytes = 1024;
+        config.fallbacks.push(FallbackConfig {
+            name: "local".to_string(),
+            storage: config.storage.clone(),
+        });
+        let message = validate_config(&config).unwrap_err().to_string();
+        assert!(message.starts_with("Found 3 configuration problems"));
+        assert!(message.contains("1. batch.max_age_secs must be greater than 0"));
+        assert!(message.contains("BATCH_MAX_BYTES"));
+        assert!(message.contains("fallbacks 'local' points at the primary storage target"));
     }
 }
@@ -0,0 +1,426 @@
+//! Kubernetes manifest generation
+
+use crate::config::{LogFormat, RuntimeConfig, ServerConfig, StorageBackend};
+use crate::types::SignalType;
+use anyhow::{Context, Result};
+use clap::Args;
+
+const DEFAULT_PORT: u16 = 4318;
+const CONFIG_MOUNT_DIR: &str = "/etc/otlp2parquet";
+const FS_DATA_DIR: &str = "/data";
+const MIB: u64 = 1024 * 1024;
+/// Baseline resident memory of the server before any buffered data
+const BASE_MEMORY_BYTES: u64 = 64 * MIB;
+/// In-flight requests assumed when sizing memory for decoded payloads
+const CONCURRENT_REQUESTS: u64 = 4;
+
+#[derive(Args)]
+pub struct KubernetesArgs {
+    /// Name used for every generated resource
+    #[arg(long, default_value = "otlp2parquet")]
+    pub name: String,
+
+    /// Namespace for the generated resources
+    #[arg(long, default_value = "default")]
+    pub namespace: String,
+
+    /// Container image (default: ghcr.io/smithclay/otlp2parquet:<this version>)
+    #[arg(long)]
+    pub image: Option<String>,
+
+    /// Initial replica count (HPA minimum)
+    #[arg(long, default_value_t = 1)]
+    pub replicas: u32,
+
+    /// HPA maximum replica count
+    #[arg(long, default_value_t = 5)]
+    pub max_replicas: u32,
+
+    /// Existing Secret whose keys are exposed as environment variables
+    /// (e.g. AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY)
+    #[arg(long, value_name = "SECRET")]
+    pub credentials_secret: Option<String>,
+}
+
+pub(super) fn execute(args: KubernetesArgs, config: &RuntimeConfig) -> Result<()> {
+    let manifests = generate_manifests(&args, config)?;
+    println!("{}", manifests);
+    Ok(())
+}
+
+fn generate_manifests(args: &KubernetesArgs, config: &RuntimeConfig) -> Result<String> {
+    let port = listen_port(config);
+    let pod_config = pod_config(config, port);
+    let config_toml =
+        toml::to_string_pretty(&pod_config).context("Failed to render configuration")?;
+
+    let image = args.image.clone().unwrap_or_else(|| {
+        format!(
+            "ghcr.io/smithclay/otlp2parquet:{}",
+            env!("CARGO_PKG_VERSION")
+        )
+    });
+    let (memory_request, memory_limit) = memory_bounds(config);
+
+    // Leave time for the final flush of buffered batches on SIGTERM
+    let grace_period = if config.batch.enabled {
+        config.batch.max_age_secs + 30
+    } else {
+        30
+    };
+
+    let env_from = args
+        .credentials_secret
+        .as_deref()
+        .map(|secret| {
+            format!(
+                "          envFrom:\n            - secretRef:\n                name: {}\n",
+                secret
+            )
+        })
+        .unwrap_or_default();
+
+    let (data_mount, data_volume) = if config.storage.backend == StorageBackend::Fs {
+        (
+            format!(
+                "            - name: data\n              mountPath: {}\n",
+                FS_DATA_DIR
+            ),
+            // emptyDir is lost with the pod; use a PersistentVolumeClaim for anything real
+            "        - name: data\n          emptyDir: {}\n".to_string(),
+        )
+    } else {
+        (String::new(), String::new())
+    };
+
+    // Enrichment watches pods cluster-wide, which needs read access to them
+    let (service_account, rbac) = if config.enrichment.kubernetes.enabled {
+        (
+            format!("      serviceAccountName: {}\n", args.name),
+            format!(
+                r#"---
+apiVersion: v1
+kind: ServiceAccount
+metadata:
+  name: {name}
+  namespace: {namespace}
+---
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRole
+metadata:
+  name: {name}-pod-reader
+rules:
+  - apiGroups: [""]
+    resources: ["pods"]
+    verbs: ["get", "list", "watch"]
+---
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRoleBinding
+metadata:
+  name: {name}-pod-reader
+roleRef:
+  apiGroup: rbac.authorization.k8s.io
+  kind: ClusterRole
+  name: {name}-pod-reader
+subjects:
+  - kind: ServiceAccount
+    name: {name}
+    namespace: {namespace}
+"#,
+                name = args.name,
+                namespace = args.namespace,
+            ),
+        )
+    } else {
+        (String::new(), String::new())
+    };
+
+    Ok(format!(
+        r#"# Kubernetes manifests for otlp2parquet
+# Apply with:
+#   kubectl apply -f otlp2parquet.yaml
+---
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: {name}
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/name: {name}
+data:
+  config.toml: |
+{config}
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: {name}
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/name: {name}
+spec:
+  replicas: {replicas}
+  selector:
+    matchLabels:
+      app.kubernetes.io/name: {name}
+  template:
+    metadata:
+      labels:
+        app.kubernetes.io/name: {name}
+    spec:
+      terminationGracePeriodSeconds: {grace_period}
+{service_account}      containers:
+        - name: otlp2parquet
+          image: {image}
+          args: ["--config", "{mount_dir}/config.toml", "serve"]
+          ports:
+            - name: otlp-http
+              containerPort: {port}
+{env_from}          resources:
+            requests:
+              cpu: 250m
+              memory: {memory_request}
+            limits:
+              memory: {memory_limit}
+          livenessProbe:
+            httpGet:
+              path: /health
+              port: otlp-http
+            periodSeconds: 10
+          readinessProbe:
+            httpGet:
+              path: /ready
+              port: otlp-http
+            periodSeconds: 5
+          volumeMounts:
+            - name: config
+              mountPath: {mount_dir}
+              readOnly: true
+{data_mount}      volumes:
+        - name: config
+          configMap:
+            name: {name}
+{data_volume}---
+apiVersion: v1
+kind: Service
+metadata:
+  name: {name}
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/name: {name}
+spec:
+  selector:
+    app.kubernetes.io/name: {name}
+  ports:
+    - name: otlp-http
+      port: {port}
+      targetPort: otlp-http
+---
+apiVersion: autoscaling/v2
+kind: HorizontalPodAutoscaler
+metadata:
+  name: {name}
+  namespace: {namespace}
+spec:
+  scaleTargetRef:
+    apiVersion: apps/v1
+    kind: Deployment
+    name: {name}
+  minReplicas: {replicas}
+  maxReplicas: {max_replicas}
+  metrics:
+    - type: Resource
+      resource:
+        name: cpu
+        target:
+          type: Utilization
+          averageUtilization: 70
+{rbac}"#,
+        name = args.name,
+        namespace = args.namespace,
+        config = indent(&config_toml, 4),
+        replicas = args.replicas,
+        max_replicas = args.max_replicas.max(args.replicas),
+        grace_period = grace_period,
+        image = image,
+        mount_dir = CONFIG_MOUNT_DIR,
+        port = port,
+        env_from = env_from,
+        memory_request = format_mib(memory_request),
+        memory_limit = format_mib(memory_limit),
+        data_mount = data_mount,
+        data_volume = data_volume,
+        service_account = service_account,
+        rbac = rbac,
+    ))
+}
+
+/// Config as the pod should see it: bound on all interfaces, JSON logs,
+/// filesystem output on the mounted volume, and no inline credentials.
+fn pod_config(config: &RuntimeConfig, port: u16) -> RuntimeConfig {
+    let mut pod = config.clone();
+
+    let server = pod.server.get_or_insert_with(ServerConfig::default);
+    server.listen_addr = format!("0.0.0.0:{}", port);
+    server.log_format = LogFormat::Json;
+
+    if let Some(fs) = pod.storage.fs.as_mut() {
+        fs.path = FS_DATA_DIR.to_string();
+    }
+    // A Deployment's replicas land on any node: watch the whole cluster
+    pod.enrichment.kubernetes.node_name = None;
+    // R2 keys come from the credentials Secret via AWS_* env vars
+    if let Some(r2) = pod.storage.r2.as_mut() {
+        r2.access_key_id.clear();
+        r2.secret_access_key.clear();
+    }
+    // A GCS key comes from the Secret via OTLP2PARQUET_GCS_CREDENTIAL
+    if let Some(gcs) = pod.storage.gcs.as_mut() {
+        gcs.credential = None;
+    }
+    // ...and Azure keys via OTLP2PARQUET_AZBLOB_ACCOUNT_KEY / _SAS_TOKEN
+    if let Some(azblob) = pod.storage.azblob.as_mut() {
+        azblob.account_key = None;
+        azblob.sas_token = None;
+    }
+
+    pod
+}
+
+fn listen_port(config: &RuntimeConfig) -> u16 {
+    config
+        .server
+        .as_ref()
+        .and_then(|s| s.listen_addr.rsplit_once(':'))
+        .and_then(|(_, port)| port.parse().ok())
+        .unwrap_or(DEFAULT_PORT)
+}
+
+/// Memory request and limit derived from batch and payload limits.
+///
+/// The request covers one full batch buffer plus a few decoded payloads in
+/// flight; the limit doubles it to absorb several services flushing at once.
+fn memory_bounds(config: &RuntimeConfig) -> (u64, u64) {
+    let batch_bytes = if config.batch.enabled {
+        config.batch.max_bytes as u64
+    } else {
+        0
+    };
+    let max_payload = [SignalType::Logs, SignalType::Traces, SignalType::Metrics]
+        .into_iter()
+        .map(|signal| config.request.max_payload_bytes_for(signal))
+        .max()
+        .unwrap_or(config.request.max_payload_bytes);
+    let request = BASE_MEMORY_BYTES + batch_bytes + CONCURRENT_REQUESTS * max_payload as u64;
+    (request, request * 2)
+}
+
+fn format_mib(bytes: u64) -> String {
+    format!("{}Mi", bytes.div_ceil(MIB))
+}
+
+fn indent(text: &str, spaces: usize) -> String {
+    let pad = " ".repeat(spaces);
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", pad, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Platform;
+
+    fn args() -> KubernetesArgs {
+        KubernetesArgs {
+            name: "otlp2parquet".to_string(),
+            namespace: "observability".to_string(),
+            image: Some("example/otlp2parquet:test".to_string()),
+            replicas: 2,
+            max_replicas: 6,
+            credentials_secret: Some("otlp2parquet-creds".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_generate_manifests() {
+        let config = RuntimeConfig::from_platform_defaults(Platform::Server);
+        let yaml = generate_manifests(&args(), &config).unwrap();
+
+        assert!(yaml.contains("kind: ConfigMap"));
+        assert!(yaml.contains("kind: Deployment"));
+        assert!(yaml.contains("kind: Service"));
+        assert!(yaml.contains("kind: HorizontalPodAutoscaler"));
+        assert!(yaml.contains("namespace: observability"));
+        assert!(yaml.contains("image: example/otlp2parquet:test"));
+        assert!(yaml.contains("minReplicas: 2"));
+        assert!(yaml.contains("maxReplicas: 6"));
+        assert!(yaml.contains("name: otlp2parquet-creds"));
+        assert!(yaml.contains("containerPort: 4318"));
+        assert!(yaml.contains("listen_addr = \"0.0.0.0:4318\""));
+        assert!(yaml.contains("emptyDir: {}"));
+    }
+
+    #[test]
+    fn test_enrichment_adds_pod_reader_rbac() {
+        let mut config = RuntimeConfig::from_platform_defaults(Platform::Server);
+        let yaml = generate_manifests(&args(), &config).unwrap();
+        assert!(!yaml.contains("kind: ClusterRole"));
+
+        config.enrichment.kubernetes.enabled = true;
+        config.enrichment.kubernetes.node_name = Some("node-a".to_string());
+        let yaml = generate_manifests(&args(), &config).unwrap();
+        assert!(yaml.contains("serviceAccountName: otlp2parquet"));
+        assert!(yaml.contains("kind: ClusterRoleBinding"));
+        assert!(yaml.contains("verbs: [\"get\", \"list\", \"watch\"]"));
+        assert!(!yaml.contains("node_name"));
+    }
+
+    #[test]
+    fn test_memory_bounds_follow_batch_limits() {
+        let mut config = RuntimeConfig::from_platform_defaults(Platform::Server);
+        config.batch.max_bytes = 256 * MIB as usize;
+        config.request.max_payload_bytes = 8 * MIB as usize;
+
+        let (request, limit) = memory_bounds(&config);
+        assert_eq!(format_mib(request), "352Mi");
+        assert_eq!(format_mib(limit), "704Mi");
+
+        config.batch.enabled = false;
+        let (request, _) = memory_bounds(&config);
+        assert_eq!(format_mib(request), "96Mi");
+
+        // The largest per-signal cap drives the in-flight estimate
+        config.request.logs_max_payload_bytes = Some(16 * MIB as usize);
+        let (request, _) = memory_bounds(&config);
+        assert_eq!(format_mib(request), "128Mi");
+    }
+
+    #[test]
+    fn test_pod_config_strips_credentials() {
+        let mut config = RuntimeConfig::from_platform_defaults(Platform::Server);
+        config.server.as_mut().unwrap().listen_addr = "127.0.0.1:9999".to_string();
+        config.storage.r2 = Some(crate::config::R2Config {
+            bucket: "b".to_string(),
+            account_id: "a".to_string(),
+            access_key_id: "key".to_string(),
+            secret_access_key: "secret".to_string(),
+            endpoint: None,
+            prefix: None,
+        });
+
+        let pod = pod_config(&config, listen_port(&config));
+        assert_eq!(pod.server.unwrap().listen_addr, "0.0.0.0:9999");
+        let r2 = pod.storage.r2.unwrap();
+        assert!(r2.access_key_id.is_empty());
+        assert!(r2.secret_access_key.is_empty());
+        assert_eq!(pod.storage.fs.unwrap().path, FS_DATA_DIR);
+    }
+}
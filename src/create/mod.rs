@@ -0,0 +1,23 @@
+//! Create command - renders deployment manifests from the effective configuration
+
+mod kubernetes;
+
+use crate::config::RuntimeConfig;
+use anyhow::Result;
+use clap::Subcommand;
+
+pub use kubernetes::KubernetesArgs;
+
+#[derive(Subcommand)]
+pub enum CreateCommand {
+    /// Generate Kubernetes manifests (ConfigMap, Deployment, Service, HPA)
+    Kubernetes(KubernetesArgs),
+}
+
+impl CreateCommand {
+    pub fn run(self, config: &RuntimeConfig) -> Result<()> {
+        match self {
+            CreateCommand::Kubernetes(args) => kubernetes::execute(args, config),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Log-to-metric rules
+
+use super::SeriesKey;
+use crate::config::LogMetricRule;
+use arrow::array::{Array, AsArray, RecordBatch, StringArray};
+use arrow::datatypes::Int32Type;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+pub(super) struct LogMetrics {
+    rules: Vec<LogMetricRule>,
+}
+
+impl LogMetrics {
+    pub(super) fn new(rules: &[LogMetricRule]) -> Option<Self> {
+        if rules.is_empty() {
+            return None;
+        }
+        Some(Self {
+            rules: rules.to_vec(),
+        })
+    }
+
+    /// Add every matching row of a decoded logs batch to `sums`.
+    pub(super) fn observe(&self, batch: &RecordBatch, sums: &mut BTreeMap<SeriesKey, f64>) {
+        let strings = |name: &str| {
+            batch
+                .column_by_name(name)
+                .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+        };
+        let service = strings("service_name");
+        let severity_text = strings("severity_text");
+        let body = strings("body");
+        let attributes = strings("log_attributes");
+        let severity_number = batch
+            .column_by_name("severity_number")
+            .and_then(|c| c.as_primitive_opt::<Int32Type>());
+
+        for row in 0..batch.num_rows() {
+            let text = |array: Option<&StringArray>| {
+                array
+                    .filter(|a| a.is_valid(row))
+                    .map(|a| a.value(row))
+                    .unwrap_or_default()
+            };
+            let severity = severity_number
+                .filter(|a| a.is_valid(row))
+                .map(|a| a.value(row))
+                .unwrap_or_default();
+            let raw_attributes = text(attributes);
+            let mut parsed: Option<Map<String, Value>> = None;
+
+            for rule in &self.rules {
+                if rule.min_severity_number.is_some_and(|min| severity < min)
+                    || rule.service.as_deref().is_some_and(|s| s != text(service))
+                    || rule
+                        .body_contains
+                        .as_deref()
+                        .is_some_and(|needle| !text(body).contains(needle))
+                {
+                    continue;
+                }
+
+                let value = match rule.value_attribute.as_deref() {
+                    None => 1.0,
+                    Some(key) => match lazy_attributes(&mut parsed, raw_attributes)
+                        .get(key)
+                        .and_then(numeric)
+                    {
+                        Some(value) => value,
+                        None => continue,
+                    },
+                };
+
+                let group = rule
+                    .group_by
+                    .iter()
+                    .map(|key| {
+                        let value = match key.as_str() {
+                            "severity_text" => text(severity_text).to_string(),
+                            "severity_number" => severity.to_string(),
+                            _ => lazy_attributes(&mut parsed, raw_attributes)
+                                .get(key)
+                                .map(display)
+                                .unwrap_or_default(),
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect();
+
+                *sums
+                    .entry(SeriesKey {
+                        metric: rule.name.clone(),
+                        service: text(service).to_string(),
+                        attributes: group,
+                    })
+                    .or_default() += value;
+            }
+        }
+    }
+}
+
+/// Parse attributes on first use: most rules never look at them
+fn lazy_attributes<'a>(
+    parsed: &'a mut Option<Map<String, Value>>,
+    raw: &str,
+) -> &'a Map<String, Value> {
+    parsed.get_or_insert_with(|| serde_json::from_str(raw).unwrap_or_default())
+}
+
+fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,500 @@
+// Derived metrics
+//
+// Aggregates values computed from other signals (log record counts, span
+// RED metrics) into delta sums and histograms per window, then emits them as
+// an OTLP JSON request through the regular metrics pipeline so they land in
+// the normal sum and histogram tables. Service graph edges have no OTLP
+// equivalent and are written to their own `service_graph` table.
+
+mod logs;
+mod service_graph;
+mod spans;
+
+use crate::clock::{now_micros, now_unix_nanos};
+use crate::config::DeriveConfig;
+use crate::{AppState, InputFormat};
+use metrics::counter;
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use logs::LogMetrics;
+use service_graph::ServiceGraph;
+use spans::SpanMetrics;
+
+/// Instrumentation scope on every derived data point
+const SCOPE_NAME: &str = "otlp2parquet.derive";
+
+/// Identity of one derived series within a window
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SeriesKey {
+    metric: String,
+    service: String,
+    attributes: Vec<(String, String)>,
+}
+
+/// Explicit-bucket histogram accumulated within a window
+#[derive(Debug, Clone, PartialEq)]
+struct HistogramPoint {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    /// One more entry than the bounds: the last bucket is (last bound, +inf)
+    bucket_counts: Vec<u64>,
+}
+
+impl HistogramPoint {
+    fn new(bounds: &[f64]) -> Self {
+        Self {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            bucket_counts: vec![0; bounds.len() + 1],
+        }
+    }
+
+    fn record(&mut self, bounds: &[f64], value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        // OTLP buckets are upper-inclusive: (bounds[i-1], bounds[i]]
+        let bucket = bounds.partition_point(|bound| *bound < value);
+        if let Some(slot) = self.bucket_counts.get_mut(bucket) {
+            *slot += 1;
+        }
+    }
+}
+
+/// Delta values accumulated since the window started
+struct Window {
+    start_nanos: u128,
+    sums: BTreeMap<SeriesKey, f64>,
+    histograms: BTreeMap<SeriesKey, HistogramPoint>,
+}
+
+impl Window {
+    fn is_empty(&self) -> bool {
+        self.sums.is_empty() && self.histograms.is_empty()
+    }
+}
+
+pub(crate) struct Derived {
+    logs: Option<LogMetrics>,
+    spans: Option<SpanMetrics>,
+    graph: Option<ServiceGraph>,
+    interval_secs: u64,
+    window: Mutex<Window>,
+}
+
+impl Derived {
+    /// `None` when no derivation is configured.
+    pub(crate) fn from_config(config: &DeriveConfig) -> Option<Self> {
+        let logs = LogMetrics::new(&config.log_metrics);
+        let spans = SpanMetrics::new(&config.span_metrics);
+        let graph = ServiceGraph::new(&config.service_graph, now_micros());
+        if logs.is_none() && spans.is_none() && graph.is_none() {
+            return None;
+        }
+        Some(Self {
+            logs,
+            spans,
+            graph,
+            interval_secs: config.interval_secs,
+            window: Mutex::new(Window {
+                start_nanos: now_unix_nanos(),
+                sums: BTreeMap::new(),
+                histograms: BTreeMap::new(),
+            }),
+        })
+    }
+
+    /// Fold decoded log batches into the current window.
+    pub(crate) fn observe_logs(&self, grouped: &crate::codec::ServiceGroupedBatches) {
+        let Some(ref logs) = self.logs else {
+            return;
+        };
+        let mut window = self.window.lock();
+        for pb in &grouped.batches {
+            logs.observe(&pb.batch, &mut window.sums);
+        }
+    }
+
+    /// Fold decoded span batches into the current window.
+    pub(crate) fn observe_spans(&self, grouped: &crate::codec::ServiceGroupedBatches) {
+        if let Some(ref graph) = self.graph {
+            for pb in &grouped.batches {
+                graph.observe(&pb.batch);
+            }
+        }
+        let Some(ref spans) = self.spans else {
+            return;
+        };
+        let mut window = self.window.lock();
+        for pb in &grouped.batches {
+            spans.observe(&pb.batch, &mut window);
+        }
+    }
+
+    /// Close the current window and render it as an OTLP JSON metrics request.
+    fn take_window(&self) -> Option<Vec<u8>> {
+        let now = now_unix_nanos();
+        let mut guard = self.window.lock();
+        let closed = std::mem::replace(
+            &mut *guard,
+            Window {
+                start_nanos: now,
+                sums: BTreeMap::new(),
+                histograms: BTreeMap::new(),
+            },
+        );
+        drop(guard);
+
+        if closed.is_empty() {
+            return None;
+        }
+        let bounds = self.spans.as_ref().map(|s| s.bounds()).unwrap_or_default();
+        Some(render_window(closed, bounds, now))
+    }
+
+    /// Emit the current window: metrics through the metrics pipeline, service
+    /// graph edges straight to their own table.
+    pub(crate) async fn emit(&self, state: &AppState) {
+        if let Some(body) = self.take_window() {
+            match crate::handlers::process_metrics(
+                state,
+                InputFormat::Json,
+                body.into(),
+                &axum::http::HeaderMap::new(),
+            )
+            .await
+            {
+                Ok(_) => debug!("Emitted derived metrics"),
+                Err(e) => {
+                    counter!("otlp.derive.errors").increment(1);
+                    warn!(error = %e.into_error(), "Failed to emit derived metrics");
+                }
+            }
+        }
+
+        let edges = self
+            .graph
+            .as_ref()
+            .and_then(|graph| graph.take(self.interval_secs, now_micros()));
+        if let Some((start_micros, batch)) = edges {
+            match crate::writer::write_table_batch(
+                service_graph::TABLE,
+                None,
+                start_micros,
+                &batch,
+                &[],
+            )
+            .await
+            {
+                Ok(path) => debug!(path = %path, edges = batch.num_rows(), "Wrote service graph"),
+                Err(e) => {
+                    counter!("otlp.derive.errors").increment(1);
+                    warn!(error = %e, "Failed to write service graph");
+                }
+            }
+        }
+    }
+}
+
+/// Emit derived metrics every `interval` until `shutdown` is set.
+pub(crate) async fn run_derived_metrics(
+    state: AppState,
+    derived: Arc<Derived>,
+    shutdown: Arc<AtomicBool>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    // First tick completes immediately
+    ticker.tick().await;
+
+    while !shutdown.load(Ordering::SeqCst) {
+        ticker.tick().await;
+        derived.emit(&state).await;
+    }
+}
+
+/// One resourceMetrics entry per service, one metric per name.
+fn render_window(window: Window, bounds: &[f64], end_nanos: u128) -> Vec<u8> {
+    let start = window.start_nanos.to_string();
+    let end = end_nanos.to_string();
+    let point = |key: &SeriesKey, fields: Value| {
+        let mut point = json!({
+            "attributes": key
+                .attributes
+                .iter()
+                .map(|(k, v)| json!({"key": k, "value": {"stringValue": v}}))
+                .collect::<Vec<_>>(),
+            "startTimeUnixNano": start,
+            "timeUnixNano": end,
+        });
+        if let (Some(point), Value::Object(fields)) = (point.as_object_mut(), fields) {
+            point.extend(fields);
+        }
+        point
+    };
+
+    // service -> metric name -> metric JSON
+    let mut by_service: BTreeMap<String, BTreeMap<String, Value>> = BTreeMap::new();
+    for (key, value) in &window.sums {
+        let metric = by_service
+            .entry(key.service.clone())
+            .or_default()
+            .entry(key.metric.clone())
+            .or_insert_with(|| {
+                json!({
+                    "name": key.metric,
+                    "sum": {
+                        // AGGREGATION_TEMPORALITY_DELTA
+                        "aggregationTemporality": 1,
+                        "isMonotonic": true,
+                        "dataPoints": [],
+                    }
+                })
+            });
+        if let Some(points) = metric["sum"]["dataPoints"].as_array_mut() {
+            points.push(point(key, json!({"asDouble": value})));
+        }
+    }
+    for (key, hist) in &window.histograms {
+        let metric = by_service
+            .entry(key.service.clone())
+            .or_default()
+            .entry(key.metric.clone())
+            .or_insert_with(|| {
+                json!({
+                    "name": key.metric,
+                    "unit": "ms",
+                    "histogram": {
+                        "aggregationTemporality": 1,
+                        "dataPoints": [],
+                    }
+                })
+            });
+        if let Some(points) = metric["histogram"]["dataPoints"].as_array_mut() {
+            points.push(point(
+                key,
+                json!({
+                    "count": hist.count.to_string(),
+                    "sum": hist.sum,
+                    "min": hist.min,
+                    "max": hist.max,
+                    "bucketCounts": hist
+                        .bucket_counts
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect::<Vec<_>>(),
+                    "explicitBounds": bounds,
+                }),
+            ));
+        }
+    }
+
+    let resource_metrics: Vec<Value> = by_service
+        .into_iter()
+        .map(|(service, metrics)| {
+            json!({
+                "resource": {
+                    "attributes": [{"key": "service.name", "value": {"stringValue": service}}]
+                },
+                "scopeMetrics": [{
+                    "scope": {"name": SCOPE_NAME},
+                    "metrics": metrics.into_values().collect::<Vec<_>>(),
+                }]
+            })
+        })
+        .collect();
+
+    json!({ "resourceMetrics": resource_metrics })
+        .to_string()
+        .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{
+        decode_logs_partitioned, decode_metrics_partitioned, decode_traces_partitioned,
+    };
+    use crate::config::{LogMetricRule, SpanMetricsConfig};
+
+    fn logs_payload() -> Vec<u8> {
+        let record = |severity: i32, text: &str, route: &str| {
+            json!({
+                "timeUnixNano": "1700000000000000000",
+                "severityNumber": severity,
+                "severityText": text,
+                "body": {"stringValue": "request handled"},
+                "attributes": [{"key": "http.route", "value": {"stringValue": route}}]
+            })
+        };
+        json!({
+            "resourceLogs": [{
+                "resource": {
+                    "attributes": [{"key": "service.name", "value": {"stringValue": "api"}}]
+                },
+                "scopeLogs": [{
+                    "logRecords": [
+                        record(9, "INFO", "/a"),
+                        record(17, "ERROR", "/a"),
+                        record(17, "ERROR", "/b")
+                    ]
+                }]
+            }]
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn rule(name: &str) -> LogMetricRule {
+        LogMetricRule {
+            name: name.to_string(),
+            min_severity_number: None,
+            service: None,
+            body_contains: None,
+            value_attribute: None,
+            group_by: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_no_rules_disables_derivation() {
+        assert!(Derived::from_config(&DeriveConfig::default()).is_none());
+    }
+
+    #[test]
+    fn test_log_rules_emit_sum_metrics() {
+        let config = DeriveConfig {
+            log_metrics: vec![
+                LogMetricRule {
+                    group_by: vec!["severity_text".to_string()],
+                    ..rule("log_records")
+                },
+                LogMetricRule {
+                    min_severity_number: Some(17),
+                    group_by: vec!["http.route".to_string()],
+                    ..rule("log_errors")
+                },
+            ],
+            ..DeriveConfig::default()
+        };
+        let derived = Derived::from_config(&config).unwrap();
+        let grouped = decode_logs_partitioned(&logs_payload(), InputFormat::Json).unwrap();
+        derived.observe_logs(&grouped);
+
+        {
+            let window = derived.window.lock();
+            let sum_for = |metric: &str, attr: (&str, &str)| {
+                window.sums.get(&SeriesKey {
+                    metric: metric.to_string(),
+                    service: "api".to_string(),
+                    attributes: vec![(attr.0.to_string(), attr.1.to_string())],
+                })
+            };
+            assert_eq!(
+                sum_for("log_records", ("severity_text", "INFO")),
+                Some(&1.0)
+            );
+            assert_eq!(
+                sum_for("log_records", ("severity_text", "ERROR")),
+                Some(&2.0)
+            );
+            assert_eq!(sum_for("log_errors", ("http.route", "/a")), Some(&1.0));
+            assert_eq!(sum_for("log_errors", ("http.route", "/b")), Some(&1.0));
+        }
+
+        let body = derived.take_window().unwrap();
+        let metrics = decode_metrics_partitioned(&body, InputFormat::Json).unwrap();
+        assert_eq!(metrics.sum.total_records, 4);
+        assert_eq!(metrics.sum.batches[0].service_name.as_ref(), "api");
+
+        // The window was reset
+        assert!(derived.take_window().is_none());
+    }
+
+    #[test]
+    fn test_histogram_buckets_are_upper_inclusive() {
+        let bounds = [10.0, 100.0];
+        let mut point = HistogramPoint::new(&bounds);
+        for value in [5.0, 10.0, 50.0, 500.0] {
+            point.record(&bounds, value);
+        }
+        assert_eq!(point.bucket_counts, vec![2, 1, 1]);
+        assert_eq!(point.count, 4);
+        assert_eq!(point.min, 5.0);
+        assert_eq!(point.max, 500.0);
+    }
+
+    #[test]
+    fn test_span_metrics_emit_red_metrics() {
+        let span = |kind: i32, status: i32, name: &str| {
+            json!({
+                "traceId": "5b8efff798038103d269b633813fc60c",
+                "spanId": "eee19b7ec3c1b174",
+                "name": name,
+                "kind": kind,
+                "startTimeUnixNano": "1700000000000000000",
+                "endTimeUnixNano": "1700000000120000000",
+                "status": {"code": status},
+                "attributes": [{"key": "http.route", "value": {"stringValue": "/users/:id"}}]
+            })
+        };
+        let payload = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [{"key": "service.name", "value": {"stringValue": "api"}}]
+                },
+                "scopeSpans": [{
+                    "spans": [
+                        span(2, 0, "GET /users/1"),
+                        span(2, 2, "GET /users/2"),
+                        // Client spans are not requests by default
+                        span(3, 0, "SELECT users")
+                    ]
+                }]
+            }]
+        })
+        .to_string()
+        .into_bytes();
+
+        let config = DeriveConfig {
+            span_metrics: SpanMetricsConfig {
+                enabled: true,
+                ..SpanMetricsConfig::default()
+            },
+            ..DeriveConfig::default()
+        };
+        let derived = Derived::from_config(&config).unwrap();
+        let grouped = decode_traces_partitioned(&payload, InputFormat::Json).unwrap();
+        derived.observe_spans(&grouped);
+
+        {
+            let window = derived.window.lock();
+            let key = |metric: &str| SeriesKey {
+                metric: metric.to_string(),
+                service: "api".to_string(),
+                attributes: vec![("route".to_string(), "/users/:id".to_string())],
+            };
+            assert_eq!(window.sums.get(&key(spans::CALLS_METRIC)), Some(&2.0));
+            assert_eq!(window.sums.get(&key(spans::ERRORS_METRIC)), Some(&1.0));
+            let duration = window.histograms.get(&key(spans::DURATION_METRIC)).unwrap();
+            assert_eq!(duration.count, 2);
+            assert_eq!(duration.sum, 240.0);
+        }
+
+        let body = derived.take_window().unwrap();
+        let metrics = decode_metrics_partitioned(&body, InputFormat::Json).unwrap();
+        assert_eq!(metrics.sum.total_records, 2);
+        assert_eq!(metrics.histogram.total_records, 1);
+    }
+}
@@ -0,0 +1,417 @@
+//! Service dependency edges derived from client/server span pairs
+//!
+//! A client (or producer) span and the server (or consumer) span whose parent
+//! it is form one call from the client's service to the server's. The halves
+//! usually arrive in different requests, so unmatched spans wait for one
+//! window boundary before they expire. Expiring client spans that name their
+//! peer (e.g. `peer.service`, `db.system`) become edges to a virtual node.
+
+use crate::config::ServiceGraphConfig;
+use arrow::array::{
+    Array, ArrayRef, AsArray, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray,
+    TimestampMicrosecondArray,
+};
+use arrow::datatypes::{DataType, Field, Int32Type, Int64Type, Schema, TimeUnit};
+use metrics::counter;
+use parking_lot::Mutex;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Directory (under the storage prefix) the edge table is written to
+pub(super) const TABLE: &str = "service_graph";
+
+/// Bound on spans waiting for their other half
+const MAX_PENDING: usize = 100_000;
+/// Bound on latency samples kept per edge per window
+const MAX_SAMPLES: usize = 1_024;
+
+/// OTLP STATUS_CODE_ERROR
+const STATUS_ERROR: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Client,
+    Server,
+}
+
+/// OTLP span kind to the side of a call it represents
+fn side(kind: i32) -> Option<Side> {
+    match kind {
+        3 | 4 => Some(Side::Client), // CLIENT, PRODUCER
+        2 | 5 => Some(Side::Server), // SERVER, CONSUMER
+        _ => None,
+    }
+}
+
+struct PendingSpan {
+    service: String,
+    duration_ms: f64,
+    error: bool,
+    /// Peer named by the client span's attributes, if any
+    peer: Option<String>,
+    generation: u64,
+}
+
+#[derive(Default)]
+struct EdgeStats {
+    calls: i64,
+    errors: i64,
+    latencies_ms: Vec<f64>,
+}
+
+/// (client service, server service, server is a virtual node)
+type EdgeKey = (String, String, bool);
+
+struct GraphState {
+    generation: u64,
+    window_start_micros: i64,
+    /// Client spans keyed by (trace_id, span_id)
+    clients: HashMap<(String, String), PendingSpan>,
+    /// Server spans keyed by (trace_id, parent_span_id)
+    servers: HashMap<(String, String), PendingSpan>,
+    edges: BTreeMap<EdgeKey, EdgeStats>,
+}
+
+pub(super) struct ServiceGraph {
+    peer_attributes: Vec<String>,
+    state: Mutex<GraphState>,
+}
+
+impl ServiceGraph {
+    pub(super) fn new(config: &ServiceGraphConfig, now_micros: i64) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        Some(Self {
+            peer_attributes: config.peer_attributes.clone(),
+            state: Mutex::new(GraphState {
+                generation: 0,
+                window_start_micros: now_micros,
+                clients: HashMap::new(),
+                servers: HashMap::new(),
+                edges: BTreeMap::new(),
+            }),
+        })
+    }
+
+    /// Match the client/server spans of a decoded batch.
+    pub(super) fn observe(&self, batch: &RecordBatch) {
+        let strings = |name: &str| {
+            batch
+                .column_by_name(name)
+                .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+        };
+        let trace_id = strings("trace_id");
+        let span_id = strings("span_id");
+        let parent_span_id = strings("parent_span_id");
+        let service = strings("service_name");
+        let attributes = strings("span_attributes");
+        let int32 = |name: &str| {
+            batch
+                .column_by_name(name)
+                .and_then(|c| c.as_primitive_opt::<Int32Type>())
+        };
+        let kind = int32("span_kind");
+        let status = int32("status_code");
+        let duration = batch
+            .column_by_name("duration")
+            .and_then(|c| c.as_primitive_opt::<Int64Type>());
+
+        let mut state = self.state.lock();
+        for row in 0..batch.num_rows() {
+            let text = |array: Option<&StringArray>| {
+                array
+                    .filter(|a| a.is_valid(row))
+                    .map(|a| a.value(row))
+                    .unwrap_or_default()
+            };
+            let Some(side) = kind
+                .filter(|a| a.is_valid(row))
+                .and_then(|a| side(a.value(row)))
+            else {
+                continue;
+            };
+            let key_span = match side {
+                Side::Client => text(span_id),
+                Side::Server => text(parent_span_id),
+            };
+            if text(trace_id).is_empty() || key_span.is_empty() {
+                continue;
+            }
+            let key = (text(trace_id).to_string(), key_span.to_string());
+
+            let span = PendingSpan {
+                service: text(service).to_string(),
+                duration_ms: duration
+                    .filter(|a| a.is_valid(row))
+                    .map(|a| a.value(row) as f64)
+                    .unwrap_or_default(),
+                error: status.is_some_and(|a| a.is_valid(row) && a.value(row) == STATUS_ERROR),
+                peer: match side {
+                    Side::Client => self.peer(text(attributes)),
+                    Side::Server => None,
+                },
+                generation: state.generation,
+            };
+            state.pair(side, key, span);
+        }
+    }
+
+    /// Close the window and return its edges, or `None` if there were none.
+    ///
+    /// Returns the window start alongside the batch for partitioning.
+    pub(super) fn take(&self, window_secs: u64, now_micros: i64) -> Option<(i64, RecordBatch)> {
+        let mut state = self.state.lock();
+        state.expire();
+        state.generation += 1;
+        let start = std::mem::replace(&mut state.window_start_micros, now_micros);
+        let edges = std::mem::take(&mut state.edges);
+        drop(state);
+
+        if edges.is_empty() {
+            return None;
+        }
+        match edges_batch(edges, start, window_secs) {
+            Ok(batch) => Some((start, batch)),
+            Err(e) => {
+                counter!("otlp.derive.errors").increment(1);
+                tracing::warn!(error = %e, "Failed to build service graph batch");
+                None
+            }
+        }
+    }
+
+    /// First configured peer attribute present on a client span
+    fn peer(&self, raw_attributes: &str) -> Option<String> {
+        if raw_attributes.is_empty() {
+            return None;
+        }
+        let attributes: Map<String, Value> = serde_json::from_str(raw_attributes).ok()?;
+        self.peer_attributes
+            .iter()
+            .find_map(|key| match attributes.get(key)? {
+                Value::String(s) if !s.is_empty() => Some(s.clone()),
+                _ => None,
+            })
+    }
+}
+
+impl GraphState {
+    fn pair(&mut self, side: Side, key: (String, String), span: PendingSpan) {
+        let (mine, theirs) = match side {
+            Side::Client => (&mut self.clients, &mut self.servers),
+            Side::Server => (&mut self.servers, &mut self.clients),
+        };
+
+        let Some(other) = theirs.remove(&key) else {
+            if mine.len() < MAX_PENDING {
+                mine.insert(key, span);
+            } else {
+                counter!("otlp.derive.service_graph.dropped").increment(1);
+            }
+            return;
+        };
+
+        let (client, server) = match side {
+            Side::Client => (span, other),
+            Side::Server => (other, span),
+        };
+        // Client-side duration includes the network hop
+        self.record(
+            (client.service, server.service, false),
+            client.duration_ms,
+            client.error || server.error,
+        );
+    }
+
+    fn record(&mut self, key: EdgeKey, latency_ms: f64, error: bool) {
+        let stats = self.edges.entry(key).or_default();
+        stats.calls += 1;
+        if error {
+            stats.errors += 1;
+        }
+        if stats.latencies_ms.len() < MAX_SAMPLES {
+            stats.latencies_ms.push(latency_ms);
+        }
+    }
+
+    /// Drop spans that have waited a full window; peers become virtual edges.
+    fn expire(&mut self) {
+        let current = self.generation;
+        self.servers.retain(|_, span| span.generation == current);
+
+        let expired: Vec<PendingSpan> = {
+            let (keep, expired): (HashMap<_, _>, HashMap<_, _>) = std::mem::take(&mut self.clients)
+                .into_iter()
+                .partition(|(_, span)| span.generation == current);
+            self.clients = keep;
+            expired.into_values().collect()
+        };
+        for span in expired {
+            if let Some(peer) = span.peer {
+                self.record((span.service, peer, true), span.duration_ms, span.error);
+            }
+        }
+    }
+}
+
+fn edges_schema() -> Schema {
+    Schema::new(vec![
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, None),
+            false,
+        ),
+        Field::new("window_seconds", DataType::Int64, false),
+        Field::new("client_service", DataType::Utf8, false),
+        Field::new("server_service", DataType::Utf8, false),
+        Field::new("virtual_node", DataType::Boolean, false),
+        Field::new("call_count", DataType::Int64, false),
+        Field::new("error_count", DataType::Int64, false),
+        Field::new("latency_p50_ms", DataType::Float64, true),
+        Field::new("latency_p95_ms", DataType::Float64, true),
+        Field::new("latency_p99_ms", DataType::Float64, true),
+    ])
+}
+
+fn edges_batch(
+    edges: BTreeMap<EdgeKey, EdgeStats>,
+    window_start_micros: i64,
+    window_secs: u64,
+) -> Result<RecordBatch, arrow::error::ArrowError> {
+    let rows = edges.len();
+    let mut clients = Vec::with_capacity(rows);
+    let mut servers = Vec::with_capacity(rows);
+    let mut virtual_nodes = Vec::with_capacity(rows);
+    let mut calls = Vec::with_capacity(rows);
+    let mut errors = Vec::with_capacity(rows);
+    let mut p50 = Vec::with_capacity(rows);
+    let mut p95 = Vec::with_capacity(rows);
+    let mut p99 = Vec::with_capacity(rows);
+
+    for ((client, server, virtual_node), mut stats) in edges {
+        stats.latencies_ms.sort_unstable_by(f64::total_cmp);
+        clients.push(client);
+        servers.push(server);
+        virtual_nodes.push(virtual_node);
+        calls.push(stats.calls);
+        errors.push(stats.errors);
+        p50.push(quantile(&stats.latencies_ms, 50));
+        p95.push(quantile(&stats.latencies_ms, 95));
+        p99.push(quantile(&stats.latencies_ms, 99));
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(TimestampMicrosecondArray::from(vec![
+            window_start_micros;
+            rows
+        ])),
+        Arc::new(Int64Array::from(vec![window_secs as i64; rows])),
+        Arc::new(StringArray::from(clients)),
+        Arc::new(StringArray::from(servers)),
+        Arc::new(BooleanArray::from(virtual_nodes)),
+        Arc::new(Int64Array::from(calls)),
+        Arc::new(Int64Array::from(errors)),
+        Arc::new(Float64Array::from(p50)),
+        Arc::new(Float64Array::from(p95)),
+        Arc::new(Float64Array::from(p99)),
+    ];
+    RecordBatch::try_new(Arc::new(edges_schema()), columns)
+}
+
+/// Nearest-rank percentile of sorted samples
+fn quantile(sorted: &[f64], percentile: usize) -> Option<f64> {
+    let rank = (sorted.len() * percentile).div_ceil(100).saturating_sub(1);
+    sorted.get(rank).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(service: &str, duration_ms: f64, peer: Option<&str>) -> PendingSpan {
+        PendingSpan {
+            service: service.to_string(),
+            duration_ms,
+            error: false,
+            peer: peer.map(str::to_string),
+            generation: 0,
+        }
+    }
+
+    fn graph() -> ServiceGraph {
+        ServiceGraph::new(
+            &ServiceGraphConfig {
+                enabled: true,
+                ..ServiceGraphConfig::default()
+            },
+            0,
+        )
+        .unwrap()
+    }
+
+    fn key(span_id: &str) -> (String, String) {
+        ("trace".to_string(), span_id.to_string())
+    }
+
+    #[test]
+    fn test_pairs_client_and_server_in_either_order() {
+        let graph = graph();
+        {
+            let mut state = graph.state.lock();
+            state.pair(Side::Client, key("a"), span("web", 30.0, None));
+            state.pair(Side::Server, key("a"), span("api", 20.0, None));
+            state.pair(Side::Server, key("b"), span("api", 10.0, None));
+            let mut late = span("web", 12.0, None);
+            late.error = true;
+            state.pair(Side::Client, key("b"), late);
+        }
+
+        let (start, batch) = graph.take(60, 1_000).unwrap();
+        assert_eq!(start, 0);
+        assert_eq!(batch.num_rows(), 1);
+        let col = |name: &str| batch.column_by_name(name).unwrap().clone();
+        assert_eq!(col("client_service").as_string::<i32>().value(0), "web");
+        assert_eq!(col("server_service").as_string::<i32>().value(0), "api");
+        assert_eq!(col("call_count").as_primitive::<Int64Type>().value(0), 2);
+        assert_eq!(col("error_count").as_primitive::<Int64Type>().value(0), 1);
+        let p50 = col("latency_p50_ms");
+        assert_eq!(
+            p50.as_primitive::<arrow::datatypes::Float64Type>().value(0),
+            12.0
+        );
+    }
+
+    #[test]
+    fn test_unmatched_client_with_peer_becomes_virtual_edge() {
+        let graph = graph();
+        graph
+            .state
+            .lock()
+            .pair(Side::Client, key("a"), span("api", 5.0, Some("postgresql")));
+
+        // Still waiting for its server span after the first window
+        assert!(graph.take(60, 1).is_none());
+
+        let (_, batch) = graph.take(60, 2).unwrap();
+        let virtual_node = batch.column_by_name("virtual_node").unwrap();
+        assert!(virtual_node.as_boolean().value(0));
+        assert_eq!(
+            batch
+                .column_by_name("server_service")
+                .unwrap()
+                .as_string::<i32>()
+                .value(0),
+            "postgresql"
+        );
+    }
+
+    #[test]
+    fn test_quantile() {
+        let samples: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(quantile(&samples, 50), Some(50.0));
+        assert_eq!(quantile(&samples, 99), Some(99.0));
+        assert_eq!(quantile(&[], 50), None);
+    }
+}
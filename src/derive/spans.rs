@@ -0,0 +1,124 @@
+//! Span RED metrics (requests, errors, duration) per service and route
+
+use super::{HistogramPoint, SeriesKey, Window};
+use crate::config::SpanMetricsConfig;
+use arrow::array::{Array, AsArray, RecordBatch, StringArray};
+use arrow::datatypes::{Int32Type, Int64Type};
+use serde_json::{Map, Value};
+
+pub(super) const CALLS_METRIC: &str = "span.calls";
+pub(super) const ERRORS_METRIC: &str = "span.errors";
+pub(super) const DURATION_METRIC: &str = "span.duration";
+
+/// OTLP STATUS_CODE_ERROR
+const STATUS_ERROR: i32 = 2;
+
+pub(super) struct SpanMetrics {
+    kinds: Vec<i32>,
+    route_attributes: Vec<String>,
+    bounds: Vec<f64>,
+}
+
+impl SpanMetrics {
+    pub(super) fn new(config: &SpanMetricsConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        Some(Self {
+            kinds: config
+                .span_kinds
+                .iter()
+                .filter_map(|kind| span_kind_code(kind))
+                .collect(),
+            route_attributes: config.route_attributes.clone(),
+            bounds: config.buckets_ms.clone(),
+        })
+    }
+
+    pub(super) fn bounds(&self) -> &[f64] {
+        &self.bounds
+    }
+
+    /// Count, error-count and time every selected span of a decoded batch.
+    pub(super) fn observe(&self, batch: &RecordBatch, window: &mut Window) {
+        let strings = |name: &str| {
+            batch
+                .column_by_name(name)
+                .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+        };
+        let int32 = |name: &str| {
+            batch
+                .column_by_name(name)
+                .and_then(|c| c.as_primitive_opt::<Int32Type>())
+        };
+        let service = strings("service_name");
+        let span_name = strings("span_name");
+        let attributes = strings("span_attributes");
+        let kind = int32("span_kind");
+        let status = int32("status_code");
+        let duration = batch
+            .column_by_name("duration")
+            .and_then(|c| c.as_primitive_opt::<Int64Type>());
+
+        for row in 0..batch.num_rows() {
+            let text = |array: Option<&StringArray>| {
+                array
+                    .filter(|a| a.is_valid(row))
+                    .map(|a| a.value(row))
+                    .unwrap_or_default()
+            };
+            let kind_code = kind.filter(|a| a.is_valid(row)).map(|a| a.value(row));
+            if !kind_code.is_some_and(|k| self.kinds.contains(&k)) {
+                continue;
+            }
+
+            let route = self
+                .route(text(attributes))
+                .unwrap_or_else(|| text(span_name).to_string());
+            let key = |metric: &str| SeriesKey {
+                metric: metric.to_string(),
+                service: text(service).to_string(),
+                attributes: vec![("route".to_string(), route.clone())],
+            };
+
+            *window.sums.entry(key(CALLS_METRIC)).or_default() += 1.0;
+            if status.is_some_and(|a| a.is_valid(row) && a.value(row) == STATUS_ERROR) {
+                *window.sums.entry(key(ERRORS_METRIC)).or_default() += 1.0;
+            }
+            if let Some(ms) = duration.filter(|a| a.is_valid(row)).map(|a| a.value(row)) {
+                window
+                    .histograms
+                    .entry(key(DURATION_METRIC))
+                    .or_insert_with(|| HistogramPoint::new(&self.bounds))
+                    .record(&self.bounds, ms as f64);
+            }
+        }
+    }
+
+    /// First configured route attribute present on the span
+    fn route(&self, raw_attributes: &str) -> Option<String> {
+        if raw_attributes.is_empty() {
+            return None;
+        }
+        let attributes: Map<String, Value> = serde_json::from_str(raw_attributes).ok()?;
+        self.route_attributes
+            .iter()
+            .find_map(|key| match attributes.get(key)? {
+                Value::String(s) => Some(s.clone()),
+                Value::Null => None,
+                other => Some(other.to_string()),
+            })
+    }
+}
+
+/// OTLP span kind number for a config name
+fn span_kind_code(name: &str) -> Option<i32> {
+    match name.to_lowercase().as_str() {
+        "internal" => Some(1),
+        "server" => Some(2),
+        "client" => Some(3),
+        "producer" => Some(4),
+        "consumer" => Some(5),
+        _ => None,
+    }
+}
@@ -0,0 +1,409 @@
+//! Doctor command - diagnoses configuration, connectivity, and storage access
+//!
+//! Prints the effective configuration (secrets redacted), then runs a series
+//! of checks and reports each with a suggested fix when it fails.
+
+use crate::config::{RuntimeConfig, StorageBackend};
+use anyhow::Result;
+use std::fmt;
+use std::time::Duration;
+
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Pass => write!(f, "✓"),
+            Status::Warn => write!(f, "!"),
+            Status::Fail => write!(f, "✗"),
+        }
+    }
+}
+
+/// Outcome of a single diagnostic
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Run every check against `config` and print a report to stdout.
+///
+/// Returns an error if any check failed, so the exit code is usable in scripts.
+pub async fn run(config: &RuntimeConfig) -> Result<()> {
+    println!("# Effective configuration (secrets redacted)\n");
+    match toml::to_string_pretty(&config.redacted()) {
+        Ok(rendered) => println!("{}", rendered),
+        Err(e) => println!("<failed to render config: {}>\n", e),
+    }
+
+    let mut checks = vec![check_config(config), check_listen_addr(config).await];
+
+    if let Some(endpoint) = storage_endpoint(config) {
+        let dns = check_dns(&endpoint).await;
+        let dns_ok = dns.status != Status::Fail;
+        checks.push(dns);
+        if dns_ok && endpoint.starts_with("https://") {
+            checks.push(check_tls(&endpoint).await);
+        }
+    }
+
+    checks.push(check_storage(config).await);
+
+    println!("# Checks\n");
+    for check in &checks {
+        println!("{} {}: {}", check.status, check.name, check.detail);
+        if let Some(fix) = &check.fix {
+            for line in fix.lines() {
+                println!("    {}", line);
+            }
+        }
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    let warned = checks.iter().filter(|c| c.status == Status::Warn).count();
+    println!();
+    if failed > 0 {
+        anyhow::bail!("{} check(s) failed, {} warning(s)", failed, warned);
+    }
+    println!("All checks passed ({} warning(s))", warned);
+    Ok(())
+}
+
+fn check_config(config: &RuntimeConfig) -> Check {
+    match config.validate() {
+        Ok(()) => Check::pass("config", "configuration is valid"),
+        // Validation errors already carry their own "How to fix" section
+        Err(e) => Check::fail("config", "configuration is invalid", format!("{:#}", e)),
+    }
+}
+
+async fn check_listen_addr(config: &RuntimeConfig) -> Check {
+    let Some(server) = config.server.as_ref() else {
+        return Check::fail(
+            "listen",
+            "no [server] section",
+            "Add a [server] section with listen_addr = \"0.0.0.0:4318\"",
+        );
+    };
+
+    match tokio::net::TcpListener::bind(&server.listen_addr).await {
+        Ok(_) => Check::pass("listen", format!("{} is available", server.listen_addr)),
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => Check::warn(
+            "listen",
+            format!("{} is already in use", server.listen_addr),
+            "Stop the process holding the port (possibly another otlp2parquet)\n\
+             or choose another port with --port or OTLP2PARQUET_LISTEN_ADDR",
+        ),
+        Err(e) => Check::fail(
+            "listen",
+            format!("cannot bind {}: {}", server.listen_addr, e),
+            "Use a host:port the current user may bind, e.g. 0.0.0.0:4318",
+        ),
+    }
+}
+
+async fn check_dns(endpoint: &str) -> Check {
+    let Some((host, port)) = endpoint_host_port(endpoint) else {
+        return Check::fail(
+            "dns",
+            format!("cannot parse endpoint '{}'", endpoint),
+            "Endpoints must look like https://host[:port]",
+        );
+    };
+
+    let lookup = tokio::net::lookup_host((host.as_str(), port));
+    match tokio::time::timeout(NETWORK_TIMEOUT, lookup).await {
+        Ok(Ok(mut addrs)) => match addrs.next() {
+            Some(addr) => Check::pass("dns", format!("{} resolves to {}", host, addr.ip())),
+            None => Check::fail(
+                "dns",
+                format!("{} resolved to no addresses", host),
+                "Check the endpoint hostname and your DNS configuration",
+            ),
+        },
+        Ok(Err(e)) => Check::fail(
+            "dns",
+            format!("cannot resolve {}: {}", host, e),
+            "Check the endpoint hostname (storage.s3.endpoint / storage.r2.account_id)\n\
+             and that this host can reach a DNS resolver",
+        ),
+        Err(_) => Check::fail(
+            "dns",
+            format!("resolving {} timed out", host),
+            "Check that this host can reach a DNS resolver",
+        ),
+    }
+}
+
+async fn check_tls(endpoint: &str) -> Check {
+    let client = match reqwest::Client::builder().timeout(NETWORK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            return Check::fail(
+                "tls",
+                format!("cannot build HTTP client: {}", e),
+                "Report this as a bug",
+            )
+        }
+    };
+
+    // Any HTTP response (even 403) means the TLS handshake succeeded
+    match client.head(endpoint).send().await {
+        Ok(response) => Check::pass(
+            "tls",
+            format!(
+                "handshake with {} ok (HTTP {})",
+                endpoint,
+                response.status()
+            ),
+        ),
+        Err(e) if e.is_timeout() => Check::fail(
+            "tls",
+            format!("connecting to {} timed out", endpoint),
+            "Check firewalls/proxies between this host and the storage endpoint",
+        ),
+        Err(e) => Check::fail(
+            "tls",
+            format!("cannot connect to {}: {}", endpoint, error_chain(&e)),
+            "Check the endpoint scheme and port; for self-signed MinIO use http://\n\
+             or install the CA certificate on this host",
+        ),
+    }
+}
+
+async fn check_storage(config: &RuntimeConfig) -> Check {
+    let op = match crate::writer::build_operator(&config.storage) {
+        Ok(op) => op,
+        Err(e) => {
+            return Check::fail(
+                "storage",
+                format!("cannot configure {} backend: {}", config.storage.backend, e),
+                "Fix the [storage] section reported above",
+            )
+        }
+    };
+
+    let probe = format!(
+        "{}.otlp2parquet-doctor-{}",
+        crate::writer::storage_prefix(&config.storage).unwrap_or_default(),
+        uuid::Uuid::new_v4()
+    );
+
+    if let Err(e) = op.write(&probe, b"doctor".to_vec()).await {
+        return Check::fail(
+            "storage",
+            format!("write to {} backend failed: {}", config.storage.backend, e),
+            storage_fix(config.storage.backend, e.kind()),
+        );
+    }
+    if let Err(e) = op.delete(&probe).await {
+        return Check::warn(
+            "storage",
+            format!("write ok but cleanup of '{}' failed: {}", probe, e),
+            "Grant delete permission or remove the probe object manually",
+        );
+    }
+
+    Check::pass(
+        "storage",
+        format!("write and delete on {} backend ok", config.storage.backend),
+    )
+}
+
+fn storage_fix(backend: StorageBackend, kind: opendal::ErrorKind) -> String {
+    match (backend, kind) {
+        (StorageBackend::Fs, opendal::ErrorKind::PermissionDenied) => {
+            "Make storage.fs.path writable by this user or choose another directory".to_string()
+        }
+        (
+            StorageBackend::Sftp | StorageBackend::Webdav | StorageBackend::Hdfs,
+            opendal::ErrorKind::PermissionDenied,
+        ) => "Check the user's credentials and that it may write below the configured root"
+            .to_string(),
+        (StorageBackend::Gcs, opendal::ErrorKind::PermissionDenied) => {
+            "Check the service account credentials (credential_path, credential, or\n\
+             GOOGLE_APPLICATION_CREDENTIALS) and that they have storage.objects.create\n\
+             and storage.objects.delete on the bucket"
+                .to_string()
+        }
+        (StorageBackend::Azblob, opendal::ErrorKind::PermissionDenied) => {
+            "Check account_key, sas_token, or the Azure AD identity, and that it may write\n\
+             and delete blobs in the container (e.g. Storage Blob Data Contributor)"
+                .to_string()
+        }
+        (_, opendal::ErrorKind::PermissionDenied) => {
+            "Check credentials (AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY, profile, or IAM role)\n\
+             and that they allow s3:PutObject and s3:DeleteObject on the bucket"
+                .to_string()
+        }
+        (_, opendal::ErrorKind::NotFound) => {
+            "The bucket does not exist; create it or fix the bucket name".to_string()
+        }
+        (_, opendal::ErrorKind::ConfigInvalid) => {
+            "Fix the [storage] section; see config.example.toml".to_string()
+        }
+        _ => "Check connectivity to the storage endpoint and the checks above".to_string(),
+    }
+}
+
+/// HTTPS/HTTP endpoint the storage backend talks to, if any.
+fn storage_endpoint(config: &RuntimeConfig) -> Option<String> {
+    match config.storage.backend {
+        // SFTP speaks SSH, not HTTP; the storage check covers it
+        StorageBackend::Fs | StorageBackend::Sftp | StorageBackend::Null => None,
+        StorageBackend::Webdav => config
+            .storage
+            .webdav
+            .as_ref()
+            .map(|webdav| webdav.endpoint.clone()),
+        StorageBackend::Hdfs => config
+            .storage
+            .hdfs
+            .as_ref()
+            .map(|hdfs| hdfs.endpoint.clone()),
+        StorageBackend::S3 => config.storage.s3.as_ref().map(|s3| {
+            s3.endpoint
+                .clone()
+                .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", s3.region))
+        }),
+        StorageBackend::Gcs => config.storage.gcs.as_ref().map(|gcs| {
+            gcs.endpoint
+                .clone()
+                .unwrap_or_else(|| "https://storage.googleapis.com".to_string())
+        }),
+        StorageBackend::Azblob => config
+            .storage
+            .azblob
+            .as_ref()
+            .map(|azblob| azblob.endpoint_or_default()),
+        StorageBackend::R2 => config.storage.r2.as_ref().map(|r2| {
+            r2.endpoint
+                .clone()
+                .unwrap_or_else(|| format!("https://{}.r2.cloudflarestorage.com", r2.account_id))
+        }),
+    }
+}
+
+/// Split `scheme://host[:port][/path]` into host and port (scheme default).
+fn endpoint_host_port(endpoint: &str) -> Option<(String, u16)> {
+    let (default_port, rest) = if let Some(rest) = endpoint.strip_prefix("https://") {
+        (443, rest)
+    } else if let Some(rest) = endpoint.strip_prefix("http://") {
+        (80, rest)
+    } else {
+        return None;
+    };
+
+    let authority = rest.split('/').next().unwrap_or_default();
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (authority, default_port),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), port))
+}
+
+/// reqwest hides the root cause (e.g. certificate errors) behind its source chain
+fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Platform, R2Config, S3Config};
+
+    #[test]
+    fn test_endpoint_host_port() {
+        assert_eq!(
+            endpoint_host_port("https://s3.us-east-1.amazonaws.com"),
+            Some(("s3.us-east-1.amazonaws.com".to_string(), 443))
+        );
+        assert_eq!(
+            endpoint_host_port("http://localhost:9000/bucket"),
+            Some(("localhost".to_string(), 9000))
+        );
+        assert_eq!(endpoint_host_port("localhost:9000"), None);
+        assert_eq!(endpoint_host_port("https://"), None);
+    }
+
+    #[test]
+    fn test_storage_endpoint_defaults() {
+        let mut config = RuntimeConfig::from_platform_defaults(Platform::Server);
+        assert_eq!(storage_endpoint(&config), None);
+
+        config.storage.backend = StorageBackend::S3;
+        config.storage.s3 = Some(S3Config {
+            bucket: "b".to_string(),
+            region: "eu-west-1".to_string(),
+            endpoint: None,
+            prefix: None,
+            storage_class: None,
+        });
+        assert_eq!(
+            storage_endpoint(&config).as_deref(),
+            Some("https://s3.eu-west-1.amazonaws.com")
+        );
+
+        config.storage.backend = StorageBackend::R2;
+        config.storage.r2 = Some(R2Config {
+            bucket: "b".to_string(),
+            account_id: "acct".to_string(),
+            access_key_id: String::new(),
+            secret_access_key: String::new(),
+            endpoint: None,
+            prefix: None,
+        });
+        assert_eq!(
+            storage_endpoint(&config).as_deref(),
+            Some("https://acct.r2.cloudflarestorage.com")
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! W3C Baggage capture into columns
+//!
+//! Copies an allow-list of baggage keys (tenant, feature flags, ...) into
+//! dedicated `baggage_<key>` columns on logs and spans, so analytics can slice
+//! by them without parsing attribute JSON or adding attribute cardinality. A
+//! record's value comes from its attributes (`<key>`, or `baggage.<key>` as
+//! written by baggage span processors) and falls back to the `baggage` header
+//! of the request that carried it.
+
+use crate::codec::ServiceGroupedBatches;
+use crate::events::column_name;
+use crate::SignalType;
+use arrow::array::{Array, ArrayRef, RecordBatch, StringArray, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::error::ArrowError;
+use metrics::counter;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Prefix of the added columns
+const COLUMN_PREFIX: &str = "baggage_";
+
+pub(crate) struct BaggageColumns {
+    /// (baggage key, column name) for each captured key
+    keys: Vec<(String, String)>,
+}
+
+impl BaggageColumns {
+    /// `None` when no keys are configured.
+    pub(crate) fn new(keys: &[String]) -> Option<Self> {
+        if keys.is_empty() {
+            return None;
+        }
+        Some(Self {
+            keys: keys
+                .iter()
+                .map(|key| {
+                    let key = key.trim().to_string();
+                    let column = format!("{}{}", COLUMN_PREFIX, column_name(&key));
+                    (key, column)
+                })
+                .collect(),
+        })
+    }
+
+    /// Column names in output order
+    pub(crate) fn columns(&self) -> impl Iterator<Item = &str> {
+        self.keys.iter().map(|(_, column)| column.as_str())
+    }
+
+    /// Add the baggage columns to every logs/traces batch. `header` is the
+    /// request's raw `baggage` header, if any.
+    pub(crate) fn enrich(
+        &self,
+        signal: SignalType,
+        header: Option<&str>,
+        grouped: &mut ServiceGroupedBatches,
+    ) {
+        let attributes_column = match signal {
+            SignalType::Logs => "log_attributes",
+            SignalType::Traces => "span_attributes",
+            SignalType::Metrics => return,
+        };
+        let header = header.map(parse_header).unwrap_or_default();
+        for pb in &mut grouped.batches {
+            match self.add_columns(&pb.batch, attributes_column, &header) {
+                Ok(Some((batch, rows))) => {
+                    counter!("otlp.enrich.baggage.records", "signal" => signal.as_str())
+                        .increment(rows);
+                    pb.batch = batch;
+                }
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, "Failed to add baggage columns"),
+            }
+        }
+    }
+
+    /// `batch` with one nullable string column per key, and the number of
+    /// rows that got at least one value. `None` when the columns exist already.
+    fn add_columns(
+        &self,
+        batch: &RecordBatch,
+        attributes_column: &str,
+        header: &HashMap<String, String>,
+    ) -> Result<Option<(RecordBatch, u64)>, ArrowError> {
+        let schema = batch.schema();
+        if self
+            .columns()
+            .any(|column| schema.column_with_name(column).is_some())
+        {
+            return Ok(None);
+        }
+        let attributes = batch
+            .column_by_name(attributes_column)
+            .and_then(|c| c.as_any().downcast_ref::<StringArray>());
+
+        let mut builders: Vec<StringBuilder> = self
+            .keys
+            .iter()
+            .map(|_| StringBuilder::with_capacity(batch.num_rows(), 0))
+            .collect();
+        let mut cache: HashMap<&str, Vec<Option<String>>> = HashMap::new();
+        let mut captured = 0u64;
+        for row in 0..batch.num_rows() {
+            let from_attributes = match attributes {
+                Some(c) if c.is_valid(row) => {
+                    let raw = c.value(row);
+                    Some(
+                        &*cache
+                            .entry(raw)
+                            .or_insert_with(|| self.from_attributes(raw)),
+                    )
+                }
+                _ => None,
+            };
+            let mut any = false;
+            for (index, (key, _)) in self.keys.iter().enumerate() {
+                let value = from_attributes
+                    .and_then(|values| values[index].as_deref())
+                    .or_else(|| header.get(key).map(String::as_str));
+                any |= value.is_some();
+                builders[index].append_option(value);
+            }
+            if any {
+                captured += 1;
+            }
+        }
+
+        let mut fields: Vec<Field> = schema.fields().iter().map(|f| f.as_ref().clone()).collect();
+        let mut columns = batch.columns().to_vec();
+        for (column, mut builder) in self.columns().zip(builders) {
+            fields.push(Field::new(column, DataType::Utf8, true));
+            columns.push(Arc::new(builder.finish()) as ArrayRef);
+        }
+        let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+        RecordBatch::try_new(Arc::new(schema), columns).map(|batch| Some((batch, captured)))
+    }
+
+    /// Value of each key in one record's attributes
+    fn from_attributes(&self, raw: &str) -> Vec<Option<String>> {
+        // Cheap pre-check: most attribute sets carry none of the keys
+        let attributes: Option<Map<String, Value>> = self
+            .keys
+            .iter()
+            .any(|(key, _)| raw.contains(key.as_str()))
+            .then(|| serde_json::from_str(raw).ok())
+            .flatten();
+        self.keys
+            .iter()
+            .map(|(key, _)| {
+                let attributes = attributes.as_ref()?;
+                let value = attributes
+                    .get(key)
+                    .or_else(|| attributes.get(&format!("baggage.{}", key)))?;
+                match value {
+                    Value::String(s) => Some(s.clone()),
+                    Value::Null => None,
+                    other => Some(other.to_string()),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Members of a W3C `baggage` header (`key1=value1;prop,key2=value2`), values
+/// percent-decoded. Malformed members are skipped.
+fn parse_header(header: &str) -> HashMap<String, String> {
+    header
+        .split(',')
+        .filter_map(|member| {
+            // Properties after ';' are metadata, not part of the value
+            let entry = member.split(';').next()?;
+            let (key, value) = entry.split_once('=')?;
+            let key = key.trim();
+            (!key.is_empty()).then(|| (key.to_string(), percent_decode(value.trim())))
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::AsArray;
+
+    #[test]
+    fn test_parse_header() {
+        let parsed = parse_header("tenant=acme;ttl=60, flag=new%20checkout,broken,=x");
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed["tenant"], "acme");
+        assert_eq!(parsed["flag"], "new checkout");
+    }
+
+    #[test]
+    fn test_attributes_win_over_header() {
+        let baggage =
+            BaggageColumns::new(&["tenant".to_string(), "feature.flag".to_string()]).unwrap();
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new(
+                "span_attributes",
+                DataType::Utf8,
+                true,
+            )])),
+            vec![Arc::new(StringArray::from(vec![
+                Some(r#"{"tenant":"globex"}"#),
+                Some(r#"{"baggage.feature.flag":"beta"}"#),
+                None,
+            ]))],
+        )
+        .unwrap();
+        let header = parse_header("tenant=acme");
+
+        let (batch, captured) = baggage
+            .add_columns(&batch, "span_attributes", &header)
+            .unwrap()
+            .unwrap();
+        assert_eq!(captured, 3);
+        let tenant = batch
+            .column_by_name("baggage_tenant")
+            .unwrap()
+            .as_string::<i32>();
+        assert_eq!(tenant.value(0), "globex");
+        assert_eq!(tenant.value(1), "acme");
+        assert_eq!(tenant.value(2), "acme");
+        let flag = batch
+            .column_by_name("baggage_feature_flag")
+            .unwrap()
+            .as_string::<i32>();
+        assert!(flag.is_null(0));
+        assert_eq!(flag.value(1), "beta");
+    }
+}
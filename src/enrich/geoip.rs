@@ -0,0 +1,166 @@
+//! GeoIP lookups of client addresses
+//!
+//! Resolves the first configured client address attribute of each record
+//! against MaxMind City and ASN databases and adds `geo.*` attributes (plus
+//! `as.number` / `as.organization.name`) next to it. Doing this once at ingest
+//! keeps geolocation out of every query over the Parquet files.
+
+use super::rewrite_json_column;
+use crate::codec::ServiceGroupedBatches;
+use crate::config::GeoIpConfig;
+use crate::SignalType;
+use anyhow::Context;
+use maxminddb::{geoip2, Reader};
+use metrics::counter;
+use serde_json::{Map, Value};
+use std::net::IpAddr;
+use tracing::warn;
+
+pub(crate) struct GeoIpEnricher {
+    city: Option<Reader<Vec<u8>>>,
+    asn: Option<Reader<Vec<u8>>>,
+    source_attributes: Vec<String>,
+    signals: Vec<SignalType>,
+}
+
+impl GeoIpEnricher {
+    /// Load the configured databases into memory.
+    pub(crate) fn open(config: &GeoIpConfig) -> anyhow::Result<Self> {
+        let open = |path: &Option<String>| {
+            path.as_deref()
+                .map(|path| {
+                    Reader::open_readfile(path)
+                        .with_context(|| format!("Failed to open GeoIP database {}", path))
+                })
+                .transpose()
+        };
+        let signals = config
+            .signals
+            .iter()
+            .filter_map(|signal| match signal.as_str() {
+                "logs" => Some(SignalType::Logs),
+                "traces" => Some(SignalType::Traces),
+                "metrics" => Some(SignalType::Metrics),
+                _ => None,
+            })
+            .collect();
+
+        Ok(Self {
+            city: open(&config.city_database)?,
+            asn: open(&config.asn_database)?,
+            source_attributes: config.source_attributes.clone(),
+            signals,
+        })
+    }
+
+    /// Add geo attributes to records of `signal` that carry a client address.
+    pub(crate) fn enrich(&self, signal: SignalType, grouped: &mut ServiceGroupedBatches) {
+        if !self.signals.contains(&signal) {
+            return;
+        }
+        let column = match signal {
+            SignalType::Logs => "log_attributes",
+            SignalType::Traces => "span_attributes",
+            SignalType::Metrics => "metric_attributes",
+        };
+        for pb in &mut grouped.batches {
+            match rewrite_json_column(&pb.batch, column, |raw| self.enrich_attributes(raw)) {
+                Ok(Some((batch, rows))) => {
+                    counter!("otlp.enrich.geoip.records", "signal" => signal.as_str())
+                        .increment(rows);
+                    pb.batch = batch;
+                }
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, "Failed to apply GeoIP enrichment"),
+            }
+        }
+    }
+
+    fn enrich_attributes(&self, raw: &str) -> Option<String> {
+        // Cheap pre-check: most records carry no client address
+        if !self
+            .source_attributes
+            .iter()
+            .any(|key| raw.contains(key.as_str()))
+        {
+            return None;
+        }
+        let mut attributes: Map<String, Value> = serde_json::from_str(raw).ok()?;
+        let ip: IpAddr = self
+            .source_attributes
+            .iter()
+            .find_map(|key| attributes.get(key)?.as_str()?.parse().ok())?;
+
+        let mut added = false;
+        for (key, value) in self.lookup(ip) {
+            if !attributes.contains_key(key) {
+                attributes.insert(key.to_string(), value);
+                added = true;
+            }
+        }
+        added.then(|| Value::Object(attributes).to_string())
+    }
+
+    /// Attributes known for `ip`; empty for private or unknown addresses
+    fn lookup(&self, ip: IpAddr) -> Vec<(&'static str, Value)> {
+        let mut found = Vec::new();
+
+        if let Some(record) = self
+            .city
+            .as_ref()
+            .and_then(|db| db.lookup::<geoip2::City>(ip).ok().flatten())
+        {
+            let country = record.country.as_ref().and_then(|c| c.iso_code);
+            if let Some(code) = record.continent.as_ref().and_then(|c| c.code) {
+                found.push(("geo.continent.code", Value::from(code)));
+            }
+            if let Some(code) = country {
+                found.push(("geo.country.iso_code", Value::from(code)));
+            }
+            let subdivision = record
+                .subdivisions
+                .as_ref()
+                .and_then(|s| s.first())
+                .and_then(|s| s.iso_code);
+            if let (Some(country), Some(subdivision)) = (country, subdivision) {
+                // ISO 3166-2, e.g. "US-CA"
+                found.push((
+                    "geo.region.iso_code",
+                    Value::from(format!("{}-{}", country, subdivision)),
+                ));
+            }
+            if let Some(name) = record
+                .city
+                .as_ref()
+                .and_then(|c| c.names.as_ref())
+                .and_then(|names| names.get("en"))
+            {
+                found.push(("geo.locality.name", Value::from(*name)));
+            }
+            if let Some(code) = record.postal.as_ref().and_then(|p| p.code) {
+                found.push(("geo.postal_code", Value::from(code)));
+            }
+            if let Some(location) = record.location {
+                if let (Some(lat), Some(lon)) = (location.latitude, location.longitude) {
+                    found.push(("geo.location.lat", Value::from(lat)));
+                    found.push(("geo.location.lon", Value::from(lon)));
+                }
+            }
+        }
+
+        if let Some(record) = self
+            .asn
+            .as_ref()
+            .and_then(|db| db.lookup::<geoip2::Asn>(ip).ok().flatten())
+        {
+            if let Some(number) = record.autonomous_system_number {
+                found.push(("as.number", Value::from(number)));
+            }
+            if let Some(org) = record.autonomous_system_organization {
+                found.push(("as.organization.name", Value::from(org)));
+            }
+        }
+
+        found
+    }
+}
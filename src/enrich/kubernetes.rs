@@ -0,0 +1,340 @@
+//! Kubernetes pod metadata on resource attributes
+//!
+//! Producers that cannot run the collector's k8sattributes processor (plain SDK
+//! exporters, short-lived jobs) often report little more than `k8s.pod.ip` or
+//! `k8s.pod.name`. A pod watch keeps an in-memory index of pod metadata used to
+//! fill in namespace, node, workload and label attributes.
+
+use super::rewrite_json_column;
+use crate::codec::ServiceGroupedBatches;
+use crate::config::KubernetesEnrichmentConfig;
+use anyhow::Context;
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Pod;
+use kube::runtime::watcher::{self, watcher, Event};
+use kube::runtime::WatchStreamExt;
+use kube::Api;
+use metrics::{counter, gauge};
+use parking_lot::RwLock;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+const POD_IP: &str = "k8s.pod.ip";
+const POD_NAME: &str = "k8s.pod.name";
+const NAMESPACE: &str = "k8s.namespace.name";
+const LABEL_PREFIX: &str = "k8s.pod.label.";
+
+/// What the index keeps per pod: only the attributes it can contribute
+#[derive(Debug, PartialEq)]
+struct PodMetadata {
+    namespace: String,
+    name: String,
+    attributes: Vec<(String, String)>,
+    labels: Vec<(String, String)>,
+}
+
+impl PodMetadata {
+    fn from_pod(pod: &Pod) -> Option<Self> {
+        let meta = &pod.metadata;
+        let namespace = meta.namespace.clone()?;
+        let name = meta.name.clone()?;
+
+        let mut attributes = vec![
+            (NAMESPACE.to_string(), namespace.clone()),
+            (POD_NAME.to_string(), name.clone()),
+        ];
+        if let Some(ref uid) = meta.uid {
+            attributes.push(("k8s.pod.uid".to_string(), uid.clone()));
+        }
+        if let Some(node) = pod.spec.as_ref().and_then(|s| s.node_name.clone()) {
+            attributes.push(("k8s.node.name".to_string(), node));
+        }
+        let controller = meta
+            .owner_references
+            .iter()
+            .flatten()
+            .find(|owner| owner.controller == Some(true));
+        if let Some(owner) = controller {
+            attributes.extend(workload_attributes(&owner.kind, &owner.name));
+        }
+
+        let labels = meta
+            .labels
+            .iter()
+            .flatten()
+            .map(|(k, v)| (format!("{}{}", LABEL_PREFIX, k), v.clone()))
+            .collect();
+
+        Some(Self {
+            namespace,
+            name,
+            attributes,
+            labels,
+        })
+    }
+}
+
+/// Semantic-convention workload attributes for a pod's controller
+fn workload_attributes(kind: &str, name: &str) -> Vec<(String, String)> {
+    let attribute = |key: &str, value: &str| (key.to_string(), value.to_string());
+    match kind {
+        // Deployment-managed ReplicaSets are named `<deployment>-<pod-template-hash>`
+        "ReplicaSet" => {
+            let mut attrs = vec![attribute("k8s.replicaset.name", name)];
+            if let Some((deployment, _)) = name.rsplit_once('-') {
+                attrs.push(attribute("k8s.deployment.name", deployment));
+            }
+            attrs
+        }
+        "StatefulSet" => vec![attribute("k8s.statefulset.name", name)],
+        "DaemonSet" => vec![attribute("k8s.daemonset.name", name)],
+        "Job" => vec![attribute("k8s.job.name", name)],
+        _ => Vec::new(),
+    }
+}
+
+#[derive(Default)]
+struct PodIndex {
+    /// Keyed by (namespace, name)
+    pods: HashMap<(String, String), Arc<PodMetadata>>,
+    /// Pod IP to (namespace, name); host-network pods are not indexed
+    by_ip: HashMap<String, (String, String)>,
+}
+
+impl PodIndex {
+    fn apply(&mut self, pod: &Pod) {
+        let Some(metadata) = PodMetadata::from_pod(pod) else {
+            return;
+        };
+        let key = (metadata.namespace.clone(), metadata.name.clone());
+        let host_network = pod
+            .spec
+            .as_ref()
+            .and_then(|s| s.host_network)
+            .unwrap_or(false);
+        if let Some(ip) = pod.status.as_ref().and_then(|s| s.pod_ip.clone()) {
+            if !host_network {
+                self.by_ip.insert(ip, key.clone());
+            }
+        }
+        self.pods.insert(key, Arc::new(metadata));
+    }
+
+    fn delete(&mut self, pod: &Pod) {
+        let (Some(namespace), Some(name)) = (&pod.metadata.namespace, &pod.metadata.name) else {
+            return;
+        };
+        let key = (namespace.clone(), name.clone());
+        self.pods.remove(&key);
+        // The IP may already belong to a newer pod
+        if let Some(ip) = pod.status.as_ref().and_then(|s| s.pod_ip.as_ref()) {
+            if self.by_ip.get(ip) == Some(&key) {
+                self.by_ip.remove(ip);
+            }
+        }
+    }
+
+    fn lookup(&self, attributes: &Map<String, Value>) -> Option<&PodMetadata> {
+        let text = |key: &str| attributes.get(key).and_then(Value::as_str);
+        if let Some(key) = text(POD_IP).and_then(|ip| self.by_ip.get(ip)) {
+            return self.pods.get(key).map(Arc::as_ref);
+        }
+        let name = text(POD_NAME)?;
+        match text(NAMESPACE) {
+            Some(namespace) => self
+                .pods
+                .get(&(namespace.to_string(), name.to_string()))
+                .map(Arc::as_ref),
+            // Names are only unique per namespace: require an unambiguous match
+            None => {
+                let mut matches = self.pods.values().filter(|p| p.name == name);
+                let first = matches.next()?;
+                matches.next().is_none().then_some(first.as_ref())
+            }
+        }
+    }
+
+    /// `raw` with missing pod attributes added, or `None` if nothing changed
+    fn enrich(&self, raw: &str, pod_labels: bool) -> Option<String> {
+        let mut attributes: Map<String, Value> = serde_json::from_str(raw).ok()?;
+        let pod = self.lookup(&attributes)?;
+
+        let labels = pod.labels.iter().filter(|_| pod_labels);
+        let mut added = false;
+        for (key, value) in pod.attributes.iter().chain(labels) {
+            if !attributes.contains_key(key) {
+                attributes.insert(key.clone(), Value::String(value.clone()));
+                added = true;
+            }
+        }
+        added.then(|| Value::Object(attributes).to_string())
+    }
+}
+
+pub(crate) struct KubernetesEnricher {
+    index: Arc<RwLock<PodIndex>>,
+    pod_labels: bool,
+}
+
+impl KubernetesEnricher {
+    /// Connect with the in-cluster (or kubeconfig) credentials and start the
+    /// pod watch. Records arriving before the first sync pass through as-is.
+    pub(crate) async fn start(config: &KubernetesEnrichmentConfig) -> anyhow::Result<Self> {
+        let client = kube::Client::try_default()
+            .await
+            .context("Failed to create Kubernetes client for enrichment")?;
+
+        let mut watch_config = watcher::Config::default();
+        if let Some(ref node) = config.node_name {
+            watch_config = watch_config.fields(&format!("spec.nodeName={}", node));
+        }
+
+        let index = Arc::new(RwLock::new(PodIndex::default()));
+        tokio::spawn(watch_pods(
+            Api::all(client),
+            watch_config,
+            Arc::clone(&index),
+        ));
+
+        Ok(Self {
+            index,
+            pod_labels: config.pod_labels,
+        })
+    }
+
+    /// Add pod metadata to every batch's `resource_attributes`.
+    pub(crate) fn enrich(&self, grouped: &mut ServiceGroupedBatches) {
+        let index = self.index.read();
+        for pb in &mut grouped.batches {
+            match rewrite_json_column(&pb.batch, "resource_attributes", |raw| {
+                index.enrich(raw, self.pod_labels)
+            }) {
+                Ok(Some((batch, rows))) => {
+                    counter!("otlp.enrich.k8s.records").increment(rows);
+                    pb.batch = batch;
+                }
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, "Failed to apply Kubernetes enrichment"),
+            }
+        }
+    }
+}
+
+/// Keep `index` in sync with the cluster. Each (re)list builds a fresh index
+/// that replaces the old one once complete, so deleted pods never linger.
+async fn watch_pods(api: Api<Pod>, config: watcher::Config, index: Arc<RwLock<PodIndex>>) {
+    let stream = watcher(api, config).default_backoff();
+    let mut stream = std::pin::pin!(stream);
+    let mut relisting: Option<PodIndex> = None;
+
+    while let Some(event) = stream.next().await {
+        match event {
+            Ok(Event::Init) => relisting = Some(PodIndex::default()),
+            Ok(Event::InitApply(pod)) => {
+                if let Some(ref mut next) = relisting {
+                    next.apply(&pod);
+                }
+            }
+            Ok(Event::InitDone) => {
+                if let Some(next) = relisting.take() {
+                    let pods = next.pods.len();
+                    *index.write() = next;
+                    gauge!("otlp.enrich.k8s.pods").set(pods as f64);
+                    info!(pods, "Kubernetes pod index synced");
+                }
+            }
+            Ok(Event::Apply(pod)) => index.write().apply(&pod),
+            Ok(Event::Delete(pod)) => index.write().delete(&pod),
+            Err(e) => {
+                counter!("otlp.enrich.k8s.watch_errors").increment(1);
+                warn!(error = %e, "Kubernetes pod watch failed; retrying");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1::{PodSpec, PodStatus};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
+    use std::collections::BTreeMap;
+
+    fn pod(namespace: &str, name: &str, ip: &str) -> Pod {
+        Pod {
+            metadata: ObjectMeta {
+                namespace: Some(namespace.to_string()),
+                name: Some(name.to_string()),
+                uid: Some("uid-1".to_string()),
+                labels: Some(BTreeMap::from([("app".to_string(), "api".to_string())])),
+                owner_references: Some(vec![OwnerReference {
+                    kind: "ReplicaSet".to_string(),
+                    name: "api-7c9f8".to_string(),
+                    controller: Some(true),
+                    ..OwnerReference::default()
+                }]),
+                ..ObjectMeta::default()
+            },
+            spec: Some(PodSpec {
+                node_name: Some("node-a".to_string()),
+                ..PodSpec::default()
+            }),
+            status: Some(PodStatus {
+                pod_ip: Some(ip.to_string()),
+                ..PodStatus::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_enrich_by_ip_keeps_existing_attributes() {
+        let mut index = PodIndex::default();
+        index.apply(&pod("prod", "api-7c9f8-x2", "10.0.0.7"));
+
+        let raw = r#"{"k8s.pod.ip":"10.0.0.7","k8s.namespace.name":"override"}"#;
+        let enriched: Value = serde_json::from_str(&index.enrich(raw, true).unwrap()).unwrap();
+        assert_eq!(enriched["k8s.namespace.name"], "override");
+        assert_eq!(enriched["k8s.pod.name"], "api-7c9f8-x2");
+        assert_eq!(enriched["k8s.node.name"], "node-a");
+        assert_eq!(enriched["k8s.deployment.name"], "api");
+        assert_eq!(enriched["k8s.pod.label.app"], "api");
+
+        let without_labels: Value =
+            serde_json::from_str(&index.enrich(raw, false).unwrap()).unwrap();
+        assert!(without_labels.get("k8s.pod.label.app").is_none());
+
+        assert!(index.enrich(r#"{"k8s.pod.ip":"10.9.9.9"}"#, true).is_none());
+    }
+
+    #[test]
+    fn test_lookup_by_name_requires_unambiguous_match() {
+        let mut index = PodIndex::default();
+        index.apply(&pod("prod", "worker", "10.0.0.1"));
+        assert!(index.enrich(r#"{"k8s.pod.name":"worker"}"#, true).is_some());
+
+        index.apply(&pod("staging", "worker", "10.0.0.2"));
+        assert!(index.enrich(r#"{"k8s.pod.name":"worker"}"#, true).is_none());
+        assert!(index
+            .enrich(
+                r#"{"k8s.pod.name":"worker","k8s.namespace.name":"staging"}"#,
+                true
+            )
+            .is_some());
+    }
+
+    #[test]
+    fn test_delete_keeps_reused_ip() {
+        let mut index = PodIndex::default();
+        let old = pod("prod", "old", "10.0.0.3");
+        index.apply(&old);
+        index.apply(&pod("prod", "new", "10.0.0.3"));
+        index.delete(&old);
+
+        let enriched: Value =
+            serde_json::from_str(&index.enrich(r#"{"k8s.pod.ip":"10.0.0.3"}"#, false).unwrap())
+                .unwrap();
+        assert_eq!(enriched["k8s.pod.name"], "new");
+    }
+}
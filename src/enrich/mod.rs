@@ -0,0 +1,124 @@
+// Attribute enrichment
+//
+// Stages that add attributes to decoded batches before they are buffered or
+// written: static fleet metadata and Kubernetes pod metadata on resource
+// attributes, and GeoIP lookups of client addresses. Each stage rewrites one
+// JSON attribute column and never overwrites attributes the producer set.
+// Baggage capture is the exception: it copies allow-listed keys into columns
+// of their own, provenance stamps where and when each row was ingested, and
+// severity normalization fixes up the logs severity columns.
+
+mod baggage;
+#[cfg(feature = "geoip")]
+mod geoip;
+#[cfg(feature = "k8s-enrichment")]
+mod kubernetes;
+mod provenance;
+mod resource;
+mod severity;
+
+pub(crate) use baggage::BaggageColumns;
+#[cfg(feature = "geoip")]
+pub(crate) use geoip::GeoIpEnricher;
+#[cfg(feature = "k8s-enrichment")]
+pub(crate) use kubernetes::KubernetesEnricher;
+pub(crate) use provenance::Provenance;
+pub(crate) use resource::{check_template, StaticAttributes};
+pub(crate) use severity::normalize_severity;
+
+use arrow::array::{Array, RecordBatch, StringArray, StringBuilder};
+use arrow::error::ArrowError;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Replace each value of the JSON string column `name` for which `rewrite`
+/// returns `Some`. Distinct values are rewritten once per batch, since rows
+/// mostly repeat a handful of resources or attribute sets.
+///
+/// Returns the new batch and the number of rows changed, or `None` when the
+/// column is missing or nothing changed.
+fn rewrite_json_column(
+    batch: &RecordBatch,
+    name: &str,
+    mut rewrite: impl FnMut(&str) -> Option<String>,
+) -> Result<Option<(RecordBatch, u64)>, ArrowError> {
+    let Ok(position) = batch.schema().index_of(name) else {
+        return Ok(None);
+    };
+    let Some(column) = batch
+        .column(position)
+        .as_any()
+        .downcast_ref::<StringArray>()
+    else {
+        return Ok(None);
+    };
+
+    let mut cache: HashMap<&str, Option<String>> = HashMap::new();
+    let mut builder = StringBuilder::with_capacity(column.len(), column.value_data().len());
+    let mut changed = 0u64;
+    for row in 0..column.len() {
+        if column.is_null(row) {
+            builder.append_null();
+            continue;
+        }
+        let raw = column.value(row);
+        match cache.entry(raw).or_insert_with(|| rewrite(raw)) {
+            Some(value) => {
+                changed += 1;
+                builder.append_value(value);
+            }
+            None => builder.append_value(raw),
+        }
+    }
+
+    if changed == 0 {
+        return Ok(None);
+    }
+    let mut columns = batch.columns().to_vec();
+    columns[position] = Arc::new(builder.finish());
+    RecordBatch::try_new(batch.schema(), columns).map(|batch| Some((batch, changed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::AsArray;
+    use arrow::datatypes::{DataType, Field, Schema};
+
+    #[test]
+    fn test_rewrite_json_column() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "resource_attributes",
+            DataType::Utf8,
+            true,
+        )]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(StringArray::from(vec![
+                Some("{}"),
+                None,
+                Some("{\"a\":1}"),
+                Some("{}"),
+            ]))],
+        )
+        .unwrap();
+
+        let mut calls = 0;
+        let (rewritten, changed) = rewrite_json_column(&batch, "resource_attributes", |raw| {
+            calls += 1;
+            (raw == "{}").then(|| "{\"added\":true}".to_string())
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(calls, 2);
+        assert_eq!(changed, 2);
+        let column = rewritten.column(0).as_string::<i32>();
+        assert_eq!(column.value(0), "{\"added\":true}");
+        assert!(column.is_null(1));
+        assert_eq!(column.value(2), "{\"a\":1}");
+
+        assert!(rewrite_json_column(&batch, "missing", |_| None)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,122 @@
+//! Provenance columns
+//!
+//! With `schema.provenance_columns`, every decoded row gets `ingest_timestamp`
+//! (when this process received the request), `ingest_host`, `ingest_runtime`
+//! and `pipeline_version`. Late or duplicated data can then be traced to the
+//! replica and release that wrote it straight from the table, without
+//! matching file names against server logs.
+
+use crate::codec::ServiceGroupedBatches;
+use arrow::array::{ArrayRef, RecordBatch, StringArray, TimestampMicrosecondArray};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::error::ArrowError;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tracing::warn;
+
+const TIMESTAMP_COLUMN: &str = "ingest_timestamp";
+const HOST_COLUMN: &str = "ingest_host";
+const RUNTIME_COLUMN: &str = "ingest_runtime";
+const VERSION_COLUMN: &str = "pipeline_version";
+
+/// Runtime recorded in `ingest_runtime`
+const RUNTIME: &str = "server";
+
+pub(crate) struct Provenance {
+    host: String,
+}
+
+impl Provenance {
+    pub(crate) fn new() -> Self {
+        Self {
+            host: super::resource::hostname().unwrap_or_else(|| "unknown".to_string()),
+        }
+    }
+
+    /// Add the provenance columns to every batch of one request.
+    pub(crate) fn enrich(&self, grouped: &mut ServiceGroupedBatches) {
+        let received_micros = (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000) as i64;
+        for pb in &mut grouped.batches {
+            match self.add_columns(&pb.batch, received_micros) {
+                Ok(Some(batch)) => pb.batch = batch,
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, "Failed to add provenance columns"),
+            }
+        }
+    }
+
+    /// `batch` with the four columns appended; `None` when they exist already.
+    fn add_columns(
+        &self,
+        batch: &RecordBatch,
+        received_micros: i64,
+    ) -> Result<Option<RecordBatch>, ArrowError> {
+        let schema = batch.schema();
+        if schema.column_with_name(TIMESTAMP_COLUMN).is_some() {
+            return Ok(None);
+        }
+        let rows = batch.num_rows();
+        let text = |value: &str| Arc::new(StringArray::from(vec![value; rows])) as ArrayRef;
+
+        let mut fields: Vec<Field> = schema.fields().iter().map(|f| f.as_ref().clone()).collect();
+        fields.extend([
+            Field::new(
+                TIMESTAMP_COLUMN,
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                false,
+            ),
+            Field::new(HOST_COLUMN, DataType::Utf8, false),
+            Field::new(RUNTIME_COLUMN, DataType::Utf8, false),
+            Field::new(VERSION_COLUMN, DataType::Utf8, false),
+        ]);
+        let mut columns = batch.columns().to_vec();
+        columns.extend([
+            Arc::new(TimestampMicrosecondArray::from(vec![received_micros; rows])) as ArrayRef,
+            text(&self.host),
+            text(RUNTIME),
+            text(env!("CARGO_PKG_VERSION")),
+        ]);
+        let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+        RecordBatch::try_new(Arc::new(schema), columns).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{AsArray, Int32Array};
+    use arrow::datatypes::TimestampMicrosecondType;
+
+    #[test]
+    fn test_provenance_columns() {
+        let schema = Arc::new(Schema::new(vec![Field::new("n", DataType::Int32, true)]));
+        let batch =
+            RecordBatch::try_new(schema, vec![Arc::new(Int32Array::from(vec![1, 2]))]).unwrap();
+        let provenance = Provenance {
+            host: "collector-0".to_string(),
+        };
+
+        let stamped = provenance
+            .add_columns(&batch, 1_700_000_000_000_000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stamped.num_columns(), 5);
+        let received = stamped
+            .column_by_name(TIMESTAMP_COLUMN)
+            .unwrap()
+            .as_primitive::<TimestampMicrosecondType>();
+        assert_eq!(received.value(1), 1_700_000_000_000_000);
+        let host = stamped
+            .column_by_name(HOST_COLUMN)
+            .unwrap()
+            .as_string::<i32>();
+        assert_eq!(host.value(0), "collector-0");
+        let version = stamped
+            .column_by_name(VERSION_COLUMN)
+            .unwrap()
+            .as_string::<i32>();
+        assert_eq!(version.value(1), env!("CARGO_PKG_VERSION"));
+
+        assert!(provenance.add_columns(&stamped, 0).unwrap().is_none());
+    }
+}
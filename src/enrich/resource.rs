@@ -0,0 +1,161 @@
+//! Static resource attributes stamped onto every record
+//!
+//! Fleet metadata (region, cluster, environment) configured once on the
+//! server instead of in every SDK. Values may reference `${hostname}` and
+//! `${env:NAME}`, resolved once at startup.
+
+use super::rewrite_json_column;
+use crate::codec::ServiceGroupedBatches;
+use anyhow::{bail, Context, Result};
+use metrics::counter;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use tracing::warn;
+
+pub(crate) struct StaticAttributes {
+    attributes: Vec<(String, String)>,
+}
+
+impl StaticAttributes {
+    /// Resolve templated values; `None` when nothing is configured.
+    pub(crate) fn new(config: &BTreeMap<String, String>) -> Result<Option<Self>> {
+        if config.is_empty() {
+            return Ok(None);
+        }
+        let attributes = config
+            .iter()
+            .map(|(key, template)| {
+                let value = expand(template, resolve_placeholder).with_context(|| {
+                    format!(
+                        "enrichment.resource_attributes.{}: cannot resolve value",
+                        key
+                    )
+                })?;
+                Ok((key.clone(), value))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Some(Self { attributes }))
+    }
+
+    /// Add configured attributes missing from each record's resource attributes.
+    pub(crate) fn enrich(&self, grouped: &mut ServiceGroupedBatches) {
+        for pb in &mut grouped.batches {
+            match rewrite_json_column(&pb.batch, "resource_attributes", |raw| self.apply(raw)) {
+                Ok(Some((batch, rows))) => {
+                    counter!("otlp.enrich.static.records").increment(rows);
+                    pb.batch = batch;
+                }
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, "Failed to apply static resource attributes"),
+            }
+        }
+    }
+
+    fn apply(&self, raw: &str) -> Option<String> {
+        let mut attributes: Map<String, Value> = serde_json::from_str(raw).ok()?;
+        let mut added = false;
+        for (key, value) in &self.attributes {
+            if !attributes.contains_key(key) {
+                attributes.insert(key.clone(), Value::String(value.clone()));
+                added = true;
+            }
+        }
+        added.then(|| Value::Object(attributes).to_string())
+    }
+}
+
+/// Replace `${...}` placeholders in `template` using `resolve`.
+pub(crate) fn expand(template: &str, resolve: impl Fn(&str) -> Result<String>) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find('}') else {
+            bail!("unterminated placeholder in '{}'", template);
+        };
+        out.push_str(&resolve(&rest[start + 2..start + 2 + len])?);
+        rest = &rest[start + 3 + len..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Check placeholder syntax without reading the environment.
+pub(crate) fn check_template(template: &str) -> Result<()> {
+    expand(template, |placeholder| {
+        known_placeholder(placeholder)?;
+        Ok(String::new())
+    })
+    .map(|_| ())
+}
+
+fn known_placeholder(placeholder: &str) -> Result<()> {
+    match placeholder.strip_prefix("env:") {
+        Some(name) if !name.is_empty() => Ok(()),
+        None if placeholder == "hostname" => Ok(()),
+        _ => bail!(
+            "unknown placeholder '${{{}}}'; supported: ${{hostname}}, ${{env:NAME}}",
+            placeholder
+        ),
+    }
+}
+
+fn resolve_placeholder(placeholder: &str) -> Result<String> {
+    known_placeholder(placeholder)?;
+    if let Some(name) = placeholder.strip_prefix("env:") {
+        return std::env::var(name)
+            .with_context(|| format!("environment variable {} is not set", name));
+    }
+    hostname().context("could not determine the hostname")
+}
+
+pub(super) fn hostname() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_placeholders() {
+        let resolve = |placeholder: &str| Ok(format!("<{}>", placeholder));
+        assert_eq!(expand("plain", resolve).unwrap(), "plain");
+        assert_eq!(
+            expand("${env:REGION}-${hostname}!", resolve).unwrap(),
+            "<env:REGION>-<hostname>!"
+        );
+        assert!(expand("${hostname", resolve).is_err());
+
+        assert!(check_template("${env:AWS_REGION}").is_ok());
+        assert!(check_template("${region}").is_err());
+        assert!(check_template("${env:}").is_err());
+    }
+
+    #[test]
+    fn test_apply_keeps_existing_attributes() {
+        let attributes = StaticAttributes {
+            attributes: vec![
+                ("cloud.region".to_string(), "us-east-1".to_string()),
+                ("deployment.environment".to_string(), "prod".to_string()),
+            ],
+        };
+        let applied: Value = serde_json::from_str(
+            &attributes
+                .apply(r#"{"deployment.environment":"staging"}"#)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(applied["cloud.region"], "us-east-1");
+        assert_eq!(applied["deployment.environment"], "staging");
+
+        assert!(attributes
+            .apply(r#"{"cloud.region":"eu","deployment.environment":"dev"}"#)
+            .is_none());
+    }
+}
@@ -0,0 +1,220 @@
+//! Log severity normalization
+//!
+//! Producers send severity as text only (`"warning"`), as a number only, or
+//! with numbers outside the OpenTelemetry range of 1-24. With
+//! `schema.normalize_severity`, `severity_number` is derived from the text
+//! when missing or invalid (otherwise clamped into 0-24), and an empty
+//! `severity_text` is filled with the short name of the number (`INFO`,
+//! `INFO2`, ...) from the log data model. A replaced number is kept in the
+//! `otlp2parquet.original_severity_number` log attribute. Text the producer
+//! sent is never rewritten.
+
+use crate::codec::ServiceGroupedBatches;
+use arrow::array::{Array, AsArray, Int32Builder, RecordBatch, StringArray, StringBuilder};
+use arrow::datatypes::Int32Type;
+use arrow::error::ArrowError;
+use metrics::counter;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Log attribute holding a severity number that was replaced
+const ORIGINAL_ATTRIBUTE: &str = "otlp2parquet.original_severity_number";
+
+/// Short names of severity numbers 1-24
+const SHORT_NAMES: [&str; 24] = [
+    "TRACE", "TRACE2", "TRACE3", "TRACE4", "DEBUG", "DEBUG2", "DEBUG3", "DEBUG4", "INFO", "INFO2",
+    "INFO3", "INFO4", "WARN", "WARN2", "WARN3", "WARN4", "ERROR", "ERROR2", "ERROR3", "ERROR4",
+    "FATAL", "FATAL2", "FATAL3", "FATAL4",
+];
+
+/// Normalize the severity columns of every logs batch.
+pub(crate) fn normalize_severity(grouped: &mut ServiceGroupedBatches) {
+    for pb in &mut grouped.batches {
+        match normalize(&pb.batch) {
+            Ok(Some((batch, rows))) => {
+                counter!("otlp.enrich.severity.records").increment(rows);
+                pb.batch = batch;
+            }
+            Ok(None) => {}
+            Err(e) => warn!(error = %e, "Failed to normalize log severity"),
+        }
+    }
+}
+
+/// `batch` with normalized severity columns and the number of rows changed,
+/// or `None` when the columns are missing or already normal.
+fn normalize(batch: &RecordBatch) -> Result<Option<(RecordBatch, u64)>, ArrowError> {
+    let schema = batch.schema();
+    let (Ok(number_index), Ok(text_index)) = (
+        schema.index_of("severity_number"),
+        schema.index_of("severity_text"),
+    ) else {
+        return Ok(None);
+    };
+    let Some(numbers) = batch.column(number_index).as_primitive_opt::<Int32Type>() else {
+        return Ok(None);
+    };
+    let Some(texts) = batch
+        .column(text_index)
+        .as_any()
+        .downcast_ref::<StringArray>()
+    else {
+        return Ok(None);
+    };
+    let attributes_index = schema.index_of("log_attributes").ok();
+    let attributes =
+        attributes_index.and_then(|i| batch.column(i).as_any().downcast_ref::<StringArray>());
+
+    let rows = batch.num_rows();
+    let mut new_numbers = Int32Builder::with_capacity(rows);
+    let mut new_texts = StringBuilder::with_capacity(rows, texts.value_data().len());
+    let mut new_attributes = attributes
+        .map(|a| StringBuilder::with_capacity(rows, a.value_data().len()))
+        .unwrap_or_default();
+    let mut changed = 0u64;
+    for row in 0..rows {
+        let number = numbers.is_valid(row).then(|| numbers.value(row));
+        let text = texts.is_valid(row).then(|| texts.value(row));
+        let (normalized, fill) = resolve(number.unwrap_or(0), text.unwrap_or_default());
+        let replaced = normalized != number.unwrap_or(0);
+        if replaced || fill.is_some() {
+            changed += 1;
+        }
+
+        if replaced {
+            new_numbers.append_value(normalized);
+        } else {
+            new_numbers.append_option(number);
+        }
+        new_texts.append_option(fill.or(text));
+        if let Some(attributes) = attributes {
+            let raw = attributes.is_valid(row).then(|| attributes.value(row));
+            match number.filter(|n| replaced && *n != 0) {
+                Some(original) => {
+                    new_attributes.append_value(with_original(raw.unwrap_or("{}"), original))
+                }
+                None => new_attributes.append_option(raw),
+            }
+        }
+    }
+
+    if changed == 0 {
+        return Ok(None);
+    }
+    let mut columns = batch.columns().to_vec();
+    columns[number_index] = Arc::new(new_numbers.finish());
+    columns[text_index] = Arc::new(new_texts.finish());
+    if let (Some(index), Some(_)) = (attributes_index, attributes) {
+        columns[index] = Arc::new(new_attributes.finish());
+    }
+    RecordBatch::try_new(schema, columns).map(|batch| Some((batch, changed)))
+}
+
+/// Normalized severity number, and the text to set if `text` is empty
+fn resolve(number: i32, text: &str) -> (i32, Option<&'static str>) {
+    let number = if (1..=24).contains(&number) {
+        number
+    } else {
+        number_from_text(text).unwrap_or(number.clamp(0, 24))
+    };
+    let fill = if text.trim().is_empty() {
+        usize::try_from(number - 1)
+            .ok()
+            .and_then(|i| SHORT_NAMES.get(i).copied())
+    } else {
+        None
+    };
+    (number, fill)
+}
+
+/// Severity number of a short name (any case) or a common level name from
+/// other logging systems (syslog, log4j, Python)
+fn number_from_text(text: &str) -> Option<i32> {
+    let text = text.trim().to_ascii_uppercase();
+    if let Some(index) = SHORT_NAMES.iter().position(|name| *name == text) {
+        return Some(index as i32 + 1);
+    }
+    match text.as_str() {
+        "FINEST" | "FINER" | "VERBOSE" => Some(1),
+        "FINE" | "DBG" | "CONFIG" => Some(5),
+        "INFORMATION" | "INFORMATIONAL" => Some(9),
+        "NOTICE" => Some(10),
+        "WARNING" => Some(13),
+        "ERR" | "SEVERE" => Some(17),
+        "CRITICAL" | "CRIT" => Some(18),
+        "ALERT" => Some(19),
+        "EMERGENCY" | "EMERG" | "PANIC" => Some(21),
+        _ => None,
+    }
+}
+
+/// `raw` attribute JSON with the original number added (unless present)
+fn with_original(raw: &str, original: i32) -> String {
+    let mut attributes: Map<String, Value> = serde_json::from_str(raw).unwrap_or_default();
+    attributes
+        .entry(ORIGINAL_ATTRIBUTE)
+        .or_insert(Value::from(original));
+    Value::Object(attributes).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Int32Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+
+    #[test]
+    fn test_resolve_severity() {
+        // Number only: text backfilled
+        assert_eq!(resolve(17, ""), (17, Some("ERROR")));
+        assert_eq!(resolve(10, " "), (10, Some("INFO2")));
+        // Text only: number derived, text kept
+        assert_eq!(resolve(0, "warning"), (13, None));
+        assert_eq!(resolve(0, "Info3"), (11, None));
+        // Invalid numbers: derived from text, else clamped
+        assert_eq!(resolve(99, "ERROR"), (17, None));
+        assert_eq!(resolve(99, ""), (24, Some("FATAL4")));
+        assert_eq!(resolve(-3, "custom"), (0, None));
+        // Nothing to go on
+        assert_eq!(resolve(0, "custom"), (0, None));
+        assert_eq!(resolve(0, ""), (0, None));
+    }
+
+    #[test]
+    fn test_normalize_records_original_number() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("severity_number", DataType::Int32, true),
+            Field::new("severity_text", DataType::Utf8, true),
+            Field::new("log_attributes", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![Some(9), None, Some(42)])),
+                Arc::new(StringArray::from(vec![Some("INFO"), Some("warn"), None])),
+                Arc::new(StringArray::from(vec![
+                    Some("{}"),
+                    None,
+                    Some(r#"{"a":1}"#),
+                ])),
+            ],
+        )
+        .unwrap();
+
+        let (normalized, changed) = normalize(&batch).unwrap().unwrap();
+        assert_eq!(changed, 2);
+        let numbers = normalized.column(0).as_primitive::<Int32Type>();
+        assert_eq!(numbers.values().to_vec(), vec![9, 13, 24]);
+        let texts = normalized.column(1).as_string::<i32>();
+        assert_eq!(texts.value(1), "warn");
+        assert_eq!(texts.value(2), "FATAL4");
+        let attributes = normalized.column(2).as_string::<i32>();
+        assert!(attributes.is_null(1));
+        let row: Value = serde_json::from_str(attributes.value(2)).unwrap();
+        assert_eq!(row[ORIGINAL_ATTRIBUTE], 42);
+        assert_eq!(row["a"], 1);
+
+        assert!(normalize(&normalized).unwrap().is_none());
+    }
+}
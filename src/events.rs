@@ -0,0 +1,325 @@
+// RUM / event records
+//
+// Browser and mobile SDKs send events (page views, clicks, web vitals) as log
+// records carrying an `event.name` attribute, usually alongside `session.id`.
+// With `events.enabled` those records are split out of the logs table into an
+// `events` table with first-class `event_name` and `session_id` columns, plus
+// one string column per attribute listed in `events.columns`.
+
+use crate::codec::{PartitionedBatch, ServiceGroupedBatches};
+use crate::config::EventsConfig;
+use arrow::array::{new_null_array, Array, ArrayRef, BooleanArray, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use metrics::counter;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Directory (under the storage prefix) the events table is written to
+pub(crate) const TABLE: &str = "events";
+
+const EVENT_NAME: &str = "event.name";
+const SESSION_ID: &str = "session.id";
+
+/// Columns copied unchanged from the logs batch
+const PASSTHROUGH_COLUMNS: [&str; 7] = [
+    "timestamp",
+    "service_name",
+    "trace_id",
+    "span_id",
+    "severity_text",
+    "body",
+    "resource_attributes",
+];
+
+/// Every built-in column of the events table; promoted attributes may not reuse these
+pub(crate) const FIXED_COLUMNS: [&str; 10] = [
+    "timestamp",
+    "service_name",
+    "event_name",
+    "session_id",
+    "trace_id",
+    "span_id",
+    "severity_text",
+    "body",
+    "resource_attributes",
+    "event_attributes",
+];
+
+/// Column name for a promoted attribute key (`url.full` -> `url_full`)
+pub(crate) fn column_name(key: &str) -> String {
+    key.trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+pub(crate) struct EventExtractor {
+    /// (attribute key, column name) for each promoted attribute
+    columns: Vec<(String, String)>,
+}
+
+/// Values pulled out of one event record's attributes
+struct EventRow {
+    name: String,
+    session_id: Option<String>,
+    promoted: Vec<Option<String>>,
+    remaining: String,
+}
+
+impl EventExtractor {
+    pub(crate) fn new(config: &EventsConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        Some(Self {
+            columns: config
+                .columns
+                .iter()
+                .map(|key| (key.clone(), column_name(key)))
+                .collect(),
+        })
+    }
+
+    /// Split event records out of a decoded logs request.
+    ///
+    /// Returns the remaining log records and one events batch per service.
+    pub(crate) fn split(
+        &self,
+        mut grouped: ServiceGroupedBatches,
+    ) -> (ServiceGroupedBatches, Vec<PartitionedBatch>) {
+        let mut events = Vec::new();
+        for pb in &mut grouped.batches {
+            let rows: Vec<Option<EventRow>> = match pb
+                .batch
+                .column_by_name("log_attributes")
+                .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+            {
+                Some(attributes) => (0..attributes.len())
+                    .map(|row| {
+                        attributes
+                            .is_valid(row)
+                            .then(|| self.event_row(attributes.value(row)))
+                            .flatten()
+                    })
+                    .collect(),
+                None => continue,
+            };
+
+            let is_event: BooleanArray = rows.iter().map(|r| Some(r.is_some())).collect();
+            let count = is_event.true_count();
+            if count == 0 {
+                continue;
+            }
+            let is_log = arrow::compute::not(&is_event);
+
+            let split =
+                arrow::compute::filter_record_batch(&pb.batch, &is_event).and_then(|event_rows| {
+                    let logs = is_log
+                        .and_then(|mask| arrow::compute::filter_record_batch(&pb.batch, &mask))?;
+                    let rows: Vec<EventRow> = rows.into_iter().flatten().collect();
+                    Ok((logs, self.build(&event_rows, &rows)?))
+                });
+            match split {
+                Ok((logs, batch)) => {
+                    counter!("otlp.events.records").increment(count as u64);
+                    pb.batch = logs;
+                    pb.record_count = pb.record_count.saturating_sub(count);
+                    grouped.total_records = grouped.total_records.saturating_sub(count);
+                    events.push(PartitionedBatch {
+                        batch,
+                        service_name: Arc::clone(&pb.service_name),
+                        min_timestamp_micros: pb.min_timestamp_micros,
+                        record_count: count,
+                    });
+                }
+                // Leave the records in the logs table rather than lose them
+                Err(e) => warn!(
+                    error = %e,
+                    service = %pb.service_name,
+                    "Failed to split event records from logs"
+                ),
+            }
+        }
+        grouped.batches.retain(|pb| pb.batch.num_rows() > 0);
+        (grouped, events)
+    }
+
+    /// Event fields for a record, or `None` if it is a plain log record
+    fn event_row(&self, raw_attributes: &str) -> Option<EventRow> {
+        // Cheap pre-check: most log records are not events
+        if !raw_attributes.contains(EVENT_NAME) {
+            return None;
+        }
+        let mut attributes: Map<String, Value> = serde_json::from_str(raw_attributes).ok()?;
+        let name = match attributes.remove(EVENT_NAME)? {
+            Value::String(s) if !s.is_empty() => s,
+            _ => return None,
+        };
+        let session_id = attributes.remove(SESSION_ID).and_then(display);
+        let promoted = self
+            .columns
+            .iter()
+            .map(|(key, _)| attributes.remove(key).and_then(display))
+            .collect();
+        Some(EventRow {
+            name,
+            session_id,
+            promoted,
+            remaining: Value::Object(attributes).to_string(),
+        })
+    }
+
+    fn build(
+        &self,
+        event_rows: &RecordBatch,
+        rows: &[EventRow],
+    ) -> Result<RecordBatch, arrow::error::ArrowError> {
+        let mut fields = Vec::with_capacity(FIXED_COLUMNS.len() + self.columns.len());
+        let mut columns: Vec<ArrayRef> = Vec::with_capacity(fields.capacity());
+        let mut push = |name: &str, array: ArrayRef| {
+            fields.push(Field::new(name, array.data_type().clone(), true));
+            columns.push(array);
+        };
+        let passthrough = |name: &str| {
+            event_rows
+                .column_by_name(name)
+                .cloned()
+                .unwrap_or_else(|| new_null_array(&DataType::Utf8, rows.len()))
+        };
+
+        push("timestamp", passthrough("timestamp"));
+        push("service_name", passthrough("service_name"));
+        push(
+            "event_name",
+            Arc::new(StringArray::from_iter_values(
+                rows.iter().map(|r| r.name.as_str()),
+            )),
+        );
+        push(
+            "session_id",
+            Arc::new(StringArray::from_iter(
+                rows.iter().map(|r| r.session_id.as_deref()),
+            )),
+        );
+        for name in &PASSTHROUGH_COLUMNS[2..] {
+            push(name, passthrough(name));
+        }
+        push(
+            "event_attributes",
+            Arc::new(StringArray::from_iter_values(
+                rows.iter().map(|r| r.remaining.as_str()),
+            )),
+        );
+        for (i, (_, column)) in self.columns.iter().enumerate() {
+            push(
+                column,
+                Arc::new(StringArray::from_iter(
+                    rows.iter().map(|r| r.promoted[i].as_deref()),
+                )),
+            );
+        }
+
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+    }
+}
+
+fn display(value: Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s),
+        other => Some(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::decode_logs_partitioned;
+    use crate::InputFormat;
+    use arrow::array::AsArray;
+    use serde_json::json;
+
+    fn payload() -> Vec<u8> {
+        let attribute =
+            |key: &str, value: &str| json!({"key": key, "value": {"stringValue": value}});
+        json!({
+            "resourceLogs": [{
+                "resource": {
+                    "attributes": [attribute("service.name", "web")]
+                },
+                "scopeLogs": [{
+                    "logRecords": [
+                        {
+                            "timeUnixNano": "1700000000000000000",
+                            "body": {"stringValue": "plain log"}
+                        },
+                        {
+                            "timeUnixNano": "1700000000000000000",
+                            "attributes": [
+                                attribute("event.name", "browser.page_view"),
+                                attribute("session.id", "s-1"),
+                                attribute("url.full", "https://example.com/"),
+                                attribute("browser.name", "firefox")
+                            ]
+                        }
+                    ]
+                }]
+            }]
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_splits_events_from_logs() {
+        let extractor = EventExtractor::new(&EventsConfig {
+            enabled: true,
+            columns: vec!["url.full".to_string()],
+        })
+        .unwrap();
+        let grouped = decode_logs_partitioned(&payload(), InputFormat::Json).unwrap();
+
+        let (logs, events) = extractor.split(grouped);
+        assert_eq!(logs.total_records, 1);
+        assert_eq!(logs.batches[0].batch.num_rows(), 1);
+        assert_eq!(events.len(), 1);
+
+        let batch = &events[0].batch;
+        let text = |name: &str| {
+            batch
+                .column_by_name(name)
+                .unwrap()
+                .as_string::<i32>()
+                .value(0)
+        };
+        assert_eq!(text("event_name"), "browser.page_view");
+        assert_eq!(text("session_id"), "s-1");
+        assert_eq!(text("url_full"), "https://example.com/");
+        assert_eq!(text("service_name"), "web");
+        let remaining: Value = serde_json::from_str(text("event_attributes")).unwrap();
+        assert_eq!(remaining, json!({"browser.name": "firefox"}));
+    }
+
+    #[test]
+    fn test_disabled_and_event_free_requests_pass_through() {
+        assert!(EventExtractor::new(&EventsConfig::default()).is_none());
+
+        let extractor = EventExtractor::new(&EventsConfig {
+            enabled: true,
+            columns: Vec::new(),
+        })
+        .unwrap();
+        let mut grouped = decode_logs_partitioned(&payload(), InputFormat::Json).unwrap();
+        grouped.batches[0].batch = grouped.batches[0].batch.slice(0, 1);
+        let (logs, events) = extractor.split(grouped);
+        assert_eq!(logs.batches[0].batch.num_rows(), 1);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_column_name() {
+        assert_eq!(column_name("url.full"), "url_full");
+        assert_eq!(column_name(" app.screen-name "), "app_screen_name");
+    }
+}
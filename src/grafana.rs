@@ -0,0 +1,481 @@
+// Grafana JSON datasource
+//
+// Implements the Grafana JSON datasource contract (GET /, POST /search,
+// POST /query) over recently written Parquet files, queried with DataFusion.
+// Intended for dev setups: files overlapping the requested range are read
+// into memory per query, so keep ranges short. Rows still buffered by the
+// batcher are included, so data is queryable before its file is written.
+
+use crate::writer::partition_hour_micros;
+use crate::{AppError, AppState, SignalType};
+use axum::extract::State;
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use datafusion::arrow::array::{Array, AsArray, BooleanArray, RecordBatch};
+use datafusion::arrow::compute::{cast, filter_record_batch};
+use datafusion::arrow::datatypes::{
+    DataType, Float64Type, Int64Type, TimeUnit, TimestampMicrosecondType,
+};
+use datafusion::datasource::MemTable;
+use datafusion::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use datafusion::prelude::SessionContext;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing::debug;
+
+/// Upper bound on files loaded per table per query
+const MAX_FILES_PER_TABLE: usize = 256;
+/// Smallest bucket width, whatever Grafana asks for
+const MIN_INTERVAL_MS: i64 = 1_000;
+const MICROS_PER_HOUR: i64 = 3_600_000_000;
+
+/// `timestamp` as microseconds since the epoch, whatever unit the table was
+/// written with (`schema.timestamp_unit`)
+const TIMESTAMP_MICROS: &str =
+    "CAST(arrow_cast(\"timestamp\", 'Timestamp(Microsecond, None)') AS BIGINT)";
+
+/// Targets offered regardless of stored data
+const STATIC_TARGETS: [&str; 3] = ["logs.count", "traces.count", "traces.errors"];
+/// Metric types whose `value` column can be plotted directly
+const VALUE_METRIC_TYPES: [&str; 2] = ["gauge", "sum"];
+
+#[derive(Deserialize)]
+pub(crate) struct QueryRequest {
+    range: TimeRange,
+    #[serde(default)]
+    targets: Vec<QueryTarget>,
+    #[serde(rename = "intervalMs")]
+    interval_ms: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct TimeRange {
+    from: String,
+    to: String,
+}
+
+#[derive(Deserialize)]
+struct QueryTarget {
+    #[serde(default)]
+    target: String,
+}
+
+/// GET /grafana - Datasource connection test
+pub(crate) async fn grafana_health() -> impl IntoResponse {
+    (StatusCode::OK, Json(json!({"status": "ok"})))
+}
+
+/// POST /grafana/search - Names usable as query targets
+pub(crate) async fn grafana_search(State(state): State<AppState>) -> Result<Json<Value>, AppError> {
+    let now = crate::clock::now_micros();
+    let mut targets: Vec<String> = STATIC_TARGETS.iter().map(|t| t.to_string()).collect();
+
+    let ctx = SessionContext::new();
+    for metric_type in VALUE_METRIC_TYPES {
+        if !register_table(
+            &ctx,
+            metric_type,
+            SignalType::Metrics,
+            Some(metric_type),
+            now - MICROS_PER_HOUR,
+            now,
+            state.pending_batches(SignalType::Metrics, Some(metric_type)),
+        )
+        .await?
+        {
+            continue;
+        }
+        let sql = format!(
+            "SELECT DISTINCT metric_name FROM {} ORDER BY metric_name",
+            metric_type
+        );
+        for batch in run_sql(&ctx, &sql).await? {
+            let names = batch.column(0).as_string::<i32>();
+            for i in 0..names.len() {
+                if names.is_valid(i) {
+                    targets.push(format!("{}:{}", metric_type, names.value(i)));
+                }
+            }
+        }
+    }
+
+    Ok(Json(json!(targets)))
+}
+
+/// POST /grafana/query - Time series for each requested target
+pub(crate) async fn grafana_query(
+    State(state): State<AppState>,
+    Json(request): Json<QueryRequest>,
+) -> Result<Json<Value>, AppError> {
+    let from = parse_time_micros(&request.range.from)?;
+    let to = parse_time_micros(&request.range.to)?;
+    if to < from {
+        return Err(AppError::bad_request(anyhow::anyhow!(
+            "range.to is before range.from"
+        )));
+    }
+    let interval_us = request
+        .interval_ms
+        .unwrap_or(MIN_INTERVAL_MS)
+        .max(MIN_INTERVAL_MS)
+        * 1_000;
+
+    let mut series = Vec::with_capacity(request.targets.len());
+    for target in &request.targets {
+        let Some(query) = TargetQuery::parse(&target.target) else {
+            return Err(AppError::bad_request(anyhow::anyhow!(
+                "unknown target '{}'; see /grafana/search",
+                target.target
+            )));
+        };
+
+        let ctx = SessionContext::new();
+        let datapoints = if register_table(
+            &ctx,
+            query.table,
+            query.signal,
+            query.metric_type,
+            from,
+            to,
+            state.pending_batches(query.signal, query.metric_type),
+        )
+        .await?
+        {
+            let sql = query.sql(from, to, interval_us);
+            debug!(target = %target.target, sql = %sql, "Grafana query");
+            datapoints(&run_sql(&ctx, &sql).await?)
+        } else {
+            Vec::new()
+        };
+
+        series.push(json!({"target": target.target, "datapoints": datapoints}));
+    }
+
+    Ok(Json(Value::Array(series)))
+}
+
+/// A supported target mapped to the table and aggregate it reads.
+#[derive(Debug, PartialEq)]
+struct TargetQuery {
+    table: &'static str,
+    signal: SignalType,
+    metric_type: Option<&'static str>,
+    aggregate: &'static str,
+    filter: Option<String>,
+}
+
+impl TargetQuery {
+    fn parse(target: &str) -> Option<Self> {
+        let (table, signal, metric_type, aggregate, filter) = match target {
+            "logs.count" => ("logs", SignalType::Logs, None, "COUNT(*)", None),
+            "traces.count" => ("traces", SignalType::Traces, None, "COUNT(*)", None),
+            // OTLP STATUS_CODE_ERROR
+            "traces.errors" => (
+                "traces",
+                SignalType::Traces,
+                None,
+                "COUNT(*)",
+                Some("status_code = 2".to_string()),
+            ),
+            _ => {
+                let (metric_type, name) = target.split_once(':')?;
+                let metric_type = VALUE_METRIC_TYPES.into_iter().find(|t| *t == metric_type)?;
+                return Some(Self {
+                    table: metric_type,
+                    signal: SignalType::Metrics,
+                    metric_type: Some(metric_type),
+                    aggregate: "AVG(value)",
+                    filter: Some(format!("metric_name = '{}'", name.replace('\'', "''"))),
+                });
+            }
+        };
+        Some(Self {
+            table,
+            signal,
+            metric_type,
+            aggregate,
+            filter,
+        })
+    }
+
+    /// Bucketed aggregate as (ts_ms BIGINT, value DOUBLE) rows
+    fn sql(&self, from_us: i64, to_us: i64, interval_us: i64) -> String {
+        let filter = self
+            .filter
+            .as_deref()
+            .map(|f| format!(" AND {}", f))
+            .unwrap_or_default();
+        format!(
+            "SELECT ({ts} / {interval}) * {interval} / 1000 AS ts_ms, \
+             CAST({aggregate} AS DOUBLE) AS value \
+             FROM {table} \
+             WHERE {ts} BETWEEN {from} AND {to}{filter} \
+             GROUP BY 1 ORDER BY 1",
+            ts = TIMESTAMP_MICROS,
+            interval = interval_us,
+            aggregate = self.aggregate,
+            table = self.table,
+            from = from_us,
+            to = to_us,
+            filter = filter,
+        )
+    }
+}
+
+/// Load files overlapping [from_us, to_us], plus the `pending` rows not yet
+/// written that fall in that range, into an in-memory table.
+///
+/// Returns false when there is nothing to query.
+pub(crate) async fn register_table(
+    ctx: &SessionContext,
+    name: &str,
+    signal: SignalType,
+    metric_type: Option<&str>,
+    from_us: i64,
+    to_us: i64,
+    pending: Vec<RecordBatch>,
+) -> Result<bool, AppError> {
+    let op = crate::writer::get_operator()
+        .ok_or_else(|| AppError::internal(anyhow::anyhow!("storage not initialized")))?;
+    let prefix = format!("{}/", crate::writer::table_prefix(signal, metric_type));
+
+    let entries = match op.list_with(&prefix).recursive(true).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == opendal::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(AppError::internal(e)),
+    };
+
+    // Newest files first so the cap keeps the most recent data
+    let mut paths: Vec<(i64, String)> = entries
+        .into_iter()
+        .map(|entry| entry.path().to_string())
+        .filter(|path| path.ends_with(".parquet"))
+        .filter_map(|path| Some((partition_hour_micros(&path)?, path)))
+        .filter(|(hour, _)| *hour <= to_us && *hour + MICROS_PER_HOUR > from_us)
+        .collect();
+    paths.sort_unstable_by(|a, b| b.cmp(a));
+    paths.truncate(MAX_FILES_PER_TABLE);
+
+    let mut batches: Vec<RecordBatch> = Vec::new();
+    for (_, path) in paths {
+        let bytes = op.read(&path).await.map_err(AppError::internal)?.to_bytes();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes)
+            .and_then(|builder| builder.build())
+            .map_err(|e| AppError::internal(anyhow::anyhow!("{}: {}", path, e)))?;
+        for batch in reader {
+            batches.push(batch.map_err(AppError::internal)?);
+        }
+    }
+
+    batches.extend(pending_in_range(pending, from_us, to_us)?);
+
+    let Some(schema) = batches.first().map(|b| b.schema()) else {
+        return Ok(false);
+    };
+    // Files written by other versions may carry a different schema
+    batches.retain(|b| b.schema() == schema);
+
+    let table = MemTable::try_new(schema, vec![batches]).map_err(AppError::internal)?;
+    ctx.register_table(name, Arc::new(table))
+        .map_err(AppError::internal)?;
+    Ok(true)
+}
+
+/// Rows of `pending` batches whose `timestamp` falls within [from_us, to_us];
+/// batches without that column are kept whole. Pending batches are already in
+/// the table's `schema.timestamp_unit`, so timestamps are cast to microseconds
+/// before comparing.
+fn pending_in_range(
+    pending: Vec<RecordBatch>,
+    from_us: i64,
+    to_us: i64,
+) -> Result<Vec<RecordBatch>, AppError> {
+    let mut kept = Vec::with_capacity(pending.len());
+    for batch in pending {
+        let Some(column) = batch.column_by_name("timestamp") else {
+            kept.push(batch);
+            continue;
+        };
+        let tz = match column.data_type() {
+            DataType::Timestamp(_, tz) => tz.clone(),
+            _ => None,
+        };
+        let micros = cast(column, &DataType::Timestamp(TimeUnit::Microsecond, tz))
+            .map_err(AppError::internal)?;
+        let in_range: BooleanArray = micros
+            .as_primitive::<TimestampMicrosecondType>()
+            .iter()
+            .map(|ts| Some(ts.is_some_and(|ts| (from_us..=to_us).contains(&ts))))
+            .collect();
+        let batch = filter_record_batch(&batch, &in_range).map_err(AppError::internal)?;
+        if batch.num_rows() > 0 {
+            kept.push(batch);
+        }
+    }
+    Ok(kept)
+}
+
+async fn run_sql(ctx: &SessionContext, sql: &str) -> Result<Vec<RecordBatch>, AppError> {
+    let frame = ctx.sql(sql).await.map_err(AppError::internal)?;
+    frame.collect().await.map_err(AppError::internal)
+}
+
+/// Grafana datapoints: `[[value, ts_ms], ...]`
+fn datapoints(batches: &[RecordBatch]) -> Vec<Value> {
+    let mut points = Vec::new();
+    for batch in batches {
+        let ts = batch.column(0).as_primitive::<Int64Type>();
+        let values = batch.column(1).as_primitive::<Float64Type>();
+        for i in 0..batch.num_rows() {
+            if ts.is_valid(i) && values.is_valid(i) {
+                points.push(json!([values.value(i), ts.value(i)]));
+            }
+        }
+    }
+    points
+}
+
+fn parse_time_micros(value: &str) -> Result<i64, AppError> {
+    OffsetDateTime::parse(value, &Rfc3339)
+        .map(|t| (t.unix_timestamp_nanos() / 1_000) as i64)
+        .map_err(|e| AppError::bad_request(anyhow::anyhow!("invalid time '{}': {}", value, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_hour_micros() {
+        let path = "logs/api/year=2024/month=01/day=02/hour=03/1704164400000000-abc.parquet";
+        assert_eq!(partition_hour_micros(path), Some(1_704_164_400_000_000));
+        assert_eq!(partition_hour_micros("logs/api/file.parquet"), None);
+    }
+
+    #[test]
+    fn test_parse_targets() {
+        assert_eq!(TargetQuery::parse("logs.count").unwrap().table, "logs");
+
+        let errors = TargetQuery::parse("traces.errors").unwrap();
+        assert!(errors.sql(0, 10, 1_000_000).contains("status_code = 2"));
+
+        let gauge = TargetQuery::parse("gauge:it's.cpu").unwrap();
+        assert_eq!(gauge.metric_type, Some("gauge"));
+        assert!(gauge
+            .sql(0, 10, 1_000_000)
+            .contains("metric_name = 'it''s.cpu'"));
+
+        assert!(TargetQuery::parse("histogram:latency").is_none());
+        assert!(TargetQuery::parse("nope").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_series_from_nanosecond_table() {
+        use datafusion::arrow::array::TimestampNanosecondArray;
+        use datafusion::arrow::datatypes::{Field, Schema};
+
+        // Written with schema.timestamp_unit = "nanosecond"
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+            false,
+        )]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(
+                TimestampNanosecondArray::from(vec![
+                    1_000_000_000,
+                    1_500_000_000,
+                    2_000_000_000,
+                    9_000_000_000,
+                ])
+                .with_timezone("UTC"),
+            )],
+        )
+        .unwrap();
+        let ctx = SessionContext::new();
+        let table = MemTable::try_new(schema, vec![vec![batch]]).unwrap();
+        ctx.register_table("logs", Arc::new(table)).unwrap();
+
+        let query = TargetQuery::parse("logs.count").unwrap();
+        let batches = run_sql(&ctx, &query.sql(1_000_000, 2_000_000, 1_000_000))
+            .await
+            .unwrap();
+        assert_eq!(
+            datapoints(&batches),
+            vec![json!([2.0, 1_000]), json!([1.0, 2_000])]
+        );
+    }
+
+    #[test]
+    fn test_pending_rows_limited_to_range() {
+        use datafusion::arrow::array::TimestampMillisecondArray;
+        use datafusion::arrow::array::{Int64Array, TimestampMicrosecondArray};
+        use datafusion::arrow::datatypes::{Field, Schema};
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(
+                "timestamp",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                true,
+            ),
+            Field::new("value", DataType::Int64, false),
+        ]));
+        let batch = |timestamps: Vec<Option<i64>>| {
+            let values = (0..timestamps.len() as i64).collect::<Vec<_>>();
+            RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(TimestampMicrosecondArray::from(timestamps)),
+                    Arc::new(Int64Array::from(values)),
+                ],
+            )
+            .unwrap()
+        };
+
+        let kept = pending_in_range(
+            vec![
+                batch(vec![Some(5), Some(10), Some(20), Some(30), None]),
+                batch(vec![Some(40), Some(50)]),
+            ],
+            10,
+            30,
+        )
+        .unwrap();
+        assert_eq!(kept.len(), 1);
+        let values = kept[0].column(1).as_primitive::<Int64Type>();
+        assert_eq!(values.values().to_vec(), vec![1, 2, 3]);
+
+        let untimed = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new(
+                "value",
+                DataType::Int64,
+                false,
+            )])),
+            vec![Arc::new(Int64Array::from(vec![1]))],
+        )
+        .unwrap();
+        assert_eq!(pending_in_range(vec![untimed], 10, 30).unwrap().len(), 1);
+
+        // A table written with schema.timestamp_unit = "millisecond"
+        let millis = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new(
+                "timestamp",
+                DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+                false,
+            )])),
+            vec![Arc::new(
+                TimestampMillisecondArray::from(vec![1, 2, 3, 4]).with_timezone("UTC"),
+            )],
+        )
+        .unwrap();
+        let kept = pending_in_range(vec![millis], 2_000, 3_000).unwrap();
+        assert_eq!(kept.len(), 1);
+        let kept = kept[0]
+            .column(0)
+            .as_primitive::<datafusion::arrow::datatypes::TimestampMillisecondType>();
+        assert_eq!(kept.values().to_vec(), vec![2, 3]);
+    }
+}
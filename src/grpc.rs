@@ -0,0 +1,185 @@
+// OTLP/gRPC ingestion
+//
+// With `server.grpc_listen_addr` set, the server also answers the OTLP
+// LogsService, MetricsService and TraceService Export RPCs on a second
+// listener. Each request is encoded back to protobuf and ingested like an
+// OTLP/HTTP protobuf request, so payload limits, request ids, batching and
+// outputs behave the same for both protocols; gRPC metadata is passed on as
+// request headers. Errors map to the gRPC codes OTLP/HTTP responses already
+// report, with a RetryInfo detail on retryable ones.
+
+use crate::handlers;
+use crate::otlp_response::{self, RETRY_AFTER_SECS};
+use crate::{AppError, AppState, SignalType};
+use axum::http::{header, HeaderValue};
+use opentelemetry_proto::tonic::collector::logs::v1::logs_service_server::{
+    LogsService, LogsServiceServer,
+};
+use opentelemetry_proto::tonic::collector::logs::v1::{
+    ExportLogsServiceRequest, ExportLogsServiceResponse,
+};
+use opentelemetry_proto::tonic::collector::metrics::v1::metrics_service_server::{
+    MetricsService, MetricsServiceServer,
+};
+use opentelemetry_proto::tonic::collector::metrics::v1::{
+    ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+};
+use opentelemetry_proto::tonic::collector::trace::v1::trace_service_server::{
+    TraceService, TraceServiceServer,
+};
+use opentelemetry_proto::tonic::collector::trace::v1::{
+    ExportTraceServiceRequest, ExportTraceServiceResponse,
+};
+use prost::Message;
+use tonic::codec::CompressionEncoding;
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Request, Response, Status};
+use tracing::error;
+
+/// The OTLP export services, routed by gRPC method path
+pub(crate) fn router(state: AppState) -> axum::Router {
+    // Decoded messages over the largest limit are refused by tonic; the
+    // per-signal limits are checked in the ingest path like for OTLP/HTTP
+    let limit = [SignalType::Logs, SignalType::Traces, SignalType::Metrics]
+        .into_iter()
+        .map(|signal| state.max_payload_bytes.for_signal(signal))
+        .max()
+        .unwrap_or(usize::MAX);
+    let service = OtlpService { state };
+    tonic::service::Routes::new(
+        LogsServiceServer::new(service.clone())
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd)
+            .accept_compressed(CompressionEncoding::Deflate)
+            .max_decoding_message_size(limit),
+    )
+    .add_service(
+        TraceServiceServer::new(service.clone())
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd)
+            .accept_compressed(CompressionEncoding::Deflate)
+            .max_decoding_message_size(limit),
+    )
+    .add_service(
+        MetricsServiceServer::new(service)
+            .accept_compressed(CompressionEncoding::Gzip)
+            .accept_compressed(CompressionEncoding::Zstd)
+            .accept_compressed(CompressionEncoding::Deflate)
+            .max_decoding_message_size(limit),
+    )
+    .into_axum_router()
+}
+
+#[derive(Clone)]
+struct OtlpService {
+    state: AppState,
+}
+
+impl OtlpService {
+    async fn ingest<Req, Resp>(
+        &self,
+        signal: SignalType,
+        request: Request<Req>,
+    ) -> Result<Response<Resp>, Status>
+    where
+        Req: Message,
+        Resp: Message + Default,
+    {
+        let mut headers = request.metadata().clone().into_headers();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-protobuf"),
+        );
+        let body = request.into_inner().encode_to_vec();
+
+        let response = handlers::export(signal, &self.state, headers, body.into())
+            .await
+            .map_err(status)?;
+        if !response.status().is_success() {
+            let message = response
+                .status()
+                .canonical_reason()
+                .unwrap_or("request failed");
+            return Err(Status::new(
+                Code::from(otlp_response::grpc_code(response.status())),
+                message,
+            ));
+        }
+
+        // Export*ServiceResponse, with the partial success if there is one
+        let reply = Resp::decode(otlp_response::export_response(&response).as_slice())
+            .map_err(|e| Status::internal(format!("Failed to encode response: {}", e)))?;
+        let mut reply = Response::new(reply);
+        let request_id = response
+            .headers()
+            .get(crate::request_id::HEADER)
+            .and_then(|v| MetadataValue::try_from(v.as_bytes()).ok());
+        if let Some(request_id) = request_id {
+            reply
+                .metadata_mut()
+                .insert(crate::request_id::HEADER, request_id);
+        }
+        Ok(reply)
+    }
+}
+
+/// gRPC status for an ingest error, with the same `google.rpc.Status` details
+/// OTLP/HTTP protobuf responses carry
+fn status(e: AppError) -> Status {
+    error!("Request error: {:?}", e.error);
+    let message = e.error.to_string();
+    let code = otlp_response::grpc_code(e.status);
+    let retry_delay = otlp_response::is_retryable(e.status).then_some(RETRY_AFTER_SECS);
+    let details = otlp_response::encode_status(code, &message, retry_delay);
+    Status::with_details(Code::from(code), message, details.into())
+}
+
+#[tonic::async_trait]
+impl LogsService for OtlpService {
+    async fn export(
+        &self,
+        request: Request<ExportLogsServiceRequest>,
+    ) -> Result<Response<ExportLogsServiceResponse>, Status> {
+        self.ingest(SignalType::Logs, request).await
+    }
+}
+
+#[tonic::async_trait]
+impl TraceService for OtlpService {
+    async fn export(
+        &self,
+        request: Request<ExportTraceServiceRequest>,
+    ) -> Result<Response<ExportTraceServiceResponse>, Status> {
+        self.ingest(SignalType::Traces, request).await
+    }
+}
+
+#[tonic::async_trait]
+impl MetricsService for OtlpService {
+    async fn export(
+        &self,
+        request: Request<ExportMetricsServiceRequest>,
+    ) -> Result<Response<ExportMetricsServiceResponse>, Status> {
+        self.ingest(SignalType::Metrics, request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_map_to_otlp_grpc_codes() {
+        let unavailable = status(AppError::unavailable(anyhow::anyhow!("s3 timeout")));
+        assert_eq!(unavailable.code(), Code::Unavailable);
+        assert_eq!(unavailable.message(), "s3 timeout");
+        // RetryInfo tells clients to retry after the delay
+        assert!(unavailable
+            .details()
+            .ends_with(&[0x12, 4, 0x0A, 2, 0x08, 5]));
+
+        let invalid = status(AppError::bad_request(anyhow::anyhow!("bad payload")));
+        assert_eq!(invalid.code(), Code::InvalidArgument);
+        assert!(!invalid.details().ends_with(&[0x08, 5]));
+    }
+}
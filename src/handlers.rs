@@ -5,18 +5,23 @@
 use crate::{InputFormat, MetricType, SignalType};
 use axum::{
     extract::State,
-    http::{HeaderMap, StatusCode},
+    http::{HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use metrics::{counter, histogram};
 
-use crate::batch::CompletedBatch;
+use crate::batch::{BufferStats, CompletedBatch};
 use crate::codec::{
     decode_logs_partitioned, decode_metrics_partitioned, decode_traces_partitioned,
-    report_skipped_metrics, ServiceGroupedBatches,
+    report_skipped_metrics, strict_violations, PartitionedBatch, ServiceGroupedBatches,
+    SkippedMetrics,
 };
+use crate::otlp_response::PartialSuccess;
+use crate::request_metrics::{IngestedServices, RequestMetrics};
+use crate::unattributed::Sorted;
 use serde_json::json;
+use std::collections::BTreeMap;
 use std::time::Instant;
 use tracing::{debug, info, warn};
 
@@ -64,6 +69,96 @@ async fn handle_signal(
     state: &AppState,
     headers: HeaderMap,
     body: axum::body::Bytes,
+) -> Result<Response, AppError> {
+    let protobuf = crate::otlp_response::wants_protobuf(&headers);
+    let result = export(signal, state, headers, body).await;
+    if protobuf {
+        return Ok(crate::otlp_response::protobuf(result));
+    }
+    result
+}
+
+/// Ingest one export request with request ids, request metrics, failure
+/// capture and buffer headers, shared by the OTLP/HTTP and OTLP/gRPC
+/// endpoints. The response is the JSON summary; callers re-encode it for
+/// their protocol.
+pub(crate) async fn export(
+    signal: SignalType,
+    state: &AppState,
+    mut headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<Response, AppError> {
+    let start = Instant::now();
+    let request_id = crate::request_id::ensure(&mut headers);
+    let mut result = ingest(signal, state, &headers, body.clone()).await;
+    state
+        .request_metrics
+        .record(signal, &result, body.len(), start.elapsed());
+    if let (Err(ref e), Some(ref captures)) = (&result, &state.captures) {
+        let content_type = headers.get("content-type").and_then(|v| v.to_str().ok());
+        captures.record(
+            signal,
+            content_type,
+            &body,
+            e.status,
+            &format!("{:#}", e.error),
+        );
+    }
+    if let Some(ref mirror) = state.mirror {
+        mirror.submit(signal, &headers, body, &result);
+    }
+    if let (Ok(response), Ok(value)) = (result.as_mut(), request_id.parse()) {
+        response
+            .headers_mut()
+            .insert(crate::request_id::HEADER, value);
+    }
+    if let Ok(response) = result.as_mut() {
+        set_buffer_headers(state, response.headers_mut());
+    }
+    result
+}
+
+/// Report the process-wide batch buffer (all tables) on ingest responses, so
+/// collectors and proxies can watch buffer health without scraping metrics.
+fn set_buffer_headers(state: &AppState, headers: &mut HeaderMap) {
+    let tables = state.buffer_stats();
+    if tables.is_empty() {
+        return;
+    }
+    let total = tables
+        .into_iter()
+        .fold(BufferStats::default(), |total, (_, stats)| {
+            total.merge(stats)
+        });
+    for (name, value) in [
+        ("x-otlp2parquet-buffered-rows", total.rows as u64),
+        ("x-otlp2parquet-buffered-bytes", total.bytes as u64),
+        ("x-otlp2parquet-buffer-age-ms", total.oldest_age_ms),
+    ] {
+        headers.insert(name, HeaderValue::from(value));
+    }
+}
+
+/// GET /admin/buffers - Rows, bytes and oldest batch age buffered per table
+pub(crate) async fn buffer_stats(State(state): State<AppState>) -> impl IntoResponse {
+    let tables = state.buffer_stats();
+    let total = tables
+        .iter()
+        .fold(BufferStats::default(), |total, (_, stats)| {
+            total.merge(*stats)
+        });
+    let tables: BTreeMap<&str, BufferStats> = tables.into_iter().collect();
+    Json(json!({
+        "total": total,
+        "tables": tables,
+    }))
+}
+
+async fn ingest(
+    signal: SignalType,
+    state: &AppState,
+    headers: &HeaderMap,
+    body: axum::body::Bytes,
 ) -> Result<Response, AppError> {
     let content_type = headers.get("content-type").and_then(|v| v.to_str().ok());
     let format = InputFormat::from_content_type(content_type);
@@ -76,26 +171,73 @@ async fn handle_signal(
         content_type
     );
 
-    let max_payload = state.max_payload_bytes;
+    let max_payload = state.max_payload_bytes.for_signal(signal);
     if body.len() > max_payload {
-        counter!("otlp.ingest.rejected").increment(1);
+        counter!("otlp.ingest.rejected", "signal" => signal.as_str()).increment(1);
         return Err(AppError::with_status(
             StatusCode::PAYLOAD_TOO_LARGE,
-            anyhow::anyhow!("payload {} exceeds limit {}", body.len(), max_payload),
+            anyhow::anyhow!(
+                "{} payload {} exceeds limit {}",
+                signal.as_str(),
+                body.len(),
+                max_payload
+            ),
         ));
     }
 
+    shed_if_storage_degraded(state, signal)?;
+    if let Some((used, max)) = crate::writer::disk_full() {
+        counter!("otlp.ingest.disk_full", "signal" => signal.as_str()).increment(1);
+        return Err(AppError::unavailable(anyhow::anyhow!(
+            "filesystem storage is full ({} of {} bytes used); retry later",
+            used,
+            max
+        )));
+    }
+
     match signal {
-        SignalType::Logs => process_logs(state, format, body).await,
-        SignalType::Traces => process_traces(state, format, body).await,
-        SignalType::Metrics => process_metrics(state, format, body).await,
+        SignalType::Logs => process_logs(state, format, body, headers).await,
+        SignalType::Traces => process_traces(state, format, body, headers).await,
+        SignalType::Metrics => process_metrics(state, format, body, headers).await,
     }
 }
 
-async fn process_logs(
+/// Fail with a 429 while the storage backend is slow, so OTLP clients back off
+/// and retry instead of piling more data into buffers that cannot drain.
+fn shed_if_storage_degraded(state: &AppState, signal: SignalType) -> Result<(), AppError> {
+    let Some(limit) = state.shed_write_p95 else {
+        return Ok(());
+    };
+    let Some(p95) = crate::writer::write_latency_p95() else {
+        return Ok(());
+    };
+    if p95 <= limit {
+        return Ok(());
+    }
+
+    counter!("otlp.ingest.shed", "signal" => signal.as_str()).increment(1);
+    warn!(
+        signal = signal.as_str(),
+        p95_ms = p95.as_millis() as u64,
+        limit_ms = limit.as_millis() as u64,
+        "Shedding request: storage write latency above limit"
+    );
+    Err(AppError::with_status(
+        StatusCode::TOO_MANY_REQUESTS,
+        anyhow::anyhow!(
+            "storage write latency p95 {}ms exceeds {}ms; retry later",
+            p95.as_millis(),
+            limit.as_millis()
+        ),
+    ))
+}
+
+/// `headers` are the request's; internal callers pass an empty map.
+pub(crate) async fn process_logs(
     state: &AppState,
     format: InputFormat,
     body: axum::body::Bytes,
+    headers: &HeaderMap,
 ) -> Result<Response, AppError> {
     let start = Instant::now();
     let body_len = body.len();
@@ -103,9 +245,16 @@ async fn process_logs(
     histogram!("otlp.ingest.bytes").record(body_len as f64);
 
     let parse_start = Instant::now();
-    let grouped = decode_logs_partitioned(&body, format).map_err(|e| {
+    let grouped = decode_lenient(state, &body, format, decode_logs_partitioned).map_err(|e| {
         AppError::bad_request(anyhow::anyhow!("Failed to parse OTLP logs request: {}", e))
     })?;
+    check_strict(state, SignalType::Logs, &body, format, None)?;
+    let request_id = crate::request_id::from_headers(headers);
+    let Sorted {
+        attributed: grouped,
+        unattributed,
+    } = attribute(state, SignalType::Logs, headers, grouped)?;
+    let grouped = enrich(state, SignalType::Logs, grouped, headers);
     debug!(
         elapsed_us = parse_start.elapsed().as_micros() as u64,
         signal = "logs",
@@ -113,19 +262,207 @@ async fn process_logs(
         "parse"
     );
 
+    if let Some(ref derived) = state.derived {
+        derived.observe_logs(&grouped);
+    }
+
+    let (grouped, events) = match state.events {
+        Some(ref extractor) => extractor.split(grouped),
+        None => (grouped, Vec::new()),
+    };
+
     // Use batching if enabled, otherwise write directly
-    if let Some(ref batcher) = state.batcher {
-        process_logs_batched(batcher, grouped, body_len, start).await
+    let services = IngestedServices::of([&grouped]);
+    let grouped = forward(state, SignalType::Logs, "logs", grouped, request_id).await;
+    let mut response = if let Some(ref batcher) = state.batcher {
+        process_logs_batched(
+            batcher,
+            &state.request_metrics,
+            grouped,
+            body_len,
+            request_id,
+            start,
+        )
+        .await?
     } else {
-        process_logs_direct(grouped, start).await
+        process_logs_direct(grouped, request_id, start).await?
+    };
+    // Only once the logs are accepted, so a retried request can't duplicate events
+    if let Some(ref events_table) = state.events_table {
+        events_table.accept(events, request_id).await;
+    }
+    write_unattributed(state, SignalType::Logs, unattributed, request_id).await;
+    response.extensions_mut().insert(services);
+    Ok(response)
+}
+
+/// Decode with `decode`; if that fails and lenient parsing is enabled, retry
+/// once with enum names from newer OTLP versions replaced by UNSPECIFIED. The
+/// strict attempt comes first so well-formed payloads pay nothing extra.
+pub(crate) fn decode_lenient<T>(
+    state: &AppState,
+    body: &[u8],
+    format: InputFormat,
+    decode: fn(&[u8], InputFormat) -> Result<T, String>,
+) -> Result<T, String> {
+    let strict_error = match decode(body, format) {
+        Ok(decoded) => return Ok(decoded),
+        Err(e) => e,
+    };
+    if !state.lenient_parsing {
+        return Err(strict_error);
+    }
+    let Some((tolerant, replaced)) = crate::codec::tolerate_unknown_enums(body, format) else {
+        return Err(strict_error);
+    };
+    let decoded = decode(&tolerant, format)?;
+    counter!("otlp.ingest.lenient_values").increment(replaced as u64);
+    debug!(
+        replaced,
+        "Decoded payload leniently after: {}", strict_error
+    );
+    Ok(decoded)
+}
+
+/// With `request.strict_schema`, refuse (400) a payload that conversion would
+/// store lossily, listing what would be lost.
+fn check_strict(
+    state: &AppState,
+    signal: SignalType,
+    body: &[u8],
+    format: InputFormat,
+    skipped: Option<&SkippedMetrics>,
+) -> Result<(), AppError> {
+    strict_error(state, signal, body, format, skipped).map_err(|e| {
+        counter!("otlp.ingest.strict_rejected", "signal" => signal.as_str()).increment(1);
+        AppError::bad_request(anyhow::anyhow!(e))
+    })
+}
+
+/// The strict-schema report for a decoded payload, if it would be refused
+pub(crate) fn strict_error(
+    state: &AppState,
+    signal: SignalType,
+    body: &[u8],
+    format: InputFormat,
+    skipped: Option<&SkippedMetrics>,
+) -> Result<(), String> {
+    /// Problems listed in the error; the rest are summarized
+    const MAX_LISTED: usize = 10;
+
+    if !state.strict_schema {
+        return Ok(());
+    }
+    let violations = strict_violations(signal, body, format, skipped);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let mut listed: Vec<String> = violations
+        .iter()
+        .take(MAX_LISTED)
+        .map(|(what, count)| format!("{} ({})", what, count))
+        .collect();
+    if violations.len() > MAX_LISTED {
+        listed.push(format!("and {} more", violations.len() - MAX_LISTED));
+    }
+    Err(format!(
+        "Rejected by request.strict_schema, the payload would be stored lossily: {}",
+        listed.join("; ")
+    ))
+}
+
+/// Apply the configured enrichment stages to freshly decoded batches.
+pub(crate) fn enrich(
+    state: &AppState,
+    signal: SignalType,
+    mut grouped: ServiceGroupedBatches,
+    headers: &HeaderMap,
+) -> ServiceGroupedBatches {
+    if signal == SignalType::Logs && state.normalize_severity {
+        crate::enrich::normalize_severity(&mut grouped);
+    }
+    if let Some(ref attributes) = state.resource_attributes {
+        attributes.enrich(&mut grouped);
+    }
+    #[cfg(feature = "k8s-enrichment")]
+    if let Some(ref k8s) = state.k8s {
+        k8s.enrich(&mut grouped);
+    }
+    #[cfg(feature = "geoip")]
+    if let Some(ref geoip) = state.geoip {
+        geoip.enrich(signal, &mut grouped);
+    }
+    if let Some(ref columns) = state.baggage {
+        let baggage = headers.get("baggage").and_then(|v| v.to_str().ok());
+        columns.enrich(signal, baggage, &mut grouped);
+    }
+    if let Some(ref provenance) = state.provenance {
+        provenance.enrich(&mut grouped);
+    }
+    grouped
+}
+
+/// Apply the `request.unattributed` policy: records without a service are
+/// rejected (400), set aside for `_unattributed/{table}`, or attributed to the
+/// service named by a request header.
+fn attribute(
+    state: &AppState,
+    signal: SignalType,
+    headers: &HeaderMap,
+    grouped: ServiceGroupedBatches,
+) -> Result<Sorted, AppError> {
+    let Some(ref unattributed) = state.unattributed else {
+        return Ok(Sorted {
+            attributed: grouped,
+            unattributed: Vec::new(),
+        });
+    };
+    unattributed
+        .sort(signal.as_str(), headers, grouped)
+        .map_err(|e| AppError::bad_request(anyhow::anyhow!(e)))
+}
+
+/// Hand the records set aside by [`attribute`] to their table, once the rest
+/// of the request is accepted so a retried request can't duplicate them.
+async fn write_unattributed(
+    state: &AppState,
+    signal: SignalType,
+    records: Vec<PartitionedBatch>,
+    request_id: Option<&str>,
+) {
+    if records.is_empty() {
+        return;
+    }
+    let table = match (signal, &state.unattributed_tables) {
+        (SignalType::Logs, Some(tables)) => &tables.logs,
+        (SignalType::Traces, Some(tables)) => &tables.traces,
+        _ => return,
+    };
+    table.accept(records, request_id).await;
+}
+
+/// Hand batches owned by another replica (`cluster.peers`) to it. Returns
+/// the records to batch here.
+async fn forward(
+    state: &AppState,
+    signal: SignalType,
+    table: &str,
+    grouped: ServiceGroupedBatches,
+    request_id: Option<&str>,
+) -> ServiceGroupedBatches {
+    match state.cluster {
+        Some(ref cluster) => cluster.forward(signal, table, grouped, request_id).await,
+        None => grouped,
     }
 }
 
 /// Process logs with batching - accumulate in memory, flush when thresholds hit
 async fn process_logs_batched(
     batcher: &crate::batch::BatchManager,
+    request_metrics: &RequestMetrics,
     grouped: ServiceGroupedBatches,
     body_len: usize,
+    request_id: Option<&str>,
     start: Instant,
 ) -> Result<Response, AppError> {
     let mut total_records: usize = 0;
@@ -147,7 +484,7 @@ async fn process_logs_batched(
 
         // Ingest into batcher - may return completed batches if thresholds hit
         let (completed, _metadata) = batcher
-            .ingest(&pb, approx_bytes_per_batch)
+            .ingest(&pb, approx_bytes_per_batch, request_id)
             .map_err(|e| AppError::internal(anyhow::anyhow!("Batch ingestion failed: {}", e)))?;
 
         if completed.is_empty() {
@@ -161,10 +498,10 @@ async fn process_logs_batched(
         } else {
             // Thresholds hit - flush completed batches
             for batch in completed {
-                let paths = persist_batch(&batch, SignalType::Logs, None)
+                let paths = persist_batch(&batch, SignalType::Logs, None, request_metrics)
                     .await
                     .map_err(|e| {
-                        AppError::internal(anyhow::anyhow!("Failed to flush batch: {}", e))
+                        AppError::unavailable(anyhow::anyhow!("Failed to flush batch: {}", e))
                     })?;
 
                 for path in &paths {
@@ -172,6 +509,7 @@ async fn process_logs_batched(
                         path = %path,
                         service = %batch.metadata.service_name,
                         rows = batch.metadata.record_count,
+                        request_ids = %batch.request_ids.join(","),
                         "Flushed batch (threshold)"
                     );
                 }
@@ -203,6 +541,7 @@ async fn process_logs_batched(
 /// Process logs directly - write each batch immediately (no batching)
 async fn process_logs_direct(
     grouped: ServiceGroupedBatches,
+    request_id: Option<&str>,
     start: Instant,
 ) -> Result<Response, AppError> {
     let write_start = Instant::now();
@@ -212,6 +551,7 @@ async fn process_logs_direct(
         None,
         "logs to storage",
         BatchWriteMode::Logs,
+        request_id,
     )
     .await?;
     debug!(
@@ -233,10 +573,11 @@ async fn process_logs_direct(
     Ok((StatusCode::OK, response).into_response())
 }
 
-async fn process_traces(
+pub(crate) async fn process_traces(
     state: &AppState,
     format: InputFormat,
     body: axum::body::Bytes,
+    headers: &HeaderMap,
 ) -> Result<Response, AppError> {
     let start = Instant::now();
     let body_len = body.len();
@@ -244,12 +585,19 @@ async fn process_traces(
     histogram!("otlp.ingest.bytes", "signal" => "traces").record(body_len as f64);
 
     let parse_start = Instant::now();
-    let grouped = decode_traces_partitioned(&body, format).map_err(|e| {
+    let grouped = decode_lenient(state, &body, format, decode_traces_partitioned).map_err(|e| {
         AppError::bad_request(anyhow::anyhow!(
             "Failed to parse OTLP traces request: {}",
             e
         ))
     })?;
+    check_strict(state, SignalType::Traces, &body, format, None)?;
+    let request_id = crate::request_id::from_headers(headers);
+    let Sorted {
+        attributed: grouped,
+        unattributed,
+    } = attribute(state, SignalType::Traces, headers, grouped)?;
+    let grouped = enrich(state, SignalType::Traces, grouped, headers);
     debug!(
         elapsed_us = parse_start.elapsed().as_micros() as u64,
         signal = "traces",
@@ -257,19 +605,38 @@ async fn process_traces(
         "parse"
     );
 
+    if let Some(ref derived) = state.derived {
+        derived.observe_spans(&grouped);
+    }
+
     // Use batching if enabled, otherwise write directly
-    if let Some(ref batcher) = state.traces_batcher {
-        process_traces_batched(batcher, grouped, body_len, start).await
+    let services = IngestedServices::of([&grouped]);
+    let grouped = forward(state, SignalType::Traces, "traces", grouped, request_id).await;
+    let mut response = if let Some(ref batcher) = state.traces_batcher {
+        process_traces_batched(
+            batcher,
+            &state.request_metrics,
+            grouped,
+            body_len,
+            request_id,
+            start,
+        )
+        .await?
     } else {
-        process_traces_direct(grouped, start).await
-    }
+        process_traces_direct(grouped, request_id, start).await?
+    };
+    write_unattributed(state, SignalType::Traces, unattributed, request_id).await;
+    response.extensions_mut().insert(services);
+    Ok(response)
 }
 
 /// Process traces with batching - accumulate in memory, flush when thresholds hit
 async fn process_traces_batched(
     batcher: &crate::batch::BatchManager,
+    request_metrics: &RequestMetrics,
     grouped: ServiceGroupedBatches,
     body_len: usize,
+    request_id: Option<&str>,
     start: Instant,
 ) -> Result<Response, AppError> {
     let mut total_records: usize = 0;
@@ -289,7 +656,7 @@ async fn process_traces_batched(
         counter!("otlp.ingest.records", "signal" => "traces").increment(pb.record_count as u64);
 
         let (completed, _metadata) = batcher
-            .ingest(&pb, approx_bytes_per_batch)
+            .ingest(&pb, approx_bytes_per_batch, request_id)
             .map_err(|e| AppError::internal(anyhow::anyhow!("Batch ingestion failed: {}", e)))?;
 
         if completed.is_empty() {
@@ -301,10 +668,10 @@ async fn process_traces_batched(
             );
         } else {
             for batch in completed {
-                let paths = persist_batch(&batch, SignalType::Traces, None)
+                let paths = persist_batch(&batch, SignalType::Traces, None, request_metrics)
                     .await
                     .map_err(|e| {
-                        AppError::internal(anyhow::anyhow!("Failed to flush batch: {}", e))
+                        AppError::unavailable(anyhow::anyhow!("Failed to flush batch: {}", e))
                     })?;
 
                 for path in &paths {
@@ -312,6 +679,7 @@ async fn process_traces_batched(
                         path = %path,
                         service = %batch.metadata.service_name,
                         rows = batch.metadata.record_count,
+                        request_ids = %batch.request_ids.join(","),
                         "Flushed traces batch (threshold)"
                     );
                 }
@@ -344,6 +712,7 @@ async fn process_traces_batched(
 /// Process traces directly - write each batch immediately (no batching)
 async fn process_traces_direct(
     grouped: ServiceGroupedBatches,
+    request_id: Option<&str>,
     start: Instant,
 ) -> Result<Response, AppError> {
     let write_start = Instant::now();
@@ -353,6 +722,7 @@ async fn process_traces_direct(
         None,
         "traces to storage",
         BatchWriteMode::Traces,
+        request_id,
     )
     .await?;
     debug!(
@@ -385,10 +755,11 @@ async fn process_traces_direct(
     Ok((StatusCode::OK, response).into_response())
 }
 
-async fn process_metrics(
+pub(crate) async fn process_metrics(
     state: &AppState,
     format: InputFormat,
     body: axum::body::Bytes,
+    headers: &HeaderMap,
 ) -> Result<Response, AppError> {
     let start = Instant::now();
     let body_len = body.len();
@@ -396,13 +767,49 @@ async fn process_metrics(
     histogram!("otlp.ingest.bytes", "signal" => "metrics").record(body_len as f64);
 
     let parse_start = Instant::now();
-    let partitioned = decode_metrics_partitioned(&body, format).map_err(|e| {
-        AppError::bad_request(anyhow::anyhow!(
-            "Failed to parse OTLP metrics request: {}",
-            e
-        ))
-    })?;
+    let mut partitioned = decode_lenient(state, &body, format, decode_metrics_partitioned)
+        .map_err(|e| {
+            AppError::bad_request(anyhow::anyhow!(
+                "Failed to parse OTLP metrics request: {}",
+                e
+            ))
+        })?;
     report_skipped_metrics(&partitioned.skipped);
+    check_strict(
+        state,
+        SignalType::Metrics,
+        &body,
+        format,
+        Some(&partitioned.skipped),
+    )?;
+    let request_id = crate::request_id::from_headers(headers);
+
+    for (metric_type, group) in [
+        (MetricType::Gauge, &mut partitioned.gauge),
+        (MetricType::Sum, &mut partitioned.sum),
+        (MetricType::Histogram, &mut partitioned.histogram),
+        (
+            MetricType::ExponentialHistogram,
+            &mut partitioned.exp_histogram,
+        ),
+    ] {
+        let table = format!("metrics/{}", metric_type.as_str());
+        let attributed = attribute(
+            state,
+            SignalType::Metrics,
+            &table,
+            headers,
+            std::mem::take(group),
+        )
+        .await?;
+        *group = enrich(state, SignalType::Metrics, attributed, headers);
+    }
+
+    if let Some(ref guard) = state.cardinality {
+        guard
+            .apply(&mut partitioned)
+            .map_err(AppError::bad_request)?;
+    }
     debug!(
         elapsed_us = parse_start.elapsed().as_micros() as u64,
         signal = "metrics",
@@ -413,18 +820,61 @@ async fn process_metrics(
         "parse"
     );
 
-    if let Some(ref mb) = state.metrics_batchers {
-        process_metrics_batched(mb, partitioned, body_len, start).await
+    let services = IngestedServices::of([
+        &partitioned.gauge,
+        &partitioned.sum,
+        &partitioned.histogram,
+        &partitioned.exp_histogram,
+    ]);
+    if state.cluster.is_some() {
+        for (metric_type, group) in [
+            (MetricType::Gauge, &mut partitioned.gauge),
+            (MetricType::Sum, &mut partitioned.sum),
+            (MetricType::Histogram, &mut partitioned.histogram),
+            (
+                MetricType::ExponentialHistogram,
+                &mut partitioned.exp_histogram,
+            ),
+        ] {
+            let table = format!("metrics/{}", metric_type.as_str());
+            *group = forward(
+                state,
+                SignalType::Metrics,
+                &table,
+                std::mem::take(group),
+                request_id,
+            )
+            .await;
+        }
+    }
+    let partial = PartialSuccess::for_metrics(&partitioned.skipped);
+    let mut response = if let Some(ref mb) = state.metrics_batchers {
+        process_metrics_batched(
+            mb,
+            &state.request_metrics,
+            partitioned,
+            body_len,
+            request_id,
+            start,
+        )
+        .await?
     } else {
-        process_metrics_direct(partitioned, start).await
+        process_metrics_direct(partitioned, request_id, start).await?
+    };
+    response.extensions_mut().insert(services);
+    if let Some(partial) = partial {
+        response.extensions_mut().insert(partial);
     }
+    Ok(response)
 }
 
 /// Process metrics with batching - accumulate per metric type, flush when thresholds hit
 async fn process_metrics_batched(
     batchers: &crate::MetricsBatchers,
+    request_metrics: &RequestMetrics,
     partitioned: crate::codec::PartitionedMetrics,
     body_len: usize,
+    request_id: Option<&str>,
     start: Instant,
 ) -> Result<Response, AppError> {
     let mut total_buffered: usize = 0;
@@ -475,8 +925,9 @@ async fn process_metrics_batched(
             counter!("otlp.ingest.records", "signal" => "metrics", "metric_type" => metric_type_str)
                 .increment(pb.record_count as u64);
 
-            let (completed, _metadata) =
-                batcher.ingest(&pb, approx_bytes_per_batch).map_err(|e| {
+            let (completed, _metadata) = batcher
+                .ingest(&pb, approx_bytes_per_batch, request_id)
+                .map_err(|e| {
                     AppError::internal(anyhow::anyhow!("Batch ingestion failed: {}", e))
                 })?;
 
@@ -490,11 +941,16 @@ async fn process_metrics_batched(
                 );
             } else {
                 for batch in completed {
-                    let paths = persist_batch(&batch, SignalType::Metrics, Some(metric_type_str))
-                        .await
-                        .map_err(|e| {
-                            AppError::internal(anyhow::anyhow!("Failed to flush batch: {}", e))
-                        })?;
+                    let paths = persist_batch(
+                        &batch,
+                        SignalType::Metrics,
+                        Some(metric_type_str),
+                        request_metrics,
+                    )
+                    .await
+                    .map_err(|e| {
+                        AppError::unavailable(anyhow::anyhow!("Failed to flush batch: {}", e))
+                    })?;
 
                     for path in &paths {
                         info!(
@@ -502,6 +958,7 @@ async fn process_metrics_batched(
                             service = %batch.metadata.service_name,
                             metric_type = metric_type_str,
                             rows = batch.metadata.record_count,
+                            request_ids = %batch.request_ids.join(","),
                             "Flushed metrics batch (threshold)"
                         );
                     }
@@ -533,7 +990,7 @@ async fn process_metrics_batched(
     histogram!("otlp.ingest.latency_ms", "signal" => "metrics")
         .record(start.elapsed().as_secs_f64() * 1000.0);
 
-    let response = Json(json!({
+    let mut response = json!({
         "status": "ok",
         "mode": "batched",
         "data_points_processed": total_processed,
@@ -545,14 +1002,18 @@ async fn process_metrics_batched(
         "summary_count": partitioned.skipped.summaries,
         "flush_count": flushed_paths.len(),
         "partitions": flushed_paths,
-    }));
+    });
+    if let Some(partial) = PartialSuccess::for_metrics(&partitioned.skipped) {
+        response["partialSuccess"] = partial.to_json();
+    }
 
-    Ok((StatusCode::OK, response).into_response())
+    Ok((StatusCode::OK, Json(response)).into_response())
 }
 
 /// Process metrics directly - write each batch immediately (no batching)
 async fn process_metrics_direct(
     partitioned: crate::codec::PartitionedMetrics,
+    request_id: Option<&str>,
     start: Instant,
 ) -> Result<Response, AppError> {
     let gauge_count = partitioned.gauge.total_records;
@@ -563,12 +1024,20 @@ async fn process_metrics_direct(
     let write_start = Instant::now();
     let mut uploaded_paths = Vec::new();
 
-    uploaded_paths.extend(write_metric_batches(MetricType::Gauge, partitioned.gauge).await?);
-    uploaded_paths.extend(write_metric_batches(MetricType::Sum, partitioned.sum).await?);
     uploaded_paths
-        .extend(write_metric_batches(MetricType::Histogram, partitioned.histogram).await?);
+        .extend(write_metric_batches(MetricType::Gauge, partitioned.gauge, request_id).await?);
+    uploaded_paths
+        .extend(write_metric_batches(MetricType::Sum, partitioned.sum, request_id).await?);
+    uploaded_paths.extend(
+        write_metric_batches(MetricType::Histogram, partitioned.histogram, request_id).await?,
+    );
     uploaded_paths.extend(
-        write_metric_batches(MetricType::ExponentialHistogram, partitioned.exp_histogram).await?,
+        write_metric_batches(
+            MetricType::ExponentialHistogram,
+            partitioned.exp_histogram,
+            request_id,
+        )
+        .await?,
     );
 
     debug!(
@@ -602,7 +1071,7 @@ async fn process_metrics_direct(
     histogram!("otlp.ingest.latency_ms", "signal" => "metrics")
         .record(start.elapsed().as_secs_f64() * 1000.0);
 
-    let response = Json(json!({
+    let mut response = json!({
         "status": "ok",
         "mode": "direct",
         "data_points_processed": gauge_count + sum_count + histogram_count + exp_histogram_count,
@@ -612,14 +1081,18 @@ async fn process_metrics_direct(
         "exponential_histogram_count": exp_histogram_count,
         "summary_count": partitioned.skipped.summaries,
         "partitions": uploaded_paths,
-    }));
+    });
+    if let Some(partial) = PartialSuccess::for_metrics(&partitioned.skipped) {
+        response["partialSuccess"] = partial.to_json();
+    }
 
-    Ok((StatusCode::OK, response).into_response())
+    Ok((StatusCode::OK, Json(response)).into_response())
 }
 
 async fn write_metric_batches(
     metric_type: MetricType,
     grouped: ServiceGroupedBatches,
+    request_id: Option<&str>,
 ) -> Result<Vec<String>, AppError> {
     if grouped.is_empty() {
         return Ok(Vec::new());
@@ -649,6 +1122,7 @@ async fn write_metric_batches(
         BatchWriteMode::Metrics {
             metric_type: metric_type.as_str(),
         },
+        request_id,
     )
     .await?;
 
@@ -661,6 +1135,7 @@ pub(crate) async fn persist_batch(
     completed: &CompletedBatch,
     signal_type: SignalType,
     metric_type: Option<&str>,
+    request_metrics: &RequestMetrics,
 ) -> Result<Vec<String>, anyhow::Error> {
     let mut paths = Vec::new();
 
@@ -669,12 +1144,13 @@ pub(crate) async fn persist_batch(
             continue;
         }
 
-        let path = crate::writer::write_batch(crate::writer::WriteBatchRequest {
+        let written = crate::writer::write_batch(crate::writer::WriteBatchRequest {
             batch,
             signal_type,
             metric_type,
             service_name: &completed.metadata.service_name,
             timestamp_micros: completed.metadata.first_timestamp_micros,
+            request_ids: &completed.request_ids,
         })
         .await?;
 
@@ -686,9 +1162,15 @@ pub(crate) async fn persist_batch(
                 counter!("otlp.metrics.flushes", "metric_type" => mt.to_string()).increment(1);
             }
         }
-        paths.push(path);
+        paths.extend(written);
     }
 
+    request_metrics.record_flush_lag(
+        signal_type,
+        &completed.metadata.service_name,
+        completed.buffered_since.elapsed(),
+    );
+
     Ok(paths)
 }
 
@@ -704,7 +1186,9 @@ async fn write_grouped_batches(
     metric_type: Option<&str>,
     error_context: &'static str,
     mode: BatchWriteMode,
+    request_id: Option<&str>,
 ) -> Result<(Vec<String>, usize), AppError> {
+    let request_ids: Vec<String> = request_id.map(str::to_string).into_iter().collect();
     let mut paths = Vec::new();
     let mut total_records = 0usize;
 
@@ -725,44 +1209,56 @@ async fn write_grouped_batches(
             BatchWriteMode::Metrics { .. } => {}
         }
 
-        let path = crate::writer::write_batch(crate::writer::WriteBatchRequest {
+        let written = crate::writer::write_batch(crate::writer::WriteBatchRequest {
             batch: &pb.batch,
             signal_type,
             metric_type,
             service_name: &pb.service_name,
             timestamp_micros: pb.min_timestamp_micros,
+            request_ids: &request_ids,
         })
         .await
         .map_err(|e| {
-            AppError::internal(anyhow::anyhow!("Failed to write {}: {}", error_context, e))
+            AppError::unavailable(anyhow::anyhow!("Failed to write {}: {}", error_context, e))
         })?;
 
+        let path = written.join(",");
         match mode {
             BatchWriteMode::Logs => {
                 counter!("otlp.batch.flushes").increment(1);
                 histogram!("otlp.batch.rows").record(pb.record_count as f64);
                 info!(
-                    "Committed batch path={} service={} rows={}",
-                    path, pb.service_name, pb.record_count
+                    "Committed batch path={} service={} rows={} request_id={}",
+                    path,
+                    pb.service_name,
+                    pb.record_count,
+                    request_id.unwrap_or("-")
                 );
             }
             BatchWriteMode::Traces => {
                 counter!("otlp.traces.flushes").increment(1);
                 histogram!("otlp.batch.rows", "signal" => "traces").record(pb.record_count as f64);
                 info!(
-                    "Committed traces batch path={} service={} spans={}",
-                    path, pb.service_name, pb.record_count
+                    "Committed traces batch path={} service={} spans={} request_id={}",
+                    path,
+                    pb.service_name,
+                    pb.record_count,
+                    request_id.unwrap_or("-")
                 );
             }
             BatchWriteMode::Metrics { metric_type } => {
                 counter!("otlp.metrics.flushes", "metric_type" => metric_type).increment(1);
                 info!(
-                    "Committed metrics batch path={} metric_type={} service={} points={}",
-                    path, metric_type, pb.service_name, pb.record_count
+                    "Committed metrics batch path={} metric_type={} service={} points={} request_id={}",
+                    path,
+                    metric_type,
+                    pb.service_name,
+                    pb.record_count,
+                    request_id.unwrap_or("-")
                 );
             }
         }
-        paths.push(path);
+        paths.extend(written);
     }
 
     Ok((paths, total_records))
@@ -0,0 +1,568 @@
+//! `import clickhouse` reads the `otel_logs` and `otel_traces` tables of the
+//! OpenTelemetry Collector's ClickHouse exporter through the HTTP interface,
+//! one time chunk per query, and rebuilds OTLP/JSON export requests from the
+//! rows (grouped by resource and scope again), so the written Parquet has the
+//! same schema as data received live.
+//!
+//! The exporter stores attribute values as strings; they are imported as
+//! string attributes. The checkpoint keeps, per source table, the end of the
+//! last fully imported chunk.
+
+use crate::config::RuntimeConfig;
+use crate::{AppState, InputFormat, SignalType};
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Rows per export request pushed through the pipeline
+const ROWS_PER_REQUEST: usize = 10_000;
+
+/// Rows of `otel_logs`, with timestamps as nanosecond strings
+const LOGS_QUERY: &str = "SELECT toString(toUnixTimestamp64Nano(Timestamp)) AS TimeUnixNano, \
+     TraceId, SpanId, TraceFlags, SeverityText, SeverityNumber, ServiceName, Body, \
+     ResourceAttributes, ScopeName, ScopeVersion, LogAttributes FROM {table} \
+     WHERE Timestamp >= fromUnixTimestamp64Nano({start:Int64}) \
+     AND Timestamp < fromUnixTimestamp64Nano({end:Int64}) FORMAT JSONEachRow";
+
+/// Rows of `otel_traces`, with nested events and links as parallel arrays
+const TRACES_QUERY: &str = "SELECT toString(toUnixTimestamp64Nano(Timestamp)) AS TimeUnixNano, \
+     TraceId, SpanId, ParentSpanId, TraceState, SpanName, SpanKind, ServiceName, \
+     ResourceAttributes, ScopeName, ScopeVersion, SpanAttributes, \
+     toString(Duration) AS DurationNanos, StatusCode, StatusMessage, \
+     arrayMap(t -> toString(toUnixTimestamp64Nano(t)), `Events.Timestamp`) AS EventTimes, \
+     `Events.Name` AS EventNames, `Events.Attributes` AS EventAttributes, \
+     `Links.TraceId` AS LinkTraceIds, `Links.SpanId` AS LinkSpanIds, \
+     `Links.TraceState` AS LinkTraceStates, `Links.Attributes` AS LinkAttributes \
+     FROM {table} \
+     WHERE Timestamp >= fromUnixTimestamp64Nano({start:Int64}) \
+     AND Timestamp < fromUnixTimestamp64Nano({end:Int64}) FORMAT JSONEachRow";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ClickHouseTable {
+    Logs,
+    Traces,
+}
+
+impl ClickHouseTable {
+    fn signal(self) -> SignalType {
+        match self {
+            ClickHouseTable::Logs => SignalType::Logs,
+            ClickHouseTable::Traces => SignalType::Traces,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ClickHouseArgs {
+    /// HTTP interface of the source ClickHouse
+    #[arg(long, default_value = "http://localhost:8123")]
+    pub url: String,
+
+    /// Database holding the exporter tables
+    #[arg(long, default_value = "otel")]
+    pub database: String,
+
+    /// Prefix of the exporter tables
+    #[arg(long, default_value = "otel_")]
+    pub table_prefix: String,
+
+    /// ClickHouse user
+    #[arg(long)]
+    pub username: Option<String>,
+
+    /// ClickHouse password [default: $CLICKHOUSE_PASSWORD]
+    #[arg(long)]
+    pub password: Option<String>,
+
+    /// Tables to import, comma separated
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [ClickHouseTable::Logs, ClickHouseTable::Traces])]
+    pub tables: Vec<ClickHouseTable>,
+
+    /// Oldest rows to import, in any format ClickHouse's
+    /// parseDateTime64BestEffort reads (default: the oldest row)
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Import rows before this time (default: up to the newest row)
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Minutes of data read per query
+    #[arg(long, default_value_t = 60)]
+    pub chunk_minutes: u64,
+
+    /// Checkpoint file
+    #[arg(
+        long,
+        value_name = "FILE",
+        default_value = ".otlp2parquet-clickhouse-import.json"
+    )]
+    pub checkpoint: PathBuf,
+}
+
+/// Per source table (`database.table`), the timestamp in nanoseconds before
+/// which every row is imported
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Checkpoint {
+    tables: BTreeMap<String, i64>,
+}
+
+struct Source {
+    client: reqwest::Client,
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl ClickHouseArgs {
+    pub async fn run(&self, config: RuntimeConfig) -> Result<()> {
+        if self.chunk_minutes == 0 {
+            bail!("--chunk-minutes must be at least 1");
+        }
+        let source = Source {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(600))
+                .build()
+                .context("Failed to build ClickHouse HTTP client")?,
+            url: self.url.clone(),
+            username: self.username.clone(),
+            password: self
+                .password
+                .clone()
+                .or_else(|| std::env::var("CLICKHOUSE_PASSWORD").ok()),
+        };
+        let mut checkpoint: Checkpoint = super::load_checkpoint(&self.checkpoint)?;
+        let state = super::start(&config).await?;
+
+        let mut total = (0u64, 0u64);
+        for &table in &self.tables {
+            let (imported, rejected) = self
+                .import_table(&state, &source, table, &mut checkpoint)
+                .await?;
+            total.0 += imported;
+            total.1 += rejected;
+        }
+
+        super::finish(&config).await;
+        println!(
+            "Imported {} rows from ClickHouse ({} rejected)",
+            total.0, total.1
+        );
+        Ok(())
+    }
+
+    /// Import one table chunk by chunk; returns rows imported and rejected
+    async fn import_table(
+        &self,
+        state: &AppState,
+        source: &Source,
+        table: ClickHouseTable,
+        checkpoint: &mut Checkpoint,
+    ) -> Result<(u64, u64)> {
+        let name = format!("{}{}", self.table_prefix, table.signal().as_str());
+        let qualified = format!(
+            "{}.{}",
+            crate::clickhouse::quote_identifier(&self.database),
+            crate::clickhouse::quote_identifier(&name)
+        );
+        let key = format!("{}.{}", self.database, name);
+
+        let Some((oldest, newest)) = self.bounds(source, &qualified).await? else {
+            tracing::info!("No rows to import from {}", key);
+            return Ok((0, 0));
+        };
+        let end = newest.saturating_add(1);
+        let mut start = checkpoint
+            .tables
+            .get(&key)
+            .copied()
+            .unwrap_or(oldest)
+            .max(oldest);
+        let chunk = (self.chunk_minutes as i64).saturating_mul(60_000_000_000);
+        let query = match table {
+            ClickHouseTable::Logs => LOGS_QUERY,
+            ClickHouseTable::Traces => TRACES_QUERY,
+        }
+        .replace("{table}", &qualified);
+
+        let (mut imported, mut rejected) = (0u64, 0u64);
+        while start < end {
+            let chunk_end = start.saturating_add(chunk).min(end);
+            let rows = source
+                .query(
+                    &query,
+                    &[("start", start.to_string()), ("end", chunk_end.to_string())],
+                )
+                .await
+                .with_context(|| format!("Failed to read {}", key))?;
+
+            let mut export = Export::default();
+            let mut lines = rows.lines().filter(|l| !l.trim().is_empty()).peekable();
+            while let Some(line) = lines.next() {
+                push_row(&mut export, table, line)
+                    .with_context(|| format!("Unexpected row in {}", key))?;
+                if export.records < ROWS_PER_REQUEST && lines.peek().is_some() {
+                    continue;
+                }
+                let (records, body) = export.take(table);
+                match super::ingest(state, table.signal(), InputFormat::Json, body).await? {
+                    Ok(()) => imported += records as u64,
+                    Err(reason) => {
+                        rejected += records as u64;
+                        tracing::warn!(table = %key, records, "Skipping rejected rows: {}", reason);
+                    }
+                }
+            }
+
+            crate::flush_pending_batches(state).await?;
+            checkpoint.tables.insert(key.clone(), chunk_end);
+            super::save_checkpoint(&self.checkpoint, checkpoint)?;
+            tracing::debug!(table = %key, start, end = chunk_end, "Imported chunk");
+            start = chunk_end;
+        }
+        tracing::info!("Imported {} rows from {}", imported, key);
+        Ok((imported, rejected))
+    }
+
+    /// Oldest and newest row timestamps in nanoseconds within `--from`/`--to`
+    async fn bounds(&self, source: &Source, table: &str) -> Result<Option<(i64, i64)>> {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        if let Some(from) = &self.from {
+            conditions.push("Timestamp >= parseDateTime64BestEffort({from:String}, 9)");
+            params.push(("from", from.clone()));
+        }
+        if let Some(to) = &self.to {
+            conditions.push("Timestamp < parseDateTime64BestEffort({to:String}, 9)");
+            params.push(("to", to.clone()));
+        }
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+        let query = format!(
+            "SELECT count() AS rows, toString(toUnixTimestamp64Nano(min(Timestamp))) AS oldest, \
+             toString(toUnixTimestamp64Nano(max(Timestamp))) AS newest FROM {}{} FORMAT JSONEachRow",
+            table, filter
+        );
+
+        #[derive(Deserialize)]
+        struct Bounds {
+            rows: Value,
+            oldest: String,
+            newest: String,
+        }
+        let text = source.query(&query, &params).await?;
+        let bounds: Bounds = serde_json::from_str(text.trim())
+            .with_context(|| format!("Unexpected ClickHouse response: {}", text.trim()))?;
+        if number(&bounds.rows) == Some(0) {
+            return Ok(None);
+        }
+        Ok(Some((bounds.oldest.parse()?, bounds.newest.parse()?)))
+    }
+}
+
+impl Source {
+    /// Run `query` with `{name:Type}` parameters, returning the response body
+    async fn query(&self, query: &str, params: &[(&str, String)]) -> Result<String> {
+        let mut url = reqwest::Url::parse(&self.url).context("Invalid ClickHouse URL")?;
+        {
+            let mut pairs = url.query_pairs_mut();
+            for (name, value) in params {
+                pairs.append_pair(&format!("param_{}", name), value);
+            }
+        }
+        let mut request = self.client.post(url).body(query.to_string());
+        if let Some(user) = &self.username {
+            request = request.header("X-ClickHouse-User", user);
+        }
+        if let Some(password) = &self.password {
+            request = request.header("X-ClickHouse-Key", password);
+        }
+        let response = request.send().await.context("ClickHouse request failed")?;
+        let status = response.status();
+        let text = response.text().await.context("ClickHouse request failed")?;
+        if !status.is_success() {
+            bail!("ClickHouse returned {}: {}", status, text.trim());
+        }
+        Ok(text)
+    }
+}
+
+/// A JSON number, also when ClickHouse quotes 64-bit integers
+fn number(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LogRow {
+    time_unix_nano: String,
+    trace_id: String,
+    span_id: String,
+    trace_flags: u32,
+    severity_text: String,
+    severity_number: i32,
+    service_name: String,
+    body: String,
+    resource_attributes: BTreeMap<String, String>,
+    scope_name: String,
+    scope_version: String,
+    log_attributes: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SpanRow {
+    time_unix_nano: String,
+    trace_id: String,
+    span_id: String,
+    parent_span_id: String,
+    trace_state: String,
+    span_name: String,
+    span_kind: String,
+    service_name: String,
+    resource_attributes: BTreeMap<String, String>,
+    scope_name: String,
+    scope_version: String,
+    span_attributes: BTreeMap<String, String>,
+    duration_nanos: String,
+    status_code: String,
+    status_message: String,
+    event_times: Vec<String>,
+    event_names: Vec<String>,
+    event_attributes: Vec<BTreeMap<String, String>>,
+    link_trace_ids: Vec<String>,
+    link_span_ids: Vec<String>,
+    link_trace_states: Vec<String>,
+    link_attributes: Vec<BTreeMap<String, String>>,
+}
+
+/// Records grouped by resource and scope, building one export request
+#[derive(Default)]
+struct Export {
+    /// Resource attributes (as JSON) -> (resource, scope -> records)
+    resources: BTreeMap<String, (Value, BTreeMap<(String, String), Vec<Value>>)>,
+    records: usize,
+}
+
+impl Export {
+    fn push(
+        &mut self,
+        service: &str,
+        resource: &BTreeMap<String, String>,
+        scope: (String, String),
+        record: Value,
+    ) {
+        let mut resource = resource.clone();
+        if !service.is_empty() {
+            resource
+                .entry("service.name".to_string())
+                .or_insert_with(|| service.to_string());
+        }
+        let key = serde_json::to_string(&resource).unwrap_or_default();
+        let (_, scopes) = self.resources.entry(key).or_insert_with(|| {
+            (
+                json!({ "attributes": attributes(&resource) }),
+                BTreeMap::new(),
+            )
+        });
+        scopes.entry(scope).or_default().push(record);
+        self.records += 1;
+    }
+
+    /// The OTLP/JSON request with every record pushed so far, and its size in
+    /// records; starts over empty
+    fn take(&mut self, table: ClickHouseTable) -> (usize, Vec<u8>) {
+        let (resources_key, scopes_key, records_key) = match table {
+            ClickHouseTable::Logs => ("resourceLogs", "scopeLogs", "logRecords"),
+            ClickHouseTable::Traces => ("resourceSpans", "scopeSpans", "spans"),
+        };
+        let resources: Vec<Value> = std::mem::take(&mut self.resources)
+            .into_values()
+            .map(|(resource, scopes)| {
+                let scopes: Vec<Value> = scopes
+                    .into_iter()
+                    .map(|((name, version), records)| {
+                        let mut scope = Map::new();
+                        scope.insert(
+                            "scope".to_string(),
+                            json!({ "name": name, "version": version }),
+                        );
+                        scope.insert(records_key.to_string(), Value::Array(records));
+                        Value::Object(scope)
+                    })
+                    .collect();
+                let mut entry = Map::new();
+                entry.insert("resource".to_string(), resource);
+                entry.insert(scopes_key.to_string(), Value::Array(scopes));
+                Value::Object(entry)
+            })
+            .collect();
+        let mut request = Map::new();
+        request.insert(resources_key.to_string(), Value::Array(resources));
+        let records = std::mem::take(&mut self.records);
+        (records, Value::Object(request).to_string().into_bytes())
+    }
+}
+
+/// Add one JSONEachRow line of `table` to `export`
+fn push_row(export: &mut Export, table: ClickHouseTable, line: &str) -> Result<()> {
+    match table {
+        ClickHouseTable::Logs => {
+            let row: LogRow = serde_json::from_str(line)?;
+            let mut record = json!({
+                "timeUnixNano": row.time_unix_nano,
+                "severityNumber": row.severity_number,
+                "severityText": row.severity_text,
+                "body": { "stringValue": row.body },
+                "attributes": attributes(&row.log_attributes),
+                "flags": row.trace_flags,
+            });
+            insert_id(&mut record, "traceId", row.trace_id);
+            insert_id(&mut record, "spanId", row.span_id);
+            export.push(
+                &row.service_name,
+                &row.resource_attributes,
+                (row.scope_name, row.scope_version),
+                record,
+            );
+        }
+        ClickHouseTable::Traces => {
+            let row: SpanRow = serde_json::from_str(line)?;
+            let start: u64 = row.time_unix_nano.parse()?;
+            let duration: u64 = row.duration_nanos.parse()?;
+            let events: Vec<Value> = row
+                .event_times
+                .iter()
+                .zip(&row.event_names)
+                .zip(&row.event_attributes)
+                .map(|((time, name), attrs)| {
+                    json!({ "timeUnixNano": time, "name": name, "attributes": attributes(attrs) })
+                })
+                .collect();
+            let links: Vec<Value> = row
+                .link_trace_ids
+                .iter()
+                .zip(&row.link_span_ids)
+                .zip(&row.link_trace_states)
+                .zip(&row.link_attributes)
+                .map(|(((trace_id, span_id), state), attrs)| {
+                    json!({
+                        "traceId": trace_id,
+                        "spanId": span_id,
+                        "traceState": state,
+                        "attributes": attributes(attrs),
+                    })
+                })
+                .collect();
+            let mut record = json!({
+                "traceId": row.trace_id,
+                "spanId": row.span_id,
+                "traceState": row.trace_state,
+                "name": row.span_name,
+                "kind": span_kind(&row.span_kind),
+                "startTimeUnixNano": row.time_unix_nano,
+                "endTimeUnixNano": start.saturating_add(duration).to_string(),
+                "attributes": attributes(&row.span_attributes),
+                "status": { "code": status_code(&row.status_code), "message": row.status_message },
+                "events": events,
+                "links": links,
+            });
+            insert_id(&mut record, "parentSpanId", row.parent_span_id);
+            export.push(
+                &row.service_name,
+                &row.resource_attributes,
+                (row.scope_name, row.scope_version),
+                record,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Set a hex id field unless it is empty
+fn insert_id(record: &mut Value, key: &str, id: String) {
+    if let (Some(record), false) = (record.as_object_mut(), id.is_empty()) {
+        record.insert(key.to_string(), Value::String(id));
+    }
+}
+
+/// OTLP/JSON string attributes
+fn attributes(map: &BTreeMap<String, String>) -> Vec<Value> {
+    map.iter()
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect()
+}
+
+/// `SpanKind` enum value for the exporter's kind name (`Server`, or
+/// `SPAN_KIND_SERVER` from older exporter versions)
+fn span_kind(name: &str) -> i32 {
+    match name
+        .trim_start_matches("SPAN_KIND_")
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "internal" => 1,
+        "server" => 2,
+        "client" => 3,
+        "producer" => 4,
+        "consumer" => 5,
+        _ => 0,
+    }
+}
+
+/// `StatusCode` enum value for the exporter's status name (`Ok`, or
+/// `STATUS_CODE_OK` from older exporter versions)
+fn status_code(name: &str) -> i32 {
+    match name
+        .trim_start_matches("STATUS_CODE_")
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "ok" => 1,
+        "error" => 2,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::AsArray;
+
+    #[test]
+    fn test_rows_become_otlp_requests() {
+        let span = r#"{"TimeUnixNano":"1736938800000000000","TraceId":"5b8efff798038103d269b633813fc60c","SpanId":"eee19b7ec3c1b174","ParentSpanId":"","TraceState":"","SpanName":"GET /cart","SpanKind":"Server","ServiceName":"cart","ResourceAttributes":{"service.name":"cart","host.name":"a"},"ScopeName":"http","ScopeVersion":"1.0","SpanAttributes":{"http.method":"GET"},"DurationNanos":"1500000","StatusCode":"STATUS_CODE_ERROR","StatusMessage":"boom","EventTimes":["1736938800000500000"],"EventNames":["exception"],"EventAttributes":[{"exception.type":"Timeout"}],"LinkTraceIds":[],"LinkSpanIds":[],"LinkTraceStates":[],"LinkAttributes":[]}"#;
+        let mut export = Export::default();
+        push_row(&mut export, ClickHouseTable::Traces, span).unwrap();
+        push_row(&mut export, ClickHouseTable::Traces, span).unwrap();
+        let (records, body) = export.take(ClickHouseTable::Traces);
+        assert_eq!(records, 2);
+        assert_eq!(export.records, 0);
+
+        let grouped = crate::codec::decode_traces_partitioned(&body, InputFormat::Json).unwrap();
+        assert_eq!(grouped.batches.len(), 1);
+        let batch = &grouped.batches[0].batch;
+        assert_eq!(batch.num_rows(), 2);
+        let column = |name: &str| batch.column_by_name(name).unwrap().clone();
+        assert_eq!(column("service_name").as_string::<i32>().value(0), "cart");
+        assert_eq!(column("span_name").as_string::<i32>().value(0), "GET /cart");
+
+        let log = r#"{"TimeUnixNano":"1736938800000000000","TraceId":"","SpanId":"","TraceFlags":0,"SeverityText":"INFO","SeverityNumber":9,"ServiceName":"cart","Body":"checkout done","ResourceAttributes":{},"ScopeName":"","ScopeVersion":"","LogAttributes":{"user":"42"}}"#;
+        let mut export = Export::default();
+        push_row(&mut export, ClickHouseTable::Logs, log).unwrap();
+        let (_, body) = export.take(ClickHouseTable::Logs);
+        let grouped = crate::codec::decode_logs_partitioned(&body, InputFormat::Json).unwrap();
+        assert_eq!(grouped.batches[0].service_name.as_ref(), "cart");
+    }
+}
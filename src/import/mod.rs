@@ -0,0 +1,102 @@
+//! Import command - backfills existing telemetry archives into the lake
+//!
+//! Each importer turns its source into OTLP export requests and pushes them
+//! through the same pipeline as the HTTP endpoints, so batching, enrichment
+//! and outputs apply as configured. Progress goes to a checkpoint file, saved
+//! only after buffered batches are flushed, so an interrupted import is rerun
+//! with the same command and continues where it stopped.
+
+mod clickhouse;
+mod otelcol;
+
+pub use clickhouse::{ClickHouseArgs, ClickHouseTable};
+pub use otelcol::{FileFormat, ImportSignal, OtelcolFileArgs};
+
+use crate::config::RuntimeConfig;
+use crate::{AppState, InputFormat, SignalType};
+use anyhow::{Context, Result};
+use axum::http::HeaderMap;
+use clap::Subcommand;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Subcommand)]
+pub enum ImportCommand {
+    /// Import the output of the OpenTelemetry Collector file exporter
+    OtelcolFile(OtelcolFileArgs),
+    /// Import the otel_logs/otel_traces tables of the ClickHouse exporter
+    Clickhouse(ClickHouseArgs),
+}
+
+impl ImportCommand {
+    pub async fn run(&self, config: RuntimeConfig) -> Result<()> {
+        match self {
+            ImportCommand::OtelcolFile(args) => args.run(config).await,
+            ImportCommand::Clickhouse(args) => args.run(config).await,
+        }
+    }
+}
+
+/// Storage, outputs and pipeline state for an import. Archives are not
+/// mirrored: the mirror target is fed by live traffic only.
+async fn start(config: &RuntimeConfig) -> Result<AppState> {
+    crate::init_tracing(config);
+    config.validate()?;
+    crate::init_outputs(config).await?;
+    let mut config = config.clone();
+    config.mirror.endpoint = None;
+    crate::build_state(&config).await
+}
+
+/// Wait for the outputs fed by the last flush
+async fn finish(config: &RuntimeConfig) {
+    crate::usage::flush().await;
+    crate::clickhouse::drain(Duration::from_secs(config.clickhouse.timeout_secs)).await;
+    crate::notify::drain(Duration::from_secs(config.notifications.timeout_secs)).await;
+    crate::writer::drain_replication(Duration::from_secs(30)).await;
+}
+
+/// Push one export request through the pipeline. Requests the pipeline
+/// rejects as invalid come back as `Err`; anything else aborts the import.
+async fn ingest(
+    state: &AppState,
+    signal: SignalType,
+    format: InputFormat,
+    body: Vec<u8>,
+) -> Result<Result<(), String>> {
+    let headers = HeaderMap::new();
+    let body = axum::body::Bytes::from(body);
+    let result = match signal {
+        SignalType::Logs => crate::handlers::process_logs(state, format, body, &headers).await,
+        SignalType::Traces => crate::handlers::process_traces(state, format, body, &headers).await,
+        SignalType::Metrics => {
+            crate::handlers::process_metrics(state, format, body, &headers).await
+        }
+    };
+    match result {
+        Ok(_) => Ok(Ok(())),
+        Err(e) if e.status.is_client_error() => Ok(Err(e.into_error().to_string())),
+        Err(e) => Err(e.into_error()),
+    }
+}
+
+/// The checkpoint at `path`, or an empty one if there is none yet
+fn load_checkpoint<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    match std::fs::read(path) {
+        Ok(data) => serde_json::from_slice(&data)
+            .with_context(|| format!("Invalid checkpoint {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Write through a temporary file so an interrupted save keeps the old one
+fn save_checkpoint<T: Serialize>(path: &Path, checkpoint: &T) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(checkpoint)?)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
@@ -0,0 +1,429 @@
+//! `import otelcol-file <dir>` reads what the OpenTelemetry Collector's
+//! `file` exporter wrote: JSON lines (one export request per line) or
+//! length-prefixed protobuf, the current file plus its rotated backups
+//! (`name-2006-01-02T15-04-05.000.ext`, optionally gzip compressed), oldest
+//! first.
+//!
+//! Progress is kept in a checkpoint file, keyed by a hash of each file's first
+//! record rather than its name, so a file renamed or compressed by rotation
+//! after a partial import resumes where it stopped instead of starting over.
+
+use crate::codec::detect_signal;
+use crate::config::RuntimeConfig;
+use crate::{AppState, InputFormat, SignalType};
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use flate2::read::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Default checkpoint file name inside the imported directory
+const CHECKPOINT_FILE: &str = ".otlp2parquet-import.json";
+
+/// Records between checkpoint saves within a file
+const CHECKPOINT_EVERY: u64 = 10_000;
+
+/// Largest length-prefixed protobuf message accepted
+const MAX_MESSAGE_BYTES: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FileFormat {
+    /// One OTLP/JSON export request per line
+    Json,
+    /// Export requests as protobuf, each prefixed by its 4-byte big-endian length
+    Proto,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportSignal {
+    Logs,
+    Traces,
+    Metrics,
+}
+
+impl From<ImportSignal> for SignalType {
+    fn from(signal: ImportSignal) -> Self {
+        match signal {
+            ImportSignal::Logs => SignalType::Logs,
+            ImportSignal::Traces => SignalType::Traces,
+            ImportSignal::Metrics => SignalType::Metrics,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct OtelcolFileArgs {
+    /// Directory the file exporter writes to (or a single file)
+    pub path: PathBuf,
+
+    /// Checkpoint file [default: <dir>/.otlp2parquet-import.json]
+    #[arg(long, value_name = "FILE")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Exporter format
+    #[arg(long, value_enum, default_value_t = FileFormat::Json)]
+    pub format: FileFormat,
+
+    /// Signal of the files (required for proto, detected per line for JSON)
+    #[arg(long, value_enum)]
+    pub signal: Option<ImportSignal>,
+}
+
+/// Progress through one file, found again by its first record
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct FileProgress {
+    /// Name the file had when last imported
+    file: String,
+    /// Records already imported
+    records: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Checkpoint {
+    files: BTreeMap<String, FileProgress>,
+}
+
+#[derive(Debug, Default)]
+struct Summary {
+    files: usize,
+    imported: u64,
+    rejected: u64,
+}
+
+impl OtelcolFileArgs {
+    pub async fn run(&self, config: RuntimeConfig) -> Result<()> {
+        if self.format == FileFormat::Proto && self.signal.is_none() {
+            bail!("--signal is required with --format proto");
+        }
+        let files = rotation_order(&self.path)?;
+        if files.is_empty() {
+            bail!("No exporter files in {}", self.path.display());
+        }
+        let checkpoint_path = self.checkpoint.clone().unwrap_or_else(|| {
+            let dir = if self.path.is_dir() {
+                self.path.as_path()
+            } else {
+                self.path.parent().unwrap_or(Path::new("."))
+            };
+            dir.join(CHECKPOINT_FILE)
+        });
+        let mut checkpoint: Checkpoint = super::load_checkpoint(&checkpoint_path)?;
+
+        let state = super::start(&config).await?;
+
+        let mut summary = Summary::default();
+        for path in &files {
+            self.import_file(
+                &state,
+                path,
+                &mut checkpoint,
+                &checkpoint_path,
+                &mut summary,
+            )
+            .await?;
+        }
+
+        super::finish(&config).await;
+
+        println!(
+            "Imported {} records from {} files ({} rejected)",
+            summary.imported, summary.files, summary.rejected
+        );
+        Ok(())
+    }
+
+    async fn import_file(
+        &self,
+        state: &AppState,
+        path: &Path,
+        checkpoint: &mut Checkpoint,
+        checkpoint_path: &Path,
+        summary: &mut Summary,
+    ) -> Result<()> {
+        let Some(fingerprint) = fingerprint(path, self.format)? else {
+            return Ok(());
+        };
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let done = checkpoint
+            .files
+            .get(&fingerprint)
+            .map_or(0, |progress| progress.records);
+
+        let mut reader = RecordReader::open(path, self.format)?;
+        let mut position = 0u64;
+        let mut imported = 0u64;
+        while let Some(record) = reader
+            .next_record()
+            .with_context(|| format!("Failed to read {}", path.display()))?
+        {
+            position += 1;
+            if position <= done {
+                continue;
+            }
+            match self.ingest(state, record).await? {
+                Ok(()) => {
+                    imported += 1;
+                    summary.imported += 1;
+                }
+                Err(reason) => {
+                    summary.rejected += 1;
+                    tracing::warn!(file = %name, record = position, "Skipping rejected record: {}", reason);
+                }
+            }
+            if position % CHECKPOINT_EVERY == 0 {
+                save_progress(
+                    state,
+                    checkpoint,
+                    checkpoint_path,
+                    &fingerprint,
+                    &name,
+                    position,
+                )
+                .await?;
+            }
+        }
+
+        if position > done {
+            save_progress(
+                state,
+                checkpoint,
+                checkpoint_path,
+                &fingerprint,
+                &name,
+                position,
+            )
+            .await?;
+            summary.files += 1;
+            tracing::info!("Imported {} records from {}", imported, name);
+        } else {
+            tracing::debug!("{} already imported", name);
+        }
+        Ok(())
+    }
+
+    /// Push one export request through the pipeline. Requests the pipeline
+    /// rejects as invalid come back as `Err`; anything else aborts the import.
+    async fn ingest(&self, state: &AppState, record: Vec<u8>) -> Result<Result<(), String>> {
+        let (signal, format) = match self.format {
+            FileFormat::Proto => (self.signal.map(SignalType::from), InputFormat::Protobuf),
+            FileFormat::Json => (
+                detect_signal(&record).or(self.signal.map(SignalType::from)),
+                InputFormat::Json,
+            ),
+        };
+        let Some(signal) = signal else {
+            return Ok(Err("not an OTLP export request".to_string()));
+        };
+        super::ingest(state, signal, format, record).await
+    }
+}
+
+/// Flush buffered batches, then record `records` of the file as imported.
+/// Saving first could mark records done that never reached storage.
+async fn save_progress(
+    state: &AppState,
+    checkpoint: &mut Checkpoint,
+    checkpoint_path: &Path,
+    fingerprint: &str,
+    name: &str,
+    records: u64,
+) -> Result<()> {
+    crate::flush_pending_batches(state).await?;
+    checkpoint.files.insert(
+        fingerprint.to_string(),
+        FileProgress {
+            file: name.to_string(),
+            records,
+        },
+    );
+    super::save_checkpoint(checkpoint_path, checkpoint)
+}
+
+/// Exporter files in import order: per base name, rotated backups oldest
+/// first, then the file still being written.
+fn rotation_order(path: &Path) -> Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let entries =
+        std::fs::read_dir(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || !entry.file_type()?.is_file() {
+            continue;
+        }
+        let (base, timestamp) = rotation_key(&name);
+        // The current file (no timestamp) sorts after its backups
+        files.push((base, timestamp.is_none(), timestamp, entry.path()));
+    }
+    files.sort();
+    Ok(files.into_iter().map(|(_, _, _, path)| path).collect())
+}
+
+/// The current file's name for a file name, with the backup timestamp
+/// (`None` for the current file itself).
+///
+/// `app-2024-01-15T10-30-00.000.json.gz` -> (`app.json`, Some(timestamp))
+fn rotation_key(name: &str) -> (String, Option<String>) {
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    // Backups keep the extension after the timestamp; a file without an
+    // extension has the timestamp at the very end
+    let mut candidates = vec![(name, "")];
+    if let Some(dot) = name.rfind('.') {
+        candidates.insert(0, name.split_at(dot));
+    }
+    for (stem, ext) in candidates {
+        let Some(split) = stem.len().checked_sub(24) else {
+            continue;
+        };
+        if !stem.is_char_boundary(split) {
+            continue;
+        }
+        let (base, timestamp) = stem.split_at(split);
+        if let Some(timestamp) = timestamp.strip_prefix('-') {
+            if is_backup_timestamp(timestamp) {
+                return (format!("{}{}", base, ext), Some(timestamp.to_string()));
+            }
+        }
+    }
+    (name.to_string(), None)
+}
+
+/// Whether `s` is a lumberjack backup timestamp, `2006-01-02T15-04-05.000`
+fn is_backup_timestamp(s: &str) -> bool {
+    s.len() == 23
+        && s.bytes().enumerate().all(|(i, b)| match i {
+            4 | 7 | 13 | 16 => b == b'-',
+            10 => b == b'T',
+            19 => b == b'.',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+/// blake3 of the file's first record, or `None` for an empty file
+fn fingerprint(path: &Path, format: FileFormat) -> Result<Option<String>> {
+    let mut reader = RecordReader::open(path, format)?;
+    let first = reader
+        .next_record()
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(first.map(|record| blake3::hash(&record).to_hex().to_string()))
+}
+
+/// Export requests of one file, decompressing gzip backups
+struct RecordReader {
+    reader: BufReader<Box<dyn Read>>,
+    format: FileFormat,
+}
+
+impl RecordReader {
+    fn open(path: &Path, format: FileFormat) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let inner: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+            Box::new(MultiGzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        Ok(Self {
+            reader: BufReader::new(inner),
+            format,
+        })
+    }
+
+    fn next_record(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        match self.format {
+            FileFormat::Json => loop {
+                let mut line = Vec::new();
+                if self.reader.read_until(b'\n', &mut line)? == 0 {
+                    return Ok(None);
+                }
+                let trimmed = line.trim_ascii();
+                if !trimmed.is_empty() {
+                    return Ok(Some(trimmed.to_vec()));
+                }
+            },
+            FileFormat::Proto => {
+                let mut len = [0u8; 4];
+                match self.reader.read_exact(&mut len) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(e) => return Err(e),
+                }
+                let len = u32::from_be_bytes(len) as usize;
+                if len > MAX_MESSAGE_BYTES {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("message of {} bytes exceeds the limit", len),
+                    ));
+                }
+                let mut message = vec![0u8; len];
+                self.reader.read_exact(&mut message)?;
+                Ok(Some(message))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    #[test]
+    fn test_rotation_order_and_fingerprints() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = b"{\"resourceLogs\":[]}\n\n{\"resourceLogs\":[{}]}\n";
+        std::fs::write(dir.path().join("otel.json"), b"{\"resourceSpans\":[]}\n").unwrap();
+        std::fs::write(dir.path().join("otel-2024-03-02T08-00-00.000.json"), b"x\n").unwrap();
+        let gz = File::create(dir.path().join("otel-2024-01-15T10-30-00.000.json.gz")).unwrap();
+        let mut encoder = GzEncoder::new(gz, flate2::Compression::default());
+        encoder.write_all(logs).unwrap();
+        encoder.finish().unwrap();
+        std::fs::write(dir.path().join(CHECKPOINT_FILE), b"{}").unwrap();
+
+        let names: Vec<String> = rotation_order(dir.path())
+            .unwrap()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "otel-2024-01-15T10-30-00.000.json.gz",
+                "otel-2024-03-02T08-00-00.000.json",
+                "otel.json"
+            ]
+        );
+
+        // A backup compressed after rotation is still recognised
+        let plain = dir.path().join("plain.json");
+        std::fs::write(&plain, logs).unwrap();
+        let compressed = dir.path().join(&names[0]);
+        assert_eq!(
+            fingerprint(&plain, FileFormat::Json).unwrap(),
+            fingerprint(&compressed, FileFormat::Json).unwrap()
+        );
+        let mut reader = RecordReader::open(&compressed, FileFormat::Json).unwrap();
+        let first = reader.next_record().unwrap().unwrap();
+        assert_eq!(detect_signal(&first), Some(SignalType::Logs));
+        assert!(reader.next_record().unwrap().is_some());
+        assert!(reader.next_record().unwrap().is_none());
+
+        let mut proto = 3u32.to_be_bytes().to_vec();
+        proto.extend_from_slice(b"abc");
+        let path = dir.path().join("otel.pb");
+        std::fs::write(&path, &proto).unwrap();
+        let mut reader = RecordReader::open(&path, FileFormat::Proto).unwrap();
+        assert_eq!(reader.next_record().unwrap().unwrap(), b"abc");
+        assert!(reader.next_record().unwrap().is_none());
+    }
+}
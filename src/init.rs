@@ -41,6 +41,56 @@ pub(crate) fn init_writer(config: &RuntimeConfig) -> Result<()> {
                 info!("Using R2 storage");
             }
         }
+        StorageBackend::Sftp => {
+            if let Some(sftp) = config.storage.sftp.as_ref() {
+                info!(
+                    "Using SFTP storage: endpoint={}, root={}",
+                    sftp.endpoint, sftp.root
+                );
+            } else {
+                info!("Using SFTP storage");
+            }
+        }
+        StorageBackend::Webdav => {
+            if let Some(webdav) = config.storage.webdav.as_ref() {
+                info!(
+                    "Using WebDAV storage: endpoint={}, root={}",
+                    webdav.endpoint, webdav.root
+                );
+            } else {
+                info!("Using WebDAV storage");
+            }
+        }
+        StorageBackend::Hdfs => {
+            if let Some(hdfs) = config.storage.hdfs.as_ref() {
+                info!(
+                    "Using HDFS storage: endpoint={}, root={}",
+                    hdfs.endpoint, hdfs.root
+                );
+            } else {
+                info!("Using HDFS storage");
+            }
+        }
+        StorageBackend::Gcs => {
+            if let Some(gcs) = config.storage.gcs.as_ref() {
+                info!("Using GCS storage: bucket={}", gcs.bucket);
+            } else {
+                info!("Using GCS storage");
+            }
+        }
+        StorageBackend::Azblob => {
+            if let Some(azblob) = config.storage.azblob.as_ref() {
+                info!(
+                    "Using Azure Blob storage: account={}, container={}",
+                    azblob.account_name, azblob.container
+                );
+            } else {
+                info!("Using Azure Blob storage");
+            }
+        }
+        StorageBackend::Null => {
+            info!("Dry run: Parquet files are encoded but not stored");
+        }
     }
     // Initialize storage for direct writes
     crate::writer::initialize_storage(config)
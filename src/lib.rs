@@ -25,29 +25,76 @@ pub mod config;
 pub mod types;
 
 pub use config::{
-    BatchConfig, EnvSource, FsConfig, LogFormat, Platform, RequestConfig, RuntimeConfig,
-    ServerConfig, StorageBackend, StorageConfig, ENV_PREFIX,
+    BatchConfig, CaptureConfig, CatalogConfig, ClickHouseConfig, ClusterConfig, DeriveConfig,
+    EnrichmentConfig, EnvSource, EventsConfig, FallbackConfig, FileNaming, FsConfig, GeoIpConfig,
+    KubernetesEnrichmentConfig, LogFormat, LogMetricRule, MirrorConfig, MirrorMode,
+    NotificationsConfig, ParquetCompression, Platform, ProbeConfig, ReplicationConfig,
+    RequestConfig, RouteConfig, RuntimeConfig, SchemaConfig, SeriesOverflow, ServerConfig,
+    ServiceGraphConfig, SpanMetricsConfig, StorageBackend, StorageConfig, TieringConfig,
+    TimestampUnit, UnattributedPolicy, UsageConfig, ENV_PREFIX,
 };
 pub use otlp2records::InputFormat;
 pub use types::{Blake3Hash, MetricType, SignalKey, SignalType};
+pub use writer::manifest::ManifestEntry;
+pub use writer::{register_writer_hook, BatchEvent, FileEvent, WriterHook};
 
+mod auth;
 mod batch;
+mod capture;
+mod cardinality;
+mod clickhouse;
+mod clock;
+mod cluster;
 pub mod codec;
+mod derive;
+mod enrich;
+mod events;
 
-use batch::{BatchConfig as BatcherConfig, BatchManager};
+use batch::{BatchConfig as BatcherConfig, BatchManager, BufferStats, TableBatcher};
+use cardinality::CardinalityGuard;
 use serde_json::json;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::signal;
+use tower_http::compression::CompressionLayer;
 use tower_http::decompression::RequestDecompressionLayer;
 use tracing::{debug, error, info, warn};
 
+#[cfg(feature = "grafana")]
+mod grafana;
+#[cfg(feature = "grpc")]
+mod grpc;
 mod handlers;
 mod init;
+mod notify;
+mod otlp_response;
+mod partitions;
+mod probe;
+mod request_id;
+mod request_metrics;
+mod retry_queue;
+#[cfg(feature = "ui")]
+mod ui;
+mod unattributed;
+mod usage;
+mod validate;
 mod writer;
 
+#[cfg(feature = "cat")]
+pub mod cat;
 pub mod connect;
+pub mod create;
+pub mod doctor;
+pub mod import;
+pub mod mirror;
+pub mod service;
+pub mod stats;
+
+#[cfg(feature = "catalog")]
+pub mod catalog;
+#[cfg(feature = "duckdb-verify")]
+pub mod verify;
 
 use handlers::{handle_logs, handle_metrics, handle_traces, health_check, ready_check};
 pub use init::init_tracing;
@@ -62,13 +109,155 @@ pub(crate) struct MetricsBatchers {
     pub exp_histogram: Arc<BatchManager>,
 }
 
+/// Writers for records set aside by the `request.unattributed` policy
+#[derive(Clone)]
+pub(crate) struct UnattributedTables {
+    pub logs: Arc<TableBatcher>,
+    pub traces: Arc<TableBatcher>,
+}
+
+/// Per-signal request body limits in bytes
+#[derive(Clone, Copy)]
+pub(crate) struct PayloadLimits {
+    pub logs: usize,
+    pub traces: usize,
+    pub metrics: usize,
+}
+
+impl PayloadLimits {
+    fn from_config(config: &RequestConfig) -> Self {
+        Self {
+            logs: config.max_payload_bytes_for(SignalType::Logs),
+            traces: config.max_payload_bytes_for(SignalType::Traces),
+            metrics: config.max_payload_bytes_for(SignalType::Metrics),
+        }
+    }
+
+    pub fn for_signal(&self, signal: SignalType) -> usize {
+        match signal {
+            SignalType::Logs => self.logs,
+            SignalType::Traces => self.traces,
+            SignalType::Metrics => self.metrics,
+        }
+    }
+}
+
 /// Application state shared across all requests
 #[derive(Clone)]
 pub(crate) struct AppState {
     pub batcher: Option<Arc<BatchManager>>,
     pub traces_batcher: Option<Arc<BatchManager>>,
     pub metrics_batchers: Option<MetricsBatchers>,
-    pub max_payload_bytes: usize,
+    pub max_payload_bytes: PayloadLimits,
+    /// Caps distinct series per metric name; `None` when disabled
+    pub cardinality: Option<Arc<CardinalityGuard>>,
+    /// Metrics derived from ingested logs/spans; `None` when nothing is configured
+    pub derived: Option<Arc<derive::Derived>>,
+    /// Splits RUM/event records out of logs; `None` when disabled
+    pub events: Option<Arc<events::EventExtractor>>,
+    /// Writes the split-out event records; `None` when events are disabled
+    pub events_table: Option<Arc<TableBatcher>>,
+    /// Fleet metadata stamped onto resource attributes; `None` when unset
+    pub resource_attributes: Option<Arc<enrich::StaticAttributes>>,
+    /// Pod metadata lookup for resource attributes; `None` when disabled
+    #[cfg(feature = "k8s-enrichment")]
+    pub k8s: Option<Arc<enrich::KubernetesEnricher>>,
+    /// Client address geolocation; `None` when disabled
+    #[cfg(feature = "geoip")]
+    pub geoip: Option<Arc<enrich::GeoIpEnricher>>,
+    /// Baggage keys captured into columns; `None` when none are configured
+    pub baggage: Option<Arc<enrich::BaggageColumns>>,
+    /// Ingest time, host and version columns; `None` when disabled
+    pub provenance: Option<Arc<enrich::Provenance>>,
+    /// Shed load while rolling p95 storage write latency exceeds this
+    pub shed_write_p95: Option<Duration>,
+    /// Retry failed JSON decodes with unknown enum names tolerated
+    pub lenient_parsing: bool,
+    /// Refuse payloads that would be stored lossily
+    pub strict_schema: bool,
+    /// Derive and backfill log severity numbers and texts
+    pub normalize_severity: bool,
+    /// Handling of records without a service; `None` keeps them as `unknown`
+    pub unattributed: Option<Arc<unattributed::Unattributed>>,
+    /// `_unattributed/{table}` writers; `None` unless the policy diverts records there
+    pub unattributed_tables: Option<UnattributedTables>,
+    /// Bodies of recently failed requests; `None` when capture is disabled
+    pub captures: Option<Arc<capture::Captures>>,
+    /// Forwards batches owned by other replicas; `None` without peers
+    pub cluster: Option<Arc<cluster::Cluster>>,
+    /// Sends accepted requests on to another OTLP endpoint; `None` when unset
+    pub mirror: Option<Arc<mirror::Mirror>>,
+    /// Latency and size histograms per route and (bounded) service
+    pub request_metrics: Arc<request_metrics::RequestMetrics>,
+}
+
+impl AppState {
+    /// Buffer depth and age per table, for the tables that are batched
+    pub(crate) fn buffer_stats(&self) -> Vec<(&'static str, BufferStats)> {
+        let mut tables = Vec::new();
+        if let Some(ref batcher) = self.batcher {
+            tables.push(("logs", batcher.stats()));
+        }
+        if let Some(ref batcher) = self.traces_batcher {
+            tables.push(("traces", batcher.stats()));
+        }
+        if let Some(ref mb) = self.metrics_batchers {
+            tables.extend([
+                ("metrics/gauge", mb.gauge.stats()),
+                ("metrics/sum", mb.sum.stats()),
+                ("metrics/histogram", mb.histogram.stats()),
+                ("metrics/exponential_histogram", mb.exp_histogram.stats()),
+            ]);
+        }
+        for table in self.side_tables() {
+            tables.extend(table.stats().map(|stats| (table.table(), stats)));
+        }
+        tables
+    }
+
+    /// Writers of tables beside the signal tables (events, `_unattributed/*`)
+    fn side_tables(&self) -> impl Iterator<Item = &Arc<TableBatcher>> {
+        self.events_table.iter().chain(
+            self.unattributed_tables
+                .iter()
+                .flat_map(|tables| [&tables.logs, &tables.traces]),
+        )
+    }
+}
+
+#[cfg(feature = "grafana")]
+impl AppState {
+    /// Rows of a signal table still buffered for writing, in the stored
+    /// schema, so queries see data before its file lands.
+    pub(crate) fn pending_batches(
+        &self,
+        signal: SignalType,
+        metric_type: Option<&str>,
+    ) -> Vec<arrow::array::RecordBatch> {
+        let batcher = match (signal, metric_type, &self.metrics_batchers) {
+            (SignalType::Logs, _, _) => self.batcher.as_ref(),
+            (SignalType::Traces, _, _) => self.traces_batcher.as_ref(),
+            (SignalType::Metrics, Some("gauge"), Some(m)) => Some(&m.gauge),
+            (SignalType::Metrics, Some("sum"), Some(m)) => Some(&m.sum),
+            (SignalType::Metrics, Some("histogram"), Some(m)) => Some(&m.histogram),
+            (SignalType::Metrics, Some("exponential_histogram"), Some(m)) => Some(&m.exp_histogram),
+            _ => None,
+        };
+        let Some(batcher) = batcher else {
+            return Vec::new();
+        };
+        batcher
+            .pending()
+            .iter()
+            .filter_map(|batch| match writer::output_batch(batch, signal) {
+                Ok(batch) => Some(batch.into_owned()),
+                Err(e) => {
+                    debug!(error = %e, "Skipping buffered batch in query");
+                    None
+                }
+            })
+            .collect()
+    }
 }
 
 /// Error type that implements IntoResponse
@@ -80,13 +269,18 @@ pub(crate) struct AppError {
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         error!("Request error: {:?}", self.error);
-        (
+        let message = self.error.to_string();
+        let mut response = (
             self.status,
             Json(json!({
-                "error": self.error.to_string(),
+                "error": message,
+                "code": otlp_response::grpc_code(self.status),
+                "message": message,
             })),
         )
-            .into_response()
+            .into_response();
+        otlp_response::set_retry_after(&mut response);
+        response
     }
 }
 
@@ -95,6 +289,11 @@ impl AppError {
         Self { status, error }
     }
 
+    /// Unwrap the underlying error for callers outside the HTTP layer.
+    pub fn into_error(self) -> anyhow::Error {
+        self.error
+    }
+
     pub fn bad_request<E>(error: E) -> Self
     where
         E: Into<anyhow::Error>,
@@ -114,6 +313,18 @@ impl AppError {
             error: error.into(),
         }
     }
+
+    /// A transient failure (storage unreachable or slow): 503, which OTLP
+    /// clients retry. Use `internal` for failures a retry cannot fix.
+    pub fn unavailable<E>(error: E) -> Self
+    where
+        E: Into<anyhow::Error>,
+    {
+        Self {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            error: error.into(),
+        }
+    }
 }
 
 /// Graceful shutdown handler
@@ -157,9 +368,23 @@ pub async fn run() -> Result<()> {
 
 /// Entry point for server mode with pre-loaded configuration (for CLI usage)
 pub async fn run_with_config(config: RuntimeConfig) -> Result<()> {
+    run_with_shutdown(config, shutdown_signal()).await
+}
+
+/// Run the server until `shutdown` resolves, then flush buffered batches.
+///
+/// Used by service hosts (e.g. the Windows service control handler) that
+/// deliver stop requests through their own channel instead of OS signals.
+pub async fn run_with_shutdown<F>(config: RuntimeConfig, shutdown: F) -> Result<()>
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
     // Initialize tracing with config
     init_tracing(&config);
 
+    // Re-check the final config: hosts and CLI flags may change it after loading
+    config.validate()?;
+
     // Configure Parquet writer properties before first use
 
     info!("Server mode - full-featured HTTP server with multi-backend storage");
@@ -172,59 +397,57 @@ pub async fn run_with_config(config: RuntimeConfig) -> Result<()> {
         .listen_addr
         .clone();
 
-    // Initialize storage
-    init_writer(&config)?;
+    init_outputs(&config).await?;
+    #[cfg(feature = "ui")]
+    ui::init();
+    writer::start_disk_quota(&config.storage);
+    writer::start_tiering(&config.tiering)?;
 
-    // Configure batching
-    let batch_config = BatcherConfig {
-        max_rows: config.batch.max_rows,
-        max_bytes: config.batch.max_bytes,
-        max_age: Duration::from_secs(config.batch.max_age_secs),
-    };
-
-    let (batcher, traces_batcher, metrics_batchers) = if !config.batch.enabled {
-        info!("Batching disabled by configuration");
-        (None, None, None)
-    } else {
-        info!(
-            "Batching enabled (max_rows={} max_bytes={} max_age={}s)",
-            batch_config.max_rows,
-            batch_config.max_bytes,
-            batch_config.max_age.as_secs()
-        );
-        let logs = Some(Arc::new(BatchManager::new(batch_config.clone())));
-        let traces = Some(Arc::new(BatchManager::new(batch_config.clone())));
-        let metrics = Some(MetricsBatchers {
-            gauge: Arc::new(BatchManager::new(batch_config.clone())),
-            sum: Arc::new(BatchManager::new(batch_config.clone())),
-            histogram: Arc::new(BatchManager::new(batch_config.clone())),
-            exp_histogram: Arc::new(BatchManager::new(batch_config)),
-        });
-        (logs, traces, metrics)
-    };
-
-    let max_payload_bytes = config.request.max_payload_bytes;
-    info!("Max payload size set to {} bytes", max_payload_bytes);
-
-    // Create app state
-    let state = AppState {
-        batcher,
-        traces_batcher,
-        metrics_batchers,
-        max_payload_bytes,
-    };
+    let state = build_state(&config).await?;
 
     let router_state = state.clone();
 
-    // Build router with gzip decompression support
-    // OTel collectors typically send gzip-compressed payloads by default
+    // Build router with request decompression (gzip, zstd, deflate)
+    // OTel collectors typically send gzip- or zstd-compressed payloads
     let app = Router::new()
         .route("/v1/logs", post(handle_logs))
         .route("/v1/traces", post(handle_traces))
         .route("/v1/metrics", post(handle_metrics))
+        .route("/v1/validate", post(validate::handle_validate))
+        .route(
+            "/v1/validate/{signal}",
+            post(validate::handle_validate_signal),
+        )
         .route("/health", get(health_check))
-        .route("/ready", get(ready_check))
-        .layer(RequestDecompressionLayer::new().gzip(true))
+        .route("/ready", get(ready_check));
+    let app = if state.cluster.is_some() {
+        app.route("/cluster/batches", post(cluster::handle_forwarded))
+    } else {
+        app
+    };
+    let app = app
+        .route("/admin/buffers", get(handlers::buffer_stats))
+        .route("/partitions", get(partitions::list_partitions));
+    let app = if state.captures.is_some() {
+        app.route("/admin/captures", get(capture::list_captures))
+            .route("/admin/captures/{id}", get(capture::download_capture))
+    } else {
+        app
+    };
+    #[cfg(feature = "grafana")]
+    let app = app
+        .route("/grafana", get(grafana::grafana_health))
+        .route("/grafana/search", post(grafana::grafana_search))
+        .route("/grafana/query", post(grafana::grafana_query));
+    #[cfg(feature = "ui")]
+    let app = app
+        .route("/ui", get(ui::index))
+        .route("/ui/api/activity", get(ui::activity))
+        .route("/ui/api/schemas", get(ui::schemas))
+        .route("/ui/api/query", post(ui::query));
+    let app = app
+        .layer(request_decompression())
+        .layer(CompressionLayer::new())
         .with_state(router_state);
 
     // Create TCP listener
@@ -237,8 +460,51 @@ pub async fn run_with_config(config: RuntimeConfig) -> Result<()> {
     info!("  POST http://{}/v1/logs    - OTLP log ingestion", addr);
     info!("  POST http://{}/v1/metrics - OTLP metrics ingestion", addr);
     info!("  POST http://{}/v1/traces  - OTLP trace ingestion", addr);
+    info!(
+        "  POST http://{}/v1/validate - Dry-run conversion report",
+        addr
+    );
     info!("  GET  http://{}/health     - Health check", addr);
     info!("  GET  http://{}/ready      - Readiness check", addr);
+    #[cfg(feature = "grafana")]
+    info!(
+        "  POST http://{}/grafana/*  - Grafana JSON datasource",
+        addr
+    );
+    #[cfg(feature = "ui")]
+    info!("  GET  http://{}/ui        - Inspect UI", addr);
+    // One shutdown signal for the HTTP listener and the gRPC one
+    let (stop_tx, stop_rx) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        shutdown.await;
+        let _ = stop_tx.send(true);
+    });
+
+    #[cfg(feature = "grpc")]
+    let grpc_handle = match config
+        .server
+        .as_ref()
+        .and_then(|s| s.grpc_listen_addr.clone())
+    {
+        Some(grpc_addr) => {
+            let listener = tokio::net::TcpListener::bind(&grpc_addr)
+                .await
+                .context(format!("Failed to bind to {}", grpc_addr))?;
+            info!("OTLP gRPC endpoint listening on {}", grpc_addr);
+            let app = grpc::router(state.clone());
+            let stop = wait_for_stop(stop_rx.clone());
+            Some(tokio::spawn(async move {
+                if let Err(e) = axum::serve(listener, app)
+                    .with_graceful_shutdown(stop)
+                    .await
+                {
+                    error!(error = %e, "gRPC server error");
+                }
+            }))
+        }
+        None => None,
+    };
+
     info!("Press Ctrl+C or send SIGTERM to stop");
 
     // Spawn background flush task if batching is enabled
@@ -256,50 +522,357 @@ pub async fn run_with_config(config: RuntimeConfig) -> Result<()> {
         None
     };
 
+    // Spawn end-to-end latency probe if enabled
+    let probe_handle = if config.probe.enabled {
+        let probe_state = state.clone();
+        let probe_shutdown = Arc::clone(&shutdown_flag);
+        let probe_config = config.probe.clone();
+        Some(tokio::spawn(async move {
+            probe::run_probe(probe_state, probe_shutdown, probe_config).await;
+        }))
+    } else {
+        None
+    };
+
+    // Spawn derived metrics emitter if any derivation is configured
+    let derive_handle = state.derived.as_ref().map(|derived| {
+        info!(
+            "Deriving {} log metric(s){}{} every {}s",
+            config.derive.log_metrics.len(),
+            if config.derive.span_metrics.enabled {
+                ", span RED metrics"
+            } else {
+                ""
+            },
+            if config.derive.service_graph.enabled {
+                ", service graph"
+            } else {
+                ""
+            },
+            config.derive.interval_secs
+        );
+        let derive_state = state.clone();
+        let derived = Arc::clone(derived);
+        let derive_shutdown = Arc::clone(&shutdown_flag);
+        let interval = Duration::from_secs(config.derive.interval_secs);
+        tokio::spawn(async move {
+            derive::run_derived_metrics(derive_state, derived, derive_shutdown, interval).await;
+        })
+    });
+
+    // Not awaited at shutdown: the final flush below covers what it would write
+    if config.usage.enabled {
+        let usage_shutdown = Arc::clone(&shutdown_flag);
+        let interval = Duration::from_secs(config.usage.flush_interval_secs);
+        tokio::spawn(async move {
+            usage::run_usage_flush(usage_shutdown, interval).await;
+        });
+    }
+
     // Start server with graceful shutdown
     axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
+        .with_graceful_shutdown(wait_for_stop(stop_rx))
         .await
         .context("Server error")?;
+    #[cfg(feature = "grpc")]
+    if let Some(handle) = grpc_handle {
+        let _ = handle.await;
+    }
 
     // Signal background task to stop and wait for it
     shutdown_flag.store(true, Ordering::SeqCst);
+    // A probe may be mid-poll for minutes; it holds nothing that needs flushing
+    if let Some(handle) = probe_handle {
+        handle.abort();
+    }
     if let Some(handle) = flush_handle {
         let _ = handle.await;
     }
+    // The emitter sleeps a whole window between ticks; emit the partial
+    // window here so it reaches the batchers before the final flush
+    if let Some(handle) = derive_handle {
+        handle.abort();
+    }
+    if let Some(ref derived) = state.derived {
+        derived.emit(&state).await;
+    }
 
     flush_pending_batches(&state).await?;
+    usage::flush().await;
+    clickhouse::drain(Duration::from_secs(config.clickhouse.timeout_secs)).await;
+    notify::drain(Duration::from_secs(config.notifications.timeout_secs)).await;
+    writer::drain_replication(Duration::from_secs(30)).await;
+    if let Some(ref mirror) = state.mirror {
+        mirror
+            .drain(Duration::from_secs(config.mirror.timeout_secs))
+            .await;
+    }
 
     info!("Server shutdown complete");
 
     Ok(())
 }
 
-async fn flush_pending_batches(state: &AppState) -> Result<()> {
-    flush_batcher(&state.batcher, SignalType::Logs, None).await?;
-    flush_batcher(&state.traces_batcher, SignalType::Traces, None).await?;
+/// Decompression of request bodies: gzip, zstd and deflate. Any other
+/// `Content-Encoding` is answered with 415 and the supported encodings in
+/// `Accept-Encoding`.
+fn request_decompression() -> RequestDecompressionLayer {
+    RequestDecompressionLayer::new()
+        .gzip(true)
+        .zstd(true)
+        .deflate(true)
+}
+
+/// Resolves once the shutdown signal has been sent
+async fn wait_for_stop(mut stop: tokio::sync::watch::Receiver<bool>) {
+    let _ = stop.wait_for(|stopped| *stopped).await;
+}
+
+/// Storage and the outputs next to it (ClickHouse, notifications,
+/// replication, usage), shared by the server and the importer.
+pub(crate) async fn init_outputs(config: &RuntimeConfig) -> Result<()> {
+    init_writer(config)?;
+    writer::check_timestamp_units(&config.schema).await?;
+    clickhouse::init(&config.clickhouse)?;
+    notify::init(&config.notifications)?;
+    writer::start_replication(&config.replication).await?;
+    usage::init(&config.usage, config.request.metrics_max_services);
+
+    Ok(())
+}
+
+/// Batchers, enrichment and the other per-request pipeline state built from
+/// `config`.
+pub(crate) async fn build_state(config: &RuntimeConfig) -> Result<AppState> {
+    // Configure batching
+    let batch_config = BatcherConfig {
+        max_rows: config.batch.max_rows,
+        max_bytes: config.batch.max_bytes,
+        max_age: Duration::from_secs(config.batch.max_age_secs),
+    };
+
+    let (batcher, traces_batcher, metrics_batchers) = if !config.batch.enabled {
+        info!("Batching disabled by configuration");
+        (None, None, None)
+    } else {
+        info!(
+            "Batching enabled (max_rows={} max_bytes={} max_age={}s)",
+            batch_config.max_rows,
+            batch_config.max_bytes,
+            batch_config.max_age.as_secs()
+        );
+        let logs = Some(Arc::new(BatchManager::new(batch_config.clone())));
+        let traces = Some(Arc::new(BatchManager::new(batch_config.clone())));
+        let metrics = Some(MetricsBatchers {
+            gauge: Arc::new(BatchManager::new(batch_config.clone())),
+            sum: Arc::new(BatchManager::new(batch_config.clone())),
+            histogram: Arc::new(BatchManager::new(batch_config.clone())),
+            exp_histogram: Arc::new(BatchManager::new(batch_config.clone())),
+        });
+        (logs, traces, metrics)
+    };
+
+    // Tables beside the signal tables are batched the same way
+    let table_batch_config = config.batch.enabled.then_some(batch_config);
+    let max_payload_bytes = PayloadLimits::from_config(&config.request);
+    info!(
+        "Max payload size set to {} bytes (logs={} traces={} metrics={})",
+        config.request.max_payload_bytes,
+        max_payload_bytes.logs,
+        max_payload_bytes.traces,
+        max_payload_bytes.metrics
+    );
+    // One flush window: series budgets reset as often as batches are cut
+    let cardinality = config.request.max_series_per_metric.map(|limit| {
+        info!(
+            "Metric cardinality limit: {} series per metric per {}s ({:?} on overflow)",
+            limit, config.batch.max_age_secs, config.request.series_overflow
+        );
+        Arc::new(CardinalityGuard::new(
+            limit,
+            config.request.series_overflow,
+            Duration::from_secs(config.batch.max_age_secs.max(1)),
+        ))
+    });
+    if config.events.enabled {
+        info!(
+            "Routing event records (event.name) to the events table ({} promoted column(s))",
+            config.events.columns.len()
+        );
+    }
+    let resource_attributes =
+        enrich::StaticAttributes::new(&config.enrichment.resource_attributes)?.map(Arc::new);
+    if resource_attributes.is_some() {
+        info!(
+            "Stamping {} static resource attribute(s) onto every record",
+            config.enrichment.resource_attributes.len()
+        );
+    }
+    #[cfg(feature = "k8s-enrichment")]
+    let k8s = if config.enrichment.kubernetes.enabled {
+        info!(
+            "Kubernetes enrichment enabled (scope: {})",
+            config
+                .enrichment
+                .kubernetes
+                .node_name
+                .as_deref()
+                .map(|node| format!("node {}", node))
+                .unwrap_or_else(|| "cluster".to_string())
+        );
+        Some(Arc::new(
+            enrich::KubernetesEnricher::start(&config.enrichment.kubernetes).await?,
+        ))
+    } else {
+        None
+    };
+    #[cfg(feature = "geoip")]
+    let geoip = if config.enrichment.geoip.enabled {
+        info!(
+            "GeoIP enrichment enabled for {} (attributes: {})",
+            config.enrichment.geoip.signals.join(", "),
+            config.enrichment.geoip.source_attributes.join(", ")
+        );
+        Some(Arc::new(enrich::GeoIpEnricher::open(
+            &config.enrichment.geoip,
+        )?))
+    } else {
+        None
+    };
+    let baggage = enrich::BaggageColumns::new(&config.enrichment.baggage_keys).map(Arc::new);
+    if let Some(ref baggage) = baggage {
+        info!(
+            "Capturing baggage into columns: {}",
+            baggage.columns().collect::<Vec<_>>().join(", ")
+        );
+    }
+    let captures = capture::Captures::new(&config.capture)?.map(Arc::new);
+    if captures.is_some() {
+        info!(
+            "Capturing the last {} failed request bodies (admin endpoint: /admin/captures)",
+            config.capture.max_requests
+        );
+    }
+    let cluster = cluster::Cluster::new(&config.cluster)?.map(Arc::new);
+    if let Some(ref cluster) = cluster {
+        info!(
+            "Peer forwarding enabled as {} ({})",
+            config.cluster.self_url.as_deref().unwrap_or_default(),
+            config
+                .cluster
+                .dns
+                .as_deref()
+                .map(|dns| format!("peers from DNS {}", dns))
+                .unwrap_or_else(|| format!("{} static peers", config.cluster.peers.len()))
+        );
+        cluster.start_discovery(&config.cluster);
+    }
+    if let Some(ms) = config.request.shed_write_p95_ms {
+        info!(
+            "Load shedding enabled above {}ms p95 storage write latency",
+            ms
+        );
+    }
+
+    Ok(AppState {
+        batcher,
+        traces_batcher,
+        metrics_batchers,
+        max_payload_bytes,
+        cardinality,
+        derived: derive::Derived::from_config(&config.derive).map(Arc::new),
+        events: events::EventExtractor::new(&config.events).map(Arc::new),
+        events_table: config.events.enabled.then(|| {
+            Arc::new(TableBatcher::new(
+                events::TABLE,
+                true,
+                table_batch_config.clone(),
+            ))
+        }),
+        resource_attributes,
+        #[cfg(feature = "k8s-enrichment")]
+        k8s,
+        #[cfg(feature = "geoip")]
+        geoip,
+        baggage,
+        provenance: config
+            .schema
+            .provenance_columns
+            .then(|| Arc::new(enrich::Provenance::new())),
+        shed_write_p95: config.request.shed_write_p95_ms.map(Duration::from_millis),
+        lenient_parsing: config.request.lenient_parsing,
+        strict_schema: config.request.strict_schema,
+        normalize_severity: config.schema.normalize_severity,
+        unattributed: unattributed::Unattributed::new(&config.request).map(Arc::new),
+        unattributed_tables: matches!(
+            config.request.unattributed,
+            UnattributedPolicy::Table | UnattributedPolicy::Infer
+        )
+        .then(|| UnattributedTables {
+            logs: Arc::new(TableBatcher::new(
+                unattributed::LOGS_TABLE,
+                false,
+                table_batch_config.clone(),
+            )),
+            traces: Arc::new(TableBatcher::new(
+                unattributed::TRACES_TABLE,
+                false,
+                table_batch_config,
+            )),
+        }),
+        captures,
+        cluster,
+        mirror: mirror::Mirror::new(&config.mirror)?.map(Arc::new),
+        request_metrics: Arc::new(request_metrics::RequestMetrics::new(
+            config.request.metrics_max_services,
+        )),
+    })
+}
+
+pub(crate) async fn flush_pending_batches(state: &AppState) -> Result<()> {
+    let request_metrics = &state.request_metrics;
+    flush_batcher(&state.batcher, SignalType::Logs, None, request_metrics).await?;
+    flush_batcher(
+        &state.traces_batcher,
+        SignalType::Traces,
+        None,
+        request_metrics,
+    )
+    .await?;
 
     if let Some(ref mb) = state.metrics_batchers {
         flush_batcher(
             &Some(Arc::clone(&mb.gauge)),
             SignalType::Metrics,
             Some("gauge"),
+            request_metrics,
+        )
+        .await?;
+        flush_batcher(
+            &Some(Arc::clone(&mb.sum)),
+            SignalType::Metrics,
+            Some("sum"),
+            request_metrics,
         )
         .await?;
-        flush_batcher(&Some(Arc::clone(&mb.sum)), SignalType::Metrics, Some("sum")).await?;
         flush_batcher(
             &Some(Arc::clone(&mb.histogram)),
             SignalType::Metrics,
             Some("histogram"),
+            request_metrics,
         )
         .await?;
         flush_batcher(
             &Some(Arc::clone(&mb.exp_histogram)),
             SignalType::Metrics,
             Some("exponential_histogram"),
+            request_metrics,
         )
         .await?;
     }
+    for table in state.side_tables() {
+        table.flush_all().await;
+    }
 
     Ok(())
 }
@@ -308,6 +881,7 @@ async fn flush_batcher(
     batcher: &Option<Arc<BatchManager>>,
     signal_type: SignalType,
     metric_type: Option<&str>,
+    request_metrics: &request_metrics::RequestMetrics,
 ) -> Result<()> {
     let Some(batcher) = batcher else {
         return Ok(());
@@ -331,7 +905,7 @@ async fn flush_batcher(
     for completed in pending {
         let rows = completed.metadata.record_count;
         let service = completed.metadata.service_name.as_ref().to_string();
-        match handlers::persist_batch(&completed, signal_type, metric_type).await {
+        match handlers::persist_batch(&completed, signal_type, metric_type, request_metrics).await {
             Ok(paths) => {
                 for path in paths {
                     info!(
@@ -339,6 +913,7 @@ async fn flush_batcher(
                         service_name = %service,
                         signal = signal_type.as_str(),
                         rows,
+                        request_ids = %completed.request_ids.join(","),
                         "Flushed pending batch"
                     );
                 }
@@ -372,31 +947,56 @@ async fn run_background_flush(state: AppState, shutdown: Arc<AtomicBool>, interv
             break;
         }
 
-        drain_expired_batcher(&state.batcher, SignalType::Logs, None).await;
-        drain_expired_batcher(&state.traces_batcher, SignalType::Traces, None).await;
+        let request_metrics = &state.request_metrics;
+        drain_expired_batcher(&state.batcher, SignalType::Logs, None, request_metrics).await;
+        drain_expired_batcher(
+            &state.traces_batcher,
+            SignalType::Traces,
+            None,
+            request_metrics,
+        )
+        .await;
 
         if let Some(ref mb) = state.metrics_batchers {
             drain_expired_batcher(
                 &Some(Arc::clone(&mb.gauge)),
                 SignalType::Metrics,
                 Some("gauge"),
+                request_metrics,
+            )
+            .await;
+            drain_expired_batcher(
+                &Some(Arc::clone(&mb.sum)),
+                SignalType::Metrics,
+                Some("sum"),
+                request_metrics,
             )
             .await;
-            drain_expired_batcher(&Some(Arc::clone(&mb.sum)), SignalType::Metrics, Some("sum"))
-                .await;
             drain_expired_batcher(
                 &Some(Arc::clone(&mb.histogram)),
                 SignalType::Metrics,
                 Some("histogram"),
+                request_metrics,
             )
             .await;
             drain_expired_batcher(
                 &Some(Arc::clone(&mb.exp_histogram)),
                 SignalType::Metrics,
                 Some("exponential_histogram"),
+                request_metrics,
             )
             .await;
         }
+        for table in state.side_tables() {
+            table.flush_expired().await;
+        }
+
+        for (table, stats) in state.buffer_stats() {
+            metrics::gauge!("otlp.batch.buffered_rows", "table" => table).set(stats.rows as f64);
+            metrics::gauge!("otlp.batch.buffered_bytes", "table" => table).set(stats.bytes as f64);
+            metrics::gauge!("otlp.batch.oldest_age_seconds", "table" => table)
+                .set(stats.oldest_age_ms as f64 / 1000.0);
+        }
     }
 
     debug!("Background flush task stopped");
@@ -406,6 +1006,7 @@ async fn drain_expired_batcher(
     batcher: &Option<Arc<BatchManager>>,
     signal_type: SignalType,
     metric_type: Option<&str>,
+    request_metrics: &request_metrics::RequestMetrics,
 ) {
     let Some(batcher) = batcher else {
         return;
@@ -416,7 +1017,9 @@ async fn drain_expired_batcher(
             for completed in expired {
                 let rows = completed.metadata.record_count;
                 let service = completed.metadata.service_name.as_ref().to_string();
-                match handlers::persist_batch(&completed, signal_type, metric_type).await {
+                match handlers::persist_batch(&completed, signal_type, metric_type, request_metrics)
+                    .await
+                {
                     Ok(paths) => {
                         for path in &paths {
                             info!(
@@ -424,6 +1027,7 @@ async fn drain_expired_batcher(
                                 service_name = %service,
                                 signal = signal_type.as_str(),
                                 rows,
+                                request_ids = %completed.request_ids.join(","),
                                 "Flushed expired batch"
                             );
                         }
@@ -449,3 +1053,54 @@ async fn drain_expired_batcher(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use std::io::Write;
+
+    #[tokio::test]
+    async fn test_request_decompression_encodings() {
+        let app = Router::new()
+            .route("/echo", post(|body: axum::body::Bytes| async move { body }))
+            .layer(request_decompression());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/echo", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"resourceLogs").unwrap();
+        let client = reqwest::Client::new();
+        let response = client
+            .post(&url)
+            .header("content-encoding", "deflate")
+            .body(encoder.finish().unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"resourceLogs");
+
+        let response = client
+            .post(&url)
+            .header("content-encoding", "zstd")
+            .body(zstd::encode_all(&b"resourceSpans"[..], 0).unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"resourceSpans");
+
+        let response = client
+            .post(&url)
+            .header("content-encoding", "br")
+            .body("compressed")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let accepted = response.headers()["accept-encoding"].to_str().unwrap();
+        assert!(accepted.contains("zstd") && accepted.contains("deflate"));
+    }
+}
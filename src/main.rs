@@ -27,6 +27,11 @@ struct Cli {
     /// Log level: trace, debug, info, warn, error
     #[arg(short = 'v', long, value_name = "LEVEL", global = true)]
     log_level: Option<String>,
+
+    /// Accept, convert and batch data as configured but discard the files
+    /// (storage backend "null")
+    #[arg(long, global = true)]
+    dry_run: bool,
 }
 
 #[derive(Subcommand)]
@@ -38,6 +43,38 @@ enum Commands {
     },
     /// Start the HTTP server (default if no subcommand given)
     Serve,
+    /// Run the server and also forward every accepted request to another
+    /// OTLP endpoint (dual-write during a migration)
+    Bridge(otlp2parquet::mirror::BridgeArgs),
+    /// Generate deployment manifests from the effective configuration
+    Create {
+        #[command(subcommand)]
+        target: otlp2parquet::create::CreateCommand,
+    },
+    /// Check configuration, storage access, and connectivity
+    Doctor,
+    /// Report daily volumes, file sizes and compression of written data
+    Stats(otlp2parquet::stats::StatsArgs),
+    /// Print the first rows of a written Parquet file
+    #[cfg(feature = "cat")]
+    Cat(otlp2parquet::cat::CatArgs),
+    /// Backfill archived OTLP data into the configured storage
+    Import {
+        #[command(subcommand)]
+        source: otlp2parquet::import::ImportCommand,
+    },
+    /// Generate a systemd, launchd, or Windows service definition
+    InstallService(otlp2parquet::service::InstallServiceArgs),
+    /// Query written Parquet through the local catalog with DuckDB
+    #[cfg(feature = "catalog")]
+    Query(otlp2parquet::catalog::QueryArgs),
+    /// Export a table slice through the local catalog to Parquet, CSV or JSONL
+    #[cfg(feature = "catalog")]
+    Export(otlp2parquet::catalog::ExportArgs),
+    /// Run under the Windows Service Control Manager (used by install-service)
+    #[cfg(windows)]
+    #[command(hide = true)]
+    WindowsService,
 }
 
 fn main() -> Result<()> {
@@ -45,10 +82,83 @@ fn main() -> Result<()> {
 
     match cli.command {
         Some(Commands::Connect { service }) => run_connect(service),
+        Some(Commands::Create { ref target }) => target.run(&load_config(&cli)?),
+        Some(Commands::Doctor) => run_doctor(cli),
+        Some(Commands::Stats(ref args)) => run_stats(&cli, args),
+        #[cfg(feature = "cat")]
+        Some(Commands::Cat(ref args)) => run_cat(&cli, args),
+        Some(Commands::Import { ref source }) => run_import(&cli, source),
+        Some(Commands::InstallService(args)) => args.run(cli.config.as_deref()),
+        #[cfg(feature = "catalog")]
+        Some(Commands::Query(ref args)) => args.run(&load_config(&cli)?),
+        #[cfg(feature = "catalog")]
+        Some(Commands::Export(ref args)) => args.run(&load_config(&cli)?),
+        #[cfg(windows)]
+        Some(Commands::WindowsService) => run_windows_service(cli),
+        Some(Commands::Bridge(ref args)) => run_bridge(&cli, args),
         Some(Commands::Serve) | None => run_server(cli),
     }
 }
 
+#[cfg(windows)]
+fn run_windows_service(cli: Cli) -> Result<()> {
+    // Services have no console; route logs to the event log from the start
+    otlp2parquet::service::windows::init_event_log(cli.log_level.as_deref().unwrap_or("info"));
+
+    // Resolve config before handing the main thread to the service dispatcher
+    let config = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to build tokio runtime")?
+        .block_on(resolve_config(&cli))?;
+    otlp2parquet::service::windows::run(config)
+}
+
+fn run_doctor(cli: Cli) -> Result<()> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to build tokio runtime")?
+        .block_on(async {
+            let config = load_config(&cli)?;
+            otlp2parquet::doctor::run(&config).await
+        })
+}
+
+fn run_stats(cli: &Cli, args: &otlp2parquet::stats::StatsArgs) -> Result<()> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to build tokio runtime")?
+        .block_on(async {
+            let config = load_config(cli)?;
+            args.run(&config).await
+        })
+}
+
+#[cfg(feature = "cat")]
+fn run_cat(cli: &Cli, args: &otlp2parquet::cat::CatArgs) -> Result<()> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to build tokio runtime")?
+        .block_on(async {
+            let config = load_config(cli)?;
+            args.run(&config).await
+        })
+}
+
+fn run_import(cli: &Cli, source: &otlp2parquet::import::ImportCommand) -> Result<()> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("Failed to build tokio runtime")?
+        .block_on(async {
+            let config = load_config(cli)?;
+            source.run(config).await
+        })
+}
+
 fn run_connect(service: otlp2parquet::connect::ConnectCommand) -> Result<()> {
     tokio::runtime::Builder::new_current_thread()
         .enable_all()
@@ -66,7 +176,48 @@ fn run_server(cli: Cli) -> Result<()> {
         .block_on(async_main(cli))
 }
 
+fn run_bridge(cli: &Cli, args: &otlp2parquet::mirror::BridgeArgs) -> Result<()> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("Failed to build tokio runtime")?
+        .block_on(async {
+            let mut config = load_config(cli)?;
+            args.apply(&mut config);
+            let config = prepare_config(config).await?;
+            otlp2parquet::run_with_config(config).await
+        })
+}
+
 async fn async_main(cli: Cli) -> Result<()> {
+    let config = resolve_config(&cli).await?;
+
+    // Step 7: Run server with resolved config
+    otlp2parquet::run_with_config(config).await
+}
+
+/// Load, override, validate, and announce the effective configuration
+async fn resolve_config(cli: &Cli) -> Result<RuntimeConfig> {
+    prepare_config(load_config(cli)?).await
+}
+
+/// Validate and announce a loaded configuration
+async fn prepare_config(config: RuntimeConfig) -> Result<RuntimeConfig> {
+    // Step 4: Initialize tracing early so validation logs show up
+    // Note: run_with_config will also call init_tracing, but that's idempotent
+    otlp2parquet::init_tracing(&config);
+
+    // Step 5: Validate configuration early (creates directories, tests write permissions)
+    validate_config(&config).await?;
+
+    // Step 6: Display startup info
+    display_startup_info(&config);
+
+    Ok(config)
+}
+
+/// Load configuration and apply CLI overrides and desktop defaults
+fn load_config(cli: &Cli) -> Result<RuntimeConfig> {
     // Step 1: Load base configuration
     let mut config = if let Some(config_path) = &cli.config {
         // Explicit config file path provided
@@ -78,23 +229,12 @@ async fn async_main(cli: Cli) -> Result<()> {
     };
 
     // Step 2: Apply CLI overrides (highest priority)
-    apply_cli_overrides(&mut config, &cli)?;
+    apply_cli_overrides(&mut config, cli)?;
 
     // Step 3: Apply desktop-friendly defaults
     apply_desktop_defaults(&mut config);
 
-    // Step 4: Initialize tracing early so validation logs show up
-    // Note: run_with_config will also call init_tracing, but that's idempotent
-    otlp2parquet::init_tracing(&config);
-
-    // Step 5: Validate configuration early (creates directories, tests write permissions)
-    validate_config(&config).await?;
-
-    // Step 6: Display startup info
-    display_startup_info(&config);
-
-    // Step 7: Run server with resolved config
-    otlp2parquet::run_with_config(config).await
+    Ok(config)
 }
 
 fn apply_cli_overrides(config: &mut RuntimeConfig, cli: &Cli) -> Result<()> {
@@ -126,6 +266,10 @@ fn apply_cli_overrides(config: &mut RuntimeConfig, cli: &Cli) -> Result<()> {
         server.log_level = level.clone();
     }
 
+    if cli.dry_run {
+        config.storage.backend = StorageBackend::Null;
+    }
+
     Ok(())
 }
 
@@ -154,6 +298,12 @@ fn display_startup_info(config: &RuntimeConfig) {
     info!("│ otlp2parquet v{}", env!("CARGO_PKG_VERSION"));
     info!("├─────────────────────────────────────────────────");
     info!("│ Listen address: http://{}", server.listen_addr);
+    if let Some(grpc_addr) = &server.grpc_listen_addr {
+        info!("│ gRPC listen address: {}", grpc_addr);
+    }
+    if let Some(endpoint) = &config.mirror.endpoint {
+        info!("│ Forwarding to: {}", endpoint);
+    }
     info!("│ Storage backend: {}", config.storage.backend);
 
     if config.storage.backend == StorageBackend::Fs {
@@ -170,6 +320,32 @@ fn display_startup_info(config: &RuntimeConfig) {
             info!("│ R2 bucket: {}", r2.bucket);
             info!("│ R2 account: {}", r2.account_id);
         }
+    } else if config.storage.backend == StorageBackend::Sftp {
+        if let Some(sftp) = &config.storage.sftp {
+            info!("│ SFTP endpoint: {}", sftp.endpoint);
+            info!("│ SFTP root: {}", sftp.root);
+        }
+    } else if config.storage.backend == StorageBackend::Webdav {
+        if let Some(webdav) = &config.storage.webdav {
+            info!("│ WebDAV endpoint: {}", webdav.endpoint);
+            info!("│ WebDAV root: {}", webdav.root);
+        }
+    } else if config.storage.backend == StorageBackend::Hdfs {
+        if let Some(hdfs) = &config.storage.hdfs {
+            info!("│ HDFS endpoint: {}", hdfs.endpoint);
+            info!("│ HDFS root: {}", hdfs.root);
+        }
+    } else if config.storage.backend == StorageBackend::Gcs {
+        if let Some(gcs) = &config.storage.gcs {
+            info!("│ GCS bucket: {}", gcs.bucket);
+        }
+    } else if config.storage.backend == StorageBackend::Azblob {
+        if let Some(azblob) = &config.storage.azblob {
+            info!("│ Azure account: {}", azblob.account_name);
+            info!("│ Azure container: {}", azblob.container);
+        }
+    } else if config.storage.backend == StorageBackend::Null {
+        info!("│ Dry run: files are discarded, not stored");
     }
 
     info!("│ Log level: {}", server.log_level);
@@ -0,0 +1,410 @@
+// Request mirroring (dual-write)
+//
+// With `mirror.endpoint` set, every OTLP request that was ingested
+// successfully is also sent, unchanged, to another OTLP/HTTP endpoint, so an
+// existing vendor backend keeps receiving data while the lake is introduced.
+// The client's answer never waits on the mirror: requests are queued and sent
+// by a background task that retries retryable failures with exponential
+// backoff. A full queue drops requests (counted in `otlp.mirror.dropped`)
+// rather than slowing down ingest. Requests that fail locally are not
+// mirrored; the client retries them and the retry is mirrored once it is
+// accepted. `otlp2parquet bridge` runs the server with a mirror configured.
+//
+// In shadow mode the mirror is a second otlp2parquet deployment under test:
+// `mirror.sample_percent` of all requests are sent whatever their local
+// outcome, and the shadow's answer is compared with the local one. Requests
+// one side accepted and the other refused, or whose partial success rejected
+// a different number of items, count in `otlp.mirror.divergent`; the local
+// answer stays authoritative.
+
+use crate::config::{MirrorConfig, MirrorMode, RuntimeConfig, ServerConfig};
+use crate::otlp_response::PartialSuccess;
+use crate::retry_queue::{backoff, spawn_worker, RetryQueue};
+use crate::{AppError, SignalType};
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+use axum::response::Response;
+use clap::Args;
+use metrics::counter;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Longest pause between retries of one request
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Args)]
+pub struct BridgeArgs {
+    /// Address to accept OTLP/HTTP on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:4319")]
+    pub listen: String,
+
+    /// OTLP/HTTP endpoint every accepted request is also sent to, e.g.
+    /// https://otlp.vendor.example (requests go to /v1/logs, ...)
+    #[arg(long, value_name = "URL")]
+    pub forward: String,
+
+    /// Header added to forwarded requests, e.g. an API key (repeatable)
+    #[arg(long = "header", value_name = "NAME=VALUE", value_parser = parse_header)]
+    pub headers: Vec<(String, String)>,
+}
+
+impl BridgeArgs {
+    /// Listen on `--listen` and mirror to `--forward`, on top of the loaded
+    /// config (storage, batching and the rest apply as configured).
+    pub fn apply(&self, config: &mut RuntimeConfig) {
+        let server = config.server.get_or_insert_with(ServerConfig::default);
+        server.listen_addr = self.listen.clone();
+        config.mirror.endpoint = Some(self.forward.clone());
+        config.mirror.headers.extend(self.headers.iter().cloned());
+    }
+}
+
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected NAME=VALUE, got '{}'", s)),
+    }
+}
+
+/// One request waiting to be mirrored
+struct Mirrored {
+    signal: SignalType,
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+    /// How the request fared locally, in shadow mode
+    local: Option<Outcome>,
+}
+
+/// Whether a request was accepted, and how many items its partial success
+/// rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Outcome {
+    accepted: bool,
+    rejected: u64,
+}
+
+impl Outcome {
+    fn of(result: &Result<Response, AppError>) -> Self {
+        match result {
+            Ok(response) => Self {
+                accepted: response.status().is_success(),
+                rejected: response
+                    .extensions()
+                    .get::<PartialSuccess>()
+                    .map_or(0, PartialSuccess::rejected),
+            },
+            Err(_) => Self {
+                accepted: false,
+                rejected: 0,
+            },
+        }
+    }
+
+    /// Divergence kind between a local and a shadow outcome, if they differ
+    fn divergence(self, shadow: Outcome) -> Option<&'static str> {
+        if self.accepted != shadow.accepted {
+            Some("status")
+        } else if self.accepted && self.rejected != shadow.rejected {
+            Some("rejected")
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) struct Mirror {
+    queue: RetryQueue<Mirrored>,
+    mode: MirrorMode,
+    sample_percent: f64,
+    /// Requests seen, for spreading the sample evenly
+    seen: AtomicU64,
+}
+
+struct Sender {
+    client: reqwest::Client,
+    endpoint: String,
+    headers: HeaderMap,
+    max_retries: u32,
+}
+
+impl Mirror {
+    /// Start the sender; `None` when no endpoint is configured.
+    pub(crate) fn new(config: &MirrorConfig) -> Result<Option<Self>> {
+        let Some(endpoint) = config.endpoint.as_deref() else {
+            return Ok(None);
+        };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .context("Failed to build mirror HTTP client")?;
+        let sender = Sender {
+            client,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            headers: request_headers(config)?,
+            max_retries: config.max_retries,
+        };
+        let sender = Arc::new(sender);
+        let queue = spawn_worker(
+            config.queue_capacity,
+            config.concurrency,
+            move |request: Mirrored| {
+                let sender = Arc::clone(&sender);
+                async move { sender.send_with_retry(&request).await }
+            },
+        );
+        match config.mode {
+            MirrorMode::Forward => info!("Mirroring accepted requests to {}", endpoint),
+            MirrorMode::Shadow => info!(
+                "Shadowing {}% of requests to {}",
+                config.sample_percent, endpoint
+            ),
+        }
+        Ok(Some(Self {
+            queue,
+            mode: config.mode,
+            sample_percent: config.sample_percent,
+            seen: AtomicU64::new(0),
+        }))
+    }
+
+    /// Queue a request for mirroring without waiting for it, given its local
+    /// `result`: accepted ones when forwarding, a sample of all when shadowing.
+    pub(crate) fn submit(
+        &self,
+        signal: SignalType,
+        headers: &HeaderMap,
+        body: Bytes,
+        result: &Result<Response, AppError>,
+    ) {
+        let local = Outcome::of(result);
+        if self.mode == MirrorMode::Forward && !local.accepted {
+            return;
+        }
+        if !self.sampled() {
+            return;
+        }
+        let request = Mirrored {
+            signal,
+            content_type: headers.get(header::CONTENT_TYPE).cloned(),
+            body,
+            local: (self.mode == MirrorMode::Shadow).then_some(local),
+        };
+        if let Err(e) = self.queue.try_push(request) {
+            counter!("otlp.mirror.dropped", "signal" => signal.as_str()).increment(1);
+            if matches!(e, mpsc::error::TrySendError::Full(_)) {
+                warn!(
+                    signal = signal.as_str(),
+                    "Mirror queue full, dropping request"
+                );
+            }
+        }
+    }
+
+    /// Whether the next request is in the sample. Request `n` is taken when
+    /// `n * percent / 100` reaches a new integer, so the sample is spread
+    /// evenly and exact over any run of requests.
+    fn sampled(&self) -> bool {
+        if self.sample_percent >= 100.0 {
+            return true;
+        }
+        let n = self.seen.fetch_add(1, Ordering::Relaxed) as f64;
+        let fraction = self.sample_percent / 100.0;
+        ((n + 1.0) * fraction).floor() > (n * fraction).floor()
+    }
+
+    /// Wait up to `timeout` for queued requests during shutdown.
+    pub(crate) async fn drain(&self, timeout: Duration) {
+        let pending = self.queue.drain(timeout).await;
+        if pending > 0 {
+            warn!(pending, "Gave up waiting for mirrored requests");
+        }
+    }
+}
+
+/// Configured headers, checked once so a bad one fails at startup
+fn request_headers(config: &MirrorConfig) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &config.headers {
+        let name = HeaderName::try_from(name.as_str())
+            .with_context(|| format!("mirror.headers: invalid header name '{}'", name))?;
+        let value = HeaderValue::try_from(value.as_str())
+            .with_context(|| format!("mirror.headers: invalid value for '{}'", name))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+impl Sender {
+    async fn send_with_retry(&self, request: &Mirrored) {
+        let signal = request.signal.as_str();
+        let mut attempt = 0u32;
+        let shadow = loop {
+            match self.send(request).await {
+                Ok(rejected) => {
+                    counter!("otlp.mirror.sent", "signal" => signal).increment(1);
+                    break Outcome {
+                        accepted: true,
+                        rejected,
+                    };
+                }
+                Err((e, true)) if attempt < self.max_retries => {
+                    attempt += 1;
+                    let delay = backoff(attempt, MAX_BACKOFF);
+                    debug!(
+                        error = %e,
+                        signal,
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        "Mirrored request failed, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err((e, _)) => {
+                    counter!("otlp.mirror.failed", "signal" => signal).increment(1);
+                    warn!(
+                        error = %e,
+                        signal,
+                        attempts = attempt + 1,
+                        "Giving up on mirrored request"
+                    );
+                    break Outcome {
+                        accepted: false,
+                        rejected: 0,
+                    };
+                }
+            }
+        };
+
+        let Some(local) = request.local else {
+            return;
+        };
+        counter!("otlp.mirror.compared", "signal" => signal).increment(1);
+        if let Some(kind) = local.divergence(shadow) {
+            counter!("otlp.mirror.divergent", "signal" => signal, "kind" => kind).increment(1);
+            debug!(
+                signal,
+                kind,
+                local_accepted = local.accepted,
+                shadow_accepted = shadow.accepted,
+                local_rejected = local.rejected,
+                shadow_rejected = shadow.rejected,
+                "Shadow response diverged"
+            );
+        }
+    }
+
+    /// Send once; returns the items the partial success rejected (shadow
+    /// mode), or an error saying whether a retry may succeed
+    async fn send(&self, request: &Mirrored) -> Result<u64, (anyhow::Error, bool)> {
+        let url = format!("{}/v1/{}", self.endpoint, request.signal.as_str());
+        let mut builder = self
+            .client
+            .post(&url)
+            .headers(self.headers.clone())
+            .body(request.body.clone());
+        if let Some(content_type) = &request.content_type {
+            builder = builder.header(header::CONTENT_TYPE, content_type.clone());
+        }
+        if request.local.is_some() {
+            // The JSON answer carries the partial success in any request format
+            builder = builder.header(header::ACCEPT, "application/json");
+        }
+        let response = match builder.send().await {
+            Ok(response) => response,
+            Err(e) => return Err((anyhow::Error::new(e).context("mirror request failed"), true)),
+        };
+        let status = response.status();
+        if status.is_success() {
+            if request.local.is_none() {
+                return Ok(0);
+            }
+            let body = response.bytes().await.unwrap_or_default();
+            let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            return Ok(rejected_items(&body));
+        }
+        let retryable = crate::otlp_response::is_retryable(status);
+        let text = response.text().await.unwrap_or_default();
+        Err((
+            anyhow::anyhow!("{} returned {}: {}", url, status, text.trim()),
+            retryable,
+        ))
+    }
+}
+
+/// Rejected count of an OTLP/JSON response's `partialSuccess` (a string or a
+/// number, per the JSON mapping of int64)
+fn rejected_items(body: &Value) -> u64 {
+    let partial = &body["partialSuccess"];
+    ["rejectedLogRecords", "rejectedSpans", "rejectedDataPoints"]
+        .iter()
+        .find_map(|key| match &partial[*key] {
+            Value::String(s) => s.parse().ok(),
+            Value::Number(n) => n.as_u64(),
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Platform;
+
+    #[test]
+    fn test_bridge_args_configure_mirror() {
+        let mut config = RuntimeConfig::from_platform_defaults(Platform::Server);
+        let args = BridgeArgs {
+            listen: "127.0.0.1:4319".to_string(),
+            forward: "https://otlp.vendor.example/".to_string(),
+            headers: vec![parse_header("x-api-key=abc=1").unwrap()],
+        };
+        args.apply(&mut config);
+        assert_eq!(
+            config.server.as_ref().unwrap().listen_addr,
+            "127.0.0.1:4319"
+        );
+        assert_eq!(
+            config.mirror.endpoint.as_deref(),
+            Some("https://otlp.vendor.example/")
+        );
+        let headers = request_headers(&config.mirror).unwrap();
+        assert_eq!(headers["x-api-key"], "abc=1");
+        assert!(parse_header("no-value").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shadow_sampling_and_divergence() {
+        let mirror = Mirror {
+            queue: spawn_worker(1, 1, |_: Mirrored| async {}),
+            mode: MirrorMode::Shadow,
+            sample_percent: 25.0,
+            seen: AtomicU64::new(0),
+        };
+        let sampled = (0..100).filter(|_| mirror.sampled()).count();
+        assert_eq!(sampled, 25);
+
+        let accepted = |rejected| Outcome {
+            accepted: true,
+            rejected,
+        };
+        let refused = Outcome {
+            accepted: false,
+            rejected: 0,
+        };
+        assert_eq!(accepted(0).divergence(accepted(0)), None);
+        assert_eq!(accepted(0).divergence(refused), Some("status"));
+        assert_eq!(accepted(2).divergence(accepted(0)), Some("rejected"));
+        assert_eq!(refused.divergence(refused), None);
+
+        let body = serde_json::json!({"partialSuccess": {"rejectedDataPoints": "3"}});
+        assert_eq!(rejected_items(&body), 3);
+        assert_eq!(rejected_items(&serde_json::json!({})), 0);
+    }
+}
@@ -0,0 +1,205 @@
+// Write notifications
+//
+// With `notifications.webhook_url`, every committed file (default storage or
+// route target) is announced with a JSON POST (table, path, rows, bytes,
+// timestamp range, blake3), so downstream loaders can react instantly instead
+// of polling manifests. Notifications are queued from a writer hook and sent by a
+// background task that retries with exponential backoff; a full queue drops
+// notifications (counted in `otlp.notify.dropped`) rather than slowing down
+// writes.
+
+use crate::config::NotificationsConfig;
+use crate::retry_queue::{backoff, spawn_worker, RetryQueue};
+use crate::writer::{register_writer_hook, FileEvent, WriterHook};
+use anyhow::{bail, Context, Result};
+use metrics::counter;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// Longest pause between retries of one notification
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Notifications queued or in flight, shared by the hook and the shutdown drain
+static QUEUE: OnceCell<Arc<RetryQueue<Notification>>> = OnceCell::new();
+
+/// Body of one webhook request
+#[derive(Debug, Clone, Serialize)]
+struct Notification {
+    event: &'static str,
+    table: String,
+    path: String,
+    rows: usize,
+    bytes: usize,
+    min_timestamp_micros: i64,
+    max_timestamp_micros: i64,
+    blake3: String,
+    written_at_micros: i64,
+}
+
+impl Notification {
+    fn committed(event: &FileEvent<'_>) -> Self {
+        Self {
+            event: "file.committed",
+            table: event.table.to_string(),
+            path: event.path.to_string(),
+            rows: event.entry.rows,
+            bytes: event.entry.bytes,
+            min_timestamp_micros: event.entry.min_timestamp_micros,
+            max_timestamp_micros: event.entry.max_timestamp_micros,
+            blake3: event.entry.blake3.clone(),
+            written_at_micros: event.entry.written_at_micros,
+        }
+    }
+}
+
+struct WebhookHook {
+    queue: Arc<RetryQueue<Notification>>,
+}
+
+struct Sender {
+    client: reqwest::Client,
+    config: NotificationsConfig,
+    url: String,
+}
+
+/// Register the webhook hook and start its sender if a URL is configured.
+pub(crate) fn init(config: &NotificationsConfig) -> Result<()> {
+    let Some(url) = config.webhook_url.clone() else {
+        return Ok(());
+    };
+    if QUEUE.get().is_some() {
+        return Ok(());
+    }
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .build()
+        .context("Failed to build webhook HTTP client")?;
+    let sender = Arc::new(Sender {
+        client,
+        config: config.clone(),
+        url: url.clone(),
+    });
+    let queue = Arc::new(spawn_worker(
+        config.queue_capacity,
+        1,
+        move |notification: Notification| {
+            let sender = Arc::clone(&sender);
+            async move { sender.send_with_retry(&notification).await }
+        },
+    ));
+    let _ = QUEUE.set(Arc::clone(&queue));
+    register_writer_hook(Arc::new(WebhookHook { queue }));
+    info!("Sending file commit notifications to {}", url);
+    Ok(())
+}
+
+impl WriterHook for WebhookHook {
+    fn on_commit(&self, event: &FileEvent<'_>) {
+        if let Err(e) = self.queue.try_push(Notification::committed(event)) {
+            counter!("otlp.notify.dropped").increment(1);
+            if matches!(e, mpsc::error::TrySendError::Full(_)) {
+                warn!(path = %event.path, "Notification queue full, dropping notification");
+            }
+        }
+    }
+}
+
+/// Wait up to `timeout` for queued notifications during shutdown.
+pub(crate) async fn drain(timeout: Duration) {
+    let Some(queue) = QUEUE.get() else {
+        return;
+    };
+    let pending = queue.drain(timeout).await;
+    if pending > 0 {
+        warn!(pending, "Gave up waiting for queued notifications");
+    }
+}
+
+impl Sender {
+    async fn send_with_retry(&self, notification: &Notification) {
+        let mut attempt = 0u32;
+        loop {
+            match self.send(notification).await {
+                Ok(()) => {
+                    counter!("otlp.notify.sent").increment(1);
+                    debug!(path = %notification.path, "Sent commit notification");
+                    return;
+                }
+                Err(e) if attempt < self.config.max_retries => {
+                    attempt += 1;
+                    let delay = backoff(attempt, MAX_BACKOFF);
+                    debug!(
+                        error = %e,
+                        path = %notification.path,
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        "Webhook notification failed, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => {
+                    counter!("otlp.notify.failed").increment(1);
+                    warn!(
+                        error = %e,
+                        path = %notification.path,
+                        attempts = attempt + 1,
+                        "Giving up on webhook notification"
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(notification)?);
+        if let Some(token) = &self.config.webhook_token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.context("webhook request failed")?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            bail!("webhook returned {}: {}", status, text.trim());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::manifest::ManifestEntry;
+
+    #[test]
+    fn test_notification_body() {
+        let entry = ManifestEntry {
+            file: "a.parquet".to_string(),
+            rows: 12,
+            bytes: 3400,
+            min_timestamp_micros: 1,
+            max_timestamp_micros: 2,
+            blake3: "9c1e".to_string(),
+            written_at_micros: 3,
+        };
+        let notification = Notification::committed(&FileEvent {
+            table: "metrics/gauge",
+            path: "metrics/gauge/web/year=2025/a.parquet",
+            entry: &entry,
+            relocated: false,
+        });
+        let body = serde_json::to_value(&notification).unwrap();
+        assert_eq!(body["event"], "file.committed");
+        assert_eq!(body["table"], "metrics/gauge");
+        assert_eq!(body["rows"], 12);
+        assert_eq!(body["blake3"], "9c1e");
+    }
+}
@@ -0,0 +1,306 @@
+// OTLP/HTTP responses
+//
+// OTLP/HTTP answers in the format of the request. Protobuf requests get an
+// `Export*ServiceResponse` on success (with `partial_success` when data points
+// were dropped) and a `google.rpc.Status` with the error message otherwise;
+// the status code and headers such as `Retry-After` and `X-Request-Id` are
+// kept. JSON requests get the JSON summary, which carries `partialSuccess`
+// and, for errors, the Status `code` and `message` fields. An explicit
+// `Accept: application/x-protobuf` or `application/json` overrides the
+// request's format.
+//
+// Clients retry only 429, 502, 503 and 504. Transient failures (storage
+// errors, load shedding) use those with `Retry-After` (and `RetryInfo` in
+// protobuf Status details); malformed or rejected data gets a 4xx and internal
+// bugs a 500, which clients drop instead of resending forever.
+
+use crate::codec::SkippedMetrics;
+use crate::{AppError, InputFormat};
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::Response;
+use serde_json::{json, Value};
+use tracing::error;
+
+const PROTOBUF: &str = "application/x-protobuf";
+const JSON: &str = "application/json";
+
+/// Seconds clients are asked to wait before retrying
+pub(crate) const RETRY_AFTER_SECS: u64 = 5;
+
+const RETRY_INFO_TYPE: &str = "type.googleapis.com/google.rpc.RetryInfo";
+
+/// Records accepted by a request but not stored, reported as the OTLP
+/// partial success
+#[derive(Debug, Clone)]
+pub(crate) struct PartialSuccess {
+    rejected: u64,
+    message: String,
+}
+
+impl PartialSuccess {
+    /// Data points a metrics request lost in decoding, if any
+    pub(crate) fn for_metrics(skipped: &SkippedMetrics) -> Option<Self> {
+        if !skipped.has_skipped() {
+            return None;
+        }
+        let invalid = skipped.nan_values + skipped.infinity_values + skipped.missing_values;
+        Some(Self {
+            rejected: skipped.total() as u64,
+            message: format!(
+                "dropped {} summary metrics (unsupported) and {} data points without a finite value",
+                skipped.summaries, invalid
+            ),
+        })
+    }
+
+    /// Items rejected
+    pub(crate) fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    /// The `partialSuccess` object of an OTLP/JSON metrics response
+    pub(crate) fn to_json(&self) -> Value {
+        json!({
+            "rejectedDataPoints": self.rejected.to_string(),
+            "errorMessage": self.message,
+        })
+    }
+}
+
+/// Whether the response should be protobuf: an `Accept` header naming
+/// protobuf or JSON decides, otherwise the request's content type.
+pub(crate) fn wants_protobuf(headers: &HeaderMap) -> bool {
+    let accepted = |wanted: &str| {
+        headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .any(|media| {
+                media
+                    .split(';')
+                    .next()
+                    .is_some_and(|t| t.trim().eq_ignore_ascii_case(wanted))
+            })
+    };
+    if accepted(PROTOBUF) {
+        return true;
+    }
+    if accepted(JSON) {
+        return false;
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    InputFormat::from_content_type(content_type) == InputFormat::Protobuf
+}
+
+/// `result` re-encoded as an OTLP protobuf response.
+pub(crate) fn protobuf(result: Result<Response, AppError>) -> Response {
+    let (mut response, message) = match result {
+        Ok(response) if response.status().is_success() => (response, None),
+        Ok(response) => {
+            let message = response
+                .status()
+                .canonical_reason()
+                .unwrap_or("request failed")
+                .to_string();
+            (response, Some(message))
+        }
+        Err(e) => {
+            error!("Request error: {:?}", e.error);
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = e.status;
+            set_retry_after(&mut response);
+            (response, Some(e.error.to_string()))
+        }
+    };
+    let body = match message {
+        Some(message) => {
+            let retry_delay = response
+                .headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok());
+            encode_status(grpc_code(response.status()), &message, retry_delay)
+        }
+        None => export_response(&response),
+    };
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(PROTOBUF));
+    headers.remove(header::CONTENT_LENGTH);
+    let (parts, _) = response.into_parts();
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// The `Export*ServiceResponse` for a successful `response`: empty, or the
+/// partial success it carries
+pub(crate) fn export_response(response: &Response) -> Vec<u8> {
+    response
+        .extensions()
+        .get::<PartialSuccess>()
+        .map(encode_partial_success)
+        .unwrap_or_default()
+}
+
+/// Whether OTLP clients retry a request answered with `status`
+pub(crate) fn is_retryable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Add `Retry-After` to a retryable error response that has none.
+pub(crate) fn set_retry_after(response: &mut Response) {
+    if is_retryable(response.status()) && !response.headers().contains_key(header::RETRY_AFTER) {
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+    }
+}
+
+/// gRPC status code for an HTTP status, as OTLP/HTTP clients interpret them
+pub(crate) fn grpc_code(status: StatusCode) -> i32 {
+    match status {
+        StatusCode::BAD_REQUEST => 3,             // INVALID_ARGUMENT
+        StatusCode::UNAUTHORIZED => 16,           // UNAUTHENTICATED
+        StatusCode::FORBIDDEN => 7,               // PERMISSION_DENIED
+        StatusCode::NOT_FOUND => 5,               // NOT_FOUND
+        StatusCode::PAYLOAD_TOO_LARGE => 8,       // RESOURCE_EXHAUSTED
+        StatusCode::TOO_MANY_REQUESTS => 8,       // RESOURCE_EXHAUSTED
+        StatusCode::SERVICE_UNAVAILABLE => 14,    // UNAVAILABLE
+        status if status.is_server_error() => 13, // INTERNAL
+        _ => 2,                                   // UNKNOWN
+    }
+}
+
+/// `google.rpc.Status { code = 1; message = 2; details = 3 }` in protobuf
+/// wire format, with a `RetryInfo` detail when a retry delay is given
+pub(crate) fn encode_status(code: i32, message: &str, retry_delay_secs: Option<u64>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(message.len() + 64);
+    if code != 0 {
+        out.push(0x08);
+        put_varint(&mut out, code as u64);
+    }
+    if !message.is_empty() {
+        put_bytes(&mut out, 0x12, message.as_bytes());
+    }
+    if let Some(secs) = retry_delay_secs {
+        // RetryInfo { retry_delay = 1: Duration { seconds = 1 } } packed in an Any
+        let mut duration = vec![0x08];
+        put_varint(&mut duration, secs);
+        let mut retry_info = Vec::new();
+        put_bytes(&mut retry_info, 0x0A, &duration);
+        let mut any = Vec::new();
+        put_bytes(&mut any, 0x0A, RETRY_INFO_TYPE.as_bytes());
+        put_bytes(&mut any, 0x12, &retry_info);
+        put_bytes(&mut out, 0x1A, &any);
+    }
+    out
+}
+
+/// `Export*ServiceResponse { partial_success = 1 }`; the rejected count and
+/// message are fields 1 and 2 for every signal
+fn encode_partial_success(partial: &PartialSuccess) -> Vec<u8> {
+    let mut inner = Vec::with_capacity(partial.message.len() + 12);
+    inner.push(0x08);
+    put_varint(&mut inner, partial.rejected);
+    put_bytes(&mut inner, 0x12, partial.message.as_bytes());
+
+    let mut out = Vec::with_capacity(inner.len() + 4);
+    put_bytes(&mut out, 0x0A, &inner);
+    out
+}
+
+/// A length-delimited field: `tag`, length, `bytes`
+fn put_bytes(out: &mut Vec<u8>, tag: u8, bytes: &[u8]) {
+    out.push(tag);
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+
+    #[test]
+    fn test_error_paths_are_classified_for_retries() {
+        let response = |error: AppError| error.into_response();
+
+        // Bad payloads and rejected records are permanent
+        let rejected = response(AppError::bad_request(anyhow::anyhow!("bad payload")));
+        assert!(!is_retryable(rejected.status()));
+        assert!(rejected.headers().get(header::RETRY_AFTER).is_none());
+        let too_large = response(AppError::with_status(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            anyhow::anyhow!("too large"),
+        ));
+        assert!(!is_retryable(too_large.status()));
+        // Bugs are not retried either
+        assert!(!is_retryable(
+            response(AppError::internal(anyhow::anyhow!("bug"))).status()
+        ));
+
+        // Storage failures are retried after a delay
+        let storage = response(AppError::unavailable(anyhow::anyhow!("s3 timeout")));
+        assert_eq!(storage.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(storage.headers()[header::RETRY_AFTER], "5");
+
+        let status = protobuf(Err(AppError::unavailable(anyhow::anyhow!("down"))));
+        assert_eq!(status.headers()[header::RETRY_AFTER], "5");
+        let body = encode_status(14, "down", Some(5));
+        assert_eq!(&body[..3], &[0x08, 14, 0x12]);
+        assert!(body.ends_with(&[0x12, 4, 0x0A, 2, 0x08, 5]));
+    }
+
+    #[test]
+    fn test_protobuf_status_encoding() {
+        let mut headers = HeaderMap::new();
+        assert!(!wants_protobuf(&headers));
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-protobuf"),
+        );
+        assert!(wants_protobuf(&headers));
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+        assert!(!wants_protobuf(&headers));
+        headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_static("application/json;q=0.5, Application/X-Protobuf"),
+        );
+        assert!(wants_protobuf(&headers));
+
+        assert_eq!(
+            encode_status(grpc_code(StatusCode::BAD_REQUEST), "bad", None),
+            vec![0x08, 3, 0x12, 3, b'b', b'a', b'd']
+        );
+        let long = "x".repeat(200);
+        assert_eq!(
+            &encode_status(13, &long, None)[..5],
+            &[0x08, 13, 0x12, 0xC8, 0x01]
+        );
+
+        let partial = PartialSuccess {
+            rejected: 2,
+            message: "x".to_string(),
+        };
+        assert_eq!(
+            encode_partial_success(&partial),
+            vec![0x0A, 5, 0x08, 2, 0x12, 1, b'x']
+        );
+    }
+}
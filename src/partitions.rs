@@ -0,0 +1,412 @@
+// Partition listing for query planners
+//
+// GET /partitions?signal=logs&from=...&to=... returns the hour partitions of
+// a signal's tables overlapping the time range, with the Parquet files in
+// each and their row counts, sizes and event-time ranges, so an external
+// query engine can plan a scan without listing the bucket itself. Files come
+// from the local catalog when it is enabled, otherwise from the partition
+// manifests; partitions without a manifest are listed, and their files have
+// no row count or time range.
+
+use crate::writer::manifest::{ManifestEntry, MANIFEST_FILE};
+use crate::writer::partition_hour_micros;
+use crate::{AppError, SignalType};
+use axum::extract::Query;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+const MICROS_PER_HOUR: i64 = 3_600_000_000;
+
+/// Metric tables, in the order they are reported
+const METRIC_TYPES: [&str; 4] = ["gauge", "sum", "histogram", "exponential_histogram"];
+
+#[derive(Deserialize)]
+pub(crate) struct PartitionsQuery {
+    signal: String,
+    /// Only this metric table (`gauge`, `sum`, ...) of the metrics signal
+    metric_type: Option<String>,
+    /// RFC 3339; open-ended when omitted
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct PartitionList {
+    signal: &'static str,
+    /// Where file details came from: `catalog` or `storage`
+    source: &'static str,
+    partitions: Vec<Partition>,
+}
+
+#[derive(Debug, Serialize)]
+struct Partition {
+    table: String,
+    /// Partition directory, e.g. `logs/web/year=2025/month=01/day=15/hour=10`
+    path: String,
+    /// Start of the partition hour (RFC 3339)
+    start: String,
+    /// Total rows, when every file's count is known
+    rows: Option<u64>,
+    bytes: u64,
+    files: Vec<PartitionFile>,
+}
+
+#[derive(Debug, Serialize)]
+struct PartitionFile {
+    path: String,
+    rows: Option<u64>,
+    bytes: u64,
+    min_timestamp_micros: Option<i64>,
+    max_timestamp_micros: Option<i64>,
+}
+
+/// Inclusive event-time window in microseconds
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    from: i64,
+    to: i64,
+}
+
+impl Window {
+    fn overlaps_hour(&self, hour: i64) -> bool {
+        hour <= self.to && hour + MICROS_PER_HOUR > self.from
+    }
+
+    /// Files without a known time range are kept
+    fn overlaps_file(&self, file: &PartitionFile) -> bool {
+        match (file.min_timestamp_micros, file.max_timestamp_micros) {
+            (Some(min), Some(max)) => min <= self.to && max >= self.from,
+            _ => true,
+        }
+    }
+}
+
+/// GET /partitions - Partitions and files of a signal within a time range
+pub(crate) async fn list_partitions(
+    Query(query): Query<PartitionsQuery>,
+) -> Result<Json<PartitionList>, AppError> {
+    let signal = match query.signal.as_str() {
+        "logs" => SignalType::Logs,
+        "traces" => SignalType::Traces,
+        "metrics" => SignalType::Metrics,
+        other => {
+            return Err(AppError::bad_request(anyhow::anyhow!(
+                "unknown signal '{}' (expected logs, traces or metrics)",
+                other
+            )))
+        }
+    };
+    let tables: Vec<Option<&str>> = match (signal, query.metric_type.as_deref()) {
+        (SignalType::Metrics, None) => METRIC_TYPES.iter().copied().map(Some).collect(),
+        (SignalType::Metrics, Some(metric_type)) if METRIC_TYPES.contains(&metric_type) => {
+            vec![Some(metric_type)]
+        }
+        (_, None) => vec![None],
+        (_, Some(metric_type)) => {
+            return Err(AppError::bad_request(anyhow::anyhow!(
+                "metric_type '{}' is not a {} table",
+                metric_type,
+                signal.as_str()
+            )))
+        }
+    };
+    let window = Window {
+        from: parse_time(query.from.as_deref())?.unwrap_or(i64::MIN),
+        to: parse_time(query.to.as_deref())?.unwrap_or(i64::MAX),
+    };
+    if window.from > window.to {
+        return Err(AppError::bad_request(anyhow::anyhow!(
+            "'from' is after 'to'"
+        )));
+    }
+
+    #[cfg(feature = "catalog")]
+    if let Some(catalog) = crate::writer::get_catalog() {
+        let files = catalog.files().map_err(AppError::internal)?;
+        let mut partitions = Vec::new();
+        for metric_type in tables {
+            let dir = format!("{}/", crate::writer::table_prefix(signal, metric_type));
+            let listed = files
+                .iter()
+                .filter(|f| f.path.starts_with(&dir))
+                .map(|f| PartitionFile {
+                    path: f.path.clone(),
+                    rows: Some(f.rows.max(0) as u64),
+                    bytes: f.bytes.max(0) as u64,
+                    min_timestamp_micros: Some(f.min_timestamp_micros),
+                    max_timestamp_micros: Some(f.max_timestamp_micros),
+                });
+            partitions.extend(group(&table_name(signal, metric_type), listed, window));
+        }
+        return Ok(Json(PartitionList {
+            signal: signal.as_str(),
+            source: "catalog",
+            partitions,
+        }));
+    }
+
+    let op = crate::writer::get_operator()
+        .ok_or_else(|| AppError::internal(anyhow::anyhow!("storage not initialized")))?;
+    let mut partitions = Vec::new();
+    for metric_type in tables {
+        let prefix = crate::writer::table_prefix(signal, metric_type);
+        let files = storage_files(op, &prefix, window).await?;
+        partitions.extend(group(
+            &table_name(signal, metric_type),
+            files.into_iter(),
+            window,
+        ));
+    }
+    Ok(Json(PartitionList {
+        signal: signal.as_str(),
+        source: "storage",
+        partitions,
+    }))
+}
+
+/// Files of the partitions under `prefix` overlapping `window`, from each
+/// partition's manifest or, without one, from the listing
+async fn storage_files(
+    op: &opendal::Operator,
+    prefix: &str,
+    window: Window,
+) -> Result<Vec<PartitionFile>, AppError> {
+    let dir = format!("{}/", prefix);
+    let entries = match op.list_with(&dir).recursive(true).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == opendal::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::unavailable(e)),
+    };
+
+    // Listed Parquet files and whether a manifest was seen, per partition
+    let mut listed: BTreeMap<String, (Vec<(String, u64)>, bool)> = BTreeMap::new();
+    for entry in &entries {
+        let path = entry.path();
+        let Some((partition, name)) = path.rsplit_once('/') else {
+            continue;
+        };
+        if !partition_hour_micros(path).is_some_and(|hour| window.overlaps_hour(hour)) {
+            continue;
+        }
+        let slot = listed.entry(partition.to_string()).or_default();
+        if name == MANIFEST_FILE {
+            slot.1 = true;
+        } else if name.ends_with(".parquet") {
+            slot.0
+                .push((path.to_string(), entry.metadata().content_length()));
+        }
+    }
+
+    let mut files = Vec::new();
+    for (partition, (parquet, has_manifest)) in listed {
+        if has_manifest {
+            let manifest = read_manifest(op, &partition).await?;
+            // Files missing from the manifest (written with manifests off or
+            // before a failed update) are still reported
+            for (path, bytes) in &parquet {
+                let name = path.rsplit_once('/').map_or(path.as_str(), |(_, n)| n);
+                match manifest.get(name) {
+                    Some(entry) => files.push(PartitionFile {
+                        path: path.clone(),
+                        rows: Some(entry.rows as u64),
+                        bytes: entry.bytes as u64,
+                        min_timestamp_micros: known(entry.min_timestamp_micros),
+                        max_timestamp_micros: known(entry.max_timestamp_micros),
+                    }),
+                    None => files.push(listed_file(op, path, *bytes).await?),
+                }
+            }
+        } else {
+            for (path, bytes) in &parquet {
+                files.push(listed_file(op, path, *bytes).await?);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Manifest entries of `partition` by file name; later lines win
+async fn read_manifest(
+    op: &opendal::Operator,
+    partition: &str,
+) -> Result<BTreeMap<String, ManifestEntry>, AppError> {
+    let path = format!("{}/{}", partition, MANIFEST_FILE);
+    let content = match op.read(&path).await {
+        Ok(content) => content.to_vec(),
+        Err(e) if e.kind() == opendal::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(AppError::unavailable(e)),
+    };
+    Ok(content
+        .split(|b| *b == b'\n')
+        .filter_map(|line| serde_json::from_slice::<ManifestEntry>(line).ok())
+        .map(|entry| (entry.file.clone(), entry))
+        .collect())
+}
+
+/// A file known only from the listing; stat it when the listing had no size
+async fn listed_file(
+    op: &opendal::Operator,
+    path: &str,
+    bytes: u64,
+) -> Result<PartitionFile, AppError> {
+    let bytes = if bytes > 0 {
+        bytes
+    } else {
+        op.stat(path)
+            .await
+            .map_err(AppError::unavailable)?
+            .content_length()
+    };
+    Ok(PartitionFile {
+        path: path.to_string(),
+        rows: None,
+        bytes,
+        min_timestamp_micros: None,
+        max_timestamp_micros: None,
+    })
+}
+
+/// Group files by partition directory, keeping those overlapping `window`;
+/// oldest partition first
+fn group(
+    table: &str,
+    files: impl Iterator<Item = PartitionFile>,
+    window: Window,
+) -> Vec<Partition> {
+    let mut partitions: BTreeMap<(i64, String), Vec<PartitionFile>> = BTreeMap::new();
+    for file in files {
+        let Some(hour) = partition_hour_micros(&file.path) else {
+            continue;
+        };
+        if !window.overlaps_hour(hour) || !window.overlaps_file(&file) {
+            continue;
+        }
+        let dir = file
+            .path
+            .rsplit_once('/')
+            .map_or("", |(dir, _)| dir)
+            .to_string();
+        partitions.entry((hour, dir)).or_default().push(file);
+    }
+    partitions
+        .into_iter()
+        .map(|((hour, path), mut files)| {
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+            Partition {
+                table: table.to_string(),
+                path,
+                start: format_micros(hour),
+                rows: files.iter().map(|f| f.rows).sum(),
+                bytes: files.iter().map(|f| f.bytes).sum(),
+                files,
+            }
+        })
+        .collect()
+}
+
+/// Table directory without the storage prefix, e.g. `metrics/gauge`
+fn table_name(signal: SignalType, metric_type: Option<&str>) -> String {
+    match metric_type {
+        Some(metric_type) => format!("metrics/{}", metric_type),
+        None => signal.as_str().to_string(),
+    }
+}
+
+/// Manifests record 0 for an unknown timestamp
+fn known(micros: i64) -> Option<i64> {
+    (micros != 0).then_some(micros)
+}
+
+fn parse_time(value: Option<&str>) -> Result<Option<i64>, AppError> {
+    value
+        .map(|value| {
+            OffsetDateTime::parse(value, &Rfc3339)
+                .map(|t| (t.unix_timestamp_nanos() / 1_000) as i64)
+                .map_err(|e| {
+                    AppError::bad_request(anyhow::anyhow!("invalid time '{}': {}", value, e))
+                })
+        })
+        .transpose()
+}
+
+fn format_micros(micros: i64) -> String {
+    OffsetDateTime::from_unix_timestamp_nanos(i128::from(micros) * 1_000)
+        .ok()
+        .and_then(|t| t.format(&Rfc3339).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_prunes_by_partition_hour_and_file_range() {
+        let hour = 1_736_935_200_000_000; // 2025-01-15T10:00:00Z
+        let file = |path: &str, rows, min_ts: Option<i64>| PartitionFile {
+            path: path.to_string(),
+            rows,
+            bytes: 100,
+            min_timestamp_micros: min_ts,
+            max_timestamp_micros: min_ts.map(|ts| ts + 1_000_000),
+        };
+        let files = vec![
+            file(
+                "logs/web/year=2025/month=01/day=15/hour=10/b.parquet",
+                Some(5),
+                Some(hour + 60_000_000),
+            ),
+            file(
+                "logs/web/year=2025/month=01/day=15/hour=10/a.parquet",
+                Some(3),
+                Some(hour),
+            ),
+            // Before `from` within the same hour
+            file(
+                "logs/web/year=2025/month=01/day=15/hour=10/c.parquet",
+                Some(7),
+                Some(hour - 10_000_000),
+            ),
+            file(
+                "logs/api/year=2025/month=01/day=15/hour=10/d.parquet",
+                None,
+                None,
+            ),
+            // Hour outside the window
+            file(
+                "logs/web/year=2025/month=01/day=15/hour=12/e.parquet",
+                Some(1),
+                None,
+            ),
+            file("logs/web/not-partitioned.parquet", Some(1), None),
+        ];
+        let window = Window {
+            from: hour,
+            to: hour + MICROS_PER_HOUR,
+        };
+        let partitions = group("logs", files.into_iter(), window);
+        assert_eq!(partitions.len(), 2);
+
+        let api = &partitions[0];
+        assert_eq!(api.path, "logs/api/year=2025/month=01/day=15/hour=10");
+        assert_eq!(api.start, "2025-01-15T10:00:00Z");
+        assert_eq!(api.rows, None);
+
+        let web = &partitions[1];
+        assert_eq!(web.table, "logs");
+        assert_eq!(web.rows, Some(8));
+        assert_eq!(web.bytes, 200);
+        let names: Vec<_> = web.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "logs/web/year=2025/month=01/day=15/hour=10/a.parquet",
+                "logs/web/year=2025/month=01/day=15/hour=10/b.parquet",
+            ]
+        );
+        assert_eq!(known(0), None);
+    }
+}
@@ -0,0 +1,232 @@
+// End-to-end latency probe
+//
+// Periodically injects a synthetic log record for a dedicated service through
+// the normal ingestion path, with the probe id as its request id, then polls
+// the record's hour partition until a file listing that request id in its
+// footer appears. Matching the id keeps a late file from an earlier probe from
+// being credited to the current one. The elapsed time (batching + encoding +
+// upload) is exported as the `ingest_to_queryable_seconds` gauge.
+
+use crate::clock::now_unix_nanos;
+use crate::config::ProbeConfig;
+use crate::{AppState, InputFormat, SignalType};
+use axum::body::Bytes;
+use axum::http::{HeaderMap, HeaderValue};
+use metrics::{counter, gauge};
+use parquet::file::metadata::ParquetMetaDataReader;
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// Service name carried by probe records; keeps them in their own partitions
+const PROBE_SERVICE_NAME: &str = "otlp2parquet-probe";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Run probes until `shutdown` is set.
+pub(crate) async fn run_probe(state: AppState, shutdown: Arc<AtomicBool>, config: ProbeConfig) {
+    let interval = Duration::from_secs(config.interval_secs);
+    let timeout = Duration::from_secs(config.timeout_secs);
+    info!(
+        "Latency probe started (interval={}s timeout={}s)",
+        config.interval_secs, config.timeout_secs
+    );
+
+    while !shutdown.load(Ordering::SeqCst) {
+        tokio::time::sleep(interval).await;
+
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let probe_id = uuid::Uuid::new_v4().to_string();
+        let start = Instant::now();
+        let time_unix_nano = now_unix_nanos();
+
+        if let Err(e) = inject(&state, &probe_id, time_unix_nano).await {
+            counter!("otlp.probe.errors").increment(1);
+            warn!(probe_id = %probe_id, error = %e, "Failed to inject probe record");
+            continue;
+        }
+
+        let partition = crate::writer::partition_dir(
+            &crate::writer::table_prefix(SignalType::Logs, None),
+            Some(PROBE_SERVICE_NAME),
+            (time_unix_nano / 1_000) as i64,
+        );
+        match wait_until_visible(&probe_id, &partition, start, timeout, &shutdown).await {
+            Some(elapsed) => {
+                gauge!("ingest_to_queryable_seconds").set(elapsed.as_secs_f64());
+                debug!(
+                    probe_id = %probe_id,
+                    elapsed_ms = elapsed.as_millis() as u64,
+                    "Probe record visible in storage"
+                );
+            }
+            None if shutdown.load(Ordering::SeqCst) => break,
+            None => {
+                counter!("otlp.probe.timeouts").increment(1);
+                warn!(
+                    probe_id = %probe_id,
+                    timeout_secs = config.timeout_secs,
+                    "Probe record not visible in storage before timeout"
+                );
+            }
+        }
+    }
+
+    debug!("Latency probe stopped");
+}
+
+/// Push one marked log record through the regular logs pipeline, with
+/// `probe_id` as the request id recorded in the file it lands in.
+async fn inject(state: &AppState, probe_id: &str, time_unix_nano: u128) -> anyhow::Result<()> {
+    let body = probe_payload(probe_id, time_unix_nano);
+    let mut headers = HeaderMap::new();
+    headers.insert(crate::request_id::HEADER, HeaderValue::from_str(probe_id)?);
+    crate::handlers::process_logs(state, InputFormat::Json, body.into(), &headers)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.into_error())
+}
+
+/// Poll `partition` until a file listing `probe_id` among its request ids
+/// shows up.
+async fn wait_until_visible(
+    probe_id: &str,
+    partition: &str,
+    start: Instant,
+    timeout: Duration,
+    shutdown: &AtomicBool,
+) -> Option<Duration> {
+    // Files already read and found to hold other probes' records
+    let mut checked = HashSet::new();
+    while start.elapsed() < timeout && !shutdown.load(Ordering::SeqCst) {
+        match find_probe_file(probe_id, partition, &mut checked).await {
+            Ok(true) => return Some(start.elapsed()),
+            Ok(false) => {}
+            Err(e) => debug!(error = %e, "Probe storage poll failed"),
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    None
+}
+
+/// Whether a file in `partition` not yet in `checked` carries `probe_id`.
+async fn find_probe_file(
+    probe_id: &str,
+    partition: &str,
+    checked: &mut HashSet<String>,
+) -> anyhow::Result<bool> {
+    let op =
+        crate::writer::get_operator().ok_or_else(|| anyhow::anyhow!("storage not initialized"))?;
+    // Recursive for the `shard=NN/` directories
+    let entries = match op
+        .list_with(&format!("{}/", partition))
+        .recursive(true)
+        .await
+    {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == opendal::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry.path();
+        if !path.ends_with(".parquet") || checked.contains(path) {
+            continue;
+        }
+        let bytes = op.read(path).await?.to_bytes();
+        if has_request_id(&bytes, probe_id)? {
+            return Ok(true);
+        }
+        checked.insert(path.to_string());
+    }
+    Ok(false)
+}
+
+/// Whether the footer of Parquet file `bytes` lists `request_id`.
+fn has_request_id(bytes: &Bytes, request_id: &str) -> anyhow::Result<bool> {
+    let metadata = ParquetMetaDataReader::new().parse_and_finish(bytes)?;
+    Ok(metadata
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|kv| kv.iter().find(|e| e.key == crate::request_id::METADATA_KEY))
+        .and_then(|e| e.value.as_deref())
+        .is_some_and(|ids| ids.split(',').any(|id| id == request_id)))
+}
+
+fn probe_payload(probe_id: &str, time_unix_nano: u128) -> Vec<u8> {
+    json!({
+        "resourceLogs": [{
+            "resource": {
+                "attributes": [{
+                    "key": "service.name",
+                    "value": {"stringValue": PROBE_SERVICE_NAME}
+                }]
+            },
+            "scopeLogs": [{
+                "scope": {"name": "otlp2parquet.probe"},
+                "logRecords": [{
+                    "timeUnixNano": time_unix_nano.to_string(),
+                    "severityNumber": 9,
+                    "severityText": "INFO",
+                    "body": {"stringValue": "otlp2parquet latency probe"},
+                    "attributes": [{
+                        "key": "otlp2parquet.probe.id",
+                        "value": {"stringValue": probe_id}
+                    }]
+                }]
+            }]
+        }]
+    })
+    .to_string()
+    .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::decode_logs_partitioned;
+
+    #[test]
+    fn test_probe_payload_decodes_to_probe_service() {
+        let payload = probe_payload("abc", 1_700_000_000_000_000_000);
+        let grouped = decode_logs_partitioned(&payload, InputFormat::Json).unwrap();
+
+        assert_eq!(grouped.total_records, 1);
+        assert_eq!(grouped.batches.len(), 1);
+        assert_eq!(grouped.batches[0].service_name.as_ref(), PROBE_SERVICE_NAME);
+    }
+
+    #[test]
+    fn test_probe_file_matched_by_request_id() {
+        use arrow::array::{Int64Array, RecordBatch};
+        use parquet::arrow::ArrowWriter;
+        use parquet::file::metadata::KeyValue;
+        use parquet::file::properties::WriterProperties;
+
+        let batch = RecordBatch::try_from_iter([(
+            "severity_number",
+            Arc::new(Int64Array::from(vec![9])) as _,
+        )])
+        .unwrap();
+        let props = WriterProperties::builder()
+            .set_key_value_metadata(Some(vec![KeyValue::new(
+                crate::request_id::METADATA_KEY.to_string(),
+                "probe-1,probe-2".to_string(),
+            )]))
+            .build();
+        let mut file = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut file, batch.schema(), Some(props)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let file = Bytes::from(file);
+        assert!(has_request_id(&file, "probe-2").unwrap());
+        assert!(!has_request_id(&file, "probe-3").unwrap());
+        assert!(!has_request_id(&file, "probe").unwrap());
+    }
+}
@@ -0,0 +1,67 @@
+// Request ids
+//
+// Every ingest request gets an id: the caller's `X-Request-Id` when it is a
+// usable token, otherwise a generated UUID. The id is echoed in the response,
+// carried through the batcher, and the ids of all requests contributing to a
+// file are stored in its Parquet key-value metadata under
+// `otlp2parquet.request_ids` (comma separated) and in flush logs, so any
+// stored row can be traced back to the HTTP request that delivered it.
+
+use axum::http::{HeaderMap, HeaderValue};
+
+pub(crate) const HEADER: &str = "x-request-id";
+
+/// Parquet key-value metadata key listing contributing request ids
+pub(crate) const METADATA_KEY: &str = "otlp2parquet.request_ids";
+
+/// Ids recorded per file; a batch built from more requests keeps the first ones
+pub(crate) const MAX_PER_FILE: usize = 256;
+
+/// Longest caller-supplied id that is honored
+const MAX_LEN: usize = 128;
+
+/// The request's id, generating one (and setting the header) if the caller
+/// sent none or an unusable one.
+pub(crate) fn ensure(headers: &mut HeaderMap) -> String {
+    if let Some(id) = from_headers(headers) {
+        return id.to_string();
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        headers.insert(HEADER, value);
+    }
+    id
+}
+
+/// A usable id from the `X-Request-Id` header: 1-128 characters of
+/// letters, digits and `-_.:/`, so it is safe in logs and comma lists.
+pub(crate) fn from_headers(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_LEN
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.:/".contains(c))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_id_honored_or_generated() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER, HeaderValue::from_static(" collector-7:42 "));
+        assert_eq!(ensure(&mut headers), "collector-7:42");
+
+        headers.insert(HEADER, HeaderValue::from_static("a,b"));
+        let generated = ensure(&mut headers);
+        assert_eq!(generated.len(), 36);
+        assert_eq!(from_headers(&headers), Some(generated.as_str()));
+    }
+}
@@ -0,0 +1,176 @@
+// Per-route and per-service request metrics
+//
+// Every ingestion request records its latency (by route, signal and status
+// class) and payload size (by route and signal). Successful requests also
+// record latency and record counts per service they carried, for autoscaling
+// and capacity planning per producer, and flushed batches record their flush
+// lag per service. Service labels are bounded by
+// `request.metrics_max_services`: the first services seen get their own label,
+// later ones share `_other`, so a misbehaving fleet cannot explode the metrics
+// backend.
+
+use crate::codec::ServiceGroupedBatches;
+use crate::{AppError, SignalType};
+use axum::http::StatusCode;
+use axum::response::Response;
+use metrics::histogram;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Label shared by values beyond the limit
+const OTHER_SERVICE: &str = "_other";
+
+/// Values of one metric label, bounded: the first `max` values seen keep
+/// their own label, later ones share `_other`
+pub(crate) struct BoundedLabels {
+    max: usize,
+    seen: Mutex<HashSet<Arc<str>>>,
+}
+
+impl BoundedLabels {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            max,
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// `value` if it is (or can become) one of the labelled values, `_other`
+    /// otherwise
+    pub(crate) fn label(&self, value: &str) -> Arc<str> {
+        let mut seen = self.seen.lock();
+        if let Some(label) = seen.get(value) {
+            return label.clone();
+        }
+        if seen.len() < self.max {
+            let label: Arc<str> = Arc::from(value);
+            seen.insert(label.clone());
+            return label;
+        }
+        Arc::from(OTHER_SERVICE)
+    }
+}
+
+/// Records per service in one ingestion request, attached to the response so
+/// the route-level timing can break the latency down per service
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IngestedServices(BTreeMap<Arc<str>, usize>);
+
+impl IngestedServices {
+    pub(crate) fn of<'a>(groups: impl IntoIterator<Item = &'a ServiceGroupedBatches>) -> Self {
+        let mut services = BTreeMap::new();
+        for pb in groups.into_iter().flat_map(|g| &g.batches) {
+            *services.entry(pb.service_name.clone()).or_default() += pb.record_count;
+        }
+        Self(services)
+    }
+}
+
+pub(crate) struct RequestMetrics {
+    services: BoundedLabels,
+}
+
+impl RequestMetrics {
+    pub(crate) fn new(max_services: usize) -> Self {
+        Self {
+            services: BoundedLabels::new(max_services),
+        }
+    }
+
+    /// Record one finished ingestion request of `bytes` bytes.
+    pub(crate) fn record(
+        &self,
+        signal: SignalType,
+        result: &Result<Response, AppError>,
+        bytes: usize,
+        elapsed: Duration,
+    ) {
+        let route = route(signal);
+        let status = match result {
+            Ok(response) => response.status(),
+            Err(e) => e.status,
+        };
+        let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+        histogram!(
+            "otlp.http.request_ms",
+            "route" => route,
+            "signal" => signal.as_str(),
+            "status" => status_class(status)
+        )
+        .record(elapsed_ms);
+        histogram!("otlp.http.request_bytes", "route" => route, "signal" => signal.as_str())
+            .record(bytes as f64);
+
+        let Some(services) = result
+            .as_ref()
+            .ok()
+            .and_then(|r| r.extensions().get::<IngestedServices>())
+        else {
+            return;
+        };
+        for (service, records) in &services.0 {
+            let service = self.services.label(service);
+            histogram!(
+                "otlp.ingest.service_ms",
+                "signal" => signal.as_str(),
+                "service" => service.to_string()
+            )
+            .record(elapsed_ms);
+            histogram!(
+                "otlp.ingest.service_records",
+                "signal" => signal.as_str(),
+                "service" => service.to_string()
+            )
+            .record(*records as f64);
+        }
+    }
+
+    /// Record the time from first buffered request to durable write of one
+    /// flushed batch, under the same bounded service labels.
+    pub(crate) fn record_flush_lag(&self, signal: SignalType, service: &Arc<str>, lag: Duration) {
+        histogram!(
+            "otlp.batch.flush_lag_seconds",
+            "signal" => signal.as_str(),
+            "service" => self.services.label(service).to_string()
+        )
+        .record(lag.as_secs_f64());
+    }
+}
+
+fn route(signal: SignalType) -> &'static str {
+    match signal {
+        SignalType::Logs => "/v1/logs",
+        SignalType::Traces => "/v1/traces",
+        SignalType::Metrics => "/v1/metrics",
+    }
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        200..=299 => "2xx",
+        400..=499 => "4xx",
+        500..=599 => "5xx",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_labels_are_bounded() {
+        let services = BoundedLabels::new(2);
+        let label = |s: &str| services.label(s).to_string();
+        assert_eq!(label("checkout"), "checkout");
+        assert_eq!(label("cart"), "cart");
+        assert_eq!(label("search"), OTHER_SERVICE);
+        // Services labelled before the limit keep their label
+        assert_eq!(label("checkout"), "checkout");
+
+        let unlabelled = BoundedLabels::new(0);
+        assert_eq!(&*unlabelled.label("checkout"), OTHER_SERVICE);
+    }
+}
@@ -0,0 +1,117 @@
+// Background retry queues
+//
+// Shared plumbing of the best-effort sinks fed from the write path (ClickHouse,
+// webhook notifications, the mirror, replication): work is queued without
+// blocking the caller, a background task retries failures with exponential
+// backoff, and shutdown waits a bounded time for the queue to empty.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+
+/// Sending half of a queue started with [`spawn_worker`]
+pub(crate) struct RetryQueue<T> {
+    tx: mpsc::Sender<T>,
+    /// Items queued or in flight
+    pending: Arc<AtomicUsize>,
+}
+
+impl<T> RetryQueue<T> {
+    /// Queue `item` without waiting; a full or closed queue hands it back.
+    pub(crate) fn try_push(&self, item: T) -> Result<(), mpsc::error::TrySendError<T>> {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        self.tx.try_send(item).inspect_err(|_| {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+        })
+    }
+
+    /// Wait up to `timeout` for queued items to be handled. Returns how many
+    /// are left.
+    pub(crate) async fn drain(&self, timeout: Duration) -> usize {
+        drain(timeout, || self.pending.load(Ordering::SeqCst)).await
+    }
+}
+
+/// Start a task handing each queued item to `handle`, at most `concurrency`
+/// at a time (in queue order for 1). `handle` does its own retrying.
+pub(crate) fn spawn_worker<T, F, Fut>(
+    capacity: usize,
+    concurrency: usize,
+    handle: F,
+) -> RetryQueue<T>
+where
+    T: Send + 'static,
+    F: Fn(T) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::channel(capacity);
+    let pending = Arc::new(AtomicUsize::new(0));
+    let slots = Arc::new(Semaphore::new(concurrency.max(1)));
+    let in_flight = Arc::clone(&pending);
+    tokio::spawn(async move {
+        while let Some(item) = rx.recv().await {
+            let Ok(permit) = Arc::clone(&slots).acquire_owned().await else {
+                return;
+            };
+            let work = handle(item);
+            let in_flight = Arc::clone(&in_flight);
+            tokio::spawn(async move {
+                work.await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                drop(permit);
+            });
+        }
+    });
+    RetryQueue { tx, pending }
+}
+
+/// Poll `pending` until it reaches zero or `timeout` passes. Returns the
+/// last count.
+pub(crate) async fn drain(timeout: Duration, pending: impl Fn() -> usize) -> usize {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let left = pending();
+        if left == 0 || tokio::time::Instant::now() >= deadline {
+            return left;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// 1s, 2s, 4s, ... capped at `max`
+pub(crate) fn backoff(attempt: u32, max: Duration) -> Duration {
+    Duration::from_secs(1u64 << attempt.saturating_sub(1).min(16)).min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let max = Duration::from_secs(30);
+        assert_eq!(backoff(1, max), Duration::from_secs(1));
+        assert_eq!(backoff(3, max), Duration::from_secs(4));
+        assert_eq!(backoff(10, max), max);
+        assert_eq!(backoff(u32::MAX, max), max);
+    }
+
+    #[tokio::test]
+    async fn test_worker_handles_queued_items() {
+        let handled = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&handled);
+        let queue = spawn_worker(8, 1, move |n: usize| {
+            let counter = Arc::clone(&counter);
+            async move {
+                counter.fetch_add(n, Ordering::SeqCst);
+            }
+        });
+        for n in 1..=3 {
+            queue.try_push(n).unwrap();
+        }
+        assert_eq!(queue.drain(Duration::from_secs(5)).await, 0);
+        assert_eq!(handled.load(Ordering::SeqCst), 6);
+    }
+}
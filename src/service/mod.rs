@@ -0,0 +1,306 @@
+//! Install-service command - generates OS service definitions for server mode
+
+#[cfg(windows)]
+pub mod windows;
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use std::path::{Path, PathBuf};
+
+const DEFAULT_SERVICE_NAME: &str = "otlp2parquet";
+
+/// Service manager to generate a definition for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ServiceTarget {
+    /// Linux systemd unit file
+    Systemd,
+    /// macOS launchd property list
+    Launchd,
+    /// Windows service registration script (PowerShell)
+    Windows,
+}
+
+impl ServiceTarget {
+    /// Service manager native to the host this binary was built for.
+    fn host_default() -> Self {
+        if cfg!(target_os = "macos") {
+            ServiceTarget::Launchd
+        } else if cfg!(windows) {
+            ServiceTarget::Windows
+        } else {
+            ServiceTarget::Systemd
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct InstallServiceArgs {
+    /// Service manager: systemd, launchd, or windows (default: host OS)
+    #[arg(long, value_enum)]
+    pub target: Option<ServiceTarget>,
+
+    /// Service name (launchd label on macOS)
+    #[arg(long, default_value = DEFAULT_SERVICE_NAME)]
+    pub name: String,
+
+    /// Path to the otlp2parquet binary (default: this executable)
+    #[arg(long, value_name = "PATH")]
+    pub binary: Option<PathBuf>,
+
+    /// User account to run the service as (systemd only)
+    #[arg(long)]
+    pub user: Option<String>,
+}
+
+/// Resolved inputs shared by every service template
+struct ServiceSpec {
+    name: String,
+    binary: String,
+    config: Option<String>,
+    user: Option<String>,
+}
+
+impl InstallServiceArgs {
+    /// Print the service definition for the selected target to stdout.
+    ///
+    /// `config` is the global `--config` flag; it is made absolute because
+    /// service managers do not start processes in the caller's directory.
+    pub fn run(self, config: Option<&Path>) -> Result<()> {
+        let binary = match self.binary {
+            Some(path) => path,
+            None => std::env::current_exe().context("Failed to resolve current executable")?,
+        };
+        let config = config
+            .map(|path| {
+                std::path::absolute(path)
+                    .with_context(|| format!("Failed to resolve config path {}", path.display()))
+            })
+            .transpose()?;
+
+        let spec = ServiceSpec {
+            name: self.name,
+            binary: binary.to_string_lossy().to_string(),
+            config: config.map(|p| p.to_string_lossy().to_string()),
+            user: self.user,
+        };
+
+        let output = match self.target.unwrap_or_else(ServiceTarget::host_default) {
+            ServiceTarget::Systemd => generate_systemd_unit(&spec),
+            ServiceTarget::Launchd => generate_launchd_plist(&spec),
+            ServiceTarget::Windows => generate_windows_script(&spec),
+        };
+        println!("{}", output);
+
+        Ok(())
+    }
+}
+
+fn generate_systemd_unit(spec: &ServiceSpec) -> String {
+    let config_arg = spec
+        .config
+        .as_deref()
+        .map(|c| format!(" --config {}", systemd_quote(c)))
+        .unwrap_or_default();
+    let user_line = spec
+        .user
+        .as_deref()
+        .map(|u| format!("User={}\n", u))
+        .unwrap_or_default();
+
+    format!(
+        r#"# systemd unit for otlp2parquet
+# Save as /etc/systemd/system/{name}.service and run:
+#   sudo systemctl daemon-reload
+#   sudo systemctl enable --now {name}
+
+[Unit]
+Description=otlp2parquet OTLP to Parquet server
+Documentation=https://github.com/smithclay/otlp2parquet
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+Type=simple
+ExecStart={binary}{config_arg} serve
+{user_line}Restart=on-failure
+RestartSec=5
+# SIGTERM triggers a graceful shutdown that flushes buffered batches
+KillSignal=SIGTERM
+TimeoutStopSec=60
+NoNewPrivileges=true
+
+[Install]
+WantedBy=multi-user.target
+"#,
+        name = spec.name,
+        binary = systemd_quote(&spec.binary),
+        config_arg = config_arg,
+        user_line = user_line,
+    )
+}
+
+fn generate_launchd_plist(spec: &ServiceSpec) -> String {
+    let config_args = spec
+        .config
+        .as_deref()
+        .map(|c| {
+            format!(
+                "\n        <string>--config</string>\n        <string>{}</string>",
+                xml_escape(c)
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- launchd agent for otlp2parquet
+     Save as ~/Library/LaunchAgents/{name}.plist and run:
+       launchctl load -w ~/Library/LaunchAgents/{name}.plist -->
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{name}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{binary}</string>{config_args}
+        <string>serve</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ExitTimeOut</key>
+    <integer>60</integer>
+    <key>StandardOutPath</key>
+    <string>/tmp/{name}.log</string>
+    <key>StandardErrorPath</key>
+    <string>/tmp/{name}.log</string>
+</dict>
+</plist>"#,
+        name = xml_escape(&spec.name),
+        binary = xml_escape(&spec.binary),
+        config_args = config_args,
+    )
+}
+
+fn generate_windows_script(spec: &ServiceSpec) -> String {
+    // The SCM launches the binary with these arguments; `windows-service`
+    // makes it register with the service control dispatcher instead of
+    // waiting for Ctrl+C.
+    let config_arg = spec
+        .config
+        .as_deref()
+        .map(|c| format!(" --config \"{}\"", c))
+        .unwrap_or_default();
+    let bin_path = format!("\"{}\"{} windows-service", spec.binary, config_arg);
+
+    format!(
+        r#"# Windows service registration for otlp2parquet
+# Run in an elevated PowerShell session:
+
+New-Service -Name '{name}' `
+  -BinaryPathName '{bin_path}' `
+  -DisplayName 'otlp2parquet' `
+  -Description 'OTLP HTTP server writing Parquet files to object storage' `
+  -StartupType Automatic
+sc.exe failure '{name}' reset= 86400 actions= restart/5000
+# Event source for service log output (Application log)
+New-EventLog -LogName Application -Source 'otlp2parquet' -ErrorAction SilentlyContinue
+Start-Service -Name '{name}'
+
+# Remove with:
+#   Stop-Service -Name '{name}'; sc.exe delete '{name}'
+"#,
+        name = spec.name.replace('\'', "''"),
+        bin_path = bin_path.replace('\'', "''"),
+    )
+}
+
+/// Quote a unit-file argument so spaces don't split it and systemd doesn't
+/// expand `%` specifiers or `$` variables inside it.
+fn systemd_quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(config: Option<&str>, user: Option<&str>) -> ServiceSpec {
+        ServiceSpec {
+            name: "otlp2parquet".to_string(),
+            binary: "/usr/local/bin/otlp2parquet".to_string(),
+            config: config.map(str::to_string),
+            user: user.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_generate_systemd_unit() {
+        let unit = generate_systemd_unit(&spec(Some("/etc/otlp2parquet.toml"), Some("otel")));
+        assert!(unit.contains(
+            r#"ExecStart="/usr/local/bin/otlp2parquet" --config "/etc/otlp2parquet.toml" serve"#
+        ));
+        assert!(unit.contains("User=otel"));
+        assert!(unit.contains("WantedBy=multi-user.target"));
+        assert!(unit.contains("/etc/systemd/system/otlp2parquet.service"));
+    }
+
+    #[test]
+    fn test_generate_systemd_unit_without_config() {
+        let unit = generate_systemd_unit(&spec(None, None));
+        assert!(unit.contains(r#"ExecStart="/usr/local/bin/otlp2parquet" serve"#));
+        assert!(!unit.contains("User="));
+    }
+
+    #[test]
+    fn test_generate_systemd_unit_quotes_paths() {
+        let mut spec = spec(Some("/etc/otlp2parquet/my config.toml"), None);
+        spec.binary = "/opt/otel tools/otlp2parquet".to_string();
+        let unit = generate_systemd_unit(&spec);
+        assert!(unit.contains(
+            r#"ExecStart="/opt/otel tools/otlp2parquet" --config "/etc/otlp2parquet/my config.toml" serve"#
+        ));
+        assert_eq!(systemd_quote(r#"a"b\c%d$e"#), r#""a\"b\\c%%d$$e""#);
+    }
+
+    #[test]
+    fn test_generate_launchd_plist() {
+        let plist = generate_launchd_plist(&spec(Some("/Users/me/a&b.toml"), None));
+        assert!(plist.contains("<string>otlp2parquet</string>"));
+        assert!(plist.contains("<string>--config</string>"));
+        assert!(plist.contains("<string>/Users/me/a&amp;b.toml</string>"));
+        assert!(plist.contains("<key>RunAtLoad</key>"));
+        assert!(plist.contains("~/Library/LaunchAgents/otlp2parquet.plist"));
+    }
+
+    #[test]
+    fn test_generate_windows_script() {
+        let mut spec = spec(Some(r"C:\otlp2parquet\config.toml"), None);
+        spec.binary = r"C:\otlp2parquet\otlp2parquet.exe".to_string();
+        let script = generate_windows_script(&spec);
+        assert!(script.contains(
+            r#"-BinaryPathName '"C:\otlp2parquet\otlp2parquet.exe" --config "C:\otlp2parquet\config.toml" windows-service'"#
+        ));
+        assert!(script.contains("-StartupType Automatic"));
+        assert!(script.contains("New-EventLog -LogName Application"));
+        assert!(script.contains("sc.exe delete 'otlp2parquet'"));
+    }
+}
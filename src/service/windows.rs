@@ -0,0 +1,202 @@
+//! Windows service host: service control handler and Event Log output
+//!
+//! `otlp2parquet windows-service` is the entry point registered with the
+//! Service Control Manager by `install-service --target windows`. Stop and
+//! shutdown requests trigger the same graceful shutdown as SIGTERM, and
+//! tracing output goes to the Application event log since services have no
+//! console.
+
+use crate::config::RuntimeConfig;
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context as LayerContext, Layer};
+use windows_service::service::{
+    ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::{define_windows_service, service_dispatcher};
+use windows_sys::Win32::System::EventLog::{
+    RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE, EVENTLOG_INFORMATION_TYPE,
+    EVENTLOG_WARNING_TYPE,
+};
+
+/// Event source and SCM name; OWN_PROCESS services ignore the name passed
+/// to the dispatcher, so this only needs to match the Event Log source.
+const SERVICE_NAME: &str = "otlp2parquet";
+
+/// Config resolved by the CLI before handing control to the dispatcher
+static SERVICE_CONFIG: OnceCell<RuntimeConfig> = OnceCell::new();
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Hand the process to the Service Control Manager and run the server
+/// until the service is stopped. Blocks until the service exits.
+pub fn run(config: RuntimeConfig) -> Result<()> {
+    SERVICE_CONFIG
+        .set(config)
+        .map_err(|_| anyhow::anyhow!("Windows service already started"))?;
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+        .context("Failed to connect to the Service Control Manager")
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        tracing::error!("Windows service failed: {:#}", e);
+    }
+}
+
+fn run_service() -> Result<()> {
+    let config = SERVICE_CONFIG
+        .get()
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("service config not initialized"))?;
+
+    let stop = Arc::new(Notify::new());
+    let handler_stop = Arc::clone(&stop);
+    let status_handle =
+        service_control_handler::register(SERVICE_NAME, move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                handler_stop.notify_one();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        })
+        .context("Failed to register service control handler")?;
+
+    let set_state = |state: ServiceState, exit_code: u32, wait_hint: Duration| {
+        let controls_accepted = if state == ServiceState::Running {
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+        } else {
+            ServiceControlAccept::empty()
+        };
+        status_handle.set_service_status(ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted,
+            exit_code: ServiceExitCode::Win32(exit_code),
+            checkpoint: 0,
+            wait_hint,
+            process_id: None,
+        })
+    };
+
+    set_state(ServiceState::Running, 0, Duration::default())
+        .context("Failed to report service status")?;
+
+    let result = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("Failed to build tokio runtime")
+        .and_then(|runtime| {
+            runtime.block_on(crate::run_with_shutdown(config, async move {
+                stop.notified().await;
+                tracing::info!("Received service stop request, starting graceful shutdown...");
+            }))
+        });
+
+    // Non-zero exit code lets the SCM recovery actions restart the service
+    let exit_code = if result.is_ok() { 0 } else { 1 };
+    set_state(ServiceState::Stopped, exit_code, Duration::default())
+        .context("Failed to report service status")?;
+
+    result
+}
+
+/// Install a global subscriber that writes to the Application event log.
+///
+/// Call before loading config: `init_tracing` is a no-op once a global
+/// subscriber is set, so the console subscriber never takes over.
+pub fn init_event_log(log_level: &str) {
+    use tracing_subscriber::{prelude::*, EnvFilter};
+
+    let env_filter = EnvFilter::try_new(log_level).unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let Some(layer) = EventLogLayer::register(SERVICE_NAME) else {
+        return;
+    };
+    let _ = tracing::subscriber::set_global_default(
+        tracing_subscriber::registry().with(env_filter).with(layer),
+    );
+}
+
+/// Tracing layer reporting events through `ReportEventW`
+struct EventLogLayer {
+    source: EventSource,
+}
+
+/// Event source handle; the Event Log API is safe to call from any thread.
+struct EventSource(windows_sys::Win32::Foundation::HANDLE);
+
+unsafe impl Send for EventSource {}
+unsafe impl Sync for EventSource {}
+
+impl EventLogLayer {
+    fn register(source: &str) -> Option<Self> {
+        let name = to_wide(source);
+        // SAFETY: `name` is a valid NUL-terminated UTF-16 string
+        let handle = unsafe { RegisterEventSourceW(std::ptr::null(), name.as_ptr()) };
+        if handle.is_null() {
+            return None;
+        }
+        Some(Self {
+            source: EventSource(handle),
+        })
+    }
+}
+
+impl<S: Subscriber> Layer<S> for EventLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: LayerContext<'_, S>) {
+        let event_type = match *event.metadata().level() {
+            Level::ERROR => EVENTLOG_ERROR_TYPE,
+            Level::WARN => EVENTLOG_WARNING_TYPE,
+            _ => EVENTLOG_INFORMATION_TYPE,
+        };
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let message = to_wide(&format!("{}: {}", event.metadata().target(), visitor.0));
+        let strings = [message.as_ptr()];
+
+        // SAFETY: handle came from RegisterEventSourceW and `strings` points
+        // at one valid NUL-terminated UTF-16 string for the duration of the call
+        unsafe {
+            ReportEventW(
+                self.source.0,
+                event_type,
+                0,
+                0,
+                std::ptr::null_mut(),
+                strings.len() as u16,
+                0,
+                strings.as_ptr(),
+                std::ptr::null(),
+            );
+        }
+    }
+}
+
+/// Formats an event as `message key=value ...`
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+fn to_wide(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
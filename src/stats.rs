@@ -0,0 +1,427 @@
+//! Stats command - summarizes what the server has written to storage
+//!
+//! Lists the Parquet files of the last few days and reports daily volumes per
+//! table and service, file-size distribution, small-file counts and
+//! compression ratios (from the file footers): the numbers needed to tune
+//! `[batch]` and compaction. `--anonymize` replaces service names with stable
+//! hashes so the report can be shared.
+
+use crate::config::RuntimeConfig;
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use parquet::file::metadata::ParquetMetaDataReader;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use time::{Date, Month, OffsetDateTime};
+
+/// Table directories below the storage prefix
+const TABLES: [&str; 6] = [
+    "logs",
+    "traces",
+    "metrics/gauge",
+    "metrics/sum",
+    "metrics/histogram",
+    "metrics/exponential_histogram",
+];
+
+/// Upper bounds (exclusive) of the file-size buckets
+const SIZE_BUCKETS: [(u64, &str); 5] = [
+    (1 << 20, "<1MiB"),
+    (8 << 20, "1-8MiB"),
+    (64 << 20, "8-64MiB"),
+    (256 << 20, "64-256MiB"),
+    (u64::MAX, ">=256MiB"),
+];
+
+/// Footers read concurrently
+const FOOTER_READS_IN_FLIGHT: usize = 16;
+
+/// Parquet footer: 4-byte metadata length + `PAR1`
+const FOOTER_LEN: u64 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StatsFormat {
+    Table,
+    Json,
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// Days to report, counting back from today (UTC)
+    #[arg(long, default_value_t = 7)]
+    pub days: u32,
+
+    /// Files smaller than this count as small files
+    #[arg(long, value_name = "BYTES", default_value_t = 8 << 20)]
+    pub small_file_bytes: u64,
+
+    /// Replace service names with stable hashes
+    #[arg(long)]
+    pub anonymize: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = StatsFormat::Table)]
+    pub format: StatsFormat,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub since: String,
+    pub small_file_bytes: u64,
+    pub tables: Vec<TableStats>,
+    pub daily: Vec<DailyVolume>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TableStats {
+    pub table: String,
+    pub files: usize,
+    pub rows: u64,
+    pub bytes: u64,
+    /// Uncompressed size of the column data
+    pub uncompressed_bytes: u64,
+    /// `uncompressed_bytes / bytes` (0 without files)
+    pub compression_ratio: f64,
+    pub median_file_bytes: u64,
+    pub small_files: usize,
+    /// Files per size bucket, smallest first
+    pub size_distribution: Vec<(&'static str, usize)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyVolume {
+    pub date: String,
+    pub table: String,
+    pub service: String,
+    pub files: usize,
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+/// One Parquet file in the window
+struct FileStats {
+    table: &'static str,
+    service: String,
+    date: Date,
+    bytes: u64,
+    rows: u64,
+    uncompressed_bytes: u64,
+}
+
+impl StatsArgs {
+    pub async fn run(&self, config: &RuntimeConfig) -> Result<()> {
+        if self.days == 0 {
+            bail!("--days must be at least 1");
+        }
+        let report = self.collect(config).await?;
+        match self.format {
+            StatsFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            StatsFormat::Table => print_report(&report),
+        }
+        Ok(())
+    }
+
+    async fn collect(&self, config: &RuntimeConfig) -> Result<Report> {
+        let op = crate::writer::build_operator(&config.storage)?;
+        let prefix = crate::writer::storage_prefix(&config.storage).unwrap_or_default();
+        let today = OffsetDateTime::now_utc().date();
+        let since = today - time::Duration::days(i64::from(self.days) - 1);
+
+        let mut files = Vec::new();
+        for table in TABLES {
+            let dir = format!("{}{}/", prefix, table);
+            let entries = match op.list_with(&dir).recursive(true).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == opendal::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e).with_context(|| format!("Failed to list {}", dir)),
+            };
+            let mut candidates = Vec::new();
+            for entry in entries {
+                let path = entry.path();
+                if !path.ends_with(".parquet") {
+                    continue;
+                }
+                let Some(date) = partition_date(path).filter(|date| *date >= since) else {
+                    continue;
+                };
+                let service = match path[dir.len()..].split_once('/') {
+                    Some((service, _)) if !service.starts_with("year=") => service,
+                    _ => "-",
+                };
+                let service = if self.anonymize && service != "-" {
+                    anonymize(service)
+                } else {
+                    service.to_string()
+                };
+                candidates.push((path.to_string(), service, date));
+            }
+
+            for chunk in candidates.chunks(FOOTER_READS_IN_FLIGHT) {
+                let mut reads = tokio::task::JoinSet::new();
+                for (path, service, date) in chunk.iter().cloned() {
+                    let op = op.clone();
+                    reads.spawn(async move {
+                        let footer = read_footer(&op, &path).await;
+                        (path, service, date, footer)
+                    });
+                }
+                while let Some(read) = reads.join_next().await {
+                    let (path, service, date, footer) = read?;
+                    let (bytes, rows, uncompressed_bytes) =
+                        footer.with_context(|| format!("Failed to read footer of {}", path))?;
+                    files.push(FileStats {
+                        table,
+                        service,
+                        date,
+                        bytes,
+                        rows,
+                        uncompressed_bytes,
+                    });
+                }
+            }
+        }
+        Ok(summarize(since, self.small_file_bytes, files))
+    }
+}
+
+/// File size, rows and uncompressed size from the Parquet footer of `path`
+async fn read_footer(op: &opendal::Operator, path: &str) -> Result<(u64, u64, u64)> {
+    let size = op.stat(path).await?.content_length();
+    if size < FOOTER_LEN * 2 {
+        bail!("too small to be a Parquet file");
+    }
+    let tail = op
+        .read_with(path)
+        .range(size - FOOTER_LEN..size)
+        .await?
+        .to_vec();
+    if tail[4..] != *b"PAR1" {
+        bail!("missing Parquet magic");
+    }
+    let metadata_len = u64::from(u32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]));
+    if metadata_len + FOOTER_LEN > size {
+        bail!("footer length {} exceeds file size", metadata_len);
+    }
+    let start = size - FOOTER_LEN - metadata_len;
+    let metadata = op
+        .read_with(path)
+        .range(start..size - FOOTER_LEN)
+        .await?
+        .to_vec();
+    let metadata = ParquetMetaDataReader::decode_metadata(&metadata)?;
+    let uncompressed = metadata
+        .row_groups()
+        .iter()
+        .map(|rg| rg.total_byte_size().max(0) as u64)
+        .sum();
+    let rows = metadata.file_metadata().num_rows().max(0) as u64;
+    Ok((size, rows, uncompressed))
+}
+
+fn summarize(since: Date, small_file_bytes: u64, files: Vec<FileStats>) -> Report {
+    let mut daily: BTreeMap<(Date, &str, String), DailyVolume> = BTreeMap::new();
+    let mut sizes: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+    let mut tables: BTreeMap<&str, TableStats> = BTreeMap::new();
+
+    for file in files {
+        let volume = daily
+            .entry((file.date, file.table, file.service.clone()))
+            .or_insert_with(|| DailyVolume {
+                date: file.date.to_string(),
+                table: file.table.to_string(),
+                service: file.service,
+                files: 0,
+                rows: 0,
+                bytes: 0,
+            });
+        volume.files += 1;
+        volume.rows += file.rows;
+        volume.bytes += file.bytes;
+
+        let stats = tables.entry(file.table).or_insert_with(|| TableStats {
+            table: file.table.to_string(),
+            files: 0,
+            rows: 0,
+            bytes: 0,
+            uncompressed_bytes: 0,
+            compression_ratio: 0.0,
+            median_file_bytes: 0,
+            small_files: 0,
+            size_distribution: SIZE_BUCKETS.iter().map(|(_, label)| (*label, 0)).collect(),
+        });
+        stats.files += 1;
+        stats.rows += file.rows;
+        stats.bytes += file.bytes;
+        stats.uncompressed_bytes += file.uncompressed_bytes;
+        if file.bytes < small_file_bytes {
+            stats.small_files += 1;
+        }
+        let bucket = SIZE_BUCKETS
+            .iter()
+            .position(|(limit, _)| file.bytes < *limit)
+            .unwrap_or(SIZE_BUCKETS.len() - 1);
+        stats.size_distribution[bucket].1 += 1;
+        sizes.entry(file.table).or_default().push(file.bytes);
+    }
+
+    for (table, stats) in tables.iter_mut() {
+        if stats.bytes > 0 {
+            stats.compression_ratio = stats.uncompressed_bytes as f64 / stats.bytes as f64;
+        }
+        if let Some(sizes) = sizes.get_mut(table) {
+            sizes.sort_unstable();
+            stats.median_file_bytes = sizes[sizes.len() / 2];
+        }
+    }
+
+    // Tables in the fixed order, days newest first
+    let mut tables: Vec<TableStats> = tables.into_values().collect();
+    tables.sort_by_key(|t| TABLES.iter().position(|name| *name == t.table));
+    let mut daily: Vec<DailyVolume> = daily.into_values().collect();
+    daily.sort_by(|a, b| b.date.cmp(&a.date));
+    Report {
+        since: since.to_string(),
+        small_file_bytes,
+        tables,
+        daily,
+    }
+}
+
+fn print_report(report: &Report) {
+    println!("Since {} (UTC)\n", report.since);
+    if report.tables.is_empty() {
+        println!("No files written in this period");
+        return;
+    }
+
+    println!(
+        "{:<30} {:>8} {:>14} {:>10} {:>10} {:>7} {:>8}",
+        "TABLE", "FILES", "ROWS", "SIZE", "MEDIAN", "RATIO", "SMALL"
+    );
+    for t in &report.tables {
+        println!(
+            "{:<30} {:>8} {:>14} {:>10} {:>10} {:>6.1}x {:>8}",
+            t.table,
+            t.files,
+            t.rows,
+            human_bytes(t.bytes),
+            human_bytes(t.median_file_bytes),
+            t.compression_ratio,
+            t.small_files
+        );
+    }
+
+    println!(
+        "\n{:<30} {}",
+        "FILE SIZES",
+        SIZE_BUCKETS.map(|(_, l)| format!("{:>10}", l)).join("")
+    );
+    for t in &report.tables {
+        let counts: String = t
+            .size_distribution
+            .iter()
+            .map(|(_, n)| format!("{:>10}", n))
+            .collect();
+        println!("{:<30} {}", t.table, counts);
+    }
+
+    println!(
+        "\n{:<10} {:<30} {:<30} {:>8} {:>14} {:>10}",
+        "DATE", "TABLE", "SERVICE", "FILES", "ROWS", "SIZE"
+    );
+    for d in &report.daily {
+        println!(
+            "{:<10} {:<30} {:<30} {:>8} {:>14} {:>10}",
+            d.date,
+            d.table,
+            d.service,
+            d.files,
+            d.rows,
+            human_bytes(d.bytes)
+        );
+    }
+    println!(
+        "\nSmall files are under {}",
+        human_bytes(report.small_file_bytes)
+    );
+}
+
+/// Date of the `year=/month=/day=` partition in `path`
+fn partition_date(path: &str) -> Option<Date> {
+    let field = |key: &str| -> Option<u32> {
+        path.split('/')
+            .find_map(|segment| segment.strip_prefix(key))
+            .and_then(|value| value.parse().ok())
+    };
+    let month = Month::try_from(u8::try_from(field("month=")?).ok()?).ok()?;
+    let day = u8::try_from(field("day=")?).ok()?;
+    Date::from_calendar_date(i32::try_from(field("year=")?).ok()?, month, day).ok()
+}
+
+/// Stable, non-reversible label for a service name
+fn anonymize(service: &str) -> String {
+    format!("svc-{}", &blake3::hash(service.as_bytes()).to_hex()[..8])
+}
+
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_buckets_and_daily_volumes() {
+        let day = |d| Date::from_calendar_date(2025, Month::January, d).unwrap();
+        let file = |table, service: &str, date, bytes| FileStats {
+            table,
+            service: service.to_string(),
+            date,
+            bytes,
+            rows: 10,
+            uncompressed_bytes: bytes * 4,
+        };
+        let report = summarize(
+            day(14),
+            8 << 20,
+            vec![
+                file("logs", "web", day(14), 512 << 10),
+                file("logs", "web", day(15), 2 << 20),
+                file("logs", "web", day(15), 100 << 20),
+                file("traces", "api", day(15), 4 << 20),
+            ],
+        );
+
+        let logs = &report.tables[0];
+        assert_eq!(
+            (logs.table.as_str(), logs.files, logs.rows),
+            ("logs", 3, 30)
+        );
+        assert_eq!(logs.small_files, 2);
+        assert_eq!(logs.median_file_bytes, 2 << 20);
+        assert_eq!(logs.compression_ratio, 4.0);
+        let buckets: Vec<usize> = logs.size_distribution.iter().map(|(_, n)| *n).collect();
+        assert_eq!(buckets, vec![1, 1, 0, 1, 0]);
+
+        assert_eq!(report.daily[0].date, "2025-01-15");
+        assert_eq!(report.daily.len(), 3);
+        assert_eq!(
+            partition_date("logs/web/year=2025/month=01/day=15/hour=10/a.parquet"),
+            Some(day(15))
+        );
+        assert_eq!(anonymize("web"), anonymize("web"));
+        assert_ne!(anonymize("web"), "web");
+    }
+}
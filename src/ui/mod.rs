@@ -0,0 +1,272 @@
+// Local inspect UI
+//
+// With the `ui` feature, `serve` also answers on /ui with a single embedded
+// page for people running the server on a laptop: recently written files,
+// rows and bytes per service since startup, the output schemas, and a SQL box
+// over the last minutes of data, including rows still buffered for writing
+// (DataFusion, as for the Grafana datasource).
+// The UI has no authentication; don't enable the feature on shared hosts.
+
+use crate::writer::{register_writer_hook, FileEvent, WriterHook};
+use crate::{AppError, AppState, SignalType};
+use axum::extract::State;
+use axum::response::Html;
+use axum::Json;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::util::pretty::pretty_format_batches;
+use datafusion::execution::context::SQLOptions;
+use datafusion::prelude::SessionContext;
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+/// Files kept for the "recent flushes" list
+const MAX_RECENT_FILES: usize = 100;
+/// Services tracked for the volume table; later ones are counted as `(other)`
+const MAX_SERVICES: usize = 1_000;
+/// Rows returned by the query box
+const MAX_QUERY_ROWS: usize = 500;
+/// Default and largest query window
+const DEFAULT_QUERY_MINUTES: i64 = 15;
+const MAX_QUERY_MINUTES: i64 = 24 * 60;
+
+/// Tables the query box can read, as (SQL name, signal, metric type)
+const QUERY_TABLES: [(&str, SignalType, Option<&str>); 6] = [
+    ("logs", SignalType::Logs, None),
+    ("traces", SignalType::Traces, None),
+    ("gauge", SignalType::Metrics, Some("gauge")),
+    ("sum", SignalType::Metrics, Some("sum")),
+    ("histogram", SignalType::Metrics, Some("histogram")),
+    (
+        "exponential_histogram",
+        SignalType::Metrics,
+        Some("exponential_histogram"),
+    ),
+];
+
+static ACTIVITY: Lazy<Mutex<Activity>> = Lazy::new(|| Mutex::new(Activity::default()));
+static REGISTERED: OnceCell<()> = OnceCell::new();
+
+/// Files written since startup, as shown by the UI
+#[derive(Default)]
+struct Activity {
+    recent: VecDeque<Flush>,
+    services: BTreeMap<String, Volume>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Flush {
+    table: String,
+    service: Option<String>,
+    path: String,
+    rows: usize,
+    bytes: usize,
+    written_at_micros: i64,
+    relocated: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct Volume {
+    files: u64,
+    rows: u64,
+    bytes: u64,
+}
+
+impl Activity {
+    fn record(&mut self, flush: Flush) {
+        let service = flush
+            .service
+            .clone()
+            .unwrap_or_else(|| "(none)".to_string());
+        let service = if self.services.len() < MAX_SERVICES || self.services.contains_key(&service)
+        {
+            service
+        } else {
+            "(other)".to_string()
+        };
+        let volume = self.services.entry(service).or_default();
+        volume.files += 1;
+        volume.rows += flush.rows as u64;
+        volume.bytes += flush.bytes as u64;
+
+        if self.recent.len() == MAX_RECENT_FILES {
+            self.recent.pop_back();
+        }
+        self.recent.push_front(flush);
+    }
+}
+
+struct ActivityHook;
+
+impl WriterHook for ActivityHook {
+    fn on_file_written(&self, event: &FileEvent<'_>) {
+        ACTIVITY.lock().record(Flush {
+            table: event.table.to_string(),
+            service: service_from_path(event.table, event.path).map(str::to_string),
+            path: event.path.to_string(),
+            rows: event.entry.rows,
+            bytes: event.entry.bytes,
+            written_at_micros: event.entry.written_at_micros,
+            relocated: event.relocated,
+        });
+    }
+}
+
+/// Start recording written files for the UI.
+pub(crate) fn init() {
+    if REGISTERED.set(()).is_ok() {
+        register_writer_hook(Arc::new(ActivityHook));
+    }
+}
+
+/// Service directory of a data file path (`{table}/{service}/year=...`);
+/// `None` for tables without service directories
+fn service_from_path<'a>(table: &str, path: &'a str) -> Option<&'a str> {
+    let (_, rest) = path.split_once(&format!("{}/", table))?;
+    rest.split('/')
+        .next()
+        .filter(|segment| !segment.starts_with("year=") && !segment.ends_with(".parquet"))
+}
+
+/// GET /ui - The inspect page
+pub(crate) async fn index() -> Html<&'static str> {
+    Html(include_str!("index.html"))
+}
+
+/// GET /ui/api/activity - Recent files and per-service volumes
+pub(crate) async fn activity() -> Json<Value> {
+    let activity = ACTIVITY.lock();
+    Json(json!({
+        "recent": activity.recent,
+        "services": activity.services,
+    }))
+}
+
+/// GET /ui/api/schemas - Columns of every output table
+pub(crate) async fn schemas() -> Json<Value> {
+    let tables: Vec<Value> = otlp2records::schema_defs()
+        .iter()
+        .map(|schema| {
+            json!({
+                "name": schema.name,
+                "fields": schema.fields.iter().map(|field| json!({
+                    "name": field.name,
+                    "type": field.field_type,
+                    "required": field.required,
+                })).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    Json(Value::Array(tables))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct QueryRequest {
+    sql: String,
+    /// Window of data loaded for the query, ending now
+    #[serde(default)]
+    minutes: Option<i64>,
+}
+
+/// POST /ui/api/query - Read-only SQL over recently written files
+pub(crate) async fn query(
+    State(state): State<AppState>,
+    Json(request): Json<QueryRequest>,
+) -> Result<Json<Value>, AppError> {
+    let minutes = request
+        .minutes
+        .unwrap_or(DEFAULT_QUERY_MINUTES)
+        .clamp(1, MAX_QUERY_MINUTES);
+    let to = (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000) as i64;
+    let from = to - minutes * 60_000_000;
+
+    // Only load the tables the statement mentions
+    let words: HashSet<String> = request
+        .sql
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .map(str::to_ascii_lowercase)
+        .collect();
+    let ctx = SessionContext::new();
+    for (name, signal, metric_type) in QUERY_TABLES {
+        if words.contains(name) {
+            let pending = state.pending_batches(signal, metric_type);
+            crate::grafana::register_table(&ctx, name, signal, metric_type, from, to, pending)
+                .await?;
+        }
+    }
+
+    // No DDL, DML (COPY ... TO) or SET: the box must not touch disk or state
+    let options = SQLOptions::new()
+        .with_allow_ddl(false)
+        .with_allow_dml(false)
+        .with_allow_statements(false);
+    let frame = ctx
+        .sql_with_options(&request.sql, options)
+        .await
+        .map_err(AppError::bad_request)?;
+    let batches = frame.collect().await.map_err(AppError::bad_request)?;
+
+    let total: usize = batches.iter().map(RecordBatch::num_rows).sum();
+    let shown = first_rows(&batches, MAX_QUERY_ROWS);
+    let table = pretty_format_batches(&shown)
+        .map_err(AppError::internal)?
+        .to_string();
+    Ok(Json(json!({
+        "table": table,
+        "rows": total,
+        "truncated": total > MAX_QUERY_ROWS,
+    })))
+}
+
+/// The first `limit` rows of `batches`
+fn first_rows(batches: &[RecordBatch], limit: usize) -> Vec<RecordBatch> {
+    let mut remaining = limit;
+    let mut rows = Vec::new();
+    for batch in batches {
+        if remaining == 0 {
+            break;
+        }
+        let take = batch.num_rows().min(remaining);
+        rows.push(batch.slice(0, take));
+        remaining -= take;
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity_tracks_recent_files_and_services() {
+        assert_eq!(
+            service_from_path("logs", "tenant-a/logs/api/year=2025/month=01/a.parquet"),
+            Some("api")
+        );
+        assert_eq!(
+            service_from_path("service_graph", "service_graph/year=2025/a.parquet"),
+            None
+        );
+
+        let mut activity = Activity::default();
+        for i in 0..MAX_RECENT_FILES + 5 {
+            activity.record(Flush {
+                table: "logs".to_string(),
+                service: Some(if i % 2 == 0 { "api" } else { "web" }.to_string()),
+                path: format!("logs/api/{}.parquet", i),
+                rows: 10,
+                bytes: 100,
+                written_at_micros: i as i64,
+                relocated: false,
+            });
+        }
+        assert_eq!(activity.recent.len(), MAX_RECENT_FILES);
+        assert_eq!(activity.recent[0].written_at_micros, 104);
+        assert_eq!(activity.services["api"].files, 53);
+        assert_eq!(activity.services["web"].rows, 520);
+    }
+}
@@ -0,0 +1,229 @@
+// Resource-less payloads
+//
+// Records whose resource carries no `service.name` (or no resource at all) are
+// grouped under the `unknown` service by the decoder, a junk partition mixing
+// unrelated producers. `request.unattributed` decides what happens to them
+// instead: reject the request, divert them to the `_unattributed` table, or
+// attribute them to the service named by a request header (optionally mapped
+// through `request.header_services`, e.g. per API key).
+
+use crate::codec::{PartitionedBatch, ServiceGroupedBatches};
+use crate::config::{RequestConfig, UnattributedPolicy};
+use arrow::array::{ArrayRef, RecordBatch, StringArray};
+use axum::http::HeaderMap;
+use metrics::counter;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Directory (under the storage prefix) unattributed records are written to,
+/// one subdirectory per signal table
+pub(crate) const TABLE: &str = "_unattributed";
+
+/// Tables unattributed logs and spans are written to
+pub(crate) const LOGS_TABLE: &str = "_unattributed/logs";
+pub(crate) const TRACES_TABLE: &str = "_unattributed/traces";
+
+/// Service name the decoder assigns when the resource has none
+const UNKNOWN_SERVICE: &str = "unknown";
+
+pub(crate) struct Unattributed {
+    policy: UnattributedPolicy,
+    service_header: Option<String>,
+    header_services: BTreeMap<String, String>,
+}
+
+/// Records of one request sorted by the policy
+pub(crate) struct Sorted {
+    /// Records to process normally, inferred services filled in
+    pub attributed: ServiceGroupedBatches,
+    /// Records for the `_unattributed` table
+    pub unattributed: Vec<PartitionedBatch>,
+}
+
+impl Unattributed {
+    /// `None` for the default `keep` policy.
+    pub(crate) fn new(config: &RequestConfig) -> Option<Self> {
+        (config.unattributed != UnattributedPolicy::Keep).then(|| Self {
+            policy: config.unattributed,
+            service_header: config.service_header.clone(),
+            header_services: config.header_services.clone(),
+        })
+    }
+
+    /// Apply the policy to a decoded request. Errors (for `reject`) describe
+    /// why the request is refused.
+    pub(crate) fn sort(
+        &self,
+        signal: &'static str,
+        headers: &HeaderMap,
+        grouped: ServiceGroupedBatches,
+    ) -> Result<Sorted, String> {
+        let (unknown, mut attributed): (Vec<_>, Vec<_>) = grouped
+            .batches
+            .into_iter()
+            .partition(|pb| is_unattributed(&pb.service_name));
+        let unknown_records: usize = unknown.iter().map(|pb| pb.record_count).sum();
+        let mut sorted = Sorted {
+            attributed: ServiceGroupedBatches {
+                batches: Vec::new(),
+                total_records: grouped.total_records - unknown_records,
+            },
+            unattributed: Vec::new(),
+        };
+        if unknown.is_empty() {
+            sorted.attributed.batches = attributed;
+            return Ok(sorted);
+        }
+        counter!("otlp.ingest.unattributed", "signal" => signal).increment(unknown_records as u64);
+
+        match self.policy {
+            UnattributedPolicy::Keep => {
+                attributed.extend(unknown);
+                sorted.attributed.total_records += unknown_records;
+            }
+            UnattributedPolicy::Reject => {
+                return Err(format!(
+                    "{} {} record(s) without a service.name resource attribute",
+                    unknown_records, signal
+                ));
+            }
+            UnattributedPolicy::Table => sorted.unattributed = unknown,
+            UnattributedPolicy::Infer => match self.infer(headers) {
+                Some(service) => {
+                    counter!("otlp.ingest.inferred_service", "signal" => signal)
+                        .increment(unknown_records as u64);
+                    for pb in unknown {
+                        attributed.push(with_service(pb, service).map_err(|e| e.to_string())?);
+                    }
+                    sorted.attributed.total_records += unknown_records;
+                }
+                None => sorted.unattributed = unknown,
+            },
+        }
+        sorted.attributed.batches = attributed;
+        Ok(sorted)
+    }
+
+    /// Service named by the request's `service_header`
+    fn infer<'a>(&'a self, headers: &'a HeaderMap) -> Option<&'a str> {
+        let value = headers
+            .get(self.service_header.as_deref()?)?
+            .to_str()
+            .ok()?
+            .trim();
+        let value = value.strip_prefix("Bearer ").unwrap_or(value).trim();
+        if self.header_services.is_empty() {
+            (!value.is_empty()).then_some(value)
+        } else {
+            self.header_services.get(value).map(String::as_str)
+        }
+    }
+}
+
+fn is_unattributed(service: &str) -> bool {
+    service.is_empty() || service == UNKNOWN_SERVICE
+}
+
+/// `pb` relabelled as `service`, in the partition and the `service_name` column
+fn with_service(
+    mut pb: PartitionedBatch,
+    service: &str,
+) -> Result<PartitionedBatch, arrow::error::ArrowError> {
+    if let Ok(position) = pb.batch.schema().index_of("service_name") {
+        let mut columns = pb.batch.columns().to_vec();
+        columns[position] =
+            Arc::new(StringArray::from(vec![service; pb.batch.num_rows()])) as ArrayRef;
+        pb.batch = RecordBatch::try_new(pb.batch.schema(), columns)?;
+    }
+    pb.service_name = Arc::from(service);
+    Ok(pb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::AsArray;
+    use arrow::datatypes::{DataType, Field, Schema};
+
+    fn grouped(services: &[&str]) -> ServiceGroupedBatches {
+        let batches: Vec<PartitionedBatch> = services
+            .iter()
+            .map(|service| PartitionedBatch {
+                batch: RecordBatch::try_new(
+                    Arc::new(Schema::new(vec![Field::new(
+                        "service_name",
+                        DataType::Utf8,
+                        false,
+                    )])),
+                    vec![Arc::new(StringArray::from(vec![*service; 2]))],
+                )
+                .unwrap(),
+                service_name: Arc::from(*service),
+                min_timestamp_micros: 0,
+                record_count: 2,
+            })
+            .collect();
+        ServiceGroupedBatches {
+            total_records: batches.len() * 2,
+            batches,
+        }
+    }
+
+    fn policy(policy: UnattributedPolicy, header_services: &[(&str, &str)]) -> Unattributed {
+        Unattributed::new(&RequestConfig {
+            unattributed: policy,
+            service_header: Some("x-api-key".to_string()),
+            header_services: header_services
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..RequestConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reject_and_table_policies() {
+        let headers = HeaderMap::new();
+        let reject = policy(UnattributedPolicy::Reject, &[]);
+        assert!(reject
+            .sort("logs", &headers, grouped(&["web", "unknown"]))
+            .is_err());
+        assert!(reject.sort("logs", &headers, grouped(&["web"])).is_ok());
+
+        let sorted = policy(UnattributedPolicy::Table, &[])
+            .sort("logs", &headers, grouped(&["web", "unknown"]))
+            .unwrap();
+        assert_eq!(sorted.attributed.batches.len(), 1);
+        assert_eq!(sorted.attributed.total_records, 2);
+        assert_eq!(sorted.unattributed.len(), 1);
+    }
+
+    #[test]
+    fn test_infer_service_from_mapped_header() {
+        let infer = policy(UnattributedPolicy::Infer, &[("key-123", "checkout")]);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "key-123".parse().unwrap());
+
+        let sorted = infer
+            .sort("traces", &headers, grouped(&["unknown"]))
+            .unwrap();
+        assert!(sorted.unattributed.is_empty());
+        let pb = &sorted.attributed.batches[0];
+        assert_eq!(&*pb.service_name, "checkout");
+        let column = pb
+            .batch
+            .column_by_name("service_name")
+            .unwrap()
+            .as_string::<i32>();
+        assert_eq!(column.value(1), "checkout");
+
+        // Unmapped keys cannot attribute anything
+        headers.insert("x-api-key", "other".parse().unwrap());
+        let sorted = infer
+            .sort("traces", &headers, grouped(&["unknown"]))
+            .unwrap();
+        assert_eq!(sorted.unattributed.len(), 1);
+        assert_eq!(sorted.attributed.total_records, 0);
+    }
+}
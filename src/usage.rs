@@ -0,0 +1,328 @@
+// Usage accounting for chargeback
+//
+// Every file written is attributed to the services (and, with
+// `usage.tenant_attribute`, tenants) whose rows it holds: rows exactly, bytes
+// in proportion to rows, and one PUT per service/tenant in the file. Totals
+// are accumulated per UTC day; every `flush_interval_secs` the accumulated
+// deltas are written to the `_usage` table, so summing its rows per day gives
+// the day's exact usage. They are also exported as `otlp.usage.*` counters,
+// whose service and tenant labels are bounded by
+// `request.metrics_max_services` like the request metrics (later values share
+// `_other`).
+
+use crate::config::UsageConfig;
+use crate::request_metrics::BoundedLabels;
+use arrow::array::{Array, ArrayRef, Int64Array, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use metrics::counter;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tracing::{debug, warn};
+
+/// Directory (under the storage prefix) usage rows are written to
+pub(crate) const TABLE: &str = "_usage";
+
+static USAGE: OnceCell<Usage> = OnceCell::new();
+
+struct Usage {
+    tenant_attribute: Option<String>,
+    totals: Mutex<BTreeMap<UsageKey, UsageCounts>>,
+    /// Counter labels; the `_usage` table keeps the exact values
+    services: BoundedLabels,
+    tenants: BoundedLabels,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct UsageKey {
+    /// UTC day the data was written, `YYYY-MM-DD`
+    day: String,
+    service: String,
+    tenant: Option<String>,
+    table: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct UsageCounts {
+    rows: u64,
+    bytes: u64,
+    puts: u64,
+}
+
+/// Enable usage accounting, labelling counters with at most `max_labels`
+/// services and tenants.
+pub(crate) fn init(config: &UsageConfig, max_labels: usize) {
+    if !config.enabled {
+        return;
+    }
+    let _ = USAGE.set(Usage {
+        tenant_attribute: config.tenant_attribute.clone(),
+        totals: Mutex::new(BTreeMap::new()),
+        services: BoundedLabels::new(max_labels),
+        tenants: BoundedLabels::new(max_labels),
+    });
+}
+
+/// Attribute a file of `bytes` holding `batch` to its services/tenants.
+/// No-op when accounting is disabled.
+pub(crate) fn record(table: &str, batch: &RecordBatch, bytes: usize) {
+    let Some(usage) = USAGE.get() else {
+        return;
+    };
+    if table == TABLE || batch.num_rows() == 0 {
+        return;
+    }
+    let day = day_string(OffsetDateTime::now_utc());
+    let shares = usage.shares(table, &day, batch, bytes);
+
+    let mut totals = usage.totals.lock();
+    for (key, counts) in shares {
+        let service = usage.services.label(&key.service).to_string();
+        let tenant = key
+            .tenant
+            .as_deref()
+            .map(|tenant| usage.tenants.label(tenant).to_string())
+            .unwrap_or_default();
+        let table = key.table.clone();
+        counter!("otlp.usage.rows", "service" => service.clone(), "tenant" => tenant.clone(), "table" => table.clone())
+            .increment(counts.rows);
+        counter!("otlp.usage.bytes", "service" => service.clone(), "tenant" => tenant.clone(), "table" => table.clone())
+            .increment(counts.bytes);
+        counter!("otlp.usage.puts", "service" => service, "tenant" => tenant, "table" => table)
+            .increment(counts.puts);
+
+        let total = totals.entry(key).or_default();
+        total.rows += counts.rows;
+        total.bytes += counts.bytes;
+        total.puts += counts.puts;
+    }
+}
+
+/// Write usage accumulated since the last flush to the `_usage` table.
+pub(crate) async fn flush() {
+    let Some(usage) = USAGE.get() else {
+        return;
+    };
+    let totals = std::mem::take(&mut *usage.totals.lock());
+    if totals.is_empty() {
+        return;
+    }
+    let now_micros = crate::clock::now_micros();
+    let batch = match usage_batch(&totals) {
+        Ok(batch) => batch,
+        Err(e) => {
+            warn!(error = %e, "Failed to build usage batch");
+            return;
+        }
+    };
+    match crate::writer::write_table_batch(TABLE, None, now_micros, &batch, &[]).await {
+        Ok(path) => debug!(path = %path, rows = batch.num_rows(), "Wrote usage"),
+        Err(e) => {
+            // Keep the deltas for the next flush rather than losing them
+            let mut current = usage.totals.lock();
+            for (key, counts) in totals {
+                let total = current.entry(key).or_default();
+                total.rows += counts.rows;
+                total.bytes += counts.bytes;
+                total.puts += counts.puts;
+            }
+            warn!(error = %e, "Failed to write usage, retrying next flush");
+        }
+    }
+}
+
+/// Flush usage every `interval` until `shutdown` is set.
+pub(crate) async fn run_usage_flush(shutdown: Arc<AtomicBool>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    // First tick completes immediately
+    ticker.tick().await;
+
+    while !shutdown.load(Ordering::SeqCst) {
+        ticker.tick().await;
+        flush().await;
+    }
+}
+
+impl Usage {
+    /// Per service/tenant share of one file
+    fn shares(
+        &self,
+        table: &str,
+        day: &str,
+        batch: &RecordBatch,
+        bytes: usize,
+    ) -> BTreeMap<UsageKey, UsageCounts> {
+        let services = batch
+            .column_by_name("service_name")
+            .and_then(|c| c.as_any().downcast_ref::<StringArray>());
+        let resources = self.tenant_attribute.as_ref().and_then(|_| {
+            batch
+                .column_by_name("resource_attributes")
+                .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+        });
+
+        let mut rows: BTreeMap<(String, Option<String>), u64> = BTreeMap::new();
+        // Rows of one service usually share their resource; reuse the last tenant
+        let mut last: Option<(&str, Option<String>)> = None;
+        for row in 0..batch.num_rows() {
+            let service = match services {
+                Some(services) if services.is_valid(row) => services.value(row),
+                _ => "",
+            };
+            let tenant = match resources {
+                Some(resources) if resources.is_valid(row) => {
+                    let raw = resources.value(row);
+                    let cached = last
+                        .as_ref()
+                        .filter(|(prev, _)| *prev == raw)
+                        .map(|(_, tenant)| tenant.clone());
+                    match cached {
+                        Some(tenant) => tenant,
+                        None => {
+                            let tenant = self.tenant(raw);
+                            last = Some((raw, tenant.clone()));
+                            tenant
+                        }
+                    }
+                }
+                _ => None,
+            };
+            *rows.entry((service.to_string(), tenant)).or_default() += 1;
+        }
+
+        let total_rows = batch.num_rows() as u64;
+        rows.into_iter()
+            .map(|((service, tenant), count)| {
+                (
+                    UsageKey {
+                        day: day.to_string(),
+                        service,
+                        tenant,
+                        table: table.to_string(),
+                    },
+                    UsageCounts {
+                        rows: count,
+                        bytes: bytes as u64 * count / total_rows,
+                        puts: 1,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn tenant(&self, raw_resource: &str) -> Option<String> {
+        let key = self.tenant_attribute.as_ref()?;
+        let attributes: Map<String, Value> = serde_json::from_str(raw_resource).ok()?;
+        match attributes.get(key)? {
+            Value::String(s) => Some(s.clone()),
+            Value::Null => None,
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+fn day_string(at: OffsetDateTime) -> String {
+    format!(
+        "{:04}-{:02}-{:02}",
+        at.year(),
+        u8::from(at.month()),
+        at.day()
+    )
+}
+
+fn usage_batch(
+    totals: &BTreeMap<UsageKey, UsageCounts>,
+) -> Result<RecordBatch, arrow::error::ArrowError> {
+    let schema = Schema::new(vec![
+        Field::new("day", DataType::Utf8, false),
+        Field::new("service_name", DataType::Utf8, false),
+        Field::new("tenant", DataType::Utf8, true),
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("rows", DataType::Int64, false),
+        Field::new("bytes", DataType::Int64, false),
+        Field::new("puts", DataType::Int64, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            totals.keys().map(|k| k.day.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            totals.keys().map(|k| k.service.as_str()),
+        )),
+        Arc::new(StringArray::from_iter(
+            totals.keys().map(|k| k.tenant.as_deref()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            totals.keys().map(|k| k.table.as_str()),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            totals.values().map(|c| c.rows as i64),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            totals.values().map(|c| c.bytes as i64),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            totals.values().map(|c| c.puts as i64),
+        )),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits_file_usage_by_service_and_tenant() {
+        let usage = Usage {
+            tenant_attribute: Some("tenant.id".to_string()),
+            totals: Mutex::new(BTreeMap::new()),
+            services: BoundedLabels::new(10),
+            tenants: BoundedLabels::new(10),
+        };
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("service_name", DataType::Utf8, false),
+                Field::new("resource_attributes", DataType::Utf8, true),
+            ])),
+            vec![
+                Arc::new(StringArray::from(vec!["api", "api", "api", "web"])),
+                Arc::new(StringArray::from(vec![
+                    Some(r#"{"tenant.id":"acme"}"#),
+                    Some(r#"{"tenant.id":"acme"}"#),
+                    Some(r#"{"tenant.id":"globex"}"#),
+                    None,
+                ])),
+            ],
+        )
+        .unwrap();
+
+        let shares = usage.shares("logs", "2025-01-15", &batch, 1000);
+        let summary: Vec<(&str, Option<&str>, UsageCounts)> = shares
+            .iter()
+            .map(|(k, c)| (k.service.as_str(), k.tenant.as_deref(), *c))
+            .collect();
+        let counts = |rows, bytes| UsageCounts {
+            rows,
+            bytes,
+            puts: 1,
+        };
+        assert_eq!(
+            summary,
+            vec![
+                ("api", Some("acme"), counts(2, 500)),
+                ("api", Some("globex"), counts(1, 250)),
+                ("web", None, counts(1, 250)),
+            ]
+        );
+
+        let batch = usage_batch(&shares).unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        assert!(batch.column_by_name("tenant").unwrap().is_null(2));
+    }
+}
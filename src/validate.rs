@@ -0,0 +1,322 @@
+// Dry-run validation endpoint
+//
+// `POST /v1/validate` (signal detected from OTLP JSON) and
+// `POST /v1/validate/{logs|traces|metrics}` run a payload through decoding,
+// the unattributed-record policy, enrichment and the events split exactly like
+// ingestion, then report what would be written instead of writing it: rows
+// and schema per table, the hour partitions touched, and warnings such as
+// skipped data points. Meant for producers checking their instrumentation.
+
+use crate::codec::{
+    decode_logs_partitioned, decode_metrics_partitioned, decode_traces_partitioned, detect_signal,
+    PartitionedBatch, ServiceGroupedBatches,
+};
+use crate::handlers::{decode_lenient, enrich, strict_error};
+use crate::{AppState, InputFormat, MetricType, SignalType};
+use arrow::datatypes::Schema;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use metrics::counter;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Validation report returned to the producer
+#[derive(Debug, Serialize)]
+struct Report {
+    valid: bool,
+    signal: Option<&'static str>,
+    bytes: usize,
+    rows: usize,
+    tables: Vec<TableReport>,
+    warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TableReport {
+    /// Table directory, e.g. `logs` or `metrics/gauge`
+    table: String,
+    rows: usize,
+    schema: Vec<ColumnReport>,
+    partitions: Vec<PartitionReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct ColumnReport {
+    name: String,
+    #[serde(rename = "type")]
+    data_type: String,
+    nullable: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct PartitionReport {
+    service: String,
+    /// Hour partition directory, relative to the storage prefix
+    path: String,
+    rows: usize,
+}
+
+/// POST /v1/validate - detect the signal from the JSON payload
+pub(crate) async fn handle_validate(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Response {
+    let signal = detect_signal(&body);
+    validate(&state, signal, &headers, &body).await
+}
+
+/// POST /v1/validate/{signal}
+pub(crate) async fn handle_validate_signal(
+    State(state): State<AppState>,
+    Path(signal): Path<String>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Response {
+    let signal = match signal.as_str() {
+        "logs" => Some(SignalType::Logs),
+        "traces" => Some(SignalType::Traces),
+        "metrics" => Some(SignalType::Metrics),
+        _ => None,
+    };
+    validate(&state, signal, &headers, &body).await
+}
+
+async fn validate(
+    state: &AppState,
+    signal: Option<SignalType>,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Response {
+    let mut report = Report {
+        valid: false,
+        signal: signal.map(|s| s.as_str()),
+        bytes: body.len(),
+        rows: 0,
+        tables: Vec::new(),
+        warnings: Vec::new(),
+        error: None,
+    };
+    let Some(signal) = signal else {
+        report.error = Some(
+            "Unknown signal: post to /v1/validate/{logs,traces,metrics} (required for protobuf)"
+                .to_string(),
+        );
+        return (StatusCode::BAD_REQUEST, Json(report)).into_response();
+    };
+    counter!("otlp.validate.requests", "signal" => signal.as_str()).increment(1);
+
+    let content_type = headers.get("content-type").and_then(|v| v.to_str().ok());
+    let format = InputFormat::from_content_type(content_type);
+    let limit = state.max_payload_bytes.for_signal(signal);
+    if body.len() > limit {
+        report.warnings.push(format!(
+            "Payload of {} bytes exceeds the {} byte limit; ingestion would reject it with 413",
+            body.len(),
+            limit
+        ));
+    }
+    if !state.lenient_parsing
+        && crate::codec::tolerate_unknown_enums(body, format).is_some()
+        && decode_check(signal, body, format).is_err()
+    {
+        report.warnings.push(
+            "Payload uses enum names unknown to this OTLP version; \
+             request.lenient_parsing would accept them"
+                .to_string(),
+        );
+    }
+
+    match dry_run(state, signal, format, headers, body, &mut report) {
+        Ok(()) => {
+            report.valid = true;
+            report.rows = report.tables.iter().map(|t| t.rows).sum();
+            (StatusCode::OK, Json(report)).into_response()
+        }
+        Err(e) => {
+            report.error = Some(e);
+            (StatusCode::BAD_REQUEST, Json(report)).into_response()
+        }
+    }
+}
+
+/// Convert `body` like ingestion would, recording the result in `report`
+fn dry_run(
+    state: &AppState,
+    signal: SignalType,
+    format: InputFormat,
+    headers: &HeaderMap,
+    body: &[u8],
+    report: &mut Report,
+) -> Result<(), String> {
+    let groups: Vec<(String, ServiceGroupedBatches)> = match signal {
+        SignalType::Logs => {
+            let grouped = decode_lenient(state, body, format, decode_logs_partitioned)
+                .map_err(|e| format!("Failed to parse OTLP logs request: {}", e))?;
+            strict_error(state, signal, body, format, None)?;
+            vec![("logs".to_string(), grouped)]
+        }
+        SignalType::Traces => {
+            let grouped = decode_lenient(state, body, format, decode_traces_partitioned)
+                .map_err(|e| format!("Failed to parse OTLP traces request: {}", e))?;
+            strict_error(state, signal, body, format, None)?;
+            vec![("traces".to_string(), grouped)]
+        }
+        SignalType::Metrics => {
+            let partitioned = decode_lenient(state, body, format, decode_metrics_partitioned)
+                .map_err(|e| format!("Failed to parse OTLP metrics request: {}", e))?;
+            strict_error(state, signal, body, format, Some(&partitioned.skipped))?;
+            let skipped = &partitioned.skipped;
+            if skipped.has_skipped() {
+                report.warnings.push(format!(
+                    "{} data point(s) would be skipped: {} summaries, {} NaN, {} infinite, {} missing values",
+                    skipped.total(),
+                    skipped.summaries,
+                    skipped.nan_values,
+                    skipped.infinity_values,
+                    skipped.missing_values
+                ));
+            }
+            [
+                (MetricType::Gauge, partitioned.gauge),
+                (MetricType::Sum, partitioned.sum),
+                (MetricType::Histogram, partitioned.histogram),
+                (MetricType::ExponentialHistogram, partitioned.exp_histogram),
+            ]
+            .into_iter()
+            .map(|(metric_type, grouped)| (format!("metrics/{}", metric_type.as_str()), grouped))
+            .collect()
+        }
+    };
+
+    for (table, grouped) in groups {
+        let grouped = match state.unattributed {
+            Some(ref unattributed) => {
+                let sorted = unattributed.sort(signal.as_str(), headers, grouped)?;
+                if !sorted.unattributed.is_empty() {
+                    let rows: usize = sorted.unattributed.iter().map(|pb| pb.record_count).sum();
+                    report.warnings.push(format!(
+                        "{} record(s) have no service.name and would go to the unattributed table",
+                        rows
+                    ));
+                    report.tables.push(table_report(
+                        format!("{}/{}", crate::unattributed::TABLE, table),
+                        &sorted.unattributed,
+                        false,
+                    ));
+                }
+                sorted.attributed
+            }
+            None => {
+                if grouped
+                    .batches
+                    .iter()
+                    .any(|pb| &*pb.service_name == "unknown")
+                {
+                    report.warnings.push(
+                        "Some records have no service.name resource attribute and would be \
+                         written under the \"unknown\" service"
+                            .to_string(),
+                    );
+                }
+                grouped
+            }
+        };
+        let grouped = enrich(state, signal, grouped, headers);
+
+        let grouped = match (signal, &state.events) {
+            (SignalType::Logs, Some(extractor)) => {
+                let (logs, events) = extractor.split(grouped);
+                if !events.is_empty() {
+                    report.tables.push(table_report(
+                        crate::events::TABLE.to_string(),
+                        &events,
+                        true,
+                    ));
+                }
+                logs
+            }
+            _ => grouped,
+        };
+        if !grouped.batches.is_empty() {
+            report
+                .tables
+                .push(table_report(table, &grouped.batches, true));
+        }
+    }
+    Ok(())
+}
+
+fn table_report(table: String, batches: &[PartitionedBatch], per_service: bool) -> TableReport {
+    let mut partitions: BTreeMap<(String, String), usize> = BTreeMap::new();
+    for pb in batches {
+        let service = per_service.then_some(&*pb.service_name);
+        let path = crate::writer::partition_dir(&table, service, pb.min_timestamp_micros);
+        *partitions
+            .entry((pb.service_name.to_string(), path))
+            .or_default() += pb.record_count;
+    }
+    TableReport {
+        rows: batches.iter().map(|pb| pb.record_count).sum(),
+        schema: batches
+            .first()
+            .map(|pb| schema_report(&pb.batch.schema()))
+            .unwrap_or_default(),
+        partitions: partitions
+            .into_iter()
+            .map(|((service, path), rows)| PartitionReport {
+                service,
+                path,
+                rows,
+            })
+            .collect(),
+        table,
+    }
+}
+
+fn schema_report(schema: &Schema) -> Vec<ColumnReport> {
+    schema
+        .fields()
+        .iter()
+        .map(|field| ColumnReport {
+            name: field.name().clone(),
+            data_type: field.data_type().to_string(),
+            nullable: field.is_nullable(),
+        })
+        .collect()
+}
+
+/// Strict decode, only to tell whether lenient parsing would matter
+fn decode_check(signal: SignalType, body: &[u8], format: InputFormat) -> Result<(), String> {
+    match signal {
+        SignalType::Logs => decode_logs_partitioned(body, format).map(|_| ()),
+        SignalType::Traces => decode_traces_partitioned(body, format).map(|_| ()),
+        SignalType::Metrics => decode_metrics_partitioned(body, format).map(|_| ()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_report_groups_partitions() {
+        let payload = std::fs::read(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/log.json"),
+        )
+        .unwrap();
+        let grouped = decode_logs_partitioned(&payload, InputFormat::Json).unwrap();
+
+        let report = table_report("logs".to_string(), &grouped.batches, true);
+        assert_eq!(report.rows, grouped.total_records);
+        assert!(report.schema.iter().any(|c| c.name == "service_name"));
+        assert!(report
+            .partitions
+            .iter()
+            .all(|p| p.path.starts_with(&format!("logs/{}/year=", p.service))));
+    }
+}
@@ -0,0 +1,162 @@
+//! Fallback storage for writes the primary storage rejects.
+//!
+//! `[[fallbacks]]` are tried in order once a write to the default storage has
+//! failed, including the operator's own retries. The file keeps its key, placed
+//! under the fallback's prefix, and a relocation entry at
+//! `_relocations/{key}.json` next to it records where it was meant to go, so a
+//! repair job can copy it back once the primary is reachable again. A network
+//! partition then costs a copy instead of dropped telemetry.
+
+use crate::config::FallbackConfig;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+use super::error::Result;
+use super::storage::Target;
+
+static FALLBACKS: OnceCell<Vec<Target>> = OnceCell::new();
+
+/// Directory of relocation entries below a fallback's prefix
+const RELOCATIONS_DIR: &str = "_relocations";
+
+/// A file written to a fallback instead of the primary storage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Relocation {
+    /// Key the file should have on the primary storage
+    pub path: String,
+    /// Name of the fallback holding it
+    pub fallback: String,
+    /// Key of the file on the fallback
+    pub stored_path: String,
+    pub rows: usize,
+    pub bytes: usize,
+    /// Why the primary write failed
+    pub error: String,
+    pub relocated_at_micros: i64,
+}
+
+/// Build operators for every configured fallback.
+pub(crate) fn init_fallbacks(configs: &[FallbackConfig]) -> Result<()> {
+    if configs.is_empty() || FALLBACKS.get().is_some() {
+        return Ok(());
+    }
+    let targets = configs
+        .iter()
+        .map(|config| Target::build(&config.name, &config.storage))
+        .collect::<Result<Vec<_>>>()?;
+    tracing::info!(
+        "Failed writes fall back to: {}",
+        targets
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let _ = FALLBACKS.set(targets);
+    Ok(())
+}
+
+/// Store `data`, meant for `path` on the primary storage, on the first
+/// fallback that accepts it. Returns that fallback and the key used there, or
+/// `None` when no fallback is configured or all of them failed too.
+pub(crate) async fn relocate(
+    path: &str,
+    data: opendal::Buffer,
+    rows: usize,
+    error: &str,
+) -> Option<(&'static Target, String)> {
+    for target in FALLBACKS.get()? {
+        let stored_path = format!("{}{}", target.prefix, path);
+        if let Err(e) = target.operator.write(&stored_path, data.clone()).await {
+            metrics::counter!("otlp.storage.fallback_failures", "fallback" => target.name.clone())
+                .increment(1);
+            tracing::warn!(
+                error = %e,
+                fallback = %target.name,
+                path = %stored_path,
+                "Fallback storage rejected write"
+            );
+            continue;
+        }
+
+        let relocation = Relocation {
+            path: path.to_string(),
+            fallback: target.name.clone(),
+            stored_path: stored_path.clone(),
+            rows,
+            bytes: data.len(),
+            error: error.to_string(),
+            relocated_at_micros: crate::clock::now_micros(),
+        };
+        // The data is safe at this point; without the entry a repair has to
+        // list the fallback instead, so only warn
+        if let Err(e) = write_relocation(target, &relocation).await {
+            tracing::warn!(error = %e, path = %stored_path, "Failed to record relocation entry");
+        }
+        metrics::counter!("otlp.storage.relocated", "fallback" => target.name.clone()).increment(1);
+        tracing::warn!(
+            fallback = %target.name,
+            path = %path,
+            stored_path = %stored_path,
+            "Primary storage write failed, wrote file to fallback: {}",
+            error
+        );
+        return Some((target, stored_path));
+    }
+    None
+}
+
+fn relocation_path(prefix: &str, path: &str) -> String {
+    format!("{}{}/{}.json", prefix, RELOCATIONS_DIR, path)
+}
+
+async fn write_relocation(
+    target: &Target,
+    relocation: &Relocation,
+) -> std::result::Result<(), String> {
+    let body = serde_json::to_vec(relocation).map_err(|e| e.to_string())?;
+    target
+        .operator
+        .write(&relocation_path(&target.prefix, &relocation.path), body)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_relocation_entry_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = Target {
+            name: "local".to_string(),
+            operator: opendal::Operator::new(
+                opendal::services::Fs::default().root(dir.path().to_str().unwrap()),
+            )
+            .unwrap()
+            .finish(),
+            prefix: "spill/".to_string(),
+        };
+        let relocation = Relocation {
+            path: "logs/web/year=2025/month=01/day=15/hour=10/a.parquet".to_string(),
+            fallback: "local".to_string(),
+            stored_path: "spill/logs/web/year=2025/month=01/day=15/hour=10/a.parquet".to_string(),
+            rows: 10,
+            bytes: 512,
+            error: "connection reset".to_string(),
+            relocated_at_micros: 1_736_938_800_000_000,
+        };
+
+        write_relocation(&target, &relocation).await.unwrap();
+
+        let stored = target
+            .operator
+            .read("spill/_relocations/logs/web/year=2025/month=01/day=15/hour=10/a.parquet.json")
+            .await
+            .unwrap();
+        let decoded: Relocation = serde_json::from_slice(&stored.to_vec()).unwrap();
+        assert_eq!(decoded, relocation);
+    }
+}
@@ -0,0 +1,143 @@
+//! Discovery metadata in Parquet footers.
+//!
+//! Every file describes itself in its key-value metadata: signal, table,
+//! services, schema and writer version, event-time range and a hash of the
+//! rows. Tools that find files by listing storage, without the catalog or
+//! manifests, can read what a file holds from the footer alone.
+
+use arrow::array::{AsArray, RecordBatch};
+use arrow::ipc::writer::StreamWriter;
+use parquet::file::metadata::KeyValue;
+use std::collections::BTreeSet;
+
+use super::manifest::timestamp_range_micros;
+
+/// Version of the output schema layout, bumped when columns change
+/// incompatibly
+const SCHEMA_VERSION: &str = "1";
+
+const SIGNAL_KEY: &str = "otlp2parquet.signal";
+const TABLE_KEY: &str = "otlp2parquet.table";
+const SERVICES_KEY: &str = "otlp2parquet.services";
+const SCHEMA_VERSION_KEY: &str = "otlp2parquet.schema_version";
+const VERSION_KEY: &str = "otlp2parquet.version";
+const MIN_TIMESTAMP_KEY: &str = "otlp2parquet.min_timestamp_micros";
+const MAX_TIMESTAMP_KEY: &str = "otlp2parquet.max_timestamp_micros";
+const CONTENT_HASH_KEY: &str = "otlp2parquet.content_hash";
+
+/// Footer entries for `batch` written to `table` (e.g. `logs`,
+/// `metrics/gauge`), followed by the request ids if there are any.
+pub(crate) fn footer_metadata(
+    table: &str,
+    batch: &RecordBatch,
+    request_ids: &[String],
+) -> Vec<KeyValue> {
+    let entry = |key: &str, value: String| KeyValue::new(key.to_string(), value);
+    let signal = table.split('/').next().unwrap_or(table);
+    let mut entries = vec![
+        entry(SIGNAL_KEY, signal.to_string()),
+        entry(TABLE_KEY, table.to_string()),
+        entry(SCHEMA_VERSION_KEY, SCHEMA_VERSION.to_string()),
+        entry(VERSION_KEY, env!("CARGO_PKG_VERSION").to_string()),
+    ];
+    if let Some(services) = services(batch) {
+        entries.push(entry(SERVICES_KEY, services));
+    }
+    if let Some((min, max)) = timestamp_range_micros(batch) {
+        entries.push(entry(MIN_TIMESTAMP_KEY, min.to_string()));
+        entries.push(entry(MAX_TIMESTAMP_KEY, max.to_string()));
+    }
+    match content_hash(batch) {
+        Some(hash) => entries.push(entry(CONTENT_HASH_KEY, hash)),
+        None => tracing::debug!("Failed to hash batch for the Parquet footer"),
+    }
+    if !request_ids.is_empty() {
+        entries.push(entry(
+            crate::request_id::METADATA_KEY,
+            request_ids.join(","),
+        ));
+    }
+    entries
+}
+
+/// Distinct `service_name` values, sorted and comma separated
+fn services(batch: &RecordBatch) -> Option<String> {
+    let column = batch
+        .column_by_name("service_name")?
+        .as_string_opt::<i32>()?;
+    let names: BTreeSet<&str> = column.iter().flatten().collect();
+    Some(names.into_iter().collect::<Vec<_>>().join(","))
+}
+
+/// blake3 of the batch in Arrow IPC stream format, hex encoded.
+///
+/// Hashes the rows rather than the file, so it is the same for any
+/// compression or row-group layout of the same data.
+fn content_hash(batch: &RecordBatch) -> Option<String> {
+    let mut hasher = HashWriter(blake3::Hasher::new());
+    let mut writer = StreamWriter::try_new(&mut hasher, &batch.schema()).ok()?;
+    writer.write(batch).ok()?;
+    writer.finish().ok()?;
+    drop(writer);
+    Some(hasher.0.finalize().to_hex().to_string())
+}
+
+/// `io::Write` into a blake3 hasher (the `std` feature is off)
+struct HashWriter(blake3::Hasher);
+
+impl std::io::Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{StringArray, TimestampNanosecondArray};
+    use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_footer_describes_batch() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(
+                "timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+            Field::new("service_name", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(TimestampNanosecondArray::from(vec![
+                    3_000_000, 1_000_000, 2_000_000,
+                ])),
+                Arc::new(StringArray::from(vec!["web", "api", "web"])),
+            ],
+        )
+        .unwrap();
+
+        let entries = footer_metadata("metrics/gauge", &batch, &["req-1".to_string()]);
+        let value = |key: &str| {
+            entries
+                .iter()
+                .find(|e| e.key == key)
+                .and_then(|e| e.value.as_deref())
+        };
+        assert_eq!(value(SIGNAL_KEY), Some("metrics"));
+        assert_eq!(value(TABLE_KEY), Some("metrics/gauge"));
+        assert_eq!(value(SERVICES_KEY), Some("api,web"));
+        assert_eq!(value(MIN_TIMESTAMP_KEY), Some("1000"));
+        assert_eq!(value(MAX_TIMESTAMP_KEY), Some("3000"));
+        assert_eq!(value(crate::request_id::METADATA_KEY), Some("req-1"));
+        assert_eq!(value(CONTENT_HASH_KEY).map(str::len), Some(64));
+        assert_eq!(value(CONTENT_HASH_KEY), content_hash(&batch).as_deref());
+    }
+}
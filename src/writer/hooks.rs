@@ -0,0 +1,126 @@
+//! Write hooks for embedders.
+//!
+//! Programs embedding the server can register [`WriterHook`]s to react to the
+//! write path (notifications, secondary indexes, cache invalidation) without
+//! forking it. Hooks run inline on the writing task, in registration order:
+//! they must return quickly and hand slow work (network calls) to a task of
+//! their own.
+
+use arrow::array::RecordBatch;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+use super::manifest::ManifestEntry;
+
+static HOOKS: Lazy<RwLock<Vec<Arc<dyn WriterHook>>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// A batch about to be encoded and written to `path`
+pub struct BatchEvent<'a> {
+    /// Table directory below the storage prefix, e.g. `logs` or `metrics/gauge`
+    pub table: &'a str,
+    /// Object path the file will be written to
+    pub path: &'a str,
+    pub batch: &'a RecordBatch,
+}
+
+/// A Parquet file stored by the writer
+pub struct FileEvent<'a> {
+    /// Table directory below the storage prefix, e.g. `logs` or `metrics/gauge`
+    pub table: &'a str,
+    /// Object path the file was stored at
+    pub path: &'a str,
+    /// Rows, bytes, timestamp range and blake3 hash of the file
+    pub entry: &'a ManifestEntry,
+    /// Stored on a fallback target because the primary storage rejected it
+    pub relocated: bool,
+}
+
+/// Callbacks on the write path. Every method defaults to doing nothing.
+pub trait WriterHook: Send + Sync {
+    /// Before a batch is encoded
+    fn on_batch_ready(&self, _event: &BatchEvent<'_>) {}
+
+    /// After the file is durable (and verified, if enabled)
+    fn on_file_written(&self, _event: &FileEvent<'_>) {}
+
+    /// After the file is listed in the partition manifest and catalog (where
+    /// enabled). Not called for relocated files, which are listed once repaired.
+    fn on_commit(&self, _event: &FileEvent<'_>) {}
+}
+
+/// Register `hook` for every subsequent write.
+pub fn register_writer_hook(hook: Arc<dyn WriterHook>) {
+    HOOKS.write().push(hook);
+}
+
+pub(crate) fn registered() -> bool {
+    !HOOKS.read().is_empty()
+}
+
+pub(crate) fn batch_ready(event: &BatchEvent<'_>) {
+    for hook in HOOKS.read().iter() {
+        hook.on_batch_ready(event);
+    }
+}
+
+pub(crate) fn file_written(event: &FileEvent<'_>) {
+    for hook in HOOKS.read().iter() {
+        hook.on_file_written(event);
+    }
+}
+
+pub(crate) fn committed(event: &FileEvent<'_>) {
+    for hook in HOOKS.read().iter() {
+        hook.on_commit(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Counting {
+        written: AtomicUsize,
+        committed: AtomicUsize,
+    }
+
+    impl WriterHook for Counting {
+        fn on_file_written(&self, event: &FileEvent<'_>) {
+            self.written.fetch_add(event.entry.rows, Ordering::SeqCst);
+        }
+
+        fn on_commit(&self, _event: &FileEvent<'_>) {
+            self.committed.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_registered_hooks_see_events() {
+        let hook = Arc::new(Counting::default());
+        register_writer_hook(hook.clone());
+        assert!(registered());
+
+        let entry = ManifestEntry {
+            file: "a.parquet".to_string(),
+            rows: 7,
+            bytes: 100,
+            min_timestamp_micros: 0,
+            max_timestamp_micros: 0,
+            blake3: String::new(),
+            written_at_micros: 0,
+        };
+        let event = FileEvent {
+            table: "logs",
+            path: "logs/svc/a.parquet",
+            entry: &entry,
+            relocated: false,
+        };
+        file_written(&event);
+        committed(&event);
+        assert_eq!(hook.written.load(Ordering::SeqCst), 7);
+        assert_eq!(hook.committed.load(Ordering::SeqCst), 1);
+    }
+}
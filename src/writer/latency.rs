@@ -0,0 +1,98 @@
+//! Rolling storage write latency, used for admission control.
+//!
+//! Only recent samples count so the estimate recovers once the backend does,
+//! even while requests are being shed and few writes happen.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Samples older than this no longer influence the percentile.
+const WINDOW: Duration = Duration::from_secs(60);
+/// Upper bound on retained samples, regardless of write rate.
+const MAX_SAMPLES: usize = 512;
+/// Below this many recent samples the estimate is too noisy to act on.
+const MIN_SAMPLES: usize = 10;
+
+static WRITE_LATENCY: Lazy<Mutex<LatencyWindow>> = Lazy::new(|| Mutex::new(LatencyWindow::new()));
+
+/// Record how long one storage write took (successful or not).
+pub(crate) fn record_write_latency(elapsed: Duration) {
+    WRITE_LATENCY.lock().record(Instant::now(), elapsed);
+    metrics::histogram!("otlp.storage.write_ms").record(elapsed.as_secs_f64() * 1000.0);
+}
+
+/// p95 of recent write latencies, or `None` without enough recent samples.
+pub(crate) fn write_latency_p95() -> Option<Duration> {
+    WRITE_LATENCY.lock().p95(Instant::now())
+}
+
+struct LatencyWindow {
+    samples: VecDeque<(Instant, Duration)>,
+}
+
+impl LatencyWindow {
+    fn new() -> Self {
+        Self {
+            samples: VecDeque::with_capacity(MAX_SAMPLES),
+        }
+    }
+
+    fn record(&mut self, now: Instant, elapsed: Duration) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now, elapsed));
+    }
+
+    fn p95(&mut self, now: Instant) -> Option<Duration> {
+        while let Some((at, _)) = self.samples.front() {
+            if now.duration_since(*at) <= WINDOW {
+                break;
+            }
+            self.samples.pop_front();
+        }
+
+        if self.samples.len() < MIN_SAMPLES {
+            return None;
+        }
+
+        let mut latencies: Vec<Duration> = self.samples.iter().map(|(_, d)| *d).collect();
+        latencies.sort_unstable();
+        let rank = (latencies.len() * 95).div_ceil(100).saturating_sub(1);
+        latencies.get(rank).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_p95_requires_minimum_samples() {
+        let mut window = LatencyWindow::new();
+        let now = Instant::now();
+        for _ in 0..MIN_SAMPLES - 1 {
+            window.record(now, Duration::from_millis(10));
+        }
+        assert_eq!(window.p95(now), None);
+
+        window.record(now, Duration::from_millis(10));
+        assert_eq!(window.p95(now), Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn test_p95_picks_tail_and_expires_old_samples() {
+        let mut window = LatencyWindow::new();
+        let start = Instant::now();
+        for i in 1..=100 {
+            window.record(start, Duration::from_millis(i));
+        }
+        assert_eq!(window.p95(start), Some(Duration::from_millis(95)));
+
+        // Everything recorded at `start` has aged out of the window
+        let later = start + WINDOW + Duration::from_secs(1);
+        assert_eq!(window.p95(later), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! Per-partition manifest files for plain Parquet output.
+//!
+//! Each partition directory gets a `_manifest.jsonl` listing the files written
+//! into it, and each table prefix gets a `_latest.json` pointer to the most
+//! recently written file. Consumers can discover data without LIST calls.
+
+use arrow::array::{AsArray, RecordBatch};
+use arrow::datatypes::{DataType, TimeUnit, TimestampMicrosecondType};
+use serde::{Deserialize, Serialize};
+
+use crate::types::Blake3Hash;
+
+use super::error::{Result, WriterError};
+
+/// Manifest file name written into every partition directory.
+pub const MANIFEST_FILE: &str = "_manifest.jsonl";
+
+/// Pointer file written at the root of every table prefix.
+pub const LATEST_FILE: &str = "_latest.json";
+
+/// Serializes manifest read-modify-write cycles within this process.
+static MANIFEST_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// One line of a partition `_manifest.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// File name relative to the partition directory
+    pub file: String,
+    pub rows: usize,
+    pub bytes: usize,
+    /// Minimum event timestamp in microseconds (0 if unknown)
+    pub min_timestamp_micros: i64,
+    /// Maximum event timestamp in microseconds (0 if unknown)
+    pub max_timestamp_micros: i64,
+    /// Blake3 hash of the file contents, hex encoded
+    pub blake3: String,
+    /// Wall-clock time the file was written, in microseconds
+    pub written_at_micros: i64,
+}
+
+/// Contents of a table-level `_latest.json` pointer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestPointer {
+    /// Full object path of the most recently written file
+    pub path: String,
+    /// Full object path of the manifest that lists it
+    pub manifest: String,
+    pub written_at_micros: i64,
+}
+
+impl ManifestEntry {
+    /// Build an entry for a freshly written file.
+    pub fn new(file_path: &str, batch: &RecordBatch, parquet_bytes: &[u8]) -> Self {
+        let file = file_path
+            .rsplit_once('/')
+            .map(|(_, name)| name)
+            .unwrap_or(file_path)
+            .to_string();
+        let (min_ts, max_ts) = timestamp_range_micros(batch).unwrap_or((0, 0));
+        let hash = Blake3Hash::new(*blake3::hash(parquet_bytes).as_bytes());
+
+        Self {
+            file,
+            rows: batch.num_rows(),
+            bytes: parquet_bytes.len(),
+            min_timestamp_micros: min_ts,
+            max_timestamp_micros: max_ts,
+            blake3: hash.to_hex(),
+            written_at_micros: crate::clock::now_micros(),
+        }
+    }
+}
+
+/// Append `entry` to the partition manifest next to `file_path` and update the
+/// table-level latest pointer under `table_prefix`.
+pub async fn record_file(
+    op: &opendal::Operator,
+    table_prefix: &str,
+    file_path: &str,
+    entry: &ManifestEntry,
+) -> Result<()> {
+    let partition_dir = file_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    let manifest_path = format!("{}/{}", partition_dir, MANIFEST_FILE);
+
+    let mut line = serde_json::to_vec(entry).map_err(|e| {
+        WriterError::write_failure(format!("Failed to encode manifest entry: {}", e))
+    })?;
+    line.push(b'\n');
+
+    let _guard = MANIFEST_LOCK.lock().await;
+
+    if op.info().full_capability().write_can_append {
+        op.write_with(&manifest_path, line)
+            .append(true)
+            .await
+            .map_err(|e| manifest_error(&manifest_path, e))?;
+    } else {
+        let mut content = match op.read(&manifest_path).await {
+            Ok(existing) => existing.to_vec(),
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(manifest_error(&manifest_path, e)),
+        };
+        content.extend_from_slice(&line);
+        op.write(&manifest_path, content)
+            .await
+            .map_err(|e| manifest_error(&manifest_path, e))?;
+    }
+
+    let latest_path = format!("{}/{}", table_prefix.trim_end_matches('/'), LATEST_FILE);
+    let pointer = LatestPointer {
+        path: file_path.to_string(),
+        manifest: manifest_path,
+        written_at_micros: entry.written_at_micros,
+    };
+    let pointer_bytes = serde_json::to_vec(&pointer).map_err(|e| {
+        WriterError::write_failure(format!("Failed to encode latest pointer: {}", e))
+    })?;
+    op.write(&latest_path, pointer_bytes)
+        .await
+        .map_err(|e| manifest_error(&latest_path, e))?;
+
+    Ok(())
+}
+
+/// Minimum and maximum of the `timestamp` column, in microseconds.
+pub(crate) fn timestamp_range_micros(batch: &RecordBatch) -> Option<(i64, i64)> {
+    let column = batch.column_by_name("timestamp")?;
+    // Tables stored in milli- or nanoseconds are compared in microseconds
+    let column = match column.data_type() {
+        DataType::Timestamp(unit, tz) if *unit != TimeUnit::Microsecond => arrow::compute::cast(
+            column,
+            &DataType::Timestamp(TimeUnit::Microsecond, tz.clone()),
+        )
+        .ok()?,
+        _ => column.clone(),
+    };
+    let ts = column.as_primitive_opt::<TimestampMicrosecondType>()?;
+    ts.iter().flatten().fold(None, |acc, v| match acc {
+        None => Some((v, v)),
+        Some((lo, hi)) => Some((lo.min(v), hi.max(v))),
+    })
+}
+
+fn manifest_error(path: &str, e: opendal::Error) -> WriterError {
+    WriterError::write_failure(format!("Failed to update manifest '{}': {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::TimestampMicrosecondArray;
+    use arrow::datatypes::{Field, Schema};
+    use std::sync::Arc;
+
+    fn batch_with_timestamps(values: Vec<i64>) -> RecordBatch {
+        let schema = Schema::new(vec![Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, None),
+            false,
+        )]);
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![Arc::new(TimestampMicrosecondArray::from(values))],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn entry_captures_rows_range_and_hash() {
+        let batch = batch_with_timestamps(vec![30, 10, 20]);
+        let entry = ManifestEntry::new("logs/svc/year=2025/a.parquet", &batch, b"PAR1data");
+
+        assert_eq!(entry.file, "a.parquet");
+        assert_eq!(entry.rows, 3);
+        assert_eq!(entry.bytes, 8);
+        assert_eq!(entry.min_timestamp_micros, 10);
+        assert_eq!(entry.max_timestamp_micros, 30);
+        assert_eq!(entry.blake3.len(), 64);
+    }
+}
@@ -6,8 +6,32 @@
 #![allow(clippy::result_large_err)]
 
 mod error;
+mod fallback;
+mod footer;
+mod hooks;
+mod latency;
+pub mod manifest;
+mod quota;
+mod replication;
+mod routing;
+mod sampling;
+mod span_text;
 mod storage;
+mod tiering;
+mod timestamps;
 mod write;
 
+pub use fallback::Relocation;
+pub use hooks::{register_writer_hook, BatchEvent, FileEvent, WriterHook};
+pub(crate) use latency::write_latency_p95;
+pub(crate) use quota::disk_full;
+pub use quota::start_disk_quota;
+pub use replication::{drain_replication, start_replication};
 pub use storage::initialize_storage;
+pub(crate) use storage::{build_operator, get_operator, storage_prefix};
+pub use tiering::start_tiering;
+pub(crate) use timestamps::check_timestamp_units;
+#[cfg(feature = "grafana")]
+pub(crate) use write::output_batch;
+pub(crate) use write::{partition_dir, partition_hour_micros, table_prefix, write_table_batch};
 pub use write::{write_batch, WriteBatchRequest};
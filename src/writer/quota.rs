@@ -0,0 +1,285 @@
+//! Disk quota for the filesystem backend.
+//!
+//! With `fs.max_disk_bytes`, a background task measures the files under the
+//! storage root every `SCAN_INTERVAL`, and as soon as writes push the running
+//! estimate past the limit. Over the limit, the `delete` policy removes whole
+//! hour partitions, oldest first across all tables and services, until usage
+//! is back under `ROTATE_TARGET_PERCENT` of the limit; the current hour is
+//! never removed. The `reject` policy keeps every file and makes ingest answer
+//! 503 until usage drops below the limit again.
+
+use crate::config::{DiskFullPolicy, StorageBackend, StorageConfig};
+use metrics::{counter, gauge};
+use once_cell::sync::OnceCell;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::Notify;
+
+const SCAN_INTERVAL: Duration = Duration::from_secs(30);
+/// Rotation frees space down to this share of the limit, so it does not run
+/// again after every file
+const ROTATE_TARGET_PERCENT: u64 = 90;
+
+static QUOTA: OnceCell<Quota> = OnceCell::new();
+
+struct Quota {
+    root: PathBuf,
+    max_bytes: u64,
+    policy: DiskFullPolicy,
+    /// Bytes under the root at the last scan plus files written since
+    used: AtomicU64,
+    /// Ingest is refused (`reject` policy at the limit)
+    full: AtomicBool,
+    wake: Notify,
+}
+
+/// An hour partition directory, e.g. `logs/api/year=2025/month=01/day=15/hour=10`
+#[derive(Debug)]
+struct Partition {
+    /// (year, month, day, hour)
+    hour: (u32, u32, u32, u32),
+    dir: PathBuf,
+    bytes: u64,
+}
+
+/// Start enforcing `fs.max_disk_bytes`. No-op for other backends or without
+/// a limit.
+pub fn start_disk_quota(storage: &StorageConfig) {
+    if storage.backend != StorageBackend::Fs || QUOTA.get().is_some() {
+        return;
+    }
+    let Some(fs) = storage.fs.as_ref() else {
+        return;
+    };
+    let Some(max_bytes) = fs.max_disk_bytes else {
+        return;
+    };
+    let quota = QUOTA.get_or_init(|| Quota {
+        root: PathBuf::from(&fs.path),
+        max_bytes,
+        policy: fs.on_disk_full,
+        used: AtomicU64::new(0),
+        full: AtomicBool::new(false),
+        wake: Notify::new(),
+    });
+    gauge!("otlp.storage.fs.max_bytes").set(max_bytes as f64);
+    tokio::spawn(run(quota));
+    tracing::info!(
+        "Limiting '{}' to {} bytes ({:?} at the limit)",
+        fs.path,
+        max_bytes,
+        fs.on_disk_full
+    );
+}
+
+/// `(used, max)` bytes while ingest must be refused for lack of space.
+pub(crate) fn disk_full() -> Option<(u64, u64)> {
+    let quota = QUOTA.get()?;
+    quota
+        .full
+        .load(Ordering::Relaxed)
+        .then(|| (quota.used.load(Ordering::Relaxed), quota.max_bytes))
+}
+
+/// Count a file just written to the filesystem root.
+pub(crate) fn record_write(bytes: u64) {
+    let Some(quota) = QUOTA.get() else {
+        return;
+    };
+    let before = quota.used.fetch_add(bytes, Ordering::Relaxed);
+    // Only on crossing: a limit that rotation cannot meet must not rescan per file
+    if before < quota.max_bytes && before + bytes >= quota.max_bytes {
+        match quota.policy {
+            DiskFullPolicy::Delete => quota.wake.notify_one(),
+            DiskFullPolicy::Reject => quota.full.store(true, Ordering::Relaxed),
+        }
+    }
+}
+
+async fn run(quota: &'static Quota) {
+    loop {
+        if let Err(e) = tokio::task::spawn_blocking(move || enforce(quota)).await {
+            tracing::warn!(error = %e, "Disk quota scan failed");
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(SCAN_INTERVAL) => {}
+            _ = quota.wake.notified() => {}
+        }
+    }
+}
+
+/// Measure the root, rotate partitions if the policy allows, and publish
+/// the new usage.
+fn enforce(quota: &Quota) {
+    let (mut used, partitions) = scan(&quota.root);
+    if used > quota.max_bytes && quota.policy == DiskFullPolicy::Delete {
+        let target = quota.max_bytes / 100 * ROTATE_TARGET_PERCENT;
+        for partition in select_for_rotation(partitions, used, target, current_hour()) {
+            match std::fs::remove_dir_all(&partition.dir) {
+                Ok(()) => {
+                    used = used.saturating_sub(partition.bytes);
+                    counter!("otlp.storage.fs.rotated_partitions").increment(1);
+                    tracing::warn!(
+                        bytes = partition.bytes,
+                        "Disk quota reached: deleted partition '{}'",
+                        partition.dir.display()
+                    );
+                    remove_empty_parents(&quota.root, &partition.dir);
+                }
+                Err(e) => tracing::warn!(
+                    error = %e,
+                    "Failed to delete partition '{}'",
+                    partition.dir.display()
+                ),
+            }
+        }
+        if used > quota.max_bytes {
+            tracing::warn!(
+                used,
+                max = quota.max_bytes,
+                "Disk quota exceeded and only current-hour partitions are left"
+            );
+        }
+    }
+    quota.used.store(used, Ordering::Relaxed);
+    quota.full.store(
+        quota.policy == DiskFullPolicy::Reject && used >= quota.max_bytes,
+        Ordering::Relaxed,
+    );
+    gauge!("otlp.storage.fs.used_bytes").set(used as f64);
+}
+
+/// Oldest partitions whose removal brings `used` down to `target`, skipping
+/// the current hour and anything after it.
+fn select_for_rotation(
+    mut partitions: Vec<Partition>,
+    mut used: u64,
+    target: u64,
+    current: (u32, u32, u32, u32),
+) -> Vec<Partition> {
+    partitions.sort_by_key(|p| p.hour);
+    partitions
+        .into_iter()
+        .filter(|p| p.hour < current)
+        .take_while(|p| {
+            let take = used > target;
+            used = used.saturating_sub(p.bytes);
+            take
+        })
+        .collect()
+}
+
+fn current_hour() -> (u32, u32, u32, u32) {
+    let now = OffsetDateTime::now_utc();
+    (
+        now.year().max(0) as u32,
+        u8::from(now.month()) as u32,
+        now.day() as u32,
+        now.hour() as u32,
+    )
+}
+
+/// Total bytes of the files below `root` and its hour partitions.
+fn scan(root: &Path) -> (u64, Vec<Partition>) {
+    let mut partitions = Vec::new();
+    let used = walk(root, [None; 3], &mut partitions);
+    (used, partitions)
+}
+
+/// Bytes of the files below `dir`, recording hour partitions on the way;
+/// `date` holds the year, month and day directories above `dir`.
+fn walk(dir: &Path, date: [Option<u32>; 3], partitions: &mut Vec<Partition>) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut total = 0;
+    for entry in entries.flatten() {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if !meta.is_dir() {
+            total += meta.len();
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let segment = partition_segment(&name);
+        let mut date = date;
+        match segment {
+            Some(("year", year)) => date = [Some(year), None, None],
+            Some(("month", month)) => date[1] = Some(month),
+            Some(("day", day)) => date[2] = Some(day),
+            _ => {}
+        }
+        let bytes = walk(&entry.path(), date, partitions);
+        if let (Some(("hour", hour)), [Some(year), Some(month), Some(day)]) = (segment, date) {
+            partitions.push(Partition {
+                hour: (year, month, day, hour),
+                dir: entry.path(),
+                bytes,
+            });
+        }
+        total += bytes;
+    }
+    total
+}
+
+/// `("year", 2025)` for a `year=2025` directory
+fn partition_segment(name: &str) -> Option<(&str, u32)> {
+    let (key, value) = name.split_once('=')?;
+    Some((key, value.parse().ok()?))
+}
+
+/// Remove the directories left empty above a deleted partition, up to `root`.
+fn remove_empty_parents(root: &Path, dir: &Path) {
+    let mut parent = dir.parent();
+    while let Some(path) = parent.filter(|p| p.starts_with(root) && *p != root) {
+        if std::fs::remove_dir(path).is_err() {
+            break;
+        }
+        parent = path.parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_deletes_oldest_partitions_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, bytes: usize| {
+            let file = dir.path().join(path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, vec![0u8; bytes]).unwrap();
+        };
+        write("logs/api/year=2025/month=01/day=15/hour=10/a.parquet", 100);
+        write("logs/web/year=2025/month=01/day=15/hour=09/b.parquet", 100);
+        write(
+            "metrics/gauge/api/year=2024/month=12/day=31/hour=23/shard=01/c.parquet",
+            100,
+        );
+        write("logs/api/year=2025/month=01/day=15/hour=11/d.parquet", 100);
+        write("_replication/pending.json", 10);
+
+        let (used, partitions) = scan(dir.path());
+        assert_eq!(used, 410);
+        assert_eq!(partitions.len(), 4);
+
+        let rotated = select_for_rotation(partitions, used, 250, (2025, 1, 15, 11));
+        let hours: Vec<_> = rotated.iter().map(|p| p.hour).collect();
+        assert_eq!(hours, vec![(2024, 12, 31, 23), (2025, 1, 15, 9)]);
+
+        // The current hour is kept even when still over the limit
+        let (used, partitions) = scan(dir.path());
+        assert_eq!(
+            select_for_rotation(partitions, used, 0, (2025, 1, 15, 10)).len(),
+            2
+        );
+
+        std::fs::remove_dir_all(&rotated[0].dir).unwrap();
+        remove_empty_parents(dir.path(), &rotated[0].dir);
+        assert!(!dir.path().join("metrics").exists());
+    }
+}
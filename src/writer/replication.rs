@@ -0,0 +1,427 @@
+//! Asynchronous replication of committed files to a second bucket/region.
+//!
+//! Every file committed to the default storage is queued and copied to the
+//! replica by a background task, so replication never adds latency to the
+//! write path. Files keep their key below the replica's prefix. The queue is
+//! checkpointed to `_replication/pending.json` on the primary storage (at most
+//! every `CHECKPOINT_INTERVAL`, and at shutdown) and reloaded on start, so
+//! copies pending across a restart are not lost. Optionally, replicated files
+//! are recorded in a second SQLite catalog.
+
+use crate::config::{ReplicationConfig, StorageBackend, StorageConfig};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+use super::error::{Result, WriterError};
+use super::manifest::ManifestEntry;
+use super::storage::Target;
+
+const CHECKPOINT_FILE: &str = "_replication/pending.json";
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+/// Longest pause after a failed round
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+static REPLICATOR: OnceCell<Replicator> = OnceCell::new();
+
+struct Replicator {
+    /// Files not yet copied, including the ones in flight, by primary path
+    pending: Mutex<BTreeMap<String, PendingCopy>>,
+    wake: Notify,
+    /// Whether copies need a manifest entry (for the replica catalog)
+    wants_entries: bool,
+    source: opendal::Operator,
+    checkpoint_path: String,
+}
+
+/// A committed file waiting to be copied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingCopy {
+    path: String,
+    table: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entry: Option<ManifestEntry>,
+}
+
+struct Worker {
+    source: opendal::Operator,
+    source_prefix: String,
+    replica: Target,
+    #[cfg(feature = "catalog")]
+    catalog: Option<crate::catalog::Catalog>,
+}
+
+/// Start the replication task if enabled, resuming from the last checkpoint.
+pub async fn start_replication(config: &ReplicationConfig) -> Result<()> {
+    if !config.enabled || REPLICATOR.get().is_some() {
+        return Ok(());
+    }
+    let storage = config.storage.as_ref().ok_or_else(|| {
+        WriterError::invalid_config("replication.storage is required".to_string())
+    })?;
+    let source = super::storage::get_operator()
+        .ok_or_else(|| {
+            WriterError::invalid_config(
+                "Storage must be initialized before replication".to_string(),
+            )
+        })?
+        .clone();
+    let source_prefix = super::storage::get_storage_prefix()
+        .unwrap_or("")
+        .to_string();
+    let replica = Target::build("replica", storage)?;
+
+    #[cfg(feature = "catalog")]
+    let catalog = config
+        .catalog_path
+        .as_ref()
+        .map(|path| crate::catalog::Catalog::open(std::path::Path::new(path)))
+        .transpose()
+        .map_err(|e| WriterError::invalid_config(format!("{:#}", e)))?;
+
+    let checkpoint_path = format!("{}{}", source_prefix, CHECKPOINT_FILE);
+    let pending = load_checkpoint(&source, &checkpoint_path).await;
+    if !pending.is_empty() {
+        tracing::info!(
+            "Resuming replication of {} files from checkpoint",
+            pending.len()
+        );
+    }
+
+    let replicator = REPLICATOR.get_or_init(|| Replicator {
+        pending: Mutex::new(pending),
+        wake: Notify::new(),
+        wants_entries: config.catalog_path.is_some(),
+        source: source.clone(),
+        checkpoint_path,
+    });
+    tokio::spawn(run(
+        replicator,
+        Worker {
+            source,
+            source_prefix,
+            replica,
+            #[cfg(feature = "catalog")]
+            catalog,
+        },
+    ));
+    tracing::info!("Replicating committed files to {}", describe(storage));
+    Ok(())
+}
+
+/// Whether committed files should carry a manifest entry for replication.
+pub(crate) fn wants_entries() -> bool {
+    REPLICATOR.get().is_some_and(|r| r.wants_entries)
+}
+
+/// Queue a file committed to the default storage for copying. No-op when
+/// replication is disabled.
+pub(crate) fn enqueue(table: &str, path: &str, entry: Option<ManifestEntry>) {
+    let Some(replicator) = REPLICATOR.get() else {
+        return;
+    };
+    replicator.pending.lock().insert(
+        path.to_string(),
+        PendingCopy {
+            path: path.to_string(),
+            table: table.to_string(),
+            entry,
+        },
+    );
+    replicator.wake.notify_one();
+}
+
+/// Wait up to `timeout` for pending copies during shutdown, then checkpoint
+/// whatever is left.
+pub async fn drain_replication(timeout: Duration) {
+    let Some(replicator) = REPLICATOR.get() else {
+        return;
+    };
+    let remaining = crate::retry_queue::drain(timeout, || replicator.pending.lock().len()).await;
+    if remaining > 0 {
+        tracing::warn!(
+            pending = remaining,
+            "Replication incomplete at shutdown, pending copies kept in checkpoint"
+        );
+    }
+    replicator.save_checkpoint().await;
+}
+
+impl Replicator {
+    async fn save_checkpoint(&self) {
+        let pending: Vec<PendingCopy> = self.pending.lock().values().cloned().collect();
+        metrics::gauge!("otlp.replication.pending").set(pending.len() as f64);
+        let body = match serde_json::to_vec(&pending) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to encode replication checkpoint");
+                return;
+            }
+        };
+        if let Err(e) = self.source.write(&self.checkpoint_path, body).await {
+            tracing::warn!(error = %e, path = %self.checkpoint_path, "Failed to write replication checkpoint");
+        }
+    }
+}
+
+async fn load_checkpoint(op: &opendal::Operator, path: &str) -> BTreeMap<String, PendingCopy> {
+    let bytes = match op.read(path).await {
+        Ok(bytes) => bytes.to_vec(),
+        Err(e) if e.kind() == opendal::ErrorKind::NotFound => return BTreeMap::new(),
+        Err(e) => {
+            tracing::warn!(error = %e, path = %path, "Failed to read replication checkpoint");
+            return BTreeMap::new();
+        }
+    };
+    match serde_json::from_slice::<Vec<PendingCopy>>(&bytes) {
+        Ok(pending) => pending.into_iter().map(|c| (c.path.clone(), c)).collect(),
+        Err(e) => {
+            tracing::warn!(error = %e, path = %path, "Ignoring unreadable replication checkpoint");
+            BTreeMap::new()
+        }
+    }
+}
+
+async fn run(replicator: &'static Replicator, worker: Worker) {
+    let mut failed_rounds = 0u32;
+    let mut last_checkpoint = Instant::now();
+    let mut dirty = false;
+
+    loop {
+        let round: Vec<PendingCopy> = replicator.pending.lock().values().cloned().collect();
+        if round.is_empty() {
+            // A stale checkpoint only costs redundant copies after a restart
+            if dirty && last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                replicator.save_checkpoint().await;
+                last_checkpoint = Instant::now();
+                dirty = false;
+            }
+            replicator.wake.notified().await;
+            continue;
+        }
+        if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+            replicator.save_checkpoint().await;
+            last_checkpoint = Instant::now();
+        }
+
+        let outcome = copy_round(&worker, &replicator.pending, round).await;
+        if outcome.removed > 0 {
+            dirty = true;
+        }
+        if outcome.failed > 0 {
+            failed_rounds += 1;
+            tokio::time::sleep(crate::retry_queue::backoff(failed_rounds, MAX_BACKOFF)).await;
+        } else {
+            failed_rounds = 0;
+        }
+    }
+}
+
+/// What a round of copies did to the queue
+struct RoundOutcome {
+    /// Entries copied or dropped
+    removed: usize,
+    /// Entries left for the next round after a failed copy
+    failed: usize,
+}
+
+/// Copy each file of `round`, removing it from `pending` once copied or once
+/// its source is gone (rotated, tiered or deleted). A failed copy does not
+/// stop the rest of the round.
+async fn copy_round(
+    worker: &Worker,
+    pending: &Mutex<BTreeMap<String, PendingCopy>>,
+    round: Vec<PendingCopy>,
+) -> RoundOutcome {
+    let mut outcome = RoundOutcome {
+        removed: 0,
+        failed: 0,
+    };
+    for copy in round {
+        match worker.copy(&copy).await {
+            Ok(copied) => {
+                if copied == Copied::SourceMissing {
+                    metrics::counter!("otlp.replication.missing").increment(1);
+                    tracing::warn!(path = %copy.path, "Source file no longer exists, dropped from replication");
+                }
+                pending.lock().remove(&copy.path);
+                outcome.removed += 1;
+            }
+            Err(e) => {
+                metrics::counter!("otlp.replication.failures").increment(1);
+                tracing::warn!(error = %e, path = %copy.path, "Failed to replicate file");
+                outcome.failed += 1;
+            }
+        }
+    }
+    outcome
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Copied {
+    Replicated,
+    /// The primary file is gone; nothing to copy, ever
+    SourceMissing,
+}
+
+impl Worker {
+    /// Replica key for a primary path: same key, replica prefix
+    fn replica_key(&self, path: &str) -> String {
+        let key = path.strip_prefix(&self.source_prefix).unwrap_or(path);
+        format!("{}{}", self.replica.prefix, key)
+    }
+
+    async fn copy(&self, copy: &PendingCopy) -> Result<Copied> {
+        let data = match self.source.read(&copy.path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => return Ok(Copied::SourceMissing),
+            Err(e) => {
+                return Err(WriterError::write_failure(format!(
+                    "Failed to read '{}': {}",
+                    copy.path, e
+                )))
+            }
+        };
+        let bytes = data.len();
+        let key = self.replica_key(&copy.path);
+        self.replica.operator.write(&key, data).await.map_err(|e| {
+            WriterError::write_failure(format!("Failed to write replica '{}': {}", key, e))
+        })?;
+
+        #[cfg(feature = "catalog")]
+        if let (Some(catalog), Some(entry)) = (&self.catalog, &copy.entry) {
+            if let Err(e) = catalog.record(&copy.table, &key, entry) {
+                tracing::warn!(error = %e, path = %key, "Failed to record replica in catalog");
+            }
+        }
+
+        metrics::counter!("otlp.replication.files").increment(1);
+        metrics::counter!("otlp.replication.bytes").increment(bytes as u64);
+        tracing::debug!("Replicated '{}' to '{}' ({} bytes)", copy.path, key, bytes);
+        Ok(Copied::Replicated)
+    }
+}
+
+pub(super) fn describe(storage: &StorageConfig) -> String {
+    match storage.backend {
+        StorageBackend::Fs => storage.fs.as_ref().map(|fs| fs.path.clone()),
+        StorageBackend::S3 => storage.s3.as_ref().map(|s3| format!("s3://{}", s3.bucket)),
+        StorageBackend::R2 => storage.r2.as_ref().map(|r2| format!("r2://{}", r2.bucket)),
+        StorageBackend::Sftp => storage
+            .sftp
+            .as_ref()
+            .map(|sftp| format!("sftp://{}{}", sftp.endpoint, sftp.root)),
+        StorageBackend::Webdav => storage
+            .webdav
+            .as_ref()
+            .map(|webdav| webdav.endpoint.clone()),
+        StorageBackend::Hdfs => storage
+            .hdfs
+            .as_ref()
+            .map(|hdfs| format!("{}{}", hdfs.endpoint.trim_end_matches('/'), hdfs.root)),
+        StorageBackend::Gcs => storage
+            .gcs
+            .as_ref()
+            .map(|gcs| format!("gs://{}", gcs.bucket)),
+        StorageBackend::Azblob => storage
+            .azblob
+            .as_ref()
+            .map(|azblob| format!("azblob://{}/{}", azblob.account_name, azblob.container)),
+        StorageBackend::Null => None,
+    }
+    .unwrap_or_else(|| storage.backend.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fs_operator(dir: &tempfile::TempDir) -> opendal::Operator {
+        opendal::Operator::new(opendal::services::Fs::default().root(dir.path().to_str().unwrap()))
+            .unwrap()
+            .finish()
+    }
+
+    fn worker(primary: &tempfile::TempDir, replica: &tempfile::TempDir) -> Worker {
+        Worker {
+            source: fs_operator(primary),
+            source_prefix: "smoke/".to_string(),
+            replica: Target {
+                name: "replica".to_string(),
+                operator: fs_operator(replica),
+                prefix: "dr/".to_string(),
+            },
+            #[cfg(feature = "catalog")]
+            catalog: None,
+        }
+    }
+
+    fn pending_copy(path: &str) -> PendingCopy {
+        PendingCopy {
+            path: path.to_string(),
+            table: "logs".to_string(),
+            entry: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copies_file_under_replica_prefix() {
+        let primary = tempfile::tempdir().unwrap();
+        let replica = tempfile::tempdir().unwrap();
+        let worker = worker(&primary, &replica);
+        let path = "smoke/logs/web/year=2025/month=01/day=15/hour=10/a.parquet";
+        worker
+            .source
+            .write(path, b"PAR1data".to_vec())
+            .await
+            .unwrap();
+
+        let copied = worker.copy(&pending_copy(path)).await.unwrap();
+        assert_eq!(copied, Copied::Replicated);
+
+        let copied = worker
+            .replica
+            .operator
+            .read("dr/logs/web/year=2025/month=01/day=15/hour=10/a.parquet")
+            .await
+            .unwrap();
+        assert_eq!(copied.to_vec(), b"PAR1data");
+    }
+
+    #[tokio::test]
+    async fn test_missing_source_is_dropped_without_blocking_the_queue() {
+        let primary = tempfile::tempdir().unwrap();
+        let replica = tempfile::tempdir().unwrap();
+        let worker = worker(&primary, &replica);
+        // Path order puts the missing file first
+        let missing = "smoke/logs/web/year=2025/month=01/day=15/hour=10/a.parquet";
+        let present = "smoke/logs/web/year=2025/month=01/day=15/hour=10/b.parquet";
+        worker
+            .source
+            .write(present, b"PAR1data".to_vec())
+            .await
+            .unwrap();
+        let pending = Mutex::new(
+            [missing, present]
+                .into_iter()
+                .map(|path| (path.to_string(), pending_copy(path)))
+                .collect::<BTreeMap<_, _>>(),
+        );
+
+        let round: Vec<PendingCopy> = pending.lock().values().cloned().collect();
+        let outcome = copy_round(&worker, &pending, round).await;
+
+        assert_eq!((outcome.removed, outcome.failed), (2, 0));
+        assert!(pending.lock().is_empty());
+        let copied = worker
+            .replica
+            .operator
+            .read("dr/logs/web/year=2025/month=01/day=15/hour=10/b.parquet")
+            .await
+            .unwrap();
+        assert_eq!(copied.to_vec(), b"PAR1data");
+    }
+}
@@ -0,0 +1,298 @@
+//! Routing of signal batches to alternative storage targets.
+//!
+//! Each `[[routes]]` entry matches on signal, service name pattern and
+//! resource attributes and names its own backend/bucket/prefix. Routes are
+//! checked in order for every batch written; rows claimed by a route are split
+//! off and written to its target, and whatever no route claims goes to the
+//! default storage. Service and signal predicates route whole batches, so
+//! only attribute predicates need to look at individual rows.
+
+use crate::config::RouteConfig;
+use crate::SignalType;
+use arrow::array::{Array, BooleanArray, RecordBatch, StringArray};
+use once_cell::sync::OnceCell;
+use serde_json::{Map, Value};
+
+use super::error::{Result, WriterError};
+use super::storage::Target;
+
+static ROUTES: OnceCell<Vec<Route>> = OnceCell::new();
+
+struct Route {
+    signals: Vec<SignalType>,
+    service: Option<String>,
+    resource_attributes: Vec<(String, String)>,
+    target: Target,
+}
+
+/// Build operators for every configured route.
+pub(crate) fn init_routes(configs: &[RouteConfig]) -> Result<()> {
+    if configs.is_empty() || ROUTES.get().is_some() {
+        return Ok(());
+    }
+    let routes = configs
+        .iter()
+        .map(Route::from_config)
+        .collect::<Result<Vec<_>>>()?;
+    for route in &routes {
+        tracing::info!("Routing {} to {}", route.describe(), route.target.name);
+    }
+    let _ = ROUTES.set(routes);
+    Ok(())
+}
+
+/// Split `batch` between route targets. `None` is the default storage.
+pub(crate) fn resolve(
+    signal_type: SignalType,
+    service_name: &str,
+    batch: &RecordBatch,
+) -> Result<Vec<(Option<&'static Target>, RecordBatch)>> {
+    let routes = ROUTES.get().map(Vec::as_slice).unwrap_or_default();
+    split(routes, signal_type, service_name, batch)
+}
+
+fn split<'a>(
+    routes: &'a [Route],
+    signal_type: SignalType,
+    service_name: &str,
+    batch: &RecordBatch,
+) -> Result<Vec<(Option<&'a Target>, RecordBatch)>> {
+    let mut parts = Vec::new();
+    let mut remaining = batch.clone();
+
+    for route in routes {
+        if remaining.num_rows() == 0 {
+            break;
+        }
+        if !route.matches_batch(signal_type, service_name) {
+            continue;
+        }
+        if route.resource_attributes.is_empty() {
+            parts.push((Some(&route.target), remaining));
+            return Ok(parts);
+        }
+
+        let mask = route.row_mask(&remaining);
+        let claimed = mask.true_count();
+        if claimed == 0 {
+            continue;
+        }
+        let rest = arrow::compute::not(&mask)
+            .and_then(|unclaimed| arrow::compute::filter_record_batch(&remaining, &unclaimed));
+        let routed = arrow::compute::filter_record_batch(&remaining, &mask);
+        match (routed, rest) {
+            (Ok(routed), Ok(rest)) => {
+                parts.push((Some(&route.target), routed));
+                remaining = rest;
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                return Err(WriterError::write_failure(format!(
+                    "Failed to split batch for route '{}': {}",
+                    route.target.name, e
+                )))
+            }
+        }
+    }
+
+    if remaining.num_rows() > 0 {
+        parts.push((None, remaining));
+    }
+    Ok(parts)
+}
+
+impl Route {
+    fn from_config(config: &RouteConfig) -> Result<Self> {
+        let signals = config
+            .signals
+            .iter()
+            .filter_map(|signal| match signal.as_str() {
+                "logs" => Some(SignalType::Logs),
+                "traces" => Some(SignalType::Traces),
+                "metrics" => Some(SignalType::Metrics),
+                _ => None,
+            })
+            .collect();
+        Ok(Self {
+            signals,
+            service: config.service.clone(),
+            resource_attributes: config
+                .resource_attributes
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            target: Target::build(&config.name, &config.storage)?,
+        })
+    }
+
+    fn describe(&self) -> String {
+        let signals = if self.signals.is_empty() {
+            "all signals".to_string()
+        } else {
+            self.signals
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<_>>()
+                .join("/")
+        };
+        let mut text = signals;
+        if let Some(service) = &self.service {
+            text.push_str(&format!(" of services '{}'", service));
+        }
+        for (key, value) in &self.resource_attributes {
+            text.push_str(&format!(" with {}={}", key, value));
+        }
+        text
+    }
+
+    fn matches_batch(&self, signal_type: SignalType, service_name: &str) -> bool {
+        (self.signals.is_empty() || self.signals.contains(&signal_type))
+            && self
+                .service
+                .as_deref()
+                .is_none_or(|pattern| glob_match(pattern, service_name))
+    }
+
+    /// Rows whose resource attributes satisfy every predicate
+    fn row_mask(&self, batch: &RecordBatch) -> BooleanArray {
+        let Some(column) = batch
+            .column_by_name("resource_attributes")
+            .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+        else {
+            return BooleanArray::from(vec![false; batch.num_rows()]);
+        };
+        // Rows of one service usually share their resource; reuse the last verdict
+        let mut last: Option<(&str, bool)> = None;
+        (0..column.len())
+            .map(|row| {
+                if column.is_null(row) {
+                    return Some(false);
+                }
+                let raw = column.value(row);
+                if let Some((prev, verdict)) = last {
+                    if prev == raw {
+                        return Some(verdict);
+                    }
+                }
+                let verdict = self.matches_attributes(raw);
+                last = Some((raw, verdict));
+                Some(verdict)
+            })
+            .collect()
+    }
+
+    fn matches_attributes(&self, raw: &str) -> bool {
+        let Ok(attributes) = serde_json::from_str::<Map<String, Value>>(raw) else {
+            return false;
+        };
+        self.resource_attributes
+            .iter()
+            .all(|(key, expected)| match attributes.get(key) {
+                None | Some(Value::Null) => false,
+                Some(_) if expected == "*" => true,
+                Some(Value::String(value)) => value == expected,
+                Some(other) => other.to_string() == *expected,
+            })
+    }
+}
+
+/// Match `text` against a pattern where `*` stands for any run of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all: exact match
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use std::sync::Arc;
+
+    fn route(name: &str, service: Option<&str>, attributes: &[(&str, &str)]) -> Route {
+        Route {
+            signals: vec![SignalType::Logs],
+            service: service.map(String::from),
+            resource_attributes: attributes
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            target: Target {
+                name: name.to_string(),
+                operator: opendal::Operator::new(opendal::services::Fs::default().root("/tmp"))
+                    .unwrap()
+                    .finish(),
+                prefix: String::new(),
+            },
+        }
+    }
+
+    fn batch(resources: &[&str]) -> RecordBatch {
+        RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new(
+                "resource_attributes",
+                DataType::Utf8,
+                true,
+            )])),
+            vec![Arc::new(StringArray::from(resources.to_vec()))],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("payments-*", "payments-api"));
+        assert!(glob_match("*-api", "payments-api"));
+        assert!(glob_match("pay*ts*api", "payments-api"));
+        assert!(glob_match("checkout", "checkout"));
+        assert!(!glob_match("checkout", "checkout-2"));
+        assert!(!glob_match("payments-*", "billing"));
+        assert!(!glob_match("a*a", "a"));
+    }
+
+    #[test]
+    fn test_splits_rows_by_attribute_and_service() {
+        let routes = vec![
+            route("pci", None, &[("compliance.scope", "pci")]),
+            route("payments", Some("payments-*"), &[]),
+        ];
+        let input = batch(&[
+            r#"{"compliance.scope":"pci"}"#,
+            r#"{"compliance.scope":"none"}"#,
+            r#"{"compliance.scope":"pci"}"#,
+        ]);
+
+        let parts = split(&routes, SignalType::Logs, "checkout", &input).unwrap();
+        let summary: Vec<(Option<&str>, usize)> = parts
+            .iter()
+            .map(|(t, b)| (t.map(|t| t.name.as_str()), b.num_rows()))
+            .collect();
+        assert_eq!(summary, vec![(Some("pci"), 2), (None, 1)]);
+
+        let parts = split(&routes, SignalType::Logs, "payments-api", &input).unwrap();
+        let summary: Vec<(Option<&str>, usize)> = parts
+            .iter()
+            .map(|(t, b)| (t.map(|t| t.name.as_str()), b.num_rows()))
+            .collect();
+        assert_eq!(summary, vec![(Some("pci"), 2), (Some("payments"), 1)]);
+
+        // Other signals fall through to the default storage untouched
+        let parts = split(&routes, SignalType::Traces, "payments-api", &input).unwrap();
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].0.is_none());
+        assert_eq!(parts[0].1.num_rows(), 3);
+    }
+}
@@ -0,0 +1,233 @@
+//! Sampling probability and adjusted-count columns.
+//!
+//! With `schema.sampling_columns`, log and span rows get
+//! `sampling_probability` and `sampling_adjusted_count` columns so queries can
+//! reweight sampled data (`sum(sampling_adjusted_count)` instead of
+//! `count(*)`). The sampling decision is read, per row, from:
+//!
+//! 1. a `sampling.adjusted_count` attribute,
+//! 2. an OTEP-235 rejection threshold: the `sampling.threshold` attribute or
+//!    `th` in the `ot` member of the W3C trace state,
+//! 3. a legacy OTEP-168 `p` value in the `ot` trace state member.
+//!
+//! Rows without any of these count as unsampled (probability and adjusted
+//! count 1).
+
+use crate::SignalType;
+use arrow::array::{Array, ArrayRef, Float64Array, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::error::{Result, WriterError};
+
+const PROBABILITY_COLUMN: &str = "sampling_probability";
+const ADJUSTED_COUNT_COLUMN: &str = "sampling_adjusted_count";
+
+/// OTEP-235 thresholds are 56-bit
+const THRESHOLD_MAX: f64 = (1u64 << 56) as f64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rate {
+    probability: f64,
+    adjusted_count: f64,
+}
+
+impl Rate {
+    const UNSAMPLED: Rate = Rate {
+        probability: 1.0,
+        adjusted_count: 1.0,
+    };
+
+    fn from_probability(probability: f64) -> Option<Self> {
+        (probability > 0.0 && probability <= 1.0).then(|| Rate {
+            probability,
+            adjusted_count: 1.0 / probability,
+        })
+    }
+}
+
+/// `batch` with sampling columns appended (borrowed for metrics, or when the
+/// columns already exist).
+pub(crate) fn add_sampling_columns(
+    batch: &RecordBatch,
+    signal_type: SignalType,
+) -> Result<Cow<'_, RecordBatch>> {
+    let attributes_column = match signal_type {
+        SignalType::Logs => "log_attributes",
+        SignalType::Traces => "span_attributes",
+        SignalType::Metrics => return Ok(Cow::Borrowed(batch)),
+    };
+    let schema = batch.schema();
+    if schema.column_with_name(ADJUSTED_COUNT_COLUMN).is_some() {
+        return Ok(Cow::Borrowed(batch));
+    }
+    let string_column = |name: &str| {
+        batch
+            .column_by_name(name)
+            .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+    };
+    let attributes = string_column(attributes_column);
+    let trace_state = string_column("trace_state");
+
+    let mut from_attributes: HashMap<&str, Option<Rate>> = HashMap::new();
+    let mut from_trace_state: HashMap<&str, Option<Rate>> = HashMap::new();
+    let rates: Vec<Rate> = (0..batch.num_rows())
+        .map(|row| {
+            let by_attributes = attributes.filter(|c| c.is_valid(row)).and_then(|c| {
+                let raw = c.value(row);
+                *from_attributes
+                    .entry(raw)
+                    .or_insert_with(|| rate_from_attributes(raw))
+            });
+            by_attributes
+                .or_else(|| {
+                    trace_state.filter(|c| c.is_valid(row)).and_then(|c| {
+                        let raw = c.value(row);
+                        *from_trace_state
+                            .entry(raw)
+                            .or_insert_with(|| rate_from_trace_state(raw))
+                    })
+                })
+                .unwrap_or(Rate::UNSAMPLED)
+        })
+        .collect();
+    let probabilities = Float64Array::from_iter_values(rates.iter().map(|r| r.probability));
+    let adjusted_counts = Float64Array::from_iter_values(rates.iter().map(|r| r.adjusted_count));
+
+    let mut fields: Vec<Field> = schema.fields().iter().map(|f| f.as_ref().clone()).collect();
+    fields.push(Field::new(PROBABILITY_COLUMN, DataType::Float64, false));
+    fields.push(Field::new(ADJUSTED_COUNT_COLUMN, DataType::Float64, false));
+    let mut columns = batch.columns().to_vec();
+    columns.push(Arc::new(probabilities) as ArrayRef);
+    columns.push(Arc::new(adjusted_counts) as ArrayRef);
+
+    let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+    RecordBatch::try_new(Arc::new(schema), columns)
+        .map(Cow::Owned)
+        .map_err(|e| WriterError::write_failure(format!("Failed to add sampling columns: {}", e)))
+}
+
+/// Sampling rate from `sampling.adjusted_count` or `sampling.threshold`
+fn rate_from_attributes(raw: &str) -> Option<Rate> {
+    // Cheap pre-check: most records carry no sampling attributes
+    if !raw.contains("sampling.") {
+        return None;
+    }
+    let attributes: Map<String, Value> = serde_json::from_str(raw).ok()?;
+    let adjusted_count = attributes
+        .get("sampling.adjusted_count")
+        .and_then(|v| match v {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        });
+    if let Some(count) = adjusted_count.filter(|c| *c >= 1.0 && c.is_finite()) {
+        return Some(Rate {
+            probability: 1.0 / count,
+            adjusted_count: count,
+        });
+    }
+    attributes
+        .get("sampling.threshold")
+        .and_then(Value::as_str)
+        .and_then(threshold_rate)
+}
+
+/// Sampling rate from the `ot` member of a W3C trace state, e.g.
+/// `ot=th:c;rv:...` (OTEP-235) or `ot=p:3` (OTEP-168)
+fn rate_from_trace_state(raw: &str) -> Option<Rate> {
+    let ot = raw
+        .split(',')
+        .find_map(|member| member.trim().strip_prefix("ot="))?;
+    let mut legacy = None;
+    for field in ot.split(';') {
+        match field.split_once(':') {
+            Some(("th", threshold)) => return threshold_rate(threshold),
+            Some(("p", p)) => {
+                legacy = p
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|p| *p <= 62)
+                    .and_then(|p| Rate::from_probability(0.5f64.powi(p as i32)))
+            }
+            _ => {}
+        }
+    }
+    legacy
+}
+
+/// Rate of an OTEP-235 rejection threshold: up to 14 hex digits, trailing
+/// zeros omitted (`0` keeps everything, `8` half).
+fn threshold_rate(threshold: &str) -> Option<Rate> {
+    if threshold.is_empty()
+        || threshold.len() > 14
+        || !threshold.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return None;
+    }
+    let padded = format!("{:0<14}", threshold);
+    let rejected = u64::from_str_radix(&padded, 16).ok()? as f64;
+    Rate::from_probability((THRESHOLD_MAX - rejected) / THRESHOLD_MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::AsArray;
+    use arrow::datatypes::Float64Type;
+
+    #[test]
+    fn test_threshold_and_trace_state_parsing() {
+        let probability = |rate: Option<Rate>| rate.map(|r| r.probability);
+        assert_eq!(probability(threshold_rate("0")), Some(1.0));
+        assert_eq!(probability(threshold_rate("8")), Some(0.5));
+        assert_eq!(probability(threshold_rate("c")), Some(0.25));
+        assert_eq!(threshold_rate("xyz"), None);
+        assert_eq!(
+            probability(rate_from_trace_state("vendor=1,ot=rv:abc;th:c")),
+            Some(0.25)
+        );
+        assert_eq!(probability(rate_from_trace_state("ot=p:3")), Some(0.125));
+        assert_eq!(rate_from_trace_state("vendor=1"), None);
+    }
+
+    #[test]
+    fn test_adds_columns_from_attributes_and_trace_state() {
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("trace_state", DataType::Utf8, true),
+                Field::new("span_attributes", DataType::Utf8, true),
+            ])),
+            vec![
+                Arc::new(StringArray::from(vec![
+                    Some("ot=th:8"),
+                    Some("ot=th:8"),
+                    None,
+                    None,
+                ])),
+                Arc::new(StringArray::from(vec![
+                    Some("{}"),
+                    Some(r#"{"sampling.adjusted_count":10}"#),
+                    Some(r#"{"sampling.threshold":"c"}"#),
+                    None,
+                ])),
+            ],
+        )
+        .unwrap();
+
+        let with_columns = add_sampling_columns(&batch, SignalType::Traces).unwrap();
+        let adjusted = with_columns
+            .column_by_name(ADJUSTED_COUNT_COLUMN)
+            .unwrap()
+            .as_primitive::<Float64Type>();
+        assert_eq!(adjusted.values().to_vec(), vec![2.0, 10.0, 4.0, 1.0]);
+
+        assert!(matches!(
+            add_sampling_columns(&batch, SignalType::Metrics).unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -0,0 +1,115 @@
+//! Span kind and status names.
+//!
+//! The traces table stores `span_kind` and `status_code` as OTLP enum
+//! numbers. With `schema.span_text_columns`, spans also get `span_kind_text`
+//! (`SERVER`, `CLIENT`, ...) and `status_code_text` (`Unset`, `Ok`, `Error`)
+//! so queries need no lookup table. Parquet dictionary-encodes the repeated
+//! names, so the numbers stay the compact representation on disk.
+
+use crate::SignalType;
+use arrow::array::{ArrayRef, AsArray, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Int32Type, Schema};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use super::error::{Result, WriterError};
+
+const KIND_COLUMN: &str = "span_kind_text";
+const STATUS_COLUMN: &str = "status_code_text";
+
+/// `batch` with the text columns appended (borrowed for logs and metrics, or
+/// when the columns already exist).
+pub(crate) fn add_span_text_columns(
+    batch: &RecordBatch,
+    signal_type: SignalType,
+) -> Result<Cow<'_, RecordBatch>> {
+    let schema = batch.schema();
+    if signal_type != SignalType::Traces || schema.column_with_name(KIND_COLUMN).is_some() {
+        return Ok(Cow::Borrowed(batch));
+    }
+
+    let names = |column: &str, name: fn(i32) -> Option<&'static str>| -> StringArray {
+        match batch
+            .column_by_name(column)
+            .and_then(|c| c.as_primitive_opt::<Int32Type>())
+        {
+            Some(codes) => codes.iter().map(|code| code.and_then(name)).collect(),
+            None => StringArray::new_null(batch.num_rows()),
+        }
+    };
+    let kinds = names("span_kind", span_kind_name);
+    let statuses = names("status_code", status_code_name);
+
+    let mut fields: Vec<Field> = schema.fields().iter().map(|f| f.as_ref().clone()).collect();
+    fields.push(Field::new(KIND_COLUMN, DataType::Utf8, true));
+    fields.push(Field::new(STATUS_COLUMN, DataType::Utf8, true));
+    let mut columns = batch.columns().to_vec();
+    columns.push(Arc::new(kinds) as ArrayRef);
+    columns.push(Arc::new(statuses) as ArrayRef);
+
+    let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+    RecordBatch::try_new(Arc::new(schema), columns)
+        .map(Cow::Owned)
+        .map_err(|e| WriterError::write_failure(format!("Failed to add span text columns: {}", e)))
+}
+
+/// Name of an OTLP `SpanKind`, `None` for unknown numbers
+fn span_kind_name(code: i32) -> Option<&'static str> {
+    match code {
+        0 => Some("UNSPECIFIED"),
+        1 => Some("INTERNAL"),
+        2 => Some("SERVER"),
+        3 => Some("CLIENT"),
+        4 => Some("PRODUCER"),
+        5 => Some("CONSUMER"),
+        _ => None,
+    }
+}
+
+/// Name of an OTLP `Status.StatusCode`, `None` for unknown numbers
+fn status_code_name(code: i32) -> Option<&'static str> {
+    match code {
+        0 => Some("Unset"),
+        1 => Some("Ok"),
+        2 => Some("Error"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, Int32Array};
+
+    #[test]
+    fn test_span_text_columns() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("span_kind", DataType::Int32, true),
+            Field::new("status_code", DataType::Int32, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![Some(2), Some(3), Some(9)])),
+                Arc::new(Int32Array::from(vec![Some(0), Some(2), None])),
+            ],
+        )
+        .unwrap();
+
+        let with_text = add_span_text_columns(&batch, SignalType::Traces).unwrap();
+        let kinds = with_text.column(2).as_string::<i32>();
+        let statuses = with_text.column(3).as_string::<i32>();
+        assert_eq!((kinds.value(0), statuses.value(0)), ("SERVER", "Unset"));
+        assert_eq!((kinds.value(1), statuses.value(1)), ("CLIENT", "Error"));
+        assert!(kinds.is_null(2) && statuses.is_null(2));
+
+        assert!(matches!(
+            add_span_text_columns(&with_text, SignalType::Traces).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(
+            add_span_text_columns(&batch, SignalType::Logs).unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -1,12 +1,65 @@
 //! Storage operator initialization and management.
 
-use crate::config::{RuntimeConfig, StorageBackend};
+use crate::config::{
+    FileNaming, FsyncPolicy, ParquetCompression, RuntimeConfig, SchemaConfig, StorageBackend,
+    StorageConfig,
+};
 use once_cell::sync::OnceCell;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use super::error::{Result, WriterError};
 
 static OPERATOR: OnceCell<opendal::Operator> = OnceCell::new();
 static STORAGE_PREFIX: OnceCell<Option<String>> = OnceCell::new();
+static WRITE_OPTIONS: OnceCell<WriteOptions> = OnceCell::new();
+#[cfg(feature = "catalog")]
+static CATALOG: OnceCell<crate::catalog::Catalog> = OnceCell::new();
+
+/// Directory below the filesystem root holding files being written
+/// (`fs.atomic_writes`); table globs never reach it
+pub(crate) const TEMP_DIR: &str = ".tmp";
+
+/// Temp files older than this at startup were left behind by a crash
+const STALE_TEMP_AGE: Duration = Duration::from_secs(3600);
+
+/// A storage location other than the default one (route or fallback)
+pub(crate) struct Target {
+    pub name: String,
+    pub operator: opendal::Operator,
+    pub prefix: String,
+}
+
+impl Target {
+    pub(crate) fn build(name: &str, storage: &StorageConfig) -> Result<Self> {
+        Ok(Self {
+            name: name.to_string(),
+            operator: build_operator(storage)?,
+            prefix: storage_prefix(storage).unwrap_or_default(),
+        })
+    }
+}
+
+/// Per-write behaviour toggles resolved from `StorageConfig`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WriteOptions {
+    pub verify_after_write: bool,
+    pub write_manifests: bool,
+    pub file_naming: FileNaming,
+    pub write_shards: u16,
+    pub compression: ParquetCompression,
+    /// Codec level for gzip/zstd; validated with the config
+    pub compression_level: Option<u32>,
+    /// Bloom filters on the trace and span id columns
+    pub bloom_filters: bool,
+    pub catalog: bool,
+    pub schema: SchemaConfig,
+    /// Discard encoded files instead of storing them (`null` backend)
+    pub dry_run: bool,
+    /// Filesystem root whose partition directories are synced after each
+    /// write (`fs.fsync = "directory"`)
+    pub sync_dir: Option<PathBuf>,
+}
 
 /// Initialize storage operator from RuntimeConfig.
 pub fn initialize_storage(config: &RuntimeConfig) -> Result<()> {
@@ -14,13 +67,83 @@ pub fn initialize_storage(config: &RuntimeConfig) -> Result<()> {
         return Ok(());
     }
 
-    let operator = match config.storage.backend {
+    let operator = build_operator(&config.storage)?;
+    let _ = STORAGE_PREFIX.set(storage_prefix(&config.storage));
+
+    let _ = WRITE_OPTIONS.set(WriteOptions {
+        verify_after_write: config.storage.verify_after_write,
+        write_manifests: config.storage.write_manifests,
+        file_naming: config.storage.file_naming,
+        write_shards: config.storage.write_shards,
+        compression: config.storage.parquet_compression,
+        compression_level: config.storage.parquet_compression_level,
+        bloom_filters: config.storage.parquet_bloom_filters,
+        catalog: config.catalog.enabled,
+        schema: config.schema.clone(),
+        dry_run: config.storage.backend == StorageBackend::Null,
+        sync_dir: config
+            .storage
+            .fs
+            .as_ref()
+            .filter(|fs| {
+                config.storage.backend == StorageBackend::Fs && fs.fsync == FsyncPolicy::Directory
+            })
+            .map(|fs| PathBuf::from(&fs.path)),
+    });
+
+    if let Some(fs) = config
+        .storage
+        .fs
+        .as_ref()
+        .filter(|fs| config.storage.backend == StorageBackend::Fs && fs.atomic_writes)
+    {
+        let removed = remove_stale_temp_files(&Path::new(&fs.path).join(TEMP_DIR), STALE_TEMP_AGE);
+        if removed > 0 {
+            tracing::info!("Removed {} partial files left by an earlier crash", removed);
+        }
+    }
+
+    super::routing::init_routes(&config.routes)?;
+    super::fallback::init_fallbacks(&config.fallbacks)?;
+
+    #[cfg(feature = "catalog")]
+    if let Some(path) = config
+        .catalog
+        .enabled
+        .then(|| config.catalog.resolved_path(&config.storage))
+        .flatten()
+    {
+        let catalog = crate::catalog::Catalog::open(&path)
+            .map_err(|e| WriterError::invalid_config(format!("{:#}", e)))?;
+        tracing::info!("Recording written files in catalog {}", path.display());
+        let _ = CATALOG.set(catalog);
+    }
+
+    match OPERATOR.set(operator) {
+        Ok(_) => {
+            tracing::debug!("Storage operator initialized");
+            Ok(())
+        }
+        Err(_) => {
+            tracing::debug!("Storage operator already initialized by another call");
+            Ok(())
+        }
+    }
+}
+
+/// Build an operator for the configured backend without registering it globally.
+pub(crate) fn build_operator(storage: &StorageConfig) -> Result<opendal::Operator> {
+    let operator = match storage.backend {
         StorageBackend::Fs => {
-            let fs = config.storage.fs.as_ref().ok_or_else(|| {
+            let fs = storage.fs.as_ref().ok_or_else(|| {
                 WriterError::invalid_config("fs config required for filesystem backend".to_string())
             })?;
 
-            let fs_builder = opendal::services::Fs::default().root(&fs.path);
+            let mut fs_builder = opendal::services::Fs::default().root(&fs.path);
+            if fs.atomic_writes {
+                let temp_dir = Path::new(&fs.path).join(TEMP_DIR);
+                fs_builder = fs_builder.atomic_write_dir(&temp_dir.to_string_lossy());
+            }
             opendal::Operator::new(fs_builder)
                 .map_err(|e| {
                     WriterError::write_failure(format!(
@@ -31,12 +154,10 @@ pub fn initialize_storage(config: &RuntimeConfig) -> Result<()> {
                 .finish()
         }
         StorageBackend::S3 => {
-            let s3 = config.storage.s3.as_ref().ok_or_else(|| {
+            let s3 = storage.s3.as_ref().ok_or_else(|| {
                 WriterError::invalid_config("s3 config required for S3 backend".to_string())
             })?;
 
-            let _ = STORAGE_PREFIX.set(s3.prefix.clone());
-
             let mut s3_builder = opendal::services::S3::default()
                 .bucket(&s3.bucket)
                 .region(&s3.region);
@@ -44,6 +165,9 @@ pub fn initialize_storage(config: &RuntimeConfig) -> Result<()> {
             if let Some(endpoint) = &s3.endpoint {
                 s3_builder = s3_builder.endpoint(endpoint);
             }
+            if let Some(class) = &s3.storage_class {
+                s3_builder = s3_builder.default_storage_class(class);
+            }
 
             opendal::Operator::new(s3_builder)
                 .map_err(|e| {
@@ -52,12 +176,10 @@ pub fn initialize_storage(config: &RuntimeConfig) -> Result<()> {
                 .finish()
         }
         StorageBackend::R2 => {
-            let r2 = config.storage.r2.as_ref().ok_or_else(|| {
+            let r2 = storage.r2.as_ref().ok_or_else(|| {
                 WriterError::invalid_config("r2 config required for R2 backend".to_string())
             })?;
 
-            let _ = STORAGE_PREFIX.set(r2.prefix.clone());
-
             let endpoint = r2
                 .endpoint
                 .clone()
@@ -76,17 +198,244 @@ pub fn initialize_storage(config: &RuntimeConfig) -> Result<()> {
                 })?
                 .finish()
         }
-    };
+        #[cfg(feature = "sftp")]
+        StorageBackend::Sftp => {
+            let sftp = storage.sftp.as_ref().ok_or_else(|| {
+                WriterError::invalid_config("sftp config required for SFTP backend".to_string())
+            })?;
 
-    match OPERATOR.set(operator) {
-        Ok(_) => {
-            tracing::debug!("Storage operator initialized");
-            Ok(())
+            let mut sftp_builder = opendal::services::Sftp::default()
+                .endpoint(&sftp.endpoint)
+                .root(&sftp.root)
+                .known_hosts_strategy(&sftp.known_hosts);
+            if let Some(user) = &sftp.user {
+                sftp_builder = sftp_builder.user(user);
+            }
+            if let Some(key) = &sftp.key {
+                sftp_builder = sftp_builder.key(key);
+            }
+
+            opendal::Operator::new(sftp_builder)
+                .map_err(|e| {
+                    WriterError::write_failure(format!("Failed to create SFTP operator: {}", e))
+                })?
+                .finish()
         }
-        Err(_) => {
-            tracing::debug!("Storage operator already initialized by another call");
-            Ok(())
+        #[cfg(feature = "webdav")]
+        StorageBackend::Webdav => {
+            let webdav = storage.webdav.as_ref().ok_or_else(|| {
+                WriterError::invalid_config("webdav config required for WebDAV backend".to_string())
+            })?;
+
+            let mut webdav_builder = opendal::services::Webdav::default()
+                .endpoint(&webdav.endpoint)
+                .root(&webdav.root);
+            if let Some(username) = &webdav.username {
+                webdav_builder = webdav_builder.username(username);
+            }
+            if let Some(password) = &webdav.password {
+                webdav_builder = webdav_builder.password(password);
+            }
+            if let Some(token) = &webdav.token {
+                webdav_builder = webdav_builder.token(token);
+            }
+
+            opendal::Operator::new(webdav_builder)
+                .map_err(|e| {
+                    WriterError::write_failure(format!("Failed to create WebDAV operator: {}", e))
+                })?
+                .finish()
         }
+        #[cfg(feature = "hdfs")]
+        StorageBackend::Hdfs => {
+            let hdfs = storage.hdfs.as_ref().ok_or_else(|| {
+                WriterError::invalid_config("hdfs config required for HDFS backend".to_string())
+            })?;
+
+            let mut hdfs_builder = opendal::services::Webhdfs::default()
+                .endpoint(&hdfs.endpoint)
+                .root(&hdfs.root);
+            if let Some(user) = &hdfs.user {
+                hdfs_builder = hdfs_builder.user_name(user);
+            }
+            if let Some(token) = &hdfs.delegation_token {
+                hdfs_builder = hdfs_builder.delegation(token);
+            }
+            if hdfs.disable_list_batch {
+                hdfs_builder = hdfs_builder.disable_list_batch();
+            }
+
+            opendal::Operator::new(hdfs_builder)
+                .map_err(|e| {
+                    WriterError::write_failure(format!("Failed to create HDFS operator: {}", e))
+                })?
+                .finish()
+        }
+        #[cfg(feature = "gcs")]
+        StorageBackend::Gcs => {
+            let gcs = storage.gcs.as_ref().ok_or_else(|| {
+                WriterError::invalid_config("gcs config required for GCS backend".to_string())
+            })?;
+
+            let mut gcs_builder = opendal::services::Gcs::default().bucket(&gcs.bucket);
+            if let Some(path) = &gcs.credential_path {
+                gcs_builder = gcs_builder.credential_path(path);
+            }
+            if let Some(credential) = &gcs.credential {
+                gcs_builder = gcs_builder.credential(credential);
+            }
+            if let Some(endpoint) = &gcs.endpoint {
+                gcs_builder = gcs_builder.endpoint(endpoint);
+            }
+            if gcs.allow_anonymous {
+                gcs_builder = gcs_builder.allow_anonymous().disable_vm_metadata();
+            }
+
+            opendal::Operator::new(gcs_builder)
+                .map_err(|e| {
+                    WriterError::write_failure(format!("Failed to create GCS operator: {}", e))
+                })?
+                .finish()
+        }
+        #[cfg(feature = "azblob")]
+        StorageBackend::Azblob => {
+            let azblob = storage.azblob.as_ref().ok_or_else(|| {
+                WriterError::invalid_config(
+                    "azblob config required for Azure Blob backend".to_string(),
+                )
+            })?;
+
+            let mut azblob_builder = opendal::services::Azblob::default()
+                .container(&azblob.container)
+                .account_name(&azblob.account_name)
+                .endpoint(&azblob.endpoint_or_default());
+            if let Some(key) = &azblob.account_key {
+                azblob_builder = azblob_builder.account_key(key);
+            }
+            if let Some(token) = &azblob.sas_token {
+                azblob_builder = azblob_builder.sas_token(token);
+            }
+
+            opendal::Operator::new(azblob_builder)
+                .map_err(|e| {
+                    WriterError::write_failure(format!(
+                        "Failed to create Azure Blob operator: {}",
+                        e
+                    ))
+                })?
+                .finish()
+        }
+        #[cfg(not(feature = "sftp"))]
+        StorageBackend::Sftp => {
+            return Err(WriterError::invalid_config(
+                "the sftp backend requires building with the `sftp` feature".to_string(),
+            ))
+        }
+        #[cfg(not(feature = "webdav"))]
+        StorageBackend::Webdav => {
+            return Err(WriterError::invalid_config(
+                "the webdav backend requires building with the `webdav` feature".to_string(),
+            ))
+        }
+        #[cfg(not(feature = "hdfs"))]
+        StorageBackend::Hdfs => {
+            return Err(WriterError::invalid_config(
+                "the hdfs backend requires building with the `hdfs` feature".to_string(),
+            ))
+        }
+        #[cfg(not(feature = "gcs"))]
+        StorageBackend::Gcs => {
+            return Err(WriterError::invalid_config(
+                "the gcs backend requires building with the `gcs` feature".to_string(),
+            ))
+        }
+        #[cfg(not(feature = "azblob"))]
+        StorageBackend::Azblob => {
+            return Err(WriterError::invalid_config(
+                "the azblob backend requires building with the `azblob` feature".to_string(),
+            ))
+        }
+        // Nothing is written in a dry run; an empty store keeps listing and
+        // stat callers (probe, startup checks) working
+        StorageBackend::Null => opendal::Operator::new(opendal::services::Memory::default())
+            .map_err(|e| {
+                WriterError::write_failure(format!("Failed to create null operator: {}", e))
+            })?
+            .finish(),
+    };
+
+    if storage.write_retries == 0 {
+        return Ok(operator);
+    }
+    // Only temporary errors (throttling, 5xx, timeouts) are retried
+    Ok(operator.layer(
+        opendal::layers::RetryLayer::new()
+            .with_max_times(storage.write_retries as usize)
+            .with_jitter(),
+    ))
+}
+
+/// Delete files in `temp_dir` older than `max_age`; returns how many.
+fn remove_stale_temp_files(temp_dir: &Path, max_age: Duration) -> usize {
+    let Ok(entries) = std::fs::read_dir(temp_dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .metadata()
+                .ok()
+                .filter(|meta| meta.is_file())
+                .and_then(|meta| meta.modified().ok())
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age >= max_age)
+        })
+        .filter(|entry| std::fs::remove_file(entry.path()).is_ok())
+        .count()
+}
+
+/// Sync the directory holding `path` (relative to the filesystem `root`) so
+/// a file just renamed into it survives power loss.
+pub(crate) async fn sync_parent_dir(root: &Path, path: &str) -> Result<()> {
+    let file = root.join(path);
+    let dir = file.parent().unwrap_or(root).to_path_buf();
+    let shown = dir.display().to_string();
+    tokio::task::spawn_blocking(move || sync_dir(&dir))
+        .await
+        .map_err(|e| WriterError::write_failure(format!("Directory sync task failed: {}", e)))?
+        .map_err(|e| {
+            WriterError::write_failure(format!("Failed to sync directory '{}': {}", shown, e))
+        })
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::File::open(dir)?.sync_all()
+}
+
+/// Directories cannot be opened for syncing here; NTFS journals renames
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Path prefix applied to every object key (S3/R2/GCS/Azure only).
+pub(crate) fn storage_prefix(storage: &StorageConfig) -> Option<String> {
+    match storage.backend {
+        // SFTP, WebDAV and HDFS put files below their `root`
+        StorageBackend::Fs
+        | StorageBackend::Sftp
+        | StorageBackend::Webdav
+        | StorageBackend::Hdfs
+        | StorageBackend::Null => None,
+        StorageBackend::S3 => storage.s3.as_ref().and_then(|s3| s3.prefix.clone()),
+        StorageBackend::R2 => storage.r2.as_ref().and_then(|r2| r2.prefix.clone()),
+        StorageBackend::Gcs => storage.gcs.as_ref().and_then(|gcs| gcs.prefix.clone()),
+        StorageBackend::Azblob => storage
+            .azblob
+            .as_ref()
+            .and_then(|azblob| azblob.prefix.clone()),
     }
 }
 
@@ -102,3 +451,69 @@ pub(crate) fn get_storage_prefix() -> Option<&'static str> {
         .and_then(|opt| opt.as_ref())
         .map(|s| s.as_str())
 }
+
+/// Get the local file catalog, if enabled.
+#[cfg(feature = "catalog")]
+pub(crate) fn get_catalog() -> Option<&'static crate::catalog::Catalog> {
+    CATALOG.get()
+}
+
+/// Get the write options configured at initialization (defaults if uninitialized).
+pub(crate) fn write_options() -> WriteOptions {
+    WRITE_OPTIONS.get().cloned().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FsConfig;
+
+    #[tokio::test]
+    async fn test_atomic_fs_writes_leave_no_partial_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_str().unwrap().to_string();
+        let storage = StorageConfig {
+            backend: StorageBackend::Fs,
+            fs: Some(FsConfig {
+                path: root,
+                ..FsConfig::default()
+            }),
+            s3: None,
+            r2: None,
+            sftp: None,
+            webdav: None,
+            hdfs: None,
+            gcs: None,
+            azblob: None,
+            verify_after_write: false,
+            write_manifests: false,
+            file_naming: FileNaming::Timestamp,
+            parquet_compression: ParquetCompression::Uncompressed,
+            parquet_compression_level: None,
+            parquet_bloom_filters: false,
+            write_shards: 0,
+            write_retries: 0,
+        };
+        let op = build_operator(&storage).unwrap();
+        op.write("logs/api/a.parquet", vec![1u8; 64]).await.unwrap();
+        sync_parent_dir(dir.path(), "logs/api/a.parquet")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read(dir.path().join("logs/api/a.parquet"))
+                .unwrap()
+                .len(),
+            64
+        );
+        let temp_dir = dir.path().join(TEMP_DIR);
+        assert_eq!(std::fs::read_dir(&temp_dir).unwrap().count(), 0);
+
+        std::fs::write(temp_dir.join("b.parquet.1234"), b"partial").unwrap();
+        assert_eq!(
+            remove_stale_temp_files(&temp_dir, Duration::from_secs(3600)),
+            0
+        );
+        assert_eq!(remove_stale_temp_files(&temp_dir, Duration::ZERO), 1);
+    }
+}
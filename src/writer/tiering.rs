@@ -0,0 +1,223 @@
+//! Hot/cold tiering of day partitions.
+//!
+//! With `tiering.enabled`, the primary storage is the hot tier. Every
+//! `interval_secs` a background task lists it and moves each file in a day
+//! partition at least `cold_after_days` old to the cold target, keeping its
+//! key below the cold prefix: copy, repoint the catalog entry, then delete the
+//! hot copy. A move cut short by a restart is repeated in the next round.
+
+use crate::config::TieringConfig;
+use once_cell::sync::OnceCell;
+use std::time::Duration;
+use time::{Date, Month, OffsetDateTime};
+
+use super::error::{Result, WriterError};
+use super::storage::Target;
+
+static STARTED: OnceCell<()> = OnceCell::new();
+
+struct Mover {
+    hot: opendal::Operator,
+    hot_prefix: String,
+    cold: Target,
+    /// Catalog location of the cold target's root, e.g. `s3://otlp-cold/`
+    #[cfg_attr(not(feature = "catalog"), allow(dead_code))]
+    cold_location: String,
+    cold_after_days: u32,
+}
+
+/// Start the tiering task if enabled.
+pub fn start_tiering(config: &TieringConfig) -> Result<()> {
+    if !config.enabled || STARTED.get().is_some() {
+        return Ok(());
+    }
+    let storage = config
+        .storage
+        .as_ref()
+        .ok_or_else(|| WriterError::invalid_config("tiering.storage is required".to_string()))?;
+    let hot = super::storage::get_operator()
+        .ok_or_else(|| {
+            WriterError::invalid_config("Storage must be initialized before tiering".to_string())
+        })?
+        .clone();
+    let describe = super::replication::describe(storage);
+    let mover = Mover {
+        hot,
+        hot_prefix: super::storage::get_storage_prefix()
+            .unwrap_or("")
+            .to_string(),
+        cold: Target::build("cold", storage)?,
+        cold_location: format!("{}/", describe.trim_end_matches('/')),
+        cold_after_days: config.cold_after_days,
+    };
+    if STARTED.set(()).is_err() {
+        return Ok(());
+    }
+
+    let interval = Duration::from_secs(config.interval_secs);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            mover.run_round().await;
+        }
+    });
+    tracing::info!(
+        "Moving partitions older than {} days to {}",
+        config.cold_after_days,
+        describe
+    );
+    Ok(())
+}
+
+impl Mover {
+    /// Move every file of a partition past the cutoff.
+    async fn run_round(&self) {
+        let cutoff = cutoff_date(OffsetDateTime::now_utc().date(), self.cold_after_days);
+        let root = if self.hot_prefix.is_empty() {
+            "/"
+        } else {
+            self.hot_prefix.as_str()
+        };
+        let entries = match self.hot.list_with(root).recursive(true).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to list hot storage for tiering");
+                return;
+            }
+        };
+
+        let mut moved = 0;
+        for entry in entries {
+            let cold = partition_day(entry.path()).is_some_and(|day| day <= cutoff);
+            if entry.metadata().is_dir() || !cold {
+                continue;
+            }
+            match self.move_file(entry.path()).await {
+                Ok(bytes) => {
+                    moved += 1;
+                    metrics::counter!("otlp.tiering.files").increment(1);
+                    metrics::counter!("otlp.tiering.bytes").increment(bytes);
+                }
+                Err(e) => {
+                    metrics::counter!("otlp.tiering.failures").increment(1);
+                    tracing::warn!(error = %e, path = %entry.path(), "Failed to move file to cold storage");
+                }
+            }
+        }
+        if moved > 0 {
+            tracing::info!("Moved {} files to cold storage", moved);
+        }
+    }
+
+    /// Cold key for a hot path: same key, cold prefix
+    fn cold_key(&self, path: &str) -> String {
+        let key = path.strip_prefix(&self.hot_prefix).unwrap_or(path);
+        format!("{}{}", self.cold.prefix, key)
+    }
+
+    async fn move_file(&self, path: &str) -> Result<u64> {
+        let data =
+            self.hot.read(path).await.map_err(|e| {
+                WriterError::write_failure(format!("Failed to read '{}': {}", path, e))
+            })?;
+        let bytes = data.len() as u64;
+        let key = self.cold_key(path);
+        self.cold.operator.write(&key, data).await.map_err(|e| {
+            WriterError::write_failure(format!("Failed to write cold copy '{}': {}", key, e))
+        })?;
+
+        #[cfg(feature = "catalog")]
+        if let Some(catalog) = super::storage::get_catalog() {
+            let location = format!("{}{}", self.cold_location, key);
+            if let Err(e) = catalog.relocate(path, &location) {
+                tracing::warn!(error = %e, path = %path, "Failed to relocate file in catalog");
+            }
+        }
+
+        self.hot.delete(path).await.map_err(|e| {
+            WriterError::write_failure(format!("Failed to delete hot copy '{}': {}", path, e))
+        })?;
+        tracing::debug!("Moved '{}' to cold storage as '{}'", path, key);
+        Ok(bytes)
+    }
+}
+
+/// Last day that is cold: `days` before `today`
+fn cutoff_date(today: Date, days: u32) -> Date {
+    today
+        .checked_sub(time::Duration::days(days as i64))
+        .unwrap_or(Date::MIN)
+}
+
+/// Day of the `year=/month=/day=` partition a path lies in
+fn partition_day(path: &str) -> Option<Date> {
+    let value = |key: &str| {
+        path.split('/')
+            .find_map(|segment| segment.strip_prefix(key))
+            .and_then(|v| v.parse::<u32>().ok())
+    };
+    let month = Month::try_from(value("month=")? as u8).ok()?;
+    Date::from_calendar_date(value("year=")? as i32, month, value("day=")? as u8).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fs_operator(dir: &tempfile::TempDir) -> opendal::Operator {
+        opendal::Operator::new(opendal::services::Fs::default().root(dir.path().to_str().unwrap()))
+            .unwrap()
+            .finish()
+    }
+
+    #[tokio::test]
+    async fn test_moves_old_partitions_to_cold_target() {
+        let hot = tempfile::tempdir().unwrap();
+        let cold = tempfile::tempdir().unwrap();
+        let mover = Mover {
+            hot: fs_operator(&hot),
+            hot_prefix: "otel/".to_string(),
+            cold: Target {
+                name: "cold".to_string(),
+                operator: fs_operator(&cold),
+                prefix: "archive/".to_string(),
+            },
+            cold_location: "s3://otlp-cold/".to_string(),
+            cold_after_days: 7,
+        };
+        let today = OffsetDateTime::now_utc().date();
+        let old = "otel/logs/web/year=2020/month=01/day=15/hour=10/a.parquet";
+        let recent = format!(
+            "otel/logs/web/year={}/month={:02}/day={:02}/hour=10/b.parquet",
+            today.year(),
+            u8::from(today.month()),
+            today.day()
+        );
+        for path in [old, recent.as_str(), "otel/logs/_latest.json"] {
+            mover.hot.write(path, b"PAR1".to_vec()).await.unwrap();
+        }
+
+        mover.run_round().await;
+
+        assert!(!mover.hot.exists(old).await.unwrap());
+        assert!(mover.hot.exists(&recent).await.unwrap());
+        assert!(mover.hot.exists("otel/logs/_latest.json").await.unwrap());
+        let moved = mover
+            .cold
+            .operator
+            .read("archive/logs/web/year=2020/month=01/day=15/hour=10/a.parquet")
+            .await
+            .unwrap();
+        assert_eq!(moved.to_vec(), b"PAR1");
+
+        assert_eq!(
+            cutoff_date(Date::from_calendar_date(2025, Month::March, 3).unwrap(), 7),
+            Date::from_calendar_date(2025, Month::February, 24).unwrap()
+        );
+        assert_eq!(
+            partition_day("logs/year=2025/month=13/day=01/a.parquet"),
+            None
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Per-table timestamp precision.
+//!
+//! Decoded batches carry microsecond timestamps. Tables configured with a
+//! different unit are cast just before encoding, and startup checks that
+//! files already in storage use the configured unit so a table never mixes
+//! precisions.
+
+use crate::config::{SchemaConfig, TimestampUnit};
+use crate::SignalType;
+use arrow::array::RecordBatch;
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use parquet::arrow::parquet_to_arrow_schema;
+use parquet::file::metadata::ParquetMetaDataReader;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use super::error::{Result, WriterError};
+
+/// Partition directories below a table prefix: service/year/month/day/hour/shard
+const MAX_DEPTH: usize = 6;
+
+fn arrow_unit(unit: TimestampUnit) -> TimeUnit {
+    match unit {
+        TimestampUnit::Millisecond => TimeUnit::Millisecond,
+        TimestampUnit::Microsecond => TimeUnit::Microsecond,
+        TimestampUnit::Nanosecond => TimeUnit::Nanosecond,
+    }
+}
+
+/// `batch` with every timestamp column in `unit` (borrowed if already so).
+pub(crate) fn cast_timestamps(
+    batch: &RecordBatch,
+    unit: TimestampUnit,
+) -> Result<Cow<'_, RecordBatch>> {
+    let target = arrow_unit(unit);
+    let schema = batch.schema();
+    let needs_cast = |field: &Field| matches!(field.data_type(), DataType::Timestamp(current, _) if *current != target);
+    if !schema.fields().iter().any(|f| needs_cast(f)) {
+        return Ok(Cow::Borrowed(batch));
+    }
+
+    let mut fields = Vec::with_capacity(schema.fields().len());
+    let mut columns = Vec::with_capacity(schema.fields().len());
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        match field.data_type() {
+            DataType::Timestamp(current, tz) if *current != target => {
+                let data_type = DataType::Timestamp(target, tz.clone());
+                let cast = arrow::compute::cast(column, &data_type).map_err(|e| {
+                    WriterError::write_failure(format!(
+                        "Failed to cast column '{}' to {} timestamps: {}",
+                        field.name(),
+                        unit,
+                        e
+                    ))
+                })?;
+                fields.push(field.as_ref().clone().with_data_type(data_type));
+                columns.push(cast);
+            }
+            _ => {
+                fields.push(field.as_ref().clone());
+                columns.push(Arc::clone(column));
+            }
+        }
+    }
+
+    let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+    RecordBatch::try_new(Arc::new(schema), columns)
+        .map(Cow::Owned)
+        .map_err(|e| WriterError::write_failure(format!("Failed to rebuild batch: {}", e)))
+}
+
+/// Fail if any signal table already holds files whose `timestamp` column uses
+/// a different unit than configured.
+///
+/// Reads the footer of one recent file per table; empty tables pass.
+pub(crate) async fn check_timestamp_units(schema: &SchemaConfig) -> Result<()> {
+    let Some(op) = super::storage::get_operator() else {
+        return Ok(());
+    };
+
+    let tables: [(SignalType, Option<&str>); 6] = [
+        (SignalType::Logs, None),
+        (SignalType::Traces, None),
+        (SignalType::Metrics, Some("gauge")),
+        (SignalType::Metrics, Some("sum")),
+        (SignalType::Metrics, Some("histogram")),
+        (SignalType::Metrics, Some("exponential_histogram")),
+    ];
+    for (signal, metric_type) in tables {
+        let prefix = format!("{}/", super::write::table_prefix(signal, metric_type));
+        let Some(path) = recent_file(op, &prefix).await? else {
+            continue;
+        };
+        let configured = schema.timestamp_unit_for(signal);
+        let Some(existing) = file_timestamp_unit(op, &path).await? else {
+            continue;
+        };
+        if existing != arrow_unit(configured) {
+            return Err(WriterError::invalid_config(format!(
+                "{} already holds files with {:?} timestamps (e.g. '{}'), but \
+                 schema.{}_timestamp_unit is {}. Mixing units in one table breaks readers; \
+                 keep the existing unit or write to a new storage prefix",
+                prefix.trim_end_matches('/'),
+                existing,
+                path,
+                signal.as_str(),
+                configured
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// A file from the newest partition under `prefix`, found by descending into
+/// the greatest child directory at each level (partition values are
+/// zero-padded, so lexical order is time order).
+async fn recent_file(op: &opendal::Operator, prefix: &str) -> Result<Option<String>> {
+    let mut dir = prefix.to_string();
+    for _ in 0..=MAX_DEPTH {
+        let entries = match op.list(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(WriterError::write_failure(format!(
+                    "Failed to list '{}': {}",
+                    dir, e
+                )))
+            }
+        };
+        let paths: Vec<&str> = entries
+            .iter()
+            .map(|e| e.path())
+            .filter(|p| *p != dir)
+            .collect();
+        if let Some(file) = paths.iter().filter(|p| p.ends_with(".parquet")).max() {
+            return Ok(Some(file.to_string()));
+        }
+        match paths.into_iter().filter(|p| p.ends_with('/')).max() {
+            Some(child) => dir = child.to_string(),
+            None => return Ok(None),
+        }
+    }
+    Ok(None)
+}
+
+async fn file_timestamp_unit(op: &opendal::Operator, path: &str) -> Result<Option<TimeUnit>> {
+    let bytes = op
+        .read(path)
+        .await
+        .map_err(|e| WriterError::write_failure(format!("Failed to read '{}': {}", path, e)))?
+        .to_bytes();
+    let metadata = ParquetMetaDataReader::new()
+        .parse_and_finish(&bytes)
+        .map_err(|e| {
+            WriterError::write_failure(format!("Failed to read '{}' footer: {}", path, e))
+        })?;
+    let file = metadata.file_metadata();
+    let schema =
+        parquet_to_arrow_schema(file.schema_descr(), file.key_value_metadata()).map_err(|e| {
+            WriterError::write_failure(format!("Failed to read '{}' schema: {}", path, e))
+        })?;
+
+    Ok(schema
+        .field_with_name("timestamp")
+        .ok()
+        .and_then(|field| match field.data_type() {
+            DataType::Timestamp(unit, _) => Some(*unit),
+            _ => None,
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::AsArray;
+    use arrow::array::{Int64Array, TimestampMicrosecondArray};
+    use arrow::datatypes::TimestampMillisecondType;
+
+    fn batch() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(
+                "timestamp",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                false,
+            ),
+            Field::new("duration", DataType::Int64, false),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(TimestampMicrosecondArray::from(vec![1_700_000_000_123_456])),
+                Arc::new(Int64Array::from(vec![5])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_default_unit_borrows() {
+        let batch = batch();
+        assert!(matches!(
+            cast_timestamps(&batch, TimestampUnit::Microsecond).unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_cast_to_milliseconds() {
+        let batch = batch();
+        let cast = cast_timestamps(&batch, TimestampUnit::Millisecond).unwrap();
+        assert_eq!(
+            cast.schema().field(0).data_type(),
+            &DataType::Timestamp(TimeUnit::Millisecond, None)
+        );
+        assert_eq!(cast.schema().field(1).data_type(), &DataType::Int64);
+        let values = cast.column(0).as_primitive::<TimestampMillisecondType>();
+        assert_eq!(values.value(0), 1_700_000_000_123);
+    }
+}
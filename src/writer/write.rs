@@ -2,14 +2,21 @@
 //!
 //! Writes OTLP Arrow RecordBatch data to partitioned Parquet files using OpenDAL.
 
+use crate::config::{FileNaming, ParquetCompression};
 use crate::SignalType;
 use arrow::array::RecordBatch;
-use otlp2records::output::to_parquet_bytes;
+use otlp2records::output::write_parquet;
+use parquet::basic::{Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use parquet::schema::types::ColumnPath;
 use std::borrow::Cow;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
 use super::error::{Result, WriterError};
+use super::hooks::{BatchEvent, FileEvent};
+use super::manifest::{record_file, ManifestEntry};
+use super::storage::{Target, WriteOptions};
 
 /// Request parameters for writing a batch to storage.
 pub struct WriteBatchRequest<'a> {
@@ -23,52 +30,350 @@ pub struct WriteBatchRequest<'a> {
     pub service_name: &'a str,
     /// Timestamp in microseconds (from OTLP-to-Arrow nanos_to_micros conversion)
     pub timestamp_micros: i64,
+    /// Ids of the requests the rows came from, recorded in the file metadata
+    pub request_ids: &'a [String],
 }
 
-/// Write a batch as a Parquet file.
+/// Write a batch as a Parquet file to `target`, or the default storage for `None`.
 async fn write_plain_parquet(
+    target: Option<&Target>,
     signal_type: SignalType,
     metric_type: Option<&str>,
     service_name: &str,
     timestamp_micros: i64,
     batch: &RecordBatch,
+    request_ids: &[String],
 ) -> Result<String> {
-    let op = super::storage::get_operator().ok_or_else(|| {
-        WriterError::write_failure(
-            "Storage operator not initialized. Call initialize_storage() with RuntimeConfig before writing."
-                .to_string(),
-        )
-    })?;
+    let storage_prefix = match target {
+        Some(target) => target.prefix.as_str(),
+        None => super::storage::get_storage_prefix().unwrap_or(""),
+    };
+    let table = signal_table(signal_type, metric_type);
+    let file_path = generate_parquet_path(
+        &format!("{}{}", storage_prefix, table),
+        service_name,
+        timestamp_micros,
+    );
+    let batch = output_batch(batch, signal_type)?;
+    store_parquet(target, &table, &file_path, &batch, request_ids).await?;
+    // Only once Parquet has the rows, so a retried request can't insert them twice
+    crate::clickhouse::send(signal_type, metric_type, &batch);
+    Ok(file_path)
+}
 
-    let file_path =
-        generate_parquet_path(signal_type, metric_type, service_name, timestamp_micros)?;
+/// `batch` as stored in a signal table: configured timestamp unit and
+/// optional schema columns applied.
+pub(crate) fn output_batch(
+    batch: &RecordBatch,
+    signal_type: SignalType,
+) -> Result<Cow<'_, RecordBatch>> {
+    let schema = &super::storage::write_options().schema;
+    let batch = super::timestamps::cast_timestamps(batch, schema.timestamp_unit_for(signal_type))?;
+    let batch = if schema.sampling_columns {
+        Cow::Owned(super::sampling::add_sampling_columns(&batch, signal_type)?.into_owned())
+    } else {
+        batch
+    };
+    let batch = if schema.span_text_columns {
+        Cow::Owned(super::span_text::add_span_text_columns(&batch, signal_type)?.into_owned())
+    } else {
+        batch
+    };
+    Ok(batch)
+}
+
+/// Write a batch to a table outside the three signal tables (e.g.
+/// `service_graph`, `events`).
+///
+/// Paths are `{table}/[{service}/]year=.../hour=.../[shard=NN/]{file}.parquet`;
+/// tables that aggregate across services pass `None`.
+pub(crate) async fn write_table_batch(
+    table: &str,
+    service_name: Option<&str>,
+    timestamp_micros: i64,
+    batch: &RecordBatch,
+    request_ids: &[String],
+) -> Result<String> {
+    let storage_prefix = super::storage::get_storage_prefix().unwrap_or("");
+    let service_dir = service_name
+        .map(|s| format!("{}/", sanitize_service_name(s)))
+        .unwrap_or_default();
+    let file_path = format!(
+        "{}{}/{}{}",
+        storage_prefix,
+        table,
+        service_dir,
+        partition_file_path(timestamp_micros, &super::storage::write_options())
+    );
+    store_parquet(None, table, &file_path, batch, request_ids).await?;
+    Ok(file_path)
+}
+
+/// Encode `batch` and upload it to `file_path` on `target` (the default
+/// storage for `None`), with optional verification and manifest bookkeeping.
+/// In a dry run the encoded file is dropped after `batch_ready` hooks run.
+/// `table` is the table directory below the storage prefix (e.g. `logs`,
+/// `metrics/gauge`).
+///
+/// If the default storage rejects the write, the file goes to the first
+/// fallback that takes it; manifests and the catalog only list files on the
+/// primary storage, so relocated files are left out until repaired.
+async fn store_parquet(
+    target: Option<&Target>,
+    table: &str,
+    file_path: &str,
+    batch: &RecordBatch,
+    request_ids: &[String],
+) -> Result<()> {
+    let options = super::storage::write_options();
+    let (op, storage_prefix) = match target {
+        Some(target) => (&target.operator, target.prefix.as_str()),
+        None => (
+            super::storage::get_operator().ok_or_else(|| {
+                WriterError::write_failure(
+                    "Storage operator not initialized. Call initialize_storage() with RuntimeConfig before writing."
+                        .to_string(),
+                )
+            })?,
+            super::storage::get_storage_prefix().unwrap_or(""),
+        ),
+    };
 
     tracing::debug!("Writing plain Parquet to path: {}", file_path);
+    super::hooks::batch_ready(&BatchEvent {
+        table,
+        path: file_path,
+        batch,
+    });
 
-    let parquet_bytes = to_parquet_bytes(batch).map_err(|e| {
-        WriterError::write_failure(format!("Failed to encode Parquet bytes: {}", e))
-    })?;
+    let parquet_bytes = encode_parquet(table, batch, request_ids, &options)?;
     let bytes_written = parquet_bytes.len();
+    if options.dry_run {
+        crate::usage::record(table, batch, bytes_written);
+        tracing::info!(
+            "Dry run: discarded {} rows for '{}' ({} bytes)",
+            batch.num_rows(),
+            file_path,
+            bytes_written
+        );
+        return Ok(());
+    }
+    let manifest_entry = (options.write_manifests
+        || options.catalog
+        || super::hooks::registered()
+        || (target.is_none() && super::replication::wants_entries()))
+    .then(|| ManifestEntry::new(file_path, batch, &parquet_bytes));
+    let data = opendal::Buffer::from(parquet_bytes);
+
+    let write_start = std::time::Instant::now();
+    let write_result = op.write(file_path, data.clone()).await;
+    super::latency::record_write_latency(write_start.elapsed());
+    let (op, stored_path, relocated) = match write_result {
+        Ok(_) => (op, file_path.to_string(), false),
+        Err(e) => {
+            let error = format!("Failed to write parquet bytes to '{}': {}", file_path, e);
+            let fallback = match target {
+                Some(_) => None,
+                None => super::fallback::relocate(file_path, data, batch.num_rows(), &error).await,
+            };
+            match fallback {
+                Some((fallback, stored_path)) => (&fallback.operator, stored_path, true),
+                None => return Err(WriterError::write_failure(error)),
+            }
+        }
+    };
 
-    op.write(&file_path, parquet_bytes).await.map_err(|e| {
-        WriterError::write_failure(format!(
-            "Failed to write parquet bytes to '{}': {}",
-            file_path, e
-        ))
-    })?;
+    if let (Some(root), false, None) = (&options.sync_dir, relocated, target) {
+        super::storage::sync_parent_dir(root, &stored_path).await?;
+    }
+    if options.verify_after_write {
+        if let Err(e) = verify_written_object(op, &stored_path, bytes_written as u64).await {
+            // A truncated object would break every reader globbing the
+            // partition; the client's retry writes a new file
+            if let Err(delete_error) = op.delete(&stored_path).await {
+                tracing::warn!(
+                    error = %delete_error,
+                    path = %stored_path,
+                    "Failed to delete object that failed write verification"
+                );
+            }
+            return Err(e);
+        }
+    }
+    // Only once verified: a file deleted above never counts against the quota
+    if !relocated && target.is_none() {
+        super::quota::record_write(bytes_written as u64);
+    }
+    crate::usage::record(table, batch, bytes_written);
+    let file_event = manifest_entry.as_ref().map(|entry| FileEvent {
+        table,
+        path: &stored_path,
+        entry,
+        relocated,
+    });
+    if let Some(event) = &file_event {
+        super::hooks::file_written(event);
+    }
+
+    // Relocated files are picked up by manifests, catalog and replication once
+    // they are repaired onto the primary storage
+    if !relocated {
+        if let Some(entry) = &manifest_entry {
+            // The data file is durable at this point; a stale manifest is recoverable
+            // by listing, so don't fail the write over it.
+            if options.write_manifests {
+                let table_prefix = format!("{}{}", storage_prefix, table);
+                if let Err(e) = record_file(op, &table_prefix, file_path, entry).await {
+                    tracing::warn!(error = %e, path = %file_path, "Failed to update partition manifest");
+                }
+            }
+            #[cfg(feature = "catalog")]
+            if let Some(catalog) = super::storage::get_catalog() {
+                if let Err(e) = catalog.record(table, file_path, entry) {
+                    tracing::warn!(error = %e, path = %file_path, "Failed to record file in catalog");
+                }
+            }
+        }
+        if let Some(event) = &file_event {
+            super::hooks::committed(event);
+        }
+        if target.is_none() {
+            super::replication::enqueue(table, file_path, manifest_entry);
+        }
+    }
 
     let row_count = batch.num_rows();
     tracing::info!(
+        request_ids = %request_ids.join(","),
         "✓ Wrote {} rows to '{}' (plain Parquet, {} bytes)",
         row_count,
-        file_path,
+        stored_path,
         bytes_written
     );
 
-    Ok(file_path)
+    Ok(())
+}
+
+/// Parquet codec for `storage.parquet_compression` and its level
+fn compression(options: &WriteOptions) -> Result<Compression> {
+    let level_error =
+        |e| WriterError::invalid_config(format!("Invalid Parquet compression level: {}", e));
+    Ok(match options.compression {
+        ParquetCompression::Uncompressed => Compression::UNCOMPRESSED,
+        ParquetCompression::Snappy => Compression::SNAPPY,
+        ParquetCompression::Gzip => Compression::GZIP(match options.compression_level {
+            Some(level) => GzipLevel::try_new(level).map_err(level_error)?,
+            None => GzipLevel::default(),
+        }),
+        ParquetCompression::Lz4 => Compression::LZ4_RAW,
+        ParquetCompression::Zstd => Compression::ZSTD(match options.compression_level {
+            Some(level) => ZstdLevel::try_new(level as i32).map_err(level_error)?,
+            None => ZstdLevel::default(),
+        }),
+    })
+}
+
+/// Columns of `table` that get a bloom filter with `parquet_bloom_filters`
+fn bloom_filter_columns(table: &str) -> &'static [&'static str] {
+    match table {
+        "traces" => &["trace_id", "span_id"],
+        "logs" => &["trace_id"],
+        _ => &[],
+    }
+}
+
+/// Encode `batch` as Parquet for `table`, with the discovery metadata and
+/// `request_ids` in the footer's key-value metadata.
+fn encode_parquet(
+    table: &str,
+    batch: &RecordBatch,
+    request_ids: &[String],
+    options: &WriteOptions,
+) -> Result<Vec<u8>> {
+    let mut props = WriterProperties::builder()
+        .set_compression(compression(options)?)
+        .set_key_value_metadata(Some(super::footer::footer_metadata(
+            table,
+            batch,
+            request_ids,
+        )));
+    if options.bloom_filters {
+        for column in bloom_filter_columns(table) {
+            props = props.set_column_bloom_filter_enabled(ColumnPath::from(*column), true);
+        }
+    }
+    let props = props.build();
+    let mut buffer = Vec::new();
+    write_parquet(batch, &mut buffer, Some(props))
+        .map(|()| buffer)
+        .map_err(|e| WriterError::write_failure(format!("Failed to encode Parquet bytes: {}", e)))
+}
+
+/// Length of the Parquet trailer: 4-byte footer length + "PAR1" magic.
+const PARQUET_TRAILER_LEN: u64 = 8;
+
+/// Re-read object metadata and the Parquet trailer after a write.
+///
+/// Some S3-compatible stores acknowledge PUTs that were silently truncated;
+/// checking the stored size and trailing magic catches that before we report success.
+async fn verify_written_object(
+    op: &opendal::Operator,
+    file_path: &str,
+    expected_len: u64,
+) -> Result<()> {
+    let meta = op.stat(file_path).await.map_err(|e| {
+        WriterError::write_failure(format!(
+            "Failed to stat '{}' for write verification: {}",
+            file_path, e
+        ))
+    })?;
+
+    let stored_len = meta.content_length();
+    if stored_len != expected_len {
+        return Err(WriterError::write_failure(format!(
+            "Write verification failed for '{}': stored {} bytes, expected {}",
+            file_path, stored_len, expected_len
+        )));
+    }
+
+    if stored_len < PARQUET_TRAILER_LEN {
+        return Err(WriterError::write_failure(format!(
+            "Write verification failed for '{}': object too small to be Parquet ({} bytes)",
+            file_path, stored_len
+        )));
+    }
+
+    let trailer = op
+        .read_with(file_path)
+        .range(stored_len - PARQUET_TRAILER_LEN..stored_len)
+        .await
+        .map_err(|e| {
+            WriterError::write_failure(format!(
+                "Failed to read Parquet footer of '{}' for write verification: {}",
+                file_path, e
+            ))
+        })?
+        .to_vec();
+
+    if !trailer.ends_with(b"PAR1") {
+        return Err(WriterError::write_failure(format!(
+            "Write verification failed for '{}': missing Parquet footer magic",
+            file_path
+        )));
+    }
+
+    tracing::debug!(
+        "Verified '{}' after write ({} bytes)",
+        file_path,
+        stored_len
+    );
+
+    Ok(())
 }
 
-pub async fn write_batch(req: WriteBatchRequest<'_>) -> Result<String> {
+/// Write a signal batch, split across route targets if `[[routes]]` claim
+/// some of its rows. Returns one path per file written.
+pub async fn write_batch(req: WriteBatchRequest<'_>) -> Result<Vec<String>> {
     let row_count = req.batch.num_rows();
 
     tracing::debug!(
@@ -79,26 +384,120 @@ pub async fn write_batch(req: WriteBatchRequest<'_>) -> Result<String> {
         req.metric_type
     );
 
-    write_plain_parquet(
-        req.signal_type,
-        req.metric_type,
-        req.service_name,
-        req.timestamp_micros,
-        req.batch,
+    let mut paths = Vec::new();
+    for (target, batch) in super::routing::resolve(req.signal_type, req.service_name, req.batch)? {
+        if let Some(target) = target {
+            metrics::counter!("otlp.routes.rows", "route" => target.name.clone())
+                .increment(batch.num_rows() as u64);
+        }
+        let path = write_plain_parquet(
+            target,
+            req.signal_type,
+            req.metric_type,
+            req.service_name,
+            req.timestamp_micros,
+            &batch,
+            req.request_ids,
+        )
+        .await?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Generate a partitioned file path below `table_prefix`.
+fn generate_parquet_path(table_prefix: &str, service_name: &str, timestamp_micros: i64) -> String {
+    format!(
+        "{}/{}/{}",
+        table_prefix,
+        sanitize_service_name(service_name),
+        partition_file_path(timestamp_micros, &super::storage::write_options())
     )
-    .await
 }
 
-/// Generate a partitioned file path for plain Parquet files.
-fn generate_parquet_path(
-    signal_type: SignalType,
-    metric_type: Option<&str>,
-    service_name: &str,
+/// Hour partition, optional shard directory and file name for a new file.
+///
+/// `timestamp` names files `{timestamp_micros}-{uuid v4}.parquet`; `uuid7`
+/// names them `{uuid v7}.parquet` so lexical order within a partition follows
+/// write time. The shard is taken from the file id's random bits, which keeps
+/// concurrent writers to the same hour on different key prefixes.
+fn partition_file_path(timestamp_micros: i64, options: &WriteOptions) -> String {
+    let (year, month, day, hour) = partition_from_timestamp(timestamp_micros);
+    let id = match options.file_naming {
+        FileNaming::Timestamp => Uuid::new_v4(),
+        FileNaming::Uuid7 => Uuid::now_v7(),
+    };
+    let shard_dir = match options.write_shards {
+        0 | 1 => String::new(),
+        shards => {
+            let bytes = id.as_bytes();
+            let shard = u16::from_be_bytes([bytes[14], bytes[15]]) % shards;
+            let width = (shards - 1).to_string().len();
+            format!("shard={:0width$}/", shard, width = width)
+        }
+    };
+    let file_name = match options.file_naming {
+        FileNaming::Timestamp => format!("{}-{}", timestamp_micros, id.simple()),
+        FileNaming::Uuid7 => id.simple().to_string(),
+    };
+
+    format!(
+        "year={}/month={:02}/day={:02}/hour={:02}/{}{}.parquet",
+        year, month, day, hour, shard_dir, file_name
+    )
+}
+
+/// Storage prefix plus signal directory, e.g. `smoke-abc/metrics/gauge`.
+pub(crate) fn table_prefix(signal_type: SignalType, metric_type: Option<&str>) -> String {
+    let storage_prefix = super::storage::get_storage_prefix().unwrap_or("");
+    format!(
+        "{}{}",
+        storage_prefix,
+        signal_table(signal_type, metric_type)
+    )
+}
+
+/// Hour partition directory a batch is written to on the default storage,
+/// e.g. `logs/web/year=2025/month=01/day=15/hour=10` (before any shard).
+pub(crate) fn partition_dir(
+    table_prefix: &str,
+    service_name: Option<&str>,
     timestamp_micros: i64,
-) -> Result<String> {
+) -> String {
     let (year, month, day, hour) = partition_from_timestamp(timestamp_micros);
+    let service_dir = service_name
+        .map(|s| format!("{}/", sanitize_service_name(s)))
+        .unwrap_or_default();
+    format!(
+        "{}/{}year={}/month={:02}/day={:02}/hour={:02}",
+        table_prefix, service_dir, year, month, day, hour
+    )
+}
 
-    let signal_prefix: Cow<'_, str> = match signal_type {
+/// Start of the `year=/month=/day=/hour=` partition in `path`, in micros.
+pub(crate) fn partition_hour_micros(path: &str) -> Option<i64> {
+    let field = |key: &str| -> Option<i64> {
+        path.split('/')
+            .find_map(|segment| segment.strip_prefix(key))
+            .and_then(|v| v.parse().ok())
+    };
+    let month = time::Month::try_from(u8::try_from(field("month=")?).ok()?).ok()?;
+    let date = time::Date::from_calendar_date(
+        i32::try_from(field("year=")?).ok()?,
+        month,
+        u8::try_from(field("day=")?).ok()?,
+    )
+    .ok()?;
+    let hour = date
+        .with_hms(u8::try_from(field("hour=")?).ok()?, 0, 0)
+        .ok()?
+        .assume_utc();
+    Some(hour.unix_timestamp() * 1_000_000)
+}
+
+/// Table directory of a signal, e.g. `logs` or `metrics/gauge`.
+fn signal_table(signal_type: SignalType, metric_type: Option<&str>) -> Cow<'static, str> {
+    match signal_type {
         SignalType::Logs => Cow::Borrowed("logs"),
         SignalType::Traces => Cow::Borrowed("traces"),
         SignalType::Metrics => {
@@ -108,25 +507,7 @@ fn generate_parquet_path(
                 Cow::Borrowed("metrics")
             }
         }
-    };
-
-    let safe_service = sanitize_service_name(service_name);
-    let suffix = Uuid::new_v4().simple();
-
-    let storage_prefix = super::storage::get_storage_prefix().unwrap_or("");
-
-    Ok(format!(
-        "{}{}/{}/year={}/month={:02}/day={:02}/hour={:02}/{}-{}.parquet",
-        storage_prefix,
-        signal_prefix,
-        safe_service,
-        year,
-        month,
-        day,
-        hour,
-        timestamp_micros,
-        suffix
-    ))
+    }
 }
 
 fn sanitize_service_name(service_name: &str) -> Cow<'_, str> {
@@ -167,6 +548,112 @@ fn partition_from_timestamp(timestamp_micros: i64) -> (i32, u8, u8, u8) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use parquet::file::metadata::{ParquetMetaData, ParquetMetaDataReader};
+
+    /// Decode the footer metadata of an encoded Parquet file
+    fn read_footer(encoded: &[u8]) -> ParquetMetaData {
+        let end = encoded.len() - 8;
+        let len = u32::from_le_bytes(encoded[end..end + 4].try_into().unwrap()) as usize;
+        ParquetMetaDataReader::decode_metadata(&encoded[end - len..end]).unwrap()
+    }
+
+    fn int64_batch() -> RecordBatch {
+        use arrow::array::Int64Array;
+        use arrow::datatypes::{DataType, Field, Schema};
+        use std::sync::Arc;
+
+        let schema = Arc::new(Schema::new(vec![Field::new("v", DataType::Int64, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(vec![1, 2]))]).unwrap()
+    }
+
+    #[test]
+    fn test_request_ids_in_parquet_metadata() {
+        let batch = int64_batch();
+        let key_value = |encoded: &[u8]| {
+            read_footer(encoded)
+                .file_metadata()
+                .key_value_metadata()
+                .and_then(|kv| kv.iter().find(|e| e.key == crate::request_id::METADATA_KEY))
+                .and_then(|e| e.value.clone())
+        };
+
+        let ids = vec!["req-1".to_string(), "req-2".to_string()];
+        assert_eq!(
+            key_value(&encode_parquet("logs", &batch, &ids, &WriteOptions::default()).unwrap())
+                .as_deref(),
+            Some("req-1,req-2")
+        );
+        assert_eq!(
+            key_value(&encode_parquet("logs", &batch, &[], &WriteOptions::default()).unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parquet_compression() {
+        let options = WriteOptions {
+            compression: ParquetCompression::Zstd,
+            compression_level: Some(19),
+            ..Default::default()
+        };
+        assert_eq!(
+            compression(&options).unwrap(),
+            Compression::ZSTD(ZstdLevel::try_new(19).unwrap())
+        );
+        let encoded = encode_parquet("logs", &int64_batch(), &[], &options).unwrap();
+        assert!(matches!(
+            read_footer(&encoded).row_group(0).column(0).compression(),
+            Compression::ZSTD(_)
+        ));
+        assert!(compression(&WriteOptions {
+            compression: ParquetCompression::Gzip,
+            compression_level: Some(11),
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_bloom_filters_on_id_columns() {
+        use arrow::array::{ArrayRef, StringArray};
+        use arrow::datatypes::{DataType, Field, Schema};
+        use std::sync::Arc;
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("trace_id", DataType::Utf8, false),
+            Field::new("span_id", DataType::Utf8, false),
+            Field::new("span_name", DataType::Utf8, false),
+        ]));
+        let column = |v: &str| Arc::new(StringArray::from(vec![v])) as ArrayRef;
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                column("5b8efff798038103d269b633813fc60c"),
+                column("eee19b7ec3c1b174"),
+                column("GET /cart"),
+            ],
+        )
+        .unwrap();
+        let filtered = |table: &str, bloom_filters| {
+            let options = WriteOptions {
+                bloom_filters,
+                ..Default::default()
+            };
+            let encoded = encode_parquet(table, &batch, &[], &options).unwrap();
+            read_footer(&encoded)
+                .row_group(0)
+                .columns()
+                .iter()
+                .filter(|c| c.bloom_filter_offset().is_some())
+                .map(|c| c.column_path().string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(filtered("traces", true), ["trace_id", "span_id"]);
+        assert_eq!(filtered("logs", true), ["trace_id"]);
+        assert!(filtered("traces", false).is_empty());
+        assert!(filtered("metrics/gauge", true).is_empty());
+    }
 
     #[test]
     fn test_extract_timestamp_from_arrow_batch() {
@@ -240,12 +727,71 @@ mod tests {
 
     #[test]
     fn path_generation_sanitizes_service() {
-        let path =
-            generate_parquet_path(SignalType::Logs, None, "svc /name", 1_736_938_800_000_000)
-                .unwrap();
+        let path = generate_parquet_path(
+            &table_prefix(SignalType::Logs, None),
+            "svc /name",
+            1_736_938_800_000_000,
+        );
         assert!(path.starts_with("logs/svc__name/year="));
         assert!(path.contains("/month="));
         assert!(path.ends_with(".parquet"));
         assert!(path.split('-').next_back().unwrap().ends_with(".parquet"));
     }
+
+    #[test]
+    fn uuid7_names_sort_by_write_time_and_shard() {
+        let options = WriteOptions {
+            file_naming: FileNaming::Uuid7,
+            write_shards: 16,
+            ..Default::default()
+        };
+        let first = partition_file_path(1_736_938_800_000_000, &options);
+        let second = partition_file_path(1_736_938_800_000_000, &options);
+        assert!(first.starts_with("year=2025/month=01/day=15/hour=11/shard="));
+
+        let name = |path: &str| path.rsplit('/').next().unwrap().to_string();
+        assert!(name(&first) < name(&second));
+        let shard = first.split('/').nth(4).unwrap();
+        let n: u16 = shard.trim_start_matches("shard=").parse().unwrap();
+        assert!(n < 16);
+        assert_eq!(shard.len(), "shard=00".len());
+
+        let unsharded = partition_file_path(1_736_938_800_000_000, &WriteOptions::default());
+        assert!(!unsharded.contains("shard="));
+        assert!(name(&unsharded).starts_with("1736938800000000-"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_written_object() {
+        let op = opendal::Operator::new(opendal::services::Memory::default())
+            .unwrap()
+            .finish();
+        let verify = |path: &'static str, expected_len: u64| {
+            let op = op.clone();
+            async move {
+                verify_written_object(&op, path, expected_len)
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+        };
+        op.write("ok.parquet", b"PAR1data\x04\x00\x00\x00PAR1".to_vec())
+            .await
+            .unwrap();
+        op.write("short.parquet", b"PAR1".to_vec()).await.unwrap();
+        op.write("bad.parquet", b"PAR1data\x04\x00\x00\x00PARX".to_vec())
+            .await
+            .unwrap();
+
+        assert!(verify("ok.parquet", 16).await.is_ok());
+        let truncated = verify("ok.parquet", 32).await.unwrap_err();
+        assert!(
+            truncated.contains("stored 16 bytes, expected 32"),
+            "{}",
+            truncated
+        );
+        let short = verify("short.parquet", 4).await.unwrap_err();
+        assert!(short.contains("too small to be Parquet"), "{}", short);
+        let bad = verify("bad.parquet", 16).await.unwrap_err();
+        assert!(bad.contains("missing Parquet footer magic"), "{}", bad);
+    }
 }
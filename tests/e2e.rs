@@ -316,3 +316,70 @@ async fn test_invalid_trace_id_encoding() {
         "Expected error for invalid trace ID encoding"
     );
 }
+
+// =============================================================================
+// Forward compatibility - payloads from newer OTLP versions
+// =============================================================================
+
+#[tokio::test]
+async fn test_lenient_parsing_of_unknown_enum_names() {
+    use arrow::array::AsArray;
+    use otlp2parquet::codec::tolerate_unknown_enums;
+
+    let traces = fs::read(testdata_path("invalid/trace_invalid_kind.json")).unwrap();
+    let (tolerant, replaced) = tolerate_unknown_enums(&traces, InputFormat::Json)
+        .expect("Expected the unknown span kind to be replaced");
+    assert_eq!(replaced, 1);
+    let grouped = decode_traces_partitioned(&tolerant, InputFormat::Json)
+        .expect("Expected lenient traces to decode");
+    let attributes = grouped.batches[0]
+        .batch
+        .column_by_name("span_attributes")
+        .unwrap()
+        .as_string::<i32>();
+    assert!(attributes
+        .value(0)
+        .contains(r#""otlp.unknown.kind":"SPAN_KIND_INVALID_TYPE""#));
+
+    let logs = fs::read(testdata_path("invalid/log_invalid_severity.json")).unwrap();
+    let (tolerant, _) = tolerate_unknown_enums(&logs, InputFormat::Auto).unwrap();
+    assert!(decode_logs_partitioned(&tolerant, InputFormat::Json).is_ok());
+
+    let metrics = fs::read(testdata_path("invalid/metrics_invalid_temporality.json")).unwrap();
+    let (tolerant, _) = tolerate_unknown_enums(&metrics, InputFormat::Json).unwrap();
+    assert!(decode_metrics_partitioned(&tolerant, InputFormat::Json).is_ok());
+
+    // Known names and protobuf payloads are left alone
+    let known = fs::read(testdata_path("trace.json")).unwrap();
+    assert!(tolerate_unknown_enums(&known, InputFormat::Json).is_none());
+    let pb = fs::read(testdata_path("traces.pb")).unwrap();
+    assert!(tolerate_unknown_enums(&pb, InputFormat::Protobuf).is_none());
+}
+
+#[tokio::test]
+async fn test_unknown_fields_from_newer_versions_are_ignored() {
+    // JSON: fields added by later OTLP versions at every level
+    let payload = fs::read(testdata_path("trace.json")).unwrap();
+    let baseline = decode_traces_partitioned(&payload, InputFormat::Json).unwrap();
+    let mut request: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+    request["futureRequestField"] = serde_json::json!(1);
+    let resource_spans = &mut request["resourceSpans"][0];
+    resource_spans["resource"]["entityRefs"] = serde_json::json!([{ "type": "service" }]);
+    resource_spans["scopeSpans"][0]["spans"][0]["futureSpanField"] =
+        serde_json::json!({ "nested": true });
+    let evolved = serde_json::to_vec(&request).unwrap();
+    let decoded = decode_traces_partitioned(&evolved, InputFormat::Json)
+        .expect("Expected unknown JSON fields to be ignored");
+    assert_eq!(decoded.total_records, baseline.total_records);
+
+    // Protobuf: an unknown length-delimited field (number 1000) appended to the
+    // request is skipped by the decoder
+    let payload = fs::read(testdata_path("logs.pb")).unwrap();
+    let baseline = decode_logs_partitioned(&payload, InputFormat::Protobuf).unwrap();
+    let mut evolved = payload.clone();
+    // Tag: (1000 << 3) | 2 as a varint, then length 3 and the bytes
+    evolved.extend_from_slice(&[0xc2, 0x3e, 3, b'n', b'e', b'w']);
+    let decoded = decode_logs_partitioned(&evolved, InputFormat::Protobuf)
+        .expect("Expected unknown protobuf fields to be skipped");
+    assert_eq!(decoded.total_records, baseline.total_records);
+}
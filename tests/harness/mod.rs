@@ -2,6 +2,8 @@
 //!
 //! Provides a unified interface for testing otlp2parquet on the server:
 //! - Local Server + MinIO
+//! - Local Server + fake-gcs-server (GCS backend)
+//! - Local Server + Azurite (Azure Blob backend)
 //!
 //! Each platform implements the `SmokeTestHarness` trait, providing:
 //! 1. deploy() - Deploy infrastructure
@@ -14,6 +16,10 @@
 //!
 //! DuckDB queries Parquet files directly from storage:
 //! - Docker/Server: MinIO S3
+//! - Azurite: through the `azure` extension
+//! - fake-gcs-server: DuckDB reads `gs://` through the S3-interoperable XML
+//!   API, which the emulator does not serve, so files are downloaded through
+//!   the JSON API and scanned locally
 
 #![cfg_attr(not(feature = "smoke-server"), allow(dead_code))]
 
@@ -103,6 +109,15 @@ pub enum StorageBackend {
         endpoint: Option<String>, // For MinIO
         credentials: S3Credentials,
     },
+    /// GCS JSON API (fake-gcs-server), read anonymously
+    Gcs { bucket: String, endpoint: String },
+    /// Azure Blob Storage (Azurite)
+    Azure {
+        container: String,
+        connection_string: String,
+    },
+    /// Local directory, e.g. files downloaded from an emulator
+    Local { root: std::path::PathBuf },
 }
 
 /// S3 credentials configuration
@@ -139,16 +154,115 @@ impl DuckDBVerifier {
     /// 2. Query Parquet files directly using glob patterns
     /// 3. Count rows for each signal type
     /// 4. Retrieve sample data for sanity checks
-    #[allow(unused_variables)]
+    ///
+    /// With the `duckdb-verify` feature the checks run in-process through
+    /// `otlp2parquet::verify`; otherwise the `duckdb` CLI must be on PATH.
     pub async fn verify(&self, prefix: &str) -> Result<ValidationReport> {
+        if let StorageBackend::Gcs { bucket, endpoint } = &self.storage_config.backend {
+            let dir = tempfile::tempdir()?;
+            let object_prefix = self.storage_config.prefix.clone().unwrap_or_default();
+            download_gcs_objects(endpoint, bucket, &object_prefix, dir.path()).await?;
+            let local = DuckDBVerifier {
+                storage_config: StorageConfig {
+                    backend: StorageBackend::Local {
+                        root: dir.path().to_path_buf(),
+                    },
+                    prefix: self.storage_config.prefix.clone(),
+                },
+            };
+            return Box::pin(local.verify(prefix)).await;
+        }
+
+        self.verify_local(prefix).await
+    }
+
+    /// Verify with the embedded DuckDB library instead of the CLI.
+    #[cfg(feature = "duckdb-verify")]
+    async fn verify_local(&self, _prefix: &str) -> Result<ValidationReport> {
+        use otlp2parquet::verify::{DuckDbVerifier as NativeVerifier, S3Access};
+
+        enum Access {
+            S3(S3Access),
+            Azure(String),
+            Local,
+        }
+
+        let access = match &self.storage_config.backend {
+            StorageBackend::S3 {
+                region,
+                endpoint,
+                credentials,
+                ..
+            } => Access::S3(match credentials {
+                S3Credentials::FromEnvironment => S3Access::CredentialChain {
+                    region: region.clone(),
+                },
+                S3Credentials::Static {
+                    access_key,
+                    secret_key,
+                } => S3Access::Static {
+                    region: region.clone(),
+                    access_key: access_key.clone(),
+                    secret_key: secret_key.clone(),
+                    endpoint: endpoint.clone(),
+                },
+            }),
+            StorageBackend::Azure {
+                connection_string, ..
+            } => Access::Azure(connection_string.clone()),
+            // Downloaded to a local directory by `verify`
+            StorageBackend::Gcs { .. } | StorageBackend::Local { .. } => Access::Local,
+        };
+
+        let globs = [
+            ("otel_logs", self.get_parquet_scan_path("logs")?),
+            ("otel_traces", self.get_parquet_scan_path("traces")?),
+            (
+                "otel_metrics_gauge",
+                self.get_parquet_scan_path("metrics/gauge")?,
+            ),
+        ];
+
+        // duckdb::Connection is blocking; keep it off the async runtime
+        tokio::task::spawn_blocking(move || {
+            let verifier = NativeVerifier::open()?;
+            match &access {
+                Access::S3(s3) => verifier.configure_s3(s3)?,
+                Access::Azure(connection_string) => verifier.configure_azure(connection_string)?,
+                Access::Local => {}
+            }
+
+            let mut report = ValidationReport {
+                tables: Vec::new(),
+                row_counts: HashMap::new(),
+                schemas_valid: true,
+                samples_valid: true,
+            };
+            for (table, glob) in &globs {
+                let count = verifier.row_count(glob)?;
+                if count == 0 {
+                    continue;
+                }
+                verifier.assert_columns(glob, &["timestamp", "service_name"])?;
+                report.tables.push(table.to_string());
+                report.row_counts.insert(table.to_string(), count as usize);
+            }
+            Ok(report)
+        })
+        .await
+        .context("DuckDB verification task panicked")?
+    }
+
+    /// Verify by running a generated script through the `duckdb` CLI.
+    #[cfg(not(feature = "duckdb-verify"))]
+    async fn verify_local(&self, prefix: &str) -> Result<ValidationReport> {
         let script = self.generate_verification_script(prefix)?;
         let output = self.execute_duckdb_script(&script).await?;
-        let report = self.parse_verification_output(&output)?;
-
-        Ok(report)
+        self.parse_verification_output(&output)
     }
 
     /// Generate DuckDB SQL script for verification
+    #[cfg(not(feature = "duckdb-verify"))]
     fn generate_verification_script(&self, _prefix: &str) -> Result<String> {
         let mut script = String::new();
 
@@ -202,6 +316,21 @@ impl DuckDBVerifier {
                     }
                 }
             }
+            StorageBackend::Azure {
+                connection_string, ..
+            } => {
+                script.push_str("INSTALL azure;\n");
+                script.push_str("LOAD azure;\n\n");
+                script.push_str(&format!(
+                    "CREATE SECRET azure_secret (
+    TYPE AZURE,
+    CONNECTION_STRING '{}'
+);\n\n",
+                    connection_string
+                ));
+            }
+            // Local files need no secret
+            StorageBackend::Gcs { .. } | StorageBackend::Local { .. } => {}
         }
 
         // Direct Parquet file scanning with glob patterns
@@ -245,10 +374,20 @@ impl DuckDBVerifier {
                     Ok(format!("s3://{}/{}/**/*.parquet", bucket, full_prefix))
                 }
             }
+            StorageBackend::Azure { container, .. } => {
+                Ok(format!("az://{}/{}/**/*.parquet", container, full_prefix))
+            }
+            StorageBackend::Local { root } => {
+                Ok(format!("{}/{}/**/*.parquet", root.display(), full_prefix))
+            }
+            StorageBackend::Gcs { .. } => {
+                anyhow::bail!("GCS objects are scanned after downloading them locally")
+            }
         }
     }
 
     /// Execute DuckDB script and return output
+    #[cfg(not(feature = "duckdb-verify"))]
     async fn execute_duckdb_script(&self, script: &str) -> Result<String> {
         use tokio::process::Command;
 
@@ -321,6 +460,7 @@ impl DuckDBVerifier {
     }
 
     /// Parse DuckDB output into validation report
+    #[cfg(not(feature = "duckdb-verify"))]
     fn parse_verification_output(&self, output: &str) -> Result<ValidationReport> {
         let mut tables = Vec::new();
         let mut row_counts = HashMap::new();
@@ -356,6 +496,80 @@ impl DuckDBVerifier {
     }
 }
 
+/// Download every object under `prefix` in a fake-gcs-server bucket into
+/// `dir`, keeping the object names as relative paths.
+async fn download_gcs_objects(
+    endpoint: &str,
+    bucket: &str,
+    prefix: &str,
+    dir: &std::path::Path,
+) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?;
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut request = client
+            .get(format!("{}/storage/v1/b/{}/o", endpoint, bucket))
+            .query(&[("prefix", prefix)]);
+        if let Some(token) = &page_token {
+            request = request.query(&[("pageToken", token.as_str())]);
+        }
+        let listing: serde_json::Value = request
+            .send()
+            .await?
+            .error_for_status()
+            .context("Failed to list GCS objects")?
+            .json()
+            .await?;
+
+        let names = listing["items"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item["name"].as_str())
+            .filter(|name| name.ends_with(".parquet"));
+        for name in names {
+            let bytes = client
+                .get(format!(
+                    "{}/download/storage/v1/b/{}/o/{}",
+                    endpoint,
+                    bucket,
+                    encode_object_name(name)
+                ))
+                .query(&[("alt", "media")])
+                .send()
+                .await?
+                .error_for_status()
+                .with_context(|| format!("Failed to download {}", name))?
+                .bytes()
+                .await?;
+            let path = dir.join(name);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, &bytes).await?;
+        }
+
+        match listing["nextPageToken"].as_str() {
+            Some(token) => page_token = Some(token.to_string()),
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Percent-encode an object name for use as one URL path segment
+fn encode_object_name(name: &str) -> String {
+    name.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
 /// Load canonical test data from testdata/ directory
 #[allow(dead_code)]
 pub struct TestDataSet {
@@ -1,6 +1,8 @@
 //! Server smoke test harness
 //!
-//! Tests otlp2parquet server running in Docker Compose with MinIO S3.
+//! Tests otlp2parquet server running in Docker Compose with MinIO S3, or with
+//! fake-gcs-server or Azurite when the matching compose file from
+//! `tests/harness/compose/` is layered over the root `docker-compose.yml`.
 
 use super::{
     DeploymentInfo, DuckDBVerifier, ExecutionStatus, S3Credentials, SmokeTestHarness,
@@ -12,29 +14,78 @@ use std::time::Duration;
 use tokio::process::Command;
 
 const S3_BUCKET: &str = "otlp";
+const GCS_BUCKET: &str = "otlp";
+const AZBLOB_CONTAINER: &str = "otlp";
+/// Azurite's fixed development account key
+const AZURITE_ACCOUNT_KEY: &str =
+    "Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==";
+
+/// Storage the server writes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)] // emulator variants are only built with smoke-gcs / smoke-azure
+pub enum Backend {
+    /// MinIO through the S3 backend
+    Minio,
+    /// fake-gcs-server through the GCS backend
+    FakeGcs,
+    /// Azurite through the Azure Blob backend
+    Azurite,
+}
+
+impl Backend {
+    /// Compose file layered over the root one, if any
+    fn compose_file(self) -> Option<&'static str> {
+        match self {
+            Backend::Minio => None,
+            Backend::FakeGcs => Some("tests/harness/compose/gcs.yml"),
+            Backend::Azurite => Some("tests/harness/compose/azurite.yml"),
+        }
+    }
+
+    /// Services to start; otlp2parquet waits for the storage init job
+    fn services(self) -> &'static [&'static str] {
+        match self {
+            Backend::Minio => &["minio", "otlp2parquet"],
+            Backend::FakeGcs => &["fake-gcs", "fake-gcs-init", "otlp2parquet"],
+            Backend::Azurite => &["azurite", "azurite-init", "otlp2parquet"],
+        }
+    }
+}
 
 /// Server harness for Docker Compose testing
 pub struct ServerHarness {
     compose_project_name: String,
+    backend: Backend,
     /// Prefix for test isolation
     prefix: String,
     // Dynamic port allocations (0 = let OS assign)
     minio_api_port: u16,
     minio_console_port: u16,
+    /// fake-gcs-server or Azurite API port
+    emulator_port: u16,
     http_port: u16,
     // Track if we've been cleaned up already to avoid double cleanup
     cleaned_up: std::sync::Arc<std::sync::Mutex<bool>>,
 }
 
 impl ServerHarness {
-    /// Create new server harness
+    /// Create new server harness writing to MinIO
     pub async fn new() -> Result<Self> {
+        Self::with_backend(Backend::Minio).await
+    }
+
+    /// Create new server harness writing to `backend`
+    pub async fn with_backend(backend: Backend) -> Result<Self> {
         // Generate unique project name for test isolation
         let test_id = uuid::Uuid::new_v4().simple().to_string();
         let project_name = format!("otlp2parquet-test-{}", &test_id[..8]);
         let prefix = format!("smoke-{}/", &test_id[..8]);
 
-        tracing::info!("Creating ServerHarness with project={}", project_name);
+        tracing::info!(
+            "Creating ServerHarness with project={} backend={:?}",
+            project_name,
+            backend
+        );
 
         // Cleanup any stale Docker containers first
         Self::cleanup_stale_containers().await;
@@ -42,20 +93,24 @@ impl ServerHarness {
         // Use OS-assigned ports to avoid conflicts
         let minio_api_port = Self::allocate_port().await?;
         let minio_console_port = Self::allocate_port().await?;
+        let emulator_port = Self::allocate_port().await?;
         let http_port = Self::allocate_port().await?;
 
         tracing::info!(
-            "Allocated ports: minio_api={}, minio_console={}, http={}",
+            "Allocated ports: minio_api={}, minio_console={}, emulator={}, http={}",
             minio_api_port,
             minio_console_port,
+            emulator_port,
             http_port
         );
 
         Ok(Self {
             compose_project_name: project_name,
+            backend,
             prefix,
             minio_api_port,
             minio_console_port,
+            emulator_port,
             http_port,
             cleaned_up: std::sync::Arc::new(std::sync::Mutex::new(false)),
         })
@@ -168,6 +223,21 @@ impl ServerHarness {
         }
     }
 
+    /// `docker compose` arguments naming the project and its compose files
+    fn compose_args(&self) -> Vec<&str> {
+        let mut args = vec![
+            "compose",
+            "-p",
+            self.compose_project_name.as_str(),
+            "-f",
+            "docker-compose.yml",
+        ];
+        if let Some(file) = self.backend.compose_file() {
+            args.extend(["-f", file]);
+        }
+        args
+    }
+
     /// Get Docker Compose environment variables for port configuration
     fn compose_env(&self) -> Vec<(String, String)> {
         vec![
@@ -183,6 +253,8 @@ impl ServerHarness {
                 "MINIO_CONSOLE_PORT".to_string(),
                 self.minio_console_port.to_string(),
             ),
+            ("FAKE_GCS_PORT".to_string(), self.emulator_port.to_string()),
+            ("AZURITE_PORT".to_string(), self.emulator_port.to_string()),
             ("HTTP_PORT".to_string(), self.http_port.to_string()),
             ("OTLP2PARQUET_PREFIX".to_string(), self.prefix.clone()),
         ]
@@ -202,8 +274,8 @@ impl SmokeTestHarness for ServerHarness {
             self.http_port
         );
 
-        // Start MinIO and otlp2parquet services
-        let services = vec!["minio", "otlp2parquet"];
+        // Start the storage and otlp2parquet services
+        let services = self.backend.services();
         tracing::info!("Starting services: {:?}", services);
 
         // Retry logic for port binding issues
@@ -215,7 +287,8 @@ impl SmokeTestHarness for ServerHarness {
                 tracing::info!("Retry attempt {} of {}", attempt, max_retries);
                 // Clean up previous attempt
                 let _ = Command::new("docker")
-                    .args(["compose", "-p", &self.compose_project_name, "down", "-v"])
+                    .args(self.compose_args())
+                    .args(["down", "-v"])
                     .output()
                     .await;
                 // Wait for ports to be released
@@ -224,7 +297,7 @@ impl SmokeTestHarness for ServerHarness {
 
             // Start services with unique project name
             let mut cmd = Command::new("docker");
-            cmd.args(["compose", "-p", &self.compose_project_name, "up", "-d"]);
+            cmd.args(self.compose_args()).args(["up", "-d"]);
 
             // Add environment variables for port configuration
             for (key, value) in self.compose_env() {
@@ -233,7 +306,7 @@ impl SmokeTestHarness for ServerHarness {
             }
 
             // Add services
-            cmd.args(&services);
+            cmd.args(services);
 
             tracing::info!(
                 "Executing: docker compose -p {} up -d {:?} (attempt {})",
@@ -296,10 +369,23 @@ impl SmokeTestHarness for ServerHarness {
         let minio_endpoint = format!("http://localhost:{}", self.minio_api_port);
         let otlp_endpoint = format!("http://localhost:{}", self.http_port);
 
-        // Wait for MinIO to be ready
-        tracing::info!("Waiting for MinIO...");
-        self.wait_for_health(&format!("{}/minio/health/live", minio_endpoint), 30)
-            .await?;
+        let emulator_endpoint = format!("http://localhost:{}", self.emulator_port);
+
+        // Wait for storage to be ready; Azurite has no unauthenticated health
+        // route, and otlp2parquet only starts once its container exists
+        match self.backend {
+            Backend::Minio => {
+                tracing::info!("Waiting for MinIO...");
+                self.wait_for_health(&format!("{}/minio/health/live", minio_endpoint), 30)
+                    .await?;
+            }
+            Backend::FakeGcs => {
+                tracing::info!("Waiting for fake-gcs-server...");
+                self.wait_for_health(&format!("{}/storage/v1/b", emulator_endpoint), 30)
+                    .await?;
+            }
+            Backend::Azurite => {}
+        }
 
         // Wait for otlp2parquet server to be ready
         tracing::info!("Waiting for otlp2parquet server...");
@@ -308,16 +394,21 @@ impl SmokeTestHarness for ServerHarness {
 
         tracing::info!("All services healthy and ready");
 
+        let (bucket, storage_endpoint) = match self.backend {
+            Backend::Minio => (S3_BUCKET, ("minio_endpoint", minio_endpoint)),
+            Backend::FakeGcs => (GCS_BUCKET, ("gcs_endpoint", emulator_endpoint)),
+            Backend::Azurite => (AZBLOB_CONTAINER, ("azurite_endpoint", emulator_endpoint)),
+        };
         Ok(DeploymentInfo {
             endpoint: otlp_endpoint,
-            bucket: S3_BUCKET.to_string(),
+            bucket: bucket.to_string(),
             prefix: self.prefix.clone(),
             resource_ids: HashMap::from([
                 (
                     "compose_project".to_string(),
                     self.compose_project_name.clone(),
                 ),
-                ("minio_endpoint".to_string(), minio_endpoint.clone()),
+                (storage_endpoint.0.to_string(), storage_endpoint.1),
             ]),
         })
     }
@@ -407,13 +498,8 @@ impl SmokeTestHarness for ServerHarness {
         );
 
         let output = Command::new("docker")
-            .args([
-                "compose",
-                "-p",
-                &self.compose_project_name,
-                "logs",
-                "otlp2parquet",
-            ])
+            .args(self.compose_args())
+            .args(["logs", "otlp2parquet"])
             .output()
             .await
             .context("Failed to get docker logs")?;
@@ -458,20 +544,40 @@ impl SmokeTestHarness for ServerHarness {
 
     fn duckdb_verifier(&self, _info: &DeploymentInfo) -> DuckDBVerifier {
         let minio_endpoint = format!("http://localhost:{}", self.minio_api_port);
+        let emulator_endpoint = format!("http://localhost:{}", self.emulator_port);
 
-        tracing::debug!("Creating DuckDB verifier with minio={}", minio_endpoint);
+        tracing::debug!(
+            "Creating DuckDB verifier for {:?} (minio={}, emulator={})",
+            self.backend,
+            minio_endpoint,
+            emulator_endpoint
+        );
 
+        let backend = match self.backend {
+            Backend::Minio => StorageBackend::S3 {
+                region: "us-east-1".to_string(),
+                bucket: S3_BUCKET.to_string(),
+                endpoint: Some(minio_endpoint),
+                credentials: S3Credentials::Static {
+                    access_key: "minioadmin".to_string(),
+                    secret_key: "minioadmin".to_string(),
+                },
+            },
+            Backend::FakeGcs => StorageBackend::Gcs {
+                bucket: GCS_BUCKET.to_string(),
+                endpoint: emulator_endpoint,
+            },
+            Backend::Azurite => StorageBackend::Azure {
+                container: AZBLOB_CONTAINER.to_string(),
+                connection_string: format!(
+                    "DefaultEndpointsProtocol=http;AccountName=devstoreaccount1;AccountKey={};BlobEndpoint={}/devstoreaccount1;",
+                    AZURITE_ACCOUNT_KEY, emulator_endpoint
+                ),
+            },
+        };
         DuckDBVerifier {
             storage_config: StorageConfig {
-                backend: StorageBackend::S3 {
-                    region: "us-east-1".to_string(),
-                    bucket: S3_BUCKET.to_string(),
-                    endpoint: Some(minio_endpoint),
-                    credentials: S3Credentials::Static {
-                        access_key: "minioadmin".to_string(),
-                        secret_key: "minioadmin".to_string(),
-                    },
-                },
+                backend,
                 prefix: Some(self.prefix.clone()),
             },
         }
@@ -497,7 +603,8 @@ impl SmokeTestHarness for ServerHarness {
         );
 
         let output = Command::new("docker")
-            .args(["compose", "-p", &self.compose_project_name, "down", "-v"])
+            .args(self.compose_args())
+            .args(["down", "-v"])
             .output()
             .await;
 
@@ -544,7 +651,8 @@ impl Drop for ServerHarness {
 
         // Use blocking command since Drop can't be async
         let output = std::process::Command::new("docker")
-            .args(["compose", "-p", &self.compose_project_name, "down", "-v"])
+            .args(self.compose_args())
+            .args(["down", "-v"])
             .output();
 
         match output {
@@ -0,0 +1,140 @@
+//! Golden tests of metric conversion edge cases.
+//!
+//! `testdata/metrics_edge_cases/*.pb` are built by
+//! `scripts/metric_edge_cases.py` (empty buckets, NaN/Infinity values,
+//! negative exponential scales, exemplar-heavy points, mixed services).
+//! `expected.json` holds, per fixture, the rows per table and service, the
+//! skipped data points, selected column values (as displayed by Arrow, `null`
+//! for nulls) and exemplar counts per row. A converter change that alters any
+//! of them fails here, and the expectation is updated with it.
+
+use arrow::array::{Array, AsArray, RecordBatch};
+use arrow::util::display::array_value_to_string;
+use otlp2parquet::codec::{decode_metrics_partitioned, PartitionedMetrics};
+use otlp2parquet::InputFormat;
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+
+const TABLES: [&str; 4] = ["gauge", "sum", "histogram", "exponential_histogram"];
+
+fn batches<'a>(metrics: &'a PartitionedMetrics, table: &str) -> Vec<(&'a str, &'a RecordBatch)> {
+    let grouped = match table {
+        "gauge" => &metrics.gauge,
+        "sum" => &metrics.sum,
+        "histogram" => &metrics.histogram,
+        "exponential_histogram" => &metrics.exp_histogram,
+        other => panic!("unknown table {}", other),
+    };
+    grouped
+        .batches
+        .iter()
+        .map(|pb| (pb.service_name.as_ref(), &pb.batch))
+        .collect()
+}
+
+/// Values of `column` across the table's batches, in order
+fn column_values(batches: &[(&str, &RecordBatch)], column: &str) -> Vec<Value> {
+    let mut values = Vec::new();
+    for (_, batch) in batches {
+        let array = batch
+            .column_by_name(column)
+            .unwrap_or_else(|| panic!("no column {}", column));
+        for row in 0..array.len() {
+            values.push(if array.is_null(row) {
+                Value::Null
+            } else {
+                Value::String(array_value_to_string(array, row).unwrap())
+            });
+        }
+    }
+    values
+}
+
+fn check_fixture(name: &str, expected: &Value) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("testdata")
+        .join("metrics_edge_cases")
+        .join(format!("{}.pb", name));
+    let body = std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    let metrics = decode_metrics_partitioned(&body, InputFormat::Protobuf)
+        .unwrap_or_else(|e| panic!("{}: {}", name, e));
+    let empty = Map::new();
+
+    let rows = expected["rows"].as_object().unwrap_or(&empty);
+    for table in TABLES {
+        let mut actual = Map::new();
+        for (service, batch) in batches(&metrics, table) {
+            actual.insert(service.to_string(), json!(batch.num_rows()));
+        }
+        let wanted = rows.get(table).cloned().unwrap_or(json!({}));
+        assert_eq!(Value::Object(actual), wanted, "{}: {} rows", name, table);
+    }
+
+    let skipped = &metrics.skipped;
+    let counts = [
+        ("summaries", skipped.summaries),
+        ("nan_values", skipped.nan_values),
+        ("infinity_values", skipped.infinity_values),
+        ("missing_values", skipped.missing_values),
+    ];
+    for (key, count) in counts {
+        let wanted = expected["skipped"][key].as_u64().unwrap_or(0);
+        assert_eq!(count as u64, wanted, "{}: skipped {}", name, key);
+    }
+
+    let columns = expected["columns"].as_object().unwrap_or(&empty);
+    for (table, columns) in columns {
+        let batches = batches(&metrics, table);
+        for (column, wanted) in columns.as_object().unwrap() {
+            assert_eq!(
+                &Value::Array(column_values(&batches, column)),
+                wanted,
+                "{}: {}.{}",
+                name,
+                table,
+                column
+            );
+        }
+    }
+
+    let exemplars = expected["exemplars"].as_object().unwrap_or(&empty);
+    for (table, wanted) in exemplars {
+        let mut actual = Vec::new();
+        for (_, batch) in batches(&metrics, table) {
+            let column = batch
+                .column_by_name("exemplars_json")
+                .unwrap()
+                .as_string::<i32>();
+            for row in 0..column.len() {
+                let count = if column.is_null(row) {
+                    0
+                } else {
+                    let parsed: Value = serde_json::from_str(column.value(row)).unwrap();
+                    parsed.as_array().map_or(0, Vec::len)
+                };
+                actual.push(json!(count));
+            }
+        }
+        assert_eq!(
+            &Value::Array(actual),
+            wanted,
+            "{}: {} exemplars",
+            name,
+            table
+        );
+    }
+}
+
+#[test]
+fn test_metric_edge_cases_match_golden_files() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("testdata")
+        .join("metrics_edge_cases")
+        .join("expected.json");
+    let expected: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    let fixtures = expected.as_object().unwrap();
+    assert!(!fixtures.is_empty());
+    for (name, expected) in fixtures {
+        check_fixture(name, expected);
+    }
+}
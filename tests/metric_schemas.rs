@@ -0,0 +1,44 @@
+//! Golden test of the metric table layouts against the OpenTelemetry Collector
+//! ClickHouse exporter.
+//!
+//! `testdata/clickhouse_metric_columns.json` maps every exporter column to the
+//! column written here, or `null` where there is none. A converter upgrade that
+//! adds or drops a mapped column fails this test, so the fixture and the
+//! metrics schema in `docs/reference.md` get updated with it.
+
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+#[test]
+fn test_metric_columns_match_clickhouse_layout() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("testdata")
+        .join("clickhouse_metric_columns.json");
+    let golden: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+
+    for (table, columns) in golden.as_object().unwrap() {
+        let schema = otlp2records::schema_def(table)
+            .unwrap_or_else(|| panic!("no converter schema for {}", table));
+        let written: HashSet<&str> = schema.fields.iter().map(|f| f.name).collect();
+        for (exporter_column, column) in columns.as_object().unwrap() {
+            match column.as_str() {
+                Some(column) => assert!(
+                    written.contains(column),
+                    "{}: {} should be written as {}",
+                    table,
+                    exporter_column,
+                    column
+                ),
+                None => assert!(
+                    !written
+                        .iter()
+                        .any(|c| c.replace('_', "") == exporter_column.to_lowercase()),
+                    "{}: {} is written now; map it in the fixture",
+                    table,
+                    exporter_column
+                ),
+            }
+        }
+    }
+}
@@ -0,0 +1,503 @@
+//! Reader compatibility tests for written Parquet
+//!
+//! Runs the server through the smoke harness, sends every signal fixture, then
+//! reads the files back with DuckDB, Spark and Trino and compares each engine's
+//! view with the Arrow reader's: column names, row count and non-null count per
+//! column. A writer setting one engine cannot decode (a nested list encoding,
+//! a timestamp unit, a codec) fails the test with the engine and table named.
+//!
+//! ## Running Tests
+//! ```bash
+//! # Requires Docker and the duckdb CLI
+//! cargo test --test reader_compat --features reader-compat -- --nocapture
+//! ```
+//!
+//! Spark and Trino run from the `SPARK_IMAGE` and `TRINO_IMAGE` containers.
+//! Trino has no schema inference for Parquet, so its tables are declared from
+//! the written schema; for Trino the check is that every column reads back.
+
+#![cfg(feature = "reader-compat")]
+
+#[allow(dead_code)]
+mod harness;
+
+use anyhow::{bail, Context, Result};
+use arrow::datatypes::{DataType, Schema};
+use harness::{DeploymentInfo, SmokeTestHarness, TestDataSet};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::Permissions;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+const SPARK_IMAGE: &str = "apache/spark:3.5.3-python3";
+const TRINO_IMAGE: &str = "trinodb/trino:476";
+
+/// Prefix of the line the Spark script prints its result on
+const SPARK_RESULT_MARKER: &str = "READER_COMPAT ";
+
+/// One table as seen by a reader
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct TableView {
+    columns: Vec<String>,
+    rows: u64,
+    non_null: BTreeMap<String, u64>,
+}
+
+#[tokio::test]
+async fn test_readers_match_written_parquet() -> Result<()> {
+    let harness = harness::server::ServerHarness::new().await?;
+    let info = harness.deploy().await?;
+
+    harness.send_signals(&info.endpoint).await?;
+    send_remaining_metrics(&info.endpoint).await?;
+
+    let status = harness.verify_execution().await?;
+    assert!(
+        status.is_healthy(),
+        "Found {} errors in execution logs: {:?}",
+        status.error_count,
+        status.sample_errors
+    );
+
+    let dir = tempfile::tempdir()?;
+    // The engines run as their image's user and only need to read the files
+    std::fs::set_permissions(dir.path(), Permissions::from_mode(0o755))?;
+    let data = dir.path().join("data");
+    let tables = download_tables(&info, &data).await?;
+    for table in ["logs", "traces", "metrics/gauge", "metrics/sum"] {
+        assert!(tables.contains_key(table), "no files written for {}", table);
+    }
+
+    let expected = tables
+        .keys()
+        .map(|table| Ok((table.clone(), arrow_view(&data.join(table))?)))
+        .collect::<Result<BTreeMap<_, _>>>()?;
+
+    let mut mismatches = Vec::new();
+    for (engine, views) in [
+        ("duckdb", duckdb_views(&data, &expected).await),
+        ("spark", spark_views(&data, &expected).await),
+        ("trino", trino_views(dir.path(), &data, &expected).await),
+    ] {
+        let views = match views {
+            Ok(views) => views,
+            Err(e) => {
+                mismatches.push(format!("{}: {:#}", engine, e));
+                continue;
+            }
+        };
+        for (table, want) in &expected {
+            match views.get(table) {
+                Some(got) if got == want => {}
+                Some(got) => mismatches.push(format!(
+                    "{} {}: expected {:?}, got {:?}",
+                    engine, table, want, got
+                )),
+                None => mismatches.push(format!("{} {}: not read", engine, table)),
+            }
+        }
+        tracing::info!("{} read {} tables", engine, views.len());
+    }
+
+    harness.cleanup().await?;
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+    Ok(())
+}
+
+/// Metric types `send_signals` leaves out, so every metrics table gets files
+async fn send_remaining_metrics(endpoint: &str) -> Result<()> {
+    let testdata = TestDataSet::load();
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+    for body in [
+        testdata.metrics_sum_pb,
+        testdata.metrics_histogram_pb,
+        testdata.metrics_exponential_histogram_pb,
+        testdata.metrics_summary_pb,
+    ] {
+        let resp = client
+            .post(format!("{}/v1/metrics", endpoint))
+            .header("content-type", "application/x-protobuf")
+            .body(body.to_vec())
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            bail!("Metrics request failed: {}", resp.status());
+        }
+    }
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    Ok(())
+}
+
+/// Copy the deployment's Parquet files to `dest`, keeping their partition
+/// paths, and return the number of files per table
+async fn download_tables(info: &DeploymentInfo, dest: &Path) -> Result<BTreeMap<String, usize>> {
+    let endpoint = info
+        .resource_ids
+        .get("minio_endpoint")
+        .context("deployment has no MinIO endpoint")?;
+    let builder = opendal::services::S3::default()
+        .bucket(&info.bucket)
+        .endpoint(endpoint)
+        .region("us-east-1")
+        .access_key_id("minioadmin")
+        .secret_access_key("minioadmin");
+    let op = opendal::Operator::new(builder)?.finish();
+
+    let mut tables = BTreeMap::new();
+    for entry in op.list_with(&info.prefix).recursive(true).await? {
+        let path = entry.path();
+        let Some(relative) = path.strip_prefix(&info.prefix) else {
+            continue;
+        };
+        if !relative.ends_with(".parquet") {
+            continue;
+        }
+        // {table}/{service}/year=.../file.parquet
+        let Some((table, _)) = relative
+            .split("/year=")
+            .next()
+            .and_then(|dir| dir.rsplit_once('/'))
+        else {
+            continue;
+        };
+        let target = dest.join(relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, op.read(path).await?.to_vec())?;
+        *tables.entry(table.to_string()).or_insert(0) += 1;
+    }
+    Ok(tables)
+}
+
+/// Parquet files under `dir`, in path order
+fn parquet_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(parquet_files(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "parquet") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Schema of the first file of a table
+fn table_schema(dir: &Path) -> Result<Schema> {
+    let file = parquet_files(dir)?
+        .into_iter()
+        .next()
+        .with_context(|| format!("no Parquet files in {}", dir.display()))?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(file)?)?;
+    Ok(builder.schema().as_ref().clone())
+}
+
+/// The table as the writer's own Arrow reader decodes it
+fn arrow_view(dir: &Path) -> Result<TableView> {
+    let schema = table_schema(dir)?;
+    let columns: Vec<String> = schema.fields().iter().map(|f| f.name().clone()).collect();
+    let mut rows = 0u64;
+    let mut nulls = vec![0u64; columns.len()];
+    for file in parquet_files(dir)? {
+        let reader =
+            ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&file)?)?.build()?;
+        for batch in reader {
+            let batch = batch?;
+            rows += batch.num_rows() as u64;
+            for (i, column) in batch.columns().iter().enumerate() {
+                nulls[i] += column.null_count() as u64;
+            }
+        }
+    }
+    let non_null = columns
+        .iter()
+        .zip(nulls)
+        .map(|(column, nulls)| (column.clone(), rows - nulls))
+        .collect();
+    Ok(TableView {
+        columns,
+        rows,
+        non_null,
+    })
+}
+
+/// `SELECT count(*), count(c)...` with each count aliased to its column
+fn count_select(columns: &[String], quote: char) -> String {
+    let counts: Vec<String> = columns
+        .iter()
+        .map(|c| format!("count({q}{c}{q}) AS {q}{c}{q}", q = quote, c = c))
+        .collect();
+    format!(
+        "SELECT count(*) AS {q}__rows{q}, {}",
+        counts.join(", "),
+        q = quote
+    )
+}
+
+/// Split a counts row back into the row count and per-column counts
+fn view_from_counts(
+    columns: Vec<String>,
+    mut counts: serde_json::Map<String, serde_json::Value>,
+) -> Result<TableView> {
+    let rows = counts
+        .remove("__rows")
+        .and_then(|v| v.as_u64())
+        .context("counts row has no row count")?;
+    let non_null = counts
+        .into_iter()
+        .map(|(column, count)| {
+            let count = count
+                .as_u64()
+                .with_context(|| format!("count of {} is not a number", column))?;
+            Ok((column, count))
+        })
+        .collect::<Result<_>>()?;
+    Ok(TableView {
+        columns,
+        rows,
+        non_null,
+    })
+}
+
+/// Run a command to completion and return its stdout
+async fn run(command: &mut Command, what: &str) -> Result<String> {
+    tracing::info!("Running {}", what);
+    let output = tokio::time::timeout(Duration::from_secs(600), command.output())
+        .await
+        .with_context(|| format!("{} timed out after 600s", what))?
+        .with_context(|| format!("Failed to run {}", what))?;
+    if !output.status.success() {
+        bail!(
+            "{} failed:\nSTDERR: {}\nSTDOUT: {}",
+            what,
+            String::from_utf8_lossy(&output.stderr),
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+    String::from_utf8(output.stdout).with_context(|| format!("Invalid UTF-8 from {}", what))
+}
+
+async fn duckdb_json(sql: &str) -> Result<Vec<serde_json::Map<String, serde_json::Value>>> {
+    let output = run(Command::new("duckdb").args(["-json", "-c", sql]), "duckdb").await?;
+    if output.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(&output).context("Invalid duckdb JSON output")
+}
+
+/// DuckDB CLI over the local files
+async fn duckdb_views(
+    data: &Path,
+    expected: &BTreeMap<String, TableView>,
+) -> Result<BTreeMap<String, TableView>> {
+    let mut views = BTreeMap::new();
+    for table in expected.keys() {
+        let scan = format!(
+            "read_parquet('{}/**/*.parquet', hive_partitioning = false)",
+            data.join(table).display()
+        );
+        let columns = duckdb_json(&format!("DESCRIBE SELECT * FROM {}", scan))
+            .await?
+            .iter()
+            .filter_map(|row| row.get("column_name")?.as_str().map(String::from))
+            .collect::<Vec<_>>();
+        let counts = duckdb_json(&format!("{} FROM {}", count_select(&columns, '"'), scan))
+            .await?
+            .into_iter()
+            .next()
+            .with_context(|| format!("no counts for {}", table))?;
+        views.insert(table.clone(), view_from_counts(columns, counts)?);
+    }
+    Ok(views)
+}
+
+/// pyspark in a container, reading the mounted files
+async fn spark_views(
+    data: &Path,
+    expected: &BTreeMap<String, TableView>,
+) -> Result<BTreeMap<String, TableView>> {
+    let scripts = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("scripts/reader_compat");
+    let output = run(
+        Command::new("docker")
+            .args(["run", "--rm", "-v"])
+            .arg(format!("{}:/data:ro", data.display()))
+            .arg("-v")
+            .arg(format!("{}:/scripts:ro", scripts.display()))
+            .args([
+                SPARK_IMAGE,
+                "/opt/spark/bin/spark-submit",
+                "--master",
+                "local[1]",
+                "/scripts/spark_read.py",
+                "/data",
+            ])
+            .args(expected.keys()),
+        "spark",
+    )
+    .await?;
+    let line = output
+        .lines()
+        .find_map(|line| line.strip_prefix(SPARK_RESULT_MARKER))
+        .context("spark printed no result")?;
+    serde_json::from_str(line).context("Invalid spark result")
+}
+
+/// Trino type of an Arrow column, for declaring the Hive tables
+fn trino_type(data_type: &DataType) -> Result<String> {
+    Ok(match data_type {
+        DataType::Boolean => "boolean".to_string(),
+        DataType::Int8 => "tinyint".to_string(),
+        DataType::Int16 | DataType::UInt8 => "smallint".to_string(),
+        DataType::Int32 | DataType::UInt16 => "integer".to_string(),
+        DataType::Int64 | DataType::UInt32 => "bigint".to_string(),
+        DataType::UInt64 => "decimal(20, 0)".to_string(),
+        DataType::Float32 => "real".to_string(),
+        DataType::Float64 => "double".to_string(),
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => "varchar".to_string(),
+        DataType::Binary
+        | DataType::LargeBinary
+        | DataType::BinaryView
+        | DataType::FixedSizeBinary(_) => "varbinary".to_string(),
+        DataType::Date32 => "date".to_string(),
+        // Precision comes from hive.timestamp-precision
+        DataType::Timestamp(_, _) => "timestamp".to_string(),
+        DataType::Dictionary(_, value) => trino_type(value)?,
+        DataType::List(field) | DataType::LargeList(field) => {
+            format!("array({})", trino_type(field.data_type())?)
+        }
+        DataType::Map(entries, _) => match entries.data_type() {
+            DataType::Struct(fields) if fields.len() == 2 => format!(
+                "map({}, {})",
+                trino_type(fields[0].data_type())?,
+                trino_type(fields[1].data_type())?
+            ),
+            other => bail!("unexpected map entries {}", other),
+        },
+        DataType::Struct(fields) => {
+            let fields = fields
+                .iter()
+                .map(|f| Ok(format!("\"{}\" {}", f.name(), trino_type(f.data_type())?)))
+                .collect::<Result<Vec<_>>>()?;
+            format!("row({})", fields.join(", "))
+        }
+        other => bail!("no Trino type for {}", other),
+    })
+}
+
+/// Trino's Hive connector with a file metastore inside the container
+const TRINO_CATALOG: &str = "connector.name=hive
+hive.metastore=file
+hive.metastore.catalog.dir=file:///tmp/compat-metastore
+fs.hadoop.enabled=true
+hive.recursive-directories=true
+hive.timestamp-precision=NANOSECONDS
+";
+
+async fn trino_query(container: &str, sql: &str) -> Result<String> {
+    run(
+        Command::new("docker").args([
+            "exec",
+            container,
+            "trino",
+            "--output-format",
+            "JSON",
+            "--execute",
+            sql,
+        ]),
+        "trino",
+    )
+    .await
+}
+
+/// Trino in a container, with one external table per written table
+async fn trino_views(
+    workdir: &Path,
+    data: &Path,
+    expected: &BTreeMap<String, TableView>,
+) -> Result<BTreeMap<String, TableView>> {
+    let catalog = workdir.join("compat.properties");
+    std::fs::write(&catalog, TRINO_CATALOG)?;
+    let container = format!("otlp2parquet-compat-{}", uuid::Uuid::new_v4().simple());
+    run(
+        Command::new("docker")
+            .args(["run", "-d", "--rm", "--name", &container, "-v"])
+            .arg(format!("{}:/data:ro", data.display()))
+            .arg("-v")
+            .arg(format!(
+                "{}:/etc/trino/catalog/compat.properties:ro",
+                catalog.display()
+            ))
+            .arg(TRINO_IMAGE),
+        "trino start",
+    )
+    .await?;
+
+    let views = trino_tables(&container, data, expected).await;
+    let _ = Command::new("docker")
+        .args(["rm", "-f", &container])
+        .output()
+        .await;
+    views
+}
+
+async fn trino_tables(
+    container: &str,
+    data: &Path,
+    expected: &BTreeMap<String, TableView>,
+) -> Result<BTreeMap<String, TableView>> {
+    let started = std::time::Instant::now();
+    while trino_query(container, "SELECT 1").await.is_err() {
+        if started.elapsed() > Duration::from_secs(180) {
+            bail!("Trino did not start within 180s");
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+    trino_query(container, "CREATE SCHEMA compat.otel").await?;
+
+    let mut views = BTreeMap::new();
+    for table in expected.keys() {
+        let name = table.replace('/', "_");
+        let schema = table_schema(&data.join(table))?;
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|f| Ok(format!("\"{}\" {}", f.name(), trino_type(f.data_type())?)))
+            .collect::<Result<Vec<_>>>()?;
+        trino_query(
+            container,
+            &format!(
+                "CREATE TABLE compat.otel.{} ({}) WITH (format = 'PARQUET', external_location = 'file:///data/{}')",
+                name,
+                columns.join(", "),
+                table
+            ),
+        )
+        .await?;
+
+        let described = trino_query(container, &format!("DESCRIBE compat.otel.{}", name)).await?;
+        let columns = described
+            .lines()
+            .map(serde_json::from_str::<serde_json::Value>)
+            .collect::<Result<Vec<_>, _>>()?
+            .iter()
+            .filter_map(|row| row.get("Column")?.as_str().map(String::from))
+            .collect::<Vec<_>>();
+        let counts = trino_query(
+            container,
+            &format!("{} FROM compat.otel.{}", count_select(&columns, '"'), name),
+        )
+        .await?;
+        let counts = serde_json::from_str(counts.trim()).context("Invalid trino counts")?;
+        views.insert(table.clone(), view_from_counts(columns, counts)?);
+    }
+    Ok(views)
+}
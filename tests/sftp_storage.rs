@@ -0,0 +1,186 @@
+//! SFTP storage backend against a containerized sshd
+//!
+//! Starts `atmoz/sftp` with a throwaway key, runs the server in-process with
+//! `backend = "sftp"`, sends the logs fixture and checks a Parquet file lands
+//! on the SFTP server.
+//!
+//! cargo test --test sftp_storage --features docker-tests,sftp
+
+#![cfg(all(feature = "docker-tests", feature = "sftp", unix))]
+
+use anyhow::{bail, Context, Result};
+use otlp2parquet::config::{SftpConfig, StorageBackend};
+use otlp2parquet::{Platform, RuntimeConfig};
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+const SFTP_IMAGE: &str = "atmoz/sftp:alpine";
+const SFTP_USER: &str = "otlp";
+const SFTP_ROOT: &str = "/upload";
+
+/// A running sshd container, removed on drop
+struct SftpContainer {
+    id: String,
+    port: u16,
+}
+
+impl SftpContainer {
+    fn start(public_key: &Path) -> Result<Self> {
+        let output = Command::new("docker")
+            .args(["run", "-d", "--rm", "-p", "127.0.0.1::22", "-v"])
+            .arg(format!(
+                "{}:/home/{}/.ssh/keys/id.pub:ro",
+                public_key.display(),
+                SFTP_USER
+            ))
+            .arg(SFTP_IMAGE)
+            .arg(format!("{}::1001::upload", SFTP_USER))
+            .output()
+            .context("Failed to run docker")?;
+        if !output.status.success() {
+            bail!(
+                "docker run failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        let id = String::from_utf8(output.stdout)?.trim().to_string();
+
+        let output = Command::new("docker")
+            .args(["port", &id, "22/tcp"])
+            .output()
+            .context("Failed to read the sshd port")?;
+        let mapping = String::from_utf8(output.stdout)?;
+        let port = mapping
+            .lines()
+            .next()
+            .and_then(|line| line.rsplit(':').next())
+            .and_then(|port| port.trim().parse().ok())
+            .context("sshd port not published")?;
+        Ok(Self { id, port })
+    }
+}
+
+impl Drop for SftpContainer {
+    fn drop(&mut self) {
+        let _ = Command::new("docker").args(["rm", "-f", &self.id]).output();
+    }
+}
+
+fn generate_key(dir: &Path) -> Result<std::path::PathBuf> {
+    let key = dir.join("id_ed25519");
+    let status = Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+        .arg(&key)
+        .status()
+        .context("Failed to run ssh-keygen")?;
+    if !status.success() {
+        bail!("ssh-keygen failed");
+    }
+    Ok(key)
+}
+
+fn free_port() -> Result<u16> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// Operator on the container's upload directory, for checking results
+fn sftp_operator(sftp: &SftpConfig) -> Result<opendal::Operator> {
+    let builder = opendal::services::Sftp::default()
+        .endpoint(&sftp.endpoint)
+        .root(&sftp.root)
+        .user(SFTP_USER)
+        .key(sftp.key.as_deref().unwrap_or_default())
+        .known_hosts_strategy("accept");
+    Ok(opendal::Operator::new(builder)?.finish())
+}
+
+/// Wait until sshd accepts the key and the upload directory is writable.
+async fn wait_for_sftp(op: &opendal::Operator) -> Result<()> {
+    let deadline = Instant::now() + Duration::from_secs(60);
+    loop {
+        match op.write(".ready", b"ok".to_vec()).await {
+            Ok(_) => return Ok(()),
+            Err(e) if Instant::now() > deadline => bail!("sshd not ready: {}", e),
+            Err(_) => tokio::time::sleep(Duration::from_millis(500)).await,
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_logs_written_over_sftp() -> Result<()> {
+    let dir = TempDir::new()?;
+    let key = generate_key(dir.path())?;
+    let container = SftpContainer::start(&key.with_extension("pub"))?;
+
+    let sftp = SftpConfig {
+        endpoint: format!("ssh://{}@127.0.0.1:{}", SFTP_USER, container.port),
+        root: SFTP_ROOT.to_string(),
+        user: Some(SFTP_USER.to_string()),
+        key: Some(key.to_string_lossy().into_owned()),
+        known_hosts: "accept".to_string(),
+    };
+    let remote = sftp_operator(&sftp)?;
+    wait_for_sftp(&remote).await?;
+
+    let http_port = free_port()?;
+    let mut config = RuntimeConfig::from_platform_defaults(Platform::Server);
+    config.storage.backend = StorageBackend::Sftp;
+    config.storage.fs = None;
+    config.storage.sftp = Some(sftp);
+    config.batch.enabled = false;
+    config
+        .server
+        .get_or_insert_with(Default::default)
+        .listen_addr = format!("127.0.0.1:{}", http_port);
+
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(otlp2parquet::run_with_shutdown(config, async move {
+        let _ = stopped.await;
+    }));
+
+    let client = reqwest::Client::new();
+    let base = format!("http://127.0.0.1:{}", http_port);
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while client.get(format!("{}/health", base)).send().await.is_err() {
+        if Instant::now() > deadline {
+            bail!("server did not start");
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    let payload = std::fs::read(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join("logs.pb"),
+    )?;
+    let response = client
+        .post(format!("{}/v1/logs", base))
+        .header("content-type", "application/x-protobuf")
+        .body(payload)
+        .send()
+        .await?;
+    assert!(
+        response.status().is_success(),
+        "ingest failed: {}",
+        response.status()
+    );
+
+    let _ = stop.send(());
+    server.await??;
+
+    let files: Vec<_> = remote
+        .list_with("logs/")
+        .recursive(true)
+        .await?
+        .into_iter()
+        .filter(|entry| entry.path().ends_with(".parquet"))
+        .collect();
+    assert!(!files.is_empty(), "no Parquet file on the SFTP server");
+    let bytes = remote.read(files[0].path()).await?.to_vec();
+    assert_eq!(&bytes[..4], b"PAR1");
+    drop(container);
+    Ok(())
+}
@@ -5,11 +5,19 @@
 //!
 //! ## Test Matrix
 //! - Server + MinIO S3
+//! - Server + fake-gcs-server (`smoke-gcs`)
+//! - Server + Azurite (`smoke-azure`)
 //!
 //! ## Running Tests
 //! ```bash
 //! # Server only (local Docker)
 //! cargo test --test smoke --features smoke-server
+//!
+//! # Also against the GCS and Azure Blob backends (emulators in Docker)
+//! cargo test --test smoke --features smoke-gcs,smoke-azure
+//!
+//! # Verify in-process instead of via the duckdb CLI
+//! cargo test --test smoke --features smoke-server,duckdb-verify
 //! ```
 
 #![cfg(feature = "smoke-server")]
@@ -45,6 +53,8 @@ fn init_tracing() {
 ///
 /// This generates:
 /// - server::test_name
+/// - gcs::test_name (with `smoke-gcs`)
+/// - azurite::test_name (with `smoke-azure`)
 macro_rules! smoke_test {
     ($test_name:ident, $test_fn:expr) => {
         paste::paste! {
@@ -60,6 +70,35 @@ macro_rules! smoke_test {
                 }
             }
 
+            #[cfg(feature = "smoke-gcs")]
+            mod [<gcs_ $test_name>] {
+                use super::*;
+
+                #[tokio::test]
+                async fn test() -> Result<()> {
+                    init_tracing();
+                    let harness = harness::server::ServerHarness::with_backend(
+                        harness::server::Backend::FakeGcs,
+                    )
+                    .await?;
+                    $test_fn(&harness).await
+                }
+            }
+
+            #[cfg(feature = "smoke-azure")]
+            mod [<azurite_ $test_name>] {
+                use super::*;
+
+                #[tokio::test]
+                async fn test() -> Result<()> {
+                    init_tracing();
+                    let harness = harness::server::ServerHarness::with_backend(
+                        harness::server::Backend::Azurite,
+                    )
+                    .await?;
+                    $test_fn(&harness).await
+                }
+            }
         }
     };
 }